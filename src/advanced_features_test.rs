@@ -30,18 +30,21 @@ mod tests {
                     data_type: DataType::Integer,
                     nullable: false,
                     default: None,
+                    default_function: None,
                 },
                 ColumnDefinition {
                     name: "name".to_string(),
                     data_type: DataType::Varchar(50),
                     nullable: false,
                     default: None,
+                    default_function: None,
                 },
                 ColumnDefinition {
                     name: "age".to_string(),
                     data_type: DataType::Integer,
                     nullable: true,
                     default: None,
+                    default_function: None,
                 },
             ],
             primary_key: Some(vec![0]), // id column is primary key
@@ -99,12 +102,14 @@ mod tests {
                     data_type: DataType::Integer,
                     nullable: false,
                     default: None,
+                    default_function: None,
                 },
                 ColumnDefinition {
                     name: "name".to_string(),
                     data_type: DataType::Varchar(50),
                     nullable: false,
                     default: None,
+                    default_function: None,
                 },
             ],
             primary_key: Some(vec![0]), // id column
@@ -117,12 +122,14 @@ mod tests {
                     data_type: DataType::Integer,
                     nullable: false,
                     default: None,
+                    default_function: None,
                 },
                 ColumnDefinition {
                     name: "user_id".to_string(),
                     data_type: DataType::Integer,
                     nullable: false,
                     default: None,
+                    default_function: None,
                 },
             ],
             primary_key: Some(vec![0]), // id column
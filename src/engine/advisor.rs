@@ -0,0 +1,246 @@
+//! 索引建议
+//!
+//! 根据负载统计中记录的查询指纹和表的列统计，找出 WHERE 条件中可以走索引的列，
+//! 估算建索引后每次查询少读的行数，给出 CREATE INDEX 建议。
+//!
+//! 指纹中的字面量已替换为 `?`，重新解析后即为带参数的语句，因此不需要另外保存语句结构。
+//! 估算采用简单的均匀分布假设：等值条件的选择率为 1/NDV，单侧范围条件为 1/3，
+//! 双侧范围（BETWEEN 或上下界都有）为 1/9，多个条件的选择率相乘；
+//! 走索引的代价记为匹配行数加上 log2(行数) 次查找。
+
+use crate::sql::parser::{BinaryOperator, Expression, FromClause, Statement};
+use crate::types::{ColumnDefinition, DataType, Schema};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// 单侧范围条件（<、> 等）的估计选择率
+const RANGE_SELECTIVITY: f64 = 1.0 / 3.0;
+
+/// 选择率高于此值时随机读取索引匹配的行并不比顺序扫描划算，不给出建议
+const MAX_SELECTIVITY: f64 = 0.2;
+
+/// 一条索引建议
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IndexAdvice {
+    pub table: String,
+    /// 索引列，等值条件列在前（按 NDV 降序），范围条件列在最后
+    pub columns: Vec<String>,
+    /// 可直接执行的建索引语句
+    pub statement: String,
+    /// 能用上此索引的查询执行次数
+    pub calls: u64,
+    /// 估计的选择率（匹配行占表行数的比例）
+    pub selectivity: f64,
+    /// 估计在已记录的负载中总共少读的行数
+    pub estimated_rows_saved: f64,
+}
+
+/// 建议所需的单表信息
+pub(crate) struct TableStats {
+    pub row_count: usize,
+    /// 列名 -> 非 NULL 值的不同值个数
+    pub distinct: HashMap<String, usize>,
+    /// 已有索引（含主键）的列
+    pub indexes: Vec<Vec<String>>,
+}
+
+/// 从查询指纹生成索引建议，按估计收益降序
+pub(crate) fn advise<'a>(
+    queries: impl IntoIterator<Item = (&'a str, u64)>,
+    table_stats: impl Fn(&str) -> Option<TableStats>,
+) -> Vec<IndexAdvice> {
+    let mut candidates: HashMap<(String, Vec<String>), IndexAdvice> = HashMap::new();
+    let mut stats_cache: HashMap<String, Option<TableStats>> = HashMap::new();
+
+    for (fingerprint, calls) in queries {
+        let Some((table, where_clause)) = parse_target(fingerprint) else {
+            continue;
+        };
+        let stats = stats_cache.entry(table.clone()).or_insert_with(|| table_stats(&table));
+        let Some(stats) = stats.as_ref() else {
+            continue;
+        };
+        let Some((columns, selectivity)) = candidate(&where_clause, stats) else {
+            continue;
+        };
+
+        let n = stats.row_count as f64;
+        let saved = n - (n * selectivity + (n + 1.0).log2());
+        if saved <= 0.0 {
+            continue;
+        }
+        let advice = candidates.entry((table.clone(), columns.clone())).or_insert_with(|| IndexAdvice {
+            statement: format!("CREATE INDEX idx_{}_{} ON {} ({})", table, columns.join("_"), table, columns.join(", ")),
+            table: table.clone(),
+            columns,
+            calls: 0,
+            selectivity,
+            estimated_rows_saved: 0.0,
+        });
+        advice.calls += calls;
+        advice.estimated_rows_saved += saved * calls as f64;
+    }
+
+    // 被另一条建议的前缀覆盖的建议并入后者：(a, b) 上的索引同样可用于只按 a 过滤的查询
+    let mut advice: Vec<IndexAdvice> = candidates.into_values().collect();
+    advice.sort_by(|a, b| b.columns.len().cmp(&a.columns.len()).then_with(|| a.statement.cmp(&b.statement)));
+    let mut merged: Vec<IndexAdvice> = Vec::new();
+    for item in advice {
+        match merged.iter_mut().find(|m| m.table == item.table && m.columns.starts_with(&item.columns)) {
+            Some(wider) => {
+                wider.calls += item.calls;
+                wider.estimated_rows_saved += item.estimated_rows_saved;
+            }
+            None => merged.push(item),
+        }
+    }
+    merged.sort_by(|a, b| {
+        b.estimated_rows_saved.total_cmp(&a.estimated_rows_saved).then_with(|| a.statement.cmp(&b.statement))
+    });
+    merged
+}
+
+/// ADVISE INDEXES 结果的模式
+pub(crate) fn advice_schema() -> Schema {
+    let column = |name: &str, data_type| ColumnDefinition::new(name.to_string(), data_type, false);
+    Schema::new(vec![
+        column("table_name", DataType::Varchar(255)),
+        column("columns", DataType::Varchar(255)),
+        column("statement", DataType::Varchar(255)),
+        column("calls", DataType::BigInt),
+        column("selectivity", DataType::Double),
+        column("estimated_rows_saved", DataType::Double),
+    ])
+}
+
+/// 单表 SELECT / UPDATE / DELETE 的目标表和 WHERE 条件
+fn parse_target(sql: &str) -> Option<(String, Expression)> {
+    match crate::sql::parse_sql(sql).ok()? {
+        Statement::Select { from_clause: Some(FromClause::Table(table)), where_clause: Some(where_clause), .. }
+        | Statement::Update { table_name: table, where_clause: Some(where_clause), .. }
+        | Statement::Delete { table_name: table, where_clause: Some(where_clause), .. } => Some((table, where_clause)),
+        _ => None,
+    }
+}
+
+/// 由 WHERE 条件得到候选索引列和估计选择率；没有可用条件或已有索引覆盖时为 None
+fn candidate(where_clause: &Expression, stats: &TableStats) -> Option<(Vec<String>, f64)> {
+    let mut equalities = Vec::new();
+    let mut ranges = Vec::new();
+    collect_predicates(where_clause, &mut equalities, &mut ranges);
+
+    // 只保留有统计信息的列；等值列按 NDV 降序，最有区分度的列放在最前
+    let distinct = |column: &String| stats.distinct.get(column).copied().filter(|&d| d > 0);
+    equalities.retain(|column| distinct(column).is_some());
+    equalities.sort_by(|a, b| distinct(b).cmp(&distinct(a)).then_with(|| a.cmp(b)));
+    equalities.dedup();
+    ranges.retain(|column| distinct(column).is_some() && !equalities.contains(column));
+
+    let mut columns = equalities.clone();
+    let mut selectivity: f64 = equalities.iter().map(|column| 1.0 / distinct(column).unwrap_or(1) as f64).product();
+    if let Some(range) = ranges.first() {
+        // 同一列出现两次范围条件视为双侧范围
+        let sides = ranges.iter().filter(|column| *column == range).count().min(2);
+        selectivity *= RANGE_SELECTIVITY.powi(sides as i32);
+        columns.push(range.clone());
+    }
+    if columns.is_empty() {
+        return None;
+    }
+    let selectivity = selectivity.max(1.0 / stats.row_count.max(1) as f64);
+    if selectivity > MAX_SELECTIVITY {
+        return None;
+    }
+    // 已有索引以这些列开头时不再建议
+    if stats.indexes.iter().any(|index| index.starts_with(&columns)) {
+        return None;
+    }
+    Some((columns, selectivity))
+}
+
+/// 收集 AND 连接的可走索引的条件：列与常量/参数的等值比较和 IN 为等值条件，
+/// 其余比较为范围条件（BETWEEN 记为上下两个范围条件）
+fn collect_predicates(expr: &Expression, equalities: &mut Vec<String>, ranges: &mut Vec<String>) {
+    match expr {
+        Expression::BinaryOp { left, op: BinaryOperator::And, right } => {
+            collect_predicates(left, equalities, ranges);
+            collect_predicates(right, equalities, ranges);
+        }
+        Expression::BinaryOp { left, op, right } => {
+            let column = match (column_name(left), column_name(right)) {
+                (Some(column), None) if is_constant(right) => column,
+                (None, Some(column)) if is_constant(left) => column,
+                _ => return,
+            };
+            match op {
+                BinaryOperator::Equal => equalities.push(column),
+                BinaryOperator::LessThan
+                | BinaryOperator::LessEqual
+                | BinaryOperator::GreaterThan
+                | BinaryOperator::GreaterEqual => ranges.push(column),
+                _ => {}
+            }
+        }
+        Expression::In { expr, list, negated: false } if list.iter().all(is_constant) => {
+            if let Some(column) = column_name(expr) {
+                equalities.push(column);
+            }
+        }
+        Expression::Between { expr, low, high, negated: false } if is_constant(low) && is_constant(high) => {
+            if let Some(column) = column_name(expr) {
+                ranges.push(column.clone());
+                ranges.push(column);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn column_name(expr: &Expression) -> Option<String> {
+    match expr {
+        Expression::Column(column) | Expression::QualifiedColumn { column, .. } => Some(column.clone()),
+        _ => None,
+    }
+}
+
+fn is_constant(expr: &Expression) -> bool {
+    matches!(expr, Expression::Literal(_) | Expression::Parameter(_))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats() -> TableStats {
+        TableStats {
+            row_count: 10_000,
+            distinct: HashMap::from([
+                ("id".to_string(), 10_000),
+                ("email".to_string(), 9_000),
+                ("status".to_string(), 3),
+                ("created".to_string(), 5_000),
+            ]),
+            indexes: vec![vec!["id".to_string()]],
+        }
+    }
+
+    #[test]
+    fn test_advise_from_fingerprints() {
+        let advice = advise(
+            [
+                ("SELECT * FROM users WHERE email = ?", 50),
+                ("SELECT * FROM users WHERE email = ? AND created > ?", 5),
+                ("SELECT * FROM users WHERE id = ?", 100),
+                ("SELECT * FROM users WHERE status = ?", 100),
+                ("SELECT * FROM users WHERE status = ? OR email = ?", 10),
+                ("DELETE FROM users WHERE created BETWEEN ? AND ?", 2),
+                ("SELECT * FROM missing WHERE a = ?", 10),
+            ],
+            |table| (table == "users").then(stats),
+        );
+        let summary: Vec<_> = advice.iter().map(|a| (a.columns.join(","), a.calls)).collect();
+        assert_eq!(summary, vec![("email,created".to_string(), 55), ("created".to_string(), 2)]);
+        assert_eq!(advice[0].statement, "CREATE INDEX idx_users_email_created ON users (email, created)");
+        assert!(advice[0].estimated_rows_saved > advice[1].estimated_rows_saved);
+    }
+}
@@ -0,0 +1,160 @@
+//! 异步数据库接口
+//!
+//! [`AsyncDatabase`] 包装一个 [`Database`]，每次调用都放到 tokio 的阻塞线程池中执行并等待结果，
+//! 因此可以在异步服务器中直接 `.await`，磁盘 I/O 不会阻塞运行时的工作线程。
+//! 它可以廉价地克隆并在多个任务间共享；同一数据库上的语句按到达顺序逐条执行。
+
+#[cfg(feature = "persistence")]
+use crate::engine::config::DatabaseConfig;
+use crate::engine::database::{Database, ExecutionError, QueryResult};
+use crate::engine::prepared::PreparedStatement;
+use crate::storage::async_io::run_blocking;
+use crate::storage::Lsn;
+use crate::types::Value;
+#[cfg(feature = "persistence")]
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// 可在异步代码中使用的数据库句柄
+#[derive(Clone)]
+pub struct AsyncDatabase {
+    inner: Arc<Mutex<Database>>,
+}
+
+impl AsyncDatabase {
+    /// 包装一个已打开的数据库
+    pub fn new(database: Database) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(database)),
+        }
+    }
+    
+    /// 打开（或创建）数据目录
+    #[cfg(feature = "persistence")]
+    pub async fn open<P: AsRef<Path>>(path: P) -> Result<Self, ExecutionError> {
+        Self::open_with_config(path, DatabaseConfig::default()).await
+    }
+    
+    /// 使用指定配置打开（或创建）数据目录
+    #[cfg(feature = "persistence")]
+    pub async fn open_with_config<P: AsRef<Path>>(path: P, config: DatabaseConfig) -> Result<Self, ExecutionError> {
+        let path: PathBuf = path.as_ref().to_path_buf();
+        let database = run_blocking(move || Database::with_config(path, config)).await?;
+        Ok(Self::new(database))
+    }
+    
+    /// 创建完全在内存中的数据库
+    pub async fn in_memory() -> Result<Self, ExecutionError> {
+        Ok(Self::new(Database::in_memory()?))
+    }
+    
+    /// 执行 SQL 语句，语义同 [`Database::execute`]
+    pub async fn execute(&self, sql: &str) -> Result<QueryResult, ExecutionError> {
+        let sql = sql.to_string();
+        self.with_database(move |db| db.execute(&sql)).await?
+    }
+    
+    /// 执行以分号分隔的多条语句，语义同 [`Database::execute_script`]
+    pub async fn execute_script(
+        &self,
+        sql: &str,
+        stop_on_error: bool,
+    ) -> Result<Vec<Result<QueryResult, ExecutionError>>, ExecutionError> {
+        let sql = sql.to_string();
+        self.with_database(move |db| db.execute_script(&sql, stop_on_error)).await
+    }
+    
+    /// 预编译 SQL 语句
+    pub async fn prepare(&self, sql: &str) -> Result<PreparedStatement, ExecutionError> {
+        let sql = sql.to_string();
+        self.with_database(move |db| db.prepare(&sql)).await?
+    }
+    
+    /// 以给定参数执行预编译语句
+    pub async fn execute_prepared(
+        &self,
+        statement: &PreparedStatement,
+        params: &[Value],
+    ) -> Result<QueryResult, ExecutionError> {
+        let statement = statement.clone();
+        let params = params.to_vec();
+        self.with_database(move |db| db.execute_prepared(&statement, &params)).await?
+    }
+    
+    /// 执行检查点
+    pub async fn checkpoint(&self) -> Result<Lsn, ExecutionError> {
+        self.with_database(|db| db.checkpoint()).await?
+    }
+    
+    /// 关闭数据库，语义同 [`Database::close`]；之后的调用返回 [`ExecutionError::DatabaseClosed`]
+    pub async fn close(&self) -> Result<(), ExecutionError> {
+        self.with_database(|db| db.close()).await?
+    }
+    
+    /// 在阻塞线程池中以独占方式访问底层数据库，用于没有异步包装的同步接口
+    pub async fn with_database<F, R>(&self, f: F) -> Result<R, ExecutionError>
+    where
+        F: FnOnce(&mut Database) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let inner = self.inner.clone();
+        run_blocking(move || {
+            let mut database = inner.lock().map_err(|_| {
+                ExecutionError::StorageError("Database is unusable: an earlier call panicked".to_string())
+            })?;
+            Ok(f(&mut database))
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[tokio::test]
+    async fn test_async_database() {
+        let db = AsyncDatabase::in_memory().await.unwrap();
+        db.execute("CREATE TABLE items (id INT PRIMARY KEY, name VARCHAR(20))").await.unwrap();
+        
+        // 多个任务共享同一个数据库
+        let tasks: Vec<_> = (0..8)
+            .map(|i| {
+                let db = db.clone();
+                tokio::spawn(async move { db.execute(&format!("INSERT INTO items VALUES ({}, 'item {}')", i, i)).await })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+        
+        let statement = db.prepare("SELECT name FROM items WHERE id = $1").await.unwrap();
+        let result = db.execute_prepared(&statement, &[Value::Integer(5)]).await.unwrap();
+        assert_eq!(result.rows[0].values[0], Value::Varchar("item 5".to_string()));
+        
+        let results = db.execute_script("SELECT COUNT(*) FROM items; SELECT * FROM missing", false).await.unwrap();
+        assert_eq!(results[0].as_ref().unwrap().rows[0].values[0], Value::Integer(8));
+        assert!(results[1].is_err());
+        
+        let tables = db.with_database(|db| db.list_tables()).await.unwrap();
+        assert_eq!(tables, vec!["items".to_string()]);
+        
+        db.close().await.unwrap();
+        assert!(matches!(db.execute("SELECT * FROM items").await, Err(ExecutionError::DatabaseClosed)));
+    }
+    
+    #[cfg(feature = "persistence")]
+    #[tokio::test]
+    async fn test_async_database_persists() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let db = AsyncDatabase::open(dir.path()).await.unwrap();
+        db.execute("CREATE TABLE t (id INT PRIMARY KEY)").await.unwrap();
+        db.execute("INSERT INTO t VALUES (1), (2)").await.unwrap();
+        db.checkpoint().await.unwrap();
+        db.close().await.unwrap();
+        
+        let db = AsyncDatabase::open(dir.path()).await.unwrap();
+        let result = db.execute("SELECT COUNT(*) FROM t").await.unwrap();
+        assert_eq!(result.rows[0].values[0], Value::Integer(2));
+    }
+}
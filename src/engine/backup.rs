@@ -0,0 +1,305 @@
+//! 在线备份
+//!
+//! BACKUP 把数据目录中构成已提交状态的文件（元数据、表快照、快照引用的堆文件和 WAL）
+//! 复制到一个新目录，并写入列出每个文件大小和校验和的清单 [`BACKUP_MANIFEST_NAME`]。
+//! 备份在两条语句之间进行，期间没有其他写入，因此得到的是一个一致的状态，
+//! 数据库无需关闭，备份结束后照常可写。备份目录本身就是一个数据目录：打开它时与崩溃后恢复相同，
+//! 先加载快照再重放 WAL。加密数据库的备份以同一个密钥加密。
+//!
+//! 恢复（`Database::restore`）先按清单校验备份中每个文件的大小和校验和，全部通过后才把文件复制到新的数据目录。
+//!
+//! 配置了 WAL 归档目录时，每个提交的批次连同提交时间也追加到归档中，检查点截断 WAL 后仍然保留。
+//! 时间点恢复（`Database::restore_to`）在恢复备份之后，把归档中备份之后提交的批次按原来的 LSN
+//! 追加到新数据目录的 WAL，直到给定的 LSN 或时间，打开数据库时与崩溃恢复一样重放。
+//! 重放只覆盖行修改和 DROP TABLE：备份之后建立的表没有快照，恢复目标跨过 CREATE TABLE 时报错；
+//! ALTER TABLE、CREATE INDEX 等其他 DDL 不写入 WAL，恢复后需要重新执行。
+
+use crate::engine::config::DatabaseConfig;
+use crate::engine::database::ExecutionError;
+use crate::storage::wal::{Lsn, Wal, WalArchive, WalBatch, WalRecord};
+use crate::storage::{DirectoryBackend, EncryptedBackend, EncryptionKey, FileBackend};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 备份清单在备份目录中的文件名
+pub const BACKUP_MANIFEST_NAME: &str = "backup_manifest.json";
+
+/// 当前的备份格式版本
+pub const BACKUP_FORMAT_VERSION: u32 = 1;
+
+/// 备份清单
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupManifest {
+    /// 备份格式版本
+    pub version: u32,
+    /// 备份时间（Unix 时间戳，秒）
+    pub created_at: u64,
+    /// 备份包含的最后一条 WAL 记录
+    pub lsn: Lsn,
+    /// 备份是否加密（打开和恢复时需要原来的密钥）
+    pub encrypted: bool,
+    /// 备份目录中的文件，按名称排序
+    pub files: Vec<BackupFile>,
+}
+
+/// 备份中的一个文件
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupFile {
+    pub name: String,
+    /// 字节数
+    pub size: u64,
+    /// 文件内容的 CRC32
+    pub checksum: u32,
+}
+
+impl BackupManifest {
+    /// 备份文件的总字节数
+    pub fn total_size(&self) -> u64 {
+        self.files.iter().map(|file| file.size).sum()
+    }
+
+    /// 备份是否包含给定名称的文件
+    pub fn contains(&self, name: &str) -> bool {
+        self.files.iter().any(|file| file.name == name)
+    }
+
+    /// 读取备份目录中的清单
+    pub fn read(dir: &Path) -> Result<Self, ExecutionError> {
+        let path = dir.join(BACKUP_MANIFEST_NAME);
+        let contents = fs::read(&path)
+            .map_err(|e| backup_error(&format!("Failed to read backup manifest '{}'", path.display()), e))?;
+        let manifest: Self = serde_json::from_slice(&contents)
+            .map_err(|e| backup_error("Invalid backup manifest", e))?;
+        if manifest.version > BACKUP_FORMAT_VERSION {
+            return Err(ExecutionError::StorageError(format!(
+                "Backup format version {} is newer than the supported version {}",
+                manifest.version, BACKUP_FORMAT_VERSION
+            )));
+        }
+        Ok(manifest)
+    }
+
+    /// 校验备份目录中的文件与清单一致：每个文件都存在，大小和校验和都相同
+    pub fn verify(&self, dir: &Path) -> Result<(), ExecutionError> {
+        for file in &self.files {
+            // 清单只能引用备份目录中的文件
+            if Path::new(&file.name).file_name() != Some(file.name.as_ref()) {
+                return Err(ExecutionError::StorageError(format!("Invalid file name '{}' in backup manifest", file.name)));
+            }
+            let data = fs::read(dir.join(&file.name))
+                .map_err(|e| backup_error(&format!("Backup file '{}' is missing", file.name), e))?;
+            if data.len() as u64 != file.size || crate::utils::checksum(&data) != file.checksum {
+                return Err(ExecutionError::StorageError(format!(
+                    "Backup file '{}' is corrupt: expected {} bytes with checksum {:08x}, found {} bytes with checksum {:08x}",
+                    file.name, file.size, file.checksum, data.len(), crate::utils::checksum(&data)
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// 为已写好的备份目录生成清单并写入目录
+    pub(crate) fn write(dir: &Path, lsn: Lsn, encrypted: bool) -> Result<Self, ExecutionError> {
+        let mut files = Vec::new();
+        for entry in fs::read_dir(dir).map_err(|e| backup_error("Failed to list backup directory", e))? {
+            let entry = entry.map_err(|e| backup_error("Failed to list backup directory", e))?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name == BACKUP_MANIFEST_NAME || !entry.path().is_file() {
+                continue;
+            }
+            let data = fs::read(entry.path()).map_err(|e| backup_error("Failed to read backup file", e))?;
+            files.push(BackupFile {
+                name,
+                size: data.len() as u64,
+                checksum: crate::utils::checksum(&data),
+            });
+        }
+        files.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let created_at = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs());
+        let manifest = Self { version: BACKUP_FORMAT_VERSION, created_at, lsn, encrypted, files };
+        let json = serde_json::to_string_pretty(&manifest)
+            .map_err(|e| ExecutionError::StorageError(format!("Serialization error: {}", e)))?;
+        let path = dir.join(BACKUP_MANIFEST_NAME);
+        fs::write(&path, json)
+            .and_then(|_| fs::File::open(&path)?.sync_all())
+            .map_err(|e| backup_error("Failed to write backup manifest", e))?;
+        Ok(manifest)
+    }
+}
+
+/// 时间点恢复重放到哪里
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecoveryTarget {
+    /// 重放归档中的全部批次
+    Latest,
+    /// 重放提交 LSN 不超过给定值的批次
+    Lsn(Lsn),
+    /// 重放在给定时间及之前提交的批次
+    Time(SystemTime),
+}
+
+impl RecoveryTarget {
+    /// 批次是否在恢复目标之内
+    fn includes(&self, batch: &WalBatch) -> Result<bool, ExecutionError> {
+        match self {
+            RecoveryTarget::Latest => Ok(true),
+            RecoveryTarget::Lsn(lsn) => Ok(batch.commit_lsn <= *lsn),
+            RecoveryTarget::Time(time) => {
+                let committed_at = batch.committed_at.ok_or_else(|| ExecutionError::StorageError(format!(
+                    "Archived batch at LSN {} has no commit time; recover to an LSN instead",
+                    batch.commit_lsn
+                )))?;
+                let target = time.duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_millis() as u64);
+                Ok(committed_at <= target)
+            }
+        }
+    }
+}
+
+/// 时间点恢复的结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecoveryInfo {
+    /// 备份包含的最后一条 WAL 记录
+    pub backup_lsn: Lsn,
+    /// 恢复后的最后一条 WAL 记录
+    pub recovered_lsn: Lsn,
+    /// 最后一个重放批次的提交时间（Unix 时间戳，毫秒；没有重放批次时为 None）
+    pub recovered_at: Option<u64>,
+    /// 从归档重放的批次数
+    pub batches: usize,
+}
+
+/// 校验备份并把清单中的文件复制到新的数据目录
+pub(crate) fn restore(backup_dir: &Path, data_dir: &Path) -> Result<BackupManifest, ExecutionError> {
+    let manifest = BackupManifest::read(backup_dir)?;
+    manifest.verify(backup_dir)?;
+    copy_backup(&manifest, backup_dir, data_dir)?;
+    Ok(manifest)
+}
+
+/// 恢复备份，再把 WAL 归档中备份之后提交的批次追加到新数据目录的 WAL，直到恢复目标
+///
+/// 备份和归档都先校验完毕，需要的批次缺失或不连续时不会创建数据目录。
+pub(crate) fn restore_to(
+    backup_dir: &Path,
+    data_dir: &Path,
+    target: RecoveryTarget,
+    config: &DatabaseConfig,
+) -> Result<RecoveryInfo, ExecutionError> {
+    let archive_dir = config.wal_archive_dir.as_deref().ok_or_else(|| {
+        ExecutionError::SemanticError("Point-in-time recovery needs a WAL archive directory".to_string())
+    })?;
+    let manifest = BackupManifest::read(backup_dir)?;
+    manifest.verify(backup_dir)?;
+    let key = config.encryption_key.as_ref();
+    let archive = open_archive(archive_dir, key)?;
+    let batches = archive.batches()
+        .map_err(|e| backup_error("Failed to read WAL archive", e))?;
+
+    let mut selected = Vec::new();
+    // 备份之后建立、在恢复目标之前没有删除的表（名称）
+    let mut created = HashMap::new();
+    let mut next_lsn = manifest.lsn + 1;
+    for batch in batches {
+        if batch.commit_lsn <= manifest.lsn {
+            continue;
+        }
+        if !target.includes(&batch)? {
+            break;
+        }
+        if batch.first_lsn != next_lsn {
+            return Err(ExecutionError::StorageError(format!(
+                "WAL archive has no batch starting at LSN {} (next archived batch starts at LSN {})",
+                next_lsn, batch.first_lsn
+            )));
+        }
+        for entry in &batch.entries {
+            match &entry.record {
+                WalRecord::CreateTable { table_id, name } if !manifest.contains(&format!("table_{}.json", table_id)) => {
+                    created.insert(*table_id, (name.clone(), entry.lsn));
+                }
+                WalRecord::DropTable { table_id, .. } => {
+                    created.remove(table_id);
+                }
+                _ => {}
+            }
+        }
+        next_lsn = batch.commit_lsn + 1;
+        selected.push(batch);
+    }
+    if let Some((name, lsn)) = created.values().min_by_key(|(_, lsn)| *lsn) {
+        return Err(ExecutionError::SemanticError(format!(
+            "Table '{}' was created after the backup at LSN {} and cannot be recovered from the WAL archive; recover to an earlier LSN",
+            name, lsn
+        )));
+    }
+
+    copy_backup(&manifest, backup_dir, data_dir)?;
+    let backend = open_directory(data_dir, key)?;
+    let (mut wal, _) = Wal::open(backend.as_ref())
+        .map_err(|e| backup_error("Failed to read restored WAL", e))?;
+    for batch in &selected {
+        wal.append_batch(backend.as_ref(), batch)
+            .map_err(|e| backup_error("Failed to replay WAL archive", e))?;
+    }
+    Ok(RecoveryInfo {
+        backup_lsn: manifest.lsn,
+        recovered_lsn: wal.last_lsn(),
+        recovered_at: selected.last().and_then(|batch| batch.committed_at),
+        batches: selected.len(),
+    })
+}
+
+/// 打开 WAL 归档目录（不存在时创建），有密钥时归档同样加密
+pub(crate) fn open_archive(dir: &Path, key: Option<&EncryptionKey>) -> Result<WalArchive, ExecutionError> {
+    Ok(WalArchive::new(open_directory(dir, key)?))
+}
+
+/// 打开目录作为存储后端，有密钥时以它加密
+pub(crate) fn open_directory(dir: &Path, key: Option<&EncryptionKey>) -> Result<Box<dyn FileBackend>, ExecutionError> {
+    let backend = DirectoryBackend::new(dir)
+        .map_err(|e| backup_error(&format!("Failed to open directory '{}'", dir.display()), e))?;
+    Ok(match key {
+        Some(key) => Box::new(EncryptedBackend::open(Box::new(backend), key.clone())?),
+        None => Box::new(backend),
+    })
+}
+
+/// 把已校验的备份文件复制到新的数据目录
+fn copy_backup(manifest: &BackupManifest, backup_dir: &Path, data_dir: &Path) -> Result<(), ExecutionError> {
+    create_target(data_dir)?;
+    for file in &manifest.files {
+        copy_file(&backup_dir.join(&file.name), &data_dir.join(&file.name))?;
+    }
+    Ok(())
+}
+
+/// 创建备份或恢复的目标目录；已存在的非空目录不会被覆盖
+pub(crate) fn create_target(dir: &Path) -> Result<(), ExecutionError> {
+    if dir.exists() {
+        let mut entries = fs::read_dir(dir).map_err(|e| backup_error("Failed to read backup directory", e))?;
+        if entries.next().is_some() {
+            return Err(ExecutionError::StorageError(format!(
+                "Directory '{}' is not empty",
+                dir.display()
+            )));
+        }
+    }
+    fs::create_dir_all(dir).map_err(|e| backup_error("Failed to create backup directory", e))
+}
+
+/// 复制一个文件并落盘
+pub(crate) fn copy_file(from: &Path, to: &Path) -> Result<(), ExecutionError> {
+    fs::copy(from, to).map_err(|e| backup_error(&format!("Failed to copy '{}'", from.display()), e))?;
+    fs::File::open(to)
+        .and_then(|file| file.sync_all())
+        .map_err(|e| backup_error(&format!("Failed to sync '{}'", to.display()), e))
+}
+
+pub(crate) fn backup_error(context: &str, error: impl std::fmt::Display) -> ExecutionError {
+    ExecutionError::StorageError(format!("{}: {}", context, error))
+}
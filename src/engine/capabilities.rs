@@ -0,0 +1,178 @@
+//! 引擎能力清单
+//!
+//! 工具和驱动可以先查询 `Database::capabilities()`，据此决定生成什么样的 SQL，
+//! 而不是执行后才收到含义不明的错误。`ExecutionError::NotImplemented` 也会标明
+//! 触发它的能力名，与这里的清单一一对应。
+
+use serde::Serialize;
+use std::fmt;
+
+/// 可查询的 SQL 能力
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    /// JOIN 连接查询
+    Joins,
+    /// 子查询
+    Subqueries,
+    /// 窗口函数（OVER 子句）
+    WindowFunctions,
+    /// WITH 公共表表达式
+    CommonTableExpressions,
+    /// BEGIN / COMMIT / ROLLBACK 显式事务
+    Transactions,
+    /// CREATE VIEW 视图
+    Views,
+    /// GROUP BY 分组
+    GroupBy,
+    /// HAVING 分组过滤
+    Having,
+    /// 聚合函数
+    Aggregates,
+    /// SELECT 列表中的表达式
+    SelectExpressions,
+    /// WHERE 条件中的谓词
+    WherePredicates,
+    /// 标量函数
+    ScalarFunctions,
+    /// 二级索引
+    Indexes,
+    /// 外键约束
+    ForeignKeys,
+    /// INSERT / UPDATE / DELETE ... RETURNING
+    Returning,
+    /// INSERT ... ON CONFLICT
+    Upsert,
+    /// 带参数占位符的预编译语句
+    PreparedStatements,
+    /// EXPLAIN
+    Explain,
+    /// DRY RUN 预演修改
+    DryRun,
+    /// COPY 批量导入导出
+    Copy,
+    /// ASSERT 数据质量断言
+    Assertions,
+    /// BACKUP 在线备份与恢复
+    Backup,
+}
+
+/// 支持程度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Support {
+    /// 完整支持
+    Full,
+    /// 部分支持，限制见说明
+    Partial,
+    /// 不支持
+    Unsupported,
+}
+
+/// 一项能力的支持情况
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CapabilityInfo {
+    pub capability: Capability,
+    pub support: Support,
+    /// 支持范围或限制的简要说明
+    pub note: &'static str,
+}
+
+impl CapabilityInfo {
+    /// 是否至少部分支持
+    pub fn is_supported(&self) -> bool {
+        self.support != Support::Unsupported
+    }
+}
+
+impl Capability {
+    /// 全部能力，按清单顺序
+    pub const ALL: &'static [Capability] = &[
+        Capability::Joins,
+        Capability::Subqueries,
+        Capability::WindowFunctions,
+        Capability::CommonTableExpressions,
+        Capability::Transactions,
+        Capability::Views,
+        Capability::GroupBy,
+        Capability::Having,
+        Capability::Aggregates,
+        Capability::SelectExpressions,
+        Capability::WherePredicates,
+        Capability::ScalarFunctions,
+        Capability::Indexes,
+        Capability::ForeignKeys,
+        Capability::Returning,
+        Capability::Upsert,
+        Capability::PreparedStatements,
+        Capability::Explain,
+        Capability::DryRun,
+        Capability::Copy,
+        Capability::Assertions,
+        Capability::Backup,
+    ];
+
+    /// 稳定的能力名，用于错误信息和工具匹配
+    pub fn name(self) -> &'static str {
+        match self {
+            Capability::Joins => "joins",
+            Capability::Subqueries => "subqueries",
+            Capability::WindowFunctions => "window_functions",
+            Capability::CommonTableExpressions => "common_table_expressions",
+            Capability::Transactions => "transactions",
+            Capability::Views => "views",
+            Capability::GroupBy => "group_by",
+            Capability::Having => "having",
+            Capability::Aggregates => "aggregates",
+            Capability::SelectExpressions => "select_expressions",
+            Capability::WherePredicates => "where_predicates",
+            Capability::ScalarFunctions => "scalar_functions",
+            Capability::Indexes => "indexes",
+            Capability::ForeignKeys => "foreign_keys",
+            Capability::Returning => "returning",
+            Capability::Upsert => "upsert",
+            Capability::PreparedStatements => "prepared_statements",
+            Capability::Explain => "explain",
+            Capability::DryRun => "dry_run",
+            Capability::Copy => "copy",
+            Capability::Assertions => "assertions",
+            Capability::Backup => "backup",
+        }
+    }
+
+    /// 当前引擎对此能力的支持情况
+    pub fn info(self) -> CapabilityInfo {
+        let (support, note) = match self {
+            Capability::Joins => (Support::Partial, "INNER / LEFT / RIGHT / FULL JOIN，按优化器选择的哈希、归并或嵌套循环连接执行；不支持表别名"),
+            Capability::Subqueries => (Support::Partial, "SELECT 的 WHERE 中 AND 连接的 IN / EXISTS 子查询改写为半连接或反连接执行；其他子查询尚不支持"),
+            Capability::WindowFunctions => (Support::Unsupported, ""),
+            Capability::CommonTableExpressions => (Support::Unsupported, ""),
+            Capability::Transactions => (Support::Partial, "BEGIN / COMMIT / ROLLBACK；单会话，事务中不能执行 DDL"),
+            Capability::Views => (Support::Partial, "仅 CREATE TEMP VIEW：会话级临时视图，不保存到磁盘"),
+            Capability::GroupBy => (Support::Partial, "按列、表达式或 SELECT 列表序号分组，不支持 SELECT *"),
+            Capability::Having => (Support::Unsupported, ""),
+            Capability::Aggregates => (Support::Full, "COUNT、SUM、AVG、MIN、MAX、STRING_AGG、STDDEV、VARIANCE"),
+            Capability::SelectExpressions => (Support::Partial, "支持列、函数调用和 CAST，不支持字面量和运算表达式"),
+            Capability::WherePredicates => (Support::Partial, "比较、AND/OR/NOT、IN、BETWEEN、LIKE、IS [NOT] NULL"),
+            Capability::ScalarFunctions => (Support::Full, "日期、数学、UPPER/LOWER/LENGTH、COALESCE/NULLIF、RANDOM、GEN_UUID、JSON_EXTRACT"),
+            Capability::Indexes => (Support::Full, "CREATE [UNIQUE] INDEX / DROP INDEX"),
+            Capability::ForeignKeys => (Support::Unsupported, "FOREIGN KEY 可以解析，但写入时不检查；ALTER TABLE ADD FOREIGN KEY 会报错"),
+            Capability::Returning => (Support::Full, ""),
+            Capability::Upsert => (Support::Full, "ON CONFLICT DO NOTHING / DO UPDATE"),
+            Capability::PreparedStatements => (Support::Full, "$n 与 ? 占位符"),
+            Capability::Explain => (Support::Full, ""),
+            Capability::DryRun => (Support::Partial, "仅 UPDATE 和 DELETE"),
+            Capability::Copy => (Support::Full, "COPY FROM 导入 CSV / NDJSON（INFER 可推断建表），COPY (SELECT ...) TO 导出 CSV"),
+            Capability::Assertions => (Support::Partial, "ASSERT (SELECT ...) 比较 / ASSERT [NOT] EXISTS；不支持 CREATE ASSERTION"),
+            Capability::Backup if cfg!(feature = "persistence") => (Support::Full, "BACKUP TO '目录'，无需关闭数据库；Database::restore 校验后恢复"),
+            Capability::Backup => (Support::Unsupported, "需要 persistence 特性"),
+        };
+        CapabilityInfo { capability: self, support, note }
+    }
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.name())
+    }
+}
@@ -0,0 +1,278 @@
+//! Arrow / Parquet 导出
+//!
+//! 把查询结果按列转换为 Arrow [`RecordBatch`]，可以直接交给 pandas、polars 等分析工具；
+//! 也可以写成 Parquet 文件。列类型对应关系：INTEGER → Int32，BIGINT → Int64，FLOAT → Float32，
+//! DOUBLE → Float64，DECIMAL(p, s) → Decimal128(p, s)，VARCHAR / TEXT / JSON → Utf8，BLOB → Binary，UUID → 16 字节的 FixedSizeBinary，BOOLEAN → Boolean，DATE → Date32，TIMESTAMP → 微秒精度的 Timestamp。
+
+use crate::engine::database::ExecutionError;
+use crate::types::{DataType, Schema, Tuple, Value};
+use arrow::array::{
+    ArrayRef, BinaryBuilder, BooleanBuilder, Date32Builder, Decimal128Builder, FixedSizeBinaryBuilder, Float32Builder, Float64Builder, Int32Builder, Int64Builder,
+    StringBuilder, TimestampMicrosecondBuilder,
+};
+use arrow::datatypes::{DataType as ArrowType, Field, Schema as ArrowSchema, SchemaRef, TimeUnit};
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::ArrowWriter;
+use std::io::Write;
+use std::sync::Arc;
+
+/// 写 Parquet 时每个 RecordBatch 的行数；按批转换，不必先把整个结果转成一个大批
+pub const PARQUET_BATCH_ROWS: usize = 8192;
+
+/// 结果模式对应的 Arrow 模式
+pub fn arrow_schema(schema: &Schema) -> SchemaRef {
+    let fields: Vec<Field> = schema.columns.iter()
+        .map(|column| Field::new(&column.name, arrow_type(&column.data_type), column.nullable))
+        .collect();
+    Arc::new(ArrowSchema::new(fields))
+}
+
+fn arrow_type(data_type: &DataType) -> ArrowType {
+    match data_type {
+        DataType::Integer => ArrowType::Int32,
+        DataType::BigInt => ArrowType::Int64,
+        DataType::Float => ArrowType::Float32,
+        DataType::Double => ArrowType::Float64,
+        DataType::Decimal(precision, scale) => ArrowType::Decimal128(*precision, *scale as i8),
+        DataType::Varchar(_) | DataType::Char(_) | DataType::Text | DataType::Json => ArrowType::Utf8,
+        DataType::Blob => ArrowType::Binary,
+        DataType::Uuid => ArrowType::FixedSizeBinary(16),
+        DataType::Boolean => ArrowType::Boolean,
+        DataType::Date => ArrowType::Date32,
+        DataType::Timestamp => ArrowType::Timestamp(TimeUnit::Microsecond, None),
+    }
+}
+
+/// 把行转换为一个 RecordBatch
+///
+/// 值的类型与列类型不同时先按 CAST 的规则转换，无法转换时报告列名和行号。
+/// 模式中标为 NOT NULL 的列出现 NULL 时，对应字段改为可空，而不是让转换失败。
+pub fn record_batch(schema: &Schema, rows: &[Tuple]) -> Result<RecordBatch, ExecutionError> {
+    let mut fields = Vec::with_capacity(schema.columns.len());
+    let mut columns = Vec::with_capacity(schema.columns.len());
+    for (i, column) in schema.columns.iter().enumerate() {
+        let values = rows.iter().enumerate()
+            .map(|(row, tuple)| {
+                let value = tuple.values.get(i).unwrap_or(&Value::Null);
+                match value {
+                    Value::Null => Ok(None),
+                    value if value.data_type() == column.data_type => Ok(Some(value.clone())),
+                    value => value.cast_to(&column.data_type).map(Some).map_err(|e| ExecutionError::EvaluationError {
+                        message: format!("row {}, column '{}': {}", row + 1, column.name, e),
+                    }),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let nullable = column.nullable || values.iter().any(Option::is_none);
+        fields.push(Field::new(&column.name, arrow_type(&column.data_type), nullable));
+        columns.push(column_array(&column.data_type, &values));
+    }
+    RecordBatch::try_new(Arc::new(ArrowSchema::new(fields)), columns)
+        .map_err(|e| ExecutionError::EvaluationError { message: format!("Arrow error: {}", e) })
+}
+
+/// 一列已转换为列类型的值
+fn column_array(data_type: &DataType, values: &[Option<Value>]) -> ArrayRef {
+    match data_type {
+        DataType::Integer => {
+            let mut builder = Int32Builder::with_capacity(values.len());
+            for value in values {
+                builder.append_option(match value { Some(Value::Integer(i)) => Some(*i), _ => None });
+            }
+            Arc::new(builder.finish())
+        }
+        DataType::BigInt => {
+            let mut builder = Int64Builder::with_capacity(values.len());
+            for value in values {
+                builder.append_option(match value { Some(Value::BigInt(i)) => Some(*i), _ => None });
+            }
+            Arc::new(builder.finish())
+        }
+        DataType::Float => {
+            let mut builder = Float32Builder::with_capacity(values.len());
+            for value in values {
+                builder.append_option(match value { Some(Value::Float(f)) => Some(*f), _ => None });
+            }
+            Arc::new(builder.finish())
+        }
+        DataType::Double => {
+            let mut builder = Float64Builder::with_capacity(values.len());
+            for value in values {
+                builder.append_option(match value { Some(Value::Double(d)) => Some(*d), _ => None });
+            }
+            Arc::new(builder.finish())
+        }
+        DataType::Decimal(precision, scale) => {
+            let mut builder = Decimal128Builder::with_capacity(values.len())
+                .with_precision_and_scale(*precision, *scale as i8)
+                .expect("DECIMAL precision and scale are within Arrow's limits");
+            for value in values {
+                // 值已转换为列的小数位数，尾数即 Arrow 的存储值
+                builder.append_option(match value { Some(Value::Decimal(d)) => Some(d.mantissa()), _ => None });
+            }
+            Arc::new(builder.finish())
+        }
+        DataType::Varchar(_) | DataType::Char(_) | DataType::Text | DataType::Json => {
+            let mut builder = StringBuilder::with_capacity(values.len(), 0);
+            for value in values {
+                builder.append_option(match value { Some(Value::Varchar(s) | Value::Json(s)) => Some(s.as_str()), _ => None });
+            }
+            Arc::new(builder.finish())
+        }
+        DataType::Blob => {
+            let mut builder = BinaryBuilder::with_capacity(values.len(), 0);
+            for value in values {
+                builder.append_option(match value { Some(Value::Blob(b)) => Some(b.as_slice()), _ => None });
+            }
+            Arc::new(builder.finish())
+        }
+        DataType::Uuid => {
+            let mut builder = FixedSizeBinaryBuilder::with_capacity(values.len(), 16);
+            for value in values {
+                match value {
+                    Some(Value::Uuid(u)) => builder.append_value(u.as_bytes()).expect("UUID is 16 bytes"),
+                    _ => builder.append_null(),
+                }
+            }
+            Arc::new(builder.finish())
+        }
+        DataType::Boolean => {
+            let mut builder = BooleanBuilder::with_capacity(values.len());
+            for value in values {
+                builder.append_option(match value { Some(Value::Boolean(b)) => Some(*b), _ => None });
+            }
+            Arc::new(builder.finish())
+        }
+        DataType::Date => {
+            let mut builder = Date32Builder::with_capacity(values.len());
+            for value in values {
+                builder.append_option(match value {
+                    #[cfg(feature = "chrono")]
+                    Some(Value::Date(d)) => Some(d.signed_duration_since(unix_epoch()).num_days() as i32),
+                    _ => None,
+                });
+            }
+            Arc::new(builder.finish())
+        }
+        DataType::Timestamp => {
+            let mut builder = TimestampMicrosecondBuilder::with_capacity(values.len());
+            for value in values {
+                builder.append_option(match value {
+                    #[cfg(feature = "chrono")]
+                    Some(Value::Timestamp(ts)) => Some(ts.and_utc().timestamp_micros()),
+                    _ => None,
+                });
+            }
+            Arc::new(builder.finish())
+        }
+    }
+}
+
+#[cfg(feature = "chrono")]
+fn unix_epoch() -> chrono::NaiveDate {
+    chrono::NaiveDate::from_ymd_opt(1970, 1, 1).expect("valid date")
+}
+
+/// 把行写成 Parquet 文件，每 [`PARQUET_BATCH_ROWS`] 行一批；返回写出的行数
+pub fn write_parquet<W: Write + Send>(writer: W, schema: &Schema, rows: &[Tuple]) -> Result<usize, ExecutionError> {
+    let parquet_error = |e: parquet::errors::ParquetError| ExecutionError::StorageError(format!("Parquet error: {}", e));
+    // 每批的字段可空性必须与文件模式一致，因此文件中的列一律可空
+    let mut file_schema = Schema { columns: schema.columns.clone(), primary_key: None };
+    for column in &mut file_schema.columns {
+        column.nullable = true;
+    }
+    let mut writer = ArrowWriter::try_new(writer, arrow_schema(&file_schema), None).map_err(parquet_error)?;
+    for chunk in rows.chunks(PARQUET_BATCH_ROWS) {
+        writer.write(&record_batch(&file_schema, chunk)?).map_err(parquet_error)?;
+    }
+    writer.close().map_err(parquet_error)?;
+    Ok(rows.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ColumnDefinition;
+    use arrow::array::{Array, BinaryArray, Decimal128Array, Float64Array, Int32Array, StringArray};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    fn sample() -> (Schema, Vec<Tuple>) {
+        let schema = Schema {
+            columns: vec![
+                ColumnDefinition { name: "id".to_string(), data_type: DataType::Integer, nullable: false, default: None, default_function: None },
+                ColumnDefinition { name: "name".to_string(), data_type: DataType::Varchar(20), nullable: true, default: None, default_function: None },
+                ColumnDefinition { name: "score".to_string(), data_type: DataType::Double, nullable: true, default: None, default_function: None },
+            ],
+            primary_key: None,
+        };
+        let rows = (0..10_000)
+            .map(|i| Tuple::new(vec![
+                Value::Integer(i),
+                if i % 3 == 0 { Value::Null } else { Value::Varchar(format!("n{}", i)) },
+                Value::Double(i as f64 / 2.0),
+            ]))
+            .collect();
+        (schema, rows)
+    }
+
+    #[test]
+    fn test_record_batch() {
+        let (schema, rows) = sample();
+        let batch = record_batch(&schema, &rows[..4]).unwrap();
+        assert_eq!(batch.num_rows(), 4);
+        assert_eq!(batch.schema().field(0).data_type(), &ArrowType::Int32);
+        assert!(!batch.schema().field(0).is_nullable());
+        let names = batch.column(1).as_any().downcast_ref::<StringArray>().unwrap();
+        assert!(names.is_null(0));
+        assert_eq!(names.value(1), "n1");
+        let scores = batch.column(2).as_any().downcast_ref::<Float64Array>().unwrap();
+        assert_eq!(scores.value(3), 1.5);
+
+        // 值按列类型转换；无法转换时报告位置
+        let mixed = vec![Tuple::new(vec![Value::BigInt(7), Value::Integer(5), Value::Null])];
+        let batch = record_batch(&schema, &mixed).unwrap();
+        assert_eq!(batch.column(0).as_any().downcast_ref::<Int32Array>().unwrap().value(0), 7);
+        let bad = vec![Tuple::new(vec![Value::Varchar("x".to_string()), Value::Null, Value::Null])];
+        let err = record_batch(&schema, &bad).unwrap_err().to_string();
+        assert!(err.contains("row 1, column 'id'"), "{}", err);
+    }
+
+    #[test]
+    fn test_decimal_column() {
+        let schema = Schema::new(vec![ColumnDefinition::new("price".to_string(), DataType::Decimal(10, 2), true)]);
+        let rows = vec![
+            Tuple::new(vec![Value::Decimal("19.99".parse().unwrap())]),
+            Tuple::new(vec![Value::Integer(3)]),
+        ];
+        let batch = record_batch(&schema, &rows).unwrap();
+        assert_eq!(batch.schema().field(0).data_type(), &ArrowType::Decimal128(10, 2));
+        let prices = batch.column(0).as_any().downcast_ref::<Decimal128Array>().unwrap();
+        assert_eq!((prices.value(0), prices.value(1)), (1999, 300));
+    }
+
+    #[test]
+    fn test_text_and_blob_columns() {
+        let schema = Schema::new(vec![
+            ColumnDefinition::new("body".to_string(), DataType::Text, true),
+            ColumnDefinition::new("data".to_string(), DataType::Blob, true),
+        ]);
+        let rows = vec![Tuple::new(vec![Value::Varchar("x".repeat(70_000)), Value::Blob(vec![0, 255])])];
+        let batch = record_batch(&schema, &rows).unwrap();
+        assert_eq!(batch.schema().field(1).data_type(), &ArrowType::Binary);
+        assert_eq!(batch.column(0).as_any().downcast_ref::<StringArray>().unwrap().value(0).len(), 70_000);
+        assert_eq!(batch.column(1).as_any().downcast_ref::<BinaryArray>().unwrap().value(0), &[0, 255]);
+    }
+
+    #[test]
+    fn test_write_parquet() {
+        let (schema, rows) = sample();
+        let file = tempfile::tempfile().unwrap();
+        assert_eq!(write_parquet(file.try_clone().unwrap(), &schema, &rows).unwrap(), rows.len());
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(file).unwrap().build().unwrap();
+        let batches: Vec<RecordBatch> = reader.collect::<Result<_, _>>().unwrap();
+        assert_eq!(batches.iter().map(RecordBatch::num_rows).sum::<usize>(), rows.len());
+        let ids = batches[0].column(0).as_any().downcast_ref::<Int32Array>().unwrap();
+        assert_eq!(ids.value(42), 42);
+    }
+}
@@ -0,0 +1,256 @@
+//! 数据库配置
+
+use crate::engine::observer::ExecutorObserver;
+use crate::storage::buffer::CachePolicyType;
+use crate::storage::compression::Compression;
+use crate::storage::encryption::EncryptionKey;
+use crate::storage::file::FileIo;
+use crate::storage::temp::DEFAULT_TEMP_QUOTA_BYTES;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 缓冲池默认页数
+pub const DEFAULT_BUFFER_POOL_SIZE: usize = 128;
+
+/// WAL 超过此大小（字节）时在提交点自动执行检查点
+pub const DEFAULT_WAL_CHECKPOINT_BYTES: u64 = 16 * 1024 * 1024;
+
+/// 默认的锁等待超时
+pub const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 索引构建默认每批处理的行数
+pub const DEFAULT_INDEX_BUILD_BATCH_ROWS: usize = 10_000;
+
+/// 后台写页线程默认的刷写间隔
+pub const DEFAULT_BACKGROUND_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 后台写页线程每轮默认最多写回的脏页数
+pub const DEFAULT_BACKGROUND_FLUSH_PAGES: usize = 32;
+
+/// ORDER BY 默认可用的工作内存（字节）
+pub const DEFAULT_SORT_WORK_MEM_BYTES: usize = 64 * 1024 * 1024;
+
+/// 打开数据库时使用的配置
+#[derive(Clone)]
+pub struct DatabaseConfig {
+    /// 缓冲池页数
+    pub buffer_pool_size: usize,
+    /// 缓冲池的页替换策略
+    pub buffer_policy: CachePolicyType,
+    /// 临时文件磁盘配额（字节）
+    pub temp_quota_bytes: u64,
+    /// ORDER BY 键相同时是否确定性地打破并列（默认顺序未指定）
+    pub deterministic_sort: bool,
+    /// GROUP BY 结果是否按分组键排序输出（默认按哈希表迭代顺序，每次运行可能不同）
+    pub deterministic_grouping: bool,
+    /// RANDOM() 的随机数种子（None 表示以当前时间为种子）
+    pub random_seed: Option<u64>,
+    /// 是否记录查询负载统计（information_schema.query_stats / table_activity）
+    pub track_workload: bool,
+    /// 算子事件观察者
+    pub observer: Option<Arc<dyn ExecutorObserver>>,
+    /// 索引构建每批处理的行数，每批结束时保存一次进度
+    pub index_build_batch_rows: usize,
+    /// 安全模式：拒绝既没有 WHERE 也没有写出 ALL 的 UPDATE / DELETE（交互式 shell 默认开启）
+    pub safe_updates: bool,
+    /// 写入 VARCHAR(n) / CHAR(n) 列的字符串超长时截断而不是报错（超出部分只有空格时总是截断）
+    pub truncate_strings: bool,
+    /// WAL 超过此大小（字节）时在提交点自动执行检查点（0 表示只在 CHECKPOINT 时截断）
+    pub wal_checkpoint_bytes: u64,
+    /// 数据目录的大小上限（字节），达到后拒绝 INSERT / COPY / CREATE INDEX（None 表示不限制）
+    pub max_data_bytes: Option<u64>,
+    /// 显式事务等待其他事务持有的行锁或表锁的最长时间
+    pub lock_timeout: Duration,
+    /// 表页文件的默认压缩方式（ALTER TABLE ... SET COMPRESSION 可按表覆盖）
+    pub page_compression: Compression,
+    /// 未压缩页文件的读写方式：系统调用或内存映射（需要 mmap 特性）
+    pub file_io: FileIo,
+    /// 后台写页线程的刷写间隔（None 表示不启动，脏页只在淘汰、检查点和关闭时写回）
+    pub background_flush_interval: Option<Duration>,
+    /// 后台写页线程每轮最多写回的脏页数
+    pub background_flush_pages: usize,
+    /// 静态数据加密密钥：页文件、快照、元数据和 WAL 都以 AES-256-GCM 加密（需要 encryption 特性）
+    pub encryption_key: Option<EncryptionKey>,
+    /// WAL 归档目录：每个提交的批次也追加到这里，检查点之后仍然保留，用于时间点恢复（None 表示不归档）
+    pub wal_archive_dir: Option<PathBuf>,
+    /// 查询结果缓存最多保存的结果数（0 表示不缓存）
+    pub result_cache_entries: usize,
+    /// ORDER BY 在内存中排序的行超过此大小（字节）时分批写入临时文件再归并（内存数据库总是在内存中排序）
+    pub sort_work_mem_bytes: usize,
+}
+
+impl DatabaseConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_buffer_pool_size(mut self, pages: usize) -> Self {
+        self.buffer_pool_size = pages;
+        self
+    }
+
+    pub fn with_buffer_policy(mut self, policy: CachePolicyType) -> Self {
+        self.buffer_policy = policy;
+        self
+    }
+
+    pub fn with_temp_quota_bytes(mut self, bytes: u64) -> Self {
+        self.temp_quota_bytes = bytes;
+        self
+    }
+
+    pub fn with_deterministic_sort(mut self, enabled: bool) -> Self {
+        self.deterministic_sort = enabled;
+        self
+    }
+
+    pub fn with_deterministic_grouping(mut self, enabled: bool) -> Self {
+        self.deterministic_grouping = enabled;
+        self
+    }
+
+    pub fn with_random_seed(mut self, seed: u64) -> Self {
+        self.random_seed = Some(seed);
+        self
+    }
+
+    pub fn with_workload_tracking(mut self, enabled: bool) -> Self {
+        self.track_workload = enabled;
+        self
+    }
+
+    pub fn with_observer(mut self, observer: Arc<dyn ExecutorObserver>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    pub fn with_index_build_batch_rows(mut self, rows: usize) -> Self {
+        self.index_build_batch_rows = rows;
+        self
+    }
+
+    pub fn with_safe_updates(mut self, enabled: bool) -> Self {
+        self.safe_updates = enabled;
+        self
+    }
+
+    pub fn with_truncate_strings(mut self, enabled: bool) -> Self {
+        self.truncate_strings = enabled;
+        self
+    }
+
+    pub fn with_wal_checkpoint_bytes(mut self, bytes: u64) -> Self {
+        self.wal_checkpoint_bytes = bytes;
+        self
+    }
+
+    pub fn with_max_data_bytes(mut self, bytes: u64) -> Self {
+        self.max_data_bytes = Some(bytes);
+        self
+    }
+
+    pub fn with_lock_timeout(mut self, timeout: Duration) -> Self {
+        self.lock_timeout = timeout;
+        self
+    }
+
+    pub fn with_page_compression(mut self, compression: Compression) -> Self {
+        self.page_compression = compression;
+        self
+    }
+
+    pub fn with_file_io(mut self, io: FileIo) -> Self {
+        self.file_io = io;
+        self
+    }
+
+    pub fn with_background_flush_interval(mut self, interval: Option<Duration>) -> Self {
+        self.background_flush_interval = interval;
+        self
+    }
+
+    pub fn with_background_flush_pages(mut self, pages: usize) -> Self {
+        self.background_flush_pages = pages;
+        self
+    }
+
+    pub fn with_encryption_key(mut self, key: EncryptionKey) -> Self {
+        self.encryption_key = Some(key);
+        self
+    }
+
+    pub fn with_wal_archive_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.wal_archive_dir = Some(dir.into());
+        self
+    }
+
+    pub fn with_result_cache_entries(mut self, entries: usize) -> Self {
+        self.result_cache_entries = entries;
+        self
+    }
+
+    pub fn with_sort_work_mem_bytes(mut self, bytes: usize) -> Self {
+        self.sort_work_mem_bytes = bytes;
+        self
+    }
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            buffer_pool_size: DEFAULT_BUFFER_POOL_SIZE,
+            buffer_policy: CachePolicyType::LRU,
+            temp_quota_bytes: DEFAULT_TEMP_QUOTA_BYTES,
+            deterministic_sort: false,
+            deterministic_grouping: false,
+            random_seed: None,
+            track_workload: true,
+            observer: None,
+            index_build_batch_rows: DEFAULT_INDEX_BUILD_BATCH_ROWS,
+            safe_updates: false,
+            truncate_strings: false,
+            wal_checkpoint_bytes: DEFAULT_WAL_CHECKPOINT_BYTES,
+            max_data_bytes: None,
+            lock_timeout: DEFAULT_LOCK_TIMEOUT,
+            page_compression: Compression::None,
+            file_io: FileIo::Syscall,
+            background_flush_interval: Some(DEFAULT_BACKGROUND_FLUSH_INTERVAL),
+            background_flush_pages: DEFAULT_BACKGROUND_FLUSH_PAGES,
+            encryption_key: None,
+            wal_archive_dir: None,
+            result_cache_entries: 0,
+            sort_work_mem_bytes: DEFAULT_SORT_WORK_MEM_BYTES,
+        }
+    }
+}
+
+impl fmt::Debug for DatabaseConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DatabaseConfig")
+            .field("buffer_pool_size", &self.buffer_pool_size)
+            .field("buffer_policy", &self.buffer_policy)
+            .field("temp_quota_bytes", &self.temp_quota_bytes)
+            .field("deterministic_sort", &self.deterministic_sort)
+            .field("deterministic_grouping", &self.deterministic_grouping)
+            .field("random_seed", &self.random_seed)
+            .field("track_workload", &self.track_workload)
+            .field("observer", &self.observer.is_some())
+            .field("index_build_batch_rows", &self.index_build_batch_rows)
+            .field("safe_updates", &self.safe_updates)
+            .field("truncate_strings", &self.truncate_strings)
+            .field("wal_checkpoint_bytes", &self.wal_checkpoint_bytes)
+            .field("max_data_bytes", &self.max_data_bytes)
+            .field("lock_timeout", &self.lock_timeout)
+            .field("page_compression", &self.page_compression)
+            .field("file_io", &self.file_io)
+            .field("background_flush_interval", &self.background_flush_interval)
+            .field("background_flush_pages", &self.background_flush_pages)
+            .field("encryption_key", &self.encryption_key)
+            .field("wal_archive_dir", &self.wal_archive_dir)
+            .field("result_cache_entries", &self.result_cache_entries)
+            .field("sort_work_mem_bytes", &self.sort_work_mem_bytes)
+            .finish()
+    }
+}
@@ -2,53 +2,346 @@
 //!
 //! 主数据库接口和查询执行协调。
 
-use crate::sql::{parse_sql, Statement};
-use crate::sql::parser::OrderByExpr;
+use crate::engine::executor::{ExecutorError, ScanRows};
+use crate::sql::{apply_rewrite_rules, parse_sql, AnalyzedStatement, Lexer, PlanError, RewriteRule, Statement, Token};
+use crate::sql::lexer::TokenInfo;
 use crate::sql::diagnostics::{DiagnosticEngine, DiagnosticContext};
-use crate::sql::optimizer::QueryOptimizer;
-use crate::storage::{BufferPool, FileManager};
-use crate::types::{Schema, Tuple, Value, DataType, ColumnDefinition};
+use crate::sql::cost;
+use crate::sql::optimizer::{OptimizedPlan, QueryOptimizer};
+use crate::sql::planner::{column_index, references_column, same_expression, ExecutionPlan, OperatorContext, ScanInput, SelectOperators, SortSettings};
+use crate::storage::{BackgroundWriter, BufferPool, BufferStats, CachePolicyType, Compression, CompressionError, EncryptionError, FileManager, TempFileManager};
+#[cfg(feature = "persistence")]
+use crate::storage::{DirectoryBackend, EncryptedBackend, EncryptionKey, FileBackend, FileError, HeapFile, MemoryBackend};
+#[cfg(feature = "persistence")]
+use crate::utils::serialize;
+#[cfg(feature = "persistence")]
+use crate::storage::temp::TEMP_DIR_NAME;
+#[cfg(feature = "persistence")]
+use crate::engine::rows::TABLE_WORK_DIR;
+use crate::storage::wal::{Lsn, WalRecord};
+#[cfg(feature = "persistence")]
+use crate::storage::wal::{Wal, WalEntry, WAL_FILE_NAME};
+#[cfg(feature = "persistence")]
+use crate::engine::backup::{self, BackupManifest, RecoveryInfo, RecoveryTarget};
+use crate::engine::advisor::{self, IndexAdvice};
+use crate::engine::capabilities::{Capability, CapabilityInfo};
+use crate::engine::config::DatabaseConfig;
+use crate::engine::functions;
+use crate::engine::diff;
+use crate::engine::dump;
+use crate::engine::export::CsvOptions;
+use crate::engine::prepared::{PreparedStatement, QueryDescription};
+use crate::engine::executor::{Executor, ProjectExecutor};
+use crate::engine::observer::{ExecutorObserver, ObservedExecutor, OperatorInfo, OperatorStats, StatsCollector};
+use crate::engine::import;
+use crate::engine::indexes::{self, IndexProbe, RowIndex};
+use crate::engine::random::RandomSource;
+use crate::engine::result_cache::{ResultCache, ResultCacheStats};
+use crate::engine::rows::TableRows;
+use crate::engine::statistics::{self, TableStatistics};
+use crate::engine::transaction::{LockType, TransactionError, TransactionId, TransactionManager};
+use crate::engine::workload::{Stopwatch, TableAccess, WorkloadTracker};
+use crate::types::{decimal, Schema, Tuple, Value, DataType, ColumnDefinition, ColumnMetadata, Decimal};
 use std::collections::HashMap;
-use std::path::{Path, PathBuf};
-use std::fs::File;
-use std::io::{Read, Write};
+#[cfg(feature = "persistence")]
+use std::path::Path;
 use serde::{Serialize, Deserialize};
 use thiserror::Error;
 
 /// 表数据存储结构
+#[cfg(feature = "persistence")]
 #[derive(Serialize, Deserialize)]
 struct TableData {
     schema: Schema,
+    /// 行直接保存在快照中（没有页文件或有行放不进一页时）
+    #[serde(default)]
     rows: Vec<Tuple>,
+    /// 保存行的堆文件版本号（见 heap_file_name）；None 表示行在 rows 中
+    #[serde(default)]
+    heap_generation: Option<u64>,
+    /// ALTER TABLE 设置的页压缩方式（None 表示使用全局配置）
+    #[serde(default)]
+    compression: Option<Compression>,
+    #[serde(default)]
+    indexes: Vec<IndexInfo>,
+    #[serde(default)]
+    row_ids: RowIds,
+    /// 快照包含的最后一条 WAL 记录；恢复时只重放之后的记录
+    #[serde(default)]
+    lsn: Lsn,
+    /// ANALYZE 收集的统计
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    statistics: Option<TableStatistics>,
+}
+
+/// 数据库元数据在存储后端中的名称
+#[cfg(feature = "persistence")]
+const METADATA_FILE_NAME: &str = "metadata.json";
+
+/// 正常关闭标记在存储后端中的名称；close 时写入，打开时读取后删除
+#[cfg(feature = "persistence")]
+const CLEAN_SHUTDOWN_MARKER: &str = "clean_shutdown";
+
+/// 表快照的堆文件名；每次快照写入新版本，快照 JSON 指向新文件后再删除旧文件
+#[cfg(feature = "persistence")]
+fn heap_file_name(table_id: u32, generation: u64) -> String {
+    format!("table_{}_{}", table_id, generation)
+}
+
+/// 表的索引文件名；保存表上已完成索引的结构，随快照整体重写
+#[cfg(feature = "persistence")]
+fn index_file_name(table_id: u32) -> String {
+    format!("table_{}.idx", table_id)
+}
+
+/// rowid 伪列名
+const ROWID_COLUMN: &str = "rowid";
+
+/// 列统计系统视图名
+pub const COLUMN_STATS_VIEW: &str = "information_schema.column_stats";
+
+/// 查询指纹统计系统视图名
+pub const QUERY_STATS_VIEW: &str = "information_schema.query_stats";
+
+/// 表访问统计系统视图名
+pub const TABLE_ACTIVITY_VIEW: &str = "information_schema.table_activity";
+
+/// 表数据版本系统视图名
+pub const TABLE_VERSIONS_VIEW: &str = "information_schema.table_versions";
+
+/// 查询改写规则系统视图名
+pub const REWRITE_RULES_VIEW: &str = "information_schema.rewrite_rules";
+
+/// 未完成的索引构建系统视图名
+pub const INDEX_BUILDS_VIEW: &str = "information_schema.index_builds";
+
+/// ANALYZE 统计系统视图名
+pub const STATISTICS_VIEW: &str = "information_schema.statistics";
+
+/// SELECT 数据源：表ID（系统视图为 None）、模式和行
+type ScanSource<'a> = (Option<u32>, std::borrow::Cow<'a, Schema>, ScanRows<'a>);
+
+/// 内存索引的位置：(表ID, 键列位置, 部分索引谓词的 SQL 文本)
+type RowIndexSlot = (u32, Vec<usize>, Option<String>);
+
+/// ASSERT 失败时附带的样本行数上限
+const ASSERT_SAMPLE_ROWS: usize = 5;
+
+/// 投影中逐行计算的列的标记（聚合列使用 usize::MAX）
+const COMPUTED_COLUMN: usize = usize::MAX - 1;
+
+/// 表的行标识：与行一一对应，删除后不复用
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct RowIds {
+    /// 与表数据同序的行标识
+    ids: Vec<u64>,
+    /// 下一个分配的行标识
+    next: u64,
+}
+
+impl RowIds {
+    /// 为已有的若干行依次分配标识（用于没有保存行标识的旧表文件）
+    #[cfg(feature = "persistence")]
+    fn for_rows(count: usize) -> Self {
+        Self {
+            ids: (1..=count as u64).collect(),
+            next: count as u64 + 1,
+        }
+    }
+
+    /// 为新追加的行分配标识并返回
+    fn push(&mut self) -> u64 {
+        self.next = self.next.max(1);
+        let id = self.next;
+        self.ids.push(id);
+        self.next += 1;
+        id
+    }
+}
+
+/// 索引元数据
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexInfo {
+    /// 索引名
+    pub name: String,
+    /// 索引键列
+    pub columns: Vec<String>,
+    /// 是否为唯一索引
+    pub unique: bool,
+    /// INCLUDE 列：值随索引保存但不属于键，只用于仅索引扫描
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    /// 部分索引的谓词：索引只包含满足谓词的行，唯一性也只在这些行之间检查
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub where_clause: Option<crate::sql::parser::Expression>,
+    /// 未完成的构建进度；构建完成前索引不生效（不检查唯一性，也不计入索引建议）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build: Option<IndexBuild>,
+}
+
+/// 索引目录中的一项：[`Database::list_indexes`] 和 `SHOW INDEXES` 的一行
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexDescription {
+    /// 所在的表
+    pub table: String,
+    /// 索引名；主键为 `{表名}_pkey`
+    pub name: String,
+    /// 索引键列
+    pub columns: Vec<String>,
+    /// 是否为唯一索引（主键也是唯一索引）
+    pub unique: bool,
+    /// 索引结构的字节数，与写入索引文件的大小相同；构建未完成时为 None
+    pub size_bytes: Option<usize>,
+    /// 索引项数；部分索引只含满足谓词的行，键中有 NULL 的行不进入索引。构建未完成时为 None
+    pub entries: Option<usize>,
+}
+
+/// 索引构建的检查点
+///
+/// 与表数据保存在同一个表文件中，因此总与行数据一致。构建期间表被修改时进度清零。
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct IndexBuild {
+    /// 已处理的行数（按表数据顺序）
+    pub rows_done: usize,
+    /// 上次检查点时表的总行数
+    pub total_rows: usize,
+    /// 唯一索引在已处理的行中见到的非 NULL 键
+    #[serde(default)]
+    keys: Vec<Vec<Value>>,
+}
+
+/// 进行中的显式事务
+struct ActiveTransaction {
+    id: TransactionId,
+    /// 事务中有语句出错：其后的语句被拒绝，COMMIT 按 ROLLBACK 处理
+    failed: bool,
+}
+
+/// 会话级临时视图：只保存在内存中，关闭数据库后消失
+struct TempView {
+    /// 已应用改写规则的 SELECT 语句，每次引用视图时重新执行
+    query: Statement,
+    /// 创建时执行查询得到的结果模式，供语义分析使用
+    schema: Schema,
 }
 
 /// 数据库元数据存储结构
+#[cfg(feature = "persistence")]
 #[derive(Serialize, Deserialize)]
 struct DatabaseMetadata {
     next_table_id: u32,
     table_catalog: HashMap<String, u32>,
+    /// 查询改写规则（旧版本的元数据中没有此项）
+    #[serde(default)]
+    rewrite_rules: Vec<RewriteRule>,
+    /// 命名查询片段（旧版本的元数据中没有此项）
+    #[serde(default)]
+    snippets: std::collections::BTreeMap<String, String>,
 }
 
 /// 主数据库实例
 pub struct Database {
-    /// 数据库目录路径
-    data_dir: PathBuf,
-    /// 数据库文件管理器
-    file_manager: FileManager,
-    /// 页面缓存的缓冲池
-    buffer_pool: BufferPool,
+    /// 持久化元数据、表快照和 WAL 的存储后端
+    #[cfg(feature = "persistence")]
+    backend: Box<dyn FileBackend>,
+    /// 预写日志
+    #[cfg(feature = "persistence")]
+    wal: Wal,
+    /// 当前工作单元中尚未写入 WAL 的记录，在提交点一次写入
+    #[cfg(feature = "persistence")]
+    wal_pending: Vec<WalRecord>,
+    /// 数据库文件管理器（内存数据库没有）
+    file_manager: Option<FileManager>,
+    /// 表工作堆文件的文件管理器，位于临时目录下（内存数据库没有）
+    work_files: Option<FileManager>,
+    /// 页面缓存的缓冲池，与后台写页线程共享
+    buffer_pool: std::sync::Arc<BufferPool>,
+    /// 周期性写回脏页的后台线程（内存数据库和未启用时没有）
+    background_writer: Option<BackgroundWriter>,
+    /// 通过 ALTER TABLE 设置了页压缩方式的表：表ID -> 压缩方式
+    table_compression: HashMap<u32, Compression>,
+    /// ANALYZE 收集的统计：表ID -> 统计，随表快照保存
+    table_statistics: HashMap<u32, TableStatistics>,
+    /// 表快照当前使用的堆文件版本：表ID -> 版本号
+    #[cfg(feature = "persistence")]
+    table_heap_generations: HashMap<u32, u64>,
     /// 表目录：表名 -> 表ID
     table_catalog: HashMap<String, u32>,
     /// 表模式：表ID -> 模式
     table_schemas: HashMap<u32, Schema>,
-    /// 表数据：表ID -> 行（简化的内存存储）
-    table_data: HashMap<u32, Vec<Tuple>>,
+    /// 表数据：表ID -> 行（有数据目录时保存在经缓冲池访问的工作堆文件中）
+    table_data: HashMap<u32, TableRows>,
+    /// 表索引：表ID -> 索引元数据
+    table_indexes: HashMap<u32, Vec<IndexInfo>>,
+    /// 内存中的索引结构：第一次用到时建立，逐行修改时随之更新，表被其他方式修改后重建
+    row_indexes: std::sync::Mutex<HashMap<RowIndexSlot, RowIndex>>,
+    /// 行标识：表ID -> 与表数据同序的 rowid
+    table_row_ids: HashMap<u32, RowIds>,
+    /// 数据版本：表ID -> 最近一次修改时的全局版本号
+    table_versions: HashMap<u32, u64>,
+    /// 全局数据版本计数器，每次表被创建或修改时递增
+    data_version: u64,
     /// 下一个可用的表ID
     next_table_id: u32,
+    /// 查询改写规则，按注册顺序应用
+    rewrite_rules: Vec<RewriteRule>,
+    /// 命名查询片段：名称 -> SQL 文本
+    snippets: std::collections::BTreeMap<String, String>,
+    /// 会话级临时视图：视图名 -> 定义
+    temp_views: HashMap<String, TempView>,
+    /// SET CONSTRAINTS ALL DEFERRED：主键和 UNIQUE 检查推迟到提交时
+    constraints_deferred: bool,
+    /// 推迟检查期间被修改、提交时需要整体检查约束的表
+    deferred_tables: std::collections::HashSet<u32>,
+    /// 当前工作单元（显式事务，或没有事务时推迟约束检查的单条语句）中被修改的表在修改前的行：
+    /// 表ID -> (行, 行标识)，回滚时据此恢复
+    before_images: HashMap<u32, (Vec<Tuple>, RowIds)>,
+    /// 事务管理器：分配事务ID并跟踪事务状态
+    transactions: TransactionManager,
+    /// 进行中的显式事务
+    transaction: Option<ActiveTransaction>,
+    /// 长时间操作的中断标志
+    interrupt: std::sync::Arc<std::sync::atomic::AtomicBool>,
     /// 错误诊断引擎
     diagnostic_engine: DiagnosticEngine,
     /// 查询优化器
     optimizer: QueryOptimizer,
+    /// 排序/哈希溢出使用的临时文件管理器（内存数据库没有）
+    temp_files: Option<TempFileManager>,
+    /// 打开时使用的配置
+    config: DatabaseConfig,
+    /// RANDOM() 使用的随机数源
+    random: RandomSource,
+    /// 查询负载统计
+    workload: WorkloadTracker,
+    /// 只读查询的结果缓存
+    result_cache: ResultCache,
+    /// 上次是否正常关闭（打开时存在正常关闭标记）
+    clean_shutdown: bool,
+    /// 已调用 close，不再接受语句
+    closed: bool,
+}
+
+/// SQL 预检结果
+#[derive(Debug, Clone)]
+pub struct ValidationResult {
+    /// 解析后的语句
+    pub statement: Statement,
+    /// 推断的结果模式（不产生结果集的语句为 None）
+    pub result_schema: Option<Schema>,
+}
+
+/// SQL 编译各阶段的中间产物
+#[derive(Debug, Clone)]
+pub struct CompiledQuery {
+    /// 词法分析得到的标记（不含结尾的 EOF）
+    pub tokens: Vec<TokenInfo>,
+    /// 语法分析得到的抽象语法树
+    pub ast: Statement,
+    /// 针对当前目录完成语义分析的语句
+    pub analyzed: AnalyzedStatement,
+    /// 优化后的执行计划（规划器尚不支持的语句为 None）
+    pub plan: Option<OptimizedPlan>,
 }
 
 /// 查询执行结果
@@ -60,12 +353,84 @@ pub struct QueryResult {
     pub message: String,
 }
 
+impl QueryResult {
+    /// 结果列的驱动元数据（不产生结果集的语句为空）
+    pub fn column_metadata(&self) -> Vec<ColumnMetadata> {
+        self.schema.as_ref().map(Schema::column_metadata).unwrap_or_default()
+    }
+    
+    /// 把结果集写成 CSV，返回写出的行数；行逐条写入 writer
+    pub fn write_csv<W: std::io::Write>(&self, writer: &mut W, options: &CsvOptions) -> std::io::Result<usize> {
+        super::export::write_csv(writer, self.schema.as_ref(), &self.rows, options)
+    }
+    
+    /// 把结果集转换为 Arrow RecordBatch
+    #[cfg(feature = "arrow")]
+    pub fn to_record_batch(&self) -> Result<arrow::record_batch::RecordBatch, ExecutionError> {
+        super::columnar::record_batch(self.result_schema()?, &self.rows)
+    }
+    
+    /// 把结果集写成 Parquet 文件，返回写出的行数
+    #[cfg(feature = "arrow")]
+    pub fn write_parquet<W: std::io::Write + Send>(&self, writer: W) -> Result<usize, ExecutionError> {
+        super::columnar::write_parquet(writer, self.result_schema()?, &self.rows)
+    }
+    
+    #[cfg(feature = "arrow")]
+    fn result_schema(&self) -> Result<&Schema, ExecutionError> {
+        self.schema.as_ref().ok_or_else(|| ExecutionError::SemanticError(
+            format!("Statement did not return a result set: {}", self.message)
+        ))
+    }
+}
+
+/// 逐行产生的查询结果，由 [`Database::query_iter`] 返回
+///
+/// 每次迭代才从执行器拉取下一行；提前丢弃时剩余的行不会被计算。出错后迭代结束。
+pub struct Rows<'a> {
+    root: Box<dyn Executor + 'a>,
+    done: bool,
+}
+
+impl Rows<'_> {
+    /// 结果列
+    ///
+    /// 函数调用等计算列的类型取第一个非 NULL 结果的类型，读到这样的行之前为 VARCHAR。
+    pub fn schema(&self) -> &Schema {
+        self.root.schema()
+    }
+}
+
+impl Iterator for Rows<'_> {
+    type Item = Result<Tuple, ExecutionError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.root.next() {
+            Ok(Some(row)) => Some(Ok(row)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e.into()))
+            }
+        }
+    }
+}
+
 /// 数据库执行错误
 #[derive(Error, Debug)]
 pub enum ExecutionError {
     #[error("SQL 解析错误: {0}")]
     ParseError(String),
     
+    #[error("语义错误: {0}")]
+    SemanticError(String),
+    
     #[error("存储错误: {0}")]
     StorageError(String),
     
@@ -84,1687 +449,5814 @@ pub enum ExecutionError {
     #[error("Primary key constraint violation: duplicate key value {key}")]
     PrimaryKeyViolation { key: String },
     
-    #[error("Not implemented: {feature}")]
-    NotImplemented { feature: String },
+    #[error("UNIQUE constraint violation: duplicate key value {key} violates '{index}'")]
+    UniqueViolation { index: String, key: String },
+    
+    
+    #[error("NOT NULL constraint violation: column '{column}' cannot be NULL")]
+    NotNullViolation { column: String },
+    
+    #[error("Not implemented: {feature} (capability '{capability}')")]
+    NotImplemented { feature: String, capability: Capability },
     
     #[error("Evaluation error: {message}")]
     EvaluationError { message: String },
+    
+    #[error("Statement expects {expected} parameter(s), got {actual}")]
+    ParameterCountMismatch { expected: usize, actual: usize },
+    
+    #[error("Interrupted while {operation}; progress has been saved, run the statement again to resume")]
+    Interrupted { operation: String },
+    
+    #[error("Safe updates mode rejects {statement} without WHERE on table '{table}'; add a WHERE clause or write {confirm} {table}")]
+    UnsafeUpdate { statement: String, confirm: String, table: String },
+    
+    #[error("Disk quota exceeded: the data directory uses {usage} bytes, the limit is {limit} bytes")]
+    DiskQuotaExceeded { usage: u64, limit: u64 },
+    
+    #[error("Current transaction is aborted, statements are ignored until ROLLBACK")]
+    TransactionAborted,
+    
+    #[error("Database is closed")]
+    DatabaseClosed,
+    
+    #[error("Encryption error: {0}")]
+    Encryption(#[from] EncryptionError),
+    
+    #[error("Assertion failed: {message} (got {actual}){}", format_samples(.samples))]
+    AssertionFailed { message: String, actual: String, samples: Vec<Tuple> },
+    
+    #[error(transparent)]
+    Transaction(#[from] TransactionError),
 }
 
 impl Database {
     /// 创建一个新的数据库实例
+    #[cfg(feature = "persistence")]
     pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, ExecutionError> {
-        let data_dir = path.as_ref().to_path_buf();
+        Self::with_config(path, DatabaseConfig::default())
+    }
+    
+    /// 使用指定配置创建数据库实例
+    #[cfg(feature = "persistence")]
+    pub fn with_config<P: AsRef<Path>>(path: P, config: DatabaseConfig) -> Result<Self, ExecutionError> {
+        let backend = DirectoryBackend::new(path)
+            .map_err(|e| ExecutionError::StorageError(format!("Failed to create database directory: {}", e)))?;
+        Self::with_backend(Box::new(backend), config)
+    }
+    
+    /// 打开以给定密钥加密的数据库；新目录会用这个密钥加密，已有目录会先校验密钥
+    #[cfg(feature = "persistence")]
+    pub fn with_encryption_key<P: AsRef<Path>>(path: P, key: EncryptionKey) -> Result<Self, ExecutionError> {
+        Self::with_config(path, DatabaseConfig::default().with_encryption_key(key))
+    }
+    
+    /// 创建完全在内存中的数据库，不访问文件系统
+    ///
+    /// 适用于测试和没有文件系统的环境（如 wasm32-unknown-unknown）。
+    pub fn in_memory() -> Result<Self, ExecutionError> {
+        let config = DatabaseConfig::default();
+        #[cfg(feature = "persistence")]
+        let database = Self::with_backend(Box::new(MemoryBackend::new()), config)?;
+        #[cfg(not(feature = "persistence"))]
+        let database = Self::from_parts(None, None, None, config);
+        Ok(database)
+    }
+    
+    /// 使用指定的存储后端创建数据库实例，并加载后端中已有的表
+    ///
+    /// 页文件和临时空间只在后端位于文件系统目录时可用。
+    /// 配置了加密密钥时先校验密钥，密钥错误、加密的数据缺少密钥都返回 [`ExecutionError::Encryption`]。
+    #[cfg(feature = "persistence")]
+    pub fn with_backend(backend: Box<dyn FileBackend>, config: DatabaseConfig) -> Result<Self, ExecutionError> {
+        let backend: Box<dyn FileBackend> = match &config.encryption_key {
+            Some(key) => Box::new(EncryptedBackend::open(backend, key.clone())?),
+            None if EncryptedBackend::is_encrypted(backend.as_ref())
+                .map_err(|e| ExecutionError::StorageError(format!("Failed to read storage backend: {}", e)))? =>
+            {
+                return Err(EncryptionError::KeyRequired.into());
+            }
+            None => backend,
+        };
+        let (file_manager, work_files, temp_files) = match backend.directory() {
+            Some(data_dir) => {
+                let file_manager = Self::new_file_manager(data_dir, &config)
+                    .map_err(|e| ExecutionError::StorageError(format!("Failed to initialize file manager: {}", e)))?;
+                // Initialize temp space (removes leftovers from a previous crash)
+                let temp_files = TempFileManager::new(data_dir.join(TEMP_DIR_NAME), config.temp_quota_bytes)
+                    .map_err(|e| ExecutionError::StorageError(format!("Failed to initialize temp space: {}", e)))?;
+                // 工作堆文件在加载表时从快照重建，上次运行留下的文件没有用处
+                let work_dir = data_dir.join(TEMP_DIR_NAME).join(TABLE_WORK_DIR);
+                if work_dir.exists() {
+                    std::fs::remove_dir_all(&work_dir)
+                        .map_err(|e| ExecutionError::StorageError(format!("Failed to clear table work files: {}", e)))?;
+                }
+                let work_files = Self::new_file_manager(&work_dir, &config)
+                    .map_err(|e| ExecutionError::StorageError(format!("Failed to initialize table work files: {}", e)))?;
+                (Some(file_manager), Some(work_files), Some(temp_files))
+            }
+            None => (None, None, None),
+        };
+        
+        let mut database = Self::from_parts(backend, file_manager, work_files, temp_files, config);
+        
+        // Load existing data if available
+        if let Err(e) = database.load_existing_tables() {
+            println!("Warning: Failed to load existing tables: {}", e);
+        }
         
-        // Ensure database directory exists
-        if !data_dir.exists() {
-            std::fs::create_dir_all(&data_dir)
-                .map_err(|e| ExecutionError::StorageError(format!("Failed to create database directory: {}", e)))?;
+        // 从当前的 LSN 开始归档之后提交的批次
+        if let Some(archive_dir) = &database.config.wal_archive_dir {
+            let archive = backup::open_archive(archive_dir, database.config.encryption_key.as_ref())?;
+            database.wal.set_archive(archive);
         }
         
-        // Initialize file manager
-        let file_manager = FileManager::new(data_dir.clone())
-            .map_err(|e| ExecutionError::StorageError(format!("Failed to initialize file manager: {}", e)))?;
+        if let Some(interval) = database.config.background_flush_interval {
+            database.start_background_writer(interval);
+        }
         
-        // Initialize buffer pool with 128 pages
-        let buffer_pool = BufferPool::new(128);
+        Ok(database)
+    }
+
+    /// 按配置的读写方式和加密密钥创建页文件管理器
+    #[cfg(feature = "persistence")]
+    fn new_file_manager(dir: &Path, config: &DatabaseConfig) -> Result<FileManager, FileError> {
+        let file_manager = FileManager::with_io(dir, config.file_io)?;
+        match &config.encryption_key {
+            Some(key) => file_manager.with_encryption(key.clone()),
+            None => Ok(file_manager),
+        }
+    }
+
+    /// 组装一个空目录的数据库实例
+    fn from_parts(
+        #[cfg(feature = "persistence")] backend: Box<dyn FileBackend>,
+        file_manager: Option<FileManager>,
+        work_files: Option<FileManager>,
+        temp_files: Option<TempFileManager>,
+        config: DatabaseConfig,
+    ) -> Self {
+        // Initialize buffer pool
+        let buffer_pool = std::sync::Arc::new(BufferPool::with_policy(config.buffer_pool_size, config.buffer_policy));
         
-        let mut database = Self {
-            data_dir,
+        Self {
+            #[cfg(feature = "persistence")]
+            backend,
+            #[cfg(feature = "persistence")]
+            wal: Wal::default(),
+            #[cfg(feature = "persistence")]
+            wal_pending: Vec::new(),
             file_manager,
+            work_files,
             buffer_pool,
+            background_writer: None,
+            table_compression: HashMap::new(),
+            table_statistics: HashMap::new(),
+            #[cfg(feature = "persistence")]
+            table_heap_generations: HashMap::new(),
             table_catalog: HashMap::new(),
             table_schemas: HashMap::new(),
             table_data: HashMap::new(),
+            table_indexes: HashMap::new(),
+            row_indexes: std::sync::Mutex::new(HashMap::new()),
+            table_row_ids: HashMap::new(),
+            table_versions: HashMap::new(),
+            data_version: 0,
             next_table_id: 1,
+            rewrite_rules: Vec::new(),
+            snippets: std::collections::BTreeMap::new(),
+            temp_views: HashMap::new(),
+            constraints_deferred: false,
+            deferred_tables: std::collections::HashSet::new(),
+            before_images: HashMap::new(),
+            transactions: TransactionManager::with_lock_timeout(config.lock_timeout),
+            transaction: None,
+            interrupt: std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false)),
             diagnostic_engine: DiagnosticEngine::new(),
             optimizer: QueryOptimizer::new(),
-        };
-        
-        // Load existing data if available
-        if let Err(e) = database.load_existing_tables() {
-            println!("Warning: Failed to load existing tables: {}", e);
+            temp_files,
+            random: RandomSource::new(config.random_seed),
+            workload: WorkloadTracker::new(),
+            result_cache: ResultCache::new(config.result_cache_entries),
+            config,
+            clean_shutdown: false,
+            closed: false,
         }
+    }
+
+    /// 解析 SQL 并应用改写规则，出错时附带诊断建议
+    fn parse_with_diagnostics(&self, sql: &str) -> Result<Statement, ExecutionError> {
+        let mut statement = parse_sql(sql).map_err(|e| self.diagnose_parse_error(e))?;
+        apply_rewrite_rules(&mut statement, &self.rewrite_rules);
+        Ok(statement)
+    }
+    
+    /// 为解析错误附加诊断建议
+    fn diagnose_parse_error(&self, error: crate::sql::ParseError) -> ExecutionError {
+        let context = DiagnosticContext::new(
+            self.table_catalog.keys().cloned().collect(),
+            self.get_all_column_names(),
+        );
+        let suggestions = self.diagnostic_engine.diagnose(&error.to_string(), Some(&context));
+        let enhanced_error = self.diagnostic_engine.format_enhanced_error(
+            &error.to_string(),
+            &suggestions
+        );
+        ExecutionError::ParseError(enhanced_error)
+    }
+    
+    /// 预编译 SQL 语句：解析并分析一次，推断参数类型和结果模式
+    pub fn prepare(&self, sql: &str) -> Result<PreparedStatement, ExecutionError> {
+        let mut parser = crate::sql::Parser::new(crate::sql::Lexer::new(sql))
+            .map_err(|e| self.diagnose_parse_error(e))?;
+        let mut statement = parser.parse_complete_statement()
+            .map_err(|e| self.diagnose_parse_error(e))?;
+        apply_rewrite_rules(&mut statement, &self.rewrite_rules);
         
-        Ok(database)
+        let analyzed = crate::sql::analyze_statement(statement, self)
+            .map_err(|e| ExecutionError::SemanticError(e.to_string()))?;
+        
+        let parameter_types = (1..=parser.parameter_count())
+            .map(|index| analyzed.parameter_type(index).cloned())
+            .collect();
+        
+        Ok(PreparedStatement::new(sql, analyzed.statement, parameter_types, analyzed.result_schema))
+    }
+    
+    /// 描述 SQL 语句的参数和结果列而不执行，供构建时校验查询的驱动和 ORM 使用
+    pub fn describe(&self, sql: &str) -> Result<QueryDescription, ExecutionError> {
+        Ok(self.prepare(sql)?.describe())
+    }
+    
+    /// 预检 SQL 语句：执行词法、语法和语义分析但不执行，返回推断的结果模式
+    pub fn validate(&self, sql: &str) -> Result<ValidationResult, ExecutionError> {
+        let statement = self.parse_with_diagnostics(sql)?;
+        let analyzed = crate::sql::analyze_statement(statement, self)
+            .map_err(|e| ExecutionError::SemanticError(e.to_string()))?;
+        
+        Ok(ValidationResult {
+            statement: analyzed.statement,
+            result_schema: analyzed.result_schema,
+        })
     }
 
+    /// 编译 SQL 语句但不执行，返回词法、语法、语义分析和优化各阶段的结果
+    ///
+    /// 供教学和调试工具逐阶段展示编译过程；任一阶段出错时返回该阶段的错误。
+    pub fn compile(&self, sql: &str) -> Result<CompiledQuery, ExecutionError> {
+        let mut lexer = Lexer::new(sql);
+        let mut tokens = Vec::new();
+        loop {
+            let info = lexer.next_token_info()
+                .map_err(|e| self.diagnose_parse_error(e.into()))?;
+            if info.token == Token::EOF {
+                break;
+            }
+            tokens.push(info);
+        }
+        
+        let ast = self.parse_with_diagnostics(sql)?;
+        let analyzed = crate::sql::analyze_statement(ast.clone(), self)
+            .map_err(|e| ExecutionError::SemanticError(e.to_string()))?;
+        
+        let plan = match crate::sql::create_plan(analyzed.clone()) {
+            Ok(plan) => Some(self.optimizer.optimize_with_catalog(plan, self)
+                .map_err(|e| ExecutionError::SemanticError(e.to_string()))?),
+            Err(PlanError::UnsupportedOperation { .. }) => None,
+            Err(e) => return Err(ExecutionError::SemanticError(e.to_string())),
+        };
+        
+        Ok(CompiledQuery { tokens, ast, analyzed, plan })
+    }
+    
     /// 执行 SQL 语句
+    ///
+    /// 可以是以分号分隔的多条语句：按顺序执行，遇到错误即停止，返回最后一条语句的结果。
+    /// 需要每条语句的结果时使用 [`Database::execute_script`]。
     pub fn execute(&mut self, sql: &str) -> Result<QueryResult, ExecutionError> {
-        // Step 1: Parse SQL with enhanced error diagnostics
-        let statement = parse_sql(sql)
-            .map_err(|e| {
-                let context = DiagnosticContext::new(
-                    self.table_catalog.keys().cloned().collect(),
-                    self.get_all_column_names(),
-                );
-                let suggestions = self.diagnostic_engine.diagnose(&e.to_string(), Some(&context));
-                let enhanced_error = self.diagnostic_engine.format_enhanced_error(
-                    &e.to_string(),
-                    &suggestions
-                );
-                ExecutionError::ParseError(enhanced_error)
-            })?;
+        let statements = crate::sql::split_statements(sql);
+        match statements.split_last() {
+            Some((last, leading)) if !leading.is_empty() => {
+                for statement in leading {
+                    self.execute_single(statement)?;
+                }
+                self.execute_single(last)
+            }
+            // 单条语句保留原文（含注释），错误位置与输入一致
+            _ => self.execute_single(sql),
+        }
+    }
+    
+    /// 执行一条 SELECT，逐行返回结果而不先把全部行放进内存
+    ///
+    /// 返回的 [`Rows`] 借用数据库，迭代期间不能修改数据库；排序和聚合仍要先读完各自的输入。
+    /// 结果不经过结果缓存，也不计入负载统计。其他语句请使用 [`Database::execute`]。
+    pub fn query_iter(&self, sql: &str) -> Result<Rows<'_>, ExecutionError> {
+        self.ensure_open()?;
+        if self.transaction.as_ref().is_some_and(|transaction| transaction.failed) {
+            return Err(ExecutionError::TransactionAborted);
+        }
+        match self.parse_with_diagnostics(sql)? {
+            query @ Statement::Select { .. } => Ok(Rows { root: self.select_plan(query)?.root, done: false }),
+            _ => Err(ExecutionError::SemanticError(
+                "query_iter only runs SELECT statements; use execute for other statements".to_string(),
+            )),
+        }
+    }
+    
+    /// 执行以分号分隔的多条语句，按顺序返回每条语句的结果
+    ///
+    /// stop_on_error 为 true 时在第一条出错的语句后停止，结果的最后一项即为该错误；
+    /// 为 false 时继续执行后续语句。只含空白和注释的脚本返回空列表。
+    pub fn execute_script(&mut self, sql: &str, stop_on_error: bool) -> Vec<Result<QueryResult, ExecutionError>> {
+        let mut results = Vec::new();
+        for statement in crate::sql::split_statements(sql) {
+            let result = self.execute_single(&statement);
+            let failed = result.is_err();
+            results.push(result);
+            if failed && stop_on_error {
+                break;
+            }
+        }
+        results
+    }
+    
+    /// 执行单条语句，开启负载统计时记录其耗时
+    fn execute_single(&mut self, sql: &str) -> Result<QueryResult, ExecutionError> {
+        self.execute_tracked(sql, |db| db.parse_with_diagnostics(sql))
+    }
+    
+    /// 以给定参数执行预编译语句
+    ///
+    /// 参数按位置绑定（下标 0 对应 `$1` 或第一个 `?`），语句不会被重新解析；
+    /// 参数个数必须与语句中的占位符个数一致，能推断出类型的参数会先转换为该类型。
+    /// 值直接代入语法树，不经过 SQL 文本，因此不存在注入问题。
+    pub fn execute_prepared(&mut self, statement: &PreparedStatement, params: &[Value]) -> Result<QueryResult, ExecutionError> {
+        self.execute_tracked(statement.sql(), |_| statement.bind(params))
+    }
+    
+    /// 取得语句并执行；开启负载统计时按 SQL 文本的指纹记录耗时（包括取得语句的时间）
+    fn execute_tracked(
+        &mut self,
+        sql: &str,
+        statement: impl FnOnce(&Self) -> Result<Statement, ExecutionError>,
+    ) -> Result<QueryResult, ExecutionError> {
+        self.ensure_open()?;
+        if !self.config.track_workload {
+            let statement = statement(self)?;
+            return self.execute_statement(statement);
+        }
         
-        // Step 2: Execute based on statement type
-        match statement {
-            Statement::CreateTable { table_name, columns, constraints: _ } => {
-                self.execute_create_table_simple(table_name, columns)
+        let stopwatch = Stopwatch::start();
+        let (tables, result) = match statement(self) {
+            Ok(statement) => (self.table_accesses(&statement), self.execute_statement(statement)),
+            Err(e) => (Vec::new(), Err(e)),
+        };
+        let rows = result.as_ref().ok().map(|r| r.affected_rows.max(r.rows.len()) as u64);
+        self.workload.record(sql, &tables, stopwatch.elapsed_micros(), rows);
+        result
+    }
+    
+    /// 语句读写的用户表（系统视图和临时视图不计入）
+    fn table_accesses(&self, statement: &Statement) -> Vec<TableAccess> {
+        let (names, write) = match statement {
+            Statement::Select { from_clause: Some(from), .. } => {
+                let mut names = Vec::new();
+                Self::from_tables(from, &mut names);
+                (names, false)
             }
-            Statement::DropTable { table_name, if_exists: _ } => {
-                self.execute_drop_table_simple(table_name)
+            Statement::Insert { table_name, .. }
+            | Statement::Update { table_name, .. }
+            | Statement::Delete { table_name, .. }
+            | Statement::Copy { table_name, .. } => (vec![table_name.clone()], true),
+            // EXPLAIN ANALYZE 实际执行语句
+            Statement::Explain { statement, analyze: true } => return self.table_accesses(statement),
+            Statement::Assert { query, .. } | Statement::CopyTo { query, .. } => return self.table_accesses(query),
+            // DRY RUN 只扫描不修改
+            Statement::DryRun { statement } => {
+                return self.table_accesses(statement).into_iter()
+                    .map(|access| TableAccess { write: false, ..access })
+                    .collect();
+            }
+            _ => return Vec::new(),
+        };
+        names.into_iter()
+            .filter(|name| Self::system_view_schema(name).is_none() && !self.temp_views.contains_key(name))
+            .map(|table| TableAccess { table, write })
+            .collect()
+    }
+    
+    /// FROM 子句中按书写顺序出现的表名
+    fn from_tables(from: &crate::sql::parser::FromClause, out: &mut Vec<String>) {
+        match from {
+            crate::sql::parser::FromClause::Table(name) => out.push(name.clone()),
+            crate::sql::parser::FromClause::Join { left, right, .. } => {
+                Self::from_tables(left, out);
+                Self::from_tables(right, out);
             }
-            Statement::Insert { table_name, columns: _, values } => {
-                self.execute_insert_simple(table_name, values)
+        }
+    }
+    
+    /// 结果可以缓存的查询读取的表；None 表示不缓存
+    ///
+    /// 只缓存显式事务之外的 SELECT（失败的事务必须拒绝查询），且只读取用户表、不含易变函数；
+    /// 系统视图和临时视图每次重新计算。
+    fn cacheable_tables(&self, statement: &Statement) -> Option<Vec<String>> {
+        if !self.result_cache.is_enabled() || self.transaction.is_some() {
+            return None;
+        }
+        let Statement::Select { from_clause: Some(from), .. } = statement else {
+            return None;
+        };
+        let mut tables = Vec::new();
+        Self::from_tables(from, &mut tables);
+        if !tables.iter().all(|table| self.table_catalog.contains_key(table) && !self.temp_views.contains_key(table)) {
+            return None;
+        }
+        let mut volatile = false;
+        statement.clone().visit_expressions_mut(&mut |expr| {
+            if let crate::sql::parser::Expression::FunctionCall { name, .. } = expr {
+                volatile |= functions::is_volatile(name);
             }
-            Statement::Select { select_list, from_clause, where_clause, group_by, having, order_by, limit, offset } => {
-                self.execute_select_complete(select_list, from_clause, where_clause, group_by, having, order_by, limit, offset)
+        });
+        (!volatile).then_some(tables)
+    }
+    
+    /// 执行已解析的语句；修改了行的 DML 递增该表的数据版本，开启结果缓存时只读查询先查缓存
+    fn execute_statement(&mut self, statement: Statement) -> Result<QueryResult, ExecutionError> {
+        fn modified_table(statement: &Statement) -> Option<String> {
+            match statement {
+                Statement::Insert { table_name, .. }
+                | Statement::Update { table_name, .. }
+                | Statement::Delete { table_name, .. }
+                | Statement::Deduplicate { table_name }
+                | Statement::Copy { table_name, .. } => Some(table_name.clone()),
+                Statement::Explain { statement, analyze: true } => modified_table(statement),
+                _ => None,
             }
-            Statement::Update { table_name, assignments, where_clause } => {
-                self.execute_update_simple(table_name, assignments, where_clause)
+        }
+        let cache_entry = self.cacheable_tables(&statement).map(|tables| (format!("{:?}", statement), tables));
+        if let Some((key, _)) = &cache_entry {
+            if let Some(result) = self.result_cache.get(key) {
+                return Ok(result);
             }
-            Statement::Delete { table_name, where_clause } => {
-                self.execute_delete_simple(table_name, where_clause)
+        }
+        let modified_table = modified_table(&statement);
+        let is_control = matches!(statement, Statement::Begin | Statement::Commit | Statement::Rollback);
+        
+        let result = self.prepare_in_transaction(&statement, modified_table.as_deref()).and_then(|()| {
+            if let Some(table_name) = &modified_table {
+                self.restart_index_builds(table_name);
             }
-            Statement::CreateIndex { index_name, table_name, columns, is_unique } => {
-                self.execute_create_index(index_name, table_name, columns, is_unique)
+            self.dispatch_statement(statement)
+        });
+        let result = match &mut self.transaction {
+            // 显式事务中推迟的检查在 COMMIT 时执行；任何语句出错都使整个事务失败
+            Some(transaction) => {
+                if result.is_err() && !is_control {
+                    transaction.failed = true;
+                }
+                result?
             }
-            Statement::DropIndex { index_name, table_name, if_exists: _ } => {
-                self.execute_drop_index(index_name, table_name)
+            // 没有显式事务时每条语句结束即为提交点：语句的修改在此写入 WAL
+            None => {
+                let result = self.commit_deferred_checks(result);
+                self.flush_wal()?;
+                self.checkpoint_if_wal_full()?;
+                result?
             }
-            Statement::Explain { statement } => {
-                self.execute_explain(*statement)
+        };
+        if let Some(table_name) = modified_table {
+            if result.affected_rows > 0 {
+                self.bump_data_version(&table_name);
             }
         }
+        if let Some((key, tables)) = cache_entry {
+            self.result_cache.insert(key, tables, result.clone());
+        }
+        Ok(result)
     }
     
-    /// 执行 CREATE TABLE 语句（简化版本）
-    fn execute_create_table_simple(&mut self, name: String, columns: Vec<crate::sql::parser::ColumnDef>) -> Result<QueryResult, ExecutionError> {
-        // Check if table already exists
-        if self.table_catalog.contains_key(&name) {
-            return Err(ExecutionError::TableAlreadyExists { table: name });
+    /// 在提交点执行推迟的约束检查；语句出错或检查失败时把被修改的表恢复到修改前
+    fn commit_deferred_checks(&mut self, result: Result<QueryResult, ExecutionError>) -> Result<QueryResult, ExecutionError> {
+        if self.before_images.is_empty() {
+            return result;
         }
-        
-        // Convert column definitions to schema and extract primary key info
-        let mut schema_columns = Vec::new();
-        let mut primary_key_columns = Vec::new();
-        
-        for (i, col_def) in columns.iter().enumerate() {
-            let column = crate::types::ColumnDefinition {
-                name: col_def.name.clone(),
-                data_type: col_def.data_type.clone(),
-                nullable: col_def.nullable,
-                default: None, // Simplified for now
-            };
-            schema_columns.push(column);
-            
-            // Check for column-level primary key
-            if col_def.primary_key {
-                primary_key_columns.push(i);
+        let result = result.and_then(|result| self.check_deferred_constraints().map(|()| result));
+        self.deferred_tables.clear();
+        if result.is_err() {
+            for (table_id, _) in self.restore_before_images() {
+                self.discard_wal_records(table_id);
             }
         }
-        
-        let primary_key = if primary_key_columns.is_empty() {
-            None
-        } else {
-            Some(primary_key_columns)
-        };
-        
-        let schema = Schema {
-            columns: schema_columns,
-            primary_key,
+        self.before_images.clear();
+        result
+    }
+    
+    /// 把保存了修改前行的表恢复原状，返回被恢复的 (表ID, 表名)
+    fn restore_before_images(&mut self) -> Vec<(u32, String)> {
+        let mut restored = Vec::new();
+        for (table_id, (rows, row_ids)) in std::mem::take(&mut self.before_images) {
+            if let Some(table_rows) = self.table_data.get_mut(&table_id) {
+                if let Err(e) = table_rows.replace(&self.buffer_pool, rows) {
+                    log::error!("Failed to restore table {}: {}", table_id, e);
+                }
+            }
+            self.table_row_ids.insert(table_id, row_ids);
+            if let Some((table_name, _)) = self.table_catalog.iter().find(|(_, &id)| id == table_id) {
+                restored.push((table_id, table_name.clone()));
+            }
+        }
+        restored
+    }
+    
+    /// 在当前工作单元中第一次修改表之前保存其行
+    fn save_before_image(&mut self, table_id: u32) {
+        if !self.before_images.contains_key(&table_id) {
+            let rows = self.table_rows(table_id).map(std::borrow::Cow::into_owned).unwrap_or_default();
+            let row_ids = self.table_row_ids.get(&table_id).cloned().unwrap_or_default();
+            self.before_images.insert(table_id, (rows, row_ids));
+        }
+    }
+    
+    /// 显式事务中执行语句前的检查：失败的事务只接受 COMMIT / ROLLBACK，DDL 不能在事务中执行；
+    /// 通过时为将被修改的表保存修改前的行
+    fn prepare_in_transaction(&mut self, statement: &Statement, modified_table: Option<&str>) -> Result<(), ExecutionError> {
+        let Some(transaction) = &self.transaction else {
+            return Ok(());
         };
-        
-        // Assign new table ID
-        let table_id = self.next_table_id;
-        self.next_table_id += 1;
-        
-        // Create table file
-        let table_file_name = format!("table_{}.db", table_id);
-        self.file_manager.create_file(&table_file_name)
-            .map_err(|e| ExecutionError::StorageError(format!("Failed to create table file: {}", e)))?;
-        
-        // Register table
-        self.table_catalog.insert(name.clone(), table_id);
-        self.table_schemas.insert(table_id, schema);
-        self.table_data.insert(table_id, Vec::new()); // Initialize empty data storage
-        
-        // Save table data and metadata
-        if let Err(e) = self.save_table(table_id, &name) {
-            println!("Warning: Failed to save table data: {}", e);
+        match statement {
+            Statement::Begin | Statement::Commit | Statement::Rollback => return Ok(()),
+            _ if transaction.failed => return Err(ExecutionError::TransactionAborted),
+            Statement::CreateTable { .. }
+            | Statement::DropTable { .. }
+            | Statement::CreateIndex { .. }
+            | Statement::DropIndex { .. }
+            | Statement::Reindex { .. }
+            | Statement::Analyze { .. }
+            | Statement::AlterTable { .. }
+            | Statement::CreateView { .. }
+            | Statement::DropView { .. } => {
+                return Err(ExecutionError::SemanticError(
+                    "DDL statements cannot run inside a transaction; COMMIT or ROLLBACK first".to_string(),
+                ))
+            }
+            _ => {}
         }
-        if let Err(e) = self.save_metadata() {
-            println!("Warning: Failed to save metadata: {}", e);
+        if let Some(table_name) = modified_table {
+            match self.table_catalog.get(table_name) {
+                Some(&table_id) => {
+                    // 插入只需意向锁，新行对其他事务不可见；DEDUPLICATE 可能删除任意行，锁住整张表
+                    let lock_type = match statement {
+                        Statement::Deduplicate { .. } => LockType::ExclusiveWrite,
+                        _ => LockType::IntentionExclusive,
+                    };
+                    self.transactions.lock_table(transaction.id, table_name, lock_type)?;
+                    self.save_before_image(table_id);
+                }
+                // COPY ... INFER 会建表
+                None if matches!(statement, Statement::Copy { .. }) => {
+                    return Err(ExecutionError::SemanticError(format!(
+                        "COPY cannot create table '{}' inside a transaction", table_name
+                    )))
+                }
+                None => {}
+            }
         }
-        
-        Ok(QueryResult {
-            rows: vec![],
-            schema: None,
-            affected_rows: 0,
-            message: format!("Table '{}' created successfully", name),
-        })
+        Ok(())
     }
     
-    /// 执行 DROP TABLE 语句（简化版本）
-    fn execute_drop_table_simple(&mut self, name: String) -> Result<QueryResult, ExecutionError> {
-        // Check if table exists
-        let table_id = self.table_catalog.get(&name)
-            .ok_or_else(|| ExecutionError::TableNotFound { table: name.clone() })?;
+    /// BEGIN：开启显式事务；此后的修改在 COMMIT 时才写入存储
+    fn begin_transaction(&mut self) -> Result<QueryResult, ExecutionError> {
+        if self.transaction.is_some() {
+            return Ok(Self::message_result("Already in a transaction".to_string()));
+        }
+        let id = self.transactions.begin_transaction()?;
+        self.transaction = Some(ActiveTransaction { id, failed: false });
+        Ok(Self::message_result("BEGIN".to_string()))
+    }
+    
+    /// COMMIT：检查推迟的约束，然后把事务中的修改写入 WAL；检查失败或事务已失败时回滚
+    fn commit_transaction(&mut self) -> Result<QueryResult, ExecutionError> {
+        let Some(transaction) = self.transaction.take() else {
+            return Ok(Self::message_result("No transaction in progress".to_string()));
+        };
+        if transaction.failed {
+            self.discard_transaction(transaction.id)?;
+            return Ok(Self::message_result("ROLLBACK (the transaction had failed)".to_string()));
+        }
+        if let Err(e) = self.check_deferred_constraints() {
+            self.discard_transaction(transaction.id)?;
+            return Err(e);
+        }
         
-        let table_id = *table_id;
+        self.deferred_tables.clear();
+        self.before_images.clear();
+        self.flush_wal()?;
+        self.transactions.commit_transaction(transaction.id)?;
+        self.checkpoint_if_wal_full()?;
+        Ok(Self::message_result("COMMIT".to_string()))
+    }
+    
+    /// ROLLBACK：丢弃事务中的全部修改
+    fn rollback_transaction(&mut self) -> Result<QueryResult, ExecutionError> {
+        let Some(transaction) = self.transaction.take() else {
+            return Ok(Self::message_result("No transaction in progress".to_string()));
+        };
+        self.discard_transaction(transaction.id)?;
+        Ok(Self::message_result("ROLLBACK".to_string()))
+    }
+    
+    /// 恢复事务修改过的表；这些表获得新的数据版本，事务中读到的版本随之失效
+    fn discard_transaction(&mut self, id: TransactionId) -> Result<(), ExecutionError> {
+        self.deferred_tables.clear();
+        for (table_id, table_name) in self.restore_before_images() {
+            self.discard_wal_records(table_id);
+            self.bump_data_version(&table_name);
+        }
+        self.transactions.rollback_transaction(id)?;
+        Ok(())
+    }
+    
+    /// 只带消息的结果
+    fn message_result(message: String) -> QueryResult {
+        QueryResult {
+            rows: vec![],
+            schema: None,
+            affected_rows: 0,
+            message,
+        }
+    }
+    
+    /// 对推迟检查期间修改过的表整体检查主键和 UNIQUE 约束
+    fn check_deferred_constraints(&self) -> Result<(), ExecutionError> {
+        for &table_id in &self.deferred_tables {
+            let Some(schema) = self.table_schemas.get(&table_id) else {
+                continue;
+            };
+            let rows = self.table_rows(table_id)?;
+            if let Some(primary_key) = &schema.primary_key {
+                if let Some(row) = find_duplicate_key(rows.iter(), primary_key) {
+                    return Err(ExecutionError::PrimaryKeyViolation { key: format_key(row, primary_key) });
+                }
+            }
+            self.check_unique_rows(table_id, &rows)?;
+        }
+        Ok(())
+    }
+    
+    /// 约束推迟检查时，在第一次修改表之前保存其行，返回是否跳过逐行检查
+    fn defer_constraint_checks(&mut self, table_id: u32) -> bool {
+        if !self.constraints_deferred {
+            return false;
+        }
+        self.save_before_image(table_id);
+        self.deferred_tables.insert(table_id);
+        true
+    }
+    
+    /// 安全模式下拒绝既没有 WHERE 也没有写出 ALL 的 UPDATE / DELETE
+    fn check_safe_update(&self, confirm: &str, table_name: &str, restricted: bool) -> Result<(), ExecutionError> {
+        if !self.config.safe_updates || restricted {
+            return Ok(());
+        }
+        Err(ExecutionError::UnsafeUpdate {
+            statement: confirm.split_whitespace().next().unwrap_or_default().to_string(),
+            confirm: confirm.to_string(),
+            table: table_name.to_string(),
+        })
+    }
+    
+    /// 数据目录已达到配置的上限时拒绝会增加数据的语句
+    ///
+    /// 只在语句开始前检查，一条语句本身写入的数据可能使用量略微超过上限。
+    fn check_disk_quota(&self) -> Result<(), ExecutionError> {
+        let Some(limit) = self.config.max_data_bytes else {
+            return Ok(());
+        };
+        let usage = self.data_size()?;
+        if usage >= limit {
+            return Err(ExecutionError::DiskQuotaExceeded { usage, limit });
+        }
+        Ok(())
+    }
+    
+    /// 为表分配新的数据版本，缓存的读取该表的查询结果随之失效
+    fn bump_data_version(&mut self, table_name: &str) {
+        if let Some(&table_id) = self.table_catalog.get(table_name) {
+            self.data_version += 1;
+            self.table_versions.insert(table_id, self.data_version);
+        }
+        self.result_cache.invalidate(table_name);
+    }
+    
+    /// 按语句类型分派执行
+    fn dispatch_statement(&mut self, statement: Statement) -> Result<QueryResult, ExecutionError> {
+        match statement {
+            Statement::CreateTable { table_name, columns, constraints } => {
+                self.execute_create_table_simple(table_name, columns, constraints)
+            }
+            Statement::DropTable { table_name, if_exists: _ } => {
+                self.execute_drop_table_simple(table_name)
+            }
+            Statement::Insert { table_name, columns, values, on_conflict, returning } => {
+                self.check_disk_quota()?;
+                self.execute_insert_simple(table_name, columns, values, on_conflict, returning)
+            }
+            query @ Statement::Select { .. } => self.execute_select_complete(query),
+            Statement::Update { where_clause: Some(ref where_clause), .. }
+            | Statement::Delete { where_clause: Some(ref where_clause), .. } if where_clause.contains_subquery() => {
+                Err(Self::subqueries_not_implemented())
+            }
+            Statement::Update { table_name, assignments, where_clause, returning, all_rows } => {
+                self.check_safe_update("UPDATE ALL", &table_name, where_clause.is_some() || all_rows)?;
+                self.execute_update_simple(table_name, assignments, where_clause, returning)
+            }
+            Statement::Delete { table_name, where_clause, returning, all_rows } => {
+                self.check_safe_update("DELETE ALL FROM", &table_name, where_clause.is_some() || all_rows)?;
+                self.execute_delete_simple(table_name, where_clause, returning)
+            }
+            Statement::CreateIndex { index_name, table_name, columns, is_unique, include, where_clause } => {
+                self.check_disk_quota()?;
+                self.execute_create_index(index_name, table_name, columns, is_unique, include, where_clause)
+            }
+            Statement::DropIndex { index_name, table_name, if_exists: _ } => {
+                self.execute_drop_index(index_name, table_name)
+            }
+            Statement::Reindex { target } => self.execute_reindex(target),
+            Statement::Analyze { table_name } => self.execute_analyze(table_name),
+            Statement::ShowIndexes { table_name } => {
+                let column = |name: &str, data_type, nullable| ColumnDefinition::new(name.to_string(), data_type, nullable);
+                let schema = Schema::new(vec![
+                    column("table_name", DataType::Varchar(255), false),
+                    column("index_name", DataType::Varchar(255), false),
+                    column("columns", DataType::Varchar(255), false),
+                    column("is_unique", DataType::Boolean, false),
+                    column("size_bytes", DataType::BigInt, true),
+                    column("entries", DataType::BigInt, true),
+                ]);
+                let count = |value: Option<usize>| value.map_or(Value::Null, |value| Value::BigInt(value as i64));
+                let rows: Vec<Tuple> = self.list_indexes(table_name.as_deref())?.into_iter().map(|index| Tuple::new(vec![
+                    Value::Varchar(index.table),
+                    Value::Varchar(index.name),
+                    Value::Varchar(index.columns.join(", ")),
+                    Value::Boolean(index.unique),
+                    count(index.size_bytes),
+                    count(index.entries),
+                ])).collect();
+                Ok(QueryResult {
+                    message: format!("{} index(es)", rows.len()),
+                    rows,
+                    schema: Some(schema),
+                    affected_rows: 0,
+                })
+            }
+            Statement::CreateView { view_name, query, temporary } => {
+                self.execute_create_view(view_name, *query, temporary)
+            }
+            Statement::DropView { view_name, if_exists } => {
+                self.execute_drop_view(view_name, if_exists)
+            }
+            Statement::Assert { query, condition, message } => {
+                self.execute_assert(*query, condition, message)
+            }
+            Statement::Explain { statement, analyze: false } => {
+                self.execute_explain(*statement)
+            }
+            Statement::Explain { statement, analyze: true } => {
+                self.execute_explain_analyze(*statement)
+            }
+            Statement::DryRun { statement } => {
+                self.execute_dry_run(*statement)
+            }
+            Statement::Deduplicate { table_name } => {
+                self.execute_deduplicate(table_name)
+            }
+            Statement::AlterTable { table_name, action } => {
+                self.execute_alter_table(table_name, action)
+            }
+            Statement::Copy { table_name, path, options } => {
+                self.check_disk_quota()?;
+                self.execute_copy_from(table_name, path, options)
+            }
+            Statement::CopyTo { query, path, options } => {
+                self.execute_copy_to(*query, path, options)
+            }
+            Statement::Begin => self.begin_transaction(),
+            Statement::Commit => self.commit_transaction(),
+            Statement::Rollback => self.rollback_transaction(),
+            Statement::SetConstraints { deferred } => {
+                self.constraints_deferred = deferred;
+                Ok(QueryResult {
+                    rows: vec![],
+                    schema: None,
+                    affected_rows: 0,
+                    message: format!("Constraints are now checked {}", if deferred { "at commit" } else { "immediately" }),
+                })
+            }
+            Statement::SetVariable { name, value } => self.execute_set_variable(&name, &value),
+            Statement::Checkpoint => {
+                let lsn = self.checkpoint()?;
+                Ok(Self::message_result(format!("CHECKPOINT at LSN {}", lsn)))
+            }
+            #[cfg(feature = "persistence")]
+            Statement::Backup { path } => {
+                let manifest = self.backup(&path)?;
+                Ok(Self::message_result(format!(
+                    "BACKUP to '{}': {} files, {} bytes at LSN {}",
+                    path, manifest.files.len(), manifest.total_size(), manifest.lsn
+                )))
+            }
+            #[cfg(not(feature = "persistence"))]
+            Statement::Backup { .. } => Err(ExecutionError::NotImplemented {
+                feature: "BACKUP without the persistence feature".to_string(),
+                capability: Capability::Backup,
+            }),
+            Statement::AdviseIndexes => {
+                let rows: Vec<Tuple> = self.advise().into_iter().map(|advice| Tuple::new(vec![
+                    Value::Varchar(advice.table),
+                    Value::Varchar(advice.columns.join(", ")),
+                    Value::Varchar(advice.statement),
+                    Value::BigInt(advice.calls as i64),
+                    Value::Double(advice.selectivity),
+                    Value::Double(advice.estimated_rows_saved),
+                ])).collect();
+                Ok(QueryResult {
+                    message: format!("{} index suggestion(s)", rows.len()),
+                    rows,
+                    schema: Some(advisor::advice_schema()),
+                    affected_rows: 0,
+                })
+            }
+        }
+    }
+    
+    /// 执行 COPY (SELECT ...) TO：把查询结果写成 CSV 文件
+    ///
+    /// 每从算子树拉取一行就交给带缓冲的 writer，结果集不在内存中攒齐；出错时删除写了一半的文件。
+    fn execute_copy_to(
+        &mut self,
+        query: Statement,
+        path: String,
+        options: crate::sql::parser::CopyOptions,
+    ) -> Result<QueryResult, ExecutionError> {
+        use crate::sql::parser::CopyFormat;
+        use std::io::Write;
         
-        // Remove table from catalog
-        self.table_catalog.remove(&name);
-        self.table_schemas.remove(&table_id);
+        if options.format == Some(CopyFormat::Ndjson) {
+            return Err(ExecutionError::NotImplemented {
+                feature: "COPY TO in NDJSON format".to_string(),
+                capability: Capability::Copy,
+            });
+        }
+        if options.infer {
+            return Err(ExecutionError::SemanticError("INFER only applies to COPY FROM".to_string()));
+        }
         
-        // Delete table file
-        let table_file_name = format!("table_{}.db", table_id);
-        self.file_manager.delete_file(&table_file_name)
-            .map_err(|e| ExecutionError::StorageError(format!("Failed to delete table file: {}", e)))?;
+        let mut plan = self.select_plan(query)?;
+        let schema = plan.root.schema().clone();
+        let io_error = |e: std::io::Error| ExecutionError::StorageError(format!("Failed to write '{}': {}", path, e));
+        let file = std::fs::File::create(&path).map_err(io_error)?;
+        let mut writer = std::io::BufWriter::new(file);
+        let csv_options = CsvOptions { delimiter: options.delimiter, header: options.header, quote: options.quote };
+        
+        // 执行器出错时结束行序列，错误留到写完之后返回
+        let mut failure = None;
+        let rows = std::iter::from_fn(|| match plan.root.next() {
+            Ok(row) => row,
+            Err(e) => {
+                failure = Some(ExecutionError::from(e));
+                None
+            }
+        });
+        let written = super::export::write_csv(&mut writer, Some(&schema), rows, &csv_options)
+            .and_then(|count| writer.flush().map(|_| count))
+            .map_err(io_error);
+        let count = match (written, failure) {
+            (Ok(count), None) => count,
+            (Err(e), _) | (Ok(_), Some(e)) => {
+                drop(writer);
+                let _ = std::fs::remove_file(&path);
+                return Err(e);
+            }
+        };
         
         Ok(QueryResult {
             rows: vec![],
             schema: None,
-            affected_rows: 0,
-            message: format!("Table '{}' dropped successfully", name),
+            affected_rows: count,
+            message: format!("Copied {} row(s) to '{}'", count, path),
         })
     }
     
-    /// 执行 INSERT 语句（简化版本）
-    fn execute_insert_simple(&mut self, table: String, values: Vec<Vec<crate::sql::parser::Expression>>) -> Result<QueryResult, ExecutionError> {
-        // Check if table exists
-        let table_id = self.table_catalog.get(&table)
-            .ok_or_else(|| ExecutionError::TableNotFound { table: table.clone() })?;
+    /// 执行 COPY FROM：读取 CSV / NDJSON 文件并插入目标表
+    ///
+    /// 目标表不存在且指定了 INFER 时，先根据采样记录推断列名和类型建表，推断出的 DDL 随结果消息返回。
+    fn execute_copy_from(
+        &mut self,
+        table_name: String,
+        path: String,
+        options: crate::sql::parser::CopyOptions,
+    ) -> Result<QueryResult, ExecutionError> {
+        use crate::sql::parser::{ColumnDef, CopyFormat};
         
-        let table_id = *table_id;
-        let schema = self.table_schemas.get(&table_id)
-            .ok_or_else(|| ExecutionError::TableNotFound { table: table.clone() })?;
+        let text = std::fs::read_to_string(&path)
+            .map_err(|e| ExecutionError::StorageError(format!("Failed to read '{}': {}", path, e)))?;
+        let format = options.format.unwrap_or_else(|| {
+            let lower = path.to_lowercase();
+            if lower.ends_with(".ndjson") || lower.ends_with(".jsonl") { CopyFormat::Ndjson } else { CopyFormat::Csv }
+        });
         
-        // Validate and convert values
-        let mut inserted_count = 0;
-        for row_expressions in values {
-            if row_expressions.len() != schema.columns.len() {
-                return Err(ExecutionError::TypeMismatch {
-                    expected: format!("{} columns", schema.columns.len()),
-                    actual: format!("{} values", row_expressions.len()),
-                });
+        // 表头（列名）和数据记录
+        let (header, records) = match format {
+            CopyFormat::Csv => {
+                let mut records = import::parse_csv(&text, options.delimiter)?;
+                let header = if options.header && !records.is_empty() {
+                    Some(records.remove(0).fields)
+                } else {
+                    None
+                };
+                (header, records)
             }
-            
-            // Convert expressions to values
-            let mut row_values = Vec::new();
-            for (i, expr) in row_expressions.iter().enumerate() {
-                let value = self.evaluate_expression(expr, &schema.columns[i].data_type)?;
-                row_values.push(value);
+            #[cfg(feature = "persistence")]
+            CopyFormat::Ndjson => {
+                let (keys, records) = import::parse_ndjson(&text)?;
+                (Some(keys.into_iter().map(Some).collect()), records)
             }
-            
-            // Create tuple
-            let tuple = Tuple { values: row_values };
-            
-            // Check primary key constraint before inserting
-            if let Some(ref primary_key_columns) = schema.primary_key {
-                self.check_primary_key_constraint(&tuple, primary_key_columns, table_id)?;
+            #[cfg(not(feature = "persistence"))]
+            CopyFormat::Ndjson => {
+                return Err(ExecutionError::NotImplemented {
+                    feature: "COPY FROM NDJSON without the persistence feature".to_string(),
+                    capability: Capability::Copy,
+                });
+            }
+        };
+        
+        let mut created = None;
+        if !self.table_catalog.contains_key(&table_name) {
+            if !options.infer {
+                return Err(ExecutionError::TableNotFound { table: table_name });
             }
+            let width = header.as_ref().map(|h| h.len())
+                .or_else(|| records.first().map(|r| r.fields.len()))
+                .ok_or_else(|| ExecutionError::EvaluationError {
+                    message: format!("cannot infer a table from empty file '{}'", path),
+                })?;
+            let names = import::column_names(header.as_deref(), width);
+            let columns: Vec<ColumnDef> = names.into_iter().enumerate()
+                .map(|(i, name)| {
+                    let sample = records.iter()
+                        .take(import::INFER_SAMPLE_ROWS)
+                        .filter_map(|record| record.fields.get(i).and_then(|field| field.as_deref()));
+                    ColumnDef {
+                        name,
+                        data_type: import::infer_type(sample),
+                        nullable: true,
+                        default: None,
+                        primary_key: false,
+                        unique: false,
+                    }
+                })
+                .collect();
             
-            // Add to table data
-            self.table_data.get_mut(&table_id).unwrap().push(tuple);
-            inserted_count += 1;
+            let ddl = format!(
+                "CREATE TABLE {} ({})",
+                table_name,
+                columns.iter().map(|c| format!("{} {}", c.name, c.data_type)).collect::<Vec<_>>().join(", ")
+            );
+            self.execute_create_table_simple(table_name.clone(), columns, Vec::new())?;
+            created = Some(ddl);
         }
         
-        // Save table data after insertion
-        if let Err(e) = self.save_table(table_id, &table) {
-            println!("Warning: Failed to save table data: {}", e);
-        }
+        let imported = match self.copy_records(&table_name, header.as_deref(), &records, created.is_none()) {
+            Ok(imported) => imported,
+            Err(e) => {
+                // 推断建出的表在导入失败时删掉，不留下空表
+                if created.is_some() {
+                    self.execute_drop_table_simple(table_name)?;
+                }
+                return Err(e);
+            }
+        };
         
+        let mut message = format!("Copied {} row(s) into '{}' from '{}'", imported, table_name, path);
+        if let Some(ddl) = created {
+            message = format!("{}; created table: {}", message, ddl);
+        }
         Ok(QueryResult {
             rows: vec![],
             schema: None,
-            affected_rows: inserted_count,
-            message: format!("Inserted {} row(s) into table '{}'", inserted_count, table),
+            affected_rows: imported,
+            message,
         })
     }
     
-    /// 简单表达式求值（仅支持字面量）
-    fn evaluate_expression(&self, expr: &crate::sql::parser::Expression, expected_type: &DataType) -> Result<Value, ExecutionError> {
-        use crate::sql::parser::Expression;
-        
-        match expr {
-            Expression::Literal(value) => {
-                // Validate the value type matches expected type (with flexible conversion)
-                match (value, expected_type) {
-                    (Value::Integer(_), DataType::Integer) => Ok(value.clone()),
-                    (Value::BigInt(_), DataType::BigInt) => Ok(value.clone()),
-                    (Value::Float(_), DataType::Float) => Ok(value.clone()),
-                    (Value::Double(d), DataType::Float) => Ok(Value::Float(*d as f32)), // Convert Double to Float
-                    (Value::Float(f), DataType::Double) => Ok(Value::Double(*f as f64)), // Convert Float to Double
-                    (Value::Double(_), DataType::Double) => Ok(value.clone()),
-                    (Value::Varchar(_), DataType::Varchar(_)) => Ok(value.clone()),
-                    (Value::Boolean(_), DataType::Boolean) => Ok(value.clone()),
-                    (Value::Date(_), DataType::Date) => Ok(value.clone()),
-                    (Value::Timestamp(_), DataType::Timestamp) => Ok(value.clone()),
-                    (Value::Null, _) => Ok(Value::Null),
-                    // Allow integer to bigint conversion
-                    (Value::Integer(i), DataType::BigInt) => Ok(Value::BigInt(*i as i64)),
-                    (Value::BigInt(i), DataType::Integer) => {
-                        if *i >= i32::MIN as i64 && *i <= i32::MAX as i64 {
-                            Ok(Value::Integer(*i as i32))
-                        } else {
-                            Err(ExecutionError::TypeMismatch {
-                                expected: "INTEGER (in range)".to_string(),
-                                actual: format!("BIGINT({})", i),
-                            })
-                        }
-                    }
-                    _ => Err(ExecutionError::TypeMismatch {
-                        expected: format!("{:?}", expected_type),
-                        actual: format!("{:?}", value),
+    /// 把 COPY 的记录导入已有的表，返回导入的行数
+    ///
+    /// by_name 时文件列按表头中的名称对应到表列，否则按位置（推断建表时列名已规范化，按位置）。
+    fn copy_records(
+        &mut self,
+        table_name: &str,
+        header: Option<&[Option<String>]>,
+        records: &[import::Record],
+        by_name: bool,
+    ) -> Result<usize, ExecutionError> {
+        let schema = self.get_table_schema(table_name)
+            .cloned()
+            .ok_or_else(|| ExecutionError::TableNotFound { table: table_name.to_string() })?;
+        // 文件列到表列的映射
+        let target_columns: Vec<usize> = match header {
+            Some(names) if by_name => names.iter()
+                .map(|name| {
+                    let name = name.as_deref().unwrap_or_default().trim();
+                    schema.columns.iter().position(|c| c.name == name).ok_or_else(|| ExecutionError::ColumnNotFound {
+                        table: table_name.to_string(),
+                        column: name.to_string(),
                     })
+                })
+                .collect::<Result<_, _>>()?,
+            _ => (0..schema.columns.len()).collect(),
+        };
+        
+        let column_names: Vec<String> = target_columns.iter().map(|&i| schema.columns[i].name.clone()).collect();
+        let value_positions = self.resolve_insert_columns(table_name, &schema, Some(&column_names))?;
+        let table_id = *self.table_catalog.get(table_name)
+            .ok_or_else(|| ExecutionError::TableNotFound { table: table_name.to_string() })?;
+        
+        // 先转换并检查全部记录，出错的行一起报告，任何一行出错都不导入
+        let mut errors = import::LineErrors::default();
+        let mut rows = Vec::with_capacity(records.len());
+        let mut lines = Vec::with_capacity(records.len());
+        for record in records {
+            if record.fields.len() != target_columns.len() {
+                errors.push(record.line, format!("expected {} fields, found {}", target_columns.len(), record.fields.len()));
+                continue;
+            }
+            match self.copy_row(&schema, &value_positions, &record.fields) {
+                Ok(row) => {
+                    rows.push(row);
+                    lines.push(record.line);
                 }
+                Err(message) => errors.push(record.line, message),
             }
-            _ => Err(ExecutionError::NotImplemented {
-                feature: format!("Expression evaluation: {:?}", expr)
-            })
         }
+        if !self.defer_constraint_checks(table_id) {
+            self.check_copy_keys(table_id, &schema, &rows, &lines, &mut errors)?;
+        }
+        errors.into_result()?;
+        
+        // 行已经转换和检查过，直接追加，不再逐行求值和扫描表
+        let imported = rows.len();
+        for row in rows {
+            self.append_row(table_id, row)?;
+        }
+        Ok(imported)
     }
     
-    /// 评估给定行的 WHERE 条件
-    fn evaluate_where_condition(
-        &self, 
-        expr: &crate::sql::parser::Expression, 
-        row: &Tuple, 
-        schema: &Schema
-    ) -> Result<bool, ExecutionError> {
-        use crate::sql::parser::Expression;
-        use crate::sql::parser::BinaryOperator;
-        
-        match expr {
-            Expression::BinaryOp { left, op, right } => {
-                match op {
-                    // Logical operators: evaluate as boolean conditions first
-                    BinaryOperator::And => {
-                        let left_bool = self.evaluate_where_condition(left, row, schema)?;
-                        let right_bool = self.evaluate_where_condition(right, row, schema)?;
-                        Ok(left_bool && right_bool)
-                    }
-                    BinaryOperator::Or => {
-                        let left_bool = self.evaluate_where_condition(left, row, schema)?;
-                        let right_bool = self.evaluate_where_condition(right, row, schema)?;
-                        Ok(left_bool || right_bool)
-                    }
-                    
-                    // Comparison operators: evaluate values first then compare
-                    _ => {
-                        let left_value = self.evaluate_where_expression(left, row, schema)?;
-                        let right_value = self.evaluate_where_expression(right, row, schema)?;
-                        
-                        match op {
-                            BinaryOperator::Equal => Ok(left_value == right_value),
-                            BinaryOperator::NotEqual => Ok(left_value != right_value),
-                            BinaryOperator::LessThan => self.compare_values(&left_value, &right_value, |cmp| cmp < 0),
-                            BinaryOperator::LessEqual => self.compare_values(&left_value, &right_value, |cmp| cmp <= 0),
-                            BinaryOperator::GreaterThan => self.compare_values(&left_value, &right_value, |cmp| cmp > 0),
-                            BinaryOperator::GreaterEqual => self.compare_values(&left_value, &right_value, |cmp| cmp >= 0),
-                            
-                            _ => Err(ExecutionError::NotImplemented {
-                                feature: format!("WHERE operator: {:?}", op)
-                            })
-                        }
-                    }
-                }
+    /// 把 COPY 的一条记录转换为整行：字段按列类型转换，文件中没有的列取默认值
+    fn copy_row(&self, schema: &Schema, value_positions: &[Option<usize>], fields: &[Option<String>]) -> Result<Tuple, String> {
+        let mut values = Vec::with_capacity(schema.columns.len());
+        for (column, position) in schema.columns.iter().zip(value_positions) {
+            let value = match position.map(|p| &fields[p]) {
+                // 字符串列与 INSERT 一样检查长度
+                Some(Some(text)) if matches!(column.data_type, DataType::Varchar(_) | DataType::Char(_)) => self
+                    .evaluate_expression(&crate::sql::parser::Expression::Literal(Value::Varchar(text.clone())), &column.data_type)
+                    .map_err(|e| format!("{} for column '{}'", e, column.name))?,
+                Some(Some(text)) => Value::Varchar(text.clone()).cast_to(&column.data_type).map_err(|_| {
+                    format!("cannot convert '{}' to {} for column '{}'", text, column.data_type, column.name)
+                })?,
+                Some(None) => Value::Null,
+                None => self.column_default(column).map_err(|e| e.to_string())?,
+            };
+            if value.is_null() && !column.nullable {
+                return Err(format!("NULL in column '{}' violates NOT NULL", column.name));
             }
-            Expression::Column(col_name) => {
-                // Column reference in WHERE should be evaluated as boolean
-                let value = self.evaluate_where_expression(expr, row, schema)?;
-                match value {
-                    Value::Boolean(b) => Ok(b),
-                    Value::Null => Ok(false),
-                    _ => Ok(true), // Non-null, non-boolean values are truthy
-                }
+            values.push(value);
+        }
+        Ok(Tuple::new(values))
+    }
+    
+    /// 列的默认值：设置了 default_function 的每次调用求一个新值，否则为建表时求出的常量
+    fn column_default(&self, column: &ColumnDefinition) -> Result<Value, ExecutionError> {
+        match &column.default_function {
+            Some(name) => {
+                let value = functions::call(name, &[], &self.random)?;
+                self.evaluate_expression(&crate::sql::parser::Expression::Literal(value), &column.data_type)
             }
-            Expression::Literal(Value::Boolean(b)) => Ok(*b),
-            _ => Err(ExecutionError::NotImplemented {
-                feature: format!("WHERE expression: {:?}", expr)
-            })
+            None => Ok(column.default.clone().unwrap_or(Value::Null)),
         }
     }
     
-    /// 在 WHERE 上下文中求值表达式（返回 Value）
-    fn evaluate_where_expression(
-        &self, 
-        expr: &crate::sql::parser::Expression, 
-        row: &Tuple, 
-        schema: &Schema
-    ) -> Result<Value, ExecutionError> {
-        use crate::sql::parser::Expression;
-        
-        match expr {
-            Expression::Literal(value) => Ok(value.clone()),
-            Expression::Column(col_name) => {
-                // Find column index
-                let col_index = schema.columns.iter()
-                    .position(|col| col.name == *col_name)
-                    .ok_or_else(|| ExecutionError::ColumnNotFound {
-                        table: "current".to_string(), // We don't have table name in this context
-                        column: col_name.clone(),
-                    })?;
-                
-                Ok(row.values[col_index].clone())
-            }
-            _ => Err(ExecutionError::NotImplemented {
-                feature: format!("WHERE expression evaluation: {:?}", expr)
-            })
-        }
-    }
-    
-    /// 比较两个值的顺序（返回排序比较结果）
-    fn compare_values<F>(&self, left: &Value, right: &Value, pred: F) -> Result<bool, ExecutionError>
-    where 
-        F: Fn(i32) -> bool
-    {
-        use std::cmp::Ordering;
-        
-        let cmp_result = match (left, right) {
-            (Value::Integer(a), Value::Integer(b)) => a.cmp(b),
-            (Value::BigInt(a), Value::BigInt(b)) => a.cmp(b),
-            (Value::Float(a), Value::Float(b)) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
-            (Value::Double(a), Value::Double(b)) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
-            (Value::Varchar(a), Value::Varchar(b)) => a.cmp(b),
-            (Value::Boolean(a), Value::Boolean(b)) => a.cmp(b),
-            // Type coercion for numbers
-            (Value::Integer(a), Value::BigInt(b)) => (*a as i64).cmp(b),
-            (Value::BigInt(a), Value::Integer(b)) => a.cmp(&(*b as i64)),
-            (Value::Float(a), Value::Double(b)) => (*a as f64).partial_cmp(b).unwrap_or(Ordering::Equal),
-            (Value::Double(a), Value::Float(b)) => a.partial_cmp(&(*b as f64)).unwrap_or(Ordering::Equal),
-            (Value::Integer(a), Value::Float(b)) => (*a as f32).partial_cmp(b).unwrap_or(Ordering::Equal),
-            (Value::Float(a), Value::Integer(b)) => a.partial_cmp(&(*b as f32)).unwrap_or(Ordering::Equal),
-            (Value::Integer(a), Value::Double(b)) => (*a as f64).partial_cmp(b).unwrap_or(Ordering::Equal),
-            (Value::Double(a), Value::Integer(b)) => a.partial_cmp(&(*b as f64)).unwrap_or(Ordering::Equal),
-            (Value::Null, _) | (_, Value::Null) => return Ok(false), // NULL comparisons are always false
-            _ => return Err(ExecutionError::TypeMismatch {
-                expected: format!("{:?}", left),
-                actual: format!("{:?}", right),
-            })
-        };
-        
-        let cmp_int = match cmp_result {
-            Ordering::Less => -1,
-            Ordering::Equal => 0,
-            Ordering::Greater => 1,
-        };
-        
-        Ok(pred(cmp_int))
-    }
-    
-    /// 简化的 WHERE 求值以避免借用冲突
-    fn simple_where_eval(
+    /// 一次性检查 COPY 的行是否违反主键或唯一索引：已有行的键只读取一遍，行之间的重复也会发现
+    fn check_copy_keys(
         &self,
-        expr: &crate::sql::parser::Expression,
-        row: &Tuple,
+        table_id: u32,
         schema: &Schema,
-    ) -> Result<bool, ExecutionError> {
-        // This is a simplified version of evaluate_where_condition
-        // to avoid borrowing conflicts in UPDATE/DELETE operations
-        use crate::sql::parser::Expression;
-        use crate::sql::parser::BinaryOperator;
+        rows: &[Tuple],
+        lines: &[usize],
+        errors: &mut import::LineErrors,
+    ) -> Result<(), ExecutionError> {
+        let existing = self.table_rows(table_id)?;
+        // (约束名, 键列, NULL 键是否参与比较, 部分索引的谓词)；与逐行插入一致，主键的 NULL 也算重复
+        let mut keys: Vec<(String, Vec<usize>, bool, Option<&crate::sql::parser::Expression>)> = Vec::new();
+        if let Some(primary_key) = &schema.primary_key {
+            keys.push(("PRIMARY KEY".to_string(), primary_key.clone(), true, None));
+        }
+        for (index, key_columns) in self.unique_index_keys(table_id) {
+            keys.push((format!("UNIQUE index '{}'", index.name), key_columns, false, index.where_clause.as_ref()));
+        }
         
-        match expr {
-            Expression::BinaryOp { left, op, right } => {
-                let left_value = self.simple_where_expr_eval(left, row, schema)?;
-                let right_value = self.simple_where_expr_eval(right, row, schema)?;
-                
-                match op {
-                    BinaryOperator::Equal => Ok(left_value == right_value),
-                    BinaryOperator::NotEqual => Ok(left_value != right_value),
-                    BinaryOperator::LessThan => self.compare_values(&left_value, &right_value, |cmp| cmp < 0),
-                    BinaryOperator::LessEqual => self.compare_values(&left_value, &right_value, |cmp| cmp <= 0),
-                    BinaryOperator::GreaterThan => self.compare_values(&left_value, &right_value, |cmp| cmp > 0),
-                    BinaryOperator::GreaterEqual => self.compare_values(&left_value, &right_value, |cmp| cmp >= 0),
-                    _ => Ok(false), // Unsupported operations default to false
-                }
-            }
-            Expression::Literal(Value::Boolean(b)) => Ok(*b),
-            _ => Ok(false), // Default to false for unsupported expressions
+        fn key_of<'a>(row: &'a Tuple, key_columns: &[usize], compare_nulls: bool) -> Option<Vec<&'a Value>> {
+            let key: Vec<&Value> = key_columns.iter().map(|&i| &row.values[i]).collect();
+            (compare_nulls || !key.iter().any(|value| value.is_null())).then_some(key)
         }
-    }
-    
-    /// 简化的 WHERE 条件表达式求值
-    fn simple_where_expr_eval(
-        &self,
-        expr: &crate::sql::parser::Expression,
-        row: &Tuple,
-        schema: &Schema,
-    ) -> Result<Value, ExecutionError> {
-        use crate::sql::parser::Expression;
         
-        match expr {
-            Expression::Literal(value) => Ok(value.clone()),
-            Expression::Column(col_name) => {
-                let col_index = schema.columns.iter()
-                    .position(|col| col.name == *col_name)
-                    .ok_or_else(|| ExecutionError::ColumnNotFound {
-                        table: "current".to_string(),
-                        column: col_name.clone(),
-                    })?;
-                
-                Ok(row.values[col_index].clone())
+        for (constraint, key_columns, compare_nulls, where_clause) in &keys {
+            let member = |row: &Tuple| self.in_partial_index(*where_clause, schema, row);
+            let mut seen: std::collections::HashSet<Vec<&Value>> = existing.iter()
+                .filter(|row| member(row))
+                .filter_map(|row| key_of(row, key_columns, *compare_nulls))
+                .collect();
+            for (row, &line) in rows.iter().zip(lines) {
+                if let Some(row_key) = key_of(row, key_columns, *compare_nulls).filter(|_| member(row)) {
+                    if !seen.insert(row_key) {
+                        errors.push(line, format!("duplicate key {} violates {}", format_key(row, key_columns), constraint));
+                    }
+                }
             }
-            _ => Err(ExecutionError::NotImplemented {
-                feature: "Complex WHERE expressions in UPDATE/DELETE".to_string()
-            })
         }
+        Ok(())
     }
     
-    /// 投影特定列（SELECT 列过滤）
-    fn project_columns(
-        &self,
-        rows: &[Tuple],
-        select_exprs: &[crate::sql::parser::SelectExpr],
-        schema: &Schema,
-        table_name: &str,
-    ) -> Result<(Vec<Tuple>, Schema), ExecutionError> {
-        use crate::sql::parser::Expression;
+    /// 执行 CREATE TABLE 语句（简化版本）
+    fn execute_create_table_simple(
+        &mut self,
+        name: String,
+        columns: Vec<crate::sql::parser::ColumnDef>,
+        constraints: Vec<crate::sql::parser::TableConstraint>,
+    ) -> Result<QueryResult, ExecutionError> {
+        // Check if table already exists
+        if self.table_catalog.contains_key(&name) {
+            return Err(ExecutionError::TableAlreadyExists { table: name });
+        }
+        if self.temp_views.contains_key(&name) {
+            return Err(ExecutionError::SemanticError(format!("'{}' is already a temporary view", name)));
+        }
         
-        // Build new schema with selected columns
-        let mut new_columns = Vec::new();
-        let mut column_indices = Vec::new();
+        // Convert column definitions to schema and extract primary key info
+        let mut schema_columns = Vec::new();
+        let mut primary_key_columns = Vec::new();
         
-        for select_expr in select_exprs {
-            match &select_expr.expr {
-                Expression::Column(col_name) => {
-                    // Find column index in original schema
-                    let col_index = schema.columns.iter()
-                        .position(|col| col.name == *col_name)
-                        .ok_or_else(|| ExecutionError::ColumnNotFound {
-                            table: table_name.to_string(),
-                            column: col_name.clone(),
-                        })?;
-                    
-                    column_indices.push(col_index);
-                    
-                    // Use alias if provided, otherwise use original column name
-                    let column_name = select_expr.alias.as_ref()
-                        .unwrap_or(col_name)
-                        .clone();
-                    
-                    let mut new_col = schema.columns[col_index].clone();
-                    new_col.name = column_name;
-                    new_columns.push(new_col);
-                }
-                Expression::FunctionCall { name, args } => {
-                    // 聚合函数调用 (e.g., COUNT(*), AVG(age))
-                    // 注意：在 project_columns 中，我们不直接计算聚合函数
-                    // 这里只是为了构建结果 schema，实际计算在 GROUP BY 处理中完成
-                    
-                    let column_name = select_expr.alias.as_ref()
-                        .unwrap_or(&format!("{}(...)", name))
-                        .clone();
-                    
-                    // 根据函数类型确定返回值类型
-                    let data_type = match name.to_uppercase().as_str() {
-                        "COUNT" => crate::types::DataType::Integer,
-                        "SUM" | "AVG" | "MAX" | "MIN" => crate::types::DataType::Double, // 默认为 Double
-                        _ => crate::types::DataType::Varchar(50), // 未知函数默认为字符串
-                    };
-                    
-                    new_columns.push(crate::types::ColumnDefinition {
-                        name: column_name,
-                        data_type,
-                        nullable: true,
-                        default: None,
-                    });
-                    
-                    // 对于聚合函数，我们需要特殊处理，暂时使用 -1 作为标记
-                    column_indices.push(usize::MAX);
-                }
-                Expression::Literal(_) => {
-                    // Literal values in SELECT (e.g., SELECT 1, 'hello')
-                    return Err(ExecutionError::NotImplemented {
-                        feature: "Literal expressions in SELECT".to_string()
-                    });
-                }
-                _ => {
-                    return Err(ExecutionError::NotImplemented {
-                        feature: format!("Complex expressions in SELECT: {:?}", select_expr.expr)
-                    });
+        for (i, col_def) in columns.iter().enumerate() {
+            // DEFAULT expressions are evaluated once, when the table is created, except a bare
+            // volatile call such as GEN_UUID() or NOW(), which is evaluated for every row
+            let (default, default_function) = match &col_def.default {
+                Some(crate::sql::parser::Expression::FunctionCall { name, args })
+                    if args.is_empty() && functions::is_volatile(name) =>
+                {
+                    // 先求一次值，检查结果能存入该列
+                    let value = functions::call(name, &[], &self.random)?;
+                    self.evaluate_expression(&crate::sql::parser::Expression::Literal(value), &col_def.data_type)?;
+                    (None, Some(name.to_uppercase()))
                 }
+                Some(expr) => (Some(self.evaluate_expression(expr, &col_def.data_type)?), None),
+                None => (None, None),
+            };
+            
+            let column = crate::types::ColumnDefinition {
+                name: col_def.name.clone(),
+                data_type: col_def.data_type.clone(),
+                nullable: col_def.nullable,
+                default,
+                default_function,
+            };
+            schema_columns.push(column);
+            
+            // Check for column-level primary key
+            if col_def.primary_key {
+                primary_key_columns.push(i);
             }
         }
         
-        // Create new schema
-        let new_schema = Schema {
-            columns: new_columns,
-            primary_key: None, // Projected query results don't have primary key
+        let mut schema = Schema {
+            columns: schema_columns,
+            primary_key: None,
         };
         
-        // Project rows to selected columns
-        let projected_rows: Vec<Tuple> = rows.iter()
-            .map(|row| {
-                let projected_values: Vec<Value> = column_indices.iter()
-                    .map(|&idx| {
-                        if idx == usize::MAX {
-                            // 对于聚合函数，暂时返回 NULL（将在 GROUP BY 中处理）
-                            crate::types::Value::Null
-                        } else {
-                            row.values[idx].clone()
-                        }
-                    })
-                    .collect();
-                
-                Tuple {
-                    values: projected_values,
-                }
-            })
+        // UNIQUE constraints are each backed by an automatically created unique index
+        let mut unique_keys: Vec<Vec<String>> = columns.iter()
+            .filter(|col| col.unique)
+            .map(|col| vec![col.name.clone()])
             .collect();
+        for constraint in constraints {
+            match constraint {
+                crate::sql::parser::TableConstraint::Unique(key) => unique_keys.push(key),
+                // PRIMARY KEY (a, b) is equivalent to marking each column PRIMARY KEY
+                crate::sql::parser::TableConstraint::PrimaryKey(key) => {
+                    if !primary_key_columns.is_empty() {
+                        return Err(ExecutionError::SemanticError(
+                            format!("table '{}' cannot have more than one primary key", name)
+                        ));
+                    }
+                    primary_key_columns = Self::column_positions(&schema, &name, &key)?;
+                }
+                _ => {}
+            }
+        }
+        if !primary_key_columns.is_empty() {
+            schema.primary_key = Some(primary_key_columns);
+        }
         
-        Ok((projected_rows, new_schema))
-    }
-    
-    /// 执行 SELECT 语句（简化版本）
-    fn execute_select_simple(
-        &self,
-        select_list: crate::sql::parser::SelectList,
-        from_clause: Option<crate::sql::parser::FromClause>,
-        where_clause: Option<crate::sql::parser::Expression>,
-    ) -> Result<QueryResult, ExecutionError> {
-        // Extract table name from FROM clause
-        let table_name = match from_clause {
-            Some(crate::sql::parser::FromClause::Table(name)) => name,
-            Some(_) => {
-                return Err(ExecutionError::NotImplemented {
-                    feature: "Complex FROM clauses".to_string()
-                });
+        let mut indexes: Vec<IndexInfo> = Vec::new();
+        for key in unique_keys {
+            if let Some(column) = key.iter().find(|c| !schema.columns.iter().any(|col| col.name == **c)) {
+                return Err(ExecutionError::ColumnNotFound { table: name.clone(), column: column.clone() });
             }
-            std::option::Option::None => {
-                return Err(ExecutionError::ParseError("Missing FROM clause".to_string()));
+            if indexes.iter().any(|index| index.columns == key) {
+                continue;
             }
-        };
-        
-        // Get table data
-        let table_id = self.table_catalog.get(&table_name)
-            .ok_or_else(|| ExecutionError::TableNotFound { table: table_name.clone() })?;
-        
-        let table_id = *table_id;
-        let schema = self.table_schemas.get(&table_id)
-            .ok_or_else(|| ExecutionError::TableNotFound { table: table_name.clone() })?;
+            indexes.push(IndexInfo {
+                name: format!("{}_{}_key", name, key.join("_")),
+                columns: key,
+                unique: true,
+                include: Vec::new(),
+                where_clause: None,
+                build: None,
+            });
+        }
         
-        let table_data = self.table_data.get(&table_id)
-            .ok_or_else(|| ExecutionError::TableNotFound { table: table_name.clone() })?;
+        // Assign new table ID
+        let table_id = self.next_table_id;
+        self.next_table_id += 1;
         
-        // Apply WHERE clause filtering
-        let filtered_rows: Vec<Tuple> = match where_clause {
-            Some(expr) => {
-                table_data.iter()
-                    .filter(|row| {
-                        match self.evaluate_where_condition(&expr, row, schema) {
-                            Ok(true) => true,
-                            _ => false, // If evaluation fails or returns false, exclude row
-                        }
-                    })
-                    .cloned()
-                    .collect()
-            }
-            std::option::Option::None => table_data.clone(),
-        };
+        // Register table
+        self.table_catalog.insert(name.clone(), table_id);
+        self.table_schemas.insert(table_id, schema);
+        let rows = self.new_table_rows(table_id, Vec::new())?;
+        self.table_data.insert(table_id, rows);
+        self.table_indexes.insert(table_id, indexes);
+        self.table_row_ids.insert(table_id, RowIds::default());
+        self.bump_data_version(&name);
         
-        // Apply column selection
-        let (result_rows, result_schema) = match select_list {
-            crate::sql::parser::SelectList::Wildcard => {
-                // SELECT * - return all columns
-                (filtered_rows.clone(), schema.clone())
-            }
-            crate::sql::parser::SelectList::Expressions(select_exprs) => {
-                // SELECT specific columns
-                self.project_columns(&filtered_rows, &select_exprs, schema, &table_name)?
-            }
-        };
+        // The snapshot is written first; the WAL record makes the table part of the catalog
+        if let Err(e) = self.save_table(table_id, &name) {
+            println!("Warning: Failed to save table data: {}", e);
+        }
+        self.log_change(WalRecord::CreateTable { table_id, name: name.clone() });
+        self.flush_wal()?;
+        if let Err(e) = self.save_metadata() {
+            println!("Warning: Failed to save metadata: {}", e);
+        }
         
         Ok(QueryResult {
-            rows: result_rows.clone(),
-            schema: Some(result_schema),
+            rows: vec![],
+            schema: None,
             affected_rows: 0,
-            message: format!("Retrieved {} row(s) from table '{}' (total: {})", 
-                result_rows.len(), table_name, table_data.len()),
+            message: format!("Table '{}' created successfully", name),
         })
     }
-
-    /// 执行具有完整功能支持的 SELECT 语句（ORDER BY、GROUP BY、LIMIT 等）
-    fn execute_select_complete(
-        &self,
-        select_list: crate::sql::parser::SelectList,
-        from_clause: Option<crate::sql::parser::FromClause>,
-        where_clause: Option<crate::sql::parser::Expression>,
-        group_by: Option<Vec<crate::sql::parser::Expression>>,
-        having: Option<crate::sql::parser::Expression>,
-        order_by: Option<Vec<crate::sql::parser::OrderByExpr>>,
-        limit: Option<u64>,
-        offset: Option<u64>,
-    ) -> Result<QueryResult, ExecutionError> {
-        use crate::engine::executor::{Executor, HashJoinExecutor, SortExecutor, LimitExecutor, GroupByExecutor, AggregateFunction};
-        use crate::sql::planner::{JoinType, SortKey};
-        use crate::sql::parser::{FromClause, OrderByExpr};
-        
-        // 检测并报告高级功能
-        let mut detected_features = Vec::new();
-        if group_by.is_some() { detected_features.push("GROUP BY"); }
-        if having.is_some() { detected_features.push("HAVING"); }
-        if order_by.is_some() { detected_features.push("ORDER BY"); }
-        if limit.is_some() { detected_features.push("LIMIT"); }
-        if offset.is_some() { detected_features.push("OFFSET"); }
-        
-        if !detected_features.is_empty() {
-            println!("🚀 执行高级SQL功能: {}", detected_features.join(", "));
-        }
-        
-        // 检测 SELECT 列表是否包含聚合函数
-        let has_aggregate_functions = self.select_list_contains_aggregates(&select_list);
-        if has_aggregate_functions && !detected_features.contains(&"GROUP BY") {
-            detected_features.push("IMPLICIT GROUP BY (aggregate functions)");
-        }
-
-        // 开始构建执行计划
-        // 1. 如果有 GROUP BY 或者 SELECT 包含聚合函数，需要特殊处理执行流程
-        let mut base_result = if group_by.is_some() || has_aggregate_functions {
-            // GROUP BY 查询：先获取原始数据（不进行列投影），然后应用分组聚合
-            let table_name = match &from_clause {
-                Some(crate::sql::parser::FromClause::Table(name)) => name.clone(),
-                _ => return Err(ExecutionError::NotImplemented { 
-                    feature: "Complex FROM clauses with GROUP BY".to_string() 
-                }),
-            };
-            
-            // 获取原始表数据和 schema（不进行列投影）
-            let table_id = self.table_catalog.get(&table_name)
-                .ok_or_else(|| ExecutionError::TableNotFound { table: table_name.clone() })?;
-            let table_id = *table_id;
-            let original_schema = self.table_schemas.get(&table_id)
-                .ok_or_else(|| ExecutionError::TableNotFound { table: table_name.clone() })?.clone();
-            let table_data = self.table_data.get(&table_id)
-                .ok_or_else(|| ExecutionError::TableNotFound { table: table_name.clone() })?;
-            
-            // 应用 WHERE 过滤但保持原始 schema
-            let filtered_rows: Vec<Tuple> = match where_clause {
-                Some(expr) => {
-                    table_data.iter()
-                        .filter(|row| {
-                            match self.evaluate_where_condition(&expr, row, &original_schema) {
-                                Ok(true) => true,
-                                _ => false,
-                            }
-                        })
-                        .cloned()
-                        .collect()
-                }
-                None => table_data.clone(),
-            };
-            
-            let filtered_result = QueryResult {
-                rows: filtered_rows,
-                schema: Some(original_schema),
-                affected_rows: 0,
-                message: "Filtered data for GROUP BY".to_string(),
-            };
-            
-            // 应用 GROUP BY 分组聚合
-            let group_expressions = group_by.unwrap_or_else(|| Vec::new()); // 如果没有 GROUP BY，使用空的分组表达式
-            self.apply_group_by_with_select(filtered_result, group_expressions, select_list, having)?
-        } else {
-            // 普通查询：执行基础查询（表扫描 + WHERE 过滤 + 列投影）
-            self.execute_select_simple(select_list.clone(), from_clause.clone(), where_clause)?
-        };
+    
+    /// 执行 DROP TABLE 语句（简化版本）
+    fn execute_drop_table_simple(&mut self, name: String) -> Result<QueryResult, ExecutionError> {
+        // Check if table exists
+        let table_id = self.table_catalog.get(&name)
+            .ok_or_else(|| ExecutionError::TableNotFound { table: name.clone() })?;
         
-        // 2. 如果有 GROUP BY，上面已经处理了，这里跳过
-        // GROUP BY 已经在上面处理完成
+        let table_id = *table_id;
+        self.log_change(WalRecord::DropTable { table_id, name: name.clone() });
+        self.flush_wal()?;
         
-        // 3. 如果有 ORDER BY，应用排序
-        if let Some(order_exprs) = order_by {
-            base_result = self.apply_order_by(base_result, order_exprs)?;
-        }
+        // Remove table from catalog
+        self.table_catalog.remove(&name);
+        self.table_schemas.remove(&table_id);
+        self.table_indexes.remove(&table_id);
+        self.table_row_ids.remove(&table_id);
+        self.table_versions.remove(&table_id);
+        self.result_cache.invalidate(&name);
+        self.table_compression.remove(&table_id);
+        self.table_statistics.remove(&table_id);
         
-        // 4. 如果有 LIMIT/OFFSET，应用分页
-        if limit.is_some() || offset.is_some() {
-            base_result = self.apply_limit_offset(base_result, limit.unwrap_or(u64::MAX), offset.unwrap_or(0))?;
+        if let Err(e) = self.remove_table_rows(table_id) {
+            println!("Warning: Failed to remove table work file: {}", e);
         }
-        
-        Ok(base_result)
-    }
-    
-    /// 应用 GROUP BY 分组聚合 (支持聚合函数)
-    fn apply_group_by_with_select(
-        &self,
-        input_result: QueryResult,
-        group_exprs: Vec<crate::sql::parser::Expression>,
-        select_list: crate::sql::parser::SelectList,
-        _having: Option<crate::sql::parser::Expression>,
+        if let Err(e) = self.remove_table_snapshot(table_id) {
+            println!("Warning: Failed to remove table data: {}", e);
+        }
+        if let Err(e) = self.save_metadata() {
+            println!("Warning: Failed to save metadata: {}", e);
+        }
+        
+        Ok(QueryResult {
+            rows: vec![],
+            schema: None,
+            affected_rows: 0,
+            message: format!("Table '{}' dropped successfully", name),
+        })
+    }
+    
+    /// 执行 INSERT 语句（简化版本）
+    fn execute_insert_simple(
+        &mut self,
+        table: String,
+        columns: Option<Vec<String>>,
+        values: Vec<Vec<crate::sql::parser::Expression>>,
+        on_conflict: Option<crate::sql::parser::OnConflict>,
+        returning: Option<crate::sql::parser::SelectList>,
     ) -> Result<QueryResult, ExecutionError> {
-        use std::collections::HashMap;
-        use crate::sql::parser::{Expression, SelectList};
+        use crate::sql::parser::ConflictAction;
         
-        // 创建分组哈希表
-        let mut groups: HashMap<Vec<Value>, Vec<Tuple>> = HashMap::new();
+        // Check if table exists
+        let table_id = self.table_catalog.get(&table)
+            .ok_or_else(|| ExecutionError::TableNotFound { table: table.clone() })?;
         
-        // 按分组表达式对元组进行分组
-        for tuple in input_result.rows {
-            let mut group_key = Vec::new();
-            
-            // 评估分组表达式
-            for expr in &group_exprs {
-                let schema = input_result.schema.as_ref().unwrap();
-                let group_value = self.evaluate_expression_for_tuple(expr, &tuple, schema)?;
-                group_key.push(group_value);
-            }
-            
-            groups.entry(group_key).or_insert_with(Vec::new).push(tuple);
-        }
+        let table_id = *table_id;
+        let schema = self.table_schemas.get(&table_id)
+            .ok_or_else(|| ExecutionError::TableNotFound { table: table.clone() })?
+            .clone();
         
-        // 解析 SELECT 子句中的表达式
-        let select_expressions = match select_list {
-            SelectList::Expressions(exprs) => exprs,
-            SelectList::Wildcard => {
-                return Err(ExecutionError::NotImplemented {
-                    feature: "GROUP BY with SELECT * not supported".to_string()
-                });
-            }
+        // Resolve the key columns used to detect conflicts
+        let conflict_columns = match &on_conflict {
+            Some(clause) => Some(self.resolve_conflict_columns(table_id, &table, &schema, &clause.target)?),
+            None => None,
         };
         
-        // 构建结果 schema
-        let mut result_columns = Vec::new();
-        for select_expr in &select_expressions {
-            let column_name = if let Some(alias) = &select_expr.alias {
-                alias.clone()
-            } else {
-                match &select_expr.expr {
-                    Expression::Column(col_name) => col_name.clone(),
-                    Expression::FunctionCall { name, .. } => {
-                        format!("{}()", name) // COUNT(), AVG(), etc.
-                    }
-                    _ => "expr".to_string(),
-                }
-            };
+        // Map every table column to its position in the VALUES rows (None = omitted)
+        let value_positions = self.resolve_insert_columns(&table, &schema, columns.as_deref())?;
+        let deferred = self.defer_constraint_checks(table_id);
+        let expected_values = columns.as_ref().map_or(schema.columns.len(), |cols| cols.len());
+        
+        // Validate and convert values
+        let mut inserted_count = 0;
+        let mut updated_count = 0;
+        let mut affected_rows = Vec::new();
+        for row_expressions in values {
+            if row_expressions.len() != expected_values {
+                return Err(ExecutionError::TypeMismatch {
+                    expected: format!("{} columns", expected_values),
+                    actual: format!("{} values", row_expressions.len()),
+                });
+            }
             
-            let data_type = match &select_expr.expr {
-                Expression::Column(_) => crate::types::DataType::Varchar(50), // 分组列
-                Expression::FunctionCall { name, .. } => {
-                    match name.to_uppercase().as_str() {
-                        "COUNT" => crate::types::DataType::Integer,
-                        "AVG" | "SUM" | "MAX" | "MIN" => crate::types::DataType::Double,
-                        _ => crate::types::DataType::Double,
-                    }
+            // Convert expressions to values, filling omitted columns and DEFAULT with column defaults
+            let mut row_values = Vec::new();
+            for (column, position) in schema.columns.iter().zip(&value_positions) {
+                let value = match position.map(|p| &row_expressions[p]) {
+                    None | Some(crate::sql::parser::Expression::Default) => self.column_default(column)?,
+                    Some(expr) => self.evaluate_expression(expr, &column.data_type)?,
+                };
+                
+                if matches!(value, Value::Null) && !column.nullable {
+                    return Err(ExecutionError::NotNullViolation { column: column.name.clone() });
                 }
-                _ => crate::types::DataType::Varchar(50),
-            };
+                row_values.push(value);
+            }
             
-            result_columns.push(crate::types::ColumnDefinition {
-                name: column_name,
-                data_type,
-                nullable: true,
-                default: None,
-            });
-        }
-        
-        // 生成聚合结果
-        let mut result_rows = Vec::new();
-        
-        for (group_key, group_tuples) in groups {
-            let mut result_values = Vec::new();
+            // Create tuple
+            let tuple = Tuple { values: row_values };
             
-            for select_expr in &select_expressions {
-                match &select_expr.expr {
-                    Expression::Column(col_name) => {
-                        // 分组列：从 group_key 中取值
-                        // 找到这个列在 GROUP BY 表达式中的位置
-                        let mut found = false;
-                        for (i, group_expr) in group_exprs.iter().enumerate() {
-                            if let Expression::Column(group_col_name) = group_expr {
-                                if group_col_name == col_name {
-                                    result_values.push(group_key[i].clone());
-                                    found = true;
-                                    break;
-                                }
+            // Handle ON CONFLICT before the regular constraint checks
+            if let (Some(clause), Some(key_columns)) = (&on_conflict, &conflict_columns) {
+                if let Some(existing_index) = self.find_conflicting_row(table_id, &tuple, key_columns, None, None)? {
+                    match &clause.action {
+                        ConflictAction::DoNothing => {}
+                        ConflictAction::DoUpdate { assignments, where_clause } => {
+                            if let Some(updated_row) = self.apply_conflict_update(
+                                table_id, &table, existing_index, &tuple, assignments, where_clause.as_ref(),
+                            )? {
+                                affected_rows.push(updated_row);
+                                updated_count += 1;
                             }
                         }
-                        
-                        if !found {
-                            result_values.push(Value::Null);
-                        }
-                    }
-                    Expression::FunctionCall { name, args } => {
-                        // 聚合函数：使用原始输入的 schema
-                        let original_schema = input_result.schema.as_ref().unwrap();
-                        let agg_value = self.compute_aggregate_function(name, args, &group_tuples, original_schema)?;
-                        result_values.push(agg_value);
-                    }
-                    _ => {
-                        result_values.push(Value::Null);
                     }
+                    continue;
                 }
             }
             
-            result_rows.push(Tuple { values: result_values });
+            // Check primary key constraint before inserting
+            if !deferred {
+                if let Some(ref primary_key_columns) = schema.primary_key {
+                    self.check_primary_key_constraint(&tuple, primary_key_columns, table_id, None)?;
+                }
+                self.check_unique_constraints(table_id, &tuple, None)?;
+            }
+            
+            // Add to table data
+            if returning.is_some() {
+                affected_rows.push(tuple.clone());
+            }
+            self.append_row(table_id, tuple)?;
+            inserted_count += 1;
         }
         
-        let row_count = result_rows.len();
+        let message = if on_conflict.is_some() {
+            format!("Inserted {} row(s), updated {} row(s) in table '{}'", inserted_count, updated_count, table)
+        } else {
+            format!("Inserted {} row(s) into table '{}'", inserted_count, table)
+        };
+        
+        let (rows, result_schema) = match returning {
+            Some(list) => {
+                let (rows, returning_schema) = self.project_returning(&affected_rows, &list, &schema, &table)?;
+                (rows, Some(returning_schema))
+            }
+            None => (vec![], None),
+        };
+        
         Ok(QueryResult {
-            rows: result_rows,
-            schema: Some(crate::types::Schema { columns: result_columns, primary_key: None }),
-            affected_rows: row_count,
-            message: format!("📊 GROUP BY 查询完成，返回 {} 行聚合结果", row_count),
+            rows,
+            schema: result_schema,
+            affected_rows: inserted_count + updated_count,
+            message,
         })
     }
     
-    /// 计算聚合函数值
-    fn compute_aggregate_function(
+    /// 解析 INSERT 列列表，返回每个表列在 VALUES 行中的位置
+    fn resolve_insert_columns(
         &self,
-        func_name: &str,
-        args: &[crate::sql::parser::Expression],
-        group_tuples: &[crate::types::Tuple],
-        schema: &crate::types::Schema,
-    ) -> Result<crate::types::Value, ExecutionError> {
-        use crate::types::Value;
+        table_name: &str,
+        schema: &Schema,
+        columns: Option<&[String]>,
+    ) -> Result<Vec<Option<usize>>, ExecutionError> {
+        let columns = match columns {
+            Some(columns) => columns,
+            None => return Ok((0..schema.columns.len()).map(Some).collect()),
+        };
         
-        match func_name.to_uppercase().as_str() {
-            "COUNT" => {
-                // COUNT(*) 或 COUNT(column)
-                if args.is_empty() || (args.len() == 1 && matches!(args[0], crate::sql::parser::Expression::Literal(Value::Varchar(ref s)) if s == "*")) {
-                    // COUNT(*) - 计算行数
-                    Ok(Value::Integer(group_tuples.len() as i32))
-                } else {
-                    // COUNT(column) - 计算非NULL值的数量
-                    let mut count = 0;
-                    for tuple in group_tuples {
-                        if let Ok(val) = self.evaluate_expression_for_tuple(&args[0], tuple, schema) {
-                            if !matches!(val, Value::Null) {
-                                count += 1;
-                            }
-                        }
-                    }
-                    Ok(Value::Integer(count))
-                }
-            }
-            "SUM" => {
-                if args.is_empty() {
-                    return Err(ExecutionError::EvaluationError {
-                        message: "SUM function requires an argument".to_string()
-                    });
-                }
-                
-                let mut sum = 0.0;
-                for tuple in group_tuples {
-                    if let Ok(val) = self.evaluate_expression_for_tuple(&args[0], tuple, schema) {
-                        sum += self.value_to_f64(&val);
-                    }
-                }
-                Ok(Value::Double(sum))
-            }
-            "AVG" => {
-                if args.is_empty() {
-                    return Err(ExecutionError::EvaluationError {
-                        message: "AVG function requires an argument".to_string()
-                    });
-                }
-                
-                let mut sum = 0.0;
-                let mut count = 0;
-                for tuple in group_tuples {
-                    if let Ok(val) = self.evaluate_expression_for_tuple(&args[0], tuple, schema) {
-                        if !matches!(val, Value::Null) {
-                            sum += self.value_to_f64(&val);
-                            count += 1;
-                        }
-                    }
-                }
-                
-                if count > 0 {
-                    Ok(Value::Double(sum / count as f64))
-                } else {
-                    Ok(Value::Null)
-                }
-            }
-            "MAX" => {
-                if args.is_empty() {
-                    return Err(ExecutionError::EvaluationError {
-                        message: "MAX function requires an argument".to_string()
-                    });
-                }
-                
-                let mut max_val: Option<f64> = None;
-                for tuple in group_tuples {
-                    if let Ok(val) = self.evaluate_expression_for_tuple(&args[0], tuple, schema) {
-                        if !matches!(val, Value::Null) {
-                            let num_val = self.value_to_f64(&val);
-                            max_val = Some(max_val.map_or(num_val, |current| current.max(num_val)));
-                        }
-                    }
-                }
-                
-                Ok(max_val.map(Value::Double).unwrap_or(Value::Null))
-            }
-            "MIN" => {
-                if args.is_empty() {
-                    return Err(ExecutionError::EvaluationError {
-                        message: "MIN function requires an argument".to_string()
-                    });
-                }
-                
-                let mut min_val: Option<f64> = None;
-                for tuple in group_tuples {
-                    if let Ok(val) = self.evaluate_expression_for_tuple(&args[0], tuple, schema) {
-                        if !matches!(val, Value::Null) {
-                            let num_val = self.value_to_f64(&val);
-                            min_val = Some(min_val.map_or(num_val, |current| current.min(num_val)));
-                        }
-                    }
-                }
-                
-                Ok(min_val.map(Value::Double).unwrap_or(Value::Null))
+        for (i, name) in columns.iter().enumerate() {
+            if !schema.columns.iter().any(|col| col.name == *name) {
+                return Err(ExecutionError::ColumnNotFound {
+                    table: table_name.to_string(),
+                    column: name.clone(),
+                });
             }
-            _ => {
-                Err(ExecutionError::NotImplemented {
-                    feature: format!("Aggregate function: {}", func_name)
-                })
+            if columns[..i].contains(name) {
+                return Err(ExecutionError::EvaluationError {
+                    message: format!("Column '{}' specified more than once", name),
+                });
             }
         }
+        
+        Ok(schema.columns.iter()
+            .map(|col| columns.iter().position(|name| *name == col.name))
+            .collect())
     }
-
-    /// 检查 SELECT 列表是否包含聚合函数
-    fn select_list_contains_aggregates(&self, select_list: &crate::sql::parser::SelectList) -> bool {
-        use crate::sql::parser::{SelectList, Expression};
+    
+    /// 解析 ON CONFLICT 的冲突目标列（未指定时使用主键）
+    ///
+    /// 目标列的集合必须恰好是主键或某个（非部分）唯一索引的键列，否则冲突的行不唯一。
+    fn resolve_conflict_columns(
+        &self,
+        table_id: u32,
+        table_name: &str,
+        schema: &Schema,
+        target: &[String],
+    ) -> Result<Vec<usize>, ExecutionError> {
+        if target.is_empty() {
+            return schema.primary_key.clone().ok_or_else(|| ExecutionError::EvaluationError {
+                message: format!(
+                    "ON CONFLICT without a conflict target requires a primary key on table '{}'",
+                    table_name
+                ),
+            });
+        }
         
-        match select_list {
-            SelectList::Wildcard => false,
-            SelectList::Expressions(expressions) => {
-                expressions.iter().any(|select_expr| {
-                    self.expression_contains_aggregates(&select_expr.expr)
+        let columns = target.iter()
+            .map(|column| {
+                schema.columns.iter()
+                    .position(|col| col.name == *column)
+                    .ok_or_else(|| ExecutionError::ColumnNotFound {
+                        table: table_name.to_string(),
+                        column: column.clone(),
+                    })
+            })
+            .collect::<Result<Vec<usize>, _>>()?;
+        
+        let sorted = |key: &[usize]| {
+            let mut key = key.to_vec();
+            key.sort_unstable();
+            key.dedup();
+            key
+        };
+        let target_key = sorted(&columns);
+        let matches_primary_key = schema.primary_key.as_deref().is_some_and(|key| sorted(key) == target_key);
+        let matches_unique_index = self.unique_index_keys(table_id)
+            .iter()
+            .any(|(index, key)| index.where_clause.is_none() && sorted(key) == target_key);
+        if !matches_primary_key && !matches_unique_index {
+            return Err(ExecutionError::EvaluationError {
+                message: format!(
+                    "there is no unique or exclusion constraint matching the ON CONFLICT specification ({}) on table '{}'",
+                    target.join(", "),
+                    table_name
+                ),
+            });
+        }
+        Ok(columns)
+    }
+    
+    /// 查找与给定元组在键列上冲突的已有行的位置（NULL 键值永不冲突；where_clause 同 [`Self::find_key_conflict`]）
+    fn find_conflicting_row(
+        &self,
+        table_id: u32,
+        tuple: &Tuple,
+        key_columns: &[usize],
+        where_clause: Option<&crate::sql::parser::Expression>,
+        skip_index: Option<usize>,
+    ) -> Result<Option<usize>, ExecutionError> {
+        let excluded: std::collections::HashSet<u64> = skip_index.map(|i| self.row_id_at(table_id, i)).into_iter().collect();
+        Ok(self.find_key_conflict(table_id, tuple, key_columns, false, where_clause, &excluded)?
+            .and_then(|row_id| self.row_position(table_id, row_id)))
+    }
+    
+    /// 查找键列上与元组相同、rowid 不在 excluded 中的已有行，返回其 rowid
+    ///
+    /// 键列是主键或某个已建好的索引的列时按内存索引查找，代价为 O(log n)；否则扫描整张表。
+    /// compare_nulls 为 false 时含 NULL 的键永不冲突；为 true 时 NULL 与 NULL 视为相同（主键），
+    /// 这种键不在索引中，只能扫描。
+    /// where_clause 为部分唯一索引的谓词：只与满足谓词的行比较，元组本身不满足时不冲突。
+    fn find_key_conflict(
+        &self,
+        table_id: u32,
+        tuple: &Tuple,
+        key_columns: &[usize],
+        compare_nulls: bool,
+        where_clause: Option<&crate::sql::parser::Expression>,
+        excluded: &std::collections::HashSet<u64>,
+    ) -> Result<Option<u64>, ExecutionError> {
+        let has_null = key_columns.iter().any(|&i| matches!(tuple.values.get(i), None | Some(Value::Null)));
+        if has_null && !compare_nulls {
+            return Ok(None);
+        }
+        let schema = self.table_schemas.get(&table_id)
+            .ok_or_else(|| ExecutionError::TableNotFound { table: format!("table_id_{}", table_id) })?;
+        if !self.in_partial_index(where_clause, schema, tuple) {
+            return Ok(None);
+        }
+        if !has_null && (where_clause.is_some() || self.is_indexed_key(table_id, key_columns)) {
+            return self.with_row_index(table_id, key_columns, &[], where_clause, |index| {
+                index.matching(tuple).iter().copied().find(|row_id| !excluded.contains(row_id))
+            });
+        }
+        
+        let ids = self.row_ids(table_id);
+        Ok(self.table_rows(table_id)?
+            .iter()
+            .enumerate()
+            .map(|(i, existing)| (ids.get(i).copied().unwrap_or_default(), existing))
+            .filter(|(row_id, existing)| !excluded.contains(row_id) && self.in_partial_index(where_clause, schema, existing))
+            .find(|(_, existing)| {
+                key_columns.iter().all(|&i| existing.values.get(i) == tuple.values.get(i))
+            })
+            .map(|(row_id, _)| row_id))
+    }
+    
+    /// 键列是否为表的主键或某个已建好的普通（非部分）索引的全部列
+    fn is_indexed_key(&self, table_id: u32, key_columns: &[usize]) -> bool {
+        let Some(schema) = self.table_schemas.get(&table_id) else {
+            return false;
+        };
+        schema.primary_key.as_deref() == Some(key_columns)
+            || self.table_indexes.get(&table_id).into_iter().flatten()
+                .filter(|index| index.build.is_none() && index.where_clause.is_none())
+                .any(|index| {
+                    index.columns.len() == key_columns.len()
+                        && index.columns.iter().zip(key_columns)
+                            .all(|(name, &i)| schema.columns.get(i).is_some_and(|column| column.name == *name))
                 })
+    }
+    
+    /// 对冲突行应用 DO UPDATE 赋值，返回更新后的行（WHERE 不满足时为 None）
+    fn apply_conflict_update(
+        &mut self,
+        table_id: u32,
+        table_name: &str,
+        existing_index: usize,
+        excluded: &Tuple,
+        assignments: &[crate::sql::parser::Assignment],
+        where_clause: Option<&crate::sql::parser::Expression>,
+    ) -> Result<Option<Tuple>, ExecutionError> {
+        let schema = &self.table_schemas.get(&table_id)
+            .ok_or_else(|| ExecutionError::TableNotFound { table: table_name.to_string() })?
+            .clone();
+        let existing = self.table_data.get(&table_id)
+            .map(|rows| rows.get(&self.buffer_pool, existing_index))
+            .transpose()?
+            .flatten()
+            .ok_or_else(|| ExecutionError::TableNotFound { table: table_name.to_string() })?;
+        self.lock_rows(table_id, table_name, &[existing_index])?;
+        
+        if let Some(condition) = where_clause {
+            let condition = Self::bind_excluded(condition, excluded, schema);
+            if !self.evaluate_where_condition(&condition, &existing, schema)? {
+                return Ok(None);
             }
         }
-    }
-
-    /// 检查表达式是否包含聚合函数（递归检查）
-    fn expression_contains_aggregates(&self, expr: &crate::sql::parser::Expression) -> bool {
-        use crate::sql::parser::Expression;
         
-        match expr {
-            Expression::FunctionCall { name, .. } => {
-                // Check if this is an aggregate function
-                matches!(name.to_uppercase().as_str(), "COUNT" | "SUM" | "AVG" | "MIN" | "MAX")
+        let mut new_row = existing.clone();
+        for assignment in assignments {
+            let col_index = schema.columns.iter()
+                .position(|col| col.name == assignment.column)
+                .ok_or_else(|| ExecutionError::ColumnNotFound {
+                    table: table_name.to_string(),
+                    column: assignment.column.clone(),
+                })?;
+            
+            let value_expr = Self::bind_excluded(&assignment.value, excluded, schema);
+            let value = match value_expr {
+                crate::sql::parser::Expression::Literal(val) => val,
+                expr => self.evaluate_expression_for_tuple(&expr, &existing, schema)?,
+            };
+            new_row.values[col_index] = self.assignment_value(schema, col_index, value)?;
+        }
+        
+        // The update must not move the row onto another row's primary key
+        if !self.defer_constraint_checks(table_id) {
+            if let Some(ref primary_key_columns) = schema.primary_key {
+                self.check_primary_key_constraint(&new_row, primary_key_columns, table_id, Some(existing_index))?;
             }
-            // For other expression types, we can add recursive checks if needed
-            _ => false
+            self.check_unique_constraints(table_id, &new_row, Some(existing_index))?;
         }
+        
+        self.update_row(table_id, existing_index, &existing, new_row.clone())?;
+        Ok(Some(new_row))
     }
     
-    /// 应用 GROUP BY 分组聚合
-    fn apply_group_by(
+    /// 把 UPDATE / ON CONFLICT DO UPDATE 赋给某列的值转换为列的类型
+    ///
+    /// 与 INSERT 使用同一套转换：DECIMAL 列按声明的精度和小数位数舍入，VARCHAR 列检查长度，CHAR 列还要补齐空格，
+    /// UUID、JSON 和日期时间列的字符串按该类型解析校验；此外数值可以赋给更宽的数值列（如 DOUBLE 列赋整数）。
+    /// 类型不符、NULL 赋给 NOT NULL 或主键列时报错。
+    fn assignment_value(&self, schema: &Schema, col_index: usize, value: Value) -> Result<Value, ExecutionError> {
+        let column = &schema.columns[col_index];
+        if value.is_null() {
+            let in_primary_key = schema.primary_key.as_ref().is_some_and(|key| key.contains(&col_index));
+            if !column.nullable || in_primary_key {
+                return Err(ExecutionError::NotNullViolation { column: column.name.clone() });
+            }
+            return Ok(Value::Null);
+        }
+        self.evaluate_expression(&crate::sql::parser::Expression::Literal(value.clone()), &column.data_type)
+            .or_else(|err| match value {
+                Value::Integer(_) | Value::BigInt(_) | Value::Float(_) | Value::Double(_)
+                    if value.is_compatible_with(&column.data_type) =>
+                {
+                    value.cast_to(&column.data_type).map_err(|e| ExecutionError::EvaluationError { message: e.to_string() })
+                }
+                _ => Err(err),
+            })
+    }
+    
+    /// 按 RETURNING 列表投影受影响的行
+    fn project_returning(
         &self,
-        input_result: QueryResult,
-        group_exprs: Vec<crate::sql::parser::Expression>,
-        _having: Option<crate::sql::parser::Expression>,
-    ) -> Result<QueryResult, ExecutionError> {
-        use std::collections::HashMap;
-        
-        // 创建分组哈希表
-        let mut groups: HashMap<Vec<Value>, Vec<Tuple>> = HashMap::new();
+        rows: &[Tuple],
+        returning: &crate::sql::parser::SelectList,
+        schema: &Schema,
+        table_name: &str,
+    ) -> Result<(Vec<Tuple>, Schema), ExecutionError> {
+        use crate::sql::parser::{Expression, SelectList};
         
-        // 按分组表达式对元组进行分组
-        for tuple in input_result.rows {
-            let mut group_key = Vec::new();
-            
-            // 评估分组表达式
-            for expr in &group_exprs {
-                let schema = input_result.schema.as_ref().unwrap();
-                let group_value = self.evaluate_expression_for_tuple(expr, &tuple, schema)?;
-                group_key.push(group_value);
+        let select_exprs = match returning {
+            SelectList::Wildcard => {
+                return Ok((rows.to_vec(), Schema { columns: schema.columns.clone(), primary_key: None }));
             }
-            
-            groups.entry(group_key).or_insert_with(Vec::new).push(tuple);
-        }
+            SelectList::Expressions(exprs) => exprs,
+        };
         
-        // 生成聚合结果
-        let mut result_rows = Vec::new();
+        let mut projected_rows = Vec::with_capacity(rows.len());
+        for row in rows {
+            let values = select_exprs.iter()
+                .map(|select_expr| self.evaluate_expression_for_tuple(&select_expr.expr, row, schema))
+                .collect::<Result<Vec<_>, _>>()?;
+            projected_rows.push(Tuple::new(values));
+        }
         
-        for (group_key, group_tuples) in groups {
-            // 计算完整的聚合函数
-            let count = group_tuples.len() as i32;
-            let numeric_values: Vec<f64> = group_tuples.iter()
-                .filter_map(|t| t.values.iter().find(|v| matches!(v, Value::Integer(_) | Value::Float(_) | Value::Double(_))))
-                .map(|v| self.value_to_f64(v))
-                .collect();
-            
-            // 构建结果元组（分组键 + 聚合值）
-            let mut result_values = group_key;
-            
-            // COUNT(*) - 总行数
-            result_values.push(Value::Integer(count));
+        let mut columns = Vec::with_capacity(select_exprs.len());
+        for (i, select_expr) in select_exprs.iter().enumerate() {
+            let source = match &select_expr.expr {
+                Expression::Column(name) | Expression::QualifiedColumn { column: name, .. } => {
+                    Some(schema.columns.iter().find(|col| col.name == *name).ok_or_else(|| {
+                        ExecutionError::ColumnNotFound {
+                            table: table_name.to_string(),
+                            column: name.clone(),
+                        }
+                    })?)
+                }
+                _ => None,
+            };
             
-            // 如果有数值列，计算其他聚合函数
-            if !numeric_values.is_empty() {
-                // SUM - 求和
-                let sum = numeric_values.iter().sum::<f64>();
-                result_values.push(Value::Double(sum));
-                
-                // AVG - 平均值
-                let avg = sum / (numeric_values.len() as f64);
-                result_values.push(Value::Double(avg));
-                
-                // MAX - 最大值
-                let max = numeric_values.iter()
-                    .fold(f64::NEG_INFINITY, |acc, &x| acc.max(x));
-                result_values.push(Value::Double(max));
-                
-                // MIN - 最小值
-                let min = numeric_values.iter()
-                    .fold(f64::INFINITY, |acc, &x| acc.min(x));
-                result_values.push(Value::Double(min));
-            } else {
-                // 如果没有数值列，添加NULL值
-                result_values.push(Value::Null); // SUM
-                result_values.push(Value::Null); // AVG
-                result_values.push(Value::Null); // MAX
-                result_values.push(Value::Null); // MIN
-            }
+            let name = select_expr.alias.clone()
+                .or_else(|| source.map(|col| col.name.clone()))
+                .unwrap_or_else(|| format!("expr_{}", i + 1));
+            let data_type = match source {
+                Some(col) => col.data_type.clone(),
+                None => projected_rows.iter()
+                    .map(|row| &row.values[i])
+                    .find(|value| !matches!(value, Value::Null))
+                    .map(|value| value.data_type())
+                    .unwrap_or(DataType::Varchar(255)),
+            };
             
-            result_rows.push(Tuple { values: result_values });
-        }
-        
-        // 构建结果schema
-        let mut result_columns = Vec::new();
-        
-        // 添加分组列
-        for (i, _) in group_exprs.iter().enumerate() {
-            result_columns.push(ColumnDefinition {
-                name: format!("group_col_{}", i),
-                data_type: DataType::Varchar(50),
-                nullable: true,
+            columns.push(ColumnDefinition {
+                name,
+                data_type,
+                nullable: source.is_none_or(|col| col.nullable),
                 default: None,
+                default_function: None,
             });
         }
         
-        // 添加聚合列
-        result_columns.push(ColumnDefinition {
-            name: "COUNT(*)".to_string(),
-            data_type: DataType::Integer,
-            nullable: false,
-            default: None,
-        });
-        
-        result_columns.push(ColumnDefinition {
-            name: "SUM".to_string(),
-            data_type: DataType::Double,
-            nullable: true,
-            default: None,
-        });
-        
-        result_columns.push(ColumnDefinition {
-            name: "AVG".to_string(),
-            data_type: DataType::Double,
-            nullable: true,
-            default: None,
-        });
+        Ok((projected_rows, Schema { columns, primary_key: None }))
+    }
+    
+    /// 将表达式中的 excluded.列名 替换为待插入行的对应值
+    fn bind_excluded(
+        expr: &crate::sql::parser::Expression,
+        excluded: &Tuple,
+        schema: &Schema,
+    ) -> crate::sql::parser::Expression {
+        use crate::sql::parser::Expression;
         
-        let row_count = result_rows.len();
-        Ok(QueryResult {
-            rows: result_rows,
-            schema: Some(Schema { columns: result_columns, primary_key: None }),
-            affected_rows: row_count,
-            message: format!("📊 GROUP BY 查询完成，返回 {} 行聚合结果", row_count),
-        })
+        match expr {
+            Expression::QualifiedColumn { table, column } if table.eq_ignore_ascii_case("excluded") => {
+                match schema.columns.iter().position(|col| col.name == *column) {
+                    Some(i) => Expression::Literal(excluded.values[i].clone()),
+                    None => expr.clone(),
+                }
+            }
+            Expression::BinaryOp { left, op, right } => Expression::BinaryOp {
+                left: Box::new(Self::bind_excluded(left, excluded, schema)),
+                op: op.clone(),
+                right: Box::new(Self::bind_excluded(right, excluded, schema)),
+            },
+            Expression::UnaryOp { op, expr: inner } => Expression::UnaryOp {
+                op: op.clone(),
+                expr: Box::new(Self::bind_excluded(inner, excluded, schema)),
+            },
+            Expression::FunctionCall { name, args } => Expression::FunctionCall {
+                name: name.clone(),
+                args: args.iter().map(|arg| Self::bind_excluded(arg, excluded, schema)).collect(),
+            },
+            Expression::Cast { expr: inner, data_type } => Expression::Cast {
+                expr: Box::new(Self::bind_excluded(inner, excluded, schema)),
+                data_type: data_type.clone(),
+            },
+            _ => expr.clone(),
+        }
     }
     
-    /// 应用 ORDER BY 排序
-    fn apply_order_by(
-        &self,
-        mut input_result: QueryResult,
-        order_exprs: Vec<OrderByExpr>,
-    ) -> Result<QueryResult, ExecutionError> {
-        // 按照 ORDER BY 表达式进行排序
-        let schema = input_result.schema.as_ref().unwrap();
-        input_result.rows.sort_by(|a, b| {
-            for order_expr in &order_exprs {
-                let a_value = self.evaluate_expression_for_tuple(&order_expr.expr, a, schema)
-                    .unwrap_or(Value::Null);
-                let b_value = self.evaluate_expression_for_tuple(&order_expr.expr, b, schema)
-                    .unwrap_or(Value::Null);
-                
-                let cmp = self.compare_values_for_sort(&a_value, &b_value);
-                match cmp {
-                    std::cmp::Ordering::Equal => continue,
-                    other => {
-                        return if order_expr.desc {
-                            other.reverse()
+    /// 简单表达式求值（仅支持字面量）
+    fn evaluate_expression(&self, expr: &crate::sql::parser::Expression, expected_type: &DataType) -> Result<Value, ExecutionError> {
+        use crate::sql::parser::Expression;
+        
+        match expr {
+            Expression::Literal(value) => {
+                // Validate the value type matches expected type (with flexible conversion)
+                match (value, expected_type) {
+                    (Value::Integer(_), DataType::Integer) => Ok(value.clone()),
+                    (Value::BigInt(_), DataType::BigInt) => Ok(value.clone()),
+                    (Value::Float(_), DataType::Float) => Ok(value.clone()),
+                    (Value::Double(d), DataType::Float) => Ok(Value::Float(*d as f32)), // Convert Double to Float
+                    (Value::Float(f), DataType::Double) => Ok(Value::Double(*f as f64)), // Convert Float to Double
+                    (Value::Double(_), DataType::Double) => Ok(value.clone()),
+                    (Value::Varchar(s), DataType::Varchar(max)) => self.fit_string_length(s, *max).map(Value::Varchar),
+                    (Value::Varchar(_), DataType::Text) => Ok(value.clone()),
+                    // CHAR 列在末尾补空格到声明的长度
+                    (Value::Varchar(s), DataType::Char(len)) => Value::Varchar(self.fit_string_length(s, *len)?)
+                        .cast_to(expected_type)
+                        .map_err(|e| ExecutionError::EvaluationError { message: e.to_string() }),
+                    (Value::Blob(_), DataType::Blob) => Ok(value.clone()),
+                    (Value::Uuid(_), DataType::Uuid) => Ok(value.clone()),
+                    // UUID 写作字符串常量
+                    (Value::Varchar(s), DataType::Uuid) => s.parse().map(Value::Uuid).map_err(|_| ExecutionError::TypeMismatch {
+                        expected: "UUID".to_string(),
+                        actual: format!("'{}'", s),
+                    }),
+                    (Value::Json(_), DataType::Json) => Ok(value.clone()),
+                    // JSON 文档写作字符串常量，写入前校验
+                    (Value::Varchar(s), DataType::Json) => crate::types::JsonValue::normalize(s)
+                        .map(Value::Json)
+                        .map_err(|e| ExecutionError::EvaluationError { message: e.to_string() }),
+                    (Value::Boolean(_), DataType::Boolean) => Ok(value.clone()),
+                    #[cfg(feature = "chrono")]
+                    (Value::Date(_), DataType::Date) => Ok(value.clone()),
+                    #[cfg(feature = "chrono")]
+                    (Value::Timestamp(_), DataType::Timestamp) => Ok(value.clone()),
+                    // 日期和时间戳可以写作字符串常量；DATE 与 TIMESTAMP 之间按 CAST 的规则转换
+                    #[cfg(feature = "chrono")]
+                    (Value::Varchar(_) | Value::Date(_) | Value::Timestamp(_), DataType::Date | DataType::Timestamp) => {
+                        value.cast_to(expected_type).map_err(|_| ExecutionError::TypeMismatch {
+                            expected: expected_type.to_string(),
+                            actual: match value {
+                                Value::Varchar(s) => format!("'{}'", s),
+                                other => other.to_string(),
+                            },
+                        })
+                    }
+                    (Value::Null, _) => Ok(Value::Null),
+                    // DECIMAL 列按声明的精度和小数位数舍入，整数部分放不下时报错
+                    (_, DataType::Decimal(..)) | (Value::Decimal(_), DataType::Float | DataType::Double) => {
+                        value.cast_to(expected_type).map_err(|e| ExecutionError::EvaluationError { message: e.to_string() })
+                    }
+                    // Allow integer to bigint conversion
+                    (Value::Integer(i), DataType::BigInt) => Ok(Value::BigInt(*i as i64)),
+                    (Value::BigInt(i), DataType::Integer) => {
+                        if *i >= i32::MIN as i64 && *i <= i32::MAX as i64 {
+                            Ok(Value::Integer(*i as i32))
                         } else {
-                            other
-                        };
+                            Err(ExecutionError::TypeMismatch {
+                                expected: "INTEGER (in range)".to_string(),
+                                actual: format!("BIGINT({})", i),
+                            })
+                        }
                     }
+                    _ => Err(ExecutionError::TypeMismatch {
+                        expected: format!("{:?}", expected_type),
+                        actual: format!("{:?}", value),
+                    })
                 }
             }
-            std::cmp::Ordering::Equal
-        });
-        
-        Ok(input_result)
+            Expression::Default => Err(ExecutionError::EvaluationError {
+                message: "DEFAULT is only allowed as an INSERT value".to_string(),
+            }),
+            _ => {
+                // Fold constant expressions such as -1 or 2 * 60
+                let value = self.evaluate_constant_expression(expr)?;
+                self.evaluate_expression(&Expression::Literal(value), expected_type)
+            }
+        }
     }
     
-    /// 应用 LIMIT 和 OFFSET
-    fn apply_limit_offset(
-        &self,
-        mut input_result: QueryResult,
-        limit: u64,
-        offset: u64,
-    ) -> Result<QueryResult, ExecutionError> {
-        let start_index = offset as usize;
-        let end_index = if limit == u64::MAX {
-            input_result.rows.len()
-        } else {
-            std::cmp::min(start_index + (limit as usize), input_result.rows.len())
-        };
-        
-        if start_index >= input_result.rows.len() {
-            input_result.rows = Vec::new();
-        } else {
-            input_result.rows = input_result.rows[start_index..end_index].to_vec();
+    /// 检查写入 VARCHAR(n) / CHAR(n) 列的字符串长度（按字符计）
+    ///
+    /// 超出部分只有空格时去掉；否则报 StringTooLong，开启 truncate_strings 时截断到 max 个字符。
+    fn fit_string_length(&self, text: &str, max: usize) -> Result<String, ExecutionError> {
+        let actual = text.chars().count();
+        if actual <= max {
+            return Ok(text.to_string());
         }
+        if self.config.truncate_strings || text.chars().skip(max).all(|c| c == ' ') {
+            return Ok(text.chars().take(max).collect());
+        }
+        Err(ExecutionError::EvaluationError {
+            message: crate::types::TypeError::StringTooLong { max, actual }.to_string(),
+        })
+    }
+    
+    /// 求值不引用任何列的常量表达式
+    fn evaluate_constant_expression(&self, expr: &crate::sql::parser::Expression) -> Result<Value, ExecutionError> {
+        // A single placeholder value keeps evaluate_expression_for_tuple from short-circuiting
+        let placeholder = Tuple::new(vec![Value::Null]);
+        self.evaluate_expression_for_tuple(expr, &placeholder, &Schema::new(vec![]))
+    }
+    
+    /// 评估给定行的 WHERE 条件
+    fn evaluate_where_condition(
+        &self, 
+        expr: &crate::sql::parser::Expression, 
+        row: &Tuple, 
+        schema: &Schema
+    ) -> Result<bool, ExecutionError> {
+        use crate::sql::parser::Expression;
+        use crate::sql::parser::BinaryOperator;
         
-        Ok(input_result)
+        match expr {
+            Expression::BinaryOp { left, op, right } => {
+                match op {
+                    // Logical operators: evaluate as boolean conditions first
+                    BinaryOperator::And => {
+                        let left_bool = self.evaluate_where_condition(left, row, schema)?;
+                        let right_bool = self.evaluate_where_condition(right, row, schema)?;
+                        Ok(left_bool && right_bool)
+                    }
+                    BinaryOperator::Or => {
+                        let left_bool = self.evaluate_where_condition(left, row, schema)?;
+                        let right_bool = self.evaluate_where_condition(right, row, schema)?;
+                        Ok(left_bool || right_bool)
+                    }
+                    
+                    // Comparison operators: evaluate values first then compare
+                    _ => {
+                        let left_value = self.evaluate_where_expression(left, row, schema)?;
+                        let right_value = self.evaluate_where_expression(right, row, schema)?;
+                        let (mut left_value, mut right_value) = exact_decimal_operands(left, left_value, right, right_value);
+                        if self.is_char_column(left, schema) || self.is_char_column(right, schema) {
+                            left_value = trim_char_padding(left_value);
+                            right_value = trim_char_padding(right_value);
+                        }
+                        
+                        match op {
+                            BinaryOperator::Equal => Ok(self.values_equal(&left_value, &right_value)),
+                            BinaryOperator::NotEqual => Ok(!self.values_equal(&left_value, &right_value)),
+                            BinaryOperator::LessThan => self.compare_values(&left_value, &right_value, |cmp| cmp < 0),
+                            BinaryOperator::LessEqual => self.compare_values(&left_value, &right_value, |cmp| cmp <= 0),
+                            BinaryOperator::GreaterThan => self.compare_values(&left_value, &right_value, |cmp| cmp > 0),
+                            BinaryOperator::GreaterEqual => self.compare_values(&left_value, &right_value, |cmp| cmp >= 0),
+                            
+                            _ => Err(ExecutionError::NotImplemented {
+                                feature: format!("WHERE operator: {:?}", op),
+                                capability: Capability::WherePredicates,
+                            })
+                        }
+                    }
+                }
+            }
+            Expression::Column(col_name) => {
+                // Column reference in WHERE should be evaluated as boolean
+                let value = self.evaluate_where_expression(expr, row, schema)?;
+                match value {
+                    Value::Boolean(b) => Ok(b),
+                    Value::Null => Ok(false),
+                    _ => Ok(true), // Non-null, non-boolean values are truthy
+                }
+            }
+            Expression::Literal(Value::Boolean(b)) => Ok(*b),
+            Expression::IsNull(operand) => Ok(self.evaluate_where_expression(operand, row, schema)?.is_null()),
+            Expression::IsNotNull(operand) => Ok(!self.evaluate_where_expression(operand, row, schema)?.is_null()),
+            Expression::Like { expr: operand, pattern, escape, negated } => {
+                let mut value = self.evaluate_where_expression(operand, row, schema)?;
+                if self.is_char_column(operand, schema) {
+                    value = trim_char_padding(value);
+                }
+                let pattern = self.evaluate_where_expression(pattern, row, schema)?;
+                let escape = match escape {
+                    Some(escape) => match self.evaluate_where_expression(escape, row, schema)? {
+                        Value::Varchar(s) if s.chars().count() == 1 => s.chars().next(),
+                        Value::Null => return Ok(false),
+                        other => return Err(ExecutionError::EvaluationError {
+                            message: format!("ESCAPE must be a single character, got {:?}", other),
+                        }),
+                    },
+                    None => None,
+                };
+                match (value, pattern) {
+                    // NULL 参与的 LIKE 结果未知，NOT LIKE 同样不匹配
+                    (Value::Null, _) | (_, Value::Null) => Ok(false),
+                    (Value::Varchar(text), Value::Varchar(pattern)) => {
+                        Ok(like_match(&text, &pattern, escape)? != *negated)
+                    }
+                    (left, right) => Err(ExecutionError::TypeMismatch {
+                        expected: "VARCHAR operands for LIKE".to_string(),
+                        actual: format!("{:?} LIKE {:?}", left, right),
+                    }),
+                }
+            }
+            Expression::In { negated, .. } | Expression::Between { negated, .. } => {
+                // 结果未知（NULL）时无论是否取反都不满足条件
+                Ok(self.evaluate_membership(expr, row, schema)?
+                    .map(|matched| matched != *negated)
+                    .unwrap_or(false))
+            }
+            _ => Err(ExecutionError::NotImplemented {
+                feature: format!("WHERE expression: {:?}", expr),
+                capability: Capability::WherePredicates,
+            })
+        }
     }
     
-    /// 评估元组上下文中的表达式
-    fn evaluate_expression_for_tuple(
+    /// 按三值逻辑求值 IN / BETWEEN（不含 NOT），None 表示结果为 NULL
+    fn evaluate_membership(
         &self,
         expr: &crate::sql::parser::Expression,
-        tuple: &Tuple,
+        row: &Tuple,
         schema: &Schema,
-    ) -> Result<Value, ExecutionError> {
+    ) -> Result<Option<bool>, ExecutionError> {
         use crate::sql::parser::Expression;
-        
-        // 边界检查：确保tuple不为空
-        if tuple.values.is_empty() {
-            return Ok(Value::Null);
+
+        match expr {
+            Expression::In { expr: operand, list, .. } => {
+                let char_operand = self.is_char_column(operand, schema);
+                let mut value = self.evaluate_where_expression(operand, row, schema)?;
+                if value.is_null() {
+                    return Ok(None);
+                }
+                if char_operand {
+                    value = trim_char_padding(value);
+                }
+                let mut saw_null = false;
+                for item in list {
+                    let mut item = self.evaluate_where_expression(item, row, schema)?;
+                    if char_operand {
+                        item = trim_char_padding(item);
+                    }
+                    if item.is_null() {
+                        saw_null = true;
+                    } else if self.compare_values(&value, &item, |cmp| cmp == 0)? {
+                        return Ok(Some(true));
+                    }
+                }
+                // x IN (..., NULL) 没有命中时结果未知
+                Ok(if saw_null { None } else { Some(false) })
+            }
+            Expression::Between { expr: operand, low, high, .. } => {
+                let mut value = self.evaluate_where_expression(operand, row, schema)?;
+                if value.is_null() {
+                    return Ok(None);
+                }
+                let mut low = self.evaluate_where_expression(low, row, schema)?;
+                let mut high = self.evaluate_where_expression(high, row, schema)?;
+                if self.is_char_column(operand, schema) {
+                    value = trim_char_padding(value);
+                    low = trim_char_padding(low);
+                    high = trim_char_padding(high);
+                }
+                let above_low = if low.is_null() {
+                    None
+                } else {
+                    Some(self.compare_values(&value, &low, |cmp| cmp >= 0)?)
+                };
+                let below_high = if high.is_null() {
+                    None
+                } else {
+                    Some(self.compare_values(&value, &high, |cmp| cmp <= 0)?)
+                };
+                Ok(match (above_low, below_high) {
+                    (Some(false), _) | (_, Some(false)) => Some(false),
+                    (Some(true), Some(true)) => Some(true),
+                    _ => None,
+                })
+            }
+            _ => Err(ExecutionError::NotImplemented {
+                feature: format!("membership predicate: {:?}", expr),
+                capability: Capability::WherePredicates,
+            })
         }
+    }
+    
+    /// 在 WHERE 上下文中求值表达式（返回 Value）
+    fn evaluate_where_expression(
+        &self, 
+        expr: &crate::sql::parser::Expression, 
+        row: &Tuple, 
+        schema: &Schema
+    ) -> Result<Value, ExecutionError> {
+        use crate::sql::parser::Expression;
         
         match expr {
             Expression::Literal(value) => Ok(value.clone()),
             Expression::Column(col_name) => {
-                // 增强错误处理：检查列名有效性
-                if col_name.is_empty() {
-                    return Err(ExecutionError::EvaluationError {
-                        message: "Empty column name in expression".to_string(),
-                    });
-                }
-                
-                let col_index = schema.columns.iter()
-                    .position(|col| col.name == *col_name)
+                // Find column index
+                let col_index = column_index(schema, None, col_name)
                     .ok_or_else(|| ExecutionError::ColumnNotFound {
-                        table: "current".to_string(),
+                        table: "current".to_string(), // We don't have table name in this context
                         column: col_name.clone(),
                     })?;
                 
-                // 边界检查：确保索引有效
-                if col_index >= tuple.values.len() {
-                    return Err(ExecutionError::EvaluationError {
-                        message: format!("Column index {} out of bounds for tuple with {} values", 
-                                       col_index, tuple.values.len()),
-                    });
-                }
-                
-                Ok(tuple.values[col_index].clone())
+                Ok(row.values[col_index].clone())
             }
-            Expression::QualifiedColumn { table, column } => {
-                // 增强错误处理：检查表名和列名
-                if column.is_empty() {
-                    return Err(ExecutionError::EvaluationError {
-                        message: format!("Empty column name in qualified expression for table {}", table),
-                    });
-                }
-                
-                // 优化的表别名解析：支持多种匹配策略
-                let col_index = self.resolve_qualified_column_index(table, column, schema)?;
-                
-                // 边界检查：确保索引有效
-                if col_index >= tuple.values.len() {
-                    return Err(ExecutionError::EvaluationError {
-                        message: format!("Column index {} out of bounds for tuple with {} values", 
-                                       col_index, tuple.values.len()),
+            Expression::QualifiedColumn { .. }
+            | Expression::BinaryOp { .. }
+            | Expression::UnaryOp { .. }
+            | Expression::FunctionCall { .. }
+            | Expression::Cast { .. }
+            | Expression::Parameter(_) => self.evaluate_expression_for_tuple(expr, row, schema),
+            _ => Err(ExecutionError::NotImplemented {
+                feature: format!("WHERE expression evaluation: {:?}", expr),
+                capability: Capability::WherePredicates,
+            })
+        }
+    }
+    
+    /// 表达式是否直接引用 CHAR 列；与 CHAR 列比较时两边的字符串都忽略尾部空格
+    fn is_char_column(&self, expr: &crate::sql::parser::Expression, schema: &Schema) -> bool {
+        use crate::sql::parser::Expression;
+
+        let index = match expr {
+            Expression::Column(name) => column_index(schema, None, name),
+            Expression::QualifiedColumn { table, column } => self.resolve_qualified_column_index(table, column, schema).ok(),
+            _ => None,
+        };
+        index.is_some_and(|i| matches!(schema.columns[i].data_type, DataType::Char(_)))
+    }
+    
+    /// 判断两个值是否相等，不同宽度的数值类型按数值比较
+    fn values_equal(&self, left: &Value, right: &Value) -> bool {
+        let is_number = |v: &Value| matches!(v, Value::Integer(_) | Value::BigInt(_) | Value::Float(_) | Value::Double(_) | Value::Decimal(_));
+        let is_temporal = |v: &Value| matches!(v.data_type(), DataType::Date | DataType::Timestamp);
+        if is_number(left) && is_number(right) && std::mem::discriminant(left) != std::mem::discriminant(right) {
+            self.compare_values(left, right, |cmp| cmp == 0).unwrap_or(false)
+        } else if let (Value::Uuid(u), Value::Varchar(s)) | (Value::Varchar(s), Value::Uuid(u)) = (left, right) {
+            s.parse::<crate::types::Uuid>().is_ok_and(|parsed| parsed == *u)
+        } else if (is_temporal(left) && matches!(right, Value::Varchar(_))) || (matches!(left, Value::Varchar(_)) && is_temporal(right)) {
+            self.compare_values(left, right, |cmp| cmp == 0).unwrap_or(false)
+        } else if let (Value::Json(doc), Value::Varchar(s)) | (Value::Varchar(s), Value::Json(doc)) = (left, right) {
+            // 与字符串常量按紧凑形式比较，空白不同的同一文档相等
+            crate::types::JsonValue::normalize(s).is_ok_and(|normalized| normalized == *doc)
+        } else {
+            left == right
+        }
+    }
+    
+    /// 比较两个值的顺序（返回排序比较结果）
+    fn compare_values<F>(&self, left: &Value, right: &Value, pred: F) -> Result<bool, ExecutionError>
+    where 
+        F: Fn(i32) -> bool
+    {
+        use std::cmp::Ordering;
+        
+        let cmp_result = match (left, right) {
+            (Value::Integer(a), Value::Integer(b)) => a.cmp(b),
+            (Value::BigInt(a), Value::BigInt(b)) => a.cmp(b),
+            (Value::Float(a), Value::Float(b)) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
+            (Value::Double(a), Value::Double(b)) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
+            (Value::Varchar(a), Value::Varchar(b)) => a.cmp(b),
+            (Value::Blob(a), Value::Blob(b)) => a.cmp(b),
+            (Value::Uuid(a), Value::Uuid(b)) => a.cmp(b),
+            (Value::Json(a), Value::Json(b)) => a.cmp(b),
+            // UUID 与字符串常量比较时先解析字符串
+            (Value::Uuid(a), Value::Varchar(s)) => a.cmp(&Self::parse_uuid(s)?),
+            (Value::Varchar(s), Value::Uuid(b)) => Self::parse_uuid(s)?.cmp(b),
+            (Value::Boolean(a), Value::Boolean(b)) => a.cmp(b),
+            #[cfg(feature = "chrono")]
+            (Value::Date(_) | Value::Timestamp(_), Value::Date(_) | Value::Timestamp(_)) => {
+                functions::as_timestamp(left).cmp(&functions::as_timestamp(right))
+            }
+            // 与字符串常量比较时先把字符串解析为时间戳
+            #[cfg(feature = "chrono")]
+            (Value::Date(_) | Value::Timestamp(_), Value::Varchar(s)) => {
+                functions::as_timestamp(left).cmp(&functions::as_timestamp(&Self::parse_timestamp(s)?))
+            }
+            #[cfg(feature = "chrono")]
+            (Value::Varchar(s), Value::Date(_) | Value::Timestamp(_)) => {
+                functions::as_timestamp(&Self::parse_timestamp(s)?).cmp(&functions::as_timestamp(right))
+            }
+            // Type coercion for numbers
+            (Value::Integer(a), Value::BigInt(b)) => (*a as i64).cmp(b),
+            (Value::BigInt(a), Value::Integer(b)) => a.cmp(&(*b as i64)),
+            (Value::Float(a), Value::Double(b)) => (*a as f64).partial_cmp(b).unwrap_or(Ordering::Equal),
+            (Value::Double(a), Value::Float(b)) => a.partial_cmp(&(*b as f64)).unwrap_or(Ordering::Equal),
+            (Value::Integer(a), Value::Float(b)) => (*a as f32).partial_cmp(b).unwrap_or(Ordering::Equal),
+            (Value::Float(a), Value::Integer(b)) => a.partial_cmp(&(*b as f32)).unwrap_or(Ordering::Equal),
+            (Value::Integer(a), Value::Double(b)) => (*a as f64).partial_cmp(b).unwrap_or(Ordering::Equal),
+            (Value::Double(a), Value::Integer(b)) => a.partial_cmp(&(*b as f64)).unwrap_or(Ordering::Equal),
+            // DECIMAL 与整数精确比较，与浮点数按浮点数比较
+            (Value::Decimal(_), Value::Integer(_) | Value::BigInt(_) | Value::Float(_) | Value::Double(_) | Value::Decimal(_))
+            | (Value::Integer(_) | Value::BigInt(_) | Value::Float(_) | Value::Double(_), Value::Decimal(_)) => {
+                left.partial_cmp(right).unwrap_or(Ordering::Equal)
+            }
+            (Value::Null, _) | (_, Value::Null) => return Ok(false), // NULL comparisons are always false
+            _ => return Err(ExecutionError::TypeMismatch {
+                expected: format!("{:?}", left),
+                actual: format!("{:?}", right),
+            })
+        };
+        
+        let cmp_int = match cmp_result {
+            Ordering::Less => -1,
+            Ordering::Equal => 0,
+            Ordering::Greater => 1,
+        };
+        
+        Ok(pred(cmp_int))
+    }
+    
+    #[cfg(feature = "chrono")]
+    fn parse_timestamp(text: &str) -> Result<Value, ExecutionError> {
+        Value::Varchar(text.to_string()).cast_to(&DataType::Timestamp).map_err(|_| ExecutionError::TypeMismatch {
+            expected: "DATE or TIMESTAMP".to_string(),
+            actual: format!("'{}'", text),
+        })
+    }
+    
+    fn parse_uuid(text: &str) -> Result<crate::types::Uuid, ExecutionError> {
+        text.parse().map_err(|_| ExecutionError::TypeMismatch {
+            expected: "UUID".to_string(),
+            actual: format!("'{}'", text),
+        })
+    }
+    
+    /// 投影特定列（SELECT 列过滤）：列引用直接取输入行中的值，其余表达式逐行计算
+    fn project_columns<'a>(
+        &'a self,
+        input: Box<dyn Executor + 'a>,
+        select_exprs: Vec<crate::sql::parser::SelectExpr>,
+        table_name: &str,
+    ) -> Result<ProjectExecutor<'a>, ExecutionError> {
+        use crate::sql::parser::{BinaryOperator, Expression};
+        
+        let schema = input.schema().clone();
+        
+        // Build new schema with selected columns
+        let mut new_columns = Vec::new();
+        let mut column_indices = Vec::new();
+        // 函数调用的结果类型取第一个非 NULL 结果的类型
+        let mut untyped = Vec::new();
+        
+        for select_expr in &select_exprs {
+            match &select_expr.expr {
+                Expression::Column(col_name) | Expression::QualifiedColumn { column: col_name, .. } => {
+                    // Find column index in original schema
+                    let col_index = match &select_expr.expr {
+                        Expression::QualifiedColumn { table, .. } => self.resolve_qualified_column_index(table, col_name, &schema).ok(),
+                        _ => column_index(&schema, None, col_name),
+                    };
+                    let col_index = col_index.ok_or_else(|| ExecutionError::ColumnNotFound {
+                        table: table_name.to_string(),
+                        column: col_name.clone(),
+                    })?;
+                    
+                    column_indices.push(col_index);
+                    
+                    // Use alias if provided, otherwise use original column name
+                    let column_name = select_expr.alias.as_ref()
+                        .unwrap_or(col_name)
+                        .clone();
+                    
+                    let mut new_col = schema.columns[col_index].clone();
+                    new_col.name = column_name;
+                    new_columns.push(new_col);
+                }
+                Expression::FunctionCall { name, .. } if !functions::is_aggregate(name) => {
+                    let column_name = select_expr.alias.clone()
+                        .unwrap_or_else(|| format!("{}(...)", name));
+                    untyped.push(new_columns.len());
+                    new_columns.push(ColumnDefinition::new(column_name, DataType::Varchar(255), true));
+                    column_indices.push(COMPUTED_COLUMN);
+                }
+                Expression::Cast { data_type, .. } => {
+                    let column_name = select_expr.alias.clone()
+                        .unwrap_or_else(|| "CAST(...)".to_string());
+                    new_columns.push(ColumnDefinition::new(column_name, data_type.clone(), true));
+                    column_indices.push(COMPUTED_COLUMN);
+                }
+                Expression::BinaryOp { op: op @ (BinaryOperator::JsonExtract | BinaryOperator::JsonExtractText), .. } => {
+                    let column_name = select_expr.alias.clone()
+                        .unwrap_or_else(|| dump::expression_sql(&select_expr.expr));
+                    let data_type = if *op == BinaryOperator::JsonExtract { DataType::Json } else { DataType::Text };
+                    new_columns.push(ColumnDefinition::new(column_name, data_type, true));
+                    column_indices.push(COMPUTED_COLUMN);
+                }
+                Expression::FunctionCall { name, args } => {
+                    // 聚合函数调用 (e.g., COUNT(*), AVG(age)) 由聚合阶段计算，这里只输出 NULL
+                    let column_name = select_expr.alias.as_ref()
+                        .unwrap_or(&format!("{}(...)", name))
+                        .clone();
+                    let data_type = self.aggregate_column_type(name, args, &[], &schema);
+                    new_columns.push(ColumnDefinition::new(column_name, data_type, true));
+                    column_indices.push(usize::MAX);
+                }
+                Expression::Literal(_) => {
+                    // Literal values in SELECT (e.g., SELECT 1, 'hello')
+                    return Err(ExecutionError::NotImplemented {
+                        feature: "Literal expressions in SELECT".to_string(),
+                        capability: Capability::SelectExpressions,
+                    });
+                }
+                _ => {
+                    return Err(ExecutionError::NotImplemented {
+                        feature: format!("Complex expressions in SELECT: {:?}", select_expr.expr),
+                        capability: Capability::SelectExpressions,
                     });
                 }
-                
-                Ok(tuple.values[col_index].clone())
             }
-            Expression::BinaryOp { left, op, right } => {
-                // 支持算术运算表达式
-                let left_val = self.evaluate_expression_for_tuple(left, tuple, schema)?;
-                let right_val = self.evaluate_expression_for_tuple(right, tuple, schema)?;
-                
-                use crate::sql::parser::BinaryOperator;
-                match op {
-                    BinaryOperator::Add => {
-                        match (left_val, right_val) {
-                            (Value::Integer(a), Value::Integer(b)) => Ok(Value::Integer(a + b)),
-                            (Value::Float(a), Value::Float(b)) => Ok(Value::Float(a + b)),
-                            (Value::Double(a), Value::Double(b)) => Ok(Value::Double(a + b)),
-                            (Value::Integer(a), Value::Double(b)) => Ok(Value::Double(a as f64 + b)),
-                            (Value::Double(a), Value::Integer(b)) => Ok(Value::Double(a + b as f64)),
-                            _ => Err(ExecutionError::EvaluationError {
-                                message: "Cannot add non-numeric values".to_string(),
-                            })
-                        }
+        }
+        
+        // Create new schema
+        let new_schema = Schema {
+            columns: new_columns,
+            primary_key: None, // Projected query results don't have primary key
+        };
+        
+        // Project each row to the selected columns
+        let project = move |row: Tuple| {
+            let values = column_indices.iter()
+                .zip(&select_exprs)
+                .map(|(&idx, select_expr)| {
+                    if idx == COMPUTED_COLUMN {
+                        self.evaluate_expression_for_tuple(&select_expr.expr, &row, &schema)
+                    } else if idx == usize::MAX {
+                        Ok(Value::Null)
+                    } else {
+                        Ok(row.values[idx].clone())
                     }
-                    BinaryOperator::Subtract => {
-                        match (left_val, right_val) {
-                            (Value::Integer(a), Value::Integer(b)) => Ok(Value::Integer(a - b)),
-                            (Value::Float(a), Value::Float(b)) => Ok(Value::Float(a - b)),
-                            (Value::Double(a), Value::Double(b)) => Ok(Value::Double(a - b)),
-                            (Value::Integer(a), Value::Double(b)) => Ok(Value::Double(a as f64 - b)),
-                            (Value::Double(a), Value::Integer(b)) => Ok(Value::Double(a - b as f64)),
-                            _ => Err(ExecutionError::EvaluationError {
-                                message: "Cannot subtract non-numeric values".to_string(),
-                            })
-                        }
+                })
+                .collect::<Result<_, ExecutionError>>()?;
+            Ok(Tuple { values })
+        };
+        
+        Ok(ProjectExecutor::new(input, new_schema, Box::new(project)).with_inferred_types(untyped))
+    }
+    
+    /// 逐行计算的投影列；类型取第一个非 NULL 结果的类型
+    fn computed_column(
+        &self,
+        name: String,
+        expr: &crate::sql::parser::Expression,
+        rows: &[Tuple],
+        schema: &Schema,
+    ) -> ColumnDefinition {
+        let data_type = rows.iter()
+            .filter_map(|row| self.evaluate_expression_for_tuple(expr, row, schema).ok())
+            .find(|value| !value.is_null())
+            .map(|value| match value {
+                Value::Varchar(_) => DataType::Varchar(255),
+                value => value.data_type(),
+            })
+            .unwrap_or(DataType::Varchar(255));
+        ColumnDefinition {
+            name,
+            data_type,
+            nullable: true,
+            default: None,
+            default_function: None,
+        }
+    }
+    
+    /// 聚合列的类型：COUNT 为 Integer，MAX/MIN 与参数同类型，字符串拼接为 Varchar，
+    /// DECIMAL 参数的 SUM/AVG 为 DECIMAL，其余为 Double
+    fn aggregate_column_type(
+        &self,
+        name: &str,
+        args: &[crate::sql::parser::Expression],
+        rows: &[Tuple],
+        schema: &Schema,
+    ) -> DataType {
+        use crate::sql::parser::Expression;
+        
+        match name.to_uppercase().as_str() {
+            "COUNT" => DataType::Integer,
+            "STRING_AGG" | "GROUP_CONCAT" => DataType::Varchar(255),
+            // DECIMAL 参数的 SUM / AVG 保持 DECIMAL
+            name @ ("SUM" | "AVG") => match args.first().map(|arg| self.computed_column(String::new(), arg, rows, schema).data_type) {
+                Some(DataType::Decimal(_, scale)) if name == "SUM" => DataType::Decimal(decimal::MAX_PRECISION, scale),
+                Some(DataType::Decimal(_, scale)) => DataType::Decimal(decimal::MAX_PRECISION, Decimal::quotient_scale(scale, 0)),
+                _ => DataType::Double,
+            },
+            "MAX" | "MIN" => match args.first() {
+                Some(Expression::Column(column)) => column_index(schema, None, column)
+                    .map(|index| schema.columns[index].data_type.clone())
+                    .unwrap_or(DataType::Double),
+                Some(arg) => self.computed_column(String::new(), arg, rows, schema).data_type,
+                None => DataType::Double,
+            },
+            _ => DataType::Double,
+        }
+    }
+    
+    /// 表的全部行；行保存在工作堆文件中时经缓冲池读取
+    fn table_rows(&self, table_id: u32) -> Result<std::borrow::Cow<'_, [Tuple]>, ExecutionError> {
+        self.table_data.get(&table_id)
+            .ok_or_else(|| ExecutionError::TableNotFound { table: format!("table_id_{}", table_id) })?
+            .rows(&self.buffer_pool)
+    }
+    
+    /// 为表创建保存给定行的存储（有数据目录时写入新的工作堆文件）
+    fn new_table_rows(&self, table_id: u32, rows: Vec<Tuple>) -> Result<TableRows, ExecutionError> {
+        TableRows::new(self.work_files.as_ref(), &self.buffer_pool, table_id, rows)
+    }
+    
+    /// 丢弃表的行、内存中的索引结构及其工作堆文件
+    fn remove_table_rows(&mut self, table_id: u32) -> Result<(), ExecutionError> {
+        self.table_data.remove(&table_id);
+        self.row_indexes.get_mut().unwrap_or_else(|e| e.into_inner()).retain(|(id, _, _), _| *id != table_id);
+        match &self.work_files {
+            Some(work_files) => TableRows::remove_file(work_files, &self.buffer_pool, table_id),
+            None => Ok(()),
+        }
+    }
+    
+    /// 解析 SELECT 的数据源：用户表或系统视图（系统视图没有表ID）
+    fn scan_source(&self, table_name: &str) -> Result<ScanSource<'_>, ExecutionError> {
+        if let Some(&table_id) = self.table_catalog.get(table_name) {
+            let schema = self.table_schemas.get(&table_id)
+                .ok_or_else(|| ExecutionError::TableNotFound { table: table_name.to_string() })?;
+            let rows = self.table_data.get(&table_id)
+                .ok_or_else(|| ExecutionError::TableNotFound { table: table_name.to_string() })?
+                .scan(&self.buffer_pool);
+            return Ok((Some(table_id), std::borrow::Cow::Borrowed(schema), rows));
+        }
+        
+        if let Some((schema, rows)) = self.system_view(table_name) {
+            return Ok((None, std::borrow::Cow::Owned(schema), ScanRows::Rows(std::borrow::Cow::Owned(rows))));
+        }
+        
+        // 临时视图每次引用时重新执行，结果反映底层表的当前数据
+        match self.temp_views.get(table_name) {
+            Some(view) => {
+                let result = self.execute_view_query(&view.query)?;
+                let schema = result.schema.unwrap_or_else(|| view.schema.clone());
+                Ok((None, std::borrow::Cow::Owned(schema), ScanRows::Rows(std::borrow::Cow::Owned(result.rows))))
+            }
+            None => Err(ExecutionError::TableNotFound { table: table_name.to_string() }),
+        }
+    }
+    
+    /// 执行视图定义中的 SELECT
+    fn execute_view_query(&self, query: &Statement) -> Result<QueryResult, ExecutionError> {
+        match query {
+            Statement::Select { .. } => self.execute_select_complete(query.clone()),
+            _ => Err(ExecutionError::SemanticError("A view must be defined by a SELECT statement".to_string())),
+        }
+    }
+    
+    /// ASSERT：执行查询并检查条件，不满足时返回 AssertionFailed
+    ///
+    /// 失败时附带最多 [`ASSERT_SAMPLE_ROWS`] 行样本：NOT EXISTS 取查询返回的行，
+    /// 比较形式取查询的 FROM / WHERE 匹配的行（查询没有 WHERE 或带 GROUP BY 时不取样本）。
+    fn execute_assert(
+        &mut self,
+        query: Statement,
+        condition: crate::sql::parser::AssertCondition,
+        message: Option<String>,
+    ) -> Result<QueryResult, ExecutionError> {
+        use crate::sql::parser::{AssertCondition, BinaryOperator, Expression, SelectList};
+        
+        let result = self.execute_view_query(&query)?;
+        let (passed, actual, expectation) = match condition {
+            AssertCondition::Exists { negated } => {
+                let actual = format!("{} row(s)", result.rows.len());
+                let expectation = if negated { "no rows" } else { "at least one row" };
+                (result.rows.is_empty() == negated, actual, expectation.to_string())
+            }
+            AssertCondition::Compare { op, expected } => {
+                let (actual, column) = match (result.rows.as_slice(), result.schema) {
+                    ([], Some(schema)) if schema.columns.len() == 1 => (Value::Null, schema.columns[0].clone()),
+                    ([row], Some(schema)) if row.values.len() == 1 => (row.values[0].clone(), schema.columns[0].clone()),
+                    _ => {
+                        return Err(ExecutionError::SemanticError(
+                            "ASSERT (SELECT ...) must return a single value; use ASSERT [NOT] EXISTS to check rows".to_string(),
+                        ))
                     }
-                    BinaryOperator::Multiply => {
-                        match (left_val, right_val) {
-                            (Value::Integer(a), Value::Integer(b)) => Ok(Value::Integer(a * b)),
-                            (Value::Float(a), Value::Float(b)) => Ok(Value::Float(a * b)),
-                            (Value::Double(a), Value::Double(b)) => Ok(Value::Double(a * b)),
-                            (Value::Integer(a), Value::Double(b)) => Ok(Value::Double(a as f64 * b)),
-                            (Value::Double(a), Value::Integer(b)) => Ok(Value::Double(a * b as f64)),
-                            _ => Err(ExecutionError::EvaluationError {
-                                message: "Cannot multiply non-numeric values".to_string(),
-                            })
-                        }
+                };
+                // 把查询结果作为单列单行的输入，复用 WHERE 的比较语义
+                let schema = Schema::new(vec![column]);
+                let row = Tuple::new(vec![actual.clone()]);
+                let symbol = match op {
+                    BinaryOperator::Equal => "=",
+                    BinaryOperator::NotEqual => "<>",
+                    BinaryOperator::LessThan => "<",
+                    BinaryOperator::LessEqual => "<=",
+                    BinaryOperator::GreaterThan => ">",
+                    BinaryOperator::GreaterEqual => ">=",
+                    _ => "?",
+                };
+                let expectation = format!("{} {}", symbol, self.evaluate_where_expression(&expected, &row, &schema)?);
+                let check = Expression::BinaryOp {
+                    left: Box::new(Expression::Column(schema.columns[0].name.clone())),
+                    op,
+                    right: Box::new(expected),
+                };
+                (self.evaluate_where_condition(&check, &row, &schema)?, actual.to_string(), expectation)
+            }
+        };
+        
+        if passed {
+            return Ok(Self::message_result(format!("ASSERT passed (got {}, expected {})", actual, expectation)));
+        }
+        
+        let samples = match (&query, result.rows.is_empty()) {
+            (_, false) if actual.ends_with("row(s)") => result.rows,
+            (Statement::Select { from_clause: Some(from), where_clause: Some(where_clause), group_by: None, .. }, _) => {
+                let sample_query = Statement::Select {
+                    select_list: SelectList::Wildcard,
+                    from_clause: Some(from.clone()),
+                    where_clause: Some(where_clause.clone()),
+                    group_by: None,
+                    having: None,
+                    order_by: None,
+                    limit: Some(ASSERT_SAMPLE_ROWS as u64),
+                    offset: None,
+                };
+                self.execute_view_query(&sample_query).map(|result| result.rows).unwrap_or_default()
+            }
+            _ => Vec::new(),
+        };
+        Err(ExecutionError::AssertionFailed {
+            message: message.unwrap_or_else(|| format!("expected {}", expectation)),
+            actual,
+            samples: samples.into_iter().take(ASSERT_SAMPLE_ROWS).collect(),
+        })
+    }
+    
+    /// CREATE TEMP VIEW：先执行一次查询以校验定义并记录结果模式
+    ///
+    /// 视图只在本次会话中存在；视图名不能与表、系统视图或其他临时视图重名，
+    /// 查询中引用的视图必须已经存在，因此视图之间不会形成循环。
+    fn execute_create_view(&mut self, view_name: String, query: Statement, temporary: bool) -> Result<QueryResult, ExecutionError> {
+        if !temporary {
+            return Err(ExecutionError::NotImplemented {
+                feature: "persistent views (use CREATE TEMP VIEW)".to_string(),
+                capability: Capability::Views,
+            });
+        }
+        if self.table_catalog.contains_key(&view_name) || Self::system_view_schema(&view_name).is_some() {
+            return Err(ExecutionError::TableAlreadyExists { table: view_name });
+        }
+        if self.temp_views.contains_key(&view_name) {
+            return Err(ExecutionError::SemanticError(format!("View '{}' already exists", view_name)));
+        }
+        
+        let schema = self.execute_view_query(&query)?.schema
+            .ok_or_else(|| ExecutionError::SemanticError("View query has no result columns".to_string()))?;
+        self.temp_views.insert(view_name.clone(), TempView { query, schema });
+        Ok(Self::message_result(format!("Temporary view '{}' created", view_name)))
+    }
+    
+    /// DROP VIEW
+    fn execute_drop_view(&mut self, view_name: String, if_exists: bool) -> Result<QueryResult, ExecutionError> {
+        if self.temp_views.remove(&view_name).is_some() {
+            Ok(Self::message_result(format!("View '{}' dropped", view_name)))
+        } else if if_exists {
+            Ok(Self::message_result(format!("View '{}' does not exist, skipping", view_name)))
+        } else {
+            Err(ExecutionError::SemanticError(format!("View '{}' does not exist", view_name)))
+        }
+    }
+    
+    /// 执行具有完整功能支持的 SELECT 语句（ORDER BY、GROUP BY、LIMIT 等），拉取全部结果行
+    fn execute_select_complete(&self, query: Statement) -> Result<QueryResult, ExecutionError> {
+        let mut plan = self.select_plan(query)?;
+        
+        // 从根算子逐行拉取结果；输出模式可能随行确定，读完之后再取
+        let mut rows = Vec::new();
+        while let Some(row) = plan.root.next()? {
+            rows.push(row);
+        }
+        let result_schema = plan.root.schema().clone();
+        
+        let (affected_rows, message) = if plan.is_aggregate {
+            (rows.len(), format!("📊 GROUP BY 查询完成，返回 {} 行聚合结果", rows.len()))
+        } else {
+            (0, format!("Retrieved {} row(s) from table '{}' (total: {})", rows.len(), plan.table_name, plan.total_rows))
+        };
+        Ok(QueryResult {
+            rows,
+            schema: Some(result_schema),
+            affected_rows,
+            message,
+        })
+    }
+    
+    /// 由规划器为 SELECT 构建算子树（见 [`QueryPlanner::build_select`](crate::sql::QueryPlanner::build_select)）
+    fn select_plan(&self, query: Statement) -> Result<SelectOperators<'_>, ExecutionError> {
+        crate::sql::QueryPlanner::new().build_select(query, self)
+    }
+    
+    /// 对已过滤的行应用 GROUP BY 分组聚合 (支持聚合函数)，返回结果行及其模式
+    fn apply_group_by_with_select(
+        &self,
+        rows: Vec<Tuple>,
+        original_schema: &Schema,
+        group_exprs: &[crate::sql::parser::Expression],
+        select_list: &crate::sql::parser::SelectList,
+    ) -> Result<(Vec<Tuple>, Schema), ExecutionError> {
+        use std::collections::HashMap;
+        use crate::sql::parser::{Expression, SelectList};
+        
+        // 解析 SELECT 子句中的表达式
+        let select_expressions = match select_list {
+            SelectList::Expressions(exprs) => exprs,
+            SelectList::Wildcard => {
+                return Err(ExecutionError::NotImplemented {
+                    feature: "GROUP BY with SELECT * not supported".to_string(),
+                    capability: Capability::GroupBy,
+                });
+            }
+        };
+        
+        // GROUP BY n 按位置引用 SELECT 列表中的第 n 个表达式
+        let group_exprs = group_exprs.iter()
+            .map(|expr| match expr {
+                &Expression::Literal(Value::Integer(position)) => {
+                    let select_expr = usize::try_from(position).ok()
+                        .and_then(|position| position.checked_sub(1))
+                        .and_then(|index| select_expressions.get(index))
+                        .ok_or_else(|| ExecutionError::SemanticError(
+                            format!("GROUP BY position {} is not in select list", position)
+                        ))?;
+                    if self.expression_contains_aggregates(&select_expr.expr) {
+                        return Err(ExecutionError::SemanticError(
+                            format!("GROUP BY position {} refers to an aggregate function", position)
+                        ));
                     }
-                    BinaryOperator::Divide => {
-                        match (left_val, right_val) {
-                            (Value::Integer(a), Value::Integer(b)) => {
-                                if b == 0 {
-                                    Err(ExecutionError::EvaluationError {
-                                        message: "Division by zero".to_string(),
-                                    })
-                                } else {
-                                    Ok(Value::Double(a as f64 / b as f64))
-                                }
-                            }
-                            (Value::Float(a), Value::Float(b)) => {
-                                if b == 0.0 {
-                                    Err(ExecutionError::EvaluationError {
-                                        message: "Division by zero".to_string(),
-                                    })
-                                } else {
-                                    Ok(Value::Float(a / b))
-                                }
-                            }
-                            (Value::Double(a), Value::Double(b)) => {
-                                if b == 0.0 {
-                                    Err(ExecutionError::EvaluationError {
-                                        message: "Division by zero".to_string(),
-                                    })
-                                } else {
-                                    Ok(Value::Double(a / b))
-                                }
-                            }
-                            (Value::Integer(a), Value::Double(b)) => {
-                                if b == 0.0 {
-                                    Err(ExecutionError::EvaluationError {
-                                        message: "Division by zero".to_string(),
-                                    })
-                                } else {
-                                    Ok(Value::Double(a as f64 / b))
-                                }
-                            }
-                            (Value::Double(a), Value::Integer(b)) => {
-                                if b == 0 {
-                                    Err(ExecutionError::EvaluationError {
-                                        message: "Division by zero".to_string(),
-                                    })
-                                } else {
-                                    Ok(Value::Double(a / b as f64))
-                                }
+                    Ok(select_expr.expr.clone())
+                }
+                expr => Ok(expr.clone()),
+            })
+            .collect::<Result<Vec<_>, ExecutionError>>()?;
+        
+        // 每个 SELECT 表达式的取值方式：与某个分组键相同的取分组键，聚合函数按组计算，
+        // 其余表达式只能引用作为分组键的列，在组内任一行上求值
+        enum GroupOutput {
+            Key(usize),
+            Aggregate,
+            PerGroup,
+        }
+        let mut outputs = Vec::new();
+        for select_expr in select_expressions {
+            let expr = &select_expr.expr;
+            if let Some(i) = group_exprs.iter().position(|group_expr| same_expression(group_expr, expr)) {
+                outputs.push(GroupOutput::Key(i));
+            } else if matches!(expr, Expression::FunctionCall { name, .. } if functions::is_aggregate(name)) {
+                outputs.push(GroupOutput::Aggregate);
+            } else if let Some(column) = original_schema.columns.iter().find(|column| {
+                references_column(expr, &column.name)
+                    && !group_exprs.iter().any(|group_expr| {
+                        matches!(group_expr, Expression::Column(_) | Expression::QualifiedColumn { .. })
+                            && references_column(group_expr, &column.name)
+                    })
+            }) {
+                return Err(ExecutionError::SemanticError(format!(
+                    "column '{}' must appear in the GROUP BY clause or be used in an aggregate function",
+                    column.name
+                )));
+            } else {
+                outputs.push(GroupOutput::PerGroup);
+            }
+        }
+        
+        // 构建结果 schema
+        let mut result_columns = Vec::new();
+        for (select_expr, output) in select_expressions.iter().zip(&outputs) {
+            let column_name = if let Some(alias) = &select_expr.alias {
+                alias.clone()
+            } else {
+                match &select_expr.expr {
+                    Expression::Column(col_name) | Expression::QualifiedColumn { column: col_name, .. } => col_name.clone(),
+                    Expression::FunctionCall { name, .. } if functions::is_aggregate(name) => {
+                        format!("{}()", name) // COUNT(), AVG(), etc.
+                    }
+                    Expression::FunctionCall { name, .. } => format!("{}(...)", name),
+                    _ => "expr".to_string(),
+                }
+            };
+            
+            let mut column = match (&select_expr.expr, output) {
+                (Expression::FunctionCall { name, args }, GroupOutput::Aggregate) => ColumnDefinition::new(
+                    column_name,
+                    self.aggregate_column_type(name, args, &rows, original_schema),
+                    true,
+                ),
+                (Expression::Column(col_name), _) => match column_index(original_schema, None, col_name) {
+                    Some(index) => ColumnDefinition::new(column_name, original_schema.columns[index].data_type.clone(), true),
+                    None => ColumnDefinition::new(column_name, DataType::Varchar(255), true),
+                },
+                (expr, _) => self.computed_column(column_name, expr, &rows, original_schema),
+            };
+            column.nullable = true;
+            result_columns.push(column);
+        }
+        
+        // 创建分组哈希表
+        let mut groups: HashMap<Vec<Value>, Vec<Tuple>> = HashMap::new();
+        
+        // 按分组表达式对元组进行分组
+        for tuple in rows {
+            let mut group_key = Vec::new();
+            
+            // 评估分组表达式
+            for expr in &group_exprs {
+                let group_value = self.evaluate_expression_for_tuple(expr, &tuple, original_schema)?;
+                group_key.push(group_value);
+            }
+            
+            groups.entry(group_key).or_insert_with(Vec::new).push(tuple);
+        }
+        
+        // 没有 GROUP BY 的聚合查询在输入为空时仍返回一行（COUNT 为 0，其余聚合为 NULL）
+        if group_exprs.is_empty() && groups.is_empty() {
+            groups.insert(Vec::new(), Vec::new());
+        }
+        
+        // 生成聚合结果
+        let mut result_rows = Vec::new();
+        
+        for (group_key, group_tuples) in self.ordered_groups(groups) {
+            let mut result_values = Vec::new();
+            
+            for (select_expr, output) in select_expressions.iter().zip(&outputs) {
+                let value = match (&select_expr.expr, output) {
+                    (_, GroupOutput::Key(i)) => group_key[*i].clone(),
+                    (Expression::FunctionCall { name, args }, GroupOutput::Aggregate) => {
+                        self.compute_aggregate_function(name, args, &group_tuples, original_schema)?
+                    }
+                    (expr, _) => match group_tuples.first() {
+                        Some(tuple) => self.evaluate_expression_for_tuple(expr, tuple, original_schema)?,
+                        None => Value::Null,
+                    },
+                };
+                result_values.push(value);
+            }
+            
+            result_rows.push(Tuple { values: result_values });
+        }
+        
+        Ok((result_rows, Schema { columns: result_columns, primary_key: None }))
+    }
+    
+    /// 计算聚合函数值
+    fn compute_aggregate_function(
+        &self,
+        func_name: &str,
+        args: &[crate::sql::parser::Expression],
+        group_tuples: &[crate::types::Tuple],
+        schema: &crate::types::Schema,
+    ) -> Result<crate::types::Value, ExecutionError> {
+        use crate::types::Value;
+        use crate::sql::parser::Expression;
+        use crate::engine::executor::AggregateAccumulator;
+        
+        match func_name.to_uppercase().as_str() {
+            "COUNT" => {
+                // COUNT(*) 或 COUNT(column)
+                if args.is_empty() || (args.len() == 1 && matches!(args[0], crate::sql::parser::Expression::Literal(Value::Varchar(ref s)) if s == "*")) {
+                    // COUNT(*) - 计算行数
+                    Ok(Value::Integer(group_tuples.len() as i32))
+                } else {
+                    // COUNT(column) - 计算非NULL值的数量
+                    let mut count = 0;
+                    for tuple in group_tuples {
+                        if let Ok(val) = self.evaluate_expression_for_tuple(&args[0], tuple, schema) {
+                            if !matches!(val, Value::Null) {
+                                count += 1;
                             }
-                            _ => Err(ExecutionError::EvaluationError {
-                                message: "Cannot divide non-numeric values".to_string(),
-                            })
                         }
                     }
+                    Ok(Value::Integer(count))
+                }
+            }
+            "SUM" => {
+                if args.is_empty() {
+                    return Err(ExecutionError::EvaluationError {
+                        message: "SUM function requires an argument".to_string()
+                    });
+                }
+                
+                // 跳过 NULL；没有任何非 NULL 值时结果为 NULL
+                let mut sum = NumericSum::default();
+                for tuple in group_tuples {
+                    if let Ok(val) = self.evaluate_expression_for_tuple(&args[0], tuple, schema) {
+                        self.numeric_aggregate_input("SUM", &val)?;
+                        sum.add(&val)?;
+                    }
+                }
+                Ok(sum.total())
+            }
+            "AVG" => {
+                if args.is_empty() {
+                    return Err(ExecutionError::EvaluationError {
+                        message: "AVG function requires an argument".to_string()
+                    });
+                }
+                
+                let mut sum = NumericSum::default();
+                for tuple in group_tuples {
+                    if let Ok(val) = self.evaluate_expression_for_tuple(&args[0], tuple, schema) {
+                        self.numeric_aggregate_input("AVG", &val)?;
+                        sum.add(&val)?;
+                    }
+                }
+                sum.average()
+            }
+            name @ ("MAX" | "MIN") => {
+                if args.is_empty() {
+                    return Err(ExecutionError::EvaluationError {
+                        message: format!("{} function requires an argument", name)
+                    });
+                }
+                
+                // 直接比较 Value，结果保持参数的类型
+                let wanted = if name == "MAX" { std::cmp::Ordering::Greater } else { std::cmp::Ordering::Less };
+                let mut best: Option<Value> = None;
+                for tuple in group_tuples {
+                    if let Ok(val) = self.evaluate_expression_for_tuple(&args[0], tuple, schema) {
+                        if !matches!(val, Value::Null)
+                            && best.as_ref().is_none_or(|current| val.partial_cmp(current) == Some(wanted))
+                        {
+                            best = Some(val);
+                        }
+                    }
+                }
+                
+                Ok(best.unwrap_or(Value::Null))
+            }
+            name @ ("STDDEV" | "STDDEV_SAMP" | "STDDEV_POP" | "VARIANCE" | "VAR_SAMP" | "VAR_POP") => {
+                if args.is_empty() {
+                    return Err(ExecutionError::EvaluationError {
+                        message: format!("{} function requires an argument", name)
+                    });
+                }
+                
+                // Welford 单遍算法：逐个更新均值和离差平方和，避免先求平方和再相减的精度损失
+                let mut count = 0u64;
+                let mut mean = 0.0;
+                let mut m2 = 0.0;
+                for tuple in group_tuples {
+                    if let Ok(val) = self.evaluate_expression_for_tuple(&args[0], tuple, schema) {
+                        if let Some(x) = self.numeric_aggregate_input(name, &val)? {
+                            count += 1;
+                            let delta = x - mean;
+                            mean += delta / count as f64;
+                            m2 += delta * (x - mean);
+                        }
+                    }
+                }
+                
+                // STDDEV / VARIANCE 是样本统计量，至少需要两个值；总体统计量至少需要一个
+                let population = name.ends_with("_POP");
+                let divisor = if population { count } else { count.saturating_sub(1) };
+                if divisor == 0 {
+                    return Ok(Value::Null);
+                }
+                let variance = m2 / divisor as f64;
+                Ok(Value::Double(if name.starts_with("STDDEV") { variance.sqrt() } else { variance }))
+            }
+            name @ ("STRING_AGG" | "GROUP_CONCAT") => {
+                // STRING_AGG(expr, separator)；GROUP_CONCAT 的分隔符可省略，默认为逗号
+                let separator = match (name, args) {
+                    (_, [_, Expression::Literal(Value::Varchar(separator))]) => separator.as_str(),
+                    ("GROUP_CONCAT", [_]) => ",",
                     _ => {
-                        // 对于比较运算符和其他操作符，暂时不支持
-                        Err(ExecutionError::EvaluationError {
-                            message: format!("Unsupported binary operator: {:?}", op),
-                        })
+                        return Err(ExecutionError::EvaluationError {
+                            message: format!("{} requires an expression and a string literal separator", name)
+                        });
+                    }
+                };
+                
+                let mut accumulator = AggregateAccumulator::new();
+                for tuple in group_tuples {
+                    let val = self.evaluate_expression_for_tuple(&args[0], tuple, schema)?;
+                    accumulator.append_text(&val, separator);
+                }
+                
+                Ok(accumulator.text.map(Value::Varchar).unwrap_or(Value::Null))
+            }
+            _ => {
+                Err(ExecutionError::NotImplemented {
+                    feature: format!("Aggregate function: {}", func_name),
+                    capability: Capability::Aggregates,
+                })
+            }
+        }
+    }
+
+    /// 检查 SELECT 列表是否包含聚合函数
+    fn select_list_contains_aggregates(&self, select_list: &crate::sql::parser::SelectList) -> bool {
+        use crate::sql::parser::{SelectList, Expression};
+        
+        match select_list {
+            SelectList::Wildcard => false,
+            SelectList::Expressions(expressions) => {
+                expressions.iter().any(|select_expr| {
+                    self.expression_contains_aggregates(&select_expr.expr)
+                })
+            }
+        }
+    }
+
+    /// 检查表达式是否包含聚合函数（递归检查）
+    fn expression_contains_aggregates(&self, expr: &crate::sql::parser::Expression) -> bool {
+        use crate::sql::parser::Expression;
+        
+        match expr {
+            Expression::FunctionCall { name, .. } => {
+                // Check if this is an aggregate function
+                functions::is_aggregate(name)
+            }
+            // For other expression types, we can add recursive checks if needed
+            _ => false
+        }
+    }
+    
+    /// 评估元组上下文中的表达式
+    fn evaluate_expression_for_tuple(
+        &self,
+        expr: &crate::sql::parser::Expression,
+        tuple: &Tuple,
+        schema: &Schema,
+    ) -> Result<Value, ExecutionError> {
+        use crate::sql::parser::Expression;
+        
+        match expr {
+            Expression::Literal(value) => Ok(value.clone()),
+            Expression::Column(col_name) => {
+                // 增强错误处理：检查列名有效性
+                if col_name.is_empty() {
+                    return Err(ExecutionError::EvaluationError {
+                        message: "Empty column name in expression".to_string(),
+                    });
+                }
+                
+                let col_index = column_index(schema, None, col_name)
+                    .ok_or_else(|| ExecutionError::ColumnNotFound {
+                        table: "current".to_string(),
+                        column: col_name.clone(),
+                    })?;
+                
+                // 边界检查：确保索引有效
+                if col_index >= tuple.values.len() {
+                    return Err(ExecutionError::EvaluationError {
+                        message: format!("Column index {} out of bounds for tuple with {} values", 
+                                       col_index, tuple.values.len()),
+                    });
+                }
+                
+                Ok(tuple.values[col_index].clone())
+            }
+            Expression::QualifiedColumn { table, column } => {
+                // 增强错误处理：检查表名和列名
+                if column.is_empty() {
+                    return Err(ExecutionError::EvaluationError {
+                        message: format!("Empty column name in qualified expression for table {}", table),
+                    });
+                }
+                
+                // 优化的表别名解析：支持多种匹配策略
+                let col_index = self.resolve_qualified_column_index(table, column, schema)?;
+                
+                // 边界检查：确保索引有效
+                if col_index >= tuple.values.len() {
+                    return Err(ExecutionError::EvaluationError {
+                        message: format!("Column index {} out of bounds for tuple with {} values", 
+                                       col_index, tuple.values.len()),
+                    });
+                }
+                
+                Ok(tuple.values[col_index].clone())
+            }
+            Expression::BinaryOp { left, op, right } => {
+                // 支持算术运算表达式
+                let left_val = self.evaluate_expression_for_tuple(left, tuple, schema)?;
+                let right_val = self.evaluate_expression_for_tuple(right, tuple, schema)?;
+                
+                use crate::sql::parser::BinaryOperator;
+                if matches!(op, BinaryOperator::JsonExtract | BinaryOperator::JsonExtractText) {
+                    return functions::json_access(op, &left_val, &right_val);
+                }
+                let (left_val, right_val) = exact_decimal_operands(left, left_val, right, right_val);
+                let (left_val, right_val) = match (left_val, right_val) {
+                    // DECIMAL 与整数或 DECIMAL 精确计算
+                    (left @ (Value::Decimal(_) | Value::Integer(_) | Value::BigInt(_)), right @ (Value::Decimal(_) | Value::Integer(_) | Value::BigInt(_)))
+                        if matches!(left, Value::Decimal(_)) || matches!(right, Value::Decimal(_)) =>
+                    {
+                        return functions::decimal_arithmetic(op, &left, &right);
+                    }
+                    // 与浮点数运算时按 DOUBLE 计算
+                    (Value::Decimal(d), right @ (Value::Float(_) | Value::Double(_))) => {
+                        (Value::Double(d.to_f64()), Value::Double(self.value_to_f64(&right)))
+                    }
+                    (left @ (Value::Float(_) | Value::Double(_)), Value::Decimal(d)) => {
+                        (Value::Double(self.value_to_f64(&left)), Value::Double(d.to_f64()))
+                    }
+                    operands => operands,
+                };
+                match op {
+                    BinaryOperator::Add => {
+                        match (left_val, right_val) {
+                            (Value::Integer(a), Value::Integer(b)) => Ok(Value::Integer(a + b)),
+                            (Value::Float(a), Value::Float(b)) => Ok(Value::Float(a + b)),
+                            (Value::Double(a), Value::Double(b)) => Ok(Value::Double(a + b)),
+                            (Value::Integer(a), Value::Double(b)) => Ok(Value::Double(a as f64 + b)),
+                            (Value::Double(a), Value::Integer(b)) => Ok(Value::Double(a + b as f64)),
+                            // 日期/时间加整数按天计算
+                            #[cfg(feature = "chrono")]
+                            (date @ (Value::Date(_) | Value::Timestamp(_)), Value::Integer(days))
+                            | (Value::Integer(days), date @ (Value::Date(_) | Value::Timestamp(_))) => {
+                                functions::date_add(&date, days as i64, "DAY")
+                            }
+                            _ => Err(ExecutionError::EvaluationError {
+                                message: "Cannot add non-numeric values".to_string(),
+                            })
+                        }
+                    }
+                    BinaryOperator::Subtract => {
+                        match (left_val, right_val) {
+                            (Value::Integer(a), Value::Integer(b)) => Ok(Value::Integer(a - b)),
+                            (Value::Float(a), Value::Float(b)) => Ok(Value::Float(a - b)),
+                            (Value::Double(a), Value::Double(b)) => Ok(Value::Double(a - b)),
+                            (Value::Integer(a), Value::Double(b)) => Ok(Value::Double(a as f64 - b)),
+                            (Value::Double(a), Value::Integer(b)) => Ok(Value::Double(a - b as f64)),
+                            #[cfg(feature = "chrono")]
+                            (date @ (Value::Date(_) | Value::Timestamp(_)), Value::Integer(days)) => {
+                                functions::date_add(&date, -(days as i64), "DAY")
+                            }
+                            #[cfg(feature = "chrono")]
+                            (left, right) => functions::date_diff(&left, &right).ok_or_else(|| ExecutionError::EvaluationError {
+                                message: "Cannot subtract non-numeric values".to_string(),
+                            }),
+                            #[cfg(not(feature = "chrono"))]
+                            _ => Err(ExecutionError::EvaluationError {
+                                message: "Cannot subtract non-numeric values".to_string(),
+                            })
+                        }
+                    }
+                    BinaryOperator::Multiply => {
+                        match (left_val, right_val) {
+                            (Value::Integer(a), Value::Integer(b)) => Ok(Value::Integer(a * b)),
+                            (Value::Float(a), Value::Float(b)) => Ok(Value::Float(a * b)),
+                            (Value::Double(a), Value::Double(b)) => Ok(Value::Double(a * b)),
+                            (Value::Integer(a), Value::Double(b)) => Ok(Value::Double(a as f64 * b)),
+                            (Value::Double(a), Value::Integer(b)) => Ok(Value::Double(a * b as f64)),
+                            _ => Err(ExecutionError::EvaluationError {
+                                message: "Cannot multiply non-numeric values".to_string(),
+                            })
+                        }
+                    }
+                    BinaryOperator::Divide => {
+                        match (left_val, right_val) {
+                            (Value::Integer(a), Value::Integer(b)) => {
+                                if b == 0 {
+                                    Err(ExecutionError::EvaluationError {
+                                        message: "Division by zero".to_string(),
+                                    })
+                                } else {
+                                    Ok(Value::Double(a as f64 / b as f64))
+                                }
+                            }
+                            (Value::Float(a), Value::Float(b)) => {
+                                if b == 0.0 {
+                                    Err(ExecutionError::EvaluationError {
+                                        message: "Division by zero".to_string(),
+                                    })
+                                } else {
+                                    Ok(Value::Float(a / b))
+                                }
+                            }
+                            (Value::Double(a), Value::Double(b)) => {
+                                if b == 0.0 {
+                                    Err(ExecutionError::EvaluationError {
+                                        message: "Division by zero".to_string(),
+                                    })
+                                } else {
+                                    Ok(Value::Double(a / b))
+                                }
+                            }
+                            (Value::Integer(a), Value::Double(b)) => {
+                                if b == 0.0 {
+                                    Err(ExecutionError::EvaluationError {
+                                        message: "Division by zero".to_string(),
+                                    })
+                                } else {
+                                    Ok(Value::Double(a as f64 / b))
+                                }
+                            }
+                            (Value::Double(a), Value::Integer(b)) => {
+                                if b == 0 {
+                                    Err(ExecutionError::EvaluationError {
+                                        message: "Division by zero".to_string(),
+                                    })
+                                } else {
+                                    Ok(Value::Double(a / b as f64))
+                                }
+                            }
+                            _ => Err(ExecutionError::EvaluationError {
+                                message: "Cannot divide non-numeric values".to_string(),
+                            })
+                        }
+                    }
+                    _ => {
+                        // 对于比较运算符和其他操作符，暂时不支持
+                        Err(ExecutionError::EvaluationError {
+                            message: format!("Unsupported binary operator: {:?}", op),
+                        })
+                    }
+                }
+            }
+            Expression::UnaryOp { op, expr: operand } => {
+                use crate::sql::parser::UnaryOperator;
+                
+                let value = self.evaluate_expression_for_tuple(operand, tuple, schema)?;
+                match (op, value) {
+                    (_, Value::Null) => Ok(Value::Null),
+                    (UnaryOperator::Plus, value @ (Value::Integer(_) | Value::BigInt(_) | Value::Float(_) | Value::Double(_) | Value::Decimal(_))) => Ok(value),
+                    (UnaryOperator::Minus, Value::Integer(i)) => i.checked_neg().map(Value::Integer).ok_or_else(|| {
+                        ExecutionError::EvaluationError { message: format!("Integer overflow negating {}", i) }
+                    }),
+                    (UnaryOperator::Minus, Value::BigInt(i)) => i.checked_neg().map(Value::BigInt).ok_or_else(|| {
+                        ExecutionError::EvaluationError { message: format!("Integer overflow negating {}", i) }
+                    }),
+                    (UnaryOperator::Minus, Value::Float(f)) => Ok(Value::Float(-f)),
+                    (UnaryOperator::Minus, Value::Double(d)) => Ok(Value::Double(-d)),
+                    (UnaryOperator::Minus, Value::Decimal(d)) => d.checked_neg().map(Value::Decimal).ok_or_else(|| {
+                        ExecutionError::EvaluationError { message: format!("DECIMAL overflow negating {}", d) }
+                    }),
+                    (UnaryOperator::Not, Value::Boolean(b)) => Ok(Value::Boolean(!b)),
+                    (op, value) => Err(ExecutionError::EvaluationError {
+                        message: format!("Unsupported unary operator {:?} for value {}", op, value),
+                    }),
+                }
+            }
+            Expression::Parameter(index) => Err(ExecutionError::EvaluationError {
+                message: format!("No value bound for parameter ${}", index),
+            }),
+            Expression::FunctionCall { name, args } if !functions::is_aggregate(name) => {
+                let args = args.iter()
+                    .map(|arg| self.evaluate_expression_for_tuple(arg, tuple, schema))
+                    .collect::<Result<Vec<_>, _>>()?;
+                functions::call(name, &args, &self.random)
+            }
+            Expression::Cast { expr, data_type } => {
+                let value = self.evaluate_expression_for_tuple(expr, tuple, schema)?;
+                value.cast_to(data_type).map_err(|e| ExecutionError::EvaluationError {
+                    message: format!("cannot cast {} to {}: {}", value, data_type, e),
+                })
+            }
+            _ => {
+                // 对于其他不支持的表达式类型，返回第一个值但记录警告
+                println!("⚠️ 不支持的表达式类型，使用元组第一个值");
+                Ok(tuple.values.get(0).cloned().unwrap_or(Value::Null))
+            }
+        }
+    }
+    
+    /// 数值聚合的输入：NULL 返回 None 以便跳过，非数值类型报错
+    fn numeric_aggregate_input(&self, function: &str, value: &Value) -> Result<Option<f64>, ExecutionError> {
+        match value {
+            Value::Null => Ok(None),
+            Value::Integer(_) | Value::BigInt(_) | Value::Float(_) | Value::Double(_) | Value::Decimal(_) => Ok(Some(self.value_to_f64(value))),
+            other => Err(ExecutionError::TypeMismatch {
+                expected: format!("numeric argument to {}", function),
+                actual: other.data_type().to_string(),
+            }),
+        }
+    }
+    
+    /// 值转换为浮点数（用于聚合计算）
+    fn value_to_f64(&self, value: &Value) -> f64 {
+        match value {
+            Value::Integer(i) => *i as f64,
+            Value::BigInt(i) => *i as f64,
+            Value::Float(f) => *f as f64,
+            Value::Double(d) => *d,
+            Value::Decimal(d) => d.to_f64(),
+            _ => 0.0,
+        }
+    }
+    
+    /// 按配置决定分组的输出顺序：默认沿用哈希表迭代顺序，开启确定性分组时按分组键排序
+    fn ordered_groups(&self, groups: HashMap<Vec<Value>, Vec<Tuple>>) -> Vec<(Vec<Value>, Vec<Tuple>)> {
+        let mut groups: Vec<_> = groups.into_iter().collect();
+        if self.config.deterministic_grouping {
+            groups.sort_by(|(a, _), (b, _)| {
+                a.iter()
+                    .zip(b)
+                    .map(|(x, y)| self.compare_values_for_sort(x, y))
+                    .find(|ordering| ordering.is_ne())
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+        }
+        groups
+    }
+    
+    /// 比较值用于排序
+    fn compare_values_for_sort(&self, a: &Value, b: &Value) -> std::cmp::Ordering {
+        use std::cmp::Ordering;
+        match (a, b) {
+            (Value::Integer(a), Value::Integer(b)) => a.cmp(b),
+            (Value::BigInt(a), Value::BigInt(b)) => a.cmp(b),
+            (Value::Integer(a), Value::BigInt(b)) => i64::from(*a).cmp(b),
+            (Value::BigInt(a), Value::Integer(b)) => a.cmp(&i64::from(*b)),
+            (Value::Float(a), Value::Float(b)) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
+            (Value::Double(a), Value::Double(b)) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
+            (Value::Decimal(x), Value::Decimal(y)) => x.cmp(y),
+            (Value::Decimal(_), Value::Integer(_) | Value::BigInt(_))
+            | (Value::Integer(_) | Value::BigInt(_), Value::Decimal(_)) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
+            (Value::Varchar(a), Value::Varchar(b)) => a.cmp(b),
+            (Value::Blob(a), Value::Blob(b)) => a.cmp(b),
+            (Value::Uuid(a), Value::Uuid(b)) => a.cmp(b),
+            (Value::Json(a), Value::Json(b)) => a.cmp(b),
+            (Value::Boolean(a), Value::Boolean(b)) => a.cmp(b),
+            #[cfg(feature = "chrono")]
+            (Value::Date(_) | Value::Timestamp(_), Value::Date(_) | Value::Timestamp(_)) => {
+                functions::as_timestamp(a).cmp(&functions::as_timestamp(b))
+            }
+            (Value::Null, Value::Null) => Ordering::Equal,
+            (Value::Null, _) => Ordering::Less,
+            (_, Value::Null) => Ordering::Greater,
+            _ => Ordering::Equal,
+        }
+    }
+    
+    /// 解析限定列名的索引（支持表别名）
+    fn resolve_qualified_column_index(
+        &self,
+        table_name: &str,
+        column_name: &str,
+        schema: &Schema,
+    ) -> Result<usize, ExecutionError> {
+        // 策略1：连接输出中匹配 "table.column" 格式的列名，单表的模式中直接匹配列名（忽略表名）
+        if let Some(index) = column_index(schema, Some(table_name), column_name) {
+            return Ok(index);
+        }
+        
+        // 策略2：模糊匹配（对于JOIN后的合并schema）
+        // 在JOIN的情况下，列名可能被重命名为 table1_column, table2_column 等形式
+        let possible_names = vec![
+            format!("{}_{}", table_name, column_name),
+            format!("{}__{}", table_name, column_name), // 双下划线分隔
+            format!("{}:{}", table_name, column_name),   // 冒号分隔
+        ];
+        
+        for possible_name in possible_names {
+            if let Some(index) = schema.columns.iter().position(|col| col.name == possible_name) {
+                return Ok(index);
+            }
+        }
+        
+        // 如果所有策略都失败，返回错误
+        Err(ExecutionError::ColumnNotFound {
+            table: table_name.to_string(),
+            column: column_name.to_string(),
+        })
+    }
+    
+    /// Execute UPDATE statement (simplified)
+    fn execute_update_simple(
+        &mut self,
+        table_name: String,
+        assignments: Vec<crate::sql::parser::Assignment>,
+        where_clause: Option<crate::sql::parser::Expression>,
+        returning: Option<crate::sql::parser::SelectList>,
+    ) -> Result<QueryResult, ExecutionError> {
+        // Get table metadata first
+        let table_id = self.table_catalog.get(&table_name)
+            .ok_or_else(|| ExecutionError::TableNotFound { table: table_name.clone() })?;
+        
+        let table_id = *table_id;
+        let schema = self.table_schemas.get(&table_id)
+            .ok_or_else(|| ExecutionError::TableNotFound { table: table_name.clone() })?
+            .clone();
+        
+        // Get immutable reference to evaluate WHERE conditions
+        let table_data_snapshot = self.table_rows(table_id)?.into_owned();
+        
+        // Evaluate which rows should be updated
+        let indices_to_update = self.matching_row_indices(table_id, &table_data_snapshot, where_clause.as_ref(), &schema);
+        self.lock_rows(table_id, &table_name, &indices_to_update)?;
+        
+        // Pre-compute new values for each row to avoid borrowing issues
+        let mut updated_rows = Vec::new();
+        for row_index in &indices_to_update {
+            if *row_index < table_data_snapshot.len() {
+                let row = &table_data_snapshot[*row_index];
+                let mut new_row = row.clone();
+                
+                // Apply assignments
+                for assignment in &assignments {
+                    // Find column index
+                    if let Some(col_index) = schema.columns.iter()
+                        .position(|col| col.name == assignment.column) {
+                        
+                        // Evaluate new value - support both literals and expressions
+                        let new_value = match &assignment.value {
+                            crate::sql::parser::Expression::Literal(val) => val.clone(),
+                            _ => {
+                                // Support complex expressions like age = age + 1
+                                self.evaluate_expression_for_tuple(&assignment.value, row, &schema)?
+                            }
+                        };
+                        
+                        // Update the value in the new row
+                        new_row.values[col_index] = self.assignment_value(&schema, col_index, new_value)?;
+                    } else {
+                        return Err(ExecutionError::ColumnNotFound {
+                            table: table_name.clone(),
+                            column: assignment.column.clone(),
+                        });
+                    }
+                }
+                updated_rows.push((*row_index, new_row));
+            }
+        }
+        
+        // Keys are checked against the table as it looks after the update
+        if !updated_rows.is_empty() && !self.defer_constraint_checks(table_id) {
+            self.check_updated_keys(table_id, &schema, &updated_rows)?;
+        }
+        
+        // Apply the pre-computed updates; in-memory indexes follow each row
+        let row_count = self.table_data.get(&table_id)
+            .ok_or_else(|| ExecutionError::TableNotFound { table: table_name.clone() })?
+            .len();
+        
+        let mut updated_count = 0;
+        let mut returned_rows = Vec::new();
+        for (row_index, new_row) in updated_rows {
+            if row_index < row_count {
+                if returning.is_some() {
+                    returned_rows.push(new_row.clone());
+                }
+                self.update_row(table_id, row_index, &table_data_snapshot[row_index], new_row)?;
+                updated_count += 1;
+            }
+        }
+        
+        let (rows, result_schema) = match returning {
+            Some(list) => {
+                let (rows, returning_schema) = self.project_returning(&returned_rows, &list, &schema, &table_name)?;
+                (rows, Some(returning_schema))
+            }
+            None => (vec![], None),
+        };
+        
+        Ok(QueryResult {
+            rows,
+            schema: result_schema,
+            affected_rows: updated_count,
+            message: format!("Updated {} row(s) in table '{}'", updated_count, table_name),
+        })
+    }
+    
+    /// 返回满足 WHERE 条件的行下标（无 WHERE 时为全部行）
+    fn matching_row_indices(
+        &self,
+        table_id: u32,
+        rows: &[Tuple],
+        where_clause: Option<&crate::sql::parser::Expression>,
+        schema: &Schema,
+    ) -> Vec<usize> {
+        let Some(expr) = where_clause else {
+            return (0..rows.len()).collect();
+        };
+        
+        let (rows, schema) = if Self::needs_rowid(schema, [expr]) {
+            let (rows, schema) = Self::with_rowid_column(self.row_ids(table_id), rows, schema);
+            (std::borrow::Cow::Owned(rows), std::borrow::Cow::Owned(schema))
+        } else {
+            (std::borrow::Cow::Borrowed(rows), std::borrow::Cow::Borrowed(schema))
+        };
+        rows.iter()
+            .enumerate()
+            .filter(|(_, row)| matches!(self.evaluate_where_condition(expr, row, &schema), Ok(true)))
+            .map(|(i, _)| i)
+            .collect()
+    }
+    
+    /// 表达式是否引用了 rowid 伪列（表中已有同名真实列时不算）
+    fn needs_rowid<'a>(
+        schema: &Schema,
+        exprs: impl IntoIterator<Item = &'a crate::sql::parser::Expression>,
+    ) -> bool {
+        !schema.columns.iter().any(|col| col.name == ROWID_COLUMN)
+            && exprs.into_iter().any(|expr| references_column(expr, ROWID_COLUMN))
+    }
+    
+    /// 显式事务中为将要修改的行加排他锁，锁一直持有到 COMMIT / ROLLBACK
+    fn lock_rows(&self, table_id: u32, table_name: &str, indices: &[usize]) -> Result<(), ExecutionError> {
+        let Some(transaction) = &self.transaction else {
+            return Ok(());
+        };
+        for &index in indices {
+            let row_id = self.row_id_at(table_id, index);
+            self.transactions.lock_row(transaction.id, table_name, row_id, LockType::ExclusiveWrite)?;
+        }
+        Ok(())
+    }
+    
+    /// 表中第 index 行的 rowid
+    fn row_id_at(&self, table_id: u32, index: usize) -> u64 {
+        self.row_ids(table_id).get(index).copied().unwrap_or_default()
+    }
+    
+    /// 表的全部 rowid，与表数据同序
+    fn row_ids(&self, table_id: u32) -> &[u64] {
+        self.table_row_ids.get(&table_id).map(|row_ids| row_ids.ids.as_slice()).unwrap_or_default()
+    }
+    
+    /// 为表上的 WHERE 选择访问路径：有可用的索引且查找足够有选择性时返回该索引查找，否则为 None（顺序扫描）
+    ///
+    /// 与 EXPLAIN 和编译出的执行计划使用同一规则（[`QueryOptimizer::prefers_index_scan`]）。
+    fn index_probe(&self, table_name: &str, where_clause: Option<&crate::sql::parser::Expression>) -> Option<(u32, IndexProbe)> {
+        let (table_id, probe, estimated_rows) = self.index_candidate(table_name, where_clause?)?;
+        let table_rows = self.table_data.get(&table_id).map_or(0, TableRows::len);
+        self.optimizer.prefers_index_scan(table_rows, estimated_rows).then_some((table_id, probe))
+    }
+    
+    /// 表上可回答 WHERE 的索引查找中取出行数最少的一个及其行数；系统视图和临时视图不用索引
+    fn index_candidate(&self, table_name: &str, where_clause: &crate::sql::parser::Expression) -> Option<(u32, IndexProbe, usize)> {
+        let table_id = *self.table_catalog.get(table_name)?;
+        let schema = self.table_schemas.get(&table_id)?;
+        let mut best: Option<(IndexProbe, usize)> = None;
+        for probe in indexes::index_candidates(&self.lookup_indexes(table_id, table_name), schema, where_clause) {
+            let rows = match &probe.key {
+                Some(key) => self.with_row_index(table_id, &probe.columns, &[], probe.where_clause.as_ref(), |index| {
+                    index.count(key, probe.range.as_ref())
+                }).ok()?,
+                None => 0,
+            };
+            if best.as_ref().is_none_or(|(_, best_rows)| rows < *best_rows) {
+                best = Some((probe, rows));
+            }
+        }
+        best.map(|(probe, rows)| (table_id, probe, rows))
+    }
+    
+    /// 可用于查找的索引：表的索引，以及没有同列的普通索引时作为唯一索引的主键 `{表名}_pkey`
+    fn lookup_indexes(&self, table_id: u32, table_name: &str) -> Vec<IndexInfo> {
+        let mut indexes = self.table_indexes.get(&table_id).cloned().unwrap_or_default();
+        let primary_key = self.table_schemas.get(&table_id).and_then(|schema| {
+            let columns: Vec<String> = schema.primary_key.as_ref()?.iter()
+                .map(|&i| schema.columns[i].name.clone())
+                .collect();
+            Some(columns)
+        });
+        if let Some(columns) = primary_key {
+            if !indexes.iter().any(|index| index.columns == columns && index.where_clause.is_none()) {
+                indexes.push(IndexInfo { name: format!("{}_pkey", table_name), columns, unique: true, include: Vec::new(), where_clause: None, build: None });
+            }
+        }
+        indexes
+    }
+    
+    /// 按索引查找取出匹配的行及其 rowid（按表数据顺序）；索引过期时先由表的当前行重建
+    fn fetch_by_index(&self, table_id: u32, probe: &IndexProbe) -> Result<(Vec<Tuple>, Vec<u64>), ExecutionError> {
+        let Some(key) = &probe.key else {
+            return Ok((Vec::new(), Vec::new()));
+        };
+        let table_rows = self.table_data.get(&table_id)
+            .ok_or_else(|| ExecutionError::TableNotFound { table: format!("table_id_{}", table_id) })?;
+        let matched = self.with_row_index(table_id, &probe.columns, &[], probe.where_clause.as_ref(), |index| {
+            index.lookup(key, probe.range.as_ref())
+        })?;
+        
+        let mut rows = Vec::with_capacity(matched.len());
+        let mut row_ids = Vec::with_capacity(matched.len());
+        for row_id in matched {
+            let Some(position) = self.row_position(table_id, row_id) else {
+                continue;
+            };
+            if let Some(row) = table_rows.get(&self.buffer_pool, position)? {
+                rows.push(row);
+                row_ids.push(row_id);
+            }
+        }
+        Ok((rows, row_ids))
+    }
+    
+    /// 仅索引扫描：与 [`Self::fetch_by_index`] 相同的行，由索引保存的键列和 INCLUDE 列构造，不读表数据
+    ///
+    /// 其余列为 NULL，只在 [`Self::covered_by_index`] 成立时使用。
+    fn fetch_index_only(&self, table_id: u32, probe: &IndexProbe) -> Result<(Vec<Tuple>, Vec<u64>), ExecutionError> {
+        let Some(key) = &probe.key else {
+            return Ok((Vec::new(), Vec::new()));
+        };
+        let width = self.table_schemas.get(&table_id).map_or(0, |schema| schema.columns.len());
+        let found = self.with_row_index(table_id, &probe.columns, &probe.include, probe.where_clause.as_ref(), |index| {
+            index.lookup_rows(key, probe.range.as_ref(), width)
+        })?;
+        Ok(found.into_iter().map(|(row_id, row)| (row, row_id)).unzip())
+    }
+    
+    /// 查询是否只用到索引的键列和 INCLUDE 列（rowid 伪列也由索引提供），可以用仅索引扫描
+    ///
+    /// exprs 为 SELECT 列表之外查询用到的表达式（WHERE、GROUP BY、HAVING）；SELECT * 要求索引包含全部列。
+    fn covered_by_index<'a>(
+        schema: &Schema,
+        probe: &IndexProbe,
+        select_list: &'a crate::sql::parser::SelectList,
+        exprs: impl IntoIterator<Item = &'a crate::sql::parser::Expression>,
+    ) -> bool {
+        let select_exprs = match select_list {
+            crate::sql::parser::SelectList::Expressions(exprs) => exprs.as_slice(),
+            crate::sql::parser::SelectList::Wildcard => &[],
+        };
+        let wildcard = matches!(select_list, crate::sql::parser::SelectList::Wildcard);
+        let exprs: Vec<&crate::sql::parser::Expression> = exprs.into_iter()
+            .chain(select_exprs.iter().map(|select_expr| &select_expr.expr))
+            .collect();
+        schema.columns.iter().enumerate()
+            .filter(|(i, _)| !probe.columns.contains(i) && !probe.include.contains(i))
+            .all(|(_, column)| !wildcard && !exprs.iter().any(|expr| references_column(expr, &column.name)))
+    }
+    
+    /// rowid 对应的行在表数据中的位置
+    fn row_position(&self, table_id: u32, row_id: u64) -> Option<usize> {
+        // rowid 按分配顺序递增，与表数据同序
+        let ids = self.row_ids(table_id);
+        ids.binary_search(&row_id).ok().or_else(|| ids.iter().position(|&id| id == row_id))
+    }
+    
+    /// 以键列上的内存索引调用 f；索引尚未建立、已过期或没有保存 include 中的列时先由表的当前行建立
+    ///
+    /// 同一组键列和谓词只有一份索引，重建时保留之前已保存的 INCLUDE 列。部分索引只由满足谓词的行建立。
+    fn with_row_index<R>(
+        &self,
+        table_id: u32,
+        columns: &[usize],
+        include: &[usize],
+        where_clause: Option<&crate::sql::parser::Expression>,
+        f: impl FnOnce(&RowIndex) -> R,
+    ) -> Result<R, ExecutionError> {
+        let table_rows = self.table_data.get(&table_id)
+            .ok_or_else(|| ExecutionError::TableNotFound { table: format!("table_id_{}", table_id) })?;
+        let schema = self.table_schemas.get(&table_id)
+            .ok_or_else(|| ExecutionError::TableNotFound { table: format!("table_id_{}", table_id) })?;
+        let mut row_indexes = self.row_indexes.lock().unwrap_or_else(|e| e.into_inner());
+        let slot = (table_id, columns.to_vec(), where_clause.map(dump::expression_sql));
+        if !row_indexes.get(&slot).is_some_and(|index| index.is_current(columns, include, table_rows.stamp())) {
+            let mut included = row_indexes.get(&slot).map(|index| index.include().to_vec()).unwrap_or_default();
+            for &column in include {
+                if !included.contains(&column) {
+                    included.push(column);
+                }
+            }
+            let rows = table_rows.rows(&self.buffer_pool)?;
+            let members = rows.iter()
+                .zip(self.row_ids(table_id).iter().copied())
+                .filter(|(row, _)| self.in_partial_index(where_clause, schema, row));
+            let index = RowIndex::build(columns.to_vec(), included, where_clause.cloned(), members, table_rows.stamp());
+            row_indexes.insert(slot.clone(), index);
+        }
+        Ok(f(&row_indexes[&slot]))
+    }
+    
+    /// 行是否属于索引：普通索引包含全部行，部分索引只包含谓词为真的行（求值出错按不满足处理）
+    fn in_partial_index(&self, where_clause: Option<&crate::sql::parser::Expression>, schema: &Schema, row: &Tuple) -> bool {
+        where_clause.is_none_or(|where_clause| matches!(self.evaluate_where_condition(where_clause, row, schema), Ok(true)))
+    }
+    
+    /// 在表末尾追加一行并分配 rowid；已建立的内存索引随之加入该行
+    fn append_row(&mut self, table_id: u32, row: Tuple) -> Result<u64, ExecutionError> {
+        let table_rows = self.table_data.get_mut(&table_id)
+            .ok_or_else(|| ExecutionError::TableNotFound { table: format!("table_id_{}", table_id) })?;
+        let previous_stamp = table_rows.stamp();
+        table_rows.push(&self.buffer_pool, row.clone())?;
+        let stamp = table_rows.stamp();
+        let row_id = self.table_row_ids.entry(table_id).or_default().push();
+        
+        self.maintain_row_indexes(table_id, Some(&row), |index, member| match member {
+            true => index.insert(&row, row_id, previous_stamp, stamp),
+            false => index.advance(previous_stamp, stamp),
+        });
+        self.log_change(WalRecord::Insert { table_id, row_id, row });
+        Ok(row_id)
+    }
+    
+    /// 把表中第 position 行由 old 替换为 row；已建立的内存索引随之移去旧键、加入新键
+    fn update_row(&mut self, table_id: u32, position: usize, old: &Tuple, row: Tuple) -> Result<(), ExecutionError> {
+        let table_rows = self.table_data.get_mut(&table_id)
+            .ok_or_else(|| ExecutionError::TableNotFound { table: format!("table_id_{}", table_id) })?;
+        let previous_stamp = table_rows.stamp();
+        table_rows.set(&self.buffer_pool, position, row.clone())?;
+        let stamp = table_rows.stamp();
+        let row_id = self.row_id_at(table_id, position);
+        
+        // 新值不再满足部分索引的谓词时只移去旧索引项
+        self.maintain_row_indexes(table_id, Some(&row), |index, member| match member {
+            true => index.update(old, &row, row_id, previous_stamp, stamp),
+            false => index.remove(old, row_id, previous_stamp, stamp),
+        });
+        self.log_change(WalRecord::Update { table_id, row_id, row });
+        Ok(())
+    }
+    
+    /// 删除表中第 position 行（其值为 old）及其 rowid；已建立的内存索引随之移去该行
+    fn delete_row(&mut self, table_id: u32, position: usize, old: &Tuple) -> Result<(), ExecutionError> {
+        let table_rows = self.table_data.get_mut(&table_id)
+            .ok_or_else(|| ExecutionError::TableNotFound { table: format!("table_id_{}", table_id) })?;
+        let previous_stamp = table_rows.stamp();
+        table_rows.remove(&self.buffer_pool, position)?;
+        let stamp = table_rows.stamp();
+        let row_ids = self.table_row_ids.entry(table_id).or_default();
+        if position >= row_ids.ids.len() {
+            return Ok(());
+        }
+        let row_id = row_ids.ids.remove(position);
+        
+        self.maintain_row_indexes(table_id, None, |index, _| index.remove(old, row_id, previous_stamp, stamp));
+        self.log_change(WalRecord::Delete { table_id, row_id });
+        Ok(())
+    }
+    
+    /// 对表上每个已建立的内存索引调用 f，并告知 row 是否属于该索引（row 为 None 时为 false）
+    fn maintain_row_indexes(&self, table_id: u32, row: Option<&Tuple>, mut f: impl FnMut(&mut RowIndex, bool)) {
+        let Some(schema) = self.table_schemas.get(&table_id) else {
+            return;
+        };
+        let mut row_indexes = self.row_indexes.lock().unwrap_or_else(|e| e.into_inner());
+        for ((id, _, _), index) in row_indexes.iter_mut() {
+            if *id == table_id {
+                let member = row.is_some_and(|row| self.in_partial_index(index.where_clause(), schema, row));
+                f(index, member);
+            }
+        }
+    }
+    
+    /// 在行和模式末尾附加 rowid 伪列，ids 与 rows 同序
+    fn with_rowid_column(ids: &[u64], rows: &[Tuple], schema: &Schema) -> (Vec<Tuple>, Schema) {
+        let rows = rows.iter()
+            .enumerate()
+            .map(|(i, row)| Self::with_rowid(row.clone(), ids.get(i).copied()))
+            .collect();
+        (rows, Self::rowid_schema(schema))
+    }
+    
+    /// 在行末附加 rowid 伪列的值
+    fn with_rowid(mut row: Tuple, id: Option<u64>) -> Tuple {
+        row.values.push(id.map_or(Value::Null, |id| Value::BigInt(id as i64)));
+        row
+    }
+    
+    /// 在模式末尾附加 rowid 伪列
+    fn rowid_schema(schema: &Schema) -> Schema {
+        let mut schema = schema.clone();
+        schema.columns.push(ColumnDefinition {
+            name: ROWID_COLUMN.to_string(),
+            data_type: DataType::BigInt,
+            nullable: false,
+            default: None,
+            default_function: None,
+        });
+        schema
+    }
+    
+    /// ALTER TABLE ... SET COMPRESSION：记录表的压缩方式并立即用它重写表的页文件
+    fn set_table_compression(
+        &mut self,
+        table_id: u32,
+        table_name: String,
+        method: Option<String>,
+    ) -> Result<QueryResult, ExecutionError> {
+        let compression = match &method {
+            Some(name) => {
+                let compression = Compression::from_name(name).ok_or_else(|| ExecutionError::SemanticError(format!(
+                    "unknown compression method '{}' (expected one of: {})",
+                    name,
+                    Compression::ALL.map(Compression::name).join(", ")
+                )))?;
+                if !compression.is_supported() {
+                    return Err(ExecutionError::StorageError(CompressionError::Unsupported(compression).to_string()));
+                }
+                self.table_compression.insert(table_id, compression);
+                compression
+            }
+            None => {
+                self.table_compression.remove(&table_id);
+                self.config.page_compression
+            }
+        };
+        
+        self.save_table(table_id, &table_name)?;
+        Ok(QueryResult {
+            rows: vec![],
+            schema: None,
+            affected_rows: 0,
+            message: format!("Table '{}' now uses {} page compression", table_name, compression),
+        })
+    }
+    
+    /// 表页文件使用的压缩方式：ALTER TABLE 设置的值，否则为全局配置
+    fn effective_compression(&self, table_id: u32) -> Compression {
+        self.table_compression.get(&table_id).copied().unwrap_or(self.config.page_compression)
+    }
+    
+    /// 表的页压缩方式，表不存在时为 None
+    pub fn table_compression(&self, table_name: &str) -> Option<Compression> {
+        self.table_catalog.get(table_name).map(|&table_id| self.effective_compression(table_id))
+    }
+    
+    /// 执行 ALTER TABLE：在已有数据上添加约束（先校验现有行再修改模式），或设置表的页压缩方式
+    fn execute_alter_table(
+        &mut self,
+        table_name: String,
+        action: crate::sql::parser::AlterTableAction,
+    ) -> Result<QueryResult, ExecutionError> {
+        use crate::sql::parser::{AlterTableAction, TableConstraint};
+        
+        let table_id = *self.table_catalog.get(&table_name)
+            .ok_or_else(|| ExecutionError::TableNotFound { table: table_name.clone() })?;
+        let (name, constraint) = match action {
+            AlterTableAction::AddConstraint { name, constraint } => (name, constraint),
+            AlterTableAction::SetCompression { method } => {
+                return self.set_table_compression(table_id, table_name, method);
+            }
+        };
+        let schema = self.table_schemas.get(&table_id)
+            .ok_or_else(|| ExecutionError::TableNotFound { table: table_name.clone() })?;
+        let rows = self.table_rows(table_id)?;
+        let rows = &*rows;
+        
+        let message = match constraint {
+            TableConstraint::PrimaryKey(columns) => {
+                if schema.primary_key.is_some() {
+                    return Err(ExecutionError::SemanticError(
+                        format!("table '{}' already has a primary key", table_name)
+                    ));
+                }
+                let positions = Self::column_positions(schema, &table_name, &columns)?;
+                for row in rows {
+                    if let Some(&i) = positions.iter().find(|&&i| row.values[i].is_null()) {
+                        return Err(ExecutionError::NotNullViolation { column: schema.columns[i].name.clone() });
+                    }
+                }
+                if let Some(row) = find_duplicate_key(rows, &positions) {
+                    return Err(ExecutionError::PrimaryKeyViolation { key: format_key(row, &positions) });
+                }
+                
+                let index = IndexInfo {
+                    name: name.unwrap_or_else(|| format!("{}_pkey", table_name)),
+                    columns,
+                    unique: true,
+                    include: Vec::new(),
+                    where_clause: None,
+                    build: None,
+                };
+                self.add_index(table_id, &table_name, index.clone())?;
+                let schema = self.table_schemas.get_mut(&table_id).unwrap();
+                for &i in &positions {
+                    schema.columns[i].nullable = false;
+                }
+                schema.primary_key = Some(positions);
+                format!("Added PRIMARY KEY '{}' to table '{}'", index.name, table_name)
+            }
+            TableConstraint::Unique(columns) => {
+                let positions = Self::column_positions(schema, &table_name, &columns)?;
+                let index = IndexInfo {
+                    name: name.unwrap_or_else(|| format!("{}_{}_key", table_name, columns.join("_"))),
+                    columns,
+                    unique: true,
+                    include: Vec::new(),
+                    where_clause: None,
+                    build: None,
+                };
+                if let Some(row) = find_duplicate_key(rows, &positions) {
+                    return Err(Self::unique_violation(&index, row, &positions));
+                }
+                
+                self.add_index(table_id, &table_name, index.clone())?;
+                format!("Added UNIQUE constraint '{}' to table '{}'", index.name, table_name)
+            }
+            // 外键需要在之后的每次写入时检查，引擎尚不支持，不能只校验一次就声称已添加
+            TableConstraint::ForeignKey { .. } => {
+                return Err(ExecutionError::NotImplemented {
+                    feature: "ALTER TABLE ADD FOREIGN KEY".to_string(),
+                    capability: Capability::ForeignKeys,
+                });
+            }
+        };
+        
+        if let Err(e) = self.save_table(table_id, &table_name) {
+            println!("Warning: Failed to save table data: {}", e);
+        }
+        
+        Ok(QueryResult {
+            rows: vec![],
+            schema: None,
+            affected_rows: 0,
+            message,
+        })
+    }
+    
+    /// 按名称解析列下标
+    fn column_positions(schema: &Schema, table_name: &str, columns: &[String]) -> Result<Vec<usize>, ExecutionError> {
+        columns.iter()
+            .map(|name| schema.columns.iter().position(|col| col.name == *name).ok_or_else(|| {
+                ExecutionError::ColumnNotFound { table: table_name.to_string(), column: name.clone() }
+            }))
+            .collect()
+    }
+    
+    /// 为表登记新索引，名称或键列（连同部分索引的谓词）与已有索引重复时报错
+    fn add_index(&mut self, table_id: u32, table_name: &str, index: IndexInfo) -> Result<(), ExecutionError> {
+        let indexes = self.table_indexes.entry(table_id).or_default();
+        let duplicate = |existing: &IndexInfo| {
+            existing.name == index.name || (existing.columns == index.columns && existing.where_clause == index.where_clause)
+        };
+        if let Some(existing) = indexes.iter().find(|existing| duplicate(existing)) {
+            return Err(ExecutionError::SemanticError(format!(
+                "index '{}' on table '{}' already covers ({})", existing.name, table_name, existing.columns.join(", ")
+            )));
+        }
+        indexes.push(index);
+        Ok(())
+    }
+    
+    /// 执行 DEDUPLICATE TABLE：删除完全重复的行，每组保留 rowid 最小的一行
+    fn execute_deduplicate(&mut self, table_name: String) -> Result<QueryResult, ExecutionError> {
+        let table_id = *self.table_catalog.get(&table_name)
+            .ok_or_else(|| ExecutionError::TableNotFound { table: table_name.clone() })?;
+        let rows = self.table_rows(table_id)?.into_owned();
+        let mut seen = std::collections::HashSet::new();
+        let keep: Vec<bool> = rows.iter().map(|row| seen.insert(row.values.clone())).collect();
+        
+        // 从后往前删除，前面各行的位置不变
+        let original_count = rows.len();
+        let mut removed = 0;
+        for (position, row) in rows.iter().enumerate().rev() {
+            if !keep[position] {
+                self.delete_row(table_id, position, row)?;
+                removed += 1;
+            }
+        }
+        
+        Ok(QueryResult {
+            rows: vec![],
+            schema: None,
+            affected_rows: removed,
+            message: format!("Removed {} duplicate row(s) from table '{}' ({} remaining)",
+                removed, table_name, original_count - removed),
+        })
+    }
+    
+    /// 执行 DRY RUN：只评估 WHERE 条件，返回将受影响的行而不做任何修改
+    fn execute_dry_run(&self, statement: Statement) -> Result<QueryResult, ExecutionError> {
+        let (table_name, where_clause, assignments, verb) = match statement {
+            Statement::Update { table_name, assignments, where_clause, .. } => {
+                (table_name, where_clause, assignments, "update")
+            }
+            Statement::Delete { table_name, where_clause, .. } => {
+                (table_name, where_clause, Vec::new(), "delete")
+            }
+            _ => return Err(ExecutionError::NotImplemented {
+                feature: "DRY RUN is only supported for UPDATE and DELETE".to_string(),
+                capability: Capability::DryRun,
+            }),
+        };
+        
+        let table_id = *self.table_catalog.get(&table_name)
+            .ok_or_else(|| ExecutionError::TableNotFound { table: table_name.clone() })?;
+        let schema = self.table_schemas.get(&table_id)
+            .ok_or_else(|| ExecutionError::TableNotFound { table: table_name.clone() })?;
+        let rows = self.table_rows(table_id)?;
+        
+        // Catch typos in SET targets as the real statement would
+        for assignment in &assignments {
+            if !schema.columns.iter().any(|col| col.name == assignment.column) {
+                return Err(ExecutionError::ColumnNotFound {
+                    table: table_name.clone(),
+                    column: assignment.column.clone(),
+                });
+            }
+        }
+        
+        let matched: Vec<Tuple> = self.matching_row_indices(table_id, &rows, where_clause.as_ref(), schema)
+            .into_iter()
+            .map(|i| rows[i].clone())
+            .collect();
+        
+        Ok(QueryResult {
+            message: format!(
+                "DRY RUN: would {} {} of {} row(s) in table '{}'; no changes made",
+                verb, matched.len(), rows.len(), table_name
+            ),
+            rows: matched,
+            schema: Some(schema.clone()),
+            affected_rows: 0,
+        })
+    }
+    
+    /// Execute DELETE statement (simplified)
+    fn execute_delete_simple(
+        &mut self,
+        table_name: String,
+        where_clause: Option<crate::sql::parser::Expression>,
+        returning: Option<crate::sql::parser::SelectList>,
+    ) -> Result<QueryResult, ExecutionError> {
+        // Get table metadata first
+        let table_id = self.table_catalog.get(&table_name)
+            .ok_or_else(|| ExecutionError::TableNotFound { table: table_name.clone() })?;
+        
+        let table_id = *table_id;
+        let schema = self.table_schemas.get(&table_id)
+            .ok_or_else(|| ExecutionError::TableNotFound { table: table_name.clone() })?
+            .clone();
+        
+        // Get immutable reference to evaluate WHERE conditions
+        let table_data_snapshot = self.table_rows(table_id)?.into_owned();
+        
+        let original_count = table_data_snapshot.len();
+        
+        // Evaluate which rows should be deleted
+        let mut indices_to_delete = self.matching_row_indices(table_id, &table_data_snapshot, where_clause.as_ref(), &schema);
+        self.lock_rows(table_id, &table_name, &indices_to_delete)?;
+        
+        // Capture deleted rows (in table order) before removing them
+        let returned_rows: Vec<Tuple> = if returning.is_some() {
+            indices_to_delete.iter().map(|&i| table_data_snapshot[i].clone()).collect()
+        } else {
+            Vec::new()
+        };
+        
+        // Sort indices in descending order to delete from back to front
+        indices_to_delete.sort_by(|a, b| b.cmp(a));
+        
+        let row_count = self.table_data.get(&table_id)
+            .ok_or_else(|| ExecutionError::TableNotFound { table: table_name.clone() })?
+            .len();
+        for &index in &indices_to_delete {
+            if index < row_count {
+                self.delete_row(table_id, index, &table_data_snapshot[index])?;
+            }
+        }
+        
+        let deleted_count = indices_to_delete.len();
+        
+        let (rows, result_schema) = match returning {
+            Some(list) => {
+                let (rows, returning_schema) = self.project_returning(&returned_rows, &list, &schema, &table_name)?;
+                (rows, Some(returning_schema))
+            }
+            None => (vec![], None),
+        };
+        
+        Ok(QueryResult {
+            rows,
+            schema: result_schema,
+            affected_rows: deleted_count,
+            message: format!("Deleted {} row(s) from table '{}' (total was: {})", 
+                deleted_count, table_name, original_count),
+        })
+    }
+    
+    /// 检查点：把全部表写入快照、刷写缓冲池脏页，然后把 WAL 截断为一条检查点记录，返回其 LSN
+    ///
+    /// 不能在显式事务中执行。
+    pub fn checkpoint(&mut self) -> Result<Lsn, ExecutionError> {
+        self.ensure_open()?;
+        if self.transaction.is_some() {
+            return Err(ExecutionError::SemanticError(
+                "CHECKPOINT cannot run inside a transaction; COMMIT or ROLLBACK first".to_string(),
+            ));
+        }
+        self.buffer_pool.flush_all()
+            .map_err(|e| ExecutionError::StorageError(format!("Failed to flush buffer pool: {}", e)))?;
+        self.write_checkpoint()
+    }
+    
+    /// 在线备份：把已提交的状态（元数据、表快照、快照引用的堆文件和 WAL）复制到一个新目录，返回备份清单
+    ///
+    /// 目录不存在时创建，已存在时必须为空。不能在显式事务中执行。备份目录可以直接作为数据目录打开，
+    /// 加密数据库的备份使用同一个密钥。
+    #[cfg(feature = "persistence")]
+    pub fn backup<P: AsRef<Path>>(&mut self, dir: P) -> Result<BackupManifest, ExecutionError> {
+        self.ensure_open()?;
+        if self.transaction.is_some() {
+            return Err(ExecutionError::SemanticError(
+                "BACKUP cannot run inside a transaction; COMMIT or ROLLBACK first".to_string(),
+            ));
+        }
+        self.flush_wal()?;
+        
+        let dir = dir.as_ref();
+        backup::create_target(dir)?;
+        let target = backup::open_directory(dir, self.config.encryption_key.as_ref())?;
+        
+        let mut table_ids: Vec<u32> = self.table_catalog.values().copied().collect();
+        table_ids.sort_unstable();
+        let blobs = [METADATA_FILE_NAME.to_string(), WAL_FILE_NAME.to_string()]
+            .into_iter()
+            .chain(table_ids.iter().flat_map(|&table_id| [format!("table_{}.json", table_id), index_file_name(table_id)]));
+        for name in blobs {
+            let data = self.backend.read(&name)
+                .map_err(|e| backup::backup_error(&format!("Failed to read '{}'", name), e))?;
+            if let Some(data) = data {
+                target.write(&name, &data)
+                    .map_err(|e| backup::backup_error(&format!("Failed to write '{}'", name), e))?;
+            }
+        }
+        
+        // 快照引用的堆文件写好后不再修改，按原样复制（加密的页仍然加密）
+        if let Some(file_manager) = &self.file_manager {
+            for (&table_id, &generation) in &self.table_heap_generations {
+                let source = file_manager.file_path(&heap_file_name(table_id, generation));
+                if let Some(file_name) = source.file_name() {
+                    backup::copy_file(&source, &dir.join(file_name))?;
+                }
+            }
+        }
+        
+        let manifest = BackupManifest::write(dir, self.wal.last_lsn(), self.config.encryption_key.is_some())?;
+        log::info!("Backup to '{}' at LSN {} ({} files)", dir.display(), manifest.lsn, manifest.files.len());
+        Ok(manifest)
+    }
+    
+    /// 从备份恢复数据目录：先按清单校验备份中每个文件的大小和校验和，全部通过后把文件复制到 data_dir，返回备份清单
+    ///
+    /// data_dir 不存在时创建，已存在时必须为空。恢复后用 [`Database::new`]（加密的备份用 [`Database::with_encryption_key`]）打开。
+    #[cfg(feature = "persistence")]
+    pub fn restore<P: AsRef<Path>, Q: AsRef<Path>>(backup_dir: P, data_dir: Q) -> Result<BackupManifest, ExecutionError> {
+        let manifest = backup::restore(backup_dir.as_ref(), data_dir.as_ref())?;
+        log::info!("Restored backup at LSN {} into '{}'", manifest.lsn, data_dir.as_ref().display());
+        Ok(manifest)
+    }
+    
+    /// 时间点恢复：恢复备份后重放 WAL 归档中备份之后提交的批次，直到给定的 LSN 或时间
+    ///
+    /// config 需要设置 [`DatabaseConfig::wal_archive_dir`]（以及加密数据库的密钥）。
+    /// data_dir 的要求与 [`Database::restore`] 相同，之后用同一个配置打开即完成重放。
+    /// 恢复后的数据库从较早的 LSN 继续写入，应改用新的归档目录并重新备份。
+    #[cfg(feature = "persistence")]
+    pub fn restore_to<P: AsRef<Path>, Q: AsRef<Path>>(
+        backup_dir: P,
+        data_dir: Q,
+        target: RecoveryTarget,
+        config: &DatabaseConfig,
+    ) -> Result<RecoveryInfo, ExecutionError> {
+        let info = backup::restore_to(backup_dir.as_ref(), data_dir.as_ref(), target, config)?;
+        log::info!(
+            "Restored backup at LSN {} into '{}' and replayed {} archived batch(es) up to LSN {}",
+            info.backup_lsn, data_dir.as_ref().display(), info.batches, info.recovered_lsn
+        );
+        Ok(info)
+    }
+    
+    /// 把全部表导出为 SQL：每张表依次是 CREATE TABLE、逐行的 INSERT 和 CREATE INDEX 语句
+    ///
+    /// 表按建立顺序输出。输出可以用 [`Database::execute_script`] 导入另一个 MiniDB，
+    /// 也可以直接交给 SQLite 或 PostgreSQL（DOUBLE 列在 PostgreSQL 中需写作 DOUBLE PRECISION）。
+    /// 显式事务中导出时包含事务尚未提交的修改。
+    pub fn dump_sql<W: std::io::Write>(&self, mut writer: W) -> Result<(), ExecutionError> {
+        let write_error = |e: std::io::Error| ExecutionError::StorageError(format!("Failed to write SQL dump: {}", e));
+        let mut tables: Vec<(&String, u32)> = self.table_catalog.iter().map(|(name, &id)| (name, id)).collect();
+        tables.sort_by_key(|&(_, table_id)| table_id);
+        
+        writeln!(writer, "-- MiniDB {} SQL dump", crate::VERSION).map_err(write_error)?;
+        for (table_name, table_id) in tables {
+            let schema = self.table_schemas.get(&table_id)
+                .ok_or_else(|| ExecutionError::TableNotFound { table: table_name.clone() })?;
+            writeln!(writer).map_err(write_error)?;
+            writeln!(writer, "{}", dump::create_table_sql(table_name, schema)).map_err(write_error)?;
+            for row in self.table_rows(table_id)?.iter() {
+                writeln!(writer, "{}", dump::insert_sql(table_name, schema, row)).map_err(write_error)?;
+            }
+            for index in self.table_indexes.get(&table_id).into_iter().flatten() {
+                writeln!(writer, "{}", dump::create_index_sql(table_name, index)).map_err(write_error)?;
+            }
+        }
+        writer.flush().map_err(write_error)
+    }
+    
+    /// 关闭数据库：回滚未提交的事务，停止后台写页线程，写回缓冲池的全部脏页并 fsync 页文件，
+    /// 最后写入正常关闭标记
+    ///
+    /// 已提交的修改在提交时就已写入 WAL，关闭不需要再保存表快照。之后执行语句返回
+    /// [`ExecutionError::DatabaseClosed`]，再次调用 close 不做任何事。数据库被丢弃时会自动关闭，
+    /// 但那时的错误只能记录到日志中，需要处理错误时应显式调用。
+    pub fn close(&mut self) -> Result<(), ExecutionError> {
+        if self.closed {
+            return Ok(());
+        }
+        self.closed = true;
+        
+        if let Some(transaction) = self.transaction.take() {
+            self.discard_transaction(transaction.id)?;
+        }
+        self.stop_background_writer();
+        self.buffer_pool.flush_all()
+            .map_err(|e| ExecutionError::StorageError(format!("Failed to flush buffer pool: {}", e)))?;
+        if let Some(file_manager) = &self.file_manager {
+            file_manager.sync_all()
+                .map_err(|e| ExecutionError::StorageError(format!("Failed to sync page files: {}", e)))?;
+        }
+        self.write_clean_shutdown_marker()
+    }
+    
+    /// 是否已经关闭
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+    
+    /// 打开时是否发现上次正常关闭的标记（为 false 时上次可能是崩溃退出，已提交的修改由 WAL 重放恢复）
+    pub fn opened_after_clean_shutdown(&self) -> bool {
+        self.clean_shutdown
+    }
+    
+    /// WAL 中最后一条已提交记录的 LSN（未启用 persistence 特性时为 0）
+    pub fn last_lsn(&self) -> Lsn {
+        #[cfg(feature = "persistence")]
+        let lsn = self.wal.last_lsn();
+        #[cfg(not(feature = "persistence"))]
+        let lsn = 0;
+        lsn
+    }
+    
+    /// 已关闭时拒绝执行
+    fn ensure_open(&self) -> Result<(), ExecutionError> {
+        if self.closed {
+            return Err(ExecutionError::DatabaseClosed);
+        }
+        Ok(())
+    }
+    
+    /// 数据目录（或内存后端）当前占用的字节数，包括快照、WAL、页文件和临时文件
+    pub fn data_size(&self) -> Result<u64, ExecutionError> {
+        #[cfg(feature = "persistence")]
+        let size = self.backend.size()
+            .map_err(|e| ExecutionError::StorageError(format!("Failed to measure data directory: {}", e)))?;
+        #[cfg(not(feature = "persistence"))]
+        let size = 0;
+        Ok(size)
+    }
+    
+    /// 缓冲池统计：帧的使用情况和页面命中 / 未命中次数
+    ///
+    /// 有数据目录时表的行保存在工作堆文件中，查询和修改的每次页访问都计入统计。
+    pub fn buffer_pool_stats(&self) -> Result<BufferStats, ExecutionError> {
+        self.buffer_pool.stats()
+            .map_err(|e| ExecutionError::StorageError(format!("Buffer pool error: {}", e)))
+    }
+    
+    /// 执行 SET name = value
+    fn execute_set_variable(&mut self, name: &str, value: &str) -> Result<QueryResult, ExecutionError> {
+        match name {
+            "buffer_policy" => {
+                let policy = CachePolicyType::from_name(value).ok_or_else(|| ExecutionError::SemanticError(format!(
+                    "Unknown buffer policy '{}'; expected one of: {}",
+                    value,
+                    CachePolicyType::ALL.map(CachePolicyType::name).join(", "),
+                )))?;
+                self.set_buffer_pool(self.config.buffer_pool_size, policy)?;
+            }
+            "buffer_pool_size" => {
+                let pages = value.parse::<usize>().ok().filter(|&pages| pages > 0).ok_or_else(|| {
+                    ExecutionError::SemanticError(format!("buffer_pool_size must be a positive number of pages, got '{}'", value))
+                })?;
+                self.set_buffer_pool(pages, self.config.buffer_policy)?;
+            }
+            "result_cache_entries" => {
+                let entries = value.parse::<usize>().map_err(|_| {
+                    ExecutionError::SemanticError(format!("result_cache_entries must be a number of results (0 disables the cache), got '{}'", value))
+                })?;
+                self.set_result_cache_entries(entries);
+            }
+            "sort_work_mem_bytes" => {
+                let bytes = value.parse::<usize>().map_err(|_| {
+                    ExecutionError::SemanticError(format!("sort_work_mem_bytes must be a number of bytes, got '{}'", value))
+                })?;
+                self.config.sort_work_mem_bytes = bytes;
+            }
+            "truncate_strings" => {
+                let enabled = match value.to_lowercase().as_str() {
+                    "on" | "true" | "1" => true,
+                    "off" | "false" | "0" => false,
+                    _ => return Err(ExecutionError::SemanticError(format!("truncate_strings must be on or off, got '{}'", value))),
+                };
+                self.set_truncate_strings(enabled);
+            }
+            _ => {
+                return Err(ExecutionError::SemanticError(format!(
+                    "Unknown setting '{}'; expected buffer_policy, buffer_pool_size, result_cache_entries, sort_work_mem_bytes or truncate_strings",
+                    name
+                )))
+            }
+        }
+        Ok(Self::message_result(format!("SET {} = {}", name, value)))
+    }
+    
+    /// 以新的页数和替换策略重建缓冲池
+    ///
+    /// 旧缓冲池的脏页先全部写回，新缓冲池从空开始按需读页；后台写页线程按原来的间隔改为服务新缓冲池。
+    pub fn set_buffer_pool(&mut self, pages: usize, policy: CachePolicyType) -> Result<(), ExecutionError> {
+        if pages == 0 {
+            return Err(ExecutionError::SemanticError("Buffer pool needs at least one page".to_string()));
+        }
+        let interval = self.background_writer.as_ref().map(BackgroundWriter::interval);
+        self.stop_background_writer();
+        let flushed = self.buffer_pool.flush_all();
+        if flushed.is_ok() {
+            self.buffer_pool = std::sync::Arc::new(BufferPool::with_policy(pages, policy));
+            self.config.buffer_pool_size = pages;
+            self.config.buffer_policy = policy;
+        }
+        if let Some(interval) = interval {
+            self.start_background_writer(interval);
+        }
+        flushed.map_err(|e| ExecutionError::StorageError(format!("Failed to flush buffer pool: {}", e)))
+    }
+    
+    /// 缓冲池当前的页数和替换策略
+    pub fn buffer_pool_settings(&self) -> (usize, CachePolicyType) {
+        (self.config.buffer_pool_size, self.config.buffer_policy)
+    }
+    
+    /// 启动后台写页线程，每隔 interval 写回最多 `background_flush_pages` 个未被固定的脏页
+    ///
+    /// 已在运行的线程先停止再按新间隔启动。内存数据库没有页文件，调用不做任何事。
+    pub fn start_background_writer(&mut self, interval: std::time::Duration) {
+        if self.file_manager.is_none() {
+            return;
+        }
+        self.stop_background_writer();
+        self.background_writer = Some(BackgroundWriter::start(
+            self.buffer_pool.clone(),
+            interval,
+            self.config.background_flush_pages,
+        ));
+    }
+    
+    /// 停止后台写页线程并等待正在进行的写回完成，返回它累计写回的页数（未运行时为 0）
+    ///
+    /// 数据库被丢弃时线程也会停止；关闭前调用可确保之后不再有后台写入。
+    pub fn stop_background_writer(&mut self) -> u64 {
+        match self.background_writer.take() {
+            Some(mut writer) => {
+                writer.stop();
+                writer.pages_written()
+            }
+            None => 0,
+        }
+    }
+    
+    /// 后台写页线程是否在运行
+    pub fn background_writer_running(&self) -> bool {
+        self.background_writer.as_ref().is_some_and(BackgroundWriter::is_running)
+    }
+    
+    /// 获取临时文件管理器（供溢出到磁盘的算子使用），内存数据库为 None
+    pub fn temp_files(&self) -> Option<&TempFileManager> {
+        self.temp_files.as_ref()
+    }
+    
+    /// 打开数据库时使用的配置
+    pub fn config(&self) -> &DatabaseConfig {
+        &self.config
+    }
+    
+    /// 引擎支持的 SQL 能力清单，供工具据此调整生成的 SQL
+    pub fn capabilities(&self) -> Vec<CapabilityInfo> {
+        Capability::ALL.iter().map(|capability| capability.info()).collect()
+    }
+    
+    /// 是否至少部分支持某项能力
+    pub fn supports(&self, capability: Capability) -> bool {
+        capability.info().is_supported()
+    }
+    
+    /// 主键和 UNIQUE 约束是否推迟到提交时检查（SET CONSTRAINTS ALL DEFERRED）
+    pub fn constraints_deferred(&self) -> bool {
+        self.constraints_deferred
+    }
+    
+    /// 当前是否处于显式事务中
+    pub fn in_transaction(&self) -> bool {
+        self.transaction.is_some()
+    }
+    
+    /// 开启或关闭 ORDER BY 并列行的确定性排序；缓存的结果按原来的顺序排列，随之清空
+    pub fn set_deterministic_sort(&mut self, enabled: bool) {
+        self.config.deterministic_sort = enabled;
+        self.result_cache.clear();
+    }
+    
+    /// 开启或关闭安全模式：不带 WHERE 的 UPDATE / DELETE 必须写出 ALL
+    pub fn set_safe_updates(&mut self, enabled: bool) {
+        self.config.safe_updates = enabled;
+    }
+    
+    /// 写入 VARCHAR(n) / CHAR(n) 列的字符串超长时截断（开启）或报错（关闭，默认）
+    pub fn set_truncate_strings(&mut self, enabled: bool) {
+        self.config.truncate_strings = enabled;
+    }
+    
+    /// 开启或关闭 GROUP BY 结果按分组键排序输出；缓存的结果随之清空
+    pub fn set_deterministic_grouping(&mut self, enabled: bool) {
+        self.config.deterministic_grouping = enabled;
+        self.result_cache.clear();
+    }
+    
+    /// 表的数据版本：表被创建或有行被修改时变为更大的值，表不存在时为 None
+    ///
+    /// 版本号在整个数据库实例内单调递增（删除后重建的表也会得到更大的版本）。
+    /// 缓存查询结果的应用可以记下版本，之后比较以判断结果是否过期。版本不持久化，
+    /// 重新打开数据库后从 0 重新计数，因此缓存不能跨越数据库实例。
+    pub fn data_version(&self, table_name: &str) -> Option<u64> {
+        let table_id = self.table_catalog.get(table_name)?;
+        Some(self.table_versions.get(table_id).copied().unwrap_or(0))
+    }
+    
+    /// 开启或关闭查询负载统计
+    pub fn set_workload_tracking(&mut self, enabled: bool) {
+        self.config.track_workload = enabled;
+    }
+    
+    /// 清空 query_stats 和 table_activity 视图的统计
+    pub fn reset_workload_stats(&mut self) {
+        self.workload.reset();
+    }
+    
+    /// 设置查询结果缓存最多保存的结果数；0 清空并关闭缓存
+    pub fn set_result_cache_entries(&mut self, entries: usize) {
+        self.result_cache.set_capacity(entries);
+        self.config.result_cache_entries = entries;
+    }
+    
+    /// 查询结果缓存的命中统计
+    pub fn result_cache_stats(&self) -> ResultCacheStats {
+        self.result_cache.stats()
+    }
+    
+    /// 注册查询改写规则，之后解析的语句在语义分析前按注册顺序应用全部规则
+    ///
+    /// 规则保存在数据库元数据中，重新打开数据库后仍然生效。
+    pub fn add_rewrite_rule(&mut self, rule: RewriteRule) -> Result<(), ExecutionError> {
+        self.rewrite_rules.push(rule);
+        self.save_metadata()
+    }
+    
+    /// 已注册的查询改写规则
+    pub fn rewrite_rules(&self) -> &[RewriteRule] {
+        &self.rewrite_rules
+    }
+    
+    /// 删除第 index 条（从 0 开始）改写规则，返回被删除的规则
+    pub fn remove_rewrite_rule(&mut self, index: usize) -> Result<Option<RewriteRule>, ExecutionError> {
+        if index >= self.rewrite_rules.len() {
+            return Ok(None);
+        }
+        let rule = self.rewrite_rules.remove(index);
+        self.save_metadata()?;
+        Ok(Some(rule))
+    }
+    
+    /// 保存命名查询片段，同名片段被覆盖；片段可以包含多条以分号分隔的语句
+    ///
+    /// 片段保存在数据库元数据中，重新打开数据库后仍然可用。名称只能包含字母、数字和下划线，
+    /// 保存前检查每条语句都能解析（不检查引用的表是否存在）。
+    pub fn save_snippet(&mut self, name: &str, sql: &str) -> Result<(), ExecutionError> {
+        if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            return Err(ExecutionError::SemanticError(format!(
+                "Invalid snippet name '{}': use letters, digits and underscores", name
+            )));
+        }
+        let statements = crate::sql::split_statements(sql);
+        if statements.is_empty() {
+            return Err(ExecutionError::SemanticError(format!("Snippet '{}' has no statements", name)));
+        }
+        for statement in statements {
+            parse_sql(&statement).map_err(|e| self.diagnose_parse_error(e))?;
+        }
+        self.snippets.insert(name.to_string(), sql.trim().to_string());
+        self.save_metadata()
+    }
+    
+    /// 按名称取查询片段
+    pub fn snippet(&self, name: &str) -> Option<&str> {
+        self.snippets.get(name).map(String::as_str)
+    }
+    
+    /// 全部查询片段，按名称排序
+    pub fn snippets(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.snippets.iter().map(|(name, sql)| (name.as_str(), sql.as_str()))
+    }
+    
+    /// 删除查询片段，返回片段是否存在
+    pub fn remove_snippet(&mut self, name: &str) -> Result<bool, ExecutionError> {
+        if self.snippets.remove(name).is_none() {
+            return Ok(false);
+        }
+        self.save_metadata()?;
+        Ok(true)
+    }
+    
+    /// 比对两个结果集，每一侧可以是表名或 SELECT 语句
+    ///
+    /// 结果的第一列 `diff` 为 `-`（只在 A 中）或 `+`（只在 B 中）；键相同而其余列不同的行
+    /// 以 `<`（A 中的值）和 `>`（B 中的值）成对列出，其余列与 A 相同。两侧都是表、
+    /// A 有主键且 B 在同样位置有同名列时按主键匹配，否则以整行匹配。两侧的列数必须相同。
+    pub fn diff(&mut self, left: &str, right: &str) -> Result<QueryResult, ExecutionError> {
+        let (left_table, left_result) = self.diff_input(left)?;
+        let (right_table, right_result) = self.diff_input(right)?;
+        let left_schema = left_result.schema.unwrap_or_else(|| Schema::new(Vec::new()));
+        let right_schema = right_result.schema.unwrap_or_else(|| Schema::new(Vec::new()));
+        if left_schema.columns.len() != right_schema.columns.len() {
+            return Err(ExecutionError::SemanticError(format!(
+                "Cannot diff results with different column counts ({} vs {})",
+                left_schema.columns.len(), right_schema.columns.len()
+            )));
+        }
+        
+        let key = match (left_table, right_table) {
+            (Some(_), Some(_)) => left_schema.primary_key.clone()
+                .filter(|key| key.iter().all(|&i| left_schema.columns[i].name == right_schema.columns[i].name))
+                .unwrap_or_default(),
+            _ => Vec::new(),
+        };
+        let changes = diff::diff_rows(&left_result.rows, &right_result.rows, &key);
+        let count = |kind: diff::Change| changes.iter().filter(|(change, _)| *change == kind).count();
+        let message = format!(
+            "{} row(s) only in A, {} row(s) only in B, {} changed (matched by {})",
+            count(diff::Change::OnlyLeft),
+            count(diff::Change::OnlyRight),
+            count(diff::Change::ChangedLeft),
+            if key.is_empty() { "whole row".to_string() } else {
+                key.iter().map(|&i| left_schema.columns[i].name.as_str()).collect::<Vec<_>>().join(", ")
+            },
+        );
+        
+        Ok(QueryResult {
+            schema: Some(diff::diff_schema(&left_schema)),
+            rows: diff::diff_tuples(changes),
+            affected_rows: 0,
+            message,
+        })
+    }
+    
+    /// 执行比对的一侧：表名按 SELECT * 读取，其余必须是 SELECT 语句；表名一侧同时返回表名
+    fn diff_input(&mut self, input: &str) -> Result<(Option<String>, QueryResult), ExecutionError> {
+        let input = input.trim().trim_end_matches(';').trim();
+        let table = self.table_catalog.contains_key(input).then(|| input.to_string());
+        let statement = if table.is_some() || self.temp_views.contains_key(input) || Self::system_view_schema(input).is_some() {
+            self.parse_with_diagnostics(&format!("SELECT * FROM {}", input))?
+        } else {
+            self.parse_with_diagnostics(input)?
+        };
+        if !matches!(statement, Statement::Select { .. }) {
+            return Err(ExecutionError::SemanticError(
+                "DIFF compares tables or SELECT queries".to_string(),
+            ));
+        }
+        Ok((table, self.execute_statement(statement)?))
+    }
+    
+    /// 根据已记录的查询负载和当前表数据给出建索引建议，按估计收益降序
+    ///
+    /// 只考虑单表 SELECT / UPDATE / DELETE 中 AND 连接的列与常量比较条件；
+    /// 主键和已有索引能覆盖的条件不会再给出建议。需要开启负载统计（默认开启）。
+    pub fn advise(&self) -> Vec<IndexAdvice> {
+        let queries = self.workload.queries().into_iter().map(|(query, stats)| (query.as_str(), stats.calls));
+        advisor::advise(queries, |table_name| {
+            let table_id = self.table_catalog.get(table_name)?;
+            let schema = self.table_schemas.get(table_id)?;
+            let data = self.table_rows(*table_id).ok()?;
+            
+            let distinct = schema.columns.iter().enumerate()
+                .map(|(i, column)| {
+                    let values: std::collections::HashSet<&Value> = data.iter()
+                        .map(|row| &row.values[i])
+                        .filter(|v| !v.is_null())
+                        .collect();
+                    (column.name.clone(), values.len())
+                })
+                .collect();
+            let mut indexes: Vec<Vec<String>> = self.table_indexes.get(table_id)
+                .map(|indexes| indexes.iter()
+                    .filter(|index| index.build.is_none() && index.where_clause.is_none())
+                    .map(|index| index.columns.clone())
+                    .collect())
+                .unwrap_or_default();
+            if let Some(primary_key) = &schema.primary_key {
+                indexes.push(primary_key.iter().map(|&i| schema.columns[i].name.clone()).collect());
+            }
+            Some(advisor::TableStats { row_count: data.len(), distinct, indexes })
+        })
+    }
+    
+    /// 重新设定 RANDOM() 的种子（None 表示以当前时间为种子）
+    pub fn set_random_seed(&mut self, seed: Option<u64>) {
+        self.config.random_seed = seed;
+        self.random.reseed(seed);
+    }
+    
+    /// List all tables in the database
+    pub fn list_tables(&self) -> Vec<String> {
+        self.table_catalog.keys().cloned().collect()
+    }
+    
+    /// 本次会话中的临时视图名，按名称排序
+    pub fn list_views(&self) -> Vec<String> {
+        let mut views: Vec<String> = self.temp_views.keys().cloned().collect();
+        views.sort();
+        views
+    }
+    
+    /// 索引目录：全部表（或 table_name 一张表）上的索引，包括主键，按表名和索引名排序
+    ///
+    /// 大小和索引项数来自内存中的索引结构，尚未建立的索引先由表的行建立。
+    pub fn list_indexes(&self, table_name: Option<&str>) -> Result<Vec<IndexDescription>, ExecutionError> {
+        let mut tables: Vec<(&String, u32)> = match table_name {
+            Some(name) => {
+                let (name, &table_id) = self.table_catalog.get_key_value(name)
+                    .ok_or_else(|| ExecutionError::TableNotFound { table: name.to_string() })?;
+                vec![(name, table_id)]
+            }
+            None => self.table_catalog.iter().map(|(name, &table_id)| (name, table_id)).collect(),
+        };
+        tables.sort();
+        
+        let mut descriptions = Vec::new();
+        for (table_name, table_id) in tables {
+            let schema = self.table_schemas.get(&table_id)
+                .ok_or_else(|| ExecutionError::TableNotFound { table: table_name.clone() })?;
+            let mut indexes = self.lookup_indexes(table_id, table_name);
+            indexes.sort_by(|a, b| a.name.cmp(&b.name));
+            for index in indexes {
+                let (size_bytes, entries) = match index.build {
+                    Some(_) => (None, None),
+                    None => {
+                        let columns = Self::column_positions(schema, table_name, &index.columns)?;
+                        let include = Self::column_positions(schema, table_name, &index.include)?;
+                        let (size, entries) = self.with_row_index(table_id, &columns, &include, index.where_clause.as_ref(), |row_index| {
+                            (row_index.size_bytes(), row_index.entry_count())
+                        })?;
+                        (Some(size), Some(entries))
                     }
-                }
-            }
-            _ => {
-                // 对于其他不支持的表达式类型，返回第一个值但记录警告
-                println!("⚠️ 不支持的表达式类型，使用元组第一个值");
-                Ok(tuple.values.get(0).cloned().unwrap_or(Value::Null))
+                };
+                descriptions.push(IndexDescription {
+                    table: table_name.clone(),
+                    name: index.name,
+                    columns: index.columns,
+                    unique: index.unique,
+                    size_bytes,
+                    entries,
+                });
             }
         }
+        Ok(descriptions)
     }
     
-    /// 值转换为浮点数（用于聚合计算）
-    fn value_to_f64(&self, value: &Value) -> f64 {
+    /// 临时视图的结果模式
+    pub fn get_view_schema(&self, view_name: &str) -> Option<&Schema> {
+        self.temp_views.get(view_name).map(|view| &view.schema)
+    }
+    
+    /// Get table schema by name
+    pub fn get_table_schema(&self, table_name: &str) -> Option<&Schema> {
+        self.table_catalog.get(table_name)
+            .and_then(|&table_id| self.table_schemas.get(&table_id))
+    }
+    
+    /// Get the indexes of a table by name
+    pub fn get_table_indexes(&self, table_name: &str) -> Option<&[IndexInfo]> {
+        self.table_catalog.get(table_name)
+            .and_then(|&table_id| self.table_indexes.get(&table_id))
+            .map(|indexes| indexes.as_slice())
+    }
+
+    /// 系统视图的模式
+    fn system_view_schema(name: &str) -> Option<Schema> {
+        let column = |name: &str, data_type, nullable| ColumnDefinition::new(name.to_string(), data_type, nullable);
+        match name {
+            COLUMN_STATS_VIEW => Some(Schema::new(vec![
+                column("table_name", DataType::Varchar(255), false),
+                column("column_name", DataType::Varchar(255), false),
+                column("data_type", DataType::Varchar(255), false),
+                column("row_count", DataType::BigInt, false),
+                column("null_count", DataType::BigInt, false),
+                column("distinct_count", DataType::BigInt, false),
+                column("min_value", DataType::Varchar(255), true),
+                column("max_value", DataType::Varchar(255), true),
+            ])),
+            QUERY_STATS_VIEW => Some(Schema::new(vec![
+                column("query", DataType::Varchar(255), false),
+                column("calls", DataType::BigInt, false),
+                column("errors", DataType::BigInt, false),
+                column("rows", DataType::BigInt, false),
+                column("total_time_ms", DataType::Double, false),
+                column("mean_time_ms", DataType::Double, false),
+                column("max_time_ms", DataType::Double, false),
+            ])),
+            TABLE_VERSIONS_VIEW => Some(Schema::new(vec![
+                column("table_name", DataType::Varchar(255), false),
+                column("data_version", DataType::BigInt, false),
+            ])),
+            REWRITE_RULES_VIEW => Some(Schema::new(vec![
+                column("position", DataType::BigInt, false),
+                column("rule", DataType::Varchar(255), false),
+            ])),
+            INDEX_BUILDS_VIEW => Some(Schema::new(vec![
+                column("table_name", DataType::Varchar(255), false),
+                column("index_name", DataType::Varchar(255), false),
+                column("rows_done", DataType::BigInt, false),
+                column("total_rows", DataType::BigInt, false),
+            ])),
+            STATISTICS_VIEW => Some(Schema::new(vec![
+                column("table_name", DataType::Varchar(255), false),
+                column("column_name", DataType::Varchar(255), false),
+                column("row_count", DataType::BigInt, false),
+                column("null_count", DataType::BigInt, false),
+                column("distinct_count", DataType::BigInt, false),
+                column("min_value", DataType::Varchar(255), true),
+                column("max_value", DataType::Varchar(255), true),
+                column("histogram", DataType::Varchar(255), false),
+            ])),
+            TABLE_ACTIVITY_VIEW => Some(Schema::new(vec![
+                column("table_name", DataType::Varchar(255), false),
+                column("reads", DataType::BigInt, false),
+                column("writes", DataType::BigInt, false),
+                column("total_time_ms", DataType::Double, false),
+            ])),
+            _ => None,
+        }
+    }
+    
+    /// 值的文本形式（字符串不加引号）
+    fn display_value(value: &Value) -> Value {
         match value {
-            Value::Integer(i) => *i as f64,
-            Value::Float(f) => *f as f64,
-            Value::Double(d) => *d,
-            _ => 0.0,
+            Value::Varchar(s) => Value::Varchar(s.clone()),
+            value => Value::Varchar(value.to_string()),
         }
     }
     
-    /// 比较值用于排序
-    fn compare_values_for_sort(&self, a: &Value, b: &Value) -> std::cmp::Ordering {
-        use std::cmp::Ordering;
-        match (a, b) {
-            (Value::Integer(a), Value::Integer(b)) => a.cmp(b),
-            (Value::Float(a), Value::Float(b)) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
-            (Value::Double(a), Value::Double(b)) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
-            (Value::Varchar(a), Value::Varchar(b)) => a.cmp(b),
-            (Value::Boolean(a), Value::Boolean(b)) => a.cmp(b),
-            (Value::Null, Value::Null) => Ordering::Equal,
-            (Value::Null, _) => Ordering::Less,
-            (_, Value::Null) => Ordering::Greater,
-            _ => Ordering::Equal,
+    /// 物化系统视图；名称不是系统视图时返回 None
+    fn system_view(&self, name: &str) -> Option<(Schema, Vec<Tuple>)> {
+        let schema = Self::system_view_schema(name)?;
+        let rows = match name {
+            QUERY_STATS_VIEW => self.query_stats_rows(),
+            TABLE_ACTIVITY_VIEW => self.table_activity_rows(),
+            TABLE_VERSIONS_VIEW => self.table_versions_rows(),
+            STATISTICS_VIEW => self.statistics_rows(),
+            REWRITE_RULES_VIEW => self.rewrite_rules.iter().enumerate()
+                .map(|(i, rule)| Tuple::new(vec![Value::BigInt(i as i64), Value::Varchar(rule.to_string())]))
+                .collect(),
+            INDEX_BUILDS_VIEW => self.index_builds().into_iter()
+                .filter_map(|(table, index)| {
+                    let build = index.build.as_ref()?;
+                    Some(Tuple::new(vec![
+                        Value::Varchar(table),
+                        Value::Varchar(index.name.clone()),
+                        Value::BigInt(build.rows_done as i64),
+                        Value::BigInt(build.total_rows as i64),
+                    ]))
+                })
+                .collect(),
+            _ => self.column_stats_rows(),
+        };
+        Some((schema, rows))
+    }
+    
+    /// query_stats 视图的行：按累计耗时降序
+    fn query_stats_rows(&self) -> Vec<Tuple> {
+        let ms = |micros: u64| Value::Double(micros as f64 / 1000.0);
+        self.workload.queries().into_iter().map(|(query, stats)| Tuple::new(vec![
+            Value::Varchar(query.clone()),
+            Value::BigInt(stats.calls as i64),
+            Value::BigInt(stats.errors as i64),
+            Value::BigInt(stats.rows as i64),
+            ms(stats.total_micros),
+            Value::Double(stats.total_micros as f64 / 1000.0 / stats.calls as f64),
+            ms(stats.max_micros),
+        ])).collect()
+    }
+    
+    /// table_activity 视图的行：按访问次数降序
+    fn table_activity_rows(&self) -> Vec<Tuple> {
+        self.workload.tables().into_iter().map(|(table, activity)| Tuple::new(vec![
+            Value::Varchar(table.clone()),
+            Value::BigInt(activity.reads as i64),
+            Value::BigInt(activity.writes as i64),
+            Value::Double(activity.total_micros as f64 / 1000.0),
+        ])).collect()
+    }
+    
+    /// table_versions 视图的行：按表名
+    fn table_versions_rows(&self) -> Vec<Tuple> {
+        let mut tables: Vec<&String> = self.table_catalog.keys().collect();
+        tables.sort();
+        tables.into_iter().map(|table| Tuple::new(vec![
+            Value::Varchar(table.clone()),
+            Value::BigInt(self.data_version(table).unwrap_or(0) as i64),
+        ])).collect()
+    }
+    
+    /// statistics 视图的行：已 ANALYZE 的表，按表名、列序；直方图写作递增的边界列表
+    fn statistics_rows(&self) -> Vec<Tuple> {
+        let mut tables: Vec<(&String, &u32)> = self.table_catalog.iter().collect();
+        tables.sort();
+        let mut rows = Vec::new();
+        for (table_name, table_id) in tables {
+            let Some(statistics) = self.table_statistics.get(table_id) else {
+                continue;
+            };
+            for column in &statistics.columns {
+                let bounds: Vec<String> = column.histogram.iter().map(dump::sql_literal).collect();
+                rows.push(Tuple::new(vec![
+                    Value::Varchar(table_name.clone()),
+                    Value::Varchar(column.name.clone()),
+                    Value::BigInt(statistics.row_count as i64),
+                    Value::BigInt(column.null_count as i64),
+                    Value::BigInt(column.distinct_count as i64),
+                    column.min.as_ref().map_or(Value::Null, Self::display_value),
+                    column.max.as_ref().map_or(Value::Null, Self::display_value),
+                    Value::Varchar(format!("[{}]", bounds.join(", "))),
+                ]));
+            }
         }
+        rows
     }
     
-    /// 解析限定列名的索引（支持表别名）
-    fn resolve_qualified_column_index(
+    /// column_stats 视图的行
+    fn column_stats_rows(&self) -> Vec<Tuple> {
+        // 统计按需扫描表数据计算，按表名、列序输出
+        let mut tables: Vec<(&String, &u32)> = self.table_catalog.iter().collect();
+        tables.sort();
+        let mut rows = Vec::new();
+        for (table_name, table_id) in tables {
+            let (Some(table_schema), Ok(data)) = (self.table_schemas.get(table_id), self.table_rows(*table_id)) else {
+                continue;
+            };
+            for (i, column) in table_schema.columns.iter().enumerate() {
+                let values: Vec<&Value> = data.iter().map(|row| &row.values[i]).filter(|v| !v.is_null()).collect();
+                let distinct: std::collections::HashSet<&Value> = values.iter().copied().collect();
+                let min = values.iter().copied().min_by(|a, b| self.compare_values_for_sort(a, b));
+                let max = values.iter().copied().max_by(|a, b| self.compare_values_for_sort(a, b));
+                rows.push(Tuple::new(vec![
+                    Value::Varchar(table_name.clone()),
+                    Value::Varchar(column.name.clone()),
+                    Value::Varchar(column.data_type.to_string()),
+                    Value::BigInt(data.len() as i64),
+                    Value::BigInt((data.len() - values.len()) as i64),
+                    Value::BigInt(distinct.len() as i64),
+                    min.map_or(Value::Null, Self::display_value),
+                    max.map_or(Value::Null, Self::display_value),
+                ]));
+            }
+        }
+        rows
+    }
+    
+    /// 检查元组的主键是否与已有行（skip_index 为被替换的行）重复
+    ///
+    /// 与 COPY 的批量检查一致，含 NULL 的主键也与相同的键冲突。
+    fn check_primary_key_constraint(
         &self,
-        table_name: &str,
-        column_name: &str,
-        schema: &Schema,
-    ) -> Result<usize, ExecutionError> {
-        // 策略1：直接匹配列名（忽略表名）
-        if let Some(index) = schema.columns.iter().position(|col| col.name == column_name) {
-            return Ok(index);
+        new_tuple: &Tuple,
+        primary_key_columns: &[usize],
+        table_id: u32,
+        skip_index: Option<usize>,
+    ) -> Result<(), ExecutionError> {
+        if let Some(&col_index) = primary_key_columns.iter().find(|&&i| i >= new_tuple.values.len()) {
+            return Err(ExecutionError::EvaluationError {
+                message: format!("Primary key column index {} out of bounds", col_index)
+            });
         }
         
-        // 策略2：尝试匹配 "table.column" 格式的列名
-        let qualified_name = format!("{}.{}", table_name, column_name);
-        if let Some(index) = schema.columns.iter().position(|col| col.name == qualified_name) {
-            return Ok(index);
+        let excluded: std::collections::HashSet<u64> = skip_index.map(|i| self.row_id_at(table_id, i)).into_iter().collect();
+        if self.find_key_conflict(table_id, new_tuple, primary_key_columns, true, None, &excluded)?.is_some() {
+            return Err(ExecutionError::PrimaryKeyViolation { key: format_key(new_tuple, primary_key_columns) });
         }
+        Ok(())
+    }
+
+    /// 表上唯一索引的键列位置
+    fn unique_index_keys(&self, table_id: u32) -> Vec<(&IndexInfo, Vec<usize>)> {
+        let schema = match self.table_schemas.get(&table_id) {
+            Some(schema) => schema,
+            None => return Vec::new(),
+        };
         
-        // 策略3：查找以表名开头的列名
-        let table_prefix = format!("{}.", table_name);
-        if let Some(index) = schema.columns.iter().position(|col| {
-            col.name.starts_with(&table_prefix) && 
-            col.name[table_prefix.len()..] == *column_name
-        }) {
-            return Ok(index);
+        self.table_indexes.get(&table_id)
+            .into_iter()
+            .flatten()
+            .filter(|index| index.unique && index.build.is_none())
+            .filter_map(|index| {
+                let positions = index.columns.iter()
+                    .map(|name| schema.columns.iter().position(|col| col.name == *name))
+                    .collect::<Option<Vec<_>>>()?;
+                Some((index, positions))
+            })
+            .collect()
+    }
+    
+    /// 检查元组是否与已有行违反 UNIQUE 约束（skip_index 为被替换的行）
+    fn check_unique_constraints(
+        &self,
+        table_id: u32,
+        tuple: &Tuple,
+        skip_index: Option<usize>,
+    ) -> Result<(), ExecutionError> {
+        for (index, key_columns) in self.unique_index_keys(table_id) {
+            if self.find_conflicting_row(table_id, tuple, &key_columns, index.where_clause.as_ref(), skip_index)?.is_some() {
+                return Err(Self::unique_violation(index, tuple, &key_columns));
+            }
         }
-        
-        // 策略4：模糊匹配（对于JOIN后的合并schema）
-        // 在JOIN的情况下，列名可能被重命名为 table1_column, table2_column 等形式
-        let possible_names = vec![
-            format!("{}_{}", table_name, column_name),
-            format!("{}__{}", table_name, column_name), // 双下划线分隔
-            format!("{}:{}", table_name, column_name),   // 冒号分隔
-        ];
-        
-        for possible_name in possible_names {
-            if let Some(index) = schema.columns.iter().position(|col| col.name == possible_name) {
-                return Ok(index);
+        Ok(())
+    }
+    
+    /// 检查一组完整的行之间是否违反 UNIQUE 约束（NULL 键值和不满足部分索引谓词的行不参与比较）
+    fn check_unique_rows(&self, table_id: u32, rows: &[Tuple]) -> Result<(), ExecutionError> {
+        let Some(schema) = self.table_schemas.get(&table_id) else {
+            return Ok(());
+        };
+        for (index, key_columns) in self.unique_index_keys(table_id) {
+            let members = rows.iter().filter(|row| self.in_partial_index(index.where_clause.as_ref(), schema, row));
+            if let Some(row) = find_duplicate_key(members, &key_columns) {
+                return Err(Self::unique_violation(index, row, &key_columns));
             }
         }
+        Ok(())
+    }
+    
+    /// 检查 UPDATE 后的行是否违反主键或 UNIQUE 约束
+    ///
+    /// 新键按索引与未被更新的行比较，被更新的行之间互相比较；只看被更新的行，不扫描整张表。
+    fn check_updated_keys(&self, table_id: u32, schema: &Schema, updated_rows: &[(usize, Tuple)]) -> Result<(), ExecutionError> {
+        let updated: std::collections::HashSet<u64> = updated_rows.iter().map(|(i, _)| self.row_id_at(table_id, *i)).collect();
+        // (唯一索引，主键为 None, 键列, NULL 键是否参与比较)
+        let mut keys: Vec<(Option<&IndexInfo>, Vec<usize>, bool)> = Vec::new();
+        if let Some(primary_key) = &schema.primary_key {
+            keys.push((None, primary_key.clone(), true));
+        }
+        for (index, key_columns) in self.unique_index_keys(table_id) {
+            keys.push((Some(index), key_columns, false));
+        }
         
-        // 如果所有策略都失败，返回错误
-        Err(ExecutionError::ColumnNotFound {
-            table: table_name.to_string(),
-            column: column_name.to_string(),
-        })
+        for (index, key_columns, compare_nulls) in keys {
+            let where_clause = index.and_then(|index| index.where_clause.as_ref());
+            let mut seen = std::collections::HashSet::new();
+            for (_, row) in updated_rows {
+                if !self.in_partial_index(where_clause, schema, row) {
+                    continue;
+                }
+                let key: Vec<&Value> = key_columns.iter().map(|&i| &row.values[i]).collect();
+                let duplicate = (compare_nulls || !key.iter().any(|value| value.is_null())) && !seen.insert(key);
+                if duplicate || self.find_key_conflict(table_id, row, &key_columns, compare_nulls, where_clause, &updated)?.is_some() {
+                    return Err(match index {
+                        Some(index) => Self::unique_violation(index, row, &key_columns),
+                        None => ExecutionError::PrimaryKeyViolation { key: format_key(row, &key_columns) },
+                    });
+                }
+            }
+        }
+        Ok(())
     }
     
-    /// Execute UPDATE statement (simplified)
-    fn execute_update_simple(
+    fn unique_violation(index: &IndexInfo, tuple: &Tuple, key_columns: &[usize]) -> ExecutionError {
+        ExecutionError::UniqueViolation {
+            index: index.name.clone(),
+            key: format_key(tuple, key_columns),
+        }
+    }
+
+    /// 获取所有列名，用于错误诊断
+    fn get_all_column_names(&self) -> Vec<String> {
+        let mut column_names = Vec::new();
+        for schema in self.table_schemas.values() {
+            for column in &schema.columns {
+                if !column_names.contains(&column.name) {
+                    column_names.push(column.name.clone());
+                }
+            }
+        }
+        column_names
+    }
+    
+    /// Execute CREATE INDEX statement
+    fn execute_create_index(
         &mut self,
+        index_name: String,
         table_name: String,
-        assignments: Vec<crate::sql::parser::Assignment>,
-        where_clause: Option<crate::sql::parser::Expression>,
+        columns: Vec<String>,
+        is_unique: bool,
+        include: Vec<String>,
+        mut where_clause: Option<crate::sql::parser::Expression>,
     ) -> Result<QueryResult, ExecutionError> {
-        // Get table metadata first
+        // Check if table exists
         let table_id = self.table_catalog.get(&table_name)
             .ok_or_else(|| ExecutionError::TableNotFound { table: table_name.clone() })?;
         
+        let schema = self.table_schemas.get(table_id)
+            .ok_or_else(|| ExecutionError::TableNotFound { table: table_name.clone() })?;
+        
+        // Validate that all columns exist
+        for column in columns.iter().chain(&include) {
+            if !schema.columns.iter().any(|col| &col.name == column) {
+                return Err(ExecutionError::ColumnNotFound { 
+                    column: column.clone(),
+                    table: table_name.clone() 
+                });
+            }
+        }
+        
+        // 部分索引的谓词只能引用本表的列和常量，并且能对每一行求值
+        if let Some(predicate) = &mut where_clause {
+            use crate::sql::parser::Expression;
+            let mut invalid = None;
+            predicate.visit_mut(&mut |expr| {
+                if invalid.is_some() {
+                    return;
+                }
+                invalid = match expr {
+                    Expression::Column(column) | Expression::QualifiedColumn { column, .. }
+                        if !schema.columns.iter().any(|col| col.name == *column) =>
+                    {
+                        Some(ExecutionError::ColumnNotFound { column: column.clone(), table: table_name.clone() })
+                    }
+                    Expression::QualifiedColumn { table, .. } if *table != table_name => {
+                        Some(ExecutionError::SemanticError(format!("index predicate can only reference table '{}'", table_name)))
+                    }
+                    Expression::FunctionCall { name, .. } => {
+                        Some(ExecutionError::SemanticError(format!("function {} is not allowed in an index predicate", name)))
+                    }
+                    Expression::Parameter(_) | Expression::Default => {
+                        Some(ExecutionError::SemanticError("index predicate must not contain parameters or DEFAULT".to_string()))
+                    }
+                    _ => None,
+                };
+            });
+            if let Some(e) = invalid {
+                return Err(e);
+            }
+            for row in self.table_rows(*table_id)?.iter() {
+                self.evaluate_where_condition(predicate, row, schema)?;
+            }
+        }
+        
+        // 目前只登记索引定义（供约束检查和索引建议使用），查询仍扫描全表。
+        // 与未完成的构建定义相同时从其检查点继续
         let table_id = *table_id;
-        let schema = self.table_schemas.get(&table_id)
-            .ok_or_else(|| ExecutionError::TableNotFound { table: table_name.clone() })?
-            .clone();
+        let resumed = self.table_indexes.get(&table_id).into_iter().flatten().any(|index| {
+            index.name == index_name
+                && index.columns == columns
+                && index.unique == is_unique
+                && index.include == include
+                && index.where_clause == where_clause
+                && index.build.is_some()
+        });
+        if !resumed {
+            self.add_index(table_id, &table_name, IndexInfo {
+                name: index_name.clone(),
+                columns: columns.clone(),
+                unique: is_unique,
+                include,
+                where_clause,
+                build: Some(IndexBuild::default()),
+            })?;
+        }
+        self.build_index(table_id, &table_name, &index_name)?;
         
-        // Get immutable reference to evaluate WHERE conditions
-        let table_data_snapshot = self.table_data.get(&table_id)
-            .ok_or_else(|| ExecutionError::TableNotFound { table: table_name.clone() })?
-            .clone();
+        Ok(QueryResult {
+            rows: vec![],
+            schema: None,
+            affected_rows: 0,
+            message: format!(
+                "Index '{}' {} on table '{}' for columns [{}]", 
+                index_name, 
+                if resumed { "build resumed and completed" } else { "created successfully" },
+                table_name,
+                columns.join(", ")
+            ),
+        })
+    }
+    
+    /// 分批构建索引，每批结束时把进度随表数据一起保存
+    ///
+    /// 进程退出或通过 [`Database::interrupt_handle`] 中断时保留已完成的批次，
+    /// 再次执行同一条 CREATE INDEX 即从检查点继续。唯一索引发现重复键时删除该索引；部分唯一索引只检查满足谓词的行。
+    fn build_index(&mut self, table_id: u32, table_name: &str, index_name: &str) -> Result<(), ExecutionError> {
+        let batch_rows = self.config.index_build_batch_rows.max(1);
+        let schema = self.table_schemas.get(&table_id)
+            .ok_or_else(|| ExecutionError::TableNotFound { table: table_name.to_string() })?;
+        let index = self.table_indexes.get(&table_id).into_iter().flatten()
+            .find(|index| index.name == index_name)
+            .ok_or_else(|| ExecutionError::SemanticError(format!("index '{}' does not exist", index_name)))?;
+        let key_columns = Self::column_positions(schema, table_name, &index.columns)?;
+        let unique = index.unique;
+        let mut seen: std::collections::HashSet<Vec<Value>> = index.build.iter()
+            .flat_map(|build| build.keys.iter().cloned())
+            .collect();
+        // 构建期间表不会被修改
+        let rows = self.table_rows(table_id)?.into_owned();
+        let total_rows = rows.len();
+        let members: Vec<bool> = if unique {
+            rows.iter().map(|row| self.in_partial_index(index.where_clause.as_ref(), schema, row)).collect()
+        } else {
+            Vec::new()
+        };
         
-        // Evaluate which rows should be updated
-        let mut indices_to_update = Vec::new();
-        match &where_clause {
-            Some(expr) => {
-                for (i, row) in table_data_snapshot.iter().enumerate() {
-                    if let Ok(true) = self.evaluate_where_condition(expr, row, &schema) {
-                        indices_to_update.push(i);
+        while let Some(index) = self.table_indexes.get_mut(&table_id)
+            .and_then(|indexes| indexes.iter_mut().find(|index| index.name == index_name)) {
+            let build = index.build.get_or_insert_with(IndexBuild::default);
+            let start = build.rows_done.min(rows.len());
+            let end = (start + batch_rows).min(rows.len());
+            let mut duplicate = None;
+            if unique {
+                for (row, _) in rows[start..end].iter().zip(&members[start..end]).filter(|(_, &member)| member) {
+                    let key: Vec<Value> = key_columns.iter().map(|&i| row.values[i].clone()).collect();
+                    if key.iter().any(Value::is_null) {
+                        continue;
+                    }
+                    if !seen.insert(key.clone()) {
+                        duplicate = Some(format_key(row, &key_columns));
+                        break;
                     }
+                    build.keys.push(key);
                 }
             }
-            std::option::Option::None => {
-                // No WHERE clause - update all rows
-                for i in 0..table_data_snapshot.len() {
-                    indices_to_update.push(i);
+            build.rows_done = end;
+            build.total_rows = rows.len();
+            let finished = end == rows.len();
+            if finished {
+                index.build = None;
+            }
+            
+            if let Some(key) = duplicate {
+                if let Some(indexes) = self.table_indexes.get_mut(&table_id) {
+                    indexes.retain(|index| index.name != index_name);
                 }
+                if let Err(e) = self.save_table(table_id, table_name) {
+                    println!("Warning: Failed to save table data: {}", e);
+                }
+                return Err(ExecutionError::UniqueViolation { index: index_name.to_string(), key });
+            }
+            // 检查点：进度和表数据写在同一个文件里
+            self.save_table(table_id, table_name)?;
+            if finished {
+                break;
+            }
+            if self.interrupt.swap(false, std::sync::atomic::Ordering::SeqCst) {
+                return Err(ExecutionError::Interrupted {
+                    operation: format!("building index '{}' ({}/{} rows done)", index_name, end, total_rows),
+                });
+            }
+        }
+        Ok(())
+    }
+    
+    /// 表被修改后，其上未完成的索引构建从头开始（已处理的行可能已被修改或删除）
+    fn restart_index_builds(&mut self, table_name: &str) {
+        let Some(table_id) = self.table_catalog.get(table_name) else {
+            return;
+        };
+        for index in self.table_indexes.get_mut(table_id).into_iter().flatten() {
+            if let Some(build) = &mut index.build {
+                *build = IndexBuild::default();
             }
         }
+    }
+    
+    /// 所有未完成的索引构建：(表名, 索引)，按表名和索引名排序
+    pub fn index_builds(&self) -> Vec<(String, IndexInfo)> {
+        let mut builds: Vec<(String, IndexInfo)> = self.table_catalog.iter()
+            .flat_map(|(table_name, table_id)| {
+                self.table_indexes.get(table_id).into_iter().flatten()
+                    .filter(|index| index.build.is_some())
+                    .map(move |index| (table_name.clone(), index.clone()))
+            })
+            .collect();
+        builds.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.name.cmp(&b.1.name)));
+        builds
+    }
+    
+    /// 中断标志：置位后，正在分批执行的长时间操作（目前为索引构建）在下一个检查点保存进度后
+    /// 以 [`ExecutionError::Interrupted`] 返回。可以从其他线程置位，例如在 Ctrl-C 处理函数中
+    pub fn interrupt_handle(&self) -> std::sync::Arc<std::sync::atomic::AtomicBool> {
+        self.interrupt.clone()
+    }
+    
+    /// Execute DROP INDEX statement
+    fn execute_drop_index(
+        &mut self,
+        index_name: String,
+        table_name: String,
+    ) -> Result<QueryResult, ExecutionError> {
+        // Check if table exists
+        let table_id = *self.table_catalog.get(&table_name)
+            .ok_or_else(|| ExecutionError::TableNotFound { table: table_name.clone() })?;
         
-        // Pre-compute new values for each row to avoid borrowing issues
-        let mut updated_rows = Vec::new();
-        for row_index in &indices_to_update {
-            if *row_index < table_data_snapshot.len() {
-                let row = &table_data_snapshot[*row_index];
-                let mut new_row = row.clone();
-                
-                // Apply assignments
-                for assignment in &assignments {
-                    // Find column index
-                    if let Some(col_index) = schema.columns.iter()
-                        .position(|col| col.name == assignment.column) {
-                        
-                        // Evaluate new value - support both literals and expressions
-                        let new_value = match &assignment.value {
-                            crate::sql::parser::Expression::Literal(val) => val.clone(),
-                            _ => {
-                                // Support complex expressions like age = age + 1
-                                match self.evaluate_expression_for_tuple(&assignment.value, row, &schema) {
-                                    Ok(val) => val,
-                                    Err(_) => {
-                                        return Err(ExecutionError::NotImplemented { 
-                                            feature: "Complex UPDATE expression evaluation failed".to_string() 
-                                        });
-                                    }
-                                }
-                            }
-                        };
-                        
-                        // Update the value in the new row
-                        new_row.values[col_index] = new_value;
-                    } else {
-                        return Err(ExecutionError::ColumnNotFound {
-                            table: table_name.clone(),
-                            column: assignment.column.clone(),
-                        });
-                    }
-                }
-                updated_rows.push((*row_index, new_row));
+        if let Some(indexes) = self.table_indexes.get_mut(&table_id) {
+            indexes.retain(|index| index.name != index_name);
+        }
+        if let Err(e) = self.save_table(table_id, &table_name) {
+            println!("Warning: Failed to save table data: {}", e);
+        }
+        
+        Ok(QueryResult {
+            rows: vec![],
+            schema: None,
+            affected_rows: 0,
+            message: format!(
+                "Index '{}' dropped successfully from table '{}'", 
+                index_name, 
+                table_name
+            ),
+        })
+    }
+    
+    /// ANALYZE：扫描表（省略表名时为全部表）计算统计，替换之前的统计并写入表快照
+    fn execute_analyze(&mut self, table_name: Option<String>) -> Result<QueryResult, ExecutionError> {
+        let mut tables: Vec<(String, u32)> = match table_name {
+            Some(name) => {
+                let table_id = *self.table_catalog.get(&name)
+                    .ok_or_else(|| ExecutionError::TableNotFound { table: name.clone() })?;
+                vec![(name, table_id)]
             }
-        }
+            None => self.table_catalog.iter().map(|(name, &table_id)| (name.clone(), table_id)).collect(),
+        };
+        tables.sort();
         
-        // Now get mutable reference and apply the pre-computed updates
-        let table_data = self.table_data.get_mut(&table_id)
+        for (table_name, table_id) in &tables {
+            let schema = self.table_schemas.get(table_id)
+                .ok_or_else(|| ExecutionError::TableNotFound { table: table_name.clone() })?;
+            let statistics = statistics::analyze(schema, &self.table_rows(*table_id)?);
+            self.table_statistics.insert(*table_id, statistics);
+            self.save_table(*table_id, table_name)?;
+        }
+        Ok(Self::message_result(format!("ANALYZE: {} table(s)", tables.len())))
+    }
+    
+    /// ANALYZE 收集的表统计；表不存在或尚未 ANALYZE 时为 None
+    pub fn table_statistics(&self, table_name: &str) -> Option<&TableStatistics> {
+        self.table_catalog.get(table_name).and_then(|table_id| self.table_statistics.get(table_id))
+    }
+    
+    /// REINDEX：丢弃内存中的索引结构，由表的当前行重建，并重写表的索引文件
+    ///
+    /// 唯一索引重建后发现重复键时报 `UniqueViolation`（表数据已违反约束），索引保留但数据需要先修正。
+    fn execute_reindex(&mut self, target: crate::sql::parser::ReindexTarget) -> Result<QueryResult, ExecutionError> {
+        use crate::sql::parser::ReindexTarget;
+        let (table_name, index_name) = match target {
+            ReindexTarget::Index { index_name, table_name } => (table_name, Some(index_name)),
+            ReindexTarget::Table(table_name) => (table_name, None),
+        };
+        let table_id = *self.table_catalog.get(&table_name)
             .ok_or_else(|| ExecutionError::TableNotFound { table: table_name.clone() })?;
-        
-        let mut updated_count = 0;
-        for (row_index, new_row) in updated_rows {
-            if row_index < table_data.len() {
-                table_data[row_index] = new_row;
-                updated_count += 1;
-            }
+        let schema = self.table_schemas.get(&table_id)
+            .ok_or_else(|| ExecutionError::TableNotFound { table: table_name.clone() })?;
+        // 仍在构建中的索引由 CREATE INDEX 续建
+        let targets: Vec<IndexInfo> = self.lookup_indexes(table_id, &table_name).into_iter()
+            .filter(|index| index.build.is_none() && index_name.as_ref().is_none_or(|name| *name == index.name))
+            .collect();
+        if let (Some(name), true) = (&index_name, targets.is_empty()) {
+            return Err(ExecutionError::SemanticError(format!("index '{}' does not exist on table '{}'", name, table_name)));
         }
         
-        // Save table data after update
-        if updated_count > 0 {
-            if let Err(e) = self.save_table(table_id, &table_name) {
-                println!("Warning: Failed to save table data: {}", e);
+        for index in &targets {
+            let columns = Self::column_positions(schema, &table_name, &index.columns)?;
+            let include = Self::column_positions(schema, &table_name, &index.include)?;
+            let slot = (table_id, columns.clone(), index.where_clause.as_ref().map(dump::expression_sql));
+            self.row_indexes.lock().unwrap_or_else(|e| e.into_inner()).remove(&slot);
+            let duplicate = self.with_row_index(table_id, &columns, &include, index.where_clause.as_ref(), |row_index| {
+                row_index.duplicate_key().map(|key| {
+                    format!("({})", key.iter().map(Value::to_string).collect::<Vec<_>>().join(", "))
+                })
+            })?;
+            if let (true, Some(key)) = (index.unique, duplicate) {
+                return Err(ExecutionError::UniqueViolation { index: index.name.clone(), key });
             }
         }
+        // 重写索引文件
+        self.save_table(table_id, &table_name)?;
         
-        Ok(QueryResult {
-            rows: vec![],
-            schema: None,
-            affected_rows: updated_count,
-            message: format!("Updated {} row(s) in table '{}'", updated_count, table_name),
-        })
+        let message = match &index_name {
+            Some(name) => format!("Index '{}' on table '{}' rebuilt", name, table_name),
+            None => format!("{} index(es) on table '{}' rebuilt", targets.len(), table_name),
+        };
+        Ok(Self::message_result(message))
     }
     
-    /// Execute DELETE statement (simplified)
-    fn execute_delete_simple(
+    /// Execute EXPLAIN statement
+    fn execute_explain(
         &mut self,
-        table_name: String,
-        where_clause: Option<crate::sql::parser::Expression>,
+        statement: Statement,
     ) -> Result<QueryResult, ExecutionError> {
-        // Get table metadata first
-        let table_id = self.table_catalog.get(&table_name)
-            .ok_or_else(|| ExecutionError::TableNotFound { table: table_name.clone() })?;
+        // Generate execution plan based on statement type
+        let execution_plan = match &statement {
+            // 连接按优化器选择的计划展示
+            Statement::Select { from_clause: Some(crate::sql::parser::FromClause::Join { .. }), .. } => {
+                self.explain_optimized_plan(statement.clone())?
+            }
+            // 子查询按去相关后的计划展示
+            Statement::Select { where_clause: Some(where_clause), .. } if where_clause.contains_subquery() => {
+                self.explain_optimized_plan(statement.clone())?
+            }
+            Statement::Select { select_list, from_clause, where_clause, group_by, having, .. } => {
+                let used: Vec<&crate::sql::parser::Expression> = group_by.iter().flatten().chain(having.as_ref()).collect();
+                self.generate_execution_plan_for_select(select_list, from_clause, where_clause, &used)
+            }
+            Statement::Insert { table_name, .. } => {
+                format!("Insert Plan:\n1. Insert into table '{}'", table_name)
+            }
+            Statement::Update { table_name, .. } => {
+                format!("Update Plan:\n1. Update table '{}'", table_name)
+            }
+            Statement::Delete { table_name, .. } => {
+                format!("Delete Plan:\n1. Delete from table '{}'", table_name)
+            }
+            _ => "Execution plan not available for this statement type".to_string(),
+        };
         
-        let table_id = *table_id;
-        let schema = self.table_schemas.get(&table_id)
-            .ok_or_else(|| ExecutionError::TableNotFound { table: table_name.clone() })?
-            .clone();
+        Ok(QueryResult {
+            rows: vec![Tuple::new(vec![Value::Varchar(execution_plan)])],
+            schema: Some(Schema {
+                columns: vec![ColumnDefinition {
+                    name: "Query Plan".to_string(),
+                    data_type: DataType::Varchar(1000),
+                    nullable: false,
+                    default: None,
+                    default_function: None,
+                }],
+                primary_key: None,
+            }),
+            affected_rows: 0,
+            message: "Query execution plan generated".to_string(),
+        })
+    }
+    
+    /// 执行 EXPLAIN ANALYZE：实际执行语句，在计划之后附上各算子的实际行数和耗时
+    ///
+    /// 统计通过观察者钩子收集，已注册的观察者照常收到事件。INSERT / UPDATE / DELETE 的修改会生效，
+    /// 整条语句记为一个算子。
+    fn execute_explain_analyze(&mut self, statement: Statement) -> Result<QueryResult, ExecutionError> {
+        let dml_operator = match &statement {
+            Statement::Select { .. } => None,
+            Statement::Insert { table_name, .. } => Some(OperatorInfo::new(0, "Insert").with_detail(table_name.clone())),
+            Statement::Update { table_name, .. } => Some(OperatorInfo::new(0, "Update").with_detail(table_name.clone())),
+            Statement::Delete { table_name, .. } => Some(OperatorInfo::new(0, "Delete").with_detail(table_name.clone())),
+            _ => {
+                return Err(ExecutionError::SemanticError(
+                    "EXPLAIN ANALYZE supports only SELECT, INSERT, UPDATE and DELETE".to_string(),
+                ))
+            }
+        };
+        let plan = self.execute_explain(statement.clone())?;
+        let plan_text = match plan.rows.first().and_then(|row| row.values.first()) {
+            Some(Value::Varchar(text)) => text.clone(),
+            _ => String::new(),
+        };
         
-        // Get immutable reference to evaluate WHERE conditions
-        let table_data_snapshot = self.table_data.get(&table_id)
-            .ok_or_else(|| ExecutionError::TableNotFound { table: table_name.clone() })?
-            .clone();
+        let collector = std::sync::Arc::new(StatsCollector::new(self.config.observer.clone()));
+        let previous = self.config.observer.replace(collector.clone());
+        let stopwatch = Stopwatch::start();
+        if let Some(info) = &dml_operator {
+            collector.on_open(info);
+        }
+        let result = self.dispatch_statement(statement);
+        let elapsed = std::time::Duration::from_micros(stopwatch.elapsed_micros());
+        if let (Some(info), Ok(result)) = (&dml_operator, &result) {
+            collector.on_next(info, result.affected_rows, elapsed);
+            collector.on_close(info, &OperatorStats {
+                rows_produced: result.affected_rows,
+                next_calls: 1,
+                elapsed,
+            });
+        }
+        self.config.observer = previous;
+        let result = result?;
         
-        let original_count = table_data_snapshot.len();
+        let report = format!(
+            "{}\nActual execution:\n{}Execution time: {:.3} ms",
+            plan_text.trim_end(),
+            collector.render(),
+            elapsed.as_secs_f64() * 1000.0
+        );
+        Ok(QueryResult {
+            rows: vec![Tuple::new(vec![Value::Varchar(report)])],
+            affected_rows: result.affected_rows,
+            message: format!("Query executed in {:.3} ms", elapsed.as_secs_f64() * 1000.0),
+            ..plan
+        })
+    }
+    
+    /// Generate execution plan for SELECT statement
+    fn generate_execution_plan_for_select(
+        &self,
+        select_list: &crate::sql::parser::SelectList,
+        from_clause: &Option<crate::sql::parser::FromClause>,
+        where_clause: &Option<crate::sql::parser::Expression>,
+        other_exprs: &[&crate::sql::parser::Expression],
+    ) -> String {
+        let mut plan = String::new();
+        plan.push_str("Select Execution Plan:\n");
         
-        // Evaluate which rows should be deleted
-        let mut indices_to_delete = Vec::new();
-        match where_clause {
-            Some(expr) => {
-                // Evaluate WHERE condition for each row
-                for (i, row) in table_data_snapshot.iter().enumerate() {
-                    if let Ok(true) = self.evaluate_where_condition(&expr, row, &schema) {
-                        indices_to_delete.push(i);
+        // Add scan operation; remember the rows an index scan fetches
+        let mut index_rows = None;
+        if let Some(from) = from_clause {
+            match from {
+                crate::sql::parser::FromClause::Table(table_name) => {
+                    // 与执行时相同的访问路径选择：索引查找取出的行足够少时用索引扫描
+                    let candidate = where_clause.as_ref()
+                        .and_then(|where_clause| self.index_candidate(table_name, where_clause));
+                    match candidate {
+                        Some((table_id, probe, estimated_rows)) => {
+                            let table_rows = self.table_data.get(&table_id).map_or(0, TableRows::len);
+                            if self.optimizer.prefers_index_scan(table_rows, estimated_rows) {
+                                // 查询只用到索引中的列时不读表数据
+                                let schema = &self.table_schemas[&table_id];
+                                let used = where_clause.iter().chain(other_exprs.iter().copied());
+                                let scan = if Self::covered_by_index(schema, &probe, select_list, used) {
+                                    "Index Only Scan"
+                                } else {
+                                    "Index Scan"
+                                };
+                                plan.push_str(&format!(
+                                    "1. {}: {} using {} ({}), estimated rows={} of {}\n",
+                                    scan, table_name, probe.index, probe.condition, estimated_rows, table_rows
+                                ));
+                                index_rows = Some(estimated_rows);
+                            } else {
+                                plan.push_str(&format!(
+                                    "1. Table Scan: {} (index {} not selective: estimated rows={} of {})\n",
+                                    table_name, probe.index, estimated_rows, table_rows
+                                ));
+                            }
+                        }
+                        None => plan.push_str(&format!("1. Table Scan: {}\n", table_name)),
                     }
                 }
-            }
-            std::option::Option::None => {
-                // No WHERE clause - delete all rows
-                for i in 0..table_data_snapshot.len() {
-                    indices_to_delete.push(i);
+                _ => {
+                    plan.push_str("1. Complex From Clause\n");
                 }
             }
         }
         
-        // Now get mutable reference and delete rows (from back to front to maintain indices)
-        let table_data = self.table_data.get_mut(&table_id)
-            .ok_or_else(|| ExecutionError::TableNotFound { table: table_name.clone() })?;
-        
-        // Sort indices in descending order to delete from back to front
-        indices_to_delete.sort_by(|a, b| b.cmp(a));
-        
-        for &index in &indices_to_delete {
-            if index < table_data.len() {
-                table_data.remove(index);
+        // Add filter operation if WHERE clause exists, with the rows expected to pass it
+        if let Some(where_clause) = where_clause {
+            match from_clause {
+                Some(crate::sql::parser::FromClause::Table(table_name)) if self.table_catalog.contains_key(table_name) => {
+                    // 选择性按 ANALYZE 统计估计（没有统计时为默认值）；索引扫描取出的行数是上限
+                    let scan = ExecutionPlan::TableScan {
+                        table_name: table_name.clone(),
+                        schema: Schema::new(Vec::new()),
+                        filter: Some(where_clause.clone()),
+                    };
+                    let rows = cost::estimate(&scan, self).rows;
+                    let rows = index_rows.map_or(rows, |fetched| rows.min(fetched as f64));
+                    plan.push_str(&format!(
+                        "2. Filter: Apply WHERE conditions (estimated rows={} of {})\n",
+                        rows.round(),
+                        self.table_data.get(&self.table_catalog[table_name]).map_or(0, TableRows::len)
+                    ));
+                }
+                _ => plan.push_str("2. Filter: Apply WHERE conditions\n"),
             }
         }
         
-        let deleted_count = indices_to_delete.len();
+        // Add projection
+        plan.push_str("3. Projection: Select specified columns\n");
         
-        // Save table data after deletion
-        if deleted_count > 0 {
-            if let Err(e) = self.save_table(table_id, &table_name) {
-                println!("Warning: Failed to save table data: {}", e);
-            }
+        // Add optimization notes
+        if where_clause.is_some() {
+            plan.push_str("\nOptimizations applied:\n");
+            plan.push_str("- Predicate pushdown: WHERE conditions applied early\n");
         }
         
-        Ok(QueryResult {
-            rows: vec![],
-            schema: None,
-            affected_rows: deleted_count,
-            message: format!("Deleted {} row(s) from table '{}' (total was: {})", 
-                deleted_count, table_name, original_count),
-        })
+        plan
     }
     
-    /// List all tables in the database
-    pub fn list_tables(&self) -> Vec<String> {
-        self.table_catalog.keys().cloned().collect()
+    /// 展示优化器为 SELECT 选择的执行计划：每个节点一行，附上按统计估计的行数和累计代价
+    fn explain_optimized_plan(&self, statement: Statement) -> Result<String, ExecutionError> {
+        let plan = self.optimized_plan(statement)?;
+        let mut text = String::from("Select Execution Plan:\n");
+        self.render_plan(&plan, 0, &mut text);
+        Ok(text)
     }
     
-    /// Get table schema by name
-    pub fn get_table_schema(&self, table_name: &str) -> Option<&Schema> {
-        self.table_catalog.get(table_name)
-            .and_then(|&table_id| self.table_schemas.get(&table_id))
+    /// 执行器遇到 IN / EXISTS 子查询时的错误
+    fn subqueries_not_implemented() -> ExecutionError {
+        ExecutionError::NotImplemented {
+            feature: "Subqueries".to_string(),
+            capability: Capability::Subqueries,
+        }
+    }
+    
+    /// 把计划节点及其输入逐行写入 out，子节点以 `-> ` 开头并按深度缩进
+    fn render_plan(&self, plan: &ExecutionPlan, depth: usize, out: &mut String) {
+        let list = |exprs: &mut dyn Iterator<Item = &crate::sql::parser::Expression>| {
+            exprs.map(dump::expression_sql).collect::<Vec<_>>().join(", ")
+        };
+        // 投影下推裁剪了扫描的列时在表名后列出保留的列
+        let scanned = |table_name: &str, schema: &Schema| {
+            let all_columns = self.table_catalog.get(table_name)
+                .and_then(|table_id| self.table_schemas.get(table_id))
+                .map_or(0, |table_schema| table_schema.columns.len());
+            if schema.columns.len() < all_columns {
+                let names: Vec<&str> = schema.columns.iter().map(|column| column.name.as_str()).collect();
+                format!("{} [{}]", table_name, names.join(", "))
+            } else {
+                table_name.to_string()
+            }
+        };
+        let (label, inputs): (String, Vec<&ExecutionPlan>) = match plan {
+            ExecutionPlan::TableScan { table_name, schema, filter } => match filter {
+                Some(filter) => (format!("Table Scan: {} ({})", scanned(table_name, schema), dump::expression_sql(filter)), vec![]),
+                None => (format!("Table Scan: {}", scanned(table_name, schema)), vec![]),
+            },
+            ExecutionPlan::IndexScan { table_name, schema, index_name, condition, .. } => (
+                format!("Index Scan: {} using {} ({})", scanned(table_name, schema), index_name, list(&mut condition.iter())),
+                vec![],
+            ),
+            ExecutionPlan::IndexOnlyScan { table_name, schema, index_name, condition, .. } => (
+                format!("Index Only Scan: {} using {} ({})", scanned(table_name, schema), index_name, list(&mut condition.iter())),
+                vec![],
+            ),
+            ExecutionPlan::Filter { input, condition } => (format!("Filter: {}", dump::expression_sql(condition)), vec![input]),
+            ExecutionPlan::Project { input, .. } => ("Project".to_string(), vec![input]),
+            ExecutionPlan::Sort { input, sort_keys } => {
+                (format!("Sort: {}", list(&mut sort_keys.iter().map(|key| &key.expression))), vec![input])
+            }
+            ExecutionPlan::Limit { input, count, .. } => (format!("Limit: {}", count), vec![input]),
+            ExecutionPlan::GroupBy { input, group_expressions, .. } if group_expressions.is_empty() => {
+                ("Aggregate".to_string(), vec![input])
+            }
+            ExecutionPlan::GroupBy { input, group_expressions, .. } => {
+                (format!("Aggregate: GROUP BY {}", list(&mut group_expressions.iter())), vec![input])
+            }
+            ExecutionPlan::Join { left, right, join_type, condition, algorithm } => {
+                let name = algorithm.operator_name();
+                let on = condition.as_ref().map(|condition| format!(" ON {}", dump::expression_sql(condition))).unwrap_or_default();
+                (format!("{} ({:?}){}", name, join_type, on), vec![left, right])
+            }
+            ExecutionPlan::Apply { input, subquery, operand, negated } => {
+                let not = if *negated { "NOT " } else { "" };
+                let predicate = match operand {
+                    Some(operand) => format!("{} {}IN", dump::expression_sql(operand), not),
+                    None => format!("{}EXISTS", not),
+                };
+                (format!("Subquery Filter: {}", predicate), vec![input, subquery])
+            }
+            other => (format!("{:?}", other), vec![]),
+        };
+        let estimate = cost::estimate(plan, self);
+        let prefix = match depth {
+            0 => String::new(),
+            depth => format!("{}-> ", "    ".repeat(depth - 1)),
+        };
+        out.push_str(&format!("{}{} (estimated rows={}, cost={:.1})\n", prefix, label, estimate.rows.round(), estimate.cost));
+        for input in inputs {
+            self.render_plan(input, depth + 1, out);
+        }
     }
+}
 
-    // ===============================
-    // 数据持久化相关方法
-    // ===============================
+// ===============================
+// 数据持久化相关方法
+// ===============================
 
-    /// 保存表数据到文件
-    fn save_table(&self, table_id: u32, table_name: &str) -> Result<(), ExecutionError> {
+#[cfg(feature = "persistence")]
+impl Database {
+    /// 保存表快照
+    ///
+    /// 行的修改记录在 WAL 中，快照只在 DDL 和索引构建检查点时重写。
+    /// 有数据目录时行写入一个新的堆文件（槽页经缓冲池读写），快照 JSON 只保存模式、索引和堆文件版本。
+    fn save_table(&mut self, table_id: u32, table_name: &str) -> Result<(), ExecutionError> {
+        // 显式事务中的修改在 COMMIT 时才写入
+        if self.transaction.is_some() {
+            return Ok(());
+        }
+        
         // 获取表的schema和数据
         let schema = self.table_schemas.get(&table_id)
             .ok_or_else(|| ExecutionError::TableNotFound { table: table_name.to_string() })?;
         
-        let rows = self.table_data.get(&table_id).cloned().unwrap_or_default();
+        let generation = self.table_heap_generations.get(&table_id).map_or(1, |generation| generation + 1);
+        let heap_generation = self.write_table_heap(table_id, generation)?.then_some(generation);
+        let rows = match heap_generation {
+            Some(_) => Vec::new(),
+            None => self.table_rows(table_id)?.into_owned(),
+        };
 
         let table_data = TableData {
             schema: schema.clone(),
             rows,
+            heap_generation,
+            compression: self.table_compression.get(&table_id).copied(),
+            indexes: self.table_indexes.get(&table_id).cloned().unwrap_or_default(),
+            row_ids: self.table_row_ids.get(&table_id).cloned().unwrap_or_default(),
+            lsn: self.wal.last_lsn(),
+            statistics: self.table_statistics.get(&table_id).cloned(),
         };
 
         // 序列化为JSON
         let json = serde_json::to_string_pretty(&table_data)
             .map_err(|e| ExecutionError::StorageError(format!("Serialization error: {}", e)))?;
 
-        // 写入文件
-        let file_path = self.data_dir.join(format!("table_{}.json", table_id));
-        let mut file = File::create(file_path)
-            .map_err(|e| ExecutionError::StorageError(format!("File creation error: {}", e)))?;
-
-        file.write_all(json.as_bytes())
+        // 写入存储后端
+        self.backend.write(&format!("table_{}.json", table_id), json.as_bytes())
             .map_err(|e| ExecutionError::StorageError(format!("Write error: {}", e)))?;
 
+        // 快照已指向新的堆文件，旧文件不再被引用
+        let replaced = match heap_generation {
+            Some(generation) => self.table_heap_generations.insert(table_id, generation),
+            None => self.table_heap_generations.remove(&table_id),
+        };
+        if let Some(old_generation) = replaced {
+            self.remove_heap_file(table_id, old_generation)?;
+        }
+        // 索引文件写入失败不影响快照，打开时由表的行重建
+        if let Some(generation) = heap_generation {
+            if let Err(e) = self.write_index_file(table_id, table_name, generation) {
+                log::warn!("Failed to write indexes of table '{}': {}", table_name, e);
+            }
+        }
+
         log::debug!("Saved table '{}' (id: {}) to disk", table_name, table_id);
         Ok(())
     }
 
-    /// 从文件加载表数据
-    fn load_table(&mut self, table_id: u32) -> Result<Option<String>, ExecutionError> {
-        let file_path = self.data_dir.join(format!("table_{}.json", table_id));
+    /// 把表的全部行逐行写入指定版本的堆文件并落盘
+    ///
+    /// 没有数据目录（内存后端）时返回 false，此时行保存在快照 JSON 中。
+    /// 放不进数据页的长行由堆文件写入溢出页链。
+    fn write_table_heap(&self, table_id: u32, generation: u64) -> Result<bool, ExecutionError> {
+        let Some(file_manager) = &self.file_manager else {
+            return Ok(false);
+        };
+        let rows = self.table_data.get(&table_id)
+            .ok_or_else(|| ExecutionError::TableNotFound { table: format!("table_id_{}", table_id) })?;
+        let row_ids = self.table_row_ids.get(&table_id).map(|row_ids| row_ids.ids.as_slice()).unwrap_or_default();
+
+        let storage_error = |e: crate::storage::StorageError| ExecutionError::StorageError(format!("Page file error: {}", e));
+        let name = heap_file_name(table_id, generation);
+        // 上次中断的快照可能留下了同名文件
+        self.remove_heap_file(table_id, generation)?;
+        let file = file_manager.create_file_with_compression(&name, self.effective_compression(table_id))
+            .map_err(|e| ExecutionError::StorageError(format!("Failed to create table file: {}", e)))?;
+        let heap = HeapFile::new(file.clone());
+        // 每条记录是 8 字节的 rowid 加上行的二进制编码；逐行读出、编码后写入，不先收集整张表
+        let mut record = Vec::new();
+        for (row, row_id) in rows.iter(&self.buffer_pool).zip(row_ids) {
+            record.clear();
+            record.extend_from_slice(&row_id.to_le_bytes());
+            serialize::encode_tuple_into(row?.as_ref(), &mut record);
+            heap.insert(&self.buffer_pool, &record).map_err(storage_error)?;
+        }
         
-        if !file_path.exists() {
-            return Ok(None); // 文件不存在，跳过
+        self.buffer_pool.flush_all()
+            .map_err(|e| ExecutionError::StorageError(format!("Buffer pool flush error: {}", e)))?;
+        file.lock()
+            .map_err(|_| ExecutionError::StorageError("Table file lock poisoned".to_string()))?
+            .sync()
+            .map_err(|e| ExecutionError::StorageError(format!("Failed to sync table file: {}", e)))?;
+        Ok(true)
+    }
+
+    /// 删除一个版本的堆文件，并丢弃缓冲池中它的页
+    fn remove_heap_file(&self, table_id: u32, generation: u64) -> Result<(), ExecutionError> {
+        let Some(file_manager) = &self.file_manager else {
+            return Ok(());
+        };
+        let name = heap_file_name(table_id, generation);
+        self.buffer_pool.discard_file(&name)
+            .map_err(|e| ExecutionError::StorageError(format!("Buffer pool error: {}", e)))?;
+        file_manager.delete_file(&name)
+            .map_err(|e| ExecutionError::StorageError(format!("Failed to delete table file: {}", e)))
+    }
+
+    /// 把表上已完成的索引写入索引文件，标记为快照的堆文件版本；尚未建立的内存索引先由表的行建立
+    fn write_index_file(&self, table_id: u32, table_name: &str, generation: u64) -> Result<(), ExecutionError> {
+        let schema = self.table_schemas.get(&table_id)
+            .ok_or_else(|| ExecutionError::TableNotFound { table: table_name.to_string() })?;
+        let mut sections = Vec::new();
+        for index in self.lookup_indexes(table_id, table_name).into_iter().filter(|index| index.build.is_none()) {
+            let columns = Self::column_positions(schema, table_name, &index.columns)?;
+            let include = Self::column_positions(schema, table_name, &index.include)?;
+            let section = self.with_row_index(table_id, &columns, &include, index.where_clause.as_ref(), RowIndex::encode)?;
+            sections.push((index.name, section));
         }
+        self.backend.write(&index_file_name(table_id), &indexes::encode_index_file(generation, &sections))
+            .map_err(|e| ExecutionError::StorageError(format!("Write error: {}", e)))
+    }
 
-        // 读取文件内容
-        let mut file = File::open(file_path)
-            .map_err(|e| ExecutionError::StorageError(format!("File open error: {}", e)))?;
+    /// 载入索引文件中与表当前的堆文件版本对应的索引结构，返回载入的索引数
+    ///
+    /// 文件不存在时返回 0；文件损坏或属于旧版本时报错，一个索引也不载入，查询时照常由表的行重建。
+    fn load_index_file(&self, table_id: u32, table_name: &str, generation: u64) -> Result<usize, ExecutionError> {
+        let data = match self.backend.read(&index_file_name(table_id)) {
+            Ok(Some(data)) => data,
+            Ok(None) => return Ok(0),
+            Err(e) => return Err(ExecutionError::StorageError(format!("Read error: {}", e))),
+        };
+        let corrupt = |reason: String| ExecutionError::StorageError(format!("Unusable index file of table '{}': {}", table_name, reason));
+        let sections = indexes::decode_index_file(&data, generation).map_err(corrupt)?;
+        let (Some(schema), Some(table_rows)) = (self.table_schemas.get(&table_id), self.table_data.get(&table_id)) else {
+            return Ok(0);
+        };
+        
+        let mut loaded = Vec::new();
+        for index in self.lookup_indexes(table_id, table_name).into_iter().filter(|index| index.build.is_none()) {
+            let Some(section) = sections.get(&index.name) else {
+                continue;
+            };
+            let columns = Self::column_positions(schema, table_name, &index.columns)?;
+            let slot = (table_id, columns, index.where_clause.as_ref().map(dump::expression_sql));
+            let row_index = RowIndex::decode(section, index.where_clause, table_rows.stamp())
+                .map_err(|reason| corrupt(format!("index '{}': {}", index.name, reason)))?;
+            loaded.push((slot, row_index));
+        }
+        let count = loaded.len();
+        self.row_indexes.lock().unwrap_or_else(|e| e.into_inner()).extend(loaded);
+        Ok(count)
+    }
 
-        let mut contents = String::new();
-        file.read_to_string(&mut contents)
-            .map_err(|e| ExecutionError::StorageError(format!("Read error: {}", e)))?;
+    /// 加载表快照及其索引文件，返回快照对应的 LSN（快照不存在时为 None）
+    fn load_table(&mut self, table_id: u32, table_name: &str) -> Result<Option<Lsn>, ExecutionError> {
+        let contents = match self.backend.read(&format!("table_{}.json", table_id)) {
+            Ok(Some(contents)) => contents,
+            Ok(None) => return Ok(None), // 文件不存在，跳过
+            Err(e) => return Err(ExecutionError::StorageError(format!("Read error: {}", e))),
+        };
 
         // 反序列化
-        let table_data: TableData = serde_json::from_str(&contents)
+        let table_data: TableData = serde_json::from_slice(&contents)
             .map_err(|e| ExecutionError::StorageError(format!("Deserialization error: {}", e)))?;
 
         // 恢复到内存中
-        let rows_count = table_data.rows.len();
+        let (rows, row_ids) = match table_data.heap_generation {
+            Some(generation) => {
+                let (rows, mut row_ids) = self.read_table_heap(table_id, generation)?;
+                row_ids.next = row_ids.next.max(table_data.row_ids.next);
+                self.table_heap_generations.insert(table_id, generation);
+                (rows, row_ids)
+            }
+            None => {
+                let rows_count = table_data.rows.len();
+                let row_ids = if table_data.row_ids.ids.len() == rows_count {
+                    table_data.row_ids
+                } else {
+                    RowIds::for_rows(rows_count)
+                };
+                (self.new_table_rows(table_id, table_data.rows)?, row_ids)
+            }
+        };
+        let rows_count = rows.len();
+        self.table_row_ids.insert(table_id, row_ids);
         self.table_schemas.insert(table_id, table_data.schema);
-        self.table_data.insert(table_id, table_data.rows);
+        self.table_data.insert(table_id, rows);
+        self.table_indexes.insert(table_id, table_data.indexes);
+        if let Some(compression) = table_data.compression {
+            self.table_compression.insert(table_id, compression);
+        }
+        if let Some(statistics) = table_data.statistics {
+            self.table_statistics.insert(table_id, statistics);
+        }
+        if let Some(generation) = table_data.heap_generation {
+            match self.load_index_file(table_id, table_name, generation) {
+                Ok(count) => log::debug!("Loaded {} index(es) of table '{}'", count, table_name),
+                Err(e) => log::warn!("{}; indexes will be rebuilt from the table", e),
+            }
+        }
 
         log::debug!("Loaded table with id {} from disk ({} rows)", table_id, rows_count);
+        Ok(Some(table_data.lsn))
+    }
+
+    /// 从堆文件读回表的行和 rowid
+    ///
+    /// 堆文件逐页读取，每解码一行就写入表的行存储，整张表不会同时放在内存中。
+    fn read_table_heap(&self, table_id: u32, generation: u64) -> Result<(TableRows, RowIds), ExecutionError> {
+        let file_manager = self.file_manager.as_ref().ok_or_else(|| {
+            ExecutionError::StorageError(format!("Rows of table {} are in a page file, but the storage backend has no data directory", table_id))
+        })?;
+        let file = file_manager.open_file(&heap_file_name(table_id, generation))
+            .map_err(|e| ExecutionError::StorageError(format!("Failed to open table file: {}", e)))?;
+        let heap = HeapFile::new(file);
+        let storage_error = |e: crate::storage::StorageError| ExecutionError::StorageError(format!("Page file error: {}", e));
+        let records = heap.records(&self.buffer_pool).map_err(storage_error)?;
+
+        let mut row_ids = RowIds::default();
+        let rows = records.map(|record| {
+            let (rid, record) = record.map_err(storage_error)?;
+            let corrupt = |reason: String| ExecutionError::StorageError(format!("Corrupt record {} in table {}: {}", rid, table_id, reason));
+            let (row_id, row) = record.split_first_chunk::<8>()
+                .ok_or_else(|| corrupt("shorter than a rowid".to_string()))?;
+            let row_id = u64::from_le_bytes(*row_id);
+            let row = serialize::decode_tuple(row).map_err(|e| corrupt(e.to_string()))?;
+            row_ids.ids.push(row_id);
+            row_ids.next = row_ids.next.max(row_id + 1);
+            Ok(row)
+        });
+        let rows = TableRows::from_rows(self.work_files.as_ref(), &self.buffer_pool, table_id, rows)?;
+        Ok((rows, row_ids))
+    }
+
+    /// 删除表快照及其堆文件和索引文件
+    fn remove_table_snapshot(&mut self, table_id: u32) -> Result<(), ExecutionError> {
+        if let Some(generation) = self.table_heap_generations.remove(&table_id) {
+            self.remove_heap_file(table_id, generation)?;
+        }
+        for name in [format!("table_{}.json", table_id), index_file_name(table_id)] {
+            self.backend.remove(&name)
+                .map_err(|e| ExecutionError::StorageError(format!("Remove error: {}", e)))?;
+        }
+        Ok(())
+    }
+
+    /// 记录一条修改，在下一个提交点写入 WAL
+    fn log_change(&mut self, record: WalRecord) {
+        self.wal_pending.push(record);
+    }
+
+    /// 把待写入的记录作为一批追加到 WAL 并落盘
+    fn flush_wal(&mut self) -> Result<(), ExecutionError> {
+        if self.wal_pending.is_empty() {
+            return Ok(());
+        }
+        let records = std::mem::take(&mut self.wal_pending);
+        self.wal.append(self.backend.as_ref(), &records)
+            .map_err(|e| ExecutionError::StorageError(format!("WAL write error: {}", e)))?;
+        Ok(())
+    }
+
+    /// 写入全部表快照和元数据后截断 WAL
+    fn write_checkpoint(&mut self) -> Result<Lsn, ExecutionError> {
+        self.flush_wal()?;
+        let mut tables: Vec<(String, u32)> = self.table_catalog.iter().map(|(name, &id)| (name.clone(), id)).collect();
+        tables.sort_by_key(|&(_, table_id)| table_id);
+        for (table_name, table_id) in tables {
+            self.save_table(table_id, &table_name)?;
+        }
+        self.save_metadata()?;
+        
+        let trimmed = self.wal.size();
+        let lsn = self.wal.checkpoint(self.backend.as_ref())
+            .map_err(|e| ExecutionError::StorageError(format!("WAL checkpoint error: {}", e)))?;
+        log::info!("Checkpoint at LSN {} (WAL was {} bytes)", lsn, trimmed);
+        Ok(lsn)
+    }
+    
+    /// WAL 超过配置的大小时执行检查点
+    fn checkpoint_if_wal_full(&mut self) -> Result<(), ExecutionError> {
+        let limit = self.config.wal_checkpoint_bytes;
+        if limit > 0 && self.wal.size() > limit {
+            self.write_checkpoint()?;
+        }
+        Ok(())
+    }
+    
+    /// 丢弃一张表尚未写入 WAL 的记录（其修改已被撤销）
+    fn discard_wal_records(&mut self, table_id: u32) {
+        self.wal_pending.retain(|record| record.table_id() != Some(table_id));
+    }
+
+    /// 按顺序重放 WAL：补上快照之后的行修改，以及元数据写入前中断的建表和删表
+    fn replay_wal(&mut self, entries: Vec<WalEntry>, mut snapshot_lsns: HashMap<u32, Lsn>) -> Result<(), ExecutionError> {
+        let mut catalog_changed = false;
+        let mut replayed = 0;
+        for WalEntry { lsn, record } in entries {
+            match record {
+                WalRecord::CreateTable { table_id, name } => {
+                    self.next_table_id = self.next_table_id.max(table_id + 1);
+                    if self.table_catalog.contains_key(&name) {
+                        continue;
+                    }
+                    // 快照在 WAL 记录之前写入；表随后又被删除时快照已不存在
+                    if let Some(snapshot_lsn) = self.load_table(table_id, &name)? {
+                        self.table_catalog.insert(name, table_id);
+                        snapshot_lsns.insert(table_id, snapshot_lsn);
+                        catalog_changed = true;
+                    }
+                }
+                WalRecord::DropTable { table_id, name } => {
+                    if self.table_catalog.get(&name) == Some(&table_id) {
+                        self.table_catalog.remove(&name);
+                        self.table_schemas.remove(&table_id);
+                        self.table_indexes.remove(&table_id);
+                        self.remove_table_rows(table_id)?;
+                        self.table_row_ids.remove(&table_id);
+                        self.table_compression.remove(&table_id);
+                        self.table_statistics.remove(&table_id);
+                        snapshot_lsns.remove(&table_id);
+                        self.remove_table_snapshot(table_id)?;
+                        catalog_changed = true;
+                    }
+                }
+                WalRecord::Checkpoint | WalRecord::Commit | WalRecord::CommitAt { .. } => {}
+                record => {
+                    let Some(table_id) = record.table_id() else {
+                        continue;
+                    };
+                    if snapshot_lsns.get(&table_id).is_some_and(|&snapshot_lsn| lsn > snapshot_lsn) {
+                        self.apply_row_change(table_id, record)?;
+                        replayed += 1;
+                    }
+                }
+            }
+        }
         
-        // 返回None，因为我们没有从文件中获取表名，需要从元数据中获取
-        Ok(None)
+        if catalog_changed {
+            self.save_metadata()?;
+        }
+        log::debug!("Replayed {} row change(s) from the write-ahead log", replayed);
+        Ok(())
+    }
+
+    /// 把一条行修改记录应用到表；从索引文件载入的索引随之逐行维护
+    fn apply_row_change(&mut self, table_id: u32, record: WalRecord) -> Result<(), ExecutionError> {
+        let (Some(rows), Some(row_ids)) = (self.table_data.get_mut(&table_id), self.table_row_ids.get_mut(&table_id)) else {
+            return Ok(());
+        };
+        let previous_stamp = rows.stamp();
+        // 修改的行：(rowid, 旧值, 新值)，插入没有旧值，删除没有新值
+        let (row_id, old, new) = match record {
+            WalRecord::Insert { row_id, row, .. } => {
+                rows.push(&self.buffer_pool, row.clone())?;
+                row_ids.ids.push(row_id);
+                row_ids.next = row_ids.next.max(row_id + 1);
+                (row_id, None, Some(row))
+            }
+            WalRecord::Update { row_id, row, .. } => {
+                let Some(position) = row_ids.ids.iter().position(|&id| id == row_id) else {
+                    return Ok(());
+                };
+                let old = rows.get(&self.buffer_pool, position)?;
+                rows.set(&self.buffer_pool, position, row.clone())?;
+                (row_id, old, Some(row))
+            }
+            WalRecord::Delete { row_id, .. } => {
+                let Some(position) = row_ids.ids.iter().position(|&id| id == row_id) else {
+                    return Ok(());
+                };
+                let old = rows.get(&self.buffer_pool, position)?;
+                rows.remove(&self.buffer_pool, position)?;
+                row_ids.ids.remove(position);
+                (row_id, old, None)
+            }
+            _ => return Ok(()),
+        };
+        let stamp = rows.stamp();
+        self.maintain_row_indexes(table_id, new.as_ref(), |index, member| match (&old, &new) {
+            (None, Some(new)) if member => index.insert(new, row_id, previous_stamp, stamp),
+            (Some(old), Some(new)) if member => index.update(old, new, row_id, previous_stamp, stamp),
+            (Some(old), _) => index.remove(old, row_id, previous_stamp, stamp),
+            (None, _) => index.advance(previous_stamp, stamp),
+        });
+        // 快照中保存的索引构建进度已不再对应表数据
+        for index in self.table_indexes.get_mut(&table_id).into_iter().flatten() {
+            if let Some(build) = &mut index.build {
+                *build = IndexBuild::default();
+            }
+        }
+        Ok(())
     }
 
     /// 保存数据库元数据
@@ -1772,16 +6264,14 @@ impl Database {
         let metadata = DatabaseMetadata {
             next_table_id: self.next_table_id,
             table_catalog: self.table_catalog.clone(),
+            rewrite_rules: self.rewrite_rules.clone(),
+            snippets: self.snippets.clone(),
         };
 
         let json = serde_json::to_string_pretty(&metadata)
             .map_err(|e| ExecutionError::StorageError(format!("Metadata serialization error: {}", e)))?;
 
-        let file_path = self.data_dir.join("metadata.json");
-        let mut file = File::create(file_path)
-            .map_err(|e| ExecutionError::StorageError(format!("Metadata file creation error: {}", e)))?;
-
-        file.write_all(json.as_bytes())
+        self.backend.write(METADATA_FILE_NAME, json.as_bytes())
             .map_err(|e| ExecutionError::StorageError(format!("Metadata write error: {}", e)))?;
 
         log::debug!("Saved database metadata (next_id: {}, tables: {})", 
@@ -1790,26 +6280,23 @@ impl Database {
     }
 
     /// 加载数据库元数据
-    fn load_metadata(&mut self) -> Result<(), ExecutionError> {
-        let file_path = self.data_dir.join("metadata.json");
-        
-        if !file_path.exists() {
-            log::debug!("No metadata file found, starting with fresh database");
-            return Ok(()); // 没有元数据文件，是新数据库
-        }
-
-        let mut file = File::open(file_path)
-            .map_err(|e| ExecutionError::StorageError(format!("Metadata file open error: {}", e)))?;
-
-        let mut contents = String::new();
-        file.read_to_string(&mut contents)
-            .map_err(|e| ExecutionError::StorageError(format!("Metadata read error: {}", e)))?;
+    fn load_metadata(&mut self) -> Result<(), ExecutionError> {
+        let contents = match self.backend.read(METADATA_FILE_NAME) {
+            Ok(Some(contents)) => contents,
+            Ok(None) => {
+                log::debug!("No metadata file found, starting with fresh database");
+                return Ok(()); // 没有元数据文件，是新数据库
+            }
+            Err(e) => return Err(ExecutionError::StorageError(format!("Metadata read error: {}", e))),
+        };
 
-        let metadata: DatabaseMetadata = serde_json::from_str(&contents)
+        let metadata: DatabaseMetadata = serde_json::from_slice(&contents)
             .map_err(|e| ExecutionError::StorageError(format!("Metadata deserialization error: {}", e)))?;
 
         self.next_table_id = metadata.next_table_id;
         self.table_catalog = metadata.table_catalog;
+        self.rewrite_rules = metadata.rewrite_rules;
+        self.snippets = metadata.snippets;
 
         log::debug!("Loaded database metadata (next_id: {}, tables: {})", 
                    self.next_table_id, self.table_catalog.len());
@@ -1818,223 +6305,445 @@ impl Database {
 
     /// 加载所有现有表
     fn load_existing_tables(&mut self) -> Result<(), ExecutionError> {
+        // 标记只说明上一次是否正常关闭，读到后即删除，本次崩溃时就不会留下
+        self.clean_shutdown = self.backend.read(CLEAN_SHUTDOWN_MARKER)
+            .and_then(|marker| {
+                self.backend.remove(CLEAN_SHUTDOWN_MARKER)?;
+                Ok(marker.is_some())
+            })
+            .map_err(|e| ExecutionError::StorageError(format!("Failed to read shutdown marker: {}", e)))?;
+        
         // 先加载元数据
         self.load_metadata()?;
 
-        // 加载所有表的数据
+        // 加载所有表的快照
+        let mut snapshot_lsns = HashMap::new();
         for (table_name, &table_id) in &self.table_catalog.clone() {
-            if let Err(e) = self.load_table(table_id) {
-                log::warn!("Failed to load table '{}' (id: {}): {}", table_name, table_id, e);
-                // 继续加载其他表，不要因为一个表加载失败就停止
+            match self.load_table(table_id, table_name) {
+                Ok(Some(lsn)) => {
+                    snapshot_lsns.insert(table_id, lsn);
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    log::warn!("Failed to load table '{}' (id: {}): {}", table_name, table_id, e);
+                    // 继续加载其他表，不要因为一个表加载失败就停止
+                }
             }
         }
+        
+        // 重放快照之后提交的修改
+        let (wal, entries) = Wal::open(self.backend.as_ref())
+            .map_err(|e| ExecutionError::StorageError(format!("WAL read error: {}", e)))?;
+        self.wal = wal;
+        self.replay_wal(entries, snapshot_lsns)?;
 
         log::info!("Database loaded: {} tables", self.table_catalog.len());
         Ok(())
     }
     
-    /// Check primary key constraint for a tuple against existing data
-    fn check_primary_key_constraint(
-        &self,
-        new_tuple: &Tuple,
-        primary_key_columns: &[usize],
-        table_id: u32
-    ) -> Result<(), ExecutionError> {
-        // Get existing table data
-        let existing_data = self.table_data.get(&table_id)
-            .ok_or_else(|| ExecutionError::TableNotFound { 
-                table: format!("table_id_{}", table_id) 
-            })?;
-        
-        // Extract primary key values from the new tuple
-        let mut new_key_values = Vec::new();
-        for &col_index in primary_key_columns {
-            if col_index >= new_tuple.values.len() {
-                return Err(ExecutionError::EvaluationError {
-                    message: format!("Primary key column index {} out of bounds", col_index)
-                });
-            }
-            new_key_values.push(new_tuple.values[col_index].clone());
+    /// 写入正常关闭标记，内容为 WAL 的最后一个 LSN
+    fn write_clean_shutdown_marker(&self) -> Result<(), ExecutionError> {
+        self.backend.write(CLEAN_SHUTDOWN_MARKER, self.wal.last_lsn().to_string().as_bytes())
+            .map_err(|e| ExecutionError::StorageError(format!("Failed to write shutdown marker: {}", e)))
+    }
+}
+
+/// 未启用 persistence 特性时数据只存在于内存中
+#[cfg(not(feature = "persistence"))]
+impl Database {
+    fn save_table(&mut self, _table_id: u32, _table_name: &str) -> Result<(), ExecutionError> {
+        Ok(())
+    }
+
+    fn save_metadata(&self) -> Result<(), ExecutionError> {
+        Ok(())
+    }
+
+    fn remove_table_snapshot(&mut self, _table_id: u32) -> Result<(), ExecutionError> {
+        Ok(())
+    }
+
+    fn log_change(&mut self, _record: WalRecord) {}
+
+    fn flush_wal(&mut self) -> Result<(), ExecutionError> {
+        Ok(())
+    }
+
+    fn discard_wal_records(&mut self, _table_id: u32) {}
+
+    fn write_checkpoint(&mut self) -> Result<Lsn, ExecutionError> {
+        Ok(0)
+    }
+
+    fn checkpoint_if_wal_full(&mut self) -> Result<(), ExecutionError> {
+        Ok(())
+    }
+
+    fn write_clean_shutdown_marker(&self) -> Result<(), ExecutionError> {
+        Ok(())
+    }
+}
+
+impl Drop for Database {
+    fn drop(&mut self) {
+        if let Err(e) = self.close() {
+            log::warn!("Failed to close database cleanly: {}", e);
         }
-        
-        // Check against existing tuples
-        for existing_tuple in existing_data {
-            let mut existing_key_values = Vec::new();
-            for &col_index in primary_key_columns {
-                if col_index >= existing_tuple.values.len() {
-                    continue; // Skip malformed data
-                }
-                existing_key_values.push(existing_tuple.values[col_index].clone());
+    }
+}
+
+/// SUM / AVG 的累加器：整数和 DECIMAL 精确累加，浮点数按 DOUBLE 累加
+///
+/// 参数中出现 DECIMAL 且没有浮点数时结果为 DECIMAL，否则为 DOUBLE。
+#[derive(Default)]
+struct NumericSum {
+    exact: Option<Decimal>,
+    float: Option<f64>,
+    has_decimal: bool,
+    count: i64,
+}
+
+impl NumericSum {
+    /// 累加一个值，NULL 被跳过
+    fn add(&mut self, value: &Value) -> Result<(), ExecutionError> {
+        let exact = match value {
+            Value::Null => return Ok(()),
+            Value::Integer(i) => Decimal::from(*i),
+            Value::BigInt(i) => Decimal::from(*i),
+            Value::Decimal(d) => {
+                self.has_decimal = true;
+                *d
             }
-            
-            // Compare key values
-            if new_key_values == existing_key_values {
-                // Found duplicate primary key
-                let key_str = new_key_values.iter()
-                    .map(|v| v.to_string())
-                    .collect::<Vec<_>>()
-                    .join(", ");
-                
-                return Err(ExecutionError::PrimaryKeyViolation {
-                    key: format!("({})", key_str)
-                });
+            Value::Float(f) => {
+                self.float = Some(self.float.unwrap_or(0.0) + *f as f64);
+                self.count += 1;
+                return Ok(());
             }
-        }
-        
+            Value::Double(d) => {
+                self.float = Some(self.float.unwrap_or(0.0) + d);
+                self.count += 1;
+                return Ok(());
+            }
+            _ => return Ok(()),
+        };
+        let total = match self.exact {
+            Some(total) => total.checked_add(&exact).ok_or_else(|| ExecutionError::EvaluationError {
+                message: "DECIMAL overflow in SUM".to_string(),
+            })?,
+            None => exact,
+        };
+        self.exact = Some(total);
+        self.count += 1;
         Ok(())
     }
 
-    /// 获取所有列名，用于错误诊断
-    fn get_all_column_names(&self) -> Vec<String> {
-        let mut column_names = Vec::new();
-        for schema in self.table_schemas.values() {
-            for column in &schema.columns {
-                if !column_names.contains(&column.name) {
-                    column_names.push(column.name.clone());
-                }
-            }
+    /// 和；没有任何值时为 NULL
+    fn total(&self) -> Value {
+        match (self.exact, self.float) {
+            (None, None) => Value::Null,
+            (Some(exact), None) if self.has_decimal => Value::Decimal(exact),
+            (exact, float) => Value::Double(exact.map_or(0.0, |d| d.to_f64()) + float.unwrap_or(0.0)),
         }
-        column_names
     }
-    
-    /// Execute CREATE INDEX statement
-    fn execute_create_index(
-        &mut self,
-        index_name: String,
-        table_name: String,
-        columns: Vec<String>,
-        _is_unique: bool,
-    ) -> Result<QueryResult, ExecutionError> {
-        // Check if table exists
-        let table_id = self.table_catalog.get(&table_name)
-            .ok_or_else(|| ExecutionError::TableNotFound { table: table_name.clone() })?;
-        
-        let schema = self.table_schemas.get(table_id)
-            .ok_or_else(|| ExecutionError::TableNotFound { table: table_name.clone() })?;
-        
-        // Validate that all columns exist
-        for column in &columns {
-            if !schema.columns.iter().any(|col| &col.name == column) {
-                return Err(ExecutionError::ColumnNotFound { 
-                    column: column.clone(),
-                    table: table_name.clone() 
-                });
-            }
-        }
-        
-        // For now, we'll just report success as the actual index creation
-        // would be handled by the storage layer in a real implementation
-        Ok(QueryResult {
-            rows: vec![],
-            schema: None,
-            affected_rows: 0,
-            message: format!(
-                "Index '{}' created successfully on table '{}' for columns [{}]", 
-                index_name, 
-                table_name,
-                columns.join(", ")
-            ),
+
+    /// 平均值；没有任何值时为 NULL
+    fn average(&self) -> Result<Value, ExecutionError> {
+        Ok(match self.total() {
+            Value::Decimal(total) => total.checked_div(&Decimal::from(self.count)).map(Value::Decimal).ok_or_else(|| {
+                ExecutionError::EvaluationError { message: "DECIMAL overflow in AVG".to_string() }
+            })?,
+            Value::Double(total) => Value::Double(total / self.count as f64),
+            _ => Value::Null,
         })
     }
-    
-    /// Execute DROP INDEX statement
-    fn execute_drop_index(
-        &mut self,
-        index_name: String,
-        table_name: String,
-    ) -> Result<QueryResult, ExecutionError> {
-        // Check if table exists
-        let _table_id = self.table_catalog.get(&table_name)
-            .ok_or_else(|| ExecutionError::TableNotFound { table: table_name.clone() })?;
-        
-        // For now, we'll just report success as the actual index dropping
-        // would be handled by the storage layer in a real implementation
-        Ok(QueryResult {
-            rows: vec![],
-            schema: None,
-            affected_rows: 0,
-            message: format!(
-                "Index '{}' dropped successfully from table '{}'", 
-                index_name, 
-                table_name
-            ),
-        })
+}
+
+/// AssertionFailed 错误信息中的样本行
+fn format_samples(samples: &[Tuple]) -> String {
+    if samples.is_empty() {
+        return String::new();
     }
-    
-    /// Execute EXPLAIN statement
-    fn execute_explain(
-        &mut self,
-        statement: Statement,
-    ) -> Result<QueryResult, ExecutionError> {
-        // Generate execution plan based on statement type
-        let execution_plan = match &statement {
-            Statement::Select { select_list, from_clause, where_clause, .. } => {
-                self.generate_execution_plan_for_select(select_list, from_clause, where_clause)
+    let rows: Vec<String> = samples.iter()
+        .map(|row| format!("({})", row.values.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", ")))
+        .collect();
+    format!("; sample rows: {}", rows.join(", "))
+}
+
+/// 另一侧为 DECIMAL 时，把小数常量（如 `0.1`、`-2.5`）按字面值转换为 DECIMAL，
+/// 使 `d + 0.1`、`d = 1.2` 精确计算，而不是经过 DOUBLE 引入舍入误差
+fn exact_decimal_operands(
+    left_expr: &crate::sql::parser::Expression,
+    left: Value,
+    right_expr: &crate::sql::parser::Expression,
+    right: Value,
+) -> (Value, Value) {
+    use crate::sql::parser::{Expression, UnaryOperator};
+
+    fn is_decimal_literal(expr: &Expression) -> bool {
+        match expr {
+            Expression::Literal(Value::Double(_)) => true,
+            Expression::UnaryOp { op: UnaryOperator::Minus | UnaryOperator::Plus, expr } => is_decimal_literal(expr),
+            _ => false,
+        }
+    }
+    // 词法分析器只接受不带指数的小数，f64 的最短表示即原文的数值
+    let exact = |value: Value| match value {
+        Value::Double(f) => f.to_string().parse::<Decimal>().map(Value::Decimal).unwrap_or(Value::Double(f)),
+        other => other,
+    };
+    match (&left, &right) {
+        (Value::Decimal(_), Value::Double(_)) if is_decimal_literal(right_expr) => (left, exact(right)),
+        (Value::Double(_), Value::Decimal(_)) if is_decimal_literal(left_expr) => (exact(left), right),
+        _ => (left, right),
+    }
+}
+
+/// 去掉字符串末尾补齐用的空格，其他值不变
+fn trim_char_padding(value: Value) -> Value {
+    match value {
+        Value::Varchar(s) => Value::Varchar(s.trim_end_matches(' ').to_string()),
+        other => other,
+    }
+}
+
+/// SQL LIKE 模式匹配：`%` 匹配任意长度字符串，`_` 匹配单个字符，
+/// `escape` 之后的字符按字面匹配
+fn like_match(text: &str, pattern: &str, escape: Option<char>) -> Result<bool, ExecutionError> {
+    enum Piece {
+        Any,
+        One,
+        Literal(char),
+    }
+
+    let mut pieces = Vec::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        pieces.push(match c {
+            c if Some(c) == escape => match chars.next() {
+                Some(escaped) => Piece::Literal(escaped),
+                None => return Err(ExecutionError::EvaluationError {
+                    message: format!("LIKE pattern '{}' ends with the escape character", pattern),
+                }),
+            },
+            '%' => Piece::Any,
+            '_' => Piece::One,
+            c => Piece::Literal(c),
+        });
+    }
+
+    // 贪心匹配，遇到失配时回溯到最近一个 % 多吞一个字符
+    let text: Vec<char> = text.chars().collect();
+    let (mut t, mut p) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pieces.get(p) {
+            Some(Piece::Any) => {
+                backtrack = Some((p, t));
+                p += 1;
             }
-            Statement::Insert { table_name, .. } => {
-                format!("Insert Plan:\n1. Insert into table '{}'", table_name)
+            Some(Piece::One) => {
+                t += 1;
+                p += 1;
             }
-            Statement::Update { table_name, .. } => {
-                format!("Update Plan:\n1. Update table '{}'", table_name)
+            Some(Piece::Literal(c)) if *c == text[t] => {
+                t += 1;
+                p += 1;
             }
-            Statement::Delete { table_name, .. } => {
-                format!("Delete Plan:\n1. Delete from table '{}'", table_name)
+            _ => match backtrack {
+                Some((star, start)) => {
+                    p = star + 1;
+                    t = start + 1;
+                    backtrack = Some((star, start + 1));
+                }
+                None => return Ok(false),
+            },
+        }
+    }
+    Ok(pieces[p..].iter().all(|piece| matches!(piece, Piece::Any)))
+}
+
+/// 找出第一个与之前某行键值相同的行（含 NULL 的键互不冲突）
+fn find_duplicate_key<'a>(rows: impl IntoIterator<Item = &'a Tuple>, key_columns: &[usize]) -> Option<&'a Tuple> {
+    let mut seen = std::collections::HashSet::new();
+    rows.into_iter().find(|row| {
+        let key: Vec<&Value> = key_columns.iter().map(|&i| &row.values[i]).collect();
+        !key.iter().any(|value| value.is_null()) && !seen.insert(key)
+    })
+}
+
+/// 将行的键值格式化为 "(v1, v2)"
+fn format_key(tuple: &Tuple, key_columns: &[usize]) -> String {
+    let key_str = key_columns.iter()
+        .map(|&i| tuple.values[i].to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("({})", key_str)
+}
+
+
+impl OperatorContext for Database {
+    /// 表、系统视图或临时视图的行；WHERE 可以用索引时只取索引找到的行，查询只用到索引中的列时不读表数据
+    fn scan_input(
+        &self,
+        table_name: &str,
+        where_clause: Option<&crate::sql::parser::Expression>,
+        select_list: &crate::sql::parser::SelectList,
+        used: &[&crate::sql::parser::Expression],
+    ) -> Result<ScanInput<'_>, ExecutionError> {
+        let used = || where_clause.into_iter().chain(used.iter().copied());
+        let (table_id, schema, table_data, probed_ids, total_rows) = match self.index_probe(table_name, where_clause) {
+            Some((table_id, probe)) => {
+                let schema = self.table_schemas.get(&table_id)
+                    .ok_or_else(|| ExecutionError::TableNotFound { table: table_name.to_string() })?;
+                let (rows, row_ids) = if Self::covered_by_index(schema, &probe, select_list, used()) {
+                    self.fetch_index_only(table_id, &probe)?
+                } else {
+                    self.fetch_by_index(table_id, &probe)?
+                };
+                let total_rows = self.table_data.get(&table_id).map_or(0, TableRows::len);
+                (Some(table_id), std::borrow::Cow::Borrowed(schema), ScanRows::Rows(std::borrow::Cow::Owned(rows)), Some(row_ids), total_rows)
+            }
+            None => {
+                let (table_id, schema, rows) = self.scan_source(table_name)?;
+                let total_rows = rows.len();
+                (table_id, schema, rows, None, total_rows)
             }
-            _ => "Execution plan not available for this statement type".to_string(),
         };
         
-        Ok(QueryResult {
-            rows: vec![Tuple::new(vec![Value::Varchar(execution_plan)])],
-            schema: Some(Schema {
-                columns: vec![ColumnDefinition {
-                    name: "Query Plan".to_string(),
-                    data_type: DataType::Varchar(1000),
-                    nullable: false,
-                    default: None,
-                }],
-                primary_key: None,
-            }),
-            affected_rows: 0,
-            message: "Query execution plan generated".to_string(),
-        })
+        // rowid 伪列只在被引用时才附加到扫描结果（系统视图没有 rowid）
+        let select_exprs = match select_list {
+            crate::sql::parser::SelectList::Expressions(exprs) => exprs.as_slice(),
+            crate::sql::parser::SelectList::Wildcard => &[],
+        };
+        let referenced = used().chain(select_exprs.iter().map(|select_expr| &select_expr.expr));
+        let table_columns = schema.columns.len();
+        let (rows, schema) = match table_id {
+            Some(table_id) if Self::needs_rowid(&schema, referenced) => {
+                // 逐行读取时才附加 rowid，不为此把整张表复制一遍
+                let ids = match probed_ids {
+                    Some(ids) => std::borrow::Cow::Owned(ids),
+                    None => std::borrow::Cow::Borrowed(self.row_ids(table_id)),
+                };
+                let len = table_data.len();
+                let fetch = move |i: usize| -> Result<Option<Tuple>, ExecutorError> {
+                    Ok(table_data.get(i)?.map(|row| Self::with_rowid(row.into_owned(), ids.get(i).copied())))
+                };
+                (ScanRows::Fetch { fetch: Box::new(fetch), len }, std::borrow::Cow::Owned(Self::rowid_schema(&schema)))
+            }
+            _ => (table_data, schema),
+        };
+        Ok(ScanInput { rows, schema, table_columns, total_rows })
     }
     
-    /// Generate execution plan for SELECT statement
-    fn generate_execution_plan_for_select(
+    fn matches(&self, condition: &crate::sql::parser::Expression, row: &Tuple, schema: &Schema) -> Result<bool, ExecutionError> {
+        self.evaluate_where_condition(condition, row, schema)
+    }
+    
+    fn evaluate(&self, expr: &crate::sql::parser::Expression, row: &Tuple, schema: &Schema) -> Result<Value, ExecutionError> {
+        self.evaluate_expression_for_tuple(expr, row, schema)
+    }
+    
+    fn contains_aggregates(&self, select_list: &crate::sql::parser::SelectList) -> bool {
+        self.select_list_contains_aggregates(select_list)
+    }
+    
+    fn project<'a>(
+        &'a self,
+        input: Box<dyn Executor + 'a>,
+        select_exprs: Vec<crate::sql::parser::SelectExpr>,
+        table_name: &str,
+    ) -> Result<Box<dyn Executor + 'a>, ExecutionError> {
+        Ok(Box::new(self.project_columns(input, select_exprs, table_name)?))
+    }
+    
+    fn aggregate(
         &self,
-        _select_list: &crate::sql::parser::SelectList,
-        from_clause: &Option<crate::sql::parser::FromClause>,
-        where_clause: &Option<crate::sql::parser::Expression>,
-    ) -> String {
-        let mut plan = String::new();
-        plan.push_str("Select Execution Plan:\n");
-        
-        // Add scan operation
-        if let Some(from) = from_clause {
-            match from {
-                crate::sql::parser::FromClause::Table(table_name) => {
-                    plan.push_str(&format!("1. Table Scan: {}\n", table_name));
-                }
-                _ => {
-                    plan.push_str("1. Complex From Clause\n");
-                }
-            }
-        }
-        
-        // Add filter operation if WHERE clause exists
-        if where_clause.is_some() {
-            plan.push_str("2. Filter: Apply WHERE conditions\n");
+        rows: Vec<Tuple>,
+        schema: &Schema,
+        group_exprs: &[crate::sql::parser::Expression],
+        select_list: &crate::sql::parser::SelectList,
+    ) -> Result<(Vec<Tuple>, Schema), ExecutionError> {
+        self.apply_group_by_with_select(rows, schema, group_exprs, select_list)
+    }
+    
+    fn compare(&self, a: &Value, b: &Value) -> std::cmp::Ordering {
+        self.compare_values_for_sort(a, b)
+    }
+    
+    fn sort_settings(&self) -> SortSettings {
+        SortSettings {
+            deterministic: self.config.deterministic_sort,
+            work_mem_bytes: self.config.sort_work_mem_bytes,
+            space: self.temp_files.as_ref().map(TempFileManager::begin_statement),
         }
-        
-        // Add projection
-        plan.push_str("3. Projection: Select specified columns\n");
-        
-        // Add optimization notes
-        if where_clause.is_some() {
-            plan.push_str("\nOptimizations applied:\n");
-            plan.push_str("- Predicate pushdown: WHERE conditions applied early\n");
+    }
+    
+    /// 配置了观察者时为算子套上发送 open/next/close 事件的包装器
+    fn observed<'a>(&self, executor: Box<dyn Executor + 'a>, info: OperatorInfo) -> Box<dyn Executor + 'a> {
+        match &self.config.observer {
+            Some(observer) => Box::new(ObservedExecutor::new(executor, observer.clone(), info)),
+            None => executor,
         }
-        
-        plan
+    }
+    
+    fn optimized_plan(&self, query: Statement) -> Result<ExecutionPlan, ExecutionError> {
+        let analyzed = crate::sql::analyze_statement(query, self)
+            .map_err(|e| ExecutionError::SemanticError(e.to_string()))?;
+        crate::sql::create_plan(analyzed)
+            .and_then(|plan| self.optimizer.optimize_with_catalog(plan, self))
+            .map(|optimized| optimized.plan)
+            .map_err(|e| ExecutionError::SemanticError(e.to_string()))
+    }
+}
+
+impl crate::sql::planner::AccessPathCatalog for Database {
+    fn table_row_count(&self, table_name: &str) -> Option<usize> {
+        let table_id = self.table_catalog.get(table_name)?;
+        self.table_data.get(table_id).map(TableRows::len)
+    }
+
+    fn table_statistics(&self, table_name: &str) -> Option<&TableStatistics> {
+        Database::table_statistics(self, table_name)
+    }
+
+    fn index_access(&self, table_name: &str, filter: &crate::sql::parser::Expression) -> Option<crate::sql::planner::IndexAccess> {
+        // 连接中下推到扫描的条件用 `表.列` 引用本表的列，按索引匹配前去掉限定
+        let mut filter = filter.clone();
+        filter.visit_mut(&mut |expr| {
+            if let crate::sql::parser::Expression::QualifiedColumn { table, column } = expr {
+                if table == table_name {
+                    *expr = crate::sql::parser::Expression::Column(column.clone());
+                }
+            }
+        });
+        let (table_id, probe, estimated_rows) = self.index_candidate(table_name, &filter)?;
+        // 键列、INCLUDE 列和 rowid 伪列（表中没有同名真实列时）都可以由索引提供
+        let schema = self.table_schemas.get(&table_id)?;
+        let has_rowid_column = schema.columns.iter().any(|column| column.name == ROWID_COLUMN);
+        let columns = probe.columns.iter().chain(&probe.include)
+            .map(|&i| schema.columns[i].name.clone())
+            .chain((!has_rowid_column).then(|| ROWID_COLUMN.to_string()))
+            .collect();
+        Some(crate::sql::planner::IndexAccess {
+            index_name: probe.index,
+            condition: probe.predicate,
+            estimated_rows,
+            columns,
+        })
+    }
+}
+
+impl crate::sql::analyzer::SchemaCatalog for Database {
+    fn get_table_schema(&self, table_name: &str) -> Option<Schema> {
+        Database::get_table_schema(self, table_name)
+            .cloned()
+            .or_else(|| Database::system_view_schema(table_name))
+            .or_else(|| self.get_view_schema(table_name).cloned())
+    }
+
+    fn table_exists(&self, table_name: &str) -> bool {
+        self.table_catalog.contains_key(table_name)
+            || Database::system_view_schema(table_name).is_some()
+            || self.temp_views.contains_key(table_name)
     }
 }
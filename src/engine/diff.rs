@@ -0,0 +1,130 @@
+//! 结果集比对
+//!
+//! 比较两个结果集（表或查询），找出只在 A 中、只在 B 中以及键相同而其余列不同的行，
+//! 用于校验迁移和导入。实现为哈希连接：B 的行按键建哈希表，A 的行逐行探测。
+//! 有键列时按键匹配；没有时以整行为键，按多重集比较（重复行按出现次数配对）。
+
+use crate::types::{ColumnDefinition, DataType, Schema, Tuple, Value};
+use std::collections::HashMap;
+
+/// 比对结果中一行的类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Change {
+    /// 只在 A 中
+    OnlyLeft,
+    /// 只在 B 中
+    OnlyRight,
+    /// 键相同而其余列不同的行在 A 中的值
+    ChangedLeft,
+    /// 键相同而其余列不同的行在 B 中的值
+    ChangedRight,
+}
+
+impl Change {
+    /// 结果中 diff 列的取值
+    pub(crate) fn marker(self) -> &'static str {
+        match self {
+            Change::OnlyLeft => "-",
+            Change::OnlyRight => "+",
+            Change::ChangedLeft => "<",
+            Change::ChangedRight => ">",
+        }
+    }
+}
+
+/// 比对两组行；key 为键列下标，为空时以整行为键
+///
+/// 结果按 A 的行序列出只在 A 中的行和成对的变更行，最后是只在 B 中的行（按 B 的行序）。
+pub(crate) fn diff_rows(left: &[Tuple], right: &[Tuple], key: &[usize]) -> Vec<(Change, Tuple)> {
+    let key_of = |row: &Tuple| -> Vec<Value> {
+        if key.is_empty() {
+            row.values.clone()
+        } else {
+            key.iter().map(|&i| row.values[i].clone()).collect()
+        }
+    };
+
+    // 逆序插入，探测时 pop 即按 B 的行序取出同键的行
+    let mut build: HashMap<Vec<Value>, Vec<usize>> = HashMap::new();
+    for (i, row) in right.iter().enumerate().rev() {
+        build.entry(key_of(row)).or_default().push(i);
+    }
+
+    let mut matched = vec![false; right.len()];
+    let mut changes = Vec::new();
+    for row in left {
+        match build.get_mut(&key_of(row)).and_then(Vec::pop) {
+            Some(j) => {
+                matched[j] = true;
+                if right[j] != *row {
+                    changes.push((Change::ChangedLeft, row.clone()));
+                    changes.push((Change::ChangedRight, right[j].clone()));
+                }
+            }
+            None => changes.push((Change::OnlyLeft, row.clone())),
+        }
+    }
+    changes.extend(
+        right.iter()
+            .zip(&matched)
+            .filter(|(_, &matched)| !matched)
+            .map(|(row, _)| (Change::OnlyRight, row.clone())),
+    );
+    changes
+}
+
+/// 比对结果的模式：diff 标记列加上 A 的各列
+pub(crate) fn diff_schema(schema: &Schema) -> Schema {
+    let mut columns = vec![ColumnDefinition::new("diff".to_string(), DataType::Varchar(1), false)];
+    columns.extend(schema.columns.iter().map(|column| ColumnDefinition {
+        default: None,
+        default_function: None,
+        ..column.clone()
+    }));
+    Schema::new(columns)
+}
+
+/// 在每行前加上 diff 标记列
+pub(crate) fn diff_tuples(changes: Vec<(Change, Tuple)>) -> Vec<Tuple> {
+    changes.into_iter()
+        .map(|(change, row)| {
+            let mut values = Vec::with_capacity(row.values.len() + 1);
+            values.push(Value::Varchar(change.marker().to_string()));
+            values.extend(row.values);
+            Tuple::new(values)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(id: i32, name: &str) -> Tuple {
+        Tuple::new(vec![Value::Integer(id), Value::Varchar(name.to_string())])
+    }
+
+    #[test]
+    fn test_diff_by_key() {
+        let left = vec![row(1, "a"), row(2, "b"), row(3, "c")];
+        let right = vec![row(4, "d"), row(3, "c"), row(2, "x")];
+        let markers: Vec<_> = diff_rows(&left, &right, &[0]).into_iter()
+            .map(|(change, row)| (change.marker(), row.values[0].clone()))
+            .collect();
+        assert_eq!(markers, vec![
+            ("-", Value::Integer(1)),
+            ("<", Value::Integer(2)),
+            (">", Value::Integer(2)),
+            ("+", Value::Integer(4)),
+        ]);
+    }
+
+    #[test]
+    fn test_diff_whole_rows_as_multiset() {
+        let left = vec![row(1, "a"), row(1, "a"), row(2, "b")];
+        let right = vec![row(1, "a"), row(2, "x")];
+        let changes: Vec<_> = diff_rows(&left, &right, &[]).into_iter().map(|(change, _)| change).collect();
+        assert_eq!(changes, vec![Change::OnlyLeft, Change::OnlyLeft, Change::OnlyRight]);
+        assert!(diff_rows(&right, &right, &[]).is_empty());
+    }
+}
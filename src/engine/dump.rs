@@ -0,0 +1,376 @@
+//! SQL 转储
+//!
+//! 把表导出为 CREATE TABLE、INSERT 和 CREATE INDEX 语句，用于迁移到另一个 MiniDB 或 SQLite / PostgreSQL。
+//! 输出只使用三者都能解析的写法：字符串中的单引号写成两个，日期和时间戳写成字符串的 CAST，
+//! 复合主键写成表级 `PRIMARY KEY (...)`。索引在数据之后建立，导入时不必逐行维护索引。
+//! 索引的 INCLUDE 列照原样写出，只有 PostgreSQL 能解析；部分索引的 WHERE 谓词 SQLite 和 PostgreSQL 都能解析。
+
+use crate::engine::database::IndexInfo;
+use crate::sql::parser::{BinaryOperator, Expression, FromClause, JoinType, SelectList, Statement, UnaryOperator};
+use crate::types::{DataType, Schema, Tuple, Value};
+
+/// 建表语句；列上保留类型、NOT NULL、DEFAULT 和主键
+pub(crate) fn create_table_sql(table_name: &str, schema: &Schema) -> String {
+    let primary_key = schema.primary_key.as_deref().unwrap_or_default();
+    let mut definitions: Vec<String> = schema.columns.iter().enumerate()
+        .map(|(i, column)| {
+            let mut definition = format!("{} {}", column.name, column.data_type);
+            if !column.nullable {
+                definition.push_str(" NOT NULL");
+            }
+            if let Some(function) = &column.default_function {
+                definition.push_str(&format!(" DEFAULT {}()", function));
+            } else if let Some(default) = &column.default {
+                definition.push_str(" DEFAULT ");
+                definition.push_str(&column_literal(default, &column.data_type));
+            }
+            if primary_key == [i] {
+                definition.push_str(" PRIMARY KEY");
+            }
+            definition
+        })
+        .collect();
+    if primary_key.len() > 1 {
+        let columns: Vec<&str> = primary_key.iter().map(|&i| schema.columns[i].name.as_str()).collect();
+        definitions.push(format!("PRIMARY KEY ({})", columns.join(", ")));
+    }
+    format!("CREATE TABLE {} (\n    {}\n);", table_name, definitions.join(",\n    "))
+}
+
+/// 插入一行的语句，列出全部列名
+pub(crate) fn insert_sql(table_name: &str, schema: &Schema, row: &Tuple) -> String {
+    let columns: Vec<&str> = schema.columns.iter().map(|column| column.name.as_str()).collect();
+    let values: Vec<String> = row.values.iter().zip(&schema.columns)
+        .map(|(value, column)| column_literal(value, &column.data_type))
+        .collect();
+    format!("INSERT INTO {} ({}) VALUES ({});", table_name, columns.join(", "), values.join(", "))
+}
+
+/// 建索引语句
+pub(crate) fn create_index_sql(table_name: &str, index: &IndexInfo) -> String {
+    let include = if index.include.is_empty() {
+        String::new()
+    } else {
+        format!(" INCLUDE ({})", index.include.join(", "))
+    };
+    let where_clause = match &index.where_clause {
+        Some(where_clause) => format!(" WHERE {}", expression_sql(where_clause)),
+        None => String::new(),
+    };
+    format!(
+        "CREATE {}INDEX {} ON {} ({}){}{};",
+        if index.unique { "UNIQUE " } else { "" },
+        index.name,
+        table_name,
+        index.columns.join(", "),
+        include,
+        where_clause
+    )
+}
+
+/// 表达式的 SQL 文本，解析后得到同样含义的表达式
+///
+/// 只在运算符优先级需要时加括号；字面量按 [`sql_literal`] 书写。
+pub(crate) fn expression_sql(expr: &Expression) -> String {
+    match expr {
+        Expression::Literal(value) => sql_literal(value),
+        Expression::Column(name) => name.clone(),
+        Expression::QualifiedColumn { table, column } => format!("{}.{}", table, column),
+        Expression::BinaryOp { left, op, right } => {
+            let precedence = binary_precedence(op);
+            format!(
+                "{} {} {}",
+                operand_sql(left, precedence),
+                binary_symbol(op),
+                operand_sql(right, precedence + 1)
+            )
+        }
+        Expression::UnaryOp { op, expr } => {
+            let operand = operand_sql(expr, PRIMARY);
+            match op {
+                UnaryOperator::Not => format!("NOT {}", operand),
+                UnaryOperator::Minus => format!("-{}", operand),
+                UnaryOperator::Plus => format!("+{}", operand),
+            }
+        }
+        Expression::FunctionCall { name, args } => {
+            let args: Vec<String> = args.iter().map(expression_sql).collect();
+            format!("{}({})", name, args.join(", "))
+        }
+        Expression::In { expr, list, negated } => {
+            let list: Vec<String> = list.iter().map(expression_sql).collect();
+            format!("{} {}IN ({})", operand_sql(expr, ADDITIVE), not(*negated), list.join(", "))
+        }
+        Expression::Between { expr, low, high, negated } => format!(
+            "{} {}BETWEEN {} AND {}",
+            operand_sql(expr, ADDITIVE),
+            not(*negated),
+            operand_sql(low, ADDITIVE),
+            operand_sql(high, ADDITIVE)
+        ),
+        Expression::Like { expr, pattern, escape, negated } => {
+            let mut sql = format!("{} {}LIKE {}", operand_sql(expr, ADDITIVE), not(*negated), operand_sql(pattern, ADDITIVE));
+            if let Some(escape) = escape {
+                sql.push_str(" ESCAPE ");
+                sql.push_str(&operand_sql(escape, PRIMARY));
+            }
+            sql
+        }
+        Expression::Cast { expr, data_type } => format!("CAST({} AS {})", expression_sql(expr), data_type),
+        Expression::IsNull(expr) => format!("{} IS NULL", operand_sql(expr, ADDITIVE)),
+        Expression::IsNotNull(expr) => format!("{} IS NOT NULL", operand_sql(expr, ADDITIVE)),
+        Expression::Default => "DEFAULT".to_string(),
+        Expression::Parameter(n) => format!("${}", n),
+        Expression::InSubquery { expr, subquery, negated } => {
+            format!("{} {}IN ({})", operand_sql(expr, ADDITIVE), not(*negated), select_sql(subquery))
+        }
+        Expression::Exists(subquery) => format!("EXISTS ({})", select_sql(subquery)),
+    }
+}
+
+/// 子查询的 SQL 文本；子查询只能是 SELECT
+fn select_sql(statement: &Statement) -> String {
+    let Statement::Select { select_list, from_clause, where_clause, group_by, having, order_by, limit, offset } = statement else {
+        return "...".to_string();
+    };
+    let list = |exprs: &mut dyn Iterator<Item = &Expression>| exprs.map(expression_sql).collect::<Vec<_>>().join(", ");
+    let mut sql = match select_list {
+        SelectList::Wildcard => "SELECT *".to_string(),
+        SelectList::Expressions(exprs) => {
+            let items: Vec<String> = exprs.iter()
+                .map(|item| match &item.alias {
+                    Some(alias) => format!("{} AS {}", expression_sql(&item.expr), alias),
+                    None => expression_sql(&item.expr),
+                })
+                .collect();
+            format!("SELECT {}", items.join(", "))
+        }
+    };
+    if let Some(from_clause) = from_clause {
+        sql.push_str(&format!(" FROM {}", from_sql(from_clause)));
+    }
+    if let Some(where_clause) = where_clause {
+        sql.push_str(&format!(" WHERE {}", expression_sql(where_clause)));
+    }
+    if let Some(group_by) = group_by {
+        sql.push_str(&format!(" GROUP BY {}", list(&mut group_by.iter())));
+    }
+    if let Some(having) = having {
+        sql.push_str(&format!(" HAVING {}", expression_sql(having)));
+    }
+    if let Some(order_by) = order_by {
+        let keys: Vec<String> = order_by.iter()
+            .map(|key| format!("{}{}", expression_sql(&key.expr), if key.desc { " DESC" } else { "" }))
+            .collect();
+        sql.push_str(&format!(" ORDER BY {}", keys.join(", ")));
+    }
+    if let Some(limit) = limit {
+        sql.push_str(&format!(" LIMIT {}", limit));
+    }
+    if let Some(offset) = offset {
+        sql.push_str(&format!(" OFFSET {}", offset));
+    }
+    sql
+}
+
+fn from_sql(from_clause: &FromClause) -> String {
+    match from_clause {
+        FromClause::Table(name) => name.clone(),
+        FromClause::Join { left, join_type, right, condition } => {
+            let join = match join_type {
+                JoinType::Inner => "JOIN",
+                JoinType::Left => "LEFT JOIN",
+                JoinType::Right => "RIGHT JOIN",
+                JoinType::Full => "FULL OUTER JOIN",
+            };
+            let on = condition.as_ref().map(|condition| format!(" ON {}", expression_sql(condition))).unwrap_or_default();
+            format!("{} {} {}{}", from_sql(left), join, from_sql(right), on)
+        }
+    }
+}
+
+// 解析器中各层的优先级，由低到高
+const OR: u8 = 1;
+const AND: u8 = 2;
+const EQUALITY: u8 = 3;
+const COMPARISON: u8 = 4;
+const ADDITIVE: u8 = 5;
+const MULTIPLICATIVE: u8 = 6;
+const UNARY: u8 = 7;
+const JSON_ACCESS: u8 = 8;
+const PRIMARY: u8 = 9;
+
+fn precedence(expr: &Expression) -> u8 {
+    match expr {
+        Expression::BinaryOp { op, .. } => binary_precedence(op),
+        Expression::UnaryOp { .. } => UNARY,
+        Expression::In { .. }
+        | Expression::Between { .. }
+        | Expression::Like { .. }
+        | Expression::IsNull(_)
+        | Expression::IsNotNull(_)
+        | Expression::InSubquery { .. } => COMPARISON,
+        _ => PRIMARY,
+    }
+}
+
+fn binary_precedence(op: &BinaryOperator) -> u8 {
+    match op {
+        BinaryOperator::Or => OR,
+        BinaryOperator::And => AND,
+        BinaryOperator::Equal | BinaryOperator::NotEqual => EQUALITY,
+        BinaryOperator::LessThan | BinaryOperator::LessEqual | BinaryOperator::GreaterThan | BinaryOperator::GreaterEqual => COMPARISON,
+        BinaryOperator::Add | BinaryOperator::Subtract => ADDITIVE,
+        BinaryOperator::Multiply | BinaryOperator::Divide | BinaryOperator::Modulo => MULTIPLICATIVE,
+        BinaryOperator::JsonExtract | BinaryOperator::JsonExtractText => JSON_ACCESS,
+    }
+}
+
+fn binary_symbol(op: &BinaryOperator) -> &'static str {
+    match op {
+        BinaryOperator::Add => "+",
+        BinaryOperator::Subtract => "-",
+        BinaryOperator::Multiply => "*",
+        BinaryOperator::Divide => "/",
+        BinaryOperator::Modulo => "%",
+        BinaryOperator::Equal => "=",
+        BinaryOperator::NotEqual => "<>",
+        BinaryOperator::LessThan => "<",
+        BinaryOperator::LessEqual => "<=",
+        BinaryOperator::GreaterThan => ">",
+        BinaryOperator::GreaterEqual => ">=",
+        BinaryOperator::And => "AND",
+        BinaryOperator::Or => "OR",
+        BinaryOperator::JsonExtract => "->",
+        BinaryOperator::JsonExtractText => "->>",
+    }
+}
+
+/// 作为操作数的子表达式；优先级低于 minimum 时加括号
+fn operand_sql(expr: &Expression, minimum: u8) -> String {
+    if precedence(expr) < minimum {
+        format!("({})", expression_sql(expr))
+    } else {
+        expression_sql(expr)
+    }
+}
+
+fn not(negated: bool) -> &'static str {
+    if negated { "NOT " } else { "" }
+}
+
+/// 值的 SQL 字面量
+pub(crate) fn sql_literal(value: &Value) -> String {
+    match value {
+        Value::Null => "NULL".to_string(),
+        Value::Integer(i) => i.to_string(),
+        Value::BigInt(i) => i.to_string(),
+        Value::Float(f) => float_literal(*f as f64, f.to_string(), &DataType::Float),
+        Value::Double(d) => float_literal(*d, d.to_string(), &DataType::Double),
+        // 经字符串转换以保留全部数字和小数位数
+        Value::Decimal(d) => cast(&d.to_string(), &value.data_type()),
+        Value::Varchar(s) => quote(s),
+        Value::Blob(_) => value.to_string(),
+        Value::Uuid(u) => cast(&u.to_string(), &DataType::Uuid),
+        Value::Json(s) => cast(s, &DataType::Json),
+        Value::Boolean(b) => if *b { "TRUE" } else { "FALSE" }.to_string(),
+        #[cfg(feature = "chrono")]
+        Value::Date(d) => cast(&d.to_string(), &DataType::Date),
+        #[cfg(feature = "chrono")]
+        Value::Timestamp(ts) => cast(&ts.to_string(), &DataType::Timestamp),
+    }
+}
+
+/// 写入某列的值的 SQL 字面量：DECIMAL 值转换为列声明的精度和小数位数，其余同 [`sql_literal`]
+fn column_literal(value: &Value, data_type: &DataType) -> String {
+    match (value, data_type) {
+        (Value::Decimal(d), DataType::Decimal(..)) => cast(&d.to_string(), data_type),
+        _ => sql_literal(value),
+    }
+}
+
+/// 浮点数字面量
+///
+/// Rust 的格式化保证读回后得到同一个值；整数值补上小数部分，否则会被当作整数字面量。
+/// NaN 和无穷没有字面量，写成字符串的 CAST。
+fn float_literal(value: f64, text: String, data_type: &DataType) -> String {
+    if value.is_nan() {
+        cast("NaN", data_type)
+    } else if value.is_infinite() {
+        cast(if value > 0.0 { "Infinity" } else { "-Infinity" }, data_type)
+    } else if text.contains('.') {
+        text
+    } else {
+        format!("{}.0", text)
+    }
+}
+
+fn cast(text: &str, data_type: &DataType) -> String {
+    format!("CAST({} AS {})", quote(text), data_type)
+}
+
+fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ColumnDefinition;
+
+    #[test]
+    fn test_dump_statements() {
+        let schema = Schema {
+            columns: vec![
+                ColumnDefinition { name: "a".to_string(), data_type: DataType::Integer, nullable: false, default: None, default_function: None },
+                ColumnDefinition { name: "b".to_string(), data_type: DataType::Varchar(20), nullable: true, default: Some(Value::Varchar("x".to_string())), default_function: None },
+                ColumnDefinition { name: "c".to_string(), data_type: DataType::Double, nullable: true, default: None, default_function: None },
+            ],
+            primary_key: Some(vec![0, 1]),
+        };
+        assert_eq!(
+            create_table_sql("t", &schema),
+            "CREATE TABLE t (\n    a INTEGER NOT NULL,\n    b VARCHAR(20) DEFAULT 'x',\n    c DOUBLE,\n    PRIMARY KEY (a, b)\n);"
+        );
+
+        let row = Tuple::new(vec![Value::Integer(-1), Value::Varchar("it's".to_string()), Value::Double(0.1)]);
+        assert_eq!(insert_sql("t", &schema, &row), "INSERT INTO t (a, b, c) VALUES (-1, 'it''s', 0.1);");
+        assert_eq!(sql_literal(&Value::Null), "NULL");
+        assert_eq!(sql_literal(&Value::Boolean(true)), "TRUE");
+        assert_eq!(sql_literal(&Value::Double(3.0)), "3.0");
+        assert_eq!(sql_literal(&Value::Double(f64::NEG_INFINITY)), "CAST('-Infinity' AS DOUBLE)");
+        assert_eq!(sql_literal(&Value::Decimal("-0.50".parse().unwrap())), "CAST('-0.50' AS DECIMAL(38, 2))");
+        assert_eq!(column_literal(&Value::Decimal("1.50".parse().unwrap()), &DataType::Decimal(8, 2)), "CAST('1.50' AS DECIMAL(8, 2))");
+
+        let index = IndexInfo { name: "t_c_idx".to_string(), columns: vec!["c".to_string()], unique: true, include: Vec::new(), where_clause: None, build: None };
+        assert_eq!(create_index_sql("t", &index), "CREATE UNIQUE INDEX t_c_idx ON t (c);");
+        let index = IndexInfo { unique: false, include: vec!["a".to_string(), "b".to_string()], ..index };
+        assert_eq!(create_index_sql("t", &index), "CREATE INDEX t_c_idx ON t (c) INCLUDE (a, b);");
+        let index = IndexInfo { include: Vec::new(), where_clause: Some(where_clause("b = 'it''s' AND c > 0")), ..index };
+        assert_eq!(create_index_sql("t", &index), "CREATE INDEX t_c_idx ON t (c) WHERE b = 'it''s' AND c > 0;");
+    }
+
+    fn where_clause(condition: &str) -> Expression {
+        match crate::sql::parse_sql(&format!("SELECT * FROM t WHERE {}", condition)).unwrap() {
+            crate::sql::parser::Statement::Select { where_clause: Some(expr), .. } => expr,
+            other => panic!("Expected SELECT with WHERE, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_expression_sql() {
+        // 重新解析得到同一个表达式
+        for condition in [
+            "(a = 1 OR b = 2) AND NOT (c < 3)",
+            "a - (b - c) * 2 >= -d",
+            "name NOT LIKE 'x!%' ESCAPE '!' AND id NOT IN (1, 2) AND c BETWEEN 1 AND 2 + 3",
+            "a + 1 IS NOT NULL OR t.b IS NULL",
+            "CAST(a AS BIGINT) = ABS(b)",
+        ] {
+            let expr = where_clause(condition);
+            assert_eq!(expression_sql(&expr), condition);
+            assert_eq!(where_clause(&expression_sql(&expr)), expr);
+        }
+        assert_eq!(expression_sql(&where_clause("a != 'x'")), "a <> 'x'");
+    }
+}
@@ -1,15 +1,26 @@
 //! 查询执行器
+//!
+//! 算子按火山模型（Volcano）组织：每个算子实现 [`Executor`]，父算子调用子算子的 `next` 逐行拉取数据，
+//! 根算子返回 None 时查询结束。表达式求值、比较等与数据库状态相关的逻辑由构建算子树的一方以闭包传入。
 
-use crate::sql::parser::Expression;
-use crate::sql::planner::{JoinType, SortKey};
-use crate::types::{DataType, Schema, Tuple, Value, ColumnDefinition};
+use crate::engine::database::ExecutionError;
+use crate::engine::external_sort::{ExternalSorter, SortedRows};
+use crate::sql::planner::JoinType;
+use crate::storage::StatementTempSpace;
+use crate::types::{DataType, Schema, Tuple, Value};
+use std::borrow::Cow;
+use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::rc::Rc;
 use thiserror::Error;
 
 pub trait Executor {
     fn next(&mut self) -> Result<Option<Tuple>, ExecutorError>;
     fn schema(&self) -> &Schema;
     fn reset(&mut self) -> Result<(), ExecutorError>;
+
+    /// 父算子不再需要更多的行（如 LIMIT 已满）；应释放资源并关闭子算子，可重复调用
+    fn close(&mut self) {}
 }
 
 #[derive(Debug)]
@@ -31,161 +42,394 @@ pub enum ExecutorError {
     
     #[error("Join error: {message}")]
     JoinError { message: String },
+
+    /// 算子调用的数据库逻辑（表达式求值、溢出排序等）返回的错误
+    #[error(transparent)]
+    Execution(Box<ExecutionError>),
+}
+
+impl From<ExecutionError> for ExecutorError {
+    fn from(error: ExecutionError) -> Self {
+        ExecutorError::Execution(Box::new(error))
+    }
+}
+
+impl From<ExecutorError> for ExecutionError {
+    fn from(error: ExecutorError) -> Self {
+        match error {
+            ExecutorError::Execution(error) => *error,
+            error => ExecutionError::EvaluationError { message: error.to_string() },
+        }
+    }
+}
+
+/// 对一行求值的过滤条件
+pub type Predicate<'a> = Box<dyn Fn(&Tuple) -> Result<bool, ExecutorError> + 'a>;
+
+/// 按位置读取一行：返回第 i 行，越过末尾时为 None
+pub type RowFetch<'a> = Box<dyn Fn(usize) -> Result<Option<Tuple>, ExecutorError> + 'a>;
+
+/// 扫描的行
+pub enum ScanRows<'a> {
+    /// 已在内存中的行
+    Rows(Cow<'a, [Tuple]>),
+    /// 扫描到第 i 行时才读取并解码的行（如表工作堆文件中的记录），整张表不会先读进内存；len 为行数
+    Fetch { fetch: RowFetch<'a>, len: usize },
+}
+
+impl ScanRows<'_> {
+    pub fn len(&self) -> usize {
+        match self {
+            ScanRows::Rows(rows) => rows.len(),
+            ScanRows::Fetch { len, .. } => *len,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 第 i 行；内存中的行不复制
+    pub fn get(&self, i: usize) -> Result<Option<Cow<'_, Tuple>>, ExecutorError> {
+        match self {
+            ScanRows::Rows(rows) => Ok(rows.get(i).map(Cow::Borrowed)),
+            ScanRows::Fetch { fetch, len } if i < *len => Ok(fetch(i)?.map(Cow::Owned)),
+            ScanRows::Fetch { .. } => Ok(None),
+        }
+    }
+}
+
+impl<'a> From<Cow<'a, [Tuple]>> for ScanRows<'a> {
+    fn from(rows: Cow<'a, [Tuple]>) -> Self {
+        ScanRows::Rows(rows)
+    }
+}
+
+/// 顺序扫描执行器：逐行输出给定的行，可先按条件过滤，再只保留部分列
+pub struct ScanExecutor<'a> {
+    rows: ScanRows<'a>,
+    position: usize,
+    filter: Option<Predicate<'a>>,
+    /// 输出的列在输入行中的位置；None 输出整行
+    columns: Option<Vec<usize>>,
+    schema: Schema,
+}
+
+impl<'a> ScanExecutor<'a> {
+    /// schema 为输出行的模式
+    pub fn new(rows: impl Into<ScanRows<'a>>, schema: Schema) -> Self {
+        Self { rows: rows.into(), position: 0, filter: None, columns: None, schema }
+    }
+
+    /// 只输出满足条件的行；条件在裁剪列之前对整行求值
+    pub fn with_filter(mut self, filter: Predicate<'a>) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    pub fn with_columns(mut self, columns: Vec<usize>) -> Self {
+        self.columns = Some(columns);
+        self
+    }
+}
+
+impl Executor for ScanExecutor<'_> {
+    fn next(&mut self) -> Result<Option<Tuple>, ExecutorError> {
+        while let Some(row) = self.rows.get(self.position)? {
+            self.position += 1;
+            if let Some(filter) = &self.filter {
+                if !filter(&row)? {
+                    continue;
+                }
+            }
+            return Ok(Some(match &self.columns {
+                Some(columns) => Tuple { values: columns.iter().map(|&i| row.values[i].clone()).collect() },
+                None => row.into_owned(),
+            }));
+        }
+        Ok(None)
+    }
+
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    fn reset(&mut self) -> Result<(), ExecutorError> {
+        self.position = 0;
+        Ok(())
+    }
+
+    fn close(&mut self) {
+        self.position = self.rows.len();
+    }
 }
 
-/// 哈希连接执行器 - 从左输入构建哈希表，使用右输入进行探测
-pub struct HashJoinExecutor {
-    left: Box<dyn Executor>,
-    right: Box<dyn Executor>,
+/// 由输入行计算输出行
+pub type Projection<'a> = Box<dyn Fn(Tuple) -> Result<Tuple, ExecutorError> + 'a>;
+
+/// 投影执行器：对每行计算 SELECT 列表
+pub struct ProjectExecutor<'a> {
+    input: Box<dyn Executor + 'a>,
+    project: Projection<'a>,
+    schema: Schema,
+    /// 类型要看结果才能确定的输出列，取第一个非 NULL 值的类型
+    untyped: Vec<usize>,
+}
+
+impl<'a> ProjectExecutor<'a> {
+    pub fn new(input: Box<dyn Executor + 'a>, schema: Schema, project: Projection<'a>) -> Self {
+        Self { input, project, schema, untyped: Vec::new() }
+    }
+
+    /// columns 中各输出列的类型随输出的行确定；在此之前为 schema 中给出的类型
+    pub fn with_inferred_types(mut self, columns: Vec<usize>) -> Self {
+        self.untyped = columns;
+        self
+    }
+}
+
+impl Executor for ProjectExecutor<'_> {
+    fn next(&mut self) -> Result<Option<Tuple>, ExecutorError> {
+        let Some(row) = self.input.next()? else {
+            return Ok(None);
+        };
+        let row = (self.project)(row)?;
+        let columns = &mut self.schema.columns;
+        self.untyped.retain(|&i| match &row.values[i] {
+            Value::Null => true,
+            Value::Varchar(_) => {
+                columns[i].data_type = DataType::Varchar(255);
+                false
+            }
+            value => {
+                columns[i].data_type = value.data_type();
+                false
+            }
+        });
+        Ok(Some(row))
+    }
+
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    fn reset(&mut self) -> Result<(), ExecutorError> {
+        self.input.reset()
+    }
+
+    fn close(&mut self) {
+        self.input.close();
+    }
+}
+
+/// 过滤执行器：只输出满足条件的行
+pub struct FilterExecutor<'a> {
+    input: Box<dyn Executor + 'a>,
+    predicate: Predicate<'a>,
+}
+
+impl<'a> FilterExecutor<'a> {
+    pub fn new(input: Box<dyn Executor + 'a>, predicate: Predicate<'a>) -> Self {
+        Self { input, predicate }
+    }
+}
+
+impl Executor for FilterExecutor<'_> {
+    fn next(&mut self) -> Result<Option<Tuple>, ExecutorError> {
+        while let Some(row) = self.input.next()? {
+            if (self.predicate)(&row)? {
+                return Ok(Some(row));
+            }
+        }
+        Ok(None)
+    }
+
+    fn schema(&self) -> &Schema {
+        self.input.schema()
+    }
+
+    fn reset(&mut self) -> Result<(), ExecutorError> {
+        self.input.reset()
+    }
+
+    fn close(&mut self) {
+        self.input.close();
+    }
+}
+
+/// 连接输出的模式：左输入的列在前；半连接和反连接只输出左输入的列
+fn join_schema(left: &Schema, right: &Schema, join_type: &JoinType) -> Schema {
+    let mut columns = left.columns.clone();
+    if !matches!(join_type, JoinType::Semi | JoinType::Anti) {
+        columns.extend(right.columns.iter().cloned());
+    }
+    Schema { columns, primary_key: None }
+}
+
+/// 左右两行拼接成的行；一侧为 None 时以 NULL 补齐
+fn combined(left: Option<&Tuple>, left_width: usize, right: Option<&Tuple>, right_width: usize) -> Tuple {
+    let mut values = match left {
+        Some(left) => left.values.clone(),
+        None => vec![Value::Null; left_width],
+    };
+    match right {
+        Some(right) => values.extend(right.values.iter().cloned()),
+        None => values.extend(std::iter::repeat_n(Value::Null, right_width)),
+    }
+    Tuple { values }
+}
+
+/// 哈希连接执行器 - 由左输入按连接键建哈希表，逐行读取右输入探测
+///
+/// 键相同的一对行再求值连接条件（对左右两行拼接成的行），条件为真才算匹配；键含 NULL 的行不与任何行匹配，
+/// INTEGER 与 BIGINT 的键按数值散列。没有连接键时左输入的行都在同一个桶中。
+/// 左输入中没有匹配的行（左外连接、全外连接、反连接）和有匹配的行（半连接）在右输入读完后输出。
+pub struct HashJoinExecutor<'a> {
+    left: Box<dyn Executor + 'a>,
+    right: Box<dyn Executor + 'a>,
     join_type: JoinType,
-    condition: Option<Expression>,
-    hash_table: HashMap<String, Vec<Tuple>>,
-    right_tuples: Vec<Tuple>,
-    current_right_index: usize,
-    current_matches: Vec<Tuple>,
-    current_match_index: usize,
+    left_keys: Vec<usize>,
+    right_keys: Vec<usize>,
+    /// 连接条件，对左右两行拼接成的行求值
+    condition: Option<Predicate<'a>>,
+    left_rows: Vec<Tuple>,
+    /// 左输入各行是否有过匹配
+    left_matched: Vec<bool>,
+    hash_table: HashMap<Vec<Value>, Vec<usize>>,
     schema: Schema,
     built: bool,
+    right_done: bool,
+    pending: std::collections::VecDeque<Tuple>,
 }
 
-impl HashJoinExecutor {
+impl<'a> HashJoinExecutor<'a> {
+    /// left_keys 和 right_keys 是两侧连接键在各自输入中的列位置，按位置一一相等的行再求值 condition
     pub fn new(
-        left: Box<dyn Executor>,
-        right: Box<dyn Executor>,
+        left: Box<dyn Executor + 'a>,
+        right: Box<dyn Executor + 'a>,
         join_type: JoinType,
-        condition: Option<Expression>,
+        left_keys: Vec<usize>,
+        right_keys: Vec<usize>,
+        condition: Option<Predicate<'a>>,
     ) -> Result<Self, ExecutorError> {
-        // Combine schemas from left and right
-        let left_schema = left.schema().clone();
-        let right_schema = right.schema().clone();
-        
-        let mut combined_columns = left_schema.columns;
-        combined_columns.extend(right_schema.columns);
-        
-        let schema = Schema {
-            columns: combined_columns,
-            primary_key: None, // JOIN results don't have primary key
-        };
+        if left_keys.len() != right_keys.len() {
+            return Err(ExecutorError::JoinError {
+                message: format!("Hash join needs the same number of keys on both sides, got {} and {}", left_keys.len(), right_keys.len()),
+            });
+        }
+        if left_keys.iter().any(|&key| key >= left.schema().columns.len())
+            || right_keys.iter().any(|&key| key >= right.schema().columns.len())
+        {
+            return Err(ExecutorError::JoinError { message: "Hash join key position out of range".to_string() });
+        }
 
+        let schema = join_schema(left.schema(), right.schema(), &join_type);
         Ok(Self {
             left,
             right,
             join_type,
+            left_keys,
+            right_keys,
             condition,
+            left_rows: Vec::new(),
+            left_matched: Vec::new(),
             hash_table: HashMap::new(),
-            right_tuples: Vec::new(),
-            current_right_index: 0,
-            current_matches: Vec::new(),
-            current_match_index: 0,
             schema,
             built: false,
+            right_done: false,
+            pending: std::collections::VecDeque::new(),
         })
     }
 
-    fn build_hash_table(&mut self) -> Result<(), ExecutorError> {
-        if self.built {
-            return Ok(());
-        }
+    /// 行的连接键；含 NULL 时为 None。数值统一为 BIGINT（整数值）或 DOUBLE，不同数值类型的相等值落在同一个桶中
+    fn key(tuple: &Tuple, positions: &[usize]) -> Option<Vec<Value>> {
+        let number = |n: f64| match n.fract() == 0.0 && n.abs() < i64::MAX as f64 {
+            true => Value::BigInt(n as i64),
+            false => Value::Double(n),
+        };
+        positions.iter()
+            .map(|&i| match &tuple.values[i] {
+                Value::Null => None,
+                Value::Integer(n) => Some(Value::BigInt(i64::from(*n))),
+                Value::Float(n) => Some(number(f64::from(*n))),
+                Value::Double(n) => Some(number(*n)),
+                value => Some(value.clone()),
+            })
+            .collect()
+    }
 
-        // Build hash table from left input
+    fn build_hash_table(&mut self) -> Result<(), ExecutorError> {
         while let Some(tuple) = self.left.next()? {
-            // For simplicity, use first column as hash key
-            // In a real implementation, this would be based on join condition
-            let key = if !tuple.values.is_empty() {
-                format!("{:?}", tuple.values[0])
-            } else {
-                "NULL".to_string()
-            };
-            
-            self.hash_table.entry(key).or_insert_with(Vec::new).push(tuple);
-        }
-
-        // Collect all right tuples
-        while let Some(tuple) = self.right.next()? {
-            self.right_tuples.push(tuple);
+            if let Some(key) = Self::key(&tuple, &self.left_keys) {
+                self.hash_table.entry(key).or_default().push(self.left_rows.len());
+            }
+            self.left_rows.push(tuple);
         }
-
+        self.left_matched = vec![false; self.left_rows.len()];
         self.built = true;
         Ok(())
     }
 
-    fn evaluate_join_condition(&self, left_tuple: &Tuple, right_tuple: &Tuple) -> Result<bool, ExecutorError> {
-        match &self.condition {
-            Some(expr) => {
-                // For simplicity, we'll implement basic equality check
-                // In a real implementation, this would be a full expression evaluator
-                self.evaluate_expression(expr, left_tuple, right_tuple)
+    /// 用一行右输入探测哈希表，结果放入待输出队列
+    fn probe(&mut self, right: Tuple) -> Result<(), ExecutorError> {
+        let (left_width, right_width) = (self.left.schema().columns.len(), right.values.len());
+        let candidates = Self::key(&right, &self.right_keys)
+            .and_then(|key| self.hash_table.get(&key))
+            .cloned()
+            .unwrap_or_default();
+        let mut right_matched = false;
+        for index in candidates {
+            let row = combined(Some(&self.left_rows[index]), left_width, Some(&right), right_width);
+            if let Some(condition) = &self.condition {
+                if !condition(&row)? {
+                    continue;
+                }
+            }
+            right_matched = true;
+            self.left_matched[index] = true;
+            if !matches!(self.join_type, JoinType::Semi | JoinType::Anti) {
+                self.pending.push_back(row);
             }
-            None => Ok(true), // Cross join
         }
+        if !right_matched && matches!(self.join_type, JoinType::Right | JoinType::Full) {
+            self.pending.push_back(combined(None, left_width, Some(&right), right_width));
+        }
+        Ok(())
     }
 
-    fn evaluate_expression(&self, _expr: &Expression, _left: &Tuple, _right: &Tuple) -> Result<bool, ExecutorError> {
-        // Simplified implementation - assume condition is always true for now
-        // In a real implementation, this would evaluate the full expression
-        Ok(true)
-    }
-
-    fn combine_tuples(&self, left: &Tuple, right: &Tuple) -> Tuple {
-        let mut combined_values = left.values.clone();
-        combined_values.extend(right.values.clone());
-        
-        Tuple {
-            values: combined_values,
+    /// 右输入读完：按连接类型输出左输入中有匹配或没有匹配的行
+    fn finish_left(&mut self) {
+        let right_width = self.right.schema().columns.len();
+        let rows = std::mem::take(&mut self.left_rows);
+        for (row, &matched) in rows.iter().zip(&self.left_matched) {
+            match (&self.join_type, matched) {
+                (JoinType::Semi, true) | (JoinType::Anti, false) => self.pending.push_back(row.clone()),
+                (JoinType::Left | JoinType::Full, false) => {
+                    self.pending.push_back(combined(Some(row), row.values.len(), None, right_width));
+                }
+                _ => {}
+            }
         }
     }
 }
 
-impl Executor for HashJoinExecutor {
+impl Executor for HashJoinExecutor<'_> {
     fn next(&mut self) -> Result<Option<Tuple>, ExecutorError> {
-        self.build_hash_table()?;
-
+        if !self.built {
+            self.build_hash_table()?;
+        }
         loop {
-            // If we have matches for the current right tuple, return them
-            if self.current_match_index < self.current_matches.len() {
-                let right_tuple = &self.right_tuples[self.current_right_index];
-                let left_tuple = &self.current_matches[self.current_match_index];
-                self.current_match_index += 1;
-                
-                if self.evaluate_join_condition(left_tuple, right_tuple)? {
-                    return Ok(Some(self.combine_tuples(left_tuple, right_tuple)));
-                }
+            if let Some(tuple) = self.pending.pop_front() {
+                return Ok(Some(tuple));
             }
-
-            // Move to next right tuple
-            if self.current_right_index >= self.right_tuples.len() {
+            if self.right_done {
                 return Ok(None);
             }
-
-            let right_tuple = &self.right_tuples[self.current_right_index];
-            let key = if !right_tuple.values.is_empty() {
-                format!("{:?}", right_tuple.values[0])
-            } else {
-                "NULL".to_string()
-            };
-
-            // Find matches in hash table
-            self.current_matches = self.hash_table.get(&key).cloned().unwrap_or_default();
-            self.current_match_index = 0;
-            self.current_right_index += 1;
-
-            // Handle different join types
-            match self.join_type {
-                JoinType::Inner => {
-                    if self.current_matches.is_empty() {
-                        continue; // No matches, skip this right tuple
-                    }
-                }
-                JoinType::Left => {
-                    if self.current_matches.is_empty() {
-                        // Left join: return right tuple with NULL values for left
-                        let null_values = vec![Value::Null; self.left.schema().columns.len()];
-                        let null_tuple = Tuple { values: null_values };
-                        return Ok(Some(self.combine_tuples(&null_tuple, right_tuple)));
-                    }
-                }
-                _ => {
-                    return Err(ExecutorError::NotImplemented);
+            match self.right.next()? {
+                Some(row) => self.probe(row)?,
+                None => {
+                    self.right_done = true;
+                    self.finish_left();
                 }
             }
         }
@@ -198,128 +442,377 @@ impl Executor for HashJoinExecutor {
     fn reset(&mut self) -> Result<(), ExecutorError> {
         self.left.reset()?;
         self.right.reset()?;
+        self.left_rows.clear();
+        self.left_matched.clear();
         self.hash_table.clear();
-        self.right_tuples.clear();
-        self.current_right_index = 0;
-        self.current_matches.clear();
-        self.current_match_index = 0;
         self.built = false;
+        self.right_done = false;
+        self.pending.clear();
         Ok(())
     }
+
+    fn close(&mut self) {
+        self.left.close();
+        self.right.close();
+        self.right_done = true;
+        self.pending.clear();
+    }
 }
 
-/// 排序执行器
-pub struct SortExecutor {
-    input: Box<dyn Executor>,
-    sort_keys: Vec<SortKey>,
-    sorted_tuples: Vec<Tuple>,
-    current_index: usize,
+/// 嵌套循环连接执行器 - 读入右输入的全部行，对左输入的每一行逐一求值连接条件
+///
+/// 没有连接条件时为笛卡尔积。右输入中没有匹配的行（右外连接、全外连接）在左输入读完后输出。
+pub struct NestedLoopJoinExecutor<'a> {
+    left: Box<dyn Executor + 'a>,
+    right: Box<dyn Executor + 'a>,
+    join_type: JoinType,
+    /// 连接条件，对左右两行拼接成的行求值
+    condition: Option<Predicate<'a>>,
+    right_rows: Option<Vec<Tuple>>,
+    /// 右输入各行是否有过匹配
+    right_matched: Vec<bool>,
     schema: Schema,
-    sorted: bool,
+    left_done: bool,
+    pending: std::collections::VecDeque<Tuple>,
 }
 
-impl SortExecutor {
-    pub fn new(input: Box<dyn Executor>, sort_keys: Vec<SortKey>) -> Self {
-        let schema = input.schema().clone();
-        
+impl<'a> NestedLoopJoinExecutor<'a> {
+    pub fn new(
+        left: Box<dyn Executor + 'a>,
+        right: Box<dyn Executor + 'a>,
+        join_type: JoinType,
+        condition: Option<Predicate<'a>>,
+    ) -> Self {
+        let schema = join_schema(left.schema(), right.schema(), &join_type);
         Self {
-            input,
-            sort_keys,
-            sorted_tuples: Vec::new(),
-            current_index: 0,
+            left,
+            right,
+            join_type,
+            condition,
+            right_rows: None,
+            right_matched: Vec::new(),
             schema,
-            sorted: false,
+            left_done: false,
+            pending: std::collections::VecDeque::new(),
         }
     }
 
-    fn sort_tuples(&mut self) -> Result<(), ExecutorError> {
-        if self.sorted {
-            return Ok(());
+    /// 连接一行左输入，结果放入待输出队列
+    fn join_left_row(&mut self, left: Tuple) -> Result<(), ExecutorError> {
+        let left_width = left.values.len();
+        let right_width = self.right.schema().columns.len();
+        let right_rows = self.right_rows.as_deref().unwrap_or_default();
+        let mut matched = false;
+        for (index, right) in right_rows.iter().enumerate() {
+            let row = combined(Some(&left), left_width, Some(right), right_width);
+            if let Some(condition) = &self.condition {
+                if !condition(&row)? {
+                    continue;
+                }
+            }
+            matched = true;
+            self.right_matched[index] = true;
+            match self.join_type {
+                JoinType::Semi | JoinType::Anti => break,
+                _ => self.pending.push_back(row),
+            }
         }
-
-        // Collect all tuples
-        while let Some(tuple) = self.input.next()? {
-            self.sorted_tuples.push(tuple);
+        match (&self.join_type, matched) {
+            (JoinType::Semi, true) | (JoinType::Anti, false) => self.pending.push_back(left),
+            (JoinType::Left | JoinType::Full, false) => {
+                self.pending.push_back(combined(Some(&left), left_width, None, right_width));
+            }
+            _ => {}
         }
+        Ok(())
+    }
+}
 
-        // Sort based on sort keys - clone to avoid borrowing issues
-        let sort_keys = self.sort_keys.clone();
-        self.sorted_tuples.sort_by(|a, b| {
-            for sort_key in &sort_keys {
-                // Simplified comparison - just compare first values for now
-                let a_val = a.values.get(0).unwrap_or(&Value::Null);
-                let b_val = b.values.get(0).unwrap_or(&Value::Null);
-                
-                if let Some(cmp) = a_val.partial_cmp(b_val) {
-                    match cmp {
-                        std::cmp::Ordering::Equal => continue,
-                        other => {
-                            return if sort_key.descending {
-                                other.reverse()
-                            } else {
-                                other
-                            };
+impl Executor for NestedLoopJoinExecutor<'_> {
+    fn next(&mut self) -> Result<Option<Tuple>, ExecutorError> {
+        if self.right_rows.is_none() && !self.left_done {
+            let mut rows = Vec::new();
+            while let Some(row) = self.right.next()? {
+                rows.push(row);
+            }
+            self.right_matched = vec![false; rows.len()];
+            self.right_rows = Some(rows);
+        }
+        loop {
+            if let Some(tuple) = self.pending.pop_front() {
+                return Ok(Some(tuple));
+            }
+            if self.left_done {
+                return Ok(None);
+            }
+            match self.left.next()? {
+                Some(row) => self.join_left_row(row)?,
+                None => {
+                    self.left_done = true;
+                    if matches!(self.join_type, JoinType::Right | JoinType::Full) {
+                        let left_width = self.left.schema().columns.len();
+                        let right_rows = self.right_rows.take().unwrap_or_default();
+                        for (row, _) in right_rows.iter().zip(&self.right_matched).filter(|(_, matched)| !**matched) {
+                            self.pending.push_back(combined(None, left_width, Some(row), row.values.len()));
                         }
                     }
                 }
             }
-            std::cmp::Ordering::Equal
-        });
+        }
+    }
 
-        self.sorted = true;
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    fn reset(&mut self) -> Result<(), ExecutorError> {
+        self.left.reset()?;
+        self.right.reset()?;
+        self.right_rows = None;
+        self.right_matched.clear();
+        self.left_done = false;
+        self.pending.clear();
         Ok(())
     }
 
-    #[allow(dead_code)]
-    fn compare_tuples_by_expression(&self, a: &Tuple, b: &Tuple, expr: &Expression) -> std::cmp::Ordering {
-        // Simplified comparison - in a real implementation, this would evaluate expressions
-        match expr {
-            Expression::Column(col_name) => {
-                // Find column index and compare values
-                if let Some(col_index) = self.find_column_index(col_name) {
-                    if col_index < a.values.len() && col_index < b.values.len() {
-                        return self.compare_values(&a.values[col_index], &b.values[col_index]);
+    fn close(&mut self) {
+        self.left.close();
+        self.right.close();
+        self.left_done = true;
+        self.pending.clear();
+    }
+}
+
+/// 归并连接执行器 - 两个输入都已按连接键升序排列，同步向前推进
+///
+/// 右输入中键相同的一组行缓存在内存中，与左输入中同键的各行逐一配对；其余行只读一遍。
+/// 键含 NULL 的行不与任何行匹配。发现输入没有按键排序时返回 JoinError。
+pub struct MergeJoinExecutor<'a> {
+    left: Box<dyn Executor + 'a>,
+    right: Box<dyn Executor + 'a>,
+    join_type: JoinType,
+    left_keys: Vec<usize>,
+    right_keys: Vec<usize>,
+    schema: Schema,
+    /// 已从右输入读出、尚未归入分组的一行
+    right_next: Option<Tuple>,
+    right_done: bool,
+    /// 当前分组的键及右输入中该键的行，matched 记录每行是否有过匹配（右外连接、全外连接用）
+    group_key: Option<Vec<Value>>,
+    group: Vec<Tuple>,
+    matched: Vec<bool>,
+    /// 上一行左输入和右输入的键，用于检查排序
+    last_left_key: Option<Vec<Value>>,
+    last_right_key: Option<Vec<Value>>,
+    left_done: bool,
+    pending: std::collections::VecDeque<Tuple>,
+}
+
+impl<'a> MergeJoinExecutor<'a> {
+    /// left_keys 和 right_keys 是两侧连接键在各自输入中的列位置，按位置一一相等即为匹配
+    pub fn new(
+        left: Box<dyn Executor + 'a>,
+        right: Box<dyn Executor + 'a>,
+        join_type: JoinType,
+        left_keys: Vec<usize>,
+        right_keys: Vec<usize>,
+    ) -> Result<Self, ExecutorError> {
+        let (left_width, right_width) = (left.schema().columns.len(), right.schema().columns.len());
+        if left_keys.is_empty() || left_keys.len() != right_keys.len() {
+            return Err(ExecutorError::JoinError {
+                message: format!("Merge join needs the same number of keys on both sides, got {} and {}", left_keys.len(), right_keys.len()),
+            });
+        }
+        if left_keys.iter().any(|&key| key >= left_width) || right_keys.iter().any(|&key| key >= right_width) {
+            return Err(ExecutorError::JoinError { message: "Merge join key position out of range".to_string() });
+        }
+
+        let schema = join_schema(left.schema(), right.schema(), &join_type);
+        Ok(Self {
+            left,
+            right,
+            join_type,
+            left_keys,
+            right_keys,
+            schema,
+            right_next: None,
+            right_done: false,
+            group_key: None,
+            group: Vec::new(),
+            matched: Vec::new(),
+            last_left_key: None,
+            last_right_key: None,
+            left_done: false,
+            pending: std::collections::VecDeque::new(),
+        })
+    }
+
+    fn key(tuple: &Tuple, positions: &[usize]) -> Vec<Value> {
+        positions.iter().map(|&i| tuple.values[i].clone()).collect()
+    }
+
+    /// 依次比较两组键；有不可比较的值时报错
+    fn compare_keys(a: &[Value], b: &[Value]) -> Result<std::cmp::Ordering, ExecutorError> {
+        for (x, y) in a.iter().zip(b) {
+            match x.partial_cmp(y) {
+                Some(std::cmp::Ordering::Equal) => continue,
+                Some(ordering) => return Ok(ordering),
+                None => {
+                    return Err(ExecutorError::JoinError { message: format!("Cannot compare join keys {:?} and {:?}", x, y) })
+                }
+            }
+        }
+        Ok(std::cmp::Ordering::Equal)
+    }
+
+    /// 记录一侧新读到的非 NULL 键，比上一个键小说明输入没有排序
+    fn check_order(last: &mut Option<Vec<Value>>, key: &[Value], side: &str) -> Result<(), ExecutorError> {
+        if let Some(previous) = last {
+            if Self::compare_keys(key, previous)? == std::cmp::Ordering::Less {
+                return Err(ExecutorError::JoinError { message: format!("Merge join {} input is not sorted on the join key", side) });
+            }
+        }
+        *last = Some(key.to_vec());
+        Ok(())
+    }
+
+    fn padded(&self, left: Option<&Tuple>, right: Option<&Tuple>) -> Tuple {
+        let mut values = match left {
+            Some(left) => left.values.clone(),
+            None => vec![Value::Null; self.left.schema().columns.len()],
+        };
+        match right {
+            Some(right) => values.extend(right.values.iter().cloned()),
+            None => values.extend(std::iter::repeat_n(Value::Null, self.right.schema().columns.len())),
+        }
+        Tuple { values }
+    }
+
+    fn keeps_unmatched_right(&self) -> bool {
+        matches!(self.join_type, JoinType::Right | JoinType::Full)
+    }
+
+    /// 结束当前分组：右外连接、全外连接输出分组中没有匹配过的右输入行
+    fn close_group(&mut self) {
+        let group = std::mem::take(&mut self.group);
+        let matched = std::mem::take(&mut self.matched);
+        if self.keeps_unmatched_right() {
+            for (row, _) in group.iter().zip(matched).filter(|(_, matched)| !matched) {
+                let padded = self.padded(None, Some(row));
+                self.pending.push_back(padded);
+            }
+        }
+        self.group_key = None;
+    }
+
+    /// 下一行右输入（先取已读出的一行）
+    fn next_right(&mut self) -> Result<Option<Tuple>, ExecutorError> {
+        if let Some(row) = self.right_next.take() {
+            return Ok(Some(row));
+        }
+        if self.right_done {
+            return Ok(None);
+        }
+        let row = self.right.next()?;
+        self.right_done = row.is_none();
+        Ok(row)
+    }
+
+    /// 推进右输入直到键不小于 key；键等于 key 的行组成新的分组，跳过的行在外连接中单独输出
+    fn advance_right(&mut self, key: &[Value]) -> Result<(), ExecutorError> {
+        while let Some(row) = self.next_right()? {
+            let right_key = Self::key(&row, &self.right_keys);
+            if right_key.iter().any(Value::is_null) {
+                if self.keeps_unmatched_right() {
+                    let padded = self.padded(None, Some(&row));
+                    self.pending.push_back(padded);
+                }
+                continue;
+            }
+            Self::check_order(&mut self.last_right_key, &right_key, "right")?;
+            match Self::compare_keys(&right_key, key)? {
+                std::cmp::Ordering::Less => {
+                    if self.keeps_unmatched_right() {
+                        let padded = self.padded(None, Some(&row));
+                        self.pending.push_back(padded);
                     }
                 }
-                std::cmp::Ordering::Equal
+                std::cmp::Ordering::Equal => {
+                    self.group_key = Some(right_key);
+                    self.group.push(row);
+                    self.matched.push(false);
+                }
+                std::cmp::Ordering::Greater => {
+                    self.right_next = Some(row);
+                    break;
+                }
             }
-            _ => std::cmp::Ordering::Equal, // TODO: Handle other expression types
         }
+        Ok(())
     }
 
-    #[allow(dead_code)]
-    fn find_column_index(&self, col_name: &str) -> Option<usize> {
-        self.schema.columns.iter().position(|c| c.name == col_name)
+    /// 连接一行左输入，结果放入待输出队列
+    fn join_left_row(&mut self, row: Tuple) -> Result<(), ExecutorError> {
+        let key = Self::key(&row, &self.left_keys);
+        let has_null = key.iter().any(Value::is_null);
+        if !has_null {
+            Self::check_order(&mut self.last_left_key, &key, "left")?;
+            if self.group_key.as_deref() != Some(key.as_slice()) {
+                self.close_group();
+                self.advance_right(&key)?;
+            }
+        }
+
+        let matches = !has_null && self.group_key.as_deref() == Some(key.as_slice()) && !self.group.is_empty();
+        match (&self.join_type, matches) {
+            (JoinType::Semi, true) | (JoinType::Anti, false) => self.pending.push_back(row),
+            (JoinType::Semi, false) | (JoinType::Anti, true) => {}
+            (_, true) => {
+                for (right, matched) in self.group.iter().zip(self.matched.iter_mut()) {
+                    *matched = true;
+                    let mut values = row.values.clone();
+                    values.extend(right.values.iter().cloned());
+                    self.pending.push_back(Tuple { values });
+                }
+            }
+            (JoinType::Left | JoinType::Full, false) => {
+                let padded = self.padded(Some(&row), None);
+                self.pending.push_back(padded);
+            }
+            (JoinType::Inner | JoinType::Right, false) => {}
+        }
+        Ok(())
     }
 
-    #[allow(dead_code)]
-    fn compare_values(&self, a: &Value, b: &Value) -> std::cmp::Ordering {
-        use std::cmp::Ordering;
-        match (a, b) {
-            (Value::Integer(a), Value::Integer(b)) => a.cmp(b),
-            (Value::BigInt(a), Value::BigInt(b)) => a.cmp(b),
-            (Value::Float(a), Value::Float(b)) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
-            (Value::Double(a), Value::Double(b)) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
-            (Value::Varchar(a), Value::Varchar(b)) => a.cmp(b),
-            (Value::Boolean(a), Value::Boolean(b)) => a.cmp(b),
-            (Value::Null, Value::Null) => Ordering::Equal,
-            (Value::Null, _) => Ordering::Less,
-            (_, Value::Null) => Ordering::Greater,
-            _ => Ordering::Equal, // Type coercion would be handled here
+    /// 左输入读完：外连接输出右输入中剩下的行
+    fn finish_right(&mut self) -> Result<(), ExecutorError> {
+        self.close_group();
+        if self.keeps_unmatched_right() {
+            while let Some(row) = self.next_right()? {
+                let padded = self.padded(None, Some(&row));
+                self.pending.push_back(padded);
+            }
         }
+        Ok(())
     }
 }
 
-impl Executor for SortExecutor {
+impl Executor for MergeJoinExecutor<'_> {
     fn next(&mut self) -> Result<Option<Tuple>, ExecutorError> {
-        self.sort_tuples()?;
-
-        if self.current_index < self.sorted_tuples.len() {
-            let tuple = self.sorted_tuples[self.current_index].clone();
-            self.current_index += 1;
-            Ok(Some(tuple))
-        } else {
-            Ok(None)
+        loop {
+            if let Some(tuple) = self.pending.pop_front() {
+                return Ok(Some(tuple));
+            }
+            if self.left_done {
+                return Ok(None);
+            }
+            match self.left.next()? {
+                Some(row) => self.join_left_row(row)?,
+                None => {
+                    self.left_done = true;
+                    self.finish_right()?;
+                }
+            }
         }
     }
 
@@ -327,41 +820,207 @@ impl Executor for SortExecutor {
         &self.schema
     }
 
+    fn reset(&mut self) -> Result<(), ExecutorError> {
+        self.left.reset()?;
+        self.right.reset()?;
+        self.right_next = None;
+        self.right_done = false;
+        self.group_key = None;
+        self.group.clear();
+        self.matched.clear();
+        self.last_left_key = None;
+        self.last_right_key = None;
+        self.left_done = false;
+        self.pending.clear();
+        Ok(())
+    }
+
+    fn close(&mut self) {
+        self.left.close();
+        self.right.close();
+        self.left_done = true;
+        self.pending.clear();
+    }
+}
+
+/// 计算一行的排序键，第二个参数为输入的模式
+pub type SortKeys<'a> = Box<dyn Fn(&Tuple, &Schema) -> Result<Vec<Value>, ExecutorError> + 'a>;
+
+/// 比较同一个排序键的两个值
+type ValueOrder<'a> = Rc<dyn Fn(&Value, &Value) -> Ordering + 'a>;
+
+/// 比较排序后的两行：行的前面是各自的排序键
+type RowOrder<'a> = Box<dyn Fn(&Tuple, &Tuple) -> Ordering + 'a>;
+
+/// 一个排序键的方向
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SortOrder {
+    pub descending: bool,
+    /// 显式的 NULLS FIRST / LAST，不受 DESC 影响；None 时 NULL 的位置由比较函数决定
+    pub nulls_first: Option<bool>,
+}
+
+/// 排序执行器：读完输入后按排序键逐行输出
+///
+/// 每行的排序键只求值一次。配置了临时空间时，超出工作内存的行分批写入临时文件，最后归并。
+pub struct SortExecutor<'a> {
+    input: Box<dyn Executor + 'a>,
+    orders: Rc<[SortOrder]>,
+    keys: SortKeys<'a>,
+    compare: ValueOrder<'a>,
+    tiebreak: bool,
+    work_mem_bytes: usize,
+    space: Option<StatementTempSpace>,
+    sorted: Option<SortedRows<RowOrder<'a>>>,
+    done: bool,
+}
+
+impl<'a> SortExecutor<'a> {
+    /// keys 为每行返回与 orders 等长的排序键，compare 比较同一个键的两个值
+    pub fn new(
+        input: Box<dyn Executor + 'a>,
+        orders: Vec<SortOrder>,
+        keys: SortKeys<'a>,
+        compare: impl Fn(&Value, &Value) -> Ordering + 'a,
+    ) -> Self {
+        Self {
+            input,
+            orders: orders.into(),
+            keys,
+            compare: Rc::new(compare),
+            tiebreak: false,
+            work_mem_bytes: usize::MAX,
+            space: None,
+            sorted: None,
+            done: false,
+        }
+    }
+
+    /// 排序键相同的行再依次比较其余各列，使输出顺序确定
+    pub fn with_tiebreak(mut self, tiebreak: bool) -> Self {
+        self.tiebreak = tiebreak;
+        self
+    }
+
+    /// 估计大小超过 work_mem_bytes 时把行写入 space 中的临时文件；space 为 None 时只在内存中排序
+    pub fn with_spill(mut self, work_mem_bytes: usize, space: Option<StatementTempSpace>) -> Self {
+        self.work_mem_bytes = work_mem_bytes;
+        self.space = space;
+        self
+    }
+
+    fn row_order(&self) -> RowOrder<'a> {
+        let (orders, compare, tiebreak) = (Rc::clone(&self.orders), Rc::clone(&self.compare), self.tiebreak);
+        Box::new(move |a: &Tuple, b: &Tuple| {
+            for (i, order) in orders.iter().enumerate() {
+                let (a_value, b_value) = (&a.values[i], &b.values[i]);
+                let cmp = match (order.nulls_first, a_value.is_null(), b_value.is_null()) {
+                    (Some(_), true, true) => Ordering::Equal,
+                    (Some(first), true, false) => if first { Ordering::Less } else { Ordering::Greater },
+                    (Some(first), false, true) => if first { Ordering::Greater } else { Ordering::Less },
+                    _ => {
+                        let cmp = compare(a_value, b_value);
+                        if order.descending { cmp.reverse() } else { cmp }
+                    }
+                };
+                if cmp.is_ne() {
+                    return cmp;
+                }
+            }
+            if !tiebreak {
+                return Ordering::Equal;
+            }
+            a.values[orders.len()..].iter()
+                .zip(&b.values[orders.len()..])
+                .map(|(a_value, b_value)| compare(a_value, b_value))
+                .find(|cmp| cmp.is_ne())
+                .unwrap_or(Ordering::Equal)
+        })
+    }
+
+    fn sort(&mut self) -> Result<(), ExecutorError> {
+        // 排序键放在行的前面一起排序，输出时再去掉
+        let mut sorter = ExternalSorter::new(self.work_mem_bytes, self.space.clone(), self.row_order());
+        while let Some(row) = self.input.next()? {
+            let mut values = (self.keys)(&row, self.input.schema())?;
+            if values.len() != self.orders.len() {
+                return Err(ExecutorError::EvaluationError {
+                    message: format!("Expected {} sort key(s), got {}", self.orders.len(), values.len()),
+                });
+            }
+            values.extend(row.values);
+            sorter.push(Tuple::new(values))?;
+        }
+        if sorter.spilled_runs() > 0 {
+            log::debug!("ORDER BY spilled {} sorted run(s) to temp files", sorter.spilled_runs());
+        }
+        self.sorted = Some(sorter.finish()?);
+        Ok(())
+    }
+}
+
+impl Executor for SortExecutor<'_> {
+    fn next(&mut self) -> Result<Option<Tuple>, ExecutorError> {
+        if self.done {
+            return Ok(None);
+        }
+        if self.sorted.is_none() {
+            self.sort()?;
+        }
+        match self.sorted.as_mut().and_then(Iterator::next) {
+            Some(row) => {
+                let mut row = row?;
+                row.values.drain(..self.orders.len());
+                Ok(Some(row))
+            }
+            None => {
+                // 输出完毕，及时删除溢出的顺串
+                self.close();
+                Ok(None)
+            }
+        }
+    }
+
+    fn schema(&self) -> &Schema {
+        self.input.schema()
+    }
+
     fn reset(&mut self) -> Result<(), ExecutorError> {
         self.input.reset()?;
-        self.sorted_tuples.clear();
-        self.current_index = 0;
-        self.sorted = false;
+        self.sorted = None;
+        self.done = false;
         Ok(())
     }
+
+    fn close(&mut self) {
+        self.input.close();
+        self.sorted = None;
+        self.done = true;
+    }
 }
 
 /// 限制执行器
-pub struct LimitExecutor {
-    input: Box<dyn Executor>,
+pub struct LimitExecutor<'a> {
+    input: Box<dyn Executor + 'a>,
     limit: u64,
     offset: u64,
     current_count: u64,
     skipped_count: u64,
-    schema: Schema,
 }
 
-impl LimitExecutor {
-    pub fn new(input: Box<dyn Executor>, limit: u64, offset: u64) -> Self {
-        let schema = input.schema().clone();
-        
+impl<'a> LimitExecutor<'a> {
+    pub fn new(input: Box<dyn Executor + 'a>, limit: u64, offset: u64) -> Self {
         Self {
             input,
             limit,
             offset,
             current_count: 0,
             skipped_count: 0,
-            schema,
         }
     }
 }
 
-impl Executor for LimitExecutor {
+impl Executor for LimitExecutor<'_> {
     fn next(&mut self) -> Result<Option<Tuple>, ExecutorError> {
         // Skip tuples for OFFSET
         while self.skipped_count < self.offset {
@@ -372,21 +1031,25 @@ impl Executor for LimitExecutor {
             }
         }
 
-        // Return tuples up to LIMIT
-        if self.current_count < self.limit {
-            if let Some(tuple) = self.input.next()? {
+        // Return tuples up to LIMIT; once it is reached the input is closed without being drained
+        if self.current_count >= self.limit {
+            self.input.close();
+            return Ok(None);
+        }
+        match self.input.next()? {
+            Some(tuple) => {
                 self.current_count += 1;
+                if self.current_count == self.limit {
+                    self.input.close();
+                }
                 Ok(Some(tuple))
-            } else {
-                Ok(None)
             }
-        } else {
-            Ok(None)
+            None => Ok(None),
         }
     }
 
     fn schema(&self) -> &Schema {
-        &self.schema
+        self.input.schema()
     }
 
     fn reset(&mut self) -> Result<(), ExecutorError> {
@@ -395,6 +1058,10 @@ impl Executor for LimitExecutor {
         self.skipped_count = 0;
         Ok(())
     }
+
+    fn close(&mut self) {
+        self.input.close();
+    }
 }
 
 /// GROUP BY 聚合函数类型
@@ -405,6 +1072,8 @@ pub enum AggregateFunction {
     Avg(String),   // column name
     Min(String),   // column name
     Max(String),   // column name
+    /// STRING_AGG / GROUP_CONCAT：按输入顺序拼接非 NULL 值
+    StringAgg { column: String, separator: String },
 }
 
 /// 聚合函数累加器
@@ -414,6 +1083,8 @@ pub struct AggregateAccumulator {
     pub sum: Option<f64>,
     pub min: Option<Value>,
     pub max: Option<Value>,
+    /// 字符串拼接类聚合的中间结果
+    pub text: Option<String>,
 }
 
 impl AggregateAccumulator {
@@ -423,6 +1094,24 @@ impl AggregateAccumulator {
             sum: None,
             min: None,
             max: None,
+            text: None,
+        }
+    }
+
+    /// 把值的文本形式追加到拼接结果，NULL 被忽略
+    pub fn append_text(&mut self, value: &Value, separator: &str) {
+        let piece = match value {
+            Value::Null => return,
+            Value::Varchar(s) => s.clone(),
+            other => other.to_string(),
+        };
+        self.count += 1;
+        match &mut self.text {
+            Some(text) => {
+                text.push_str(separator);
+                text.push_str(&piece);
+            }
+            None => self.text = Some(piece),
         }
     }
 
@@ -465,6 +1154,17 @@ impl AggregateAccumulator {
                     self.max = Some(double_val);
                 }
             },
+            Value::Decimal(d) => {
+                self.sum = Some(self.sum.unwrap_or(0.0) + d.to_f64());
+                
+                let decimal_val = Value::Decimal(*d);
+                if self.min.is_none() || self.compare_values(&decimal_val, self.min.as_ref().unwrap())? < 0 {
+                    self.min = Some(decimal_val.clone());
+                }
+                if self.max.is_none() || self.compare_values(&decimal_val, self.max.as_ref().unwrap())? > 0 {
+                    self.max = Some(decimal_val);
+                }
+            },
             Value::Varchar(s) => {
                 let str_val = Value::Varchar(s.clone());
                 if self.min.is_none() || self.compare_values(&str_val, self.min.as_ref().unwrap())? < 0 {
@@ -474,6 +1174,27 @@ impl AggregateAccumulator {
                     self.max = Some(str_val);
                 }
             },
+            Value::Blob(b) => {
+                let blob_val = Value::Blob(b.clone());
+                if self.min.is_none() || self.compare_values(&blob_val, self.min.as_ref().unwrap())? < 0 {
+                    self.min = Some(blob_val.clone());
+                }
+                if self.max.is_none() || self.compare_values(&blob_val, self.max.as_ref().unwrap())? > 0 {
+                    self.max = Some(blob_val);
+                }
+            },
+            Value::Json(_) => {
+                // JSON 文档没有顺序，MIN / MAX 不适用，只计数
+            },
+            Value::Uuid(u) => {
+                let uuid_val = Value::Uuid(*u);
+                if self.min.is_none() || self.compare_values(&uuid_val, self.min.as_ref().unwrap())? < 0 {
+                    self.min = Some(uuid_val.clone());
+                }
+                if self.max.is_none() || self.compare_values(&uuid_val, self.max.as_ref().unwrap())? > 0 {
+                    self.max = Some(uuid_val);
+                }
+            },
             Value::Boolean(_) => {
                 // For boolean values, we only count
             },
@@ -489,6 +1210,7 @@ impl AggregateAccumulator {
                     self.max = Some(bigint_val);
                 }
             },
+            #[cfg(feature = "chrono")]
             Value::Date(d) => {
                 let date_val = Value::Date(*d);
                 if self.min.is_none() || self.compare_values(&date_val, self.min.as_ref().unwrap())? < 0 {
@@ -498,6 +1220,7 @@ impl AggregateAccumulator {
                     self.max = Some(date_val);
                 }
             },
+            #[cfg(feature = "chrono")]
             Value::Timestamp(ts) => {
                 let ts_val = Value::Timestamp(*ts);
                 if self.min.is_none() || self.compare_values(&ts_val, self.min.as_ref().unwrap())? < 0 {
@@ -522,9 +1245,14 @@ impl AggregateAccumulator {
             (Value::BigInt(a), Value::BigInt(b)) => Ok(a.cmp(b) as i32),
             (Value::Float(a), Value::Float(b)) => Ok(a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal) as i32),
             (Value::Double(a), Value::Double(b)) => Ok(a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal) as i32),
+            (Value::Decimal(a), Value::Decimal(b)) => Ok(a.cmp(b) as i32),
             (Value::Varchar(a), Value::Varchar(b)) => Ok(a.cmp(b) as i32),
+            (Value::Blob(a), Value::Blob(b)) => Ok(a.cmp(b) as i32),
+            (Value::Uuid(a), Value::Uuid(b)) => Ok(a.cmp(b) as i32),
             (Value::Boolean(a), Value::Boolean(b)) => Ok(a.cmp(b) as i32),
+            #[cfg(feature = "chrono")]
             (Value::Date(a), Value::Date(b)) => Ok(a.cmp(b) as i32),
+            #[cfg(feature = "chrono")]
             (Value::Timestamp(a), Value::Timestamp(b)) => Ok(a.cmp(b) as i32),
             _ => Err(ExecutorError::TypeError {
                 message: format!("Cannot compare {:?} and {:?}", a, b),
@@ -559,158 +1287,52 @@ impl AggregateAccumulator {
             AggregateFunction::Max(_) => {
                 self.max.clone().unwrap_or(Value::Null)
             },
+            AggregateFunction::StringAgg { .. } => {
+                self.text.clone().map(Value::Varchar).unwrap_or(Value::Null)
+            },
         }
     }
 }
 
-/// 具有聚合功能的 GROUP BY 执行器
-pub struct GroupByExecutor {
-    input: Box<dyn Executor>,
-    group_expressions: Vec<Expression>,
-    aggregate_functions: Vec<AggregateFunction>,
-    groups: HashMap<Vec<Value>, Vec<AggregateAccumulator>>,
+/// 由全部输入行算出分组结果及其模式，第二个参数为输入的模式
+pub type Aggregation<'a> = Box<dyn Fn(Vec<Tuple>, &Schema) -> Result<(Vec<Tuple>, Schema), ExecutorError> + 'a>;
+
+/// GROUP BY 执行器：读完输入后一次算出全部分组再逐行输出
+///
+/// 输出列的类型可能取决于输入的值，模式在第一次调用 next 之后才确定。
+pub struct GroupByExecutor<'a> {
+    input: Box<dyn Executor + 'a>,
+    aggregate: Aggregation<'a>,
     result_iterator: std::vec::IntoIter<Tuple>,
     schema: Schema,
     materialized: bool,
 }
 
-impl GroupByExecutor {
-    pub fn new(
-        input: Box<dyn Executor>,
-        group_expressions: Vec<Expression>,
-        aggregate_functions: Vec<AggregateFunction>,
-    ) -> Self {
-        // Create schema: group columns + aggregate columns
-        let mut columns = Vec::new();
-        
-        // Add group by columns
-        for (i, _) in group_expressions.iter().enumerate() {
-            columns.push(ColumnDefinition {
-                name: format!("group_{}", i),
-                data_type: DataType::Varchar(255), // Simplified for now
-                nullable: false,
-                default: None,
-            });
-        }
-        
-        // Add aggregate columns
-        for func in &aggregate_functions {
-            let col_name = match func {
-                AggregateFunction::Count => "count".to_string(),
-                AggregateFunction::Sum(col) => format!("sum_{}", col),
-                AggregateFunction::Avg(col) => format!("avg_{}", col),
-                AggregateFunction::Min(col) => format!("min_{}", col),
-                AggregateFunction::Max(col) => format!("max_{}", col),
-            };
-            
-            columns.push(ColumnDefinition {
-                name: col_name,
-                data_type: DataType::Double, // Use double for aggregates
-                nullable: true,
-                default: None,
-            });
-        }
-        
-        let schema = Schema { columns, primary_key: None };
-        
+impl<'a> GroupByExecutor<'a> {
+    pub fn new(input: Box<dyn Executor + 'a>, aggregate: Aggregation<'a>) -> Self {
         Self {
             input,
-            group_expressions,
-            aggregate_functions,
-            groups: HashMap::new(),
+            aggregate,
             result_iterator: Vec::new().into_iter(),
-            schema,
+            schema: Schema::new(Vec::new()),
             materialized: false,
         }
     }
 
     fn materialize(&mut self) -> Result<(), ExecutorError> {
-        if self.materialized {
-            return Ok(());
-        }
-
-        // Process all input tuples
+        let mut rows = Vec::new();
         while let Some(tuple) = self.input.next()? {
-            // Evaluate group by expressions
-            let group_key: Vec<Value> = self.group_expressions
-                .iter()
-                .map(|expr| self.evaluate_expression(expr, &tuple))
-                .collect::<Result<_, _>>()?;
-
-            // Get or create accumulators for this group
-            let accumulators = self.groups.entry(group_key).or_insert_with(|| {
-                self.aggregate_functions
-                    .iter()
-                    .map(|_| AggregateAccumulator::new())
-                    .collect()
-            });
-
-            // Update accumulators
-            for (i, func) in self.aggregate_functions.iter().enumerate() {
-                match func {
-                    AggregateFunction::Count => {
-                        accumulators[i].count += 1;
-                    },
-                    AggregateFunction::Sum(col_name) |
-                    AggregateFunction::Avg(col_name) |
-                    AggregateFunction::Min(col_name) |
-                    AggregateFunction::Max(col_name) => {
-                        if let Some(col_idx) = self.input.schema().columns.iter().position(|c| &c.name == col_name) {
-                            accumulators[i].update(&tuple.values[col_idx])?;
-                        }
-                    },
-                }
-            }
+            rows.push(tuple);
         }
-
-        // Convert groups to result tuples
-        let mut results = Vec::new();
-        for (group_key, accumulators) in &self.groups {
-            let mut values = group_key.clone();
-            
-            // Add aggregate results
-            for (i, func) in self.aggregate_functions.iter().enumerate() {
-                values.push(accumulators[i].get_result(func));
-            }
-            
-            results.push(Tuple { values });
-        }
-
+        let (results, schema) = (self.aggregate)(rows, self.input.schema())?;
         self.result_iterator = results.into_iter();
+        self.schema = schema;
         self.materialized = true;
         Ok(())
     }
-
-    fn evaluate_expression(&self, expr: &Expression, tuple: &Tuple) -> Result<Value, ExecutorError> {
-        match expr {
-            Expression::Literal(value) => Ok(value.clone()),
-            Expression::Column(name) => {
-                if let Some(index) = self.input.schema().columns.iter().position(|c| &c.name == name) {
-                    Ok(tuple.values[index].clone())
-                } else {
-                    Err(ExecutorError::EvaluationError {
-                        message: format!("Column not found: {}", name),
-                    })
-                }
-            },
-            Expression::QualifiedColumn { table: _, column } => {
-                if let Some(index) = self.input.schema().columns.iter().position(|c| &c.name == column) {
-                    Ok(tuple.values[index].clone())
-                } else {
-                    Err(ExecutorError::EvaluationError {
-                        message: format!("Column not found: {}", column),
-                    })
-                }
-            },
-            _ => {
-                // Simplified expression evaluation
-                Ok(Value::Null)
-            }
-        }
-    }
 }
 
-impl Executor for GroupByExecutor {
+impl Executor for GroupByExecutor<'_> {
     fn next(&mut self) -> Result<Option<Tuple>, ExecutorError> {
         if !self.materialized {
             self.materialize()?;
@@ -725,9 +1347,217 @@ impl Executor for GroupByExecutor {
 
     fn reset(&mut self) -> Result<(), ExecutorError> {
         self.input.reset()?;
-        self.groups.clear();
         self.result_iterator = Vec::new().into_iter();
         self.materialized = false;
         Ok(())
     }
+
+    fn close(&mut self) {
+        self.input.close();
+        self.result_iterator = Vec::new().into_iter();
+        self.materialized = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ColumnDefinition;
+
+    /// 按给定顺序逐行输出内存中的行
+    struct RowsExecutor {
+        schema: Schema,
+        rows: Vec<Tuple>,
+        position: usize,
+    }
+
+    impl RowsExecutor {
+        fn boxed(name: &str, keys: &[Option<i32>]) -> Box<dyn Executor> {
+            let column = ColumnDefinition { name: name.to_string(), data_type: DataType::Integer, nullable: true, default: None, default_function: None };
+            let rows = keys.iter()
+                .map(|key| Tuple { values: vec![key.map(Value::Integer).unwrap_or(Value::Null)] })
+                .collect();
+            Box::new(Self { schema: Schema { columns: vec![column], primary_key: None }, rows, position: 0 })
+        }
+    }
+
+    impl Executor for RowsExecutor {
+        fn next(&mut self) -> Result<Option<Tuple>, ExecutorError> {
+            self.position += 1;
+            Ok(self.rows.get(self.position - 1).cloned())
+        }
+
+        fn schema(&self) -> &Schema {
+            &self.schema
+        }
+
+        fn reset(&mut self) -> Result<(), ExecutorError> {
+            self.position = 0;
+            Ok(())
+        }
+    }
+
+    fn merge_join(join_type: JoinType, left: &[Option<i32>], right: &[Option<i32>]) -> Result<Vec<Vec<Value>>, ExecutorError> {
+        let mut join = MergeJoinExecutor::new(
+            RowsExecutor::boxed("l", left),
+            RowsExecutor::boxed("r", right),
+            join_type,
+            vec![0],
+            vec![0],
+        )?;
+        let mut rows = Vec::new();
+        while let Some(tuple) = join.next()? {
+            rows.push(tuple.values);
+        }
+        Ok(rows)
+    }
+
+    #[test]
+    fn test_merge_join() {
+        use Value::{Integer as I, Null};
+        let left = [None, Some(1), Some(2), Some(2), Some(4)];
+        let right = [None, Some(2), Some(2), Some(3), Some(4)];
+
+        assert_eq!(merge_join(JoinType::Inner, &left, &right).unwrap(), vec![
+            vec![I(2), I(2)], vec![I(2), I(2)], vec![I(2), I(2)], vec![I(2), I(2)], vec![I(4), I(4)],
+        ]);
+        assert_eq!(merge_join(JoinType::Left, &left, &right).unwrap(), vec![
+            vec![Null, Null], vec![I(1), Null],
+            vec![I(2), I(2)], vec![I(2), I(2)], vec![I(2), I(2)], vec![I(2), I(2)], vec![I(4), I(4)],
+        ]);
+        assert_eq!(merge_join(JoinType::Full, &[Some(1), Some(4)], &right).unwrap(), vec![
+            vec![Null, Null], vec![I(1), Null], vec![Null, I(2)], vec![Null, I(2)], vec![Null, I(3)], vec![I(4), I(4)],
+        ]);
+        assert_eq!(merge_join(JoinType::Semi, &left, &right).unwrap(), vec![vec![I(2)], vec![I(2)], vec![I(4)]]);
+        assert_eq!(merge_join(JoinType::Anti, &left, &right).unwrap(), vec![vec![Null], vec![I(1)]]);
+
+        // 输入没有按连接键排序
+        assert!(matches!(
+            merge_join(JoinType::Inner, &[Some(3), Some(1)], &right),
+            Err(ExecutorError::JoinError { .. })
+        ));
+        // 两侧连接键数量不同
+        assert!(MergeJoinExecutor::new(
+            RowsExecutor::boxed("l", &[]),
+            RowsExecutor::boxed("r", &[]),
+            JoinType::Inner,
+            vec![0],
+            vec![],
+        ).is_err());
+    }
+
+    fn collect(mut join: impl Executor) -> Result<Vec<Vec<Value>>, ExecutorError> {
+        let mut rows = Vec::new();
+        while let Some(tuple) = join.next()? {
+            rows.push(tuple.values);
+        }
+        Ok(rows)
+    }
+
+    #[test]
+    fn test_hash_join() {
+        use Value::{Integer as I, Null};
+        let hash_join = |join_type: JoinType, left: &[Option<i32>], right: &[Option<i32>]| {
+            collect(HashJoinExecutor::new(
+                RowsExecutor::boxed("l", left),
+                RowsExecutor::boxed("r", right),
+                join_type,
+                vec![0],
+                vec![0],
+                None,
+            )?)
+        };
+        let left = [Some(2), None, Some(1), Some(4), Some(2)];
+        let right = [Some(4), Some(2), None, Some(3)];
+
+        // 按右输入的顺序探测；NULL 键不匹配任何行
+        assert_eq!(hash_join(JoinType::Inner, &left, &right).unwrap(), vec![
+            vec![I(4), I(4)], vec![I(2), I(2)], vec![I(2), I(2)],
+        ]);
+        assert_eq!(hash_join(JoinType::Left, &left, &right).unwrap(), vec![
+            vec![I(4), I(4)], vec![I(2), I(2)], vec![I(2), I(2)], vec![Null, Null], vec![I(1), Null],
+        ]);
+        assert_eq!(hash_join(JoinType::Right, &left, &right).unwrap(), vec![
+            vec![I(4), I(4)], vec![I(2), I(2)], vec![I(2), I(2)], vec![Null, Null], vec![Null, I(3)],
+        ]);
+        assert_eq!(hash_join(JoinType::Semi, &left, &right).unwrap(), vec![vec![I(2)], vec![I(4)], vec![I(2)]]);
+        assert_eq!(hash_join(JoinType::Anti, &left, &right).unwrap(), vec![vec![Null], vec![I(1)]]);
+
+        // 键相同的行还要满足连接条件
+        let join = HashJoinExecutor::new(
+            RowsExecutor::boxed("l", &left),
+            RowsExecutor::boxed("r", &right),
+            JoinType::Inner,
+            vec![0],
+            vec![0],
+            Some(Box::new(|row: &Tuple| Ok(row.values[0] != I(2)))),
+        ).unwrap();
+        assert_eq!(collect(join).unwrap(), vec![vec![I(4), I(4)]]);
+        assert!(HashJoinExecutor::new(
+            RowsExecutor::boxed("l", &[]),
+            RowsExecutor::boxed("r", &[]),
+            JoinType::Inner,
+            vec![1],
+            vec![0],
+            None,
+        ).is_err());
+    }
+
+    #[test]
+    fn test_nested_loop_join() {
+        use Value::{Integer as I, Null};
+        // l < r
+        let nested_loop = |join_type: JoinType| {
+            collect(NestedLoopJoinExecutor::new(
+                RowsExecutor::boxed("l", &[Some(1), Some(3), None]),
+                RowsExecutor::boxed("r", &[Some(2), Some(0)]),
+                join_type,
+                Some(Box::new(|row: &Tuple| Ok(matches!((&row.values[0], &row.values[1]), (I(l), I(r)) if l < r)))),
+            ))
+        };
+
+        assert_eq!(nested_loop(JoinType::Inner).unwrap(), vec![vec![I(1), I(2)]]);
+        assert_eq!(nested_loop(JoinType::Full).unwrap(), vec![
+            vec![I(1), I(2)], vec![I(3), Null], vec![Null, Null], vec![Null, I(0)],
+        ]);
+        assert_eq!(nested_loop(JoinType::Anti).unwrap(), vec![vec![I(3)], vec![Null]]);
+
+        // 没有连接条件时为笛卡尔积
+        let join = NestedLoopJoinExecutor::new(
+            RowsExecutor::boxed("l", &[Some(1), Some(2)]),
+            RowsExecutor::boxed("r", &[Some(3), Some(4)]),
+            JoinType::Inner,
+            None,
+        );
+        assert_eq!(collect(join).unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_scan_sort_limit_pipeline() {
+        use Value::{Integer as I, Null};
+        let schema = Schema::new(vec![ColumnDefinition::new("n".to_string(), DataType::Integer, true)]);
+        let rows: Vec<Tuple> = [Some(3), None, Some(1), Some(5), Some(4), Some(2)].iter()
+            .map(|n| Tuple::new(vec![n.map(I).unwrap_or(Null)]))
+            .collect();
+
+        // WHERE n <> 4 ORDER BY n DESC NULLS FIRST LIMIT 3
+        let scan = ScanExecutor::new(Cow::Borrowed(rows.as_slice()), schema)
+            .with_filter(Box::new(|row: &Tuple| Ok(row.values[0] != I(4))));
+        let sort = SortExecutor::new(
+            Box::new(scan),
+            vec![SortOrder { descending: true, nulls_first: Some(true) }],
+            Box::new(|row: &Tuple, _: &Schema| Ok(vec![row.values[0].clone()])),
+            |a: &Value, b: &Value| a.partial_cmp(b).unwrap_or(Ordering::Equal),
+        );
+        let mut limit = LimitExecutor::new(Box::new(sort), 3, 0);
+        let mut output = Vec::new();
+        while let Some(row) = limit.next().unwrap() {
+            output.push(row.values[0].clone());
+        }
+        assert_eq!(output, vec![Null, I(5), I(3)]);
+        assert_eq!(limit.schema().columns[0].name, "n");
+
+        limit.reset().unwrap();
+        assert_eq!(limit.next().unwrap().map(|row| row.values), Some(vec![Null]));
+    }
 }
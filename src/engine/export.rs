@@ -0,0 +1,153 @@
+//! CSV 导出
+//!
+//! 把查询结果逐行写入任意 [`Write`]，不在内存中拼出整个文件。
+//! 输出遵循 COPY FROM 的约定，导出的文件可以原样导回：NULL 写成不加引号的空字段，
+//! 空字符串写成 `""`，字段内的双引号写成两个。
+
+use crate::types::{Schema, Tuple, Value};
+use crate::utils::encoding;
+use std::borrow::Borrow;
+use std::io::{self, Write};
+
+pub use crate::sql::parser::QuoteStyle;
+
+/// CSV 写出选项
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CsvOptions {
+    /// 字段分隔符
+    pub delimiter: char,
+    /// 是否先写一行列名
+    pub header: bool,
+    /// 字段何时加引号
+    pub quote: QuoteStyle,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self { delimiter: ',', header: true, quote: QuoteStyle::Minimal }
+    }
+}
+
+/// 把行写成 CSV，返回写出的数据行数（不含列名行）
+///
+/// 有模式且开启 `header` 时先写列名；每行写完即交给 writer，需要缓冲时由调用方包一层 `BufWriter`。
+pub fn write_csv<W: Write, R: Borrow<Tuple>>(
+    writer: &mut W,
+    schema: Option<&Schema>,
+    rows: impl IntoIterator<Item = R>,
+    options: &CsvOptions,
+) -> io::Result<usize> {
+    let mut line = String::new();
+    if let (Some(schema), true) = (schema, options.header) {
+        for (i, column) in schema.columns.iter().enumerate() {
+            if i > 0 {
+                line.push(options.delimiter);
+            }
+            // 列名不是数值，NONNUMERIC 时也加引号
+            let quoted = options.quote != QuoteStyle::Minimal || needs_quotes(&column.name, options.delimiter);
+            push_field(&mut line, &column.name, quoted);
+        }
+        line.push('\n');
+        writer.write_all(line.as_bytes())?;
+    }
+
+    let mut count = 0;
+    for row in rows {
+        line.clear();
+        for (i, value) in row.borrow().values.iter().enumerate() {
+            if i > 0 {
+                line.push(options.delimiter);
+            }
+            if let Some(text) = field_text(value) {
+                let quoted = match options.quote {
+                    QuoteStyle::All => true,
+                    QuoteStyle::NonNumeric if !is_numeric(value) => true,
+                    _ => needs_quotes(&text, options.delimiter),
+                };
+                push_field(&mut line, &text, quoted);
+            }
+        }
+        line.push('\n');
+        writer.write_all(line.as_bytes())?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// 字段的原文；NULL 为 None
+fn field_text(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::Varchar(s) => Some(s.clone()),
+        // 与 CAST 为 TEXT 的写法相同，COPY FROM 写入 BLOB 列时解码回原来的字节
+        Value::Blob(b) => Some(format!("\\x{}", encoding::encode_hex(b))),
+        other => Some(other.to_string()),
+    }
+}
+
+fn is_numeric(value: &Value) -> bool {
+    matches!(value, Value::Integer(_) | Value::BigInt(_) | Value::Float(_) | Value::Double(_) | Value::Decimal(_) | Value::Boolean(_))
+}
+
+/// 不加引号就无法原样读回的字段：空字符串（会被读成 NULL）、含分隔符、引号或换行、首尾有空白
+fn needs_quotes(text: &str, delimiter: char) -> bool {
+    text.is_empty()
+        || text.starts_with(char::is_whitespace)
+        || text.ends_with(char::is_whitespace)
+        || text.contains([delimiter, '"', '\n', '\r'])
+}
+
+fn push_field(line: &mut String, text: &str, quoted: bool) {
+    if quoted {
+        line.push('"');
+        line.push_str(&text.replace('"', "\"\""));
+        line.push('"');
+    } else {
+        line.push_str(text);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::import::parse_csv;
+    use crate::types::{ColumnDefinition, DataType};
+
+    #[test]
+    fn test_write_csv() {
+        let schema = Schema {
+            columns: vec![
+                ColumnDefinition { name: "id".to_string(), data_type: DataType::Integer, nullable: false, default: None, default_function: None },
+                ColumnDefinition { name: "note".to_string(), data_type: DataType::Varchar(50), nullable: true, default: None, default_function: None },
+            ],
+            primary_key: None,
+        };
+        let rows = vec![
+            Tuple::new(vec![Value::Integer(1), Value::Varchar("plain".to_string())]),
+            Tuple::new(vec![Value::Integer(2), Value::Varchar("a, \"b\"\nc".to_string())]),
+            Tuple::new(vec![Value::Integer(3), Value::Varchar(String::new())]),
+            Tuple::new(vec![Value::Integer(4), Value::Null]),
+        ];
+
+        let mut out = Vec::new();
+        assert_eq!(write_csv(&mut out, Some(&schema), &rows, &CsvOptions::default()).unwrap(), 4);
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(text, "id,note\n1,plain\n2,\"a, \"\"b\"\"\nc\"\n3,\"\"\n4,\n");
+
+        // 导出的文件按 COPY FROM 的规则读回原值
+        let records = parse_csv(&text, ',').unwrap();
+        assert_eq!(records[2].fields[1].as_deref(), Some("a, \"b\"\nc"));
+        assert_eq!(records[3].fields[1].as_deref(), Some(""));
+        assert_eq!(records[4].fields[1], None);
+
+        let options = CsvOptions { delimiter: ';', header: false, quote: QuoteStyle::NonNumeric };
+        let mut out = Vec::new();
+        write_csv(&mut out, Some(&schema), &rows[..1], &options).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "1;\"plain\"\n");
+
+        let options = CsvOptions { quote: QuoteStyle::All, ..CsvOptions::default() };
+        let mut out = Vec::new();
+        write_csv(&mut out, Some(&schema), &rows[3..], &options).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "\"id\",\"note\"\n\"4\",\n");
+    }
+}
@@ -0,0 +1,204 @@
+//! 外部归并排序
+//!
+//! ORDER BY 的行先在内存中累积；估计大小超过工作内存（`DatabaseConfig::sort_work_mem_bytes`）时，
+//! 把当前这批排好序写入语句临时目录中的一个顺串文件，最后把各顺串和内存中剩下的行多路归并输出。
+//! 顺串计入临时文件配额，排序结束或失败时随语句的临时目录一起删除。
+//! 没有临时目录（内存数据库）时不溢出，总是在内存中排序。
+
+use crate::engine::database::ExecutionError;
+use crate::storage::{StatementTempSpace, TempFile};
+use crate::types::{Tuple, Value};
+use crate::utils::serialize;
+use std::cmp::Ordering;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+
+/// 按比较函数排序任意多行，超出工作内存的部分溢出到临时文件
+///
+/// 排序是稳定的：比较结果相等的行保持放入的顺序。
+pub(crate) struct ExternalSorter<F> {
+    compare: F,
+    work_mem_bytes: usize,
+    space: Option<StatementTempSpace>,
+    /// 尚未写出的行及其估计大小
+    buffer: Vec<Tuple>,
+    buffer_bytes: usize,
+    /// 已写出的顺串，按写出顺序排列
+    runs: Vec<TempFile>,
+}
+
+impl<F: Fn(&Tuple, &Tuple) -> Ordering> ExternalSorter<F> {
+    /// space 为 None 时不溢出
+    pub(crate) fn new(work_mem_bytes: usize, space: Option<StatementTempSpace>, compare: F) -> Self {
+        Self { compare, work_mem_bytes, space, buffer: Vec::new(), buffer_bytes: 0, runs: Vec::new() }
+    }
+
+    pub(crate) fn push(&mut self, tuple: Tuple) -> Result<(), ExecutionError> {
+        self.buffer_bytes += tuple_bytes(&tuple);
+        self.buffer.push(tuple);
+        if self.buffer_bytes > self.work_mem_bytes {
+            if let Some(space) = &self.space {
+                let run = write_run(space, &mut self.buffer, &self.compare)?;
+                self.runs.push(run);
+                self.buffer_bytes = 0;
+            }
+        }
+        Ok(())
+    }
+
+    /// 已写出的顺串数
+    pub(crate) fn spilled_runs(&self) -> usize {
+        self.runs.len()
+    }
+
+    /// 结束输入，按顺序逐行返回
+    pub(crate) fn finish(mut self) -> Result<SortedRows<F>, ExecutionError> {
+        self.buffer.sort_by(&self.compare);
+        let mut sources: Vec<RunSource> = self.runs.into_iter()
+            .map(|mut run| {
+                run.rewind().map_err(spill_error)?;
+                Ok(RunSource::File(BufReader::new(run)))
+            })
+            .collect::<Result<_, ExecutionError>>()?;
+        sources.push(RunSource::Memory(self.buffer.into_iter()));
+        let heads = sources.iter_mut().map(RunSource::next).collect::<Result<_, _>>()?;
+        Ok(SortedRows { compare: self.compare, sources, heads })
+    }
+}
+
+/// 排序后写出一个顺串：每行为 u32 长度前缀加行的二进制编码
+fn write_run<F: Fn(&Tuple, &Tuple) -> Ordering>(
+    space: &StatementTempSpace,
+    rows: &mut Vec<Tuple>,
+    compare: &F,
+) -> Result<TempFile, ExecutionError> {
+    rows.sort_by(compare);
+    let file = space.create_file("sort_run").map_err(|e| ExecutionError::StorageError(format!("Failed to spill sort run: {}", e)))?;
+    let mut writer = BufWriter::new(file);
+    let mut record = Vec::new();
+    for row in rows.drain(..) {
+        record.clear();
+        serialize::encode_tuple_into(&row, &mut record);
+        writer.write_all(&(record.len() as u32).to_le_bytes()).map_err(spill_error)?;
+        writer.write_all(&record).map_err(spill_error)?;
+    }
+    writer.into_inner().map_err(|e| spill_error(e.into_error()))
+}
+
+/// 行在内存中占用的估计字节数
+fn tuple_bytes(tuple: &Tuple) -> usize {
+    std::mem::size_of::<Tuple>()
+        + tuple.values.iter().map(|value| std::mem::size_of::<Value>() + value.serialized_size()).sum::<usize>()
+}
+
+fn spill_error(e: io::Error) -> ExecutionError {
+    ExecutionError::StorageError(format!("Failed to spill sort run: {}", e))
+}
+
+/// 归并的一路输入
+enum RunSource {
+    File(BufReader<TempFile>),
+    Memory(std::vec::IntoIter<Tuple>),
+}
+
+impl RunSource {
+    fn next(&mut self) -> Result<Option<Tuple>, ExecutionError> {
+        let reader = match self {
+            RunSource::Memory(rows) => return Ok(rows.next()),
+            RunSource::File(reader) => reader,
+        };
+        let mut length = [0u8; 4];
+        match reader.read_exact(&mut length) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(spill_error(e)),
+        }
+        let mut record = vec![0; u32::from_le_bytes(length) as usize];
+        reader.read_exact(&mut record).map_err(spill_error)?;
+        serialize::decode_tuple(&record)
+            .map(Some)
+            .map_err(|e| ExecutionError::StorageError(format!("Corrupt sort run: {}", e)))
+    }
+}
+
+/// 多路归并各顺串的输出
+pub(crate) struct SortedRows<F> {
+    compare: F,
+    sources: Vec<RunSource>,
+    /// 每路输入的下一行
+    heads: Vec<Option<Tuple>>,
+}
+
+impl<F: Fn(&Tuple, &Tuple) -> Ordering> Iterator for SortedRows<F> {
+    type Item = Result<Tuple, ExecutionError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // 相等时取较早的一路：顺串按输入顺序写出，各自又是稳定排序的，整体仍然稳定
+        let mut smallest: Option<(usize, &Tuple)> = None;
+        for (index, head) in self.heads.iter().enumerate() {
+            if let Some(row) = head {
+                if smallest.is_none_or(|(_, best)| (self.compare)(row, best) == Ordering::Less) {
+                    smallest = Some((index, row));
+                }
+            }
+        }
+        let (index, _) = smallest?;
+        let next = match self.sources[index].next() {
+            Ok(next) => next,
+            Err(e) => return Some(Err(e)),
+        };
+        std::mem::replace(&mut self.heads[index], next).map(Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::TempFileManager;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_spills_and_merges_runs() {
+        let root = TempDir::new().unwrap();
+        let manager = TempFileManager::new(root.path(), 1024 * 1024).unwrap();
+        // 按第一列排序；第二列记录放入顺序，用来检查稳定性
+        let by_key = |a: &Tuple, b: &Tuple| a.values[0].partial_cmp(&b.values[0]).unwrap();
+        let rows: Vec<Tuple> = (0..200)
+            .map(|i| Tuple::new(vec![Value::Integer((i * 37) % 50), Value::Integer(i)]))
+            .collect();
+
+        let mut sorter = ExternalSorter::new(1024, Some(manager.begin_statement()), by_key);
+        for row in rows.clone() {
+            sorter.push(row).unwrap();
+        }
+        assert!(sorter.spilled_runs() > 1);
+        assert!(manager.used_bytes() > 0);
+        let sorted: Vec<Tuple> = sorter.finish().unwrap().collect::<Result<_, _>>().unwrap();
+
+        let mut expected = rows.clone();
+        expected.sort_by(by_key);
+        assert_eq!(sorted, expected);
+        assert_eq!(manager.used_bytes(), 0);
+
+        // 没有临时空间时只在内存中排序
+        let mut sorter = ExternalSorter::new(1024, None, by_key);
+        for row in rows {
+            sorter.push(row).unwrap();
+        }
+        assert_eq!(sorter.spilled_runs(), 0);
+        assert_eq!(sorter.finish().unwrap().collect::<Result<Vec<_>, _>>().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_spill_respects_temp_quota() {
+        let root = TempDir::new().unwrap();
+        let manager = TempFileManager::new(root.path(), 64).unwrap();
+        let mut sorter = ExternalSorter::new(0, Some(manager.begin_statement()), |a: &Tuple, b: &Tuple| {
+            a.values[0].partial_cmp(&b.values[0]).unwrap()
+        });
+        let error = (0..100)
+            .map(|i| sorter.push(Tuple::new(vec![Value::Varchar(format!("row {}", i))])))
+            .find_map(Result::err)
+            .unwrap();
+        assert!(error.to_string().contains("quota"), "{}", error);
+    }
+}
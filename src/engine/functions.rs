@@ -0,0 +1,501 @@
+//! 标量函数
+//!
+//! 表达式求值器遇到非聚合的函数调用时，先求值全部参数，再按函数名分派到这里。
+//! 除特别说明外，任一参数为 NULL 时结果为 NULL（COALESCE 和 NULLIF 除外）。
+
+use crate::engine::capabilities::Capability;
+use crate::engine::database::ExecutionError;
+use crate::engine::random::RandomSource;
+use crate::sql::parser::BinaryOperator;
+use crate::types::{DataType, Decimal, JsonValue, Uuid, Value};
+#[cfg(feature = "chrono")]
+use chrono::{Datelike, Duration, Local, Months, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+
+/// 聚合函数名，由 GROUP BY 路径处理而不是逐行求值
+pub(crate) const AGGREGATE_FUNCTIONS: &[&str] = &["COUNT", "SUM", "AVG", "MIN", "MAX", "STRING_AGG", "GROUP_CONCAT",
+    "STDDEV", "STDDEV_SAMP", "STDDEV_POP", "VARIANCE", "VAR_SAMP", "VAR_POP",
+];
+
+/// 是否为聚合函数
+pub(crate) fn is_aggregate(name: &str) -> bool {
+    AGGREGATE_FUNCTIONS.iter().any(|f| f.eq_ignore_ascii_case(name))
+}
+
+/// 相同参数下每次调用结果可能不同的函数，含有它们的查询不缓存结果
+const VOLATILE_FUNCTIONS: &[&str] = &["RANDOM", "RAND", "GEN_UUID", "UUID", "NOW", "CURRENT_TIMESTAMP", "CURRENT_DATE"];
+
+/// 是否为易变函数
+pub(crate) fn is_volatile(name: &str) -> bool {
+    VOLATILE_FUNCTIONS.iter().any(|f| f.eq_ignore_ascii_case(name))
+}
+
+/// 调用标量函数
+pub(crate) fn call(name: &str, args: &[Value], random: &RandomSource) -> Result<Value, ExecutionError> {
+    let upper = name.to_uppercase();
+    match upper.as_str() {
+        "RANDOM" | "RAND" => {
+            expect_args(&upper, args, 0, 0)?;
+            Ok(Value::Double(random.next_f64()))
+        }
+        "GEN_UUID" | "UUID" => {
+            expect_args(&upper, args, 0, 0)?;
+            let bits = ((random.next_u64() as u128) << 64) | random.next_u64() as u128;
+            Ok(Value::Uuid(Uuid::new_v4(bits)))
+        }
+        #[cfg(feature = "chrono")]
+        "NOW" | "CURRENT_TIMESTAMP" => {
+            expect_args(&upper, args, 0, 0)?;
+            Ok(Value::Timestamp(Local::now().naive_local()))
+        }
+        #[cfg(feature = "chrono")]
+        "CURRENT_DATE" => {
+            expect_args(&upper, args, 0, 0)?;
+            Ok(Value::Date(Local::now().date_naive()))
+        }
+        #[cfg(feature = "chrono")]
+        "EXTRACT" => {
+            expect_args(&upper, args, 2, 2)?;
+            let field = unit_name(&upper, &args[0])?;
+            match &args[1] {
+                Value::Null => Ok(Value::Null),
+                value => extract(&field, value),
+            }
+        }
+        #[cfg(feature = "chrono")]
+        "DATE_ADD" | "DATE_SUB" => {
+            expect_args(&upper, args, 2, 3)?;
+            let unit = match args.get(2) {
+                Some(unit) => unit_name(&upper, unit)?,
+                None => "DAY".to_string(),
+            };
+            let amount = match &args[1] {
+                Value::Null => return Ok(Value::Null),
+                Value::Integer(n) => *n as i64,
+                Value::BigInt(n) => *n,
+                other => return Err(ExecutionError::TypeMismatch {
+                    expected: format!("integer amount for {}", upper),
+                    actual: format!("{:?}", other),
+                }),
+            };
+            let amount = if upper == "DATE_SUB" { -amount } else { amount };
+            match &args[0] {
+                Value::Null => Ok(Value::Null),
+                value => date_add(value, amount, &unit),
+            }
+        }
+        "COALESCE" => {
+            expect_args(&upper, args, 1, usize::MAX)?;
+            let common = common_type(&upper, args)?;
+            match (args.iter().find(|arg| !arg.is_null()), common) {
+                (Some(value), Some(common)) => cast(value, &common),
+                _ => Ok(Value::Null),
+            }
+        }
+        "NULLIF" => {
+            expect_args(&upper, args, 2, 2)?;
+            // 结果为第一个参数（或 NULL），比较时两边先统一到公共类型
+            match common_type(&upper, args)? {
+                Some(common) if cast(&args[0], &common)? == cast(&args[1], &common)? => Ok(Value::Null),
+                _ => Ok(args[0].clone()),
+            }
+        }
+        "ABS" | "CEIL" | "CEILING" | "FLOOR" => {
+            expect_args(&upper, args, 1, 1)?;
+            unary_numeric(&upper, &args[0])
+        }
+        "ROUND" => {
+            expect_args(&upper, args, 1, 2)?;
+            let digits = match args.get(1) {
+                None => 0,
+                Some(Value::Integer(n)) => *n,
+                Some(Value::Null) => return Ok(Value::Null),
+                Some(other) => return Err(ExecutionError::TypeMismatch {
+                    expected: "integer digits for ROUND".to_string(),
+                    actual: format!("{:?}", other),
+                }),
+            };
+            round(&args[0], digits)
+        }
+        "MOD" => {
+            expect_args(&upper, args, 2, 2)?;
+            modulo(&args[0], &args[1])
+        }
+        "POWER" | "POW" => {
+            expect_args(&upper, args, 2, 2)?;
+            match (to_f64(&upper, &args[0])?, to_f64(&upper, &args[1])?) {
+                (Some(base), Some(exponent)) => finite(&upper, base.powf(exponent)),
+                _ => Ok(Value::Null),
+            }
+        }
+        "SQRT" => {
+            expect_args(&upper, args, 1, 1)?;
+            match to_f64(&upper, &args[0])? {
+                Some(x) if x < 0.0 => Err(ExecutionError::EvaluationError {
+                    message: format!("cannot take square root of negative number {}", x),
+                }),
+                Some(x) => Ok(Value::Double(x.sqrt())),
+                None => Ok(Value::Null),
+            }
+        }
+        "UPPER" | "LOWER" => {
+            expect_args(&upper, args, 1, 1)?;
+            match &args[0] {
+                Value::Null => Ok(Value::Null),
+                Value::Varchar(s) if upper == "UPPER" => Ok(Value::Varchar(s.to_uppercase())),
+                Value::Varchar(s) => Ok(Value::Varchar(s.to_lowercase())),
+                other => Err(ExecutionError::TypeMismatch {
+                    expected: format!("string argument for {}", upper),
+                    actual: format!("{:?}", other),
+                }),
+            }
+        }
+        "LENGTH" => {
+            // 字符串按字符计数，BLOB 按字节计数
+            expect_args(&upper, args, 1, 1)?;
+            match &args[0] {
+                Value::Null => Ok(Value::Null),
+                Value::Varchar(s) => Ok(Value::Integer(s.chars().count() as i32)),
+                Value::Blob(b) => Ok(Value::Integer(b.len() as i32)),
+                other => Err(ExecutionError::TypeMismatch {
+                    expected: "string or BLOB argument for LENGTH".to_string(),
+                    actual: format!("{:?}", other),
+                }),
+            }
+        }
+        "JSON_EXTRACT" => {
+            // JSON_EXTRACT(doc, '$.path')，路径不存在时为 NULL
+            expect_args(&upper, args, 2, 2)?;
+            let (document, path) = match (&args[0], &args[1]) {
+                (Value::Null, _) | (_, Value::Null) => return Ok(Value::Null),
+                (document, Value::Varchar(path)) => (json_document(&upper, document)?, path),
+                (_, other) => return Err(ExecutionError::TypeMismatch {
+                    expected: "string path for JSON_EXTRACT".to_string(),
+                    actual: format!("{:?}", other),
+                }),
+            };
+            let found = document.extract_path(path).map_err(|e| ExecutionError::EvaluationError { message: e.to_string() })?;
+            Ok(found.map_or(Value::Null, |value| Value::Json(value.to_string())))
+        }
+        _ => Err(ExecutionError::NotImplemented {
+            feature: format!("function {}", name),
+            capability: Capability::ScalarFunctions,
+        }),
+    }
+}
+
+/// 解析 JSON 函数或运算符的文档参数；字符串按 JSON 文本解析
+fn json_document(function: &str, value: &Value) -> Result<JsonValue, ExecutionError> {
+    let text = match value {
+        Value::Json(text) | Value::Varchar(text) => text,
+        other => return Err(ExecutionError::TypeMismatch {
+            expected: format!("JSON argument for {}", function),
+            actual: format!("{:?}", other),
+        }),
+    };
+    text.parse().map_err(|e: crate::types::json::JsonError| ExecutionError::EvaluationError { message: e.to_string() })
+}
+
+/// `->` / `->>`：字符串按对象的键、整数按数组下标取值，取不到时为 NULL
+///
+/// `->` 的结果仍是 JSON，`->>` 的结果是文本：字符串取其内容，JSON 的 null 为 NULL。
+pub(crate) fn json_access(op: &BinaryOperator, document: &Value, key: &Value) -> Result<Value, ExecutionError> {
+    let symbol = if *op == BinaryOperator::JsonExtract { "->" } else { "->>" };
+    if document.is_null() || key.is_null() {
+        return Ok(Value::Null);
+    }
+    let document = json_document(symbol, document)?;
+    let found = match key {
+        Value::Varchar(name) => document.get(name),
+        Value::Integer(index) => document.index(*index as i64),
+        Value::BigInt(index) => document.index(*index),
+        other => return Err(ExecutionError::TypeMismatch {
+            expected: format!("string key or integer index for {}", symbol),
+            actual: format!("{:?}", other),
+        }),
+    };
+    Ok(match (found, op) {
+        (None, _) => Value::Null,
+        (Some(value), BinaryOperator::JsonExtract) => Value::Json(value.to_string()),
+        (Some(value), _) => value.to_text().map_or(Value::Null, Value::Varchar),
+    })
+}
+
+/// 非 NULL 参数的公共类型；全部为 NULL 时为 None
+fn common_type(function: &str, args: &[Value]) -> Result<Option<DataType>, ExecutionError> {
+    let mut common: Option<DataType> = None;
+    for arg in args.iter().filter(|arg| !arg.is_null()) {
+        let arg_type = arg.data_type();
+        common = Some(match common {
+            None => arg_type,
+            Some(current) => current.common_type(&arg_type).ok_or_else(|| ExecutionError::TypeMismatch {
+                expected: format!("arguments of {} with a common type ({})", function, current),
+                actual: format!("{:?}", arg),
+            })?,
+        });
+    }
+    Ok(common)
+}
+
+fn cast(value: &Value, data_type: &DataType) -> Result<Value, ExecutionError> {
+    value.cast_to(data_type).map_err(|e| ExecutionError::EvaluationError { message: e.to_string() })
+}
+
+/// ABS/CEIL/FLOOR：结果与参数同类型
+fn unary_numeric(function: &str, value: &Value) -> Result<Value, ExecutionError> {
+    let overflow = || ExecutionError::EvaluationError {
+        message: format!("integer overflow in {}({})", function, value),
+    };
+    match (function, value) {
+        (_, Value::Null) => Ok(Value::Null),
+        ("ABS", Value::Integer(i)) => i.checked_abs().map(Value::Integer).ok_or_else(overflow),
+        ("ABS", Value::BigInt(i)) => i.checked_abs().map(Value::BigInt).ok_or_else(overflow),
+        ("ABS", Value::Float(f)) => Ok(Value::Float(f.abs())),
+        ("ABS", Value::Double(d)) => Ok(Value::Double(d.abs())),
+        ("ABS", Value::Decimal(d)) => d.checked_abs().map(Value::Decimal).ok_or_else(overflow),
+        ("FLOOR", Value::Decimal(d)) => d.floor().map(Value::Decimal).ok_or_else(overflow),
+        (_, Value::Decimal(d)) => d.ceil().map(Value::Decimal).ok_or_else(overflow),
+        // 整数取整不变
+        (_, Value::Integer(_) | Value::BigInt(_)) => Ok(value.clone()),
+        ("FLOOR", Value::Float(f)) => Ok(Value::Float(f.floor())),
+        ("FLOOR", Value::Double(d)) => Ok(Value::Double(d.floor())),
+        (_, Value::Float(f)) => Ok(Value::Float(f.ceil())),
+        (_, Value::Double(d)) => Ok(Value::Double(d.ceil())),
+        (_, other) => Err(not_numeric(function, other)),
+    }
+}
+
+/// ROUND(x, digits)：四舍五入（远离零），结果与参数同类型
+fn round(value: &Value, digits: i32) -> Result<Value, ExecutionError> {
+    let scale = 10f64.powi(digits);
+    let round = |x: f64| (x * scale).round() / scale;
+    match value {
+        Value::Null => Ok(Value::Null),
+        Value::Integer(_) | Value::BigInt(_) if digits >= 0 => Ok(value.clone()),
+        Value::Integer(i) => Ok(Value::Integer(round(*i as f64) as i32)),
+        Value::BigInt(i) => Ok(Value::BigInt(round(*i as f64) as i64)),
+        Value::Float(f) => Ok(Value::Float(round(*f as f64) as f32)),
+        Value::Double(d) => Ok(Value::Double(round(*d))),
+        Value::Decimal(d) => d.round(digits).map(Value::Decimal).ok_or_else(|| ExecutionError::EvaluationError {
+            message: format!("DECIMAL overflow in ROUND({}, {})", d, digits),
+        }),
+        other => Err(not_numeric("ROUND", other)),
+    }
+}
+
+/// MOD(a, b)：两个整数时为整数余数（符号同被除数），否则按浮点计算
+fn modulo(left: &Value, right: &Value) -> Result<Value, ExecutionError> {
+    let division_by_zero = || ExecutionError::EvaluationError {
+        message: "Division by zero".to_string(),
+    };
+    match (left, right) {
+        (Value::Null, _) | (_, Value::Null) => Ok(Value::Null),
+        (Value::Integer(a), Value::Integer(b)) => {
+            if *b == 0 {
+                return Err(division_by_zero());
+            }
+            Ok(Value::Integer(a.wrapping_rem(*b)))
+        }
+        (Value::Integer(_) | Value::BigInt(_), Value::Integer(_) | Value::BigInt(_)) => {
+            let (a, b) = (as_i64(left), as_i64(right));
+            if b == 0 {
+                return Err(division_by_zero());
+            }
+            Ok(Value::BigInt(a.wrapping_rem(b)))
+        }
+        _ => match (to_f64("MOD", left)?, to_f64("MOD", right)?) {
+            (Some(_), Some(0.0)) => Err(division_by_zero()),
+            (Some(a), Some(b)) => Ok(Value::Double(a % b)),
+            _ => Ok(Value::Null),
+        },
+    }
+}
+
+fn as_i64(value: &Value) -> i64 {
+    match value {
+        Value::Integer(i) => *i as i64,
+        Value::BigInt(i) => *i,
+        _ => 0,
+    }
+}
+
+/// 数值参数转为 f64；NULL 为 None
+fn to_f64(function: &str, value: &Value) -> Result<Option<f64>, ExecutionError> {
+    match value {
+        Value::Null => Ok(None),
+        Value::Integer(i) => Ok(Some(*i as f64)),
+        Value::BigInt(i) => Ok(Some(*i as f64)),
+        Value::Float(f) => Ok(Some(*f as f64)),
+        Value::Double(d) => Ok(Some(*d)),
+        Value::Decimal(d) => Ok(Some(d.to_f64())),
+        other => Err(not_numeric(function, other)),
+    }
+}
+
+fn finite(function: &str, x: f64) -> Result<Value, ExecutionError> {
+    if x.is_finite() {
+        Ok(Value::Double(x))
+    } else {
+        Err(ExecutionError::EvaluationError {
+            message: format!("{} result is out of range", function),
+        })
+    }
+}
+
+fn not_numeric(function: &str, value: &Value) -> ExecutionError {
+    ExecutionError::TypeMismatch {
+        expected: format!("numeric argument for {}", function),
+        actual: format!("{:?}", value),
+    }
+}
+
+/// DECIMAL 与整数或 DECIMAL 的四则运算，结果为 DECIMAL
+///
+/// 加、减、乘是精确的，除法的小数位数见 [`Decimal::quotient_scale`]；溢出或除以零时报错。
+pub(crate) fn decimal_arithmetic(op: &BinaryOperator, left: &Value, right: &Value) -> Result<Value, ExecutionError> {
+    let as_decimal = |value: &Value| match value {
+        Value::Integer(i) => Some(Decimal::from(*i)),
+        Value::BigInt(i) => Some(Decimal::from(*i)),
+        Value::Decimal(d) => Some(*d),
+        _ => None,
+    };
+    let (Some(a), Some(b)) = (as_decimal(left), as_decimal(right)) else {
+        return Err(ExecutionError::TypeMismatch {
+            expected: "DECIMAL or integer operands".to_string(),
+            actual: format!("{} and {}", left.data_type(), right.data_type()),
+        });
+    };
+    let result = match op {
+        BinaryOperator::Add => a.checked_add(&b),
+        BinaryOperator::Subtract => a.checked_sub(&b),
+        BinaryOperator::Multiply => a.checked_mul(&b),
+        BinaryOperator::Divide if b.is_zero() => {
+            return Err(ExecutionError::EvaluationError { message: "Division by zero".to_string() });
+        }
+        BinaryOperator::Divide => a.checked_div(&b),
+        _ => {
+            return Err(ExecutionError::EvaluationError {
+                message: format!("Unsupported binary operator: {:?}", op),
+            })
+        }
+    };
+    result.map(Value::Decimal).ok_or_else(|| ExecutionError::EvaluationError {
+        message: format!("DECIMAL overflow in {} {:?} {}", a, op, b),
+    })
+}
+
+/// 日期/时间加上若干单位；Date 加上时、分、秒时提升为 Timestamp
+#[cfg(feature = "chrono")]
+pub(crate) fn date_add(value: &Value, amount: i64, unit: &str) -> Result<Value, ExecutionError> {
+    let (timestamp, is_date) = match value {
+        Value::Date(date) => (date.and_time(NaiveTime::MIN), true),
+        Value::Timestamp(ts) => (*ts, false),
+        other => return Err(ExecutionError::TypeMismatch {
+            expected: "DATE or TIMESTAMP".to_string(),
+            actual: format!("{:?}", other),
+        }),
+    };
+
+    let overflow = || ExecutionError::EvaluationError {
+        message: format!("date arithmetic out of range: {} {} {}", value, amount, unit),
+    };
+    let shifted = match unit {
+        "YEAR" | "MONTH" => {
+            let months = if unit == "YEAR" { amount.checked_mul(12).ok_or_else(overflow)? } else { amount };
+            let months = Months::new(u32::try_from(months.unsigned_abs()).map_err(|_| overflow())?);
+            if amount >= 0 {
+                timestamp.checked_add_months(months)
+            } else {
+                timestamp.checked_sub_months(months)
+            }
+        }
+        "DAY" => Duration::try_days(amount).and_then(|d| timestamp.checked_add_signed(d)),
+        "HOUR" => Duration::try_hours(amount).and_then(|d| timestamp.checked_add_signed(d)),
+        "MINUTE" => Duration::try_minutes(amount).and_then(|d| timestamp.checked_add_signed(d)),
+        "SECOND" => Duration::try_seconds(amount).and_then(|d| timestamp.checked_add_signed(d)),
+        other => return Err(ExecutionError::EvaluationError {
+            message: format!("unknown date unit '{}'", other),
+        }),
+    }
+    .ok_or_else(overflow)?;
+
+    if is_date && matches!(unit, "YEAR" | "MONTH" | "DAY") {
+        Ok(Value::Date(shifted.date()))
+    } else {
+        Ok(Value::Timestamp(shifted))
+    }
+}
+
+/// 两个日期/时间之差：DATE - DATE 为天数，涉及 TIMESTAMP 时为秒数
+#[cfg(feature = "chrono")]
+pub(crate) fn date_diff(left: &Value, right: &Value) -> Option<Value> {
+    match (left, right) {
+        (Value::Date(a), Value::Date(b)) => {
+            i32::try_from(a.signed_duration_since(*b).num_days()).ok().map(Value::Integer)
+        }
+        _ => {
+            let a = as_timestamp(left)?;
+            let b = as_timestamp(right)?;
+            Some(Value::BigInt(a.signed_duration_since(b).num_seconds()))
+        }
+    }
+}
+
+/// 将 DATE/TIMESTAMP 统一为 NaiveDateTime（DATE 取当天零点）
+#[cfg(feature = "chrono")]
+pub(crate) fn as_timestamp(value: &Value) -> Option<NaiveDateTime> {
+    match value {
+        Value::Date(date) => Some(date.and_time(NaiveTime::MIN)),
+        Value::Timestamp(ts) => Some(*ts),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "chrono")]
+fn extract(field: &str, value: &Value) -> Result<Value, ExecutionError> {
+    let ts = as_timestamp(value).ok_or_else(|| ExecutionError::TypeMismatch {
+        expected: "DATE or TIMESTAMP".to_string(),
+        actual: format!("{:?}", value),
+    })?;
+    let date: NaiveDate = ts.date();
+    let part = match field {
+        "YEAR" => date.year(),
+        "MONTH" => date.month() as i32,
+        "DAY" => date.day() as i32,
+        "HOUR" => ts.hour() as i32,
+        "MINUTE" => ts.minute() as i32,
+        "SECOND" => ts.second() as i32,
+        // 0 = 星期日，与 PostgreSQL 一致
+        "DOW" => date.weekday().num_days_from_sunday() as i32,
+        "DOY" => date.ordinal() as i32,
+        other => return Err(ExecutionError::EvaluationError {
+            message: format!("unknown EXTRACT field '{}'", other),
+        }),
+    };
+    Ok(Value::Integer(part))
+}
+
+/// 单位参数（如 'DAY'）统一为大写
+#[cfg(feature = "chrono")]
+fn unit_name(function: &str, value: &Value) -> Result<String, ExecutionError> {
+    match value {
+        Value::Varchar(unit) => Ok(unit.to_uppercase()),
+        other => Err(ExecutionError::TypeMismatch {
+            expected: format!("unit name for {}", function),
+            actual: format!("{:?}", other),
+        }),
+    }
+}
+
+fn expect_args(function: &str, args: &[Value], min: usize, max: usize) -> Result<(), ExecutionError> {
+    if args.len() < min || args.len() > max {
+        let expected = match max {
+            usize::MAX => format!("at least {}", min),
+            max if max == min => min.to_string(),
+            max => format!("{} to {}", min, max),
+        };
+        return Err(ExecutionError::EvaluationError {
+            message: format!("{} expects {} argument(s), got {}", function, expected, args.len()),
+        });
+    }
+    Ok(())
+}
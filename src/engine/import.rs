@@ -0,0 +1,315 @@
+//! COPY FROM 的文件解析与模式推断
+//!
+//! CSV 和 NDJSON 文件先统一解析为带行号的文本记录（`None` 表示 NULL），
+//! 再由执行器按目标列类型转换。目标表不存在且开启 INFER 时，
+//! 从前 [`INFER_SAMPLE_ROWS`] 条记录推断每列的类型和列名。
+
+use crate::engine::database::ExecutionError;
+use crate::sql::lexer::{Lexer, Token};
+use crate::types::{DataType, Value};
+
+/// 推断类型时采样的记录数
+pub(crate) const INFER_SAMPLE_ROWS: usize = 1000;
+
+/// COPY 出错时最多列出的行数
+pub(crate) const MAX_REPORTED_ERRORS: usize = 20;
+
+/// 文件中的一条记录
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Record {
+    /// 记录起始行号（从 1 开始）
+    pub line: usize,
+    pub fields: Vec<Option<String>>,
+}
+
+/// COPY 中出错的行：全部记录检查完后一起报告
+#[derive(Debug, Default)]
+pub(crate) struct LineErrors {
+    /// 前 [`MAX_REPORTED_ERRORS`] 个错误，格式为 "line N: 原因"
+    messages: Vec<String>,
+    count: usize,
+}
+
+impl LineErrors {
+    pub(crate) fn push(&mut self, line: usize, message: impl std::fmt::Display) {
+        if self.messages.len() < MAX_REPORTED_ERRORS {
+            self.messages.push(format!("line {}: {}", line, message));
+        }
+        self.count += 1;
+    }
+
+    /// 没有错误时为 Ok；只有一行出错时直接返回该行的错误
+    pub(crate) fn into_result(self) -> Result<(), ExecutionError> {
+        let message = match self.count {
+            0 => return Ok(()),
+            1 => self.messages.concat(),
+            count => {
+                let mut message = format!("{} lines rejected, nothing was copied: {}", count, self.messages.join("; "));
+                if count > self.messages.len() {
+                    message.push_str(&format!("; and {} more", count - self.messages.len()));
+                }
+                message
+            }
+        };
+        Err(ExecutionError::EvaluationError { message })
+    }
+}
+
+/// 解析 CSV 文本
+///
+/// 字段可以用双引号包围，引号内的 `""` 表示一个双引号，并可包含分隔符和换行。
+/// 未加引号的空字段为 NULL，`""` 为空字符串；空行被跳过。
+pub(crate) fn parse_csv(text: &str, delimiter: char) -> Result<Vec<Record>, ExecutionError> {
+    let mut records = Vec::new();
+    let mut fields: Vec<Option<String>> = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut record_line = 1;
+    let mut chars = text.chars().peekable();
+
+    let finish_field = |fields: &mut Vec<Option<String>>, field: &mut String, quoted: &mut bool| {
+        let value = std::mem::take(field);
+        fields.push(if value.is_empty() && !*quoted { None } else { Some(value) });
+        *quoted = false;
+    };
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                c => {
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    field.push(c);
+                }
+            }
+            continue;
+        }
+
+        match c {
+            '"' if field.is_empty() && !quoted => {
+                quoted = true;
+                in_quotes = true;
+            }
+            c if c == delimiter => finish_field(&mut fields, &mut field, &mut quoted),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                if !fields.is_empty() || !field.is_empty() || quoted {
+                    finish_field(&mut fields, &mut field, &mut quoted);
+                    records.push(Record { line: record_line, fields: std::mem::take(&mut fields) });
+                }
+                line += 1;
+                record_line = line;
+            }
+            c => field.push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err(ExecutionError::EvaluationError {
+            message: format!("line {}: unterminated quoted field", record_line),
+        });
+    }
+    if !fields.is_empty() || !field.is_empty() || quoted {
+        finish_field(&mut fields, &mut field, &mut quoted);
+        records.push(Record { line: record_line, fields });
+    }
+    Ok(records)
+}
+
+/// 解析 NDJSON 文本：每个非空行是一个 JSON 对象
+///
+/// 返回按首次出现顺序排列的键和按这些键取值的记录；缺少的键和 JSON null 为 NULL，
+/// 字符串取原文，其余值（数字、布尔、嵌套对象和数组）取其 JSON 文本。
+#[cfg(feature = "persistence")]
+pub(crate) fn parse_ndjson(text: &str) -> Result<(Vec<String>, Vec<Record>), ExecutionError> {
+    let mut keys: Vec<String> = Vec::new();
+    let mut objects = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let object = match serde_json::from_str::<serde_json::Value>(line) {
+            Ok(serde_json::Value::Object(object)) => object,
+            Ok(_) => {
+                return Err(ExecutionError::EvaluationError {
+                    message: format!("line {}: expected a JSON object", i + 1),
+                })
+            }
+            Err(e) => {
+                return Err(ExecutionError::EvaluationError {
+                    message: format!("line {}: invalid JSON: {}", i + 1, e),
+                })
+            }
+        };
+        for key in object.keys() {
+            if !keys.contains(key) {
+                keys.push(key.clone());
+            }
+        }
+        objects.push((i + 1, object));
+    }
+
+    let records = objects.into_iter()
+        .map(|(line, object)| Record {
+            line,
+            fields: keys.iter()
+                .map(|key| match object.get(key) {
+                    None | Some(serde_json::Value::Null) => None,
+                    Some(serde_json::Value::String(s)) => Some(s.clone()),
+                    Some(other) => Some(other.to_string()),
+                })
+                .collect(),
+        })
+        .collect();
+    Ok((keys, records))
+}
+
+/// 根据采样值推断列类型
+///
+/// 依次尝试 INTEGER、BIGINT、DOUBLE、BOOLEAN、DATE、TIMESTAMP，取能容纳全部非 NULL 值的第一个；
+/// 都不满足（或全部为 NULL）时为 VARCHAR，长度至少为 255。
+pub(crate) fn infer_type<'a>(values: impl IntoIterator<Item = &'a str>) -> DataType {
+    let mut candidates = vec![
+        DataType::Integer,
+        DataType::BigInt,
+        DataType::Double,
+        DataType::Boolean,
+        DataType::Date,
+        DataType::Timestamp,
+    ];
+    let mut max_len = 0;
+    let mut seen = false;
+    for value in values {
+        seen = true;
+        max_len = max_len.max(value.chars().count());
+        candidates.retain(|candidate| fits(value, candidate));
+    }
+    match candidates.first() {
+        Some(data_type) if seen => data_type.clone(),
+        _ => DataType::Varchar(max_len.max(255)),
+    }
+}
+
+/// 文本能否无损地解析为指定类型
+fn fits(value: &str, data_type: &DataType) -> bool {
+    let trimmed = value.trim();
+    match data_type {
+        // 只有形如 YYYY-MM-DD 的值算作日期，带时间的值留给 TIMESTAMP
+        DataType::Date if trimmed.len() != 10 => false,
+        // 字符串转浮点会接受 inf / NaN，这里要求至少含一个数字
+        DataType::Double if !trimmed.chars().any(|c| c.is_ascii_digit()) => false,
+        _ => Value::Varchar(value.to_string()).cast_to(data_type).is_ok(),
+    }
+}
+
+/// 推断出的列名：取表头（没有表头时为 column1、column2...），
+/// 非字母数字的字符替换为下划线，与关键字冲突或以数字开头的名称加下划线，重名时追加序号
+pub(crate) fn column_names(header: Option<&[Option<String>]>, count: usize) -> Vec<String> {
+    let mut names: Vec<String> = Vec::with_capacity(count);
+    for i in 0..count {
+        let raw = header
+            .and_then(|header| header.get(i).cloned().flatten())
+            .unwrap_or_default();
+        let mut name: String = raw.trim()
+            .chars()
+            .map(|c| if c.is_alphanumeric() || c == '_' { c } else { '_' })
+            .collect();
+        if name.is_empty() {
+            name = format!("column{}", i + 1);
+        }
+        if name.starts_with(|c: char| c.is_ascii_digit()) {
+            name.insert(0, '_');
+        }
+        if !is_plain_identifier(&name) {
+            name.push('_');
+        }
+        let base = name.clone();
+        let mut suffix = 2;
+        while names.contains(&name) {
+            name = format!("{}_{}", base, suffix);
+            suffix += 1;
+        }
+        names.push(name);
+    }
+    names
+}
+
+/// 名称是否会被词法分析为普通标识符（而不是关键字）
+fn is_plain_identifier(name: &str) -> bool {
+    matches!(Lexer::new(name).next_token(), Ok(Token::Identifier(ref ident)) if ident == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_errors() {
+        assert!(LineErrors::default().into_result().is_ok());
+
+        let mut errors = LineErrors::default();
+        errors.push(3, "bad value");
+        assert_eq!(errors.into_result().unwrap_err().to_string(), "Evaluation error: line 3: bad value");
+
+        let mut errors = LineErrors::default();
+        for line in 1..=MAX_REPORTED_ERRORS + 5 {
+            errors.push(line, "bad value");
+        }
+        let message = errors.into_result().unwrap_err().to_string();
+        assert!(message.contains(&format!("{} lines rejected", MAX_REPORTED_ERRORS + 5)), "{}", message);
+        assert!(message.ends_with("; and 5 more"), "{}", message);
+    }
+
+    #[test]
+    fn test_parse_csv_quoting() {
+        let records = parse_csv("a,b\r\n1,\"x, \"\"y\"\"\"\n\n2,\n3,\"\"\n\"multi\nline\",z", ',').unwrap();
+        let fields: Vec<_> = records.iter().map(|r| (r.line, r.fields.clone())).collect();
+        let some = |s: &str| Some(s.to_string());
+        assert_eq!(fields, vec![
+            (1, vec![some("a"), some("b")]),
+            (2, vec![some("1"), some("x, \"y\"")]),
+            (4, vec![some("2"), None]),
+            (5, vec![some("3"), some("")]),
+            (6, vec![some("multi\nline"), some("z")]),
+        ]);
+        assert!(parse_csv("1,\"open", ',').is_err());
+        assert_eq!(parse_csv("1;2", ';').unwrap()[0].fields, vec![some("1"), some("2")]);
+    }
+
+    #[test]
+    fn test_infer_type() {
+        assert_eq!(infer_type(["1", "-2", "30"]), DataType::Integer);
+        assert_eq!(infer_type(["1", "9000000000"]), DataType::BigInt);
+        assert_eq!(infer_type(["1", "2.5"]), DataType::Double);
+        assert_eq!(infer_type(["true", "no"]), DataType::Boolean);
+        assert_eq!(infer_type(["inf", "1"]), DataType::Varchar(255));
+        assert_eq!(infer_type(["abc", "1"]), DataType::Varchar(255));
+        assert_eq!(infer_type(std::iter::empty()), DataType::Varchar(255));
+        let long = "x".repeat(300);
+        assert_eq!(infer_type([long.as_str()]), DataType::Varchar(300));
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_infer_date_types() {
+        assert_eq!(infer_type(["2024-01-31", "2023-12-01"]), DataType::Date);
+        assert_eq!(infer_type(["2024-01-31", "2024-01-31 08:30:00"]), DataType::Timestamp);
+    }
+
+    #[test]
+    fn test_column_names() {
+        let header = [Some("First Name".to_string()), Some("select".to_string()), None, Some("2x".to_string()), Some("first_name".to_string())];
+        assert_eq!(
+            column_names(Some(&header), 6),
+            vec!["First_Name", "select_", "column3", "_2x", "first_name", "column6"]
+        );
+    }
+}
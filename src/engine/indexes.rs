@@ -0,0 +1,990 @@
+//! 二级索引的查找
+//!
+//! 索引元数据（[`IndexInfo`]）随表快照保存；索引结构本身在第一次用于查询或唯一性检查时由表的行建立并留在内存中，
+//! 同一组键列只有一份。每个索引记下建立时表行的修改戳（[`TableRows::stamp`](super::rows::TableRows::stamp)）：
+//! INSERT、UPDATE 和 DELETE 逐行修改表（包括 WAL 重放）时同时增删索引项并更新戳；其他修改（回滚、整表替换）使戳不同，
+//! 下次查找前重新建立。
+//!
+//! 键是索引列的值，按 [`IndexKey`] 的顺序保存在 B 树中，指向具有该键的各行的 rowid。
+//! 任一键列为 NULL 或 NaN 的行不进入索引：等值和比较条件不会匹配这些值，它们也不违反唯一约束。
+//! 查找先按等值条件定位键的前缀，再在下一个键列上按范围条件从下界开始顺序走过，越过上界即停止。
+//! 索引还保存各行 INCLUDE 列的值；查询只用到键列和 INCLUDE 列时由索引直接构造结果行（仅索引扫描），不读表数据。
+//!
+//! 部分索引（`CREATE INDEX ... WHERE 谓词`）只包含满足谓词的行，由调用方对行求值后决定是否加入。
+//! 只有 WHERE 蕴含索引谓词时查询才能用它（[`implies`]），否则会漏掉不在索引中的行。
+//!
+//! 有数据目录时，表快照写入后把已完成索引的结构写进该表的索引文件（[`encode_index_file`]），打开数据库时直接载入，
+//! 不必由表的行重建；文件标记了快照的堆文件版本并带校验和，版本不符或损坏时忽略并照常重建。
+
+use crate::engine::database::IndexInfo;
+use crate::sql::parser::{BinaryOperator, Expression};
+use crate::storage::index::IndexKey;
+use crate::types::{DataType, Schema, Tuple, Value};
+#[cfg(feature = "persistence")]
+use crate::utils;
+use crate::utils::serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Bound;
+
+/// 内存中的索引：键 -> rowid（按 rowid 递增）
+#[derive(Debug)]
+pub(crate) struct RowIndex {
+    /// 键列在表模式中的位置
+    columns: Vec<usize>,
+    /// 随索引保存的 INCLUDE 列在表模式中的位置
+    include: Vec<usize>,
+    /// 部分索引的谓词
+    where_clause: Option<Expression>,
+    /// 建立时表行的修改戳
+    stamp: u64,
+    entries: BTreeMap<IndexKey, Vec<u64>>,
+    /// 索引中各行的 INCLUDE 列值，按 rowid
+    included: HashMap<u64, Vec<Value>>,
+}
+
+impl RowIndex {
+    /// 由表的行及其 rowid 建立索引，同时保存 include 列的值；部分索引只传入满足谓词的行
+    pub(crate) fn build<'a>(
+        columns: Vec<usize>,
+        include: Vec<usize>,
+        where_clause: Option<Expression>,
+        rows: impl IntoIterator<Item = (&'a Tuple, u64)>,
+        stamp: u64,
+    ) -> Self {
+        let mut index = Self { columns, include, where_clause, stamp, entries: BTreeMap::new(), included: HashMap::new() };
+        for (row, row_id) in rows {
+            index.add(row, row_id);
+        }
+        index
+    }
+
+    /// 索引是否仍对应表的当前行和当前的键列，并保存了 include 中的各列
+    pub(crate) fn is_current(&self, columns: &[usize], include: &[usize], stamp: u64) -> bool {
+        self.stamp == stamp && self.columns == columns && include.iter().all(|column| self.include.contains(column))
+    }
+
+    /// 随索引保存的 INCLUDE 列
+    pub(crate) fn include(&self) -> &[usize] {
+        &self.include
+    }
+
+    /// 部分索引的谓词
+    pub(crate) fn where_clause(&self) -> Option<&Expression> {
+        self.where_clause.as_ref()
+    }
+
+    /// 编码为索引文件中的一节：键列和 INCLUDE 列的位置、按键顺序的各键及其 rowid、各行的 INCLUDE 列值
+    #[cfg(feature = "persistence")]
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        for positions in [&self.columns, &self.include] {
+            out.extend_from_slice(&(positions.len() as u32).to_le_bytes());
+            for &position in positions {
+                out.extend_from_slice(&(position as u32).to_le_bytes());
+            }
+        }
+        out.extend_from_slice(&(self.entries.len() as u32).to_le_bytes());
+        for (key, row_ids) in &self.entries {
+            put_bytes(&mut out, &serialize::encode_tuple(&Tuple::new(key.values().to_vec())));
+            out.extend_from_slice(&(row_ids.len() as u32).to_le_bytes());
+            for row_id in row_ids {
+                out.extend_from_slice(&row_id.to_le_bytes());
+            }
+        }
+        let mut included: Vec<_> = self.included.iter().collect();
+        included.sort_unstable_by_key(|(row_id, _)| **row_id);
+        out.extend_from_slice(&(included.len() as u32).to_le_bytes());
+        for (row_id, values) in included {
+            out.extend_from_slice(&row_id.to_le_bytes());
+            put_bytes(&mut out, &serialize::encode_tuple(&Tuple::new(values.clone())));
+        }
+        out
+    }
+
+    /// 由 [`encode`](Self::encode) 的结果恢复索引；谓词不写入文件，由调用方按索引元数据给出，戳为载入时表行的修改戳
+    #[cfg(feature = "persistence")]
+    pub(crate) fn decode(data: &[u8], where_clause: Option<Expression>, stamp: u64) -> Result<Self, String> {
+        let mut reader = IndexReader { data, offset: 0 };
+        let mut positions = || -> Result<Vec<usize>, String> {
+            let count = reader.u32()?;
+            (0..count).map(|_| reader.u32().map(|position| position as usize)).collect()
+        };
+        let columns = positions()?;
+        let include = positions()?;
+        let mut entries = BTreeMap::new();
+        for _ in 0..reader.u32()? {
+            let key = reader.tuple()?.values;
+            if key.len() != columns.len() {
+                return Err(format!("key with {} value(s) in an index on {} column(s)", key.len(), columns.len()));
+            }
+            let row_ids = (0..reader.u32()?).map(|_| reader.u64()).collect::<Result<Vec<u64>, _>>()?;
+            if row_ids.is_empty() || !row_ids.is_sorted() {
+                return Err(format!("row ids of key {:?} are empty or out of order", key));
+            }
+            entries.insert(IndexKey::new(key), row_ids);
+        }
+        let mut included = HashMap::new();
+        for _ in 0..reader.u32()? {
+            let row_id = reader.u64()?;
+            included.insert(row_id, reader.tuple()?.values);
+        }
+        if reader.offset != data.len() {
+            return Err(format!("{} trailing byte(s)", data.len() - reader.offset));
+        }
+        Ok(Self { columns, include, where_clause, stamp, entries, included })
+    }
+
+    /// 索引项数：索引中各行的 rowid 个数（部分索引只计满足谓词的行，键含 NULL 的行不计）
+    pub(crate) fn entry_count(&self) -> usize {
+        self.entries.values().map(Vec::len).sum()
+    }
+
+    /// 索引结构的字节数，即 [`encode`](Self::encode) 结果的长度，不实际生成编码
+    pub(crate) fn size_bytes(&self) -> usize {
+        let mut buffer = Vec::new();
+        let mut size = 4 * (3 + self.columns.len() + self.include.len()) + 4;
+        for (key, row_ids) in &self.entries {
+            buffer.clear();
+            serialize::encode_tuple_into(&Tuple::new(key.values().to_vec()), &mut buffer);
+            size += 4 + buffer.len() + 4 + 8 * row_ids.len();
+        }
+        for values in self.included.values() {
+            buffer.clear();
+            serialize::encode_tuple_into(&Tuple::new(values.clone()), &mut buffer);
+            size += 8 + 4 + buffer.len();
+        }
+        size
+    }
+
+    /// 有多行的某个键；唯一索引中出现时说明表数据违反了唯一约束
+    pub(crate) fn duplicate_key(&self) -> Option<&[Value]> {
+        self.entries.iter().find(|(_, row_ids)| row_ids.len() > 1).map(|(key, _)| key.values())
+    }
+
+    /// 键的前若干列等于 prefix、下一列落在 range 内的全部行的 rowid，按 rowid 递增
+    pub(crate) fn lookup(&self, prefix: &[Value], range: Option<&KeyRange>) -> Vec<u64> {
+        let mut row_ids: Vec<u64> = self.scan(prefix, range).flat_map(|(_, ids)| ids).copied().collect();
+        if prefix.len() < self.columns.len() {
+            row_ids.sort_unstable();
+        }
+        row_ids
+    }
+
+    /// 与 row 键值相同的行的 rowid；row 的键含 NULL 或 NaN 时为空
+    pub(crate) fn matching(&self, row: &Tuple) -> &[u64] {
+        row_key(row, &self.columns)
+            .and_then(|key| self.entries.get(&key))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// 把插入到表末尾的行加入索引；索引建立后表还被别处修改过（戳不是 previous_stamp）时不动，留待重建
+    pub(crate) fn insert(&mut self, row: &Tuple, row_id: u64, previous_stamp: u64, stamp: u64) {
+        if self.stamp != previous_stamp {
+            return;
+        }
+        self.add(row, row_id);
+        self.stamp = stamp;
+    }
+
+    /// 行由 old 改为 new：移去旧键下的索引项，加入新键；戳不是 previous_stamp 时不动，留待重建
+    pub(crate) fn update(&mut self, old: &Tuple, new: &Tuple, row_id: u64, previous_stamp: u64, stamp: u64) {
+        if self.stamp != previous_stamp {
+            return;
+        }
+        self.discard(old, row_id);
+        self.add(new, row_id);
+        self.stamp = stamp;
+    }
+
+    /// 表被修改但不涉及索引中的行（例如插入的行不满足部分索引的谓词）：只更新戳
+    pub(crate) fn advance(&mut self, previous_stamp: u64, stamp: u64) {
+        if self.stamp == previous_stamp {
+            self.stamp = stamp;
+        }
+    }
+
+    /// 移去被删除的行；戳不是 previous_stamp 时不动，留待重建
+    pub(crate) fn remove(&mut self, row: &Tuple, row_id: u64, previous_stamp: u64, stamp: u64) {
+        if self.stamp != previous_stamp {
+            return;
+        }
+        self.discard(row, row_id);
+        self.stamp = stamp;
+    }
+
+    fn add(&mut self, row: &Tuple, row_id: u64) {
+        let Some(key) = row_key(row, &self.columns) else {
+            return;
+        };
+        // 保持各键下的 rowid 递增；追加的行 rowid 最大，直接放在末尾
+        let row_ids = self.entries.entry(key).or_default();
+        if let Err(position) = row_ids.binary_search(&row_id) {
+            row_ids.insert(position, row_id);
+        }
+        if !self.include.is_empty() {
+            let values = self.include.iter()
+                .map(|&column| row.values.get(column).cloned().unwrap_or(Value::Null))
+                .collect();
+            self.included.insert(row_id, values);
+        }
+    }
+
+    fn discard(&mut self, row: &Tuple, row_id: u64) {
+        self.included.remove(&row_id);
+        let Some(key) = row_key(row, &self.columns) else {
+            return;
+        };
+        if let Some(row_ids) = self.entries.get_mut(&key) {
+            if let Ok(position) = row_ids.binary_search(&row_id) {
+                row_ids.remove(position);
+            }
+            if row_ids.is_empty() {
+                self.entries.remove(&key);
+            }
+        }
+    }
+
+    /// 与 [`lookup`](Self::lookup) 相同的行，由索引中的键列和 INCLUDE 列值构造，按 rowid 递增
+    ///
+    /// 行宽为 width，其余列为 NULL；调用方须确认查询只用到键列和 INCLUDE 列。
+    pub(crate) fn lookup_rows(&self, prefix: &[Value], range: Option<&KeyRange>, width: usize) -> Vec<(u64, Tuple)> {
+        let mut rows = Vec::new();
+        for (key, row_ids) in self.scan(prefix, range) {
+            for &row_id in row_ids {
+                let mut values = vec![Value::Null; width];
+                for (&column, value) in self.columns.iter().zip(key.values()) {
+                    values[column] = value.clone();
+                }
+                for (&column, value) in self.include.iter().zip(self.included.get(&row_id).into_iter().flatten()) {
+                    values[column] = value.clone();
+                }
+                rows.push((row_id, Tuple::new(values)));
+            }
+        }
+        if prefix.len() < self.columns.len() {
+            rows.sort_unstable_by_key(|(row_id, _)| *row_id);
+        }
+        rows
+    }
+
+    /// 键的前若干列等于 prefix、下一列落在 range 内的行数
+    pub(crate) fn count(&self, prefix: &[Value], range: Option<&KeyRange>) -> usize {
+        self.scan(prefix, range).map(|(_, ids)| ids.len()).sum()
+    }
+
+    /// 按键的顺序走过满足条件的各键：从前缀和范围下界处开始，前缀不同或越过上界即停止
+    fn scan<'a>(
+        &'a self,
+        prefix: &'a [Value],
+        range: Option<&'a KeyRange>,
+    ) -> impl Iterator<Item = (&'a IndexKey, &'a Vec<u64>)> + 'a {
+        let depth = prefix.len();
+        let mut start = prefix.to_vec();
+        if let Some(Bound::Included(value) | Bound::Excluded(value)) = range.map(|range| &range.lower) {
+            start.push(value.clone());
+        }
+        let next_value = move |key: &'a IndexKey| key.values().get(depth);
+        self.entries.range(IndexKey::new(start)..)
+            .take_while(move |(key, _)| {
+                key.values().len() >= depth
+                    && key.values()[..depth] == *prefix
+                    && range.is_none_or(|range| next_value(key).is_some_and(|value| range.below_upper(value)))
+            })
+            // 下界不含时跳过等于下界的键，它们排在最前面
+            .skip_while(move |(key, _)| range.is_some_and(|range| !next_value(key).is_some_and(|value| range.above_lower(value))))
+    }
+}
+
+/// 行在索引列上的键；含 NULL 或 NaN 时为 None
+fn row_key(row: &Tuple, columns: &[usize]) -> Option<IndexKey> {
+    let values: Vec<Value> = columns.iter()
+        .map(|&column| row.values.get(column).cloned().unwrap_or(Value::Null))
+        .collect();
+    if values.iter().any(|value| matches!(value, Value::Null) || is_nan(value)) {
+        return None;
+    }
+    Some(IndexKey::new(values))
+}
+
+fn is_nan(value: &Value) -> bool {
+    match value {
+        Value::Float(f) => f.is_nan(),
+        Value::Double(d) => d.is_nan(),
+        _ => false,
+    }
+}
+
+/// 索引文件开头的标识和格式版本
+#[cfg(feature = "persistence")]
+const INDEX_FILE_MAGIC: &[u8; 8] = b"MDBINDEX";
+#[cfg(feature = "persistence")]
+const INDEX_FILE_VERSION: u32 = 1;
+
+/// 把一张表各索引编码后的节写成索引文件
+///
+/// 文件依次是标识、版本、表快照的堆文件版本、节数，每节为索引名和 [`RowIndex::encode`] 的结果，末尾是
+/// 之前全部字节的 CRC32。堆文件版本使较早快照留下的索引文件不会被用于较新的表数据。
+#[cfg(feature = "persistence")]
+pub(crate) fn encode_index_file(generation: u64, sections: &[(String, Vec<u8>)]) -> Vec<u8> {
+    let mut out = INDEX_FILE_MAGIC.to_vec();
+    out.extend_from_slice(&INDEX_FILE_VERSION.to_le_bytes());
+    out.extend_from_slice(&generation.to_le_bytes());
+    out.extend_from_slice(&(sections.len() as u32).to_le_bytes());
+    for (name, section) in sections {
+        put_bytes(&mut out, name.as_bytes());
+        put_bytes(&mut out, section);
+    }
+    let checksum = utils::checksum(&out);
+    out.extend_from_slice(&checksum.to_le_bytes());
+    out
+}
+
+/// 校验索引文件并取出各节，按索引名；文件损坏、格式版本不同或不是为 generation 版本的堆文件写的时报告原因
+#[cfg(feature = "persistence")]
+pub(crate) fn decode_index_file(data: &[u8], generation: u64) -> Result<HashMap<String, &[u8]>, String> {
+    let (body, checksum) = data.split_last_chunk::<4>().ok_or("file is truncated")?;
+    if utils::checksum(body) != u32::from_le_bytes(*checksum) {
+        return Err("checksum mismatch".to_string());
+    }
+    let mut reader = IndexReader { data: body, offset: 0 };
+    if reader.take(INDEX_FILE_MAGIC.len())? != INDEX_FILE_MAGIC {
+        return Err("not an index file".to_string());
+    }
+    let version = reader.u32()?;
+    if version != INDEX_FILE_VERSION {
+        return Err(format!("unsupported format version {}", version));
+    }
+    let written_for = reader.u64()?;
+    if written_for != generation {
+        return Err(format!("written for table file version {}, the table is at version {}", written_for, generation));
+    }
+    let mut sections = HashMap::new();
+    for _ in 0..reader.u32()? {
+        let name = String::from_utf8(reader.bytes()?.to_vec()).map_err(|_| "index name is not UTF-8".to_string())?;
+        sections.insert(name, reader.bytes()?);
+    }
+    Ok(sections)
+}
+
+/// 写入带 u32 长度前缀的字节串
+#[cfg(feature = "persistence")]
+fn put_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    out.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+    out.extend_from_slice(bytes);
+}
+
+/// 按顺序读取索引文件的字段，数据不足时报告截断位置
+#[cfg(feature = "persistence")]
+struct IndexReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+#[cfg(feature = "persistence")]
+impl<'a> IndexReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let bytes = self.data.get(self.offset..self.offset.saturating_add(len))
+            .ok_or_else(|| format!("unexpected end of data at byte {}", self.offset))?;
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().expect("4 bytes")))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().expect("8 bytes")))
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8], String> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+
+    fn tuple(&mut self) -> Result<Tuple, String> {
+        let offset = self.offset;
+        serialize::decode_tuple(self.bytes()?).map_err(|e| format!("bad value at byte {}: {}", offset, e))
+    }
+}
+
+/// 键列上的取值范围，端点已转换为列类型
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct KeyRange {
+    pub lower: Bound<Value>,
+    pub upper: Bound<Value>,
+}
+
+impl KeyRange {
+    fn above_lower(&self, value: &Value) -> bool {
+        match &self.lower {
+            Bound::Included(lower) => value >= lower,
+            Bound::Excluded(lower) => value > lower,
+            Bound::Unbounded => true,
+        }
+    }
+
+    fn below_upper(&self, value: &Value) -> bool {
+        match &self.upper {
+            Bound::Included(upper) => value <= upper,
+            Bound::Excluded(upper) => value < upper,
+            Bound::Unbounded => true,
+        }
+    }
+
+    /// 是否包含 other 中的全部值
+    fn contains(&self, other: &KeyRange) -> bool {
+        let lower = match (&self.lower, &other.lower) {
+            (Bound::Unbounded, _) => true,
+            (_, Bound::Unbounded) => false,
+            (Bound::Excluded(a), Bound::Included(b)) => a < b,
+            (Bound::Included(a) | Bound::Excluded(a), Bound::Included(b) | Bound::Excluded(b)) => a <= b,
+        };
+        let upper = match (&self.upper, &other.upper) {
+            (Bound::Unbounded, _) => true,
+            (_, Bound::Unbounded) => false,
+            (Bound::Excluded(a), Bound::Included(b)) => a > b,
+            (Bound::Included(a) | Bound::Excluded(a), Bound::Included(b) | Bound::Excluded(b)) => a >= b,
+        };
+        lower && upper
+    }
+
+    /// 与另一范围的交集：各取较紧的端点
+    fn intersect(self, other: KeyRange) -> KeyRange {
+        let lower = match (&self.lower, &other.lower) {
+            (Bound::Unbounded, _) => other.lower,
+            (_, Bound::Unbounded) => self.lower,
+            (Bound::Included(a) | Bound::Excluded(a), Bound::Included(b) | Bound::Excluded(b)) => {
+                if a > b || (a == b && matches!(self.lower, Bound::Excluded(_))) { self.lower } else { other.lower }
+            }
+        };
+        let upper = match (&self.upper, &other.upper) {
+            (Bound::Unbounded, _) => other.upper,
+            (_, Bound::Unbounded) => self.upper,
+            (Bound::Included(a) | Bound::Excluded(a), Bound::Included(b) | Bound::Excluded(b)) => {
+                if a < b || (a == b && matches!(self.upper, Bound::Excluded(_))) { self.upper } else { other.upper }
+            }
+        };
+        KeyRange { lower, upper }
+    }
+}
+
+/// 一次索引查找：用哪个索引、键列位置、要匹配的键前缀和下一键列上的范围
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct IndexProbe {
+    pub index: String,
+    /// 索引全部键列在表模式中的位置
+    pub columns: Vec<usize>,
+    /// INCLUDE 列在表模式中的位置
+    pub include: Vec<usize>,
+    /// 已转换为列类型的等值键前缀；None 表示条件不可能成立（例如超出 INTEGER 范围的常量），不必查找
+    pub key: Option<Vec<Value>>,
+    /// 紧接键前缀的下一个键列上的范围（`>`、`<=`、BETWEEN 等）；None 表示只按键前缀查找
+    pub range: Option<KeyRange>,
+    /// 参与查找的条件，用于 EXPLAIN，例如 `email = 'a@x'` 或 `age > 30`
+    pub condition: String,
+    /// 同一条件的表达式，用于执行计划中的索引扫描节点
+    pub predicate: Expression,
+    /// 部分索引的谓词；索引只包含满足它的行
+    pub where_clause: Option<Expression>,
+}
+
+/// 可回答 WHERE 的索引查找
+///
+/// WHERE 中用 AND 连接的 `列 = 常量` 条件覆盖某个已建好的索引的最左若干列，或在第一个没有等值条件的键列上
+/// 有比较或 BETWEEN 条件时可用；部分索引还要求 WHERE 蕴含其谓词。按等值覆盖的列数从多到少、有范围的在前、唯一索引在前排列；
+/// 由调用方按各查找取出的行数选定其一。找到的行仍需对整个 WHERE 求值，索引只负责缩小范围。
+pub(crate) fn index_candidates(indexes: &[IndexInfo], schema: &Schema, where_clause: &Expression) -> Vec<IndexProbe> {
+    let mut conditions = Vec::new();
+    collect_conditions(where_clause, &mut conditions);
+    if conditions.is_empty() {
+        return Vec::new();
+    }
+
+    let mut candidates: Vec<(usize, bool, bool, IndexProbe)> = Vec::new();
+    for index in indexes.iter().filter(|index| index.build.is_none()) {
+        if index.where_clause.as_ref().is_some_and(|predicate| !implies(where_clause, predicate, schema)) {
+            continue;
+        }
+        let Some(columns) = index.columns.iter()
+            .map(|name| schema.columns.iter().position(|column| column.name == *name))
+            .collect::<Option<Vec<usize>>>()
+        else {
+            continue;
+        };
+
+        let mut key = Some(Vec::new());
+        let mut range: Option<KeyRange> = None;
+        let mut equalities = 0;
+        let mut used: Vec<&Condition> = Vec::new();
+        for (&column, name) in columns.iter().zip(&index.columns) {
+            let data_type = &schema.columns[column].data_type;
+            let equality = conditions.iter().find_map(|condition| match &condition.kind {
+                ConditionKind::Equal(value) if condition.column == *name => {
+                    key_value(value, data_type).map(|key| (key, condition))
+                }
+                _ => None,
+            });
+            if let Some((value, condition)) = equality {
+                used.push(condition);
+                equalities += 1;
+                match (&mut key, value) {
+                    (Some(key), Some(value)) => key.push(value),
+                    _ => key = None,
+                }
+                continue;
+            }
+
+            // 第一个没有等值条件的键列上可以再用范围条件，之后的键列不再有序
+            for condition in conditions.iter().filter(|condition| condition.column == *name) {
+                if let ConditionKind::Range(bounds) = &condition.kind {
+                    if let Some(bounds) = key_range(bounds, data_type) {
+                        range = Some(match range.take() {
+                            Some(range) => range.intersect(bounds),
+                            None => bounds,
+                        });
+                        used.push(condition);
+                    }
+                }
+            }
+            break;
+        }
+        if used.is_empty() {
+            continue;
+        }
+
+        let predicate = used.iter()
+            .map(|condition| condition.expr.clone())
+            .reduce(|left, right| Expression::BinaryOp { left: Box::new(left), op: BinaryOperator::And, right: Box::new(right) })
+            .expect("at least one condition");
+        let condition = used.iter().map(|condition| condition.text.as_str()).collect::<Vec<_>>().join(" AND ");
+        let has_range = range.is_some();
+        let include = index.include.iter()
+            .filter_map(|name| schema.columns.iter().position(|column| column.name == *name))
+            .collect();
+        let probe = IndexProbe {
+            index: index.name.clone(),
+            columns,
+            include,
+            key,
+            range,
+            condition,
+            predicate,
+            where_clause: index.where_clause.clone(),
+        };
+        candidates.push((equalities, has_range, index.unique, probe));
+    }
+    // 稳定排序：同等条件下保持索引的定义顺序
+    candidates.sort_by_key(|(equalities, has_range, unique, _)| std::cmp::Reverse((*equalities, *has_range, *unique)));
+    candidates.into_iter().map(|(_, _, _, probe)| probe).collect()
+}
+
+/// WHERE 是否蕴含部分索引的谓词：谓词中用 AND 连接的每个条件都由 WHERE 保证
+///
+/// 判断是保守的，不确定时为 false。一个条件由 WHERE 保证，当且仅当它与 WHERE 中某个 AND 条件相同；
+/// 或者是 `列 IS NOT NULL`，而 WHERE 对该列有与常量的比较；或者是与常量的比较或 BETWEEN，
+/// 而 WHERE 对该列的各条件的交集落在其范围内（浮点列除外，理由同 [`key_range`]）。
+pub(crate) fn implies(where_clause: &Expression, predicate: &Expression, schema: &Schema) -> bool {
+    let mut given = Vec::new();
+    conjuncts(where_clause, &mut given);
+    let mut conditions = Vec::new();
+    collect_conditions(where_clause, &mut conditions);
+    let mut required = Vec::new();
+    conjuncts(predicate, &mut required);
+
+    required.into_iter().all(|required| {
+        if given.contains(&required) {
+            return true;
+        }
+        if let Expression::IsNotNull(operand) = required {
+            return matches!(operand.as_ref(), Expression::Column(column) if conditions.iter().any(|condition| condition.column == *column));
+        }
+        let mut own = Vec::new();
+        collect_conditions(required, &mut own);
+        let [condition] = own.as_slice() else {
+            return false;
+        };
+        let Some(data_type) = schema.columns.iter()
+            .find(|column| column.name == condition.column)
+            .map(|column| &column.data_type)
+        else {
+            return false;
+        };
+        let Some(bounds) = exact_range(&condition.kind, data_type) else {
+            return false;
+        };
+        // WHERE 一侧的端点不能转换时放开，只会使包含关系更难成立
+        conditions.iter()
+            .filter(|given| given.column == condition.column)
+            .filter_map(|given| match &given.kind {
+                ConditionKind::Equal(value) => key_value(value, data_type).flatten()
+                    .map(|value| KeyRange { lower: Bound::Included(value.clone()), upper: Bound::Included(value) }),
+                ConditionKind::Range(range) => key_range(range, data_type),
+            })
+            .reduce(KeyRange::intersect)
+            .is_some_and(|range| bounds.contains(&range))
+    })
+}
+
+/// AND 连接的各个条件
+fn conjuncts<'a>(expr: &'a Expression, out: &mut Vec<&'a Expression>) {
+    match expr {
+        Expression::BinaryOp { left, op: BinaryOperator::And, right } => {
+            conjuncts(left, out);
+            conjuncts(right, out);
+        }
+        other => out.push(other),
+    }
+}
+
+/// 索引谓词中一个条件的范围，端点须能原样转换为列类型；浮点列没有范围
+fn exact_range(kind: &ConditionKind, data_type: &DataType) -> Option<KeyRange> {
+    if matches!(data_type, DataType::Float | DataType::Double) {
+        return None;
+    }
+    let convert = |bound: &Bound<Value>| match bound {
+        Bound::Included(value) => key_value(value, data_type).flatten().map(Bound::Included),
+        Bound::Excluded(value) => key_value(value, data_type).flatten().map(Bound::Excluded),
+        Bound::Unbounded => Some(Bound::Unbounded),
+    };
+    match kind {
+        ConditionKind::Equal(value) => {
+            let value = key_value(value, data_type).flatten()?;
+            Some(KeyRange { lower: Bound::Included(value.clone()), upper: Bound::Included(value) })
+        }
+        ConditionKind::Range(range) => Some(KeyRange { lower: convert(&range.lower)?, upper: convert(&range.upper)? }),
+    }
+}
+
+/// WHERE 中一个可由索引回答的条件
+struct Condition {
+    column: String,
+    kind: ConditionKind,
+    /// 条件文本，用于 EXPLAIN
+    text: String,
+    expr: Expression,
+}
+
+enum ConditionKind {
+    /// `列 = 常量`
+    Equal(Value),
+    /// `列 < 常量` 等比较或 `列 BETWEEN 常量 AND 常量`，端点尚未转换为列类型
+    Range(KeyRange),
+}
+
+/// 收集 AND 连接的 `列 op 常量` / `常量 op 列`（op 为 = < <= > >=）和 `列 BETWEEN 常量 AND 常量`
+fn collect_conditions(expr: &Expression, conditions: &mut Vec<Condition>) {
+    match expr {
+        Expression::BinaryOp { left, op: BinaryOperator::And, right } => {
+            collect_conditions(left, conditions);
+            collect_conditions(right, conditions);
+        }
+        Expression::BinaryOp { left, op, right } => {
+            // 常量在左边时把比较方向反过来：`5 < age` 即 `age > 5`
+            let (column, constant, op) = match (left.as_ref(), right.as_ref()) {
+                (Expression::Column(column), constant) => (column, constant, op.clone()),
+                (constant, Expression::Column(column)) => (column, constant, match op {
+                    BinaryOperator::LessThan => BinaryOperator::GreaterThan,
+                    BinaryOperator::LessEqual => BinaryOperator::GreaterEqual,
+                    BinaryOperator::GreaterThan => BinaryOperator::LessThan,
+                    BinaryOperator::GreaterEqual => BinaryOperator::LessEqual,
+                    op => op.clone(),
+                }),
+                _ => return,
+            };
+            let Some(value) = constant_value(constant) else {
+                return;
+            };
+            let (symbol, kind) = match op {
+                BinaryOperator::Equal => ("=", ConditionKind::Equal(value.clone())),
+                BinaryOperator::LessThan => ("<", ConditionKind::Range(KeyRange { lower: Bound::Unbounded, upper: Bound::Excluded(value.clone()) })),
+                BinaryOperator::LessEqual => ("<=", ConditionKind::Range(KeyRange { lower: Bound::Unbounded, upper: Bound::Included(value.clone()) })),
+                BinaryOperator::GreaterThan => (">", ConditionKind::Range(KeyRange { lower: Bound::Excluded(value.clone()), upper: Bound::Unbounded })),
+                BinaryOperator::GreaterEqual => (">=", ConditionKind::Range(KeyRange { lower: Bound::Included(value.clone()), upper: Bound::Unbounded })),
+                _ => return,
+            };
+            let text = format!("{} {} {}", column, symbol, value);
+            conditions.push(Condition { column: column.clone(), kind, text, expr: expr.clone() });
+        }
+        Expression::Between { expr: operand, low, high, negated: false } => {
+            let (Expression::Column(column), Some(low), Some(high)) = (operand.as_ref(), constant_value(low), constant_value(high)) else {
+                return;
+            };
+            let text = format!("{} BETWEEN {} AND {}", column, low, high);
+            let kind = ConditionKind::Range(KeyRange { lower: Bound::Included(low), upper: Bound::Included(high) });
+            conditions.push(Condition { column: column.clone(), kind, text, expr: expr.clone() });
+        }
+        _ => {}
+    }
+}
+
+/// 字面量，或对字面量的 CAST
+fn constant_value(expr: &Expression) -> Option<Value> {
+    match expr {
+        Expression::Literal(value) => Some(value.clone()),
+        Expression::Cast { expr, data_type } => match expr.as_ref() {
+            Expression::Literal(value) => value.cast_to(data_type).ok(),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// 把常量转换为键列类型的键值
+///
+/// 返回 None 表示不能用索引回答（类型不同，比较语义与键的顺序不一致）；
+/// Some(None) 表示没有行能等于该常量（超出列类型的范围或为 NaN）。
+fn key_value(value: &Value, data_type: &DataType) -> Option<Option<Value>> {
+    match (value, data_type) {
+        (Value::Null, _) => None,
+        (value, _) if is_nan(value) => Some(None),
+        (Value::Integer(i), DataType::BigInt) => Some(Some(Value::BigInt(*i as i64))),
+        (Value::BigInt(i), DataType::Integer) => Some(i32::try_from(*i).ok().map(Value::Integer)),
+        (Value::Varchar(_), DataType::Varchar(_) | DataType::Text) => Some(Some(value.clone())),
+        // CHAR 键是补齐空格后的值，常量同样补齐；去掉尾部空格仍超长的不等于任何键
+        (Value::Varchar(s), DataType::Char(len)) if s.trim_end_matches(' ').chars().count() > *len => Some(None),
+        (Value::Varchar(_), DataType::Char(_)) => Some(value.cast_to(data_type).ok()),
+        // 字符串常量按 UUID 解析，解析不了的不等于任何 UUID
+        (Value::Varchar(s), DataType::Uuid) => Some(s.parse().ok().map(Value::Uuid)),
+        (Value::Varchar(s), DataType::Json) => Some(crate::types::JsonValue::normalize(s).ok().map(Value::Json)),
+        (Value::Varchar(_), DataType::Date | DataType::Timestamp) => Some(value.cast_to(data_type).ok()),
+        // DECIMAL 键按数值比较，小数位数不同也无妨；浮点数不能原样转换
+        (Value::Decimal(_), DataType::Decimal(..)) => Some(Some(value.clone())),
+        (Value::Integer(i), DataType::Decimal(..)) => Some(Some(Value::Decimal((*i).into()))),
+        (Value::BigInt(i), DataType::Decimal(..)) => Some(Some(Value::Decimal((*i).into()))),
+        (value, data_type) if value.data_type() == *data_type => Some(Some(value.clone())),
+        _ => None,
+    }
+}
+
+/// 把范围的端点转换为键列类型；不能转换的端点放开（行仍按整个 WHERE 求值），两端都放开时不可用
+///
+/// 浮点列不用范围：比较 NaN 时按相等处理，`x >= 1.0` 对 NaN 成立，而 NaN 不在索引中。
+/// CHAR 列也不用范围：比较时忽略尾部空格，与补齐空格后的键的顺序不完全一致。
+fn key_range(range: &KeyRange, data_type: &DataType) -> Option<KeyRange> {
+    if matches!(data_type, DataType::Float | DataType::Double | DataType::Char(_)) {
+        return None;
+    }
+    let convert = |bound: &Bound<Value>| match bound {
+        Bound::Included(value) => key_value(value, data_type).flatten().map_or(Bound::Unbounded, Bound::Included),
+        Bound::Excluded(value) => key_value(value, data_type).flatten().map_or(Bound::Unbounded, Bound::Excluded),
+        Bound::Unbounded => Bound::Unbounded,
+    };
+    let range = KeyRange { lower: convert(&range.lower), upper: convert(&range.upper) };
+    (range.lower != Bound::Unbounded || range.upper != Bound::Unbounded).then_some(range)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql::parse_sql;
+    use crate::sql::parser::Statement;
+    use crate::types::ColumnDefinition;
+
+    fn where_clause(sql: &str) -> Expression {
+        match parse_sql(sql).unwrap() {
+            Statement::Select { where_clause: Some(expr), .. } => expr,
+            other => panic!("Expected SELECT with WHERE, got {:?}", other),
+        }
+    }
+
+    fn index(name: &str, columns: &[&str], unique: bool) -> IndexInfo {
+        IndexInfo { name: name.to_string(), columns: columns.iter().map(|c| c.to_string()).collect(), unique, include: Vec::new(), where_clause: None, build: None }
+    }
+
+    #[test]
+    fn test_choose_index() {
+        let schema = Schema::new(vec![
+            ColumnDefinition { name: "a".to_string(), data_type: DataType::Integer, nullable: true, default: None, default_function: None },
+            ColumnDefinition { name: "b".to_string(), data_type: DataType::BigInt, nullable: true, default: None, default_function: None },
+            ColumnDefinition { name: "c".to_string(), data_type: DataType::Varchar(10), nullable: true, default: None, default_function: None },
+        ]);
+        let indexes = vec![index("t_a", &["a"], false), index("t_ab", &["a", "b"], false), index("t_c", &["c"], true)];
+
+        let best = |sql: &str| index_candidates(&indexes, &schema, &where_clause(sql)).into_iter().next();
+
+        let candidates = index_candidates(&indexes, &schema, &where_clause("SELECT * FROM t WHERE b = 2 AND a = 1"));
+        assert_eq!(candidates.iter().map(|probe| probe.index.as_str()).collect::<Vec<_>>(), vec!["t_ab", "t_a"]);
+        let probe = &candidates[0];
+        assert_eq!(probe.index, "t_ab");
+        assert_eq!(probe.key, Some(vec![Value::Integer(1), Value::BigInt(2)]));
+        assert_eq!(probe.condition, "a = 1 AND b = 2");
+        assert!(matches!(probe.predicate, Expression::BinaryOp { op: BinaryOperator::And, .. }));
+
+        let probe = best("SELECT * FROM t WHERE 'x' = c AND a > 1").unwrap();
+        assert_eq!(probe.index, "t_c");
+
+        // 只能用最左列：b 单独出现时不能用 (a, b)
+        assert!(best("SELECT * FROM t WHERE b = 2").is_none());
+        assert!(best("SELECT * FROM t WHERE a = 1 OR c = 'x'").is_none());
+        assert!(best("SELECT * FROM t WHERE c = 1").is_none());
+
+        let probe = best("SELECT * FROM t WHERE a = CAST('9999999999' AS BIGINT)").unwrap();
+        assert_eq!(probe.key, None);
+
+        // 等值前缀之后的键列上的范围，多个范围取交集
+        let probe = best("SELECT * FROM t WHERE a = 1 AND b > 5 AND 9 >= b").unwrap();
+        assert_eq!(probe.index, "t_ab");
+        assert_eq!(probe.key, Some(vec![Value::Integer(1)]));
+        assert_eq!(probe.range, Some(KeyRange { lower: Bound::Excluded(Value::BigInt(5)), upper: Bound::Included(Value::BigInt(9)) }));
+        assert_eq!(probe.condition, "a = 1 AND b > 5 AND b <= 9");
+
+        let probe = best("SELECT * FROM t WHERE a BETWEEN 3 AND 7").unwrap();
+        assert_eq!(probe.index, "t_a");
+        assert_eq!(probe.key, Some(vec![]));
+        assert_eq!(probe.range, Some(KeyRange { lower: Bound::Included(Value::Integer(3)), upper: Bound::Included(Value::Integer(7)) }));
+        assert!(best("SELECT * FROM t WHERE a NOT BETWEEN 3 AND 7").is_none());
+        assert!(best("SELECT * FROM t WHERE a <> 3").is_none());
+        // 范围之后的键列不再有序，b 上的等值条件不参与 (a, b) 的查找
+        let probe = best("SELECT * FROM t WHERE a < 3 AND b = 2").unwrap();
+        assert_eq!(probe.condition, "a < 3");
+    }
+
+    #[test]
+    fn test_partial_index_implication() {
+        let schema = Schema::new(vec![
+            ColumnDefinition { name: "a".to_string(), data_type: DataType::Integer, nullable: true, default: None, default_function: None },
+            ColumnDefinition { name: "b".to_string(), data_type: DataType::BigInt, nullable: true, default: None, default_function: None },
+            ColumnDefinition { name: "c".to_string(), data_type: DataType::Varchar(10), nullable: true, default: None, default_function: None },
+            ColumnDefinition { name: "d".to_string(), data_type: DataType::Double, nullable: true, default: None, default_function: None },
+        ]);
+        let implied = |query: &str, predicate: &str| {
+            implies(
+                &where_clause(&format!("SELECT * FROM t WHERE {}", query)),
+                &where_clause(&format!("SELECT * FROM t WHERE {}", predicate)),
+                &schema,
+            )
+        };
+
+        // 相同的条件，或谓词的每个 AND 条件都出现在 WHERE 中
+        assert!(implied("c = 'open' AND a = 1", "c = 'open'"));
+        assert!(implied("a = 1 AND (c = 'x' OR c = 'y')", "c = 'x' OR c = 'y'"));
+        assert!(!implied("a = 1", "c = 'open'"));
+        assert!(!implied("c = 'open' OR a = 1", "c = 'open'"));
+        assert!(!implied("c = 'open'", "c = 'open' AND a > 0"));
+
+        // WHERE 对该列的范围落在谓词的范围内
+        assert!(implied("b = 5", "b > 0"));
+        assert!(implied("b BETWEEN 3 AND 7 AND b < 6", "b >= 3 AND b <= 6"));
+        assert!(implied("a >= 10", "a > 5"));
+        assert!(!implied("a >= 5", "a > 5"));
+        assert!(!implied("a < 10", "a > 5"));
+        assert!(implied("a = 3", "a BETWEEN 1 AND 3"));
+        assert!(!implied("a = 3", "a NOT BETWEEN 1 AND 3"));
+        assert!(implied("c = 'b'", "c > 'a'"));
+
+        // 比较隐含不为 NULL
+        assert!(implied("a > 1", "a IS NOT NULL"));
+        assert!(!implied("b > 1", "a IS NOT NULL"));
+
+        // 浮点列只认相同的条件
+        assert!(!implied("d > 2.0", "d > 1.0"));
+        assert!(implied("d > 1.0", "d > 1.0"));
+
+        // 只有被蕴含的部分索引才是候选
+        let mut partial = index("t_a_open", &["a"], false);
+        partial.where_clause = Some(where_clause("SELECT * FROM t WHERE c = 'open'"));
+        let candidates = |sql: &str| index_candidates(std::slice::from_ref(&partial), &schema, &where_clause(sql));
+        assert!(candidates("SELECT * FROM t WHERE a = 1").is_empty());
+        let probe = candidates("SELECT * FROM t WHERE a = 1 AND c = 'open'").pop().unwrap();
+        assert_eq!(probe.index, "t_a_open");
+        assert_eq!(probe.where_clause, partial.where_clause);
+    }
+
+    #[test]
+    fn test_row_index_lookup() {
+        let rows: Vec<Tuple> = [(1, "x"), (2, "y"), (1, "z")].iter()
+            .map(|(a, c)| Tuple::new(vec![Value::Integer(*a), Value::Varchar(c.to_string())]))
+            .chain(std::iter::once(Tuple::new(vec![Value::Null, Value::Varchar("n".to_string())])))
+            .collect();
+        let index = RowIndex::build(vec![0, 1], Vec::new(), None, rows.iter().zip([10, 20, 30, 40]), 7);
+        assert!(index.is_current(&[0, 1], &[], 7));
+        assert!(!index.is_current(&[0, 1], &[], 8));
+        assert!(!index.is_current(&[0, 1], &[2], 7));
+        assert_eq!(index.lookup(&[Value::Integer(1)], None), vec![10, 30]);
+        assert_eq!(index.lookup(&[Value::Integer(1), Value::Varchar("z".to_string())], None), vec![30]);
+        assert!(index.lookup(&[Value::Integer(3)], None).is_empty());
+        assert_eq!(index.count(&[Value::Integer(1)], None), 2);
+        assert_eq!(index.count(&[Value::Integer(3)], None), 0);
+
+        // 前缀之后的键列上按范围查找；NULL 键不在索引中
+        let range = |lower, upper| KeyRange { lower, upper };
+        let above = range(Bound::Excluded(Value::Integer(1)), Bound::Unbounded);
+        assert_eq!(index.lookup(&[], Some(&above)), vec![20]);
+        let up_to_two = range(Bound::Unbounded, Bound::Included(Value::Integer(2)));
+        assert_eq!(index.lookup(&[], Some(&up_to_two)), vec![10, 20, 30]);
+        let after_x = range(Bound::Excluded(Value::Varchar("x".to_string())), Bound::Unbounded);
+        assert_eq!(index.lookup(&[Value::Integer(1)], Some(&after_x)), vec![30]);
+        let empty = range(Bound::Included(Value::Integer(2)), Bound::Excluded(Value::Integer(2)));
+        assert_eq!(index.count(&[], Some(&empty)), 0);
+        assert_eq!(index.matching(&rows[2]), &[30]);
+        assert!(index.matching(&rows[3]).is_empty());
+
+        // 追加的行直接进入索引；戳不符（表在别处被修改过）时保持过期
+        let mut index = index;
+        index.insert(&rows[0], 50, 7, 8);
+        assert!(index.is_current(&[0, 1], &[], 8));
+        assert_eq!(index.matching(&rows[0]), &[10, 50]);
+        index.insert(&rows[1], 60, 7, 9);
+        assert!(!index.is_current(&[0, 1], &[], 9));
+        // 不进入部分索引的行只推进戳
+        index.advance(8, 10);
+        assert!(index.is_current(&[0, 1], &[], 10));
+        assert_eq!(index.count(&[], None), 4);
+
+        // 更新和删除就地修改索引项，各键下的 rowid 保持递增
+        let mut index = RowIndex::build(vec![0, 1], Vec::new(), None, rows.iter().zip([10, 20, 30, 40]), 7);
+        index.update(&rows[1], &rows[0], 20, 7, 8);
+        assert_eq!(index.matching(&rows[0]), &[10, 20]);
+        assert!(index.matching(&rows[1]).is_empty());
+        assert_eq!(index.count(&[Value::Integer(2)], None), 0);
+        index.update(&rows[3], &rows[2], 40, 8, 9);
+        assert_eq!(index.matching(&rows[2]), &[30, 40]);
+        index.remove(&rows[0], 10, 9, 10);
+        assert_eq!(index.lookup(&[Value::Integer(1)], None), vec![20, 30, 40]);
+        assert!(index.is_current(&[0, 1], &[], 10));
+        index.remove(&rows[0], 20, 7, 11);
+        assert!(!index.is_current(&[0, 1], &[], 11));
+
+        // 仅索引扫描：键列和 INCLUDE 列的值来自索引，其余列为 NULL
+        let rows: Vec<Tuple> = [(1, 5, 7), (2, 6, 8), (1, 9, 9)].iter()
+            .map(|&(a, b, c)| Tuple::new(vec![Value::Integer(a), Value::Integer(b), Value::Integer(c)]))
+            .collect();
+        let mut index = RowIndex::build(vec![0], vec![2], None, rows[..2].iter().zip([1, 2]), 1);
+        index.insert(&rows[2], 3, 1, 2);
+        assert!(index.is_current(&[0], &[2], 2));
+        let found = index.lookup_rows(&[Value::Integer(1)], None, 3);
+        assert_eq!(found, vec![
+            (1, Tuple::new(vec![Value::Integer(1), Value::Null, Value::Integer(7)])),
+            (3, Tuple::new(vec![Value::Integer(1), Value::Null, Value::Integer(9)])),
+        ]);
+    }
+
+    #[cfg(feature = "persistence")]
+    #[test]
+    fn test_index_file() {
+        let rows: Vec<Tuple> = [(1, "x"), (2, "y"), (1, "z")].iter()
+            .map(|(a, c)| Tuple::new(vec![Value::Integer(*a), Value::Varchar(c.to_string())]))
+            .collect();
+        let index = RowIndex::build(vec![0], vec![1], None, rows.iter().zip([10, 20, 30]), 7);
+        assert_eq!(index.duplicate_key(), Some(&[Value::Integer(1)][..]));
+        let file = encode_index_file(4, &[("t_a".to_string(), index.encode())]);
+
+        // 载入的索引使用新的戳，内容与原索引相同
+        let sections = decode_index_file(&file, 4).unwrap();
+        let loaded = RowIndex::decode(sections["t_a"], None, 9).unwrap();
+        assert!(loaded.is_current(&[0], &[1], 9));
+        assert_eq!(loaded.lookup(&[Value::Integer(1)], None), vec![10, 30]);
+        assert_eq!(loaded.lookup_rows(&[Value::Integer(2)], None, 2), index.lookup_rows(&[Value::Integer(2)], None, 2));
+
+        // 为其他版本的堆文件写的、损坏的或截断的文件都不能载入
+        assert!(decode_index_file(&file, 5).unwrap_err().contains("version"));
+        let mut corrupt = file.clone();
+        corrupt[20] ^= 1;
+        assert_eq!(decode_index_file(&corrupt, 4).unwrap_err(), "checksum mismatch");
+        assert!(decode_index_file(&file[..3], 4).is_err());
+        assert!(RowIndex::decode(&sections["t_a"][..10], None, 9).is_err());
+        assert_eq!(index.size_bytes(), sections["t_a"].len());
+        assert_eq!(index.entry_count(), 3);
+    }
+}
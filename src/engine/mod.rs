@@ -3,16 +3,51 @@
 //! 此模块提供核心数据库功能，包括
 //! 查询执行、表管理和事务处理。
 
+pub mod advisor;
+#[cfg(feature = "persistence")]
+pub mod backup;
+#[cfg(feature = "async")]
+pub mod async_database;
+pub mod capabilities;
+#[cfg(feature = "arrow")]
+pub mod columnar;
+pub mod config;
 pub mod database;
+mod diff;
+mod dump;
 pub mod executor;
+pub mod export;
+mod external_sort;
+mod functions;
+mod import;
+mod indexes;
+pub mod observer;
+pub mod prepared;
+mod random;
+pub mod result_cache;
+mod rows;
+pub mod statistics;
 pub mod table;
 pub mod transaction;
+pub mod workload;
 
-#[cfg(test)]
+#[cfg(all(test, feature = "persistence"))]
 mod tests;
 
 // Re-export commonly used types
-pub use database::{Database, QueryResult};
+pub use advisor::IndexAdvice;
+#[cfg(feature = "persistence")]
+pub use backup::{BackupFile, BackupManifest, RecoveryInfo, RecoveryTarget};
+#[cfg(feature = "async")]
+pub use async_database::AsyncDatabase;
+pub use capabilities::{Capability, CapabilityInfo, Support};
+pub use config::DatabaseConfig;
+pub use database::{CompiledQuery, Database, IndexBuild, IndexDescription, IndexInfo, QueryResult, Rows, ValidationResult};
 pub use executor::{Executor, ExecutorError};
+pub use export::{CsvOptions, QuoteStyle};
+pub use observer::{ExecutorObserver, ObservedExecutor, OperatorInfo, OperatorStats};
+pub use prepared::{PreparedStatement, QueryDescription};
+pub use result_cache::ResultCacheStats;
+pub use statistics::{ColumnStatistics, TableStatistics};
 pub use table::{Table, TableError, TableId};
-pub use transaction::{Transaction, TransactionError, TransactionManager};
+pub use transaction::{LockManager, LockType, Transaction, TransactionError, TransactionManager};
@@ -0,0 +1,224 @@
+//! 算子级执行观察钩子
+//!
+//! 外部工具（性能分析器、火焰图生成器等）实现 [`ExecutorObserver`]，
+//! 通过 [`DatabaseConfig`](crate::engine::DatabaseConfig) 注册后即可接收
+//! 每个算子的 open/next/close 事件，无需修改引擎本身。
+
+use crate::engine::executor::{Executor, ExecutorError};
+use crate::types::{Schema, Tuple};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// 算子标识信息
+#[derive(Debug, Clone, PartialEq)]
+pub struct OperatorInfo {
+    /// 语句内唯一的算子ID
+    pub id: usize,
+    /// 算子名称（如 "Scan"、"Sort"）
+    pub name: String,
+    /// 附加描述（如表名）
+    pub detail: Option<String>,
+    /// 父算子ID，根算子为 None
+    pub parent: Option<usize>,
+}
+
+impl OperatorInfo {
+    pub fn new(id: usize, name: impl Into<String>) -> Self {
+        Self {
+            id,
+            name: name.into(),
+            detail: None,
+            parent: None,
+        }
+    }
+
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    pub fn with_parent(mut self, parent: usize) -> Self {
+        self.parent = Some(parent);
+        self
+    }
+}
+
+/// 算子关闭时汇总的统计信息
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OperatorStats {
+    /// 输出的行数
+    pub rows_produced: usize,
+    /// next 调用次数
+    pub next_calls: usize,
+    /// 累计耗时，包含子算子
+    pub elapsed: Duration,
+}
+
+/// 算子事件观察者
+///
+/// 所有方法默认为空实现。回调在执行线程上同步调用，应尽量轻量。
+pub trait ExecutorObserver: Send + Sync {
+    /// 算子开始产生数据
+    fn on_open(&self, _operator: &OperatorInfo) {}
+
+    /// 一次 next 调用完成
+    ///
+    /// `rows` 为本次产生的行数（0 或 1）。
+    fn on_next(&self, _operator: &OperatorInfo, _rows: usize, _elapsed: Duration) {}
+
+    /// 算子结束：输出完毕、出错，或父算子不再需要更多的行
+    fn on_close(&self, _operator: &OperatorInfo, _stats: &OperatorStats) {}
+}
+
+/// 记录各算子关闭时的统计，供 EXPLAIN ANALYZE 使用；所有事件同时转发给原先注册的观察者
+pub(crate) struct StatsCollector {
+    forward: Option<Arc<dyn ExecutorObserver>>,
+    operators: Mutex<Vec<(OperatorInfo, OperatorStats)>>,
+}
+
+impl StatsCollector {
+    pub(crate) fn new(forward: Option<Arc<dyn ExecutorObserver>>) -> Self {
+        Self {
+            forward,
+            operators: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// 按算子树缩进排列的统计，根算子在前
+    pub(crate) fn render(&self) -> String {
+        fn render_node(operators: &[(OperatorInfo, OperatorStats)], index: usize, depth: usize, out: &mut String) {
+            let (info, stats) = &operators[index];
+            let indent = if depth == 0 { String::new() } else { format!("{}-> ", "    ".repeat(depth - 1)) };
+            let name = match &info.detail {
+                Some(detail) => format!("{} on {}", info.name, detail),
+                None => info.name.clone(),
+            };
+            out.push_str(&format!(
+                "{}{} (actual rows={}, time={:.3} ms)\n",
+                indent,
+                name,
+                stats.rows_produced,
+                stats.elapsed.as_secs_f64() * 1000.0
+            ));
+            // 子算子按编号（计划中的顺序）排列，而不是按关闭的先后
+            let mut children: Vec<usize> = (0..operators.len())
+                .filter(|&child| operators[child].0.parent == Some(info.id))
+                .collect();
+            children.sort_by_key(|&child| operators[child].0.id);
+            for child in children {
+                render_node(operators, child, depth + 1, out);
+            }
+        }
+
+        let operators = self.operators.lock().unwrap_or_else(|e| e.into_inner());
+        let mut out = String::new();
+        for (index, (info, _)) in operators.iter().enumerate() {
+            if info.parent.is_none() {
+                render_node(&operators, index, 0, &mut out);
+            }
+        }
+        out
+    }
+}
+
+impl ExecutorObserver for StatsCollector {
+    fn on_open(&self, operator: &OperatorInfo) {
+        if let Some(forward) = &self.forward {
+            forward.on_open(operator);
+        }
+    }
+
+    fn on_next(&self, operator: &OperatorInfo, rows: usize, elapsed: Duration) {
+        if let Some(forward) = &self.forward {
+            forward.on_next(operator, rows, elapsed);
+        }
+    }
+
+    fn on_close(&self, operator: &OperatorInfo, stats: &OperatorStats) {
+        self.operators.lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push((operator.clone(), stats.clone()));
+        if let Some(forward) = &self.forward {
+            forward.on_close(operator, stats);
+        }
+    }
+}
+
+/// 为任意执行器发送观察事件的包装器
+pub struct ObservedExecutor<'a> {
+    inner: Box<dyn Executor + 'a>,
+    observer: Arc<dyn ExecutorObserver>,
+    info: OperatorInfo,
+    stats: OperatorStats,
+    opened: bool,
+}
+
+impl<'a> ObservedExecutor<'a> {
+    pub fn new(inner: Box<dyn Executor + 'a>, observer: Arc<dyn ExecutorObserver>, info: OperatorInfo) -> Self {
+        Self {
+            inner,
+            observer,
+            info,
+            stats: OperatorStats::default(),
+            opened: false,
+        }
+    }
+
+    /// 被观察算子的标识信息
+    pub fn info(&self) -> &OperatorInfo {
+        &self.info
+    }
+
+    fn finish(&mut self) {
+        if self.opened {
+            self.observer.on_close(&self.info, &self.stats);
+            self.opened = false;
+        }
+    }
+}
+
+impl Executor for ObservedExecutor<'_> {
+    fn next(&mut self) -> Result<Option<Tuple>, ExecutorError> {
+        if !self.opened {
+            self.stats = OperatorStats::default();
+            self.observer.on_open(&self.info);
+            self.opened = true;
+        }
+
+        let start = Instant::now();
+        let result = self.inner.next();
+        let elapsed = start.elapsed();
+
+        let rows = usize::from(matches!(result, Ok(Some(_))));
+        self.stats.rows_produced += rows;
+        self.stats.next_calls += 1;
+        self.stats.elapsed += elapsed;
+        self.observer.on_next(&self.info, rows, elapsed);
+
+        if !matches!(result, Ok(Some(_))) {
+            self.finish();
+        }
+        result
+    }
+
+    fn schema(&self) -> &Schema {
+        self.inner.schema()
+    }
+
+    fn reset(&mut self) -> Result<(), ExecutorError> {
+        self.finish();
+        self.inner.reset()
+    }
+
+    fn close(&mut self) {
+        self.inner.close();
+        self.finish();
+    }
+}
+
+impl Drop for ObservedExecutor<'_> {
+    fn drop(&mut self) {
+        // 没有被关闭就放弃的算子也要发出 close 事件
+        self.finish();
+    }
+}
@@ -0,0 +1,134 @@
+//! 预编译语句
+//!
+//! 预编译语句只解析和分析一次，客户端无需执行即可得到参数类型和结果模式，
+//! 便于 GUI 客户端和 ORM 生成类型化的绑定。执行时把参数值代入语法树中的占位符，
+//! 重复执行同一语句不需要重新解析。
+
+use crate::engine::database::{Database, ExecutionError, QueryResult};
+use crate::sql::parser::Expression;
+use crate::sql::Statement;
+use crate::types::{ColumnMetadata, DataType, Schema, Value};
+use serde::{Deserialize, Serialize};
+
+/// 已解析并完成语义分析的 SQL 语句
+#[derive(Debug, Clone)]
+pub struct PreparedStatement {
+    sql: String,
+    statement: Statement,
+    parameter_types: Vec<Option<DataType>>,
+    result_schema: Option<Schema>,
+}
+
+impl PreparedStatement {
+    pub(crate) fn new(
+        sql: &str,
+        statement: Statement,
+        parameter_types: Vec<Option<DataType>>,
+        result_schema: Option<Schema>,
+    ) -> Self {
+        Self {
+            sql: sql.to_string(),
+            statement,
+            parameter_types,
+            result_schema,
+        }
+    }
+
+    /// 原始 SQL 文本
+    pub fn sql(&self) -> &str {
+        &self.sql
+    }
+
+    /// 解析后的语句
+    pub fn statement(&self) -> &Statement {
+        &self.statement
+    }
+
+    /// 参数个数（按最大占位符编号计）
+    pub fn parameter_count(&self) -> usize {
+        self.parameter_types.len()
+    }
+
+    /// 各参数的推断类型，下标 0 对应 `$1`；无法从上下文推断时为 None
+    pub fn parameter_types(&self) -> &[Option<DataType>] {
+        &self.parameter_types
+    }
+
+    /// 结果集的模式；不产生结果集的语句为 None
+    pub fn result_schema(&self) -> Option<&Schema> {
+        self.result_schema.as_ref()
+    }
+
+    /// 结果列的驱动元数据；不产生结果集的语句为空
+    pub fn column_metadata(&self) -> Vec<ColumnMetadata> {
+        self.result_schema.as_ref().map(Schema::column_metadata).unwrap_or_default()
+    }
+
+    /// 以给定参数执行语句，等同于 [`Database::execute_prepared`]
+    pub fn execute(&self, database: &mut Database, params: &[Value]) -> Result<QueryResult, ExecutionError> {
+        database.execute_prepared(self, params)
+    }
+
+    /// 把参数值代入占位符，得到可直接执行的语句
+    pub(crate) fn bind(&self, params: &[Value]) -> Result<Statement, ExecutionError> {
+        if params.len() != self.parameter_types.len() {
+            return Err(ExecutionError::ParameterCountMismatch {
+                expected: self.parameter_types.len(),
+                actual: params.len(),
+            });
+        }
+        let values = params.iter()
+            .zip(&self.parameter_types)
+            .enumerate()
+            .map(|(i, (value, data_type))| match data_type {
+                Some(data_type) if !value.is_null() => value.cast_to(data_type).map_err(|e| ExecutionError::EvaluationError {
+                    message: format!("parameter ${}: cannot cast {} to {}: {}", i + 1, value, data_type, e),
+                }),
+                _ => Ok(value.clone()),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut statement = self.statement.clone();
+        statement.visit_expressions_mut(&mut |expr| {
+            if let Expression::Parameter(index) = expr {
+                *expr = Expression::Literal(values[*index - 1].clone());
+            }
+        });
+        Ok(statement)
+    }
+
+    /// 语句的静态描述
+    pub fn describe(&self) -> QueryDescription {
+        QueryDescription {
+            sql: self.sql.clone(),
+            parameters: self.parameter_types.clone(),
+            result_schema: self.result_schema.clone(),
+        }
+    }
+}
+
+/// 查询的静态描述
+///
+/// 供 sqlx 风格的宏或轻量 ORM 在构建时校验查询：描述可以序列化保存，
+/// 之后在没有数据库连接时复用，或与当前数据库重新描述的结果比较以发现模式变化。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueryDescription {
+    /// 原始 SQL 文本
+    pub sql: String,
+    /// 各参数的推断类型，下标 0 对应 `$1`；无法从上下文推断时为 None
+    pub parameters: Vec<Option<DataType>>,
+    /// 结果集的模式；不产生结果集的语句为 None
+    pub result_schema: Option<Schema>,
+}
+
+impl QueryDescription {
+    /// 语句是否产生结果集
+    pub fn returns_rows(&self) -> bool {
+        self.result_schema.is_some()
+    }
+
+    /// 结果列的驱动元数据；不产生结果集的语句为空
+    pub fn column_metadata(&self) -> Vec<ColumnMetadata> {
+        self.result_schema.as_ref().map(Schema::column_metadata).unwrap_or_default()
+    }
+}
@@ -0,0 +1,59 @@
+//! 可设定种子的随机数源
+//!
+//! RANDOM() 等函数从这里取值。设定种子后同一序列的查询产生相同的结果，
+//! 便于编写可复现的测试；未设定种子时以当前时间初始化。
+//! 算法为 SplitMix64，足以满足采样需求，但不适用于任何安全用途。
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+const GOLDEN_GAMMA: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// 数据库级随机数源
+#[derive(Debug)]
+pub(crate) struct RandomSource {
+    state: AtomicU64,
+}
+
+impl RandomSource {
+    /// 使用给定种子创建；None 表示以当前时间为种子
+    pub(crate) fn new(seed: Option<u64>) -> Self {
+        Self {
+            state: AtomicU64::new(seed.unwrap_or_else(time_seed)),
+        }
+    }
+
+    /// 重新设定种子，之后的序列从头开始
+    pub(crate) fn reseed(&self, seed: Option<u64>) {
+        self.state.store(seed.unwrap_or_else(time_seed), Ordering::Relaxed);
+    }
+
+    /// 下一个 64 位随机数
+    pub(crate) fn next_u64(&self) -> u64 {
+        let state = self.state.fetch_add(GOLDEN_GAMMA, Ordering::Relaxed).wrapping_add(GOLDEN_GAMMA);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// [0, 1) 区间内均匀分布的浮点数
+    pub(crate) fn next_f64(&self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+// 通过 chrono 取时间：wasm32 上 std::time::SystemTime::now 会 panic，而 chrono 开启
+// wasmbind 后使用 JavaScript 的 Date
+#[cfg(feature = "chrono")]
+fn time_seed() -> u64 {
+    chrono::Utc::now()
+        .timestamp_nanos_opt()
+        .map_or(GOLDEN_GAMMA, |nanos| nanos as u64)
+}
+
+#[cfg(not(feature = "chrono"))]
+fn time_seed() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(GOLDEN_GAMMA, |d| d.as_nanos() as u64)
+}
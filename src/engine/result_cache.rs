@@ -0,0 +1,140 @@
+//! 查询结果缓存
+//!
+//! 开启后（`DatabaseConfig::with_result_cache_entries` 或 `SET result_cache_entries = N`），
+//! 只读 SELECT 的结果按语句的语法树缓存：空白、关键字大小写不同但语法树相同的查询命中同一条。
+//! 任何语句修改了表的行（INSERT / UPDATE / DELETE / COPY / DEDUPLICATE、事务回滚）或删除、重建表时，
+//! 引用该表的缓存结果全部失效。含 RANDOM() / NOW() 等易变函数、读取系统视图或临时视图的查询不缓存。
+
+use crate::engine::database::QueryResult;
+use std::collections::HashMap;
+
+/// 一条缓存的查询结果
+#[derive(Debug)]
+struct CachedResult {
+    result: QueryResult,
+    /// 查询读取的表
+    tables: Vec<String>,
+    /// 最近一次命中（或写入）时的访问序号，用于淘汰
+    last_used: u64,
+}
+
+/// 结果缓存的命中统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResultCacheStats {
+    /// 当前缓存的结果数
+    pub entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+    /// 因表被修改而失效的结果数
+    pub invalidations: u64,
+}
+
+/// 按语句缓存查询结果，容量满时淘汰最久未使用的一条
+#[derive(Debug, Default)]
+pub(crate) struct ResultCache {
+    entries: HashMap<String, CachedResult>,
+    /// 最多缓存的结果数；0 表示关闭
+    capacity: usize,
+    clock: u64,
+    stats: ResultCacheStats,
+}
+
+impl ResultCache {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self { capacity, ..Self::default() }
+    }
+
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    /// 调整容量；缩小时淘汰多出的结果，0 清空并关闭缓存
+    pub(crate) fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.entries.len() > capacity {
+            self.evict();
+        }
+    }
+
+    /// 查找缓存的结果并记录命中或未命中
+    pub(crate) fn get(&mut self, key: &str) -> Option<QueryResult> {
+        self.clock += 1;
+        match self.entries.get_mut(key) {
+            Some(entry) => {
+                entry.last_used = self.clock;
+                self.stats.hits += 1;
+                Some(entry.result.clone())
+            }
+            None => {
+                self.stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// 缓存读取 tables 的查询结果
+    pub(crate) fn insert(&mut self, key: String, tables: Vec<String>, result: QueryResult) {
+        if !self.is_enabled() {
+            return;
+        }
+        if !self.entries.contains_key(&key) && self.entries.len() >= self.capacity {
+            self.evict();
+        }
+        self.clock += 1;
+        self.entries.insert(key, CachedResult { result, tables, last_used: self.clock });
+    }
+
+    /// 丢弃读取了该表的全部结果
+    pub(crate) fn invalidate(&mut self, table: &str) {
+        let before = self.entries.len();
+        self.entries.retain(|_, entry| !entry.tables.iter().any(|name| name == table));
+        self.stats.invalidations += (before - self.entries.len()) as u64;
+    }
+
+    /// 丢弃全部结果（统计保留）
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub(crate) fn stats(&self) -> ResultCacheStats {
+        ResultCacheStats { entries: self.entries.len(), ..self.stats }
+    }
+
+    /// 淘汰最久未使用的结果
+    fn evict(&mut self) {
+        let coldest = self.entries.iter()
+            .min_by_key(|(_, entry)| entry.last_used)
+            .map(|(key, _)| key.clone());
+        if let Some(key) = coldest {
+            self.entries.remove(&key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(message: &str) -> QueryResult {
+        QueryResult { rows: vec![], schema: None, affected_rows: 0, message: message.to_string() }
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let mut cache = ResultCache::new(2);
+        cache.insert("a".to_string(), vec!["t".to_string()], result("a"));
+        cache.insert("b".to_string(), vec!["u".to_string()], result("b"));
+        assert!(cache.get("a").is_some());
+        cache.insert("c".to_string(), vec!["t".to_string()], result("c"));
+        assert!(cache.get("b").is_none());
+        assert_eq!(cache.get("c").map(|r| r.message), Some("c".to_string()));
+
+        cache.invalidate("t");
+        assert!(cache.get("a").is_none());
+        assert_eq!(cache.stats(), ResultCacheStats { entries: 0, hits: 2, misses: 2, invalidations: 2 });
+
+        cache.set_capacity(0);
+        cache.insert("a".to_string(), vec![], result("a"));
+        assert_eq!(cache.stats().entries, 0);
+    }
+}
@@ -0,0 +1,232 @@
+//! 表的行存储
+//!
+//! 有数据目录时，每张表的行保存在临时目录下的一个工作堆文件中，查询和修改都经缓冲池读写槽页，
+//! 缓冲池的大小和替换策略因此直接影响执行性能。工作文件只在数据库打开期间使用：
+//! 持久化仍由快照和 WAL 负责，打开数据库时工作文件从快照重建。
+//! 内存数据库没有数据目录，行直接保存在内存中。
+
+use super::database::ExecutionError;
+use super::executor::{ExecutorError, ScanRows};
+use crate::storage::{BufferPool, FileManager, HeapFile, RecordId, StorageError};
+use crate::types::Tuple;
+use crate::utils::serialize;
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// 临时目录下保存工作堆文件的子目录
+#[cfg(feature = "persistence")]
+pub(crate) const TABLE_WORK_DIR: &str = "tables";
+
+/// 全局递增的修改戳，不同表、不同时刻的修改不会得到相同的值
+static NEXT_STAMP: AtomicU64 = AtomicU64::new(1);
+
+fn next_stamp() -> u64 {
+    NEXT_STAMP.fetch_add(1, Ordering::Relaxed)
+}
+
+/// 一张表的行，与表的 rowid 同序
+#[derive(Debug)]
+pub(crate) struct TableRows {
+    storage: Storage,
+    /// 最近一次修改的修改戳；据此判断由这些行建立的索引是否过期
+    stamp: u64,
+}
+
+#[derive(Debug)]
+enum Storage {
+    /// 行直接保存在内存中
+    Memory(Vec<Tuple>),
+    /// 行保存在工作堆文件中，第 i 行的记录ID 是 records[i]
+    Paged { heap: HeapFile, records: Vec<RecordId> },
+}
+
+impl TableRows {
+    /// 创建保存给定行的存储：有工作目录时写入表的新工作堆文件，否则保存在内存中
+    pub(crate) fn new(
+        work_files: Option<&FileManager>,
+        pool: &BufferPool,
+        table_id: u32,
+        rows: Vec<Tuple>,
+    ) -> Result<Self, ExecutionError> {
+        match work_files {
+            Some(_) => Self::from_rows(work_files, pool, table_id, rows.into_iter().map(Ok)),
+            None => Ok(TableRows { storage: Storage::Memory(rows), stamp: next_stamp() }),
+        }
+    }
+
+    /// 同 new，但逐行取得要保存的行：有工作目录时每取一行就写入工作堆文件，不先把全部行收集起来
+    pub(crate) fn from_rows(
+        work_files: Option<&FileManager>,
+        pool: &BufferPool,
+        table_id: u32,
+        rows: impl IntoIterator<Item = Result<Tuple, ExecutionError>>,
+    ) -> Result<Self, ExecutionError> {
+        let rows = rows.into_iter();
+        let Some(work_files) = work_files else {
+            return Ok(TableRows { storage: Storage::Memory(rows.collect::<Result<_, _>>()?), stamp: next_stamp() });
+        };
+        Self::remove_file(work_files, pool, table_id)?;
+        let file = work_files.create_file(&work_file_name(table_id))
+            .map_err(|e| ExecutionError::StorageError(format!("Failed to create table work file: {}", e)))?;
+        let storage = Storage::Paged { heap: HeapFile::new(file), records: Vec::with_capacity(rows.size_hint().0) };
+        let mut table_rows = TableRows { storage, stamp: next_stamp() };
+        for row in rows {
+            table_rows.push(pool, row?)?;
+        }
+        Ok(table_rows)
+    }
+
+    /// 删除表的工作堆文件，缓冲池中它的页直接丢弃
+    pub(crate) fn remove_file(work_files: &FileManager, pool: &BufferPool, table_id: u32) -> Result<(), ExecutionError> {
+        let name = work_file_name(table_id);
+        pool.discard_file(&name)
+            .map_err(|e| ExecutionError::StorageError(format!("Buffer pool error: {}", e)))?;
+        work_files.delete_file(&name)
+            .map_err(|e| ExecutionError::StorageError(format!("Failed to delete table work file: {}", e)))
+    }
+
+    /// 修改戳：每次修改后都不同
+    pub(crate) fn stamp(&self) -> u64 {
+        self.stamp
+    }
+
+    /// 行数
+    pub(crate) fn len(&self) -> usize {
+        match &self.storage {
+            Storage::Memory(rows) => rows.len(),
+            Storage::Paged { records, .. } => records.len(),
+        }
+    }
+
+    /// 全部行；分页存储时逐行经缓冲池读取
+    pub(crate) fn rows(&self, pool: &BufferPool) -> Result<Cow<'_, [Tuple]>, ExecutionError> {
+        match &self.storage {
+            Storage::Memory(rows) => Ok(Cow::Borrowed(rows)),
+            Storage::Paged { heap, records } => records.iter()
+                .map(|&rid| read_row(heap, pool, rid))
+                .collect::<Result<Vec<_>, _>>()
+                .map(Cow::Owned),
+        }
+    }
+
+    /// 按行的顺序逐行读取；分页存储时每读一行才经缓冲池取出该记录，不先读出整张表
+    #[cfg(feature = "persistence")]
+    pub(crate) fn iter<'a>(&'a self, pool: &'a BufferPool) -> Box<dyn Iterator<Item = Result<Cow<'a, Tuple>, ExecutionError>> + 'a> {
+        match &self.storage {
+            Storage::Memory(rows) => Box::new(rows.iter().map(|row| Ok(Cow::Borrowed(row)))),
+            Storage::Paged { heap, records } => Box::new(records.iter().map(move |&rid| read_row(heap, pool, rid).map(Cow::Owned))),
+        }
+    }
+
+    /// 供顺序扫描逐行读取的行：分页存储时每读一行才经缓冲池取出该记录所在的页并解码，不先读出整张表
+    pub(crate) fn scan<'a>(&'a self, pool: &'a BufferPool) -> ScanRows<'a> {
+        match &self.storage {
+            Storage::Memory(rows) => ScanRows::Rows(Cow::Borrowed(rows)),
+            Storage::Paged { heap, records } => ScanRows::Fetch {
+                fetch: Box::new(move |index| {
+                    records.get(index)
+                        .map(|&rid| read_row(heap, pool, rid))
+                        .transpose()
+                        .map_err(ExecutorError::from)
+                }),
+                len: records.len(),
+            },
+        }
+    }
+
+    /// 第 index 行
+    pub(crate) fn get(&self, pool: &BufferPool, index: usize) -> Result<Option<Tuple>, ExecutionError> {
+        match &self.storage {
+            Storage::Memory(rows) => Ok(rows.get(index).cloned()),
+            Storage::Paged { heap, records } => records.get(index)
+                .map(|&rid| read_row(heap, pool, rid))
+                .transpose(),
+        }
+    }
+
+    /// 在末尾追加一行
+    pub(crate) fn push(&mut self, pool: &BufferPool, row: Tuple) -> Result<(), ExecutionError> {
+        self.stamp = next_stamp();
+        match &mut self.storage {
+            Storage::Memory(rows) => rows.push(row),
+            Storage::Paged { heap, records } => records.push(write_row(heap, pool, &row)?),
+        }
+        Ok(())
+    }
+
+    /// 替换第 index 行（index 越界时什么也不做）
+    pub(crate) fn set(&mut self, pool: &BufferPool, index: usize, row: Tuple) -> Result<(), ExecutionError> {
+        self.stamp = next_stamp();
+        match &mut self.storage {
+            Storage::Memory(rows) => {
+                if let Some(slot) = rows.get_mut(index) {
+                    *slot = row;
+                }
+            }
+            Storage::Paged { heap, records } => {
+                if let Some(rid) = records.get_mut(index) {
+                    // 变长的行可能被移到另一页
+                    *rid = heap.update(pool, *rid, &serialize::encode_tuple(&row)).map_err(page_error)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 删除第 index 行（index 越界时什么也不做）
+    pub(crate) fn remove(&mut self, pool: &BufferPool, index: usize) -> Result<(), ExecutionError> {
+        self.stamp = next_stamp();
+        match &mut self.storage {
+            Storage::Memory(rows) => {
+                if index < rows.len() {
+                    rows.remove(index);
+                }
+            }
+            Storage::Paged { heap, records } => {
+                if index < records.len() {
+                    heap.delete(pool, records[index]).map_err(page_error)?;
+                    records.remove(index);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 用给定的行替换全部行
+    pub(crate) fn replace(&mut self, pool: &BufferPool, rows: Vec<Tuple>) -> Result<(), ExecutionError> {
+        self.stamp = next_stamp();
+        match &mut self.storage {
+            Storage::Memory(current) => *current = rows,
+            Storage::Paged { heap, records } => {
+                for rid in records.drain(..) {
+                    heap.delete(pool, rid).map_err(page_error)?;
+                }
+                for row in &rows {
+                    records.push(write_row(heap, pool, row)?);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 表的工作堆文件名
+fn work_file_name(table_id: u32) -> String {
+    format!("table_{}_work", table_id)
+}
+
+/// 编码并写入一行，返回其记录ID
+fn write_row(heap: &HeapFile, pool: &BufferPool, row: &Tuple) -> Result<RecordId, ExecutionError> {
+    heap.insert(pool, &serialize::encode_tuple(row)).map_err(page_error)
+}
+
+/// 读取并解码一条记录
+fn read_row(heap: &HeapFile, pool: &BufferPool, rid: RecordId) -> Result<Tuple, ExecutionError> {
+    let record = heap.get(pool, rid).map_err(page_error)?;
+    serialize::decode_tuple(&record)
+        .map_err(|e| ExecutionError::StorageError(format!("Corrupt row {} in table work file: {}", rid, e)))
+}
+
+fn page_error(error: StorageError) -> ExecutionError {
+    ExecutionError::StorageError(format!("Page file error: {}", error))
+}
@@ -0,0 +1,135 @@
+//! ANALYZE 收集的表统计
+//!
+//! `ANALYZE [表]` 扫描表数据，为每列记录 NULL 数、不同值个数（NDV）、最小值、最大值和一个等深直方图，
+//! 保存在表快照中，重新打开数据库后仍然可用。统计只在 ANALYZE 时更新，之后的修改不会反映在其中。
+//!
+//! 等深直方图把按顺序排列的非 NULL 值分成行数大致相同的桶，只保存桶的边界：
+//! 第一个边界是最小值，最后一个是最大值，相邻两个边界之间约有 1/桶数 的非 NULL 行。
+//! NaN 和与该列其他值类型不可比较的值不参与最值和直方图。
+
+use crate::types::{Schema, Tuple, Value};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+/// 直方图的桶数；可比较的值不多于桶数时每个值都是一个边界
+pub const HISTOGRAM_BUCKETS: usize = 10;
+
+/// 一张表的统计
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TableStatistics {
+    /// ANALYZE 时的行数
+    pub row_count: usize,
+    /// 各列的统计，按列序
+    pub columns: Vec<ColumnStatistics>,
+}
+
+/// 一列的统计
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColumnStatistics {
+    pub name: String,
+    pub null_count: usize,
+    /// 不同的非 NULL 值个数
+    pub distinct_count: usize,
+    /// 最小值；没有可比较的非 NULL 值时为 None
+    pub min: Option<Value>,
+    pub max: Option<Value>,
+    /// 等深直方图的桶边界，递增；桶数为边界数减一，没有可比较的非 NULL 值时为空
+    pub histogram: Vec<Value>,
+}
+
+impl TableStatistics {
+    /// 按列名查找一列的统计
+    pub fn column(&self, name: &str) -> Option<&ColumnStatistics> {
+        self.columns.iter().find(|column| column.name == name)
+    }
+}
+
+/// 扫描表的行，计算各列的统计
+pub(crate) fn analyze(schema: &Schema, rows: &[Tuple]) -> TableStatistics {
+    let columns = schema.columns.iter().enumerate()
+        .map(|(i, column)| {
+            let values: Vec<&Value> = rows.iter()
+                .map(|row| row.values.get(i).unwrap_or(&Value::Null))
+                .filter(|value| !value.is_null())
+                .collect();
+            let distinct_count = values.iter().copied().collect::<HashSet<&Value>>().len();
+            let sorted = sorted_values(&values);
+            ColumnStatistics {
+                name: column.name.clone(),
+                null_count: rows.len() - values.len(),
+                distinct_count,
+                min: sorted.first().map(|&value| value.clone()),
+                max: sorted.last().map(|&value| value.clone()),
+                histogram: histogram(&sorted, HISTOGRAM_BUCKETS),
+            }
+        })
+        .collect();
+    TableStatistics { row_count: rows.len(), columns }
+}
+
+/// 可比较的值，递增排列：去掉 NaN 和与第一个值不可比较的值，其余的值之间是全序
+fn sorted_values<'a>(values: &[&'a Value]) -> Vec<&'a Value> {
+    let mut sorted: Vec<&Value> = values.iter().copied()
+        .filter(|value| !matches!(value, Value::Float(f) if f.is_nan()) && !matches!(value, Value::Double(d) if d.is_nan()))
+        .collect();
+    if let Some(&first) = sorted.first() {
+        sorted.retain(|value| value.partial_cmp(&first).is_some());
+    }
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    sorted
+}
+
+/// 递增排列的值的等深直方图边界
+fn histogram(sorted: &[&Value], buckets: usize) -> Vec<Value> {
+    if sorted.is_empty() {
+        return Vec::new();
+    }
+    let buckets = buckets.min(sorted.len() - 1).max(1);
+    (0..=buckets)
+        .map(|k| sorted[(k * (sorted.len() - 1)) / buckets].clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ColumnDefinition, DataType};
+
+    #[test]
+    fn test_analyze() {
+        let schema = Schema::new(vec![
+            ColumnDefinition { name: "n".to_string(), data_type: DataType::Integer, nullable: true, default: None, default_function: None },
+            ColumnDefinition { name: "x".to_string(), data_type: DataType::Double, nullable: true, default: None, default_function: None },
+        ]);
+        let rows: Vec<Tuple> = (0..100)
+            .map(|i| Tuple::new(vec![
+                if i % 4 == 0 { Value::Null } else { Value::Integer(i % 30) },
+                if i == 7 { Value::Double(f64::NAN) } else { Value::Double(i as f64) },
+            ]))
+            .collect();
+        let stats = analyze(&schema, &rows);
+        assert_eq!(stats.row_count, 100);
+
+        let n = stats.column("n").unwrap();
+        assert_eq!(n.null_count, 25);
+        assert_eq!(n.distinct_count, 30);
+        assert_eq!((n.min.clone(), n.max.clone()), (Some(Value::Integer(0)), Some(Value::Integer(29))));
+        assert_eq!(n.histogram.len(), HISTOGRAM_BUCKETS + 1);
+        assert_eq!(n.histogram.first(), n.min.as_ref());
+        assert_eq!(n.histogram.last(), n.max.as_ref());
+        assert!(n.histogram.windows(2).all(|pair| pair[0] <= pair[1]));
+
+        // NaN 不参与最值和直方图
+        let x = stats.column("x").unwrap();
+        assert_eq!(x.max, Some(Value::Double(99.0)));
+        assert_eq!(x.histogram[5], Value::Double(50.0));
+
+        // 值不多于桶数时每个值都是边界；没有值时没有直方图
+        let few = analyze(&schema, &rows[1..4]);
+        assert_eq!(few.column("n").unwrap().histogram, vec![Value::Integer(1), Value::Integer(2), Value::Integer(3)]);
+        let empty = analyze(&schema, &[]);
+        assert!(empty.column("n").unwrap().histogram.is_empty());
+        assert_eq!(empty.column("n").unwrap().min, None);
+    }
+}
@@ -4,6 +4,7 @@
 //! 表创建、数据插入和基本查询。
 
 use super::database::{Database, ExecutionError};
+use super::export::{CsvOptions, QuoteStyle};
 use crate::sql::parse_sql;
 use crate::types::{DataType, Value};
 use std::fs;
@@ -263,3 +264,4216 @@ fn test_insert_column_mismatch() {
     // Clean up
     let _ = fs::remove_dir_all(test_dir);
 }
+
+/// 测试 INSERT ... ON CONFLICT
+#[test]
+fn test_insert_on_conflict() {
+    let test_dir = "test_db_on_conflict";
+    let _ = fs::remove_dir_all(test_dir);
+
+    let mut db = Database::new(test_dir).expect("Failed to create database");
+
+    db.execute("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(20), visits INT)")
+        .expect("Failed to create table");
+    db.execute("INSERT INTO users VALUES (1, 'Alice', 1)")
+        .expect("Failed to insert data");
+
+    // DO NOTHING skips the conflicting row but still inserts the others
+    let result = db
+        .execute("INSERT INTO users VALUES (1, 'Other', 1), (2, 'Bob', 1) ON CONFLICT DO NOTHING")
+        .expect("Failed to execute upsert");
+    assert_eq!(result.affected_rows, 1);
+
+    // DO UPDATE applies the assignments to the existing row
+    let result = db
+        .execute("INSERT INTO users VALUES (1, 'Alicia', 1) ON CONFLICT (id) DO UPDATE SET name = excluded.name, visits = visits + 1")
+        .expect("Failed to execute upsert");
+    assert_eq!(result.affected_rows, 1);
+
+    let result = db
+        .execute("SELECT name, visits FROM users WHERE id = 1")
+        .expect("Failed to select");
+    assert_eq!(result.rows.len(), 1);
+    assert_eq!(result.rows[0].values, vec![Value::Varchar("Alicia".to_string()), Value::Integer(2)]);
+
+    // The conflict target must be exactly the primary key or a unique index
+    for target in ["name", "id, name"] {
+        let result = db.execute(&format!(
+            "INSERT INTO users VALUES (3, 'Alicia', 1) ON CONFLICT ({}) DO UPDATE SET visits = visits + 1", target
+        ));
+        match result {
+            Err(ExecutionError::EvaluationError { message }) => {
+                assert!(message.contains("no unique or exclusion constraint"), "{}", message)
+            }
+            other => panic!("expected an error for ON CONFLICT ({}), got {:?}", target, other),
+        }
+    }
+    assert_eq!(db.execute("SELECT * FROM users").unwrap().rows.len(), 2);
+
+    // Without ON CONFLICT the primary key is still enforced
+    let result = db.execute("INSERT INTO users VALUES (2, 'Bobby', 1)");
+    assert!(matches!(result, Err(ExecutionError::PrimaryKeyViolation { .. })));
+
+    // DO UPDATE values are checked like UPDATE: column types, NOT NULL and NULL primary keys
+    db.execute("CREATE TABLE scores (id INT PRIMARY KEY, score INT, owner VARCHAR(20) NOT NULL)")
+        .expect("Failed to create table");
+    db.execute("INSERT INTO scores VALUES (1, 10, 'ann')").expect("Failed to insert data");
+    let upsert = |db: &mut Database, set: &str| {
+        db.execute(&format!("INSERT INTO scores VALUES (1, 0, 'x') ON CONFLICT (id) DO UPDATE SET {}", set))
+    };
+    assert!(matches!(upsert(&mut db, "score = 'abc'"), Err(ExecutionError::TypeMismatch { .. })));
+    assert!(matches!(upsert(&mut db, "id = NULL"), Err(ExecutionError::NotNullViolation { .. })));
+    assert!(matches!(upsert(&mut db, "owner = NULL"), Err(ExecutionError::NotNullViolation { .. })));
+    assert!(matches!(db.execute("UPDATE scores SET score = 'abc'"), Err(ExecutionError::TypeMismatch { .. })));
+    assert!(matches!(db.execute("UPDATE scores SET id = NULL"), Err(ExecutionError::NotNullViolation { .. })));
+    upsert(&mut db, "score = excluded.score + 5").expect("Failed to execute upsert");
+    let result = db.execute("SELECT id, score, owner FROM scores").expect("Failed to select");
+    assert_eq!(result.rows[0].values, vec![Value::Integer(1), Value::Integer(5), Value::Varchar("ann".to_string())]);
+
+    // Clean up
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+/// 测试 DML 语句的 RETURNING 子句
+#[test]
+fn test_returning_clause() {
+    let test_dir = "test_db_returning";
+    let _ = fs::remove_dir_all(test_dir);
+
+    let mut db = Database::new(test_dir).expect("Failed to create database");
+
+    db.execute("CREATE TABLE items (id INT, qty INT)")
+        .expect("Failed to create table");
+
+    let result = db
+        .execute("INSERT INTO items VALUES (1, 10), (2, 20), (3, 30) RETURNING id")
+        .expect("Failed to insert");
+    assert_eq!(result.affected_rows, 3);
+    assert_eq!(result.rows.len(), 3);
+    assert_eq!(result.schema.unwrap().columns[0].name, "id");
+
+    let result = db
+        .execute("UPDATE items SET qty = qty + 1 WHERE id = 2 RETURNING id, qty AS new_qty")
+        .expect("Failed to update");
+    assert_eq!(result.rows, vec![crate::types::Tuple::new(vec![Value::Integer(2), Value::Integer(21)])]);
+    assert_eq!(result.schema.unwrap().columns[1].name, "new_qty");
+
+    let result = db
+        .execute("DELETE FROM items WHERE id < 3 RETURNING *")
+        .expect("Failed to delete");
+    assert_eq!(result.affected_rows, 2);
+    assert_eq!(result.rows.len(), 2);
+    assert_eq!(result.rows[0].values, vec![Value::Integer(1), Value::Integer(10)]);
+    assert_eq!(result.schema.unwrap().columns.len(), 2);
+
+    // Without RETURNING, DML results stay empty
+    let result = db.execute("DELETE FROM items").expect("Failed to delete");
+    assert!(result.rows.is_empty());
+    assert!(result.schema.is_none());
+
+    // Clean up
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+/// 测试列默认值
+#[test]
+fn test_column_defaults() {
+    let test_dir = "test_db_defaults";
+    let _ = fs::remove_dir_all(test_dir);
+
+    let mut db = Database::new(test_dir).expect("Failed to create database");
+
+    db.execute("CREATE TABLE tasks (id INT NOT NULL, title VARCHAR(20) DEFAULT 'untitled', priority INT DEFAULT -1, done BOOL DEFAULT FALSE NOT NULL)")
+        .expect("Failed to create table");
+
+    let schema = db.get_table_schema("tasks").expect("Table should exist");
+    assert_eq!(schema.columns[2].default, Some(Value::Integer(-1)));
+
+    // Omitted columns and the DEFAULT keyword both take the column default
+    db.execute("INSERT INTO tasks (id) VALUES (1)").expect("Failed to insert");
+    db.execute("INSERT INTO tasks (priority, id) VALUES (5, 2)").expect("Failed to insert");
+    db.execute("INSERT INTO tasks VALUES (3, DEFAULT, DEFAULT, TRUE)").expect("Failed to insert");
+
+    let result = db.execute("SELECT * FROM tasks ORDER BY id").expect("Failed to select");
+    assert_eq!(result.rows[0].values, vec![
+        Value::Integer(1),
+        Value::Varchar("untitled".to_string()),
+        Value::Integer(-1),
+        Value::Boolean(false),
+    ]);
+    assert_eq!(result.rows[1].values[2], Value::Integer(5));
+    assert_eq!(result.rows[2].values[1], Value::Varchar("untitled".to_string()));
+
+    // NOT NULL columns without a default must be provided
+    let result = db.execute("INSERT INTO tasks (title) VALUES ('x')");
+    assert!(matches!(result, Err(ExecutionError::NotNullViolation { column }) if column == "id"));
+
+    // Clean up
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+/// 记录算子事件的观察者
+#[derive(Default)]
+struct RecordingObserver {
+    events: std::sync::Mutex<Vec<String>>,
+}
+
+impl super::observer::ExecutorObserver for RecordingObserver {
+    fn on_open(&self, operator: &super::observer::OperatorInfo) {
+        self.events.lock().unwrap().push(format!("open {}", operator.name));
+    }
+
+    fn on_close(&self, operator: &super::observer::OperatorInfo, stats: &super::observer::OperatorStats) {
+        self.events.lock().unwrap().push(format!("close {} rows={}", operator.name, stats.rows_produced));
+    }
+}
+
+/// 测试算子观察钩子
+#[test]
+fn test_executor_observer() {
+    use super::config::DatabaseConfig;
+    use std::sync::Arc;
+
+    let test_dir = "test_db_observer";
+    let _ = fs::remove_dir_all(test_dir);
+
+    let observer = Arc::new(RecordingObserver::default());
+    let config = DatabaseConfig::default().with_observer(observer.clone());
+    let mut db = Database::with_config(test_dir, config).expect("Failed to create database");
+
+    db.execute("CREATE TABLE nums (n INT)").expect("Failed to create table");
+    for n in 1..=5 {
+        db.execute(&format!("INSERT INTO nums VALUES ({})", n)).expect("Failed to insert");
+    }
+
+    observer.events.lock().unwrap().clear();
+    db.execute("SELECT n FROM nums WHERE n > 1 ORDER BY n DESC LIMIT 2").expect("Failed to select");
+
+    // 算子逐行拉取：排序读完扫描的输出，LIMIT 取满两行后关闭排序
+    let events = observer.events.lock().unwrap().clone();
+    assert_eq!(events, vec![
+        "open Limit", "open Sort", "open Scan", "close Scan rows=4",
+        "close Sort rows=2", "close Limit rows=2",
+    ]);
+
+    // Clean up
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+/// 测试 EXPLAIN ANALYZE 实际执行语句并报告各算子的行数
+#[test]
+fn test_explain_analyze() {
+    let mut db = Database::in_memory().expect("Failed to create database");
+    db.execute("CREATE TABLE nums (n INT)").expect("Failed to create table");
+    for n in 1..=5 {
+        db.execute(&format!("INSERT INTO nums VALUES ({})", n)).expect("Failed to insert");
+    }
+
+    let report = |result: super::database::QueryResult| match &result.rows[0].values[0] {
+        Value::Varchar(text) => text.clone(),
+        other => panic!("Expected plan text, got {:?}", other),
+    };
+    let text = report(db.execute("EXPLAIN ANALYZE SELECT n FROM nums WHERE n > 1 ORDER BY n DESC LIMIT 2").unwrap());
+    let lines: Vec<&str> = text.lines().collect();
+    let actual = lines.iter().position(|line| *line == "Actual execution:").expect("missing statistics");
+    assert!(lines[0].starts_with("Select Execution Plan"));
+    assert!(lines[actual + 1].starts_with("Limit (actual rows=2,"));
+    assert!(lines[actual + 2].starts_with("-> Sort (actual rows=2,"));
+    assert!(lines[actual + 3].starts_with("    -> Scan on nums (actual rows=4,"));
+    assert!(lines[actual + 4].starts_with("Execution time:"));
+
+    // DML 实际生效
+    let result = db.execute("EXPLAIN ANALYZE DELETE FROM nums WHERE n < 3").unwrap();
+    assert_eq!(result.affected_rows, 2);
+    assert!(report(result).contains("Delete on nums (actual rows=2,"));
+    assert_eq!(db.execute("SELECT * FROM nums").unwrap().rows.len(), 3);
+
+    // 普通 EXPLAIN 不执行；DDL 不能 EXPLAIN ANALYZE
+    db.execute("EXPLAIN DELETE FROM nums").unwrap();
+    assert_eq!(db.execute("SELECT * FROM nums").unwrap().rows.len(), 3);
+    assert!(db.execute("EXPLAIN ANALYZE DROP TABLE nums").is_err());
+}
+
+/// 测试逐行返回结果的 query_iter
+#[test]
+fn test_query_iter() {
+    use super::config::DatabaseConfig;
+    use crate::storage::MemoryBackend;
+    use crate::types::Tuple;
+    use std::sync::Arc;
+
+    let observer = Arc::new(RecordingObserver::default());
+    let config = DatabaseConfig::default().with_observer(observer.clone());
+    let mut db = Database::with_backend(Box::new(MemoryBackend::new()), config).expect("Failed to create database");
+    db.execute("CREATE TABLE nums (n INT, label VARCHAR(10))").expect("Failed to create table");
+    for n in 1..=100 {
+        db.execute(&format!("INSERT INTO nums VALUES ({}, 'n{}')", n, n)).expect("Failed to insert");
+    }
+
+    // 只取前三行：扫描在迭代器被丢弃时结束，没有读完整张表
+    observer.events.lock().unwrap().clear();
+    let mut rows = db.query_iter("SELECT * FROM nums").expect("Failed to query");
+    assert_eq!(rows.schema().columns.len(), 2);
+    let first: Vec<Tuple> = rows.by_ref().take(3).collect::<Result<_, _>>().unwrap();
+    assert_eq!(first[2].values, vec![Value::Integer(3), Value::Varchar("n3".to_string())]);
+    drop(rows);
+    assert_eq!(*observer.events.lock().unwrap(), vec!["open Scan", "close Scan rows=3"]);
+
+    // 与 execute 返回相同的行
+    let streamed: Vec<Tuple> = db.query_iter("SELECT label, UPPER(label) FROM nums WHERE n > 95 ORDER BY n DESC")
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    let result = db.execute("SELECT label, UPPER(label) FROM nums WHERE n > 95 ORDER BY n DESC").unwrap();
+    assert_eq!(streamed, result.rows);
+    assert_eq!(streamed.len(), 5);
+
+    let mut rows = db.query_iter("SELECT UPPER(label) FROM nums LIMIT 1").unwrap();
+    assert!(rows.next().is_some());
+    assert_eq!(rows.schema().columns[0].data_type, DataType::Varchar(255));
+    assert!(rows.next().is_none());
+    drop(rows);
+
+    assert!(matches!(db.query_iter("DELETE FROM nums"), Err(ExecutionError::SemanticError(_))));
+    assert!(matches!(db.query_iter("SELECT * FROM missing"), Err(ExecutionError::TableNotFound { .. })));
+    assert_eq!(db.execute("SELECT * FROM nums").unwrap().rows.len(), 100);
+}
+
+/// 测试 ORDER BY 并列行的确定性排序
+#[test]
+fn test_deterministic_sort() {
+    let test_dir = "test_db_deterministic_sort";
+    let _ = fs::remove_dir_all(test_dir);
+
+    let mut db = Database::new(test_dir).expect("Failed to create database");
+    db.set_deterministic_sort(true);
+
+    db.execute("CREATE TABLE emp (dept VARCHAR(10), n INT)").expect("Failed to create table");
+    for (dept, n) in [("d", 1), ("a", 2), ("c", 3), ("a", 4), ("b", 5)] {
+        db.execute(&format!("INSERT INTO emp VALUES ('{}', {})", dept, n)).expect("Failed to insert");
+    }
+
+    // Groups come out of a hash table; ties on cnt are broken by dept
+    for _ in 0..5 {
+        let result = db.execute("SELECT dept, COUNT(*) AS cnt FROM emp GROUP BY dept ORDER BY cnt")
+            .expect("Failed to select");
+        let depts: Vec<Value> = result.rows.iter().map(|row| row.values[0].clone()).collect();
+        assert_eq!(depts, ["b", "c", "d", "a"].map(|d| Value::Varchar(d.to_string())));
+    }
+
+    // Ties on the sort key fall back to the remaining columns
+    let result = db.execute("SELECT dept, n FROM emp ORDER BY dept DESC").expect("Failed to select");
+    let ns: Vec<Value> = result.rows.iter().map(|row| row.values[1].clone()).collect();
+    assert_eq!(ns, [1, 3, 5, 2, 4].map(Value::Integer));
+
+    // Clean up
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+/// 测试确定性分组输出与可设定种子的 RANDOM()
+#[test]
+fn test_plan_stability() {
+    use super::config::DatabaseConfig;
+
+    let test_dir = "test_db_plan_stability";
+    let _ = fs::remove_dir_all(test_dir);
+
+    let config = DatabaseConfig::default()
+        .with_deterministic_grouping(true)
+        .with_random_seed(42);
+    let mut db = Database::with_config(test_dir, config).expect("Failed to create database");
+
+    db.execute("CREATE TABLE emp (dept VARCHAR(10), n INT)").expect("Failed to create table");
+    for (dept, n) in [("'d'", 1), ("'a'", 2), ("NULL", 3), ("'c'", 4), ("'a'", 5), ("'b'", 6)] {
+        db.execute(&format!("INSERT INTO emp VALUES ({}, {})", dept, n)).expect("Failed to insert");
+    }
+
+    // Without ORDER BY, groups come out sorted by key (NULL first)
+    let expected = vec![
+        Value::Null,
+        Value::Varchar("a".to_string()),
+        Value::Varchar("b".to_string()),
+        Value::Varchar("c".to_string()),
+        Value::Varchar("d".to_string()),
+    ];
+    for _ in 0..5 {
+        let result = db.execute("SELECT dept, COUNT(*) FROM emp GROUP BY dept").expect("Failed to select");
+        let depts: Vec<Value> = result.rows.iter().map(|row| row.values[0].clone()).collect();
+        assert_eq!(depts, expected);
+    }
+
+    // The same seed replays the same sequence
+    let sample = |db: &mut Database| -> Vec<Value> {
+        let result = db.execute("SELECT n, RANDOM() AS r FROM emp").expect("Failed to select");
+        result.rows.iter().map(|row| row.values[1].clone()).collect()
+    };
+    let first = sample(&mut db);
+    assert!(first.iter().all(|v| matches!(v, Value::Double(x) if (0.0..1.0).contains(x))));
+    let second = sample(&mut db);
+    assert_ne!(first, second);
+
+    db.set_random_seed(Some(42));
+    assert_eq!(sample(&mut db), first);
+
+    let mut other = Database::with_config(test_dir, DatabaseConfig::default().with_random_seed(42))
+        .expect("Failed to open database");
+    assert_eq!(sample(&mut other), first);
+
+    // Clean up
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+/// 测试 UNIQUE 约束
+#[test]
+fn test_unique_constraints() {
+    let test_dir = "test_db_unique";
+    let _ = fs::remove_dir_all(test_dir);
+
+    {
+        let mut db = Database::new(test_dir).expect("Failed to create database");
+        db.execute("CREATE TABLE users (id INT PRIMARY KEY, email VARCHAR(50) UNIQUE, first VARCHAR(20), last VARCHAR(20), UNIQUE (first, last))")
+            .expect("Failed to create table");
+
+        let indexes = db.get_table_indexes("users").expect("Table should exist");
+        assert_eq!(indexes.len(), 2);
+        assert_eq!(indexes[0].name, "users_email_key");
+        assert!(indexes.iter().all(|index| index.unique));
+
+        db.execute("INSERT INTO users VALUES (1, 'a@x.com', 'Ann', 'Lee')").expect("Failed to insert");
+        db.execute("INSERT INTO users VALUES (2, 'b@x.com', 'Ann', 'Kim')").expect("Failed to insert");
+
+        let result = db.execute("INSERT INTO users VALUES (3, 'a@x.com', 'Bob', 'Lee')");
+        assert!(matches!(result, Err(ExecutionError::UniqueViolation { index, .. }) if index == "users_email_key"));
+        let result = db.execute("INSERT INTO users VALUES (3, 'c@x.com', 'Ann', 'Lee')");
+        assert!(matches!(result, Err(ExecutionError::UniqueViolation { index, .. }) if index == "users_first_last_key"));
+
+        // NULLs never collide
+        db.execute("INSERT INTO users VALUES (3, NULL, 'Cy', 'Ng')").expect("Failed to insert");
+        db.execute("INSERT INTO users VALUES (4, NULL, 'Di', 'Ng')").expect("Failed to insert");
+
+        // Updates are checked against the resulting table
+        let result = db.execute("UPDATE users SET email = 'b@x.com' WHERE id = 1");
+        assert!(matches!(result, Err(ExecutionError::UniqueViolation { .. })));
+        let result = db.execute("UPDATE users SET last = 'Lee'");
+        assert!(matches!(result, Err(ExecutionError::UniqueViolation { .. })));
+        db.execute("UPDATE users SET email = 'z@x.com' WHERE id = 1").expect("Failed to update");
+
+        // ON CONFLICT can target a UNIQUE column
+        db.execute("INSERT INTO users VALUES (5, 'z@x.com', 'Ed', 'Wu') ON CONFLICT (email) DO NOTHING")
+            .expect("Failed to insert");
+        let result = db.execute("SELECT * FROM users").expect("Failed to select");
+        assert_eq!(result.rows.len(), 4);
+    }
+
+    // Unique indexes survive a restart
+    let mut db = Database::new(test_dir).expect("Failed to reopen database");
+    assert_eq!(db.get_table_indexes("users").map(|indexes| indexes.len()), Some(2));
+    let result = db.execute("INSERT INTO users VALUES (6, 'b@x.com', 'Fay', 'Ho')");
+    assert!(matches!(result, Err(ExecutionError::UniqueViolation { .. })));
+
+    // Clean up
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+/// 测试 SQL 预检
+#[test]
+fn test_validate() {
+    let test_dir = "test_db_validate";
+    let _ = fs::remove_dir_all(test_dir);
+
+    let mut db = Database::new(test_dir).expect("Failed to create database");
+    db.execute("CREATE TABLE users (id INT NOT NULL, name VARCHAR(20))").expect("Failed to create table");
+
+    let validated = db.validate("SELECT name, id FROM users WHERE id > 1").expect("Query should be valid");
+    let schema = validated.result_schema.expect("SELECT has a result schema");
+    assert_eq!(schema.columns[0].name, "name");
+    assert_eq!(schema.columns[1].data_type, DataType::Integer);
+
+    // Statements without a result set validate without a schema
+    let validated = db.validate("INSERT INTO users VALUES (1, 'a')").expect("Insert should be valid");
+    assert!(validated.result_schema.is_none());
+
+    assert!(matches!(db.validate("SELECT FROM"), Err(ExecutionError::ParseError(_))));
+    assert!(matches!(db.validate("SELECT nope FROM users"), Err(ExecutionError::SemanticError(_))));
+    assert!(matches!(db.validate("SELECT * FROM missing"), Err(ExecutionError::SemanticError(_))));
+
+    // Validation never executes the statement
+    db.validate("DROP TABLE users").expect("Drop should be valid");
+    assert!(db.get_table_schema("users").is_some());
+    let result = db.execute("SELECT * FROM users").expect("Failed to select");
+    assert!(result.rows.is_empty());
+
+    // Clean up
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+/// 测试预编译语句的参数类型和结果模式推断
+#[test]
+fn test_prepared_statement_types() {
+    let test_dir = "test_db_prepared_types";
+    let _ = fs::remove_dir_all(test_dir);
+
+    let mut db = Database::new(test_dir).expect("Failed to create database");
+    db.execute("CREATE TABLE users (id INT NOT NULL, name VARCHAR(20), active BOOL)").expect("Failed to create table");
+
+    let stmt = db.prepare("SELECT id, name FROM users WHERE id > $1 AND name = $2").expect("Failed to prepare");
+    assert_eq!(stmt.parameter_types(), &[Some(DataType::Integer), Some(DataType::Varchar(20))]);
+    let schema = stmt.result_schema().expect("SELECT has a result schema");
+    assert_eq!(schema.columns.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(), vec!["id", "name"]);
+
+    let stmt = db.prepare("INSERT INTO users VALUES (?, ?, ?)").expect("Failed to prepare");
+    assert_eq!(stmt.parameter_types(), &[
+        Some(DataType::Integer),
+        Some(DataType::Varchar(20)),
+        Some(DataType::Boolean),
+    ]);
+    assert!(stmt.result_schema().is_none());
+
+    let stmt = db.prepare("UPDATE users SET active = $2 WHERE id = $1 RETURNING name").expect("Failed to prepare");
+    assert_eq!(stmt.parameter_types(), &[Some(DataType::Integer), Some(DataType::Boolean)]);
+    assert_eq!(stmt.result_schema().map(|s| s.columns.len()), Some(1));
+
+    // Preparing is side-effect free and reports semantic errors up front
+    assert!(matches!(db.prepare("SELECT * FROM users WHERE missing = ?"), Err(ExecutionError::SemanticError(_))));
+    let result = db.execute("SELECT * FROM users").expect("Failed to select");
+    assert!(result.rows.is_empty());
+
+    // Clean up
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+/// 测试预编译语句绑定参数执行
+#[test]
+fn test_prepared_statement_execute() {
+    let mut db = Database::in_memory().expect("Failed to create database");
+    db.execute("CREATE TABLE users (id BIGINT NOT NULL, name VARCHAR(20), active BOOL)").expect("Failed to create table");
+
+    let insert = db.prepare("INSERT INTO users VALUES (?, ?, ?)").expect("Failed to prepare");
+    for (id, name) in [(1, "alice"), (2, "o'brien"), (3, "carol")] {
+        // INTEGER 参数按推断的 BIGINT 类型转换后代入
+        let result = insert.execute(&mut db, &[Value::Integer(id), Value::Varchar(name.to_string()), Value::Boolean(id != 2)])
+            .expect("Failed to execute prepared insert");
+        assert_eq!(result.affected_rows, 1);
+    }
+    insert.execute(&mut db, &[Value::BigInt(4), Value::Null, Value::Null]).expect("Failed to insert NULLs");
+
+    // 参数值不经过 SQL 文本，引号和注释符号不会改变语句结构
+    let select = db.prepare("SELECT id FROM users WHERE name = $1 OR id = $2").expect("Failed to prepare");
+    let result = db.execute_prepared(&select, &[Value::Varchar("o'brien".to_string()), Value::BigInt(3)])
+        .expect("Failed to execute prepared select");
+    let mut ids: Vec<Value> = result.rows.into_iter().map(|row| row.values[0].clone()).collect();
+    ids.sort_by_key(|v| v.to_string());
+    assert_eq!(ids, vec![Value::BigInt(2), Value::BigInt(3)]);
+    let result = select.execute(&mut db, &[Value::Varchar("x' OR '1' = '1".to_string()), Value::BigInt(0)])
+        .expect("Failed to execute prepared select");
+    assert!(result.rows.is_empty());
+
+    let update = db.prepare("UPDATE users SET active = $2 WHERE id = $1").expect("Failed to prepare");
+    assert_eq!(update.execute(&mut db, &[Value::BigInt(2), Value::Boolean(true)]).unwrap().affected_rows, 1);
+
+    assert!(matches!(
+        select.execute(&mut db, &[Value::Varchar("alice".to_string())]),
+        Err(ExecutionError::ParameterCountMismatch { expected: 2, actual: 1 })
+    ));
+    assert!(matches!(
+        update.execute(&mut db, &[Value::Varchar("abc".to_string()), Value::Boolean(true)]),
+        Err(ExecutionError::EvaluationError { .. })
+    ));
+
+    // 重复执行按同一指纹计入负载统计
+    let result = db.execute("SELECT calls FROM information_schema.query_stats WHERE query = 'INSERT INTO users VALUES (?, ?, ?)'")
+        .expect("Failed to query workload stats");
+    assert_eq!(result.rows[0].values, vec![Value::BigInt(4)]);
+}
+
+/// 测试查询改写规则
+#[test]
+fn test_rewrite_rules() {
+    use crate::sql::RewriteRule;
+
+    let test_dir = "test_db_rewrite_rules";
+    let _ = fs::remove_dir_all(test_dir);
+
+    {
+        let mut db = Database::new(test_dir).expect("Failed to create database");
+        db.execute("CREATE TABLE clients (id INT, name VARCHAR(20))").expect("Failed to create table");
+        db.execute("INSERT INTO clients VALUES (1, 'a'); INSERT INTO clients VALUES (2, 'b'); INSERT INTO clients VALUES (3, 'c')")
+            .expect("Failed to insert");
+
+        db.add_rewrite_rule(RewriteRule::RenameTable { from: "customers".to_string(), to: "clients".to_string() }).unwrap();
+        db.add_rewrite_rule(RewriteRule::RenameColumn {
+            table: "clients".to_string(),
+            from: "fullname".to_string(),
+            to: "name".to_string(),
+        }).unwrap();
+        db.add_rewrite_rule(RewriteRule::ForceLimit { table: "clients".to_string(), limit: 2 }).unwrap();
+
+        // 旧名称的语句照常执行，结果列名保持不变
+        db.execute("INSERT INTO customers (id, fullname) VALUES (4, 'd')").expect("Failed to insert with legacy names");
+        let result = db.execute("SELECT fullname FROM customers WHERE id > 1 ORDER BY id").expect("Failed to select with legacy names");
+        assert_eq!(result.schema.as_ref().unwrap().columns[0].name, "fullname");
+        assert_eq!(result.rows.len(), 2);
+        assert_eq!(result.rows[0].values, vec![Value::Varchar("b".to_string())]);
+
+        let stmt = db.prepare("UPDATE customers SET fullname = ? WHERE id = ?").expect("Failed to prepare with legacy names");
+        assert_eq!(stmt.execute(&mut db, &[Value::Varchar("z".to_string()), Value::Integer(1)]).unwrap().affected_rows, 1);
+
+        let result = db.execute("SELECT position, rule FROM information_schema.rewrite_rules").expect("Failed to list rules");
+        assert_eq!(result.rows.len(), 3);
+        assert_eq!(result.rows[1].values[1], Value::Varchar("RENAME COLUMN clients.fullname TO name".to_string()));
+    }
+
+    // 规则随元数据持久化
+    let mut db = Database::new(test_dir).expect("Failed to reopen database");
+    assert_eq!(db.rewrite_rules().len(), 3);
+    let removed = db.remove_rewrite_rule(2).expect("Failed to remove rule");
+    assert_eq!(removed, Some(RewriteRule::ForceLimit { table: "clients".to_string(), limit: 2 }));
+    let result = db.execute("SELECT fullname FROM customers").expect("Failed to select");
+    assert_eq!(result.rows.len(), 4);
+    assert_eq!(db.execute("SELECT * FROM customers WHERE fullname = 'z'").unwrap().rows.len(), 1);
+
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+/// 测试显式事务的提交、回滚和失败处理
+#[test]
+fn test_transactions() {
+    let test_dir = "test_db_transactions";
+    let _ = fs::remove_dir_all(test_dir);
+    let count = |db: &mut Database| db.execute("SELECT * FROM accounts").unwrap().rows.len();
+
+    let mut db = Database::new(test_dir).expect("Failed to create database");
+    db.execute("CREATE TABLE accounts (id INT PRIMARY KEY, balance INT)").unwrap();
+    db.execute("INSERT INTO accounts VALUES (1, 100)").unwrap();
+
+    // 事务中的修改对本会话可见，提交前不写入存储
+    db.execute("BEGIN").unwrap();
+    assert!(db.in_transaction());
+    db.execute("INSERT INTO accounts VALUES (2, 50)").unwrap();
+    db.execute("UPDATE accounts SET balance = 0 WHERE id = 1").unwrap();
+    assert_eq!(count(&mut db), 2);
+    assert_eq!(count(&mut Database::new(test_dir).unwrap()), 1);
+    db.execute("COMMIT").unwrap();
+    assert!(!db.in_transaction());
+    assert_eq!(count(&mut Database::new(test_dir).unwrap()), 2);
+
+    // ROLLBACK 丢弃修改，并为表分配新的数据版本
+    let version = db.data_version("accounts").unwrap();
+    db.execute("START TRANSACTION").unwrap();
+    db.execute("DELETE FROM accounts").unwrap();
+    assert_eq!(count(&mut db), 0);
+    db.execute("ROLLBACK").unwrap();
+    assert_eq!(count(&mut db), 2);
+    assert!(db.data_version("accounts").unwrap() > version);
+
+    // 出错后事务失败：其后的语句被拒绝，COMMIT 按 ROLLBACK 处理
+    db.execute("BEGIN").unwrap();
+    db.execute("INSERT INTO accounts VALUES (3, 10)").unwrap();
+    assert!(matches!(db.execute("INSERT INTO accounts VALUES (1, 10)").unwrap_err(), ExecutionError::PrimaryKeyViolation { .. }));
+    assert!(matches!(db.execute("SELECT * FROM accounts").unwrap_err(), ExecutionError::TransactionAborted));
+    assert!(db.execute("COMMIT").unwrap().message.contains("ROLLBACK"));
+    assert_eq!(count(&mut db), 2);
+
+    // DDL 不能在事务中执行
+    db.execute("BEGIN").unwrap();
+    assert!(db.execute("CREATE TABLE other (id INT)").is_err());
+    db.execute("ROLLBACK").unwrap();
+
+    // 推迟的约束在 COMMIT 时检查，失败则回滚整个事务
+    db.execute("SET CONSTRAINTS ALL DEFERRED").unwrap();
+    db.execute("BEGIN").unwrap();
+    db.execute("INSERT INTO accounts VALUES (1, 5)").unwrap();
+    db.execute("DELETE FROM accounts WHERE balance = 0").unwrap();
+    db.execute("COMMIT").unwrap();
+    assert_eq!(count(&mut Database::new(test_dir).unwrap()), 2);
+    db.execute("BEGIN").unwrap();
+    db.execute("INSERT INTO accounts VALUES (2, 5)").unwrap();
+    assert!(matches!(db.execute("COMMIT").unwrap_err(), ExecutionError::PrimaryKeyViolation { .. }));
+    assert!(!db.in_transaction());
+    assert_eq!(count(&mut db), 2);
+
+    drop(db);
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+/// 测试 CREATE UNIQUE INDEX 和主键在 INSERT / UPDATE 时按索引检查重复
+#[test]
+fn test_unique_index_enforcement() {
+    let mut db = Database::in_memory().expect("Failed to create database");
+    db.execute("CREATE TABLE accounts (id INT PRIMARY KEY, login VARCHAR(20), region INT)").unwrap();
+    for id in 0..500 {
+        db.execute(&format!("INSERT INTO accounts VALUES ({}, 'user{}', {})", id, id, id % 7)).unwrap();
+    }
+    db.execute("CREATE UNIQUE INDEX accounts_login ON accounts (login)").unwrap();
+
+    // 插入的行随即进入索引，紧接着的重复也能发现
+    db.execute("INSERT INTO accounts VALUES (500, 'fresh', 1)").unwrap();
+    let result = db.execute("INSERT INTO accounts VALUES (501, 'fresh', 2)");
+    assert!(matches!(result, Err(ExecutionError::UniqueViolation { index, key }) if index == "accounts_login" && key == "('fresh')"));
+    let result = db.execute("INSERT INTO accounts VALUES (499, 'other', 2)");
+    assert!(matches!(result, Err(ExecutionError::PrimaryKeyViolation { key }) if key == "(499)"));
+    db.execute("INSERT INTO accounts VALUES (501, NULL, 2), (502, NULL, 3)").unwrap();
+
+    // UPDATE 的新键与其余的行和被更新的其他行比较
+    let result = db.execute("UPDATE accounts SET login = 'user7' WHERE id = 8");
+    assert!(matches!(result, Err(ExecutionError::UniqueViolation { .. })));
+    let result = db.execute("UPDATE accounts SET id = 3 WHERE id = 4");
+    assert!(matches!(result, Err(ExecutionError::PrimaryKeyViolation { key }) if key == "(3)"));
+    let result = db.execute("UPDATE accounts SET login = 'same' WHERE region = 5");
+    assert!(matches!(result, Err(ExecutionError::UniqueViolation { .. })));
+    // 键整体平移时被更新的旧键不算冲突
+    db.execute("UPDATE accounts SET id = id + 1000").unwrap();
+    db.execute("UPDATE accounts SET login = 'user8' WHERE id = 1007").unwrap_err();
+    db.execute("UPDATE accounts SET login = 'renamed' WHERE id = 1008").unwrap();
+    db.execute("INSERT INTO accounts VALUES (8, 'user8', 1)").unwrap();
+    assert_eq!(db.execute("SELECT * FROM accounts").unwrap().rows.len(), 504);
+}
+
+/// 测试推迟到提交时检查的约束
+#[test]
+fn test_deferred_constraints() {
+    let mut db = Database::in_memory().expect("Failed to create database");
+    db.execute("CREATE TABLE codes (id INT PRIMARY KEY, code VARCHAR(10) UNIQUE)").expect("Failed to create table");
+    db.execute("INSERT INTO codes VALUES (1, 'a'), (2, 'b')").expect("Failed to insert");
+
+    // 交换两行的 code：逐行检查时第一行更新后会与第二行暂时冲突
+    let swap = "INSERT INTO codes VALUES (1, 'b'), (2, 'a') ON CONFLICT (id) DO UPDATE SET code = excluded.code";
+    assert!(matches!(db.execute(swap), Err(ExecutionError::UniqueViolation { .. })));
+
+    db.execute("SET CONSTRAINTS ALL DEFERRED").expect("Failed to defer constraints");
+    assert!(db.constraints_deferred());
+    db.execute(swap).expect("Swap should succeed when checked at commit");
+    let result = db.execute("SELECT code FROM codes ORDER BY id").expect("Failed to select");
+    assert_eq!(result.rows[0].values, vec![Value::Varchar("b".to_string())]);
+
+    // 提交时仍有冲突则整条语句回滚，已插入的行也被撤销
+    let result = db.execute("INSERT INTO codes VALUES (5, 'e'), (1, 'z')");
+    assert!(matches!(result, Err(ExecutionError::PrimaryKeyViolation { .. })));
+    assert_eq!(db.execute("SELECT * FROM codes").unwrap().rows.len(), 2);
+    assert!(matches!(db.execute("UPDATE codes SET code = 'x'"), Err(ExecutionError::UniqueViolation { .. })));
+    assert_eq!(db.execute("SELECT * FROM codes WHERE code = 'x'").unwrap().rows.len(), 0);
+
+    db.execute("SET CONSTRAINTS ALL IMMEDIATE").expect("Failed to restore immediate checks");
+    assert!(!db.constraints_deferred());
+    let swap_back = "INSERT INTO codes VALUES (1, 'a'), (2, 'b') ON CONFLICT (id) DO UPDATE SET code = excluded.code";
+    assert!(db.execute(swap_back).is_err());
+}
+
+/// 测试 DRY RUN 不修改数据
+#[test]
+fn test_dry_run() {
+    let test_dir = "test_db_dry_run";
+    let _ = fs::remove_dir_all(test_dir);
+
+    let mut db = Database::new(test_dir).expect("Failed to create database");
+    db.execute("CREATE TABLE items (id INT, qty INT)").expect("Failed to create table");
+    for (id, qty) in [(1, 0), (2, 5), (3, 0)] {
+        db.execute(&format!("INSERT INTO items VALUES ({}, {})", id, qty)).expect("Failed to insert");
+    }
+
+    let result = db.execute("DRY RUN DELETE FROM items WHERE qty = 0").expect("Failed to dry run");
+    assert_eq!(result.rows.len(), 2);
+    assert_eq!(result.affected_rows, 0);
+    assert!(result.message.contains("would delete 2 of 3"));
+
+    let result = db.execute("DRY RUN UPDATE items SET qty = 1").expect("Failed to dry run");
+    assert_eq!(result.rows.len(), 3);
+
+    let result = db.execute("DRY RUN UPDATE items SET missing = 1");
+    assert!(matches!(result, Err(ExecutionError::ColumnNotFound { .. })));
+
+    // DRY RUN in the wrong place is a parse error, not a real update
+    let result = db.execute("UPDATE items SET qty = 9 WHERE id = 1 DRY RUN");
+    assert!(matches!(result, Err(ExecutionError::ParseError(_))), "{:?}", result);
+    let result = db.execute("SELECT * FROM items WHERE qty = 9").expect("Failed to select");
+    assert!(result.rows.is_empty());
+
+    // Nothing was changed
+    let result = db.execute("SELECT * FROM items WHERE qty = 0").expect("Failed to select");
+    assert_eq!(result.rows.len(), 2);
+
+    // Clean up
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+#[test]
+fn test_like() {
+    let test_dir = "test_db_like";
+    let _ = fs::remove_dir_all(test_dir);
+
+    let mut db = Database::new(test_dir).expect("Failed to create database");
+    db.execute("CREATE TABLE files (id INT, name VARCHAR(50))").expect("Failed to create table");
+    for (id, name) in [(1, "report.txt"), (2, "report_2024.csv"), (3, "100%.txt"), (4, "notes")] {
+        db.execute(&format!("INSERT INTO files VALUES ({}, '{}')", id, name)).expect("Failed to insert");
+    }
+    db.execute("INSERT INTO files VALUES (5, NULL)").expect("Failed to insert");
+
+    let count = |db: &mut Database, sql: &str| db.execute(sql).expect("Failed to select").rows.len();
+    assert_eq!(count(&mut db, "SELECT * FROM files WHERE name LIKE 'report%'"), 2);
+    assert_eq!(count(&mut db, "SELECT * FROM files WHERE name LIKE '%.txt'"), 2);
+    assert_eq!(count(&mut db, "SELECT * FROM files WHERE name LIKE 'note_'"), 1);
+    assert_eq!(count(&mut db, "SELECT * FROM files WHERE name LIKE 'Report%'"), 0);
+    assert_eq!(count(&mut db, "SELECT * FROM files WHERE name LIKE '%!%%' ESCAPE '!'"), 1);
+    assert_eq!(count(&mut db, "SELECT * FROM files WHERE name LIKE 'report!_%' ESCAPE '!'"), 1);
+    // NULL 既不匹配 LIKE 也不匹配 NOT LIKE
+    assert_eq!(count(&mut db, "SELECT * FROM files WHERE name NOT LIKE '%.txt'"), 2);
+
+    let result = db.execute("DELETE FROM files WHERE name LIKE '%.csv'").expect("Failed to delete");
+    assert_eq!(result.affected_rows, 1);
+
+    // Clean up
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+#[test]
+fn test_in_and_between() {
+    let test_dir = "test_db_in_between";
+    let _ = fs::remove_dir_all(test_dir);
+
+    let mut db = Database::new(test_dir).expect("Failed to create database");
+    db.execute("CREATE TABLE scores (id INT, score INT)").expect("Failed to create table");
+    for (id, score) in [(1, 10), (2, 20), (3, 30), (4, 40)] {
+        db.execute(&format!("INSERT INTO scores VALUES ({}, {})", id, score)).expect("Failed to insert");
+    }
+    db.execute("INSERT INTO scores VALUES (5, NULL)").expect("Failed to insert");
+
+    let count = |db: &mut Database, sql: &str| db.execute(sql).expect("Failed to select").rows.len();
+    assert_eq!(count(&mut db, "SELECT * FROM scores WHERE score IN (10, 30, 50)"), 2);
+    assert_eq!(count(&mut db, "SELECT * FROM scores WHERE score NOT IN (10, 30)"), 2);
+    assert_eq!(count(&mut db, "SELECT * FROM scores WHERE score BETWEEN 20 AND 30"), 2);
+    assert_eq!(count(&mut db, "SELECT * FROM scores WHERE score NOT BETWEEN 20 AND 30"), 2);
+    assert_eq!(count(&mut db, "SELECT * FROM scores WHERE score BETWEEN 15.5 AND 25"), 1);
+
+    // NULL 语义：NOT IN 列表含 NULL 时没有行满足，NULL 边界使结果未知
+    assert_eq!(count(&mut db, "SELECT * FROM scores WHERE score IN (10, NULL)"), 1);
+    assert_eq!(count(&mut db, "SELECT * FROM scores WHERE score NOT IN (10, NULL)"), 0);
+    assert_eq!(count(&mut db, "SELECT * FROM scores WHERE score BETWEEN NULL AND 30"), 0);
+    assert_eq!(count(&mut db, "SELECT * FROM scores WHERE score NOT BETWEEN NULL AND 15"), 3);
+
+    let result = db.execute("DELETE FROM scores WHERE id IN (1, 2)").expect("Failed to delete");
+    assert_eq!(result.affected_rows, 2);
+
+    // Clean up
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+#[test]
+fn test_rowid() {
+    let test_dir = "test_db_rowid";
+    let _ = fs::remove_dir_all(test_dir);
+
+    {
+        let mut db = Database::new(test_dir).expect("Failed to create database");
+        db.execute("CREATE TABLE dup (v INT)").expect("Failed to create table");
+        for v in [7, 7, 8] {
+            db.execute(&format!("INSERT INTO dup VALUES ({})", v)).expect("Failed to insert");
+        }
+
+        // rowid 不出现在 SELECT * 中
+        let result = db.execute("SELECT * FROM dup").expect("Failed to select");
+        assert_eq!(result.schema.unwrap().columns.len(), 1);
+
+        let result = db.execute("SELECT rowid, v FROM dup WHERE v = 7").expect("Failed to select");
+        let ids: Vec<Value> = result.rows.iter().map(|row| row.values[0].clone()).collect();
+        assert_eq!(ids, vec![Value::BigInt(1), Value::BigInt(2)]);
+
+        assert!(db.validate("DELETE FROM dup WHERE rowid = 2").is_ok());
+
+        // 删除重复行中的一行
+        let result = db.execute("DELETE FROM dup WHERE rowid = 2").expect("Failed to delete");
+        assert_eq!(result.affected_rows, 1);
+    }
+
+    // rowid 在重新打开后保持不变，且不会被复用
+    let mut db = Database::new(test_dir).expect("Failed to reopen database");
+    db.execute("INSERT INTO dup VALUES (9)").expect("Failed to insert");
+    let result = db.execute("SELECT rowid FROM dup").expect("Failed to select");
+    let ids: Vec<Value> = result.rows.iter().map(|row| row.values[0].clone()).collect();
+    assert_eq!(ids, vec![Value::BigInt(1), Value::BigInt(3), Value::BigInt(4)]);
+
+    // Clean up
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+#[test]
+fn test_deduplicate_table() {
+    let test_dir = "test_db_deduplicate";
+    let _ = fs::remove_dir_all(test_dir);
+
+    let mut db = Database::new(test_dir).expect("Failed to create database");
+    db.execute("CREATE TABLE events (kind VARCHAR(10), n INT)").expect("Failed to create table");
+    for (kind, n) in [("a", 1), ("b", 2), ("a", 1), ("a", 2), ("a", 1)] {
+        db.execute(&format!("INSERT INTO events VALUES ('{}', {})", kind, n)).expect("Failed to insert");
+    }
+
+    let result = db.execute("DEDUPLICATE TABLE events").expect("Failed to deduplicate");
+    assert_eq!(result.affected_rows, 2);
+
+    // 每组保留最早插入的一行
+    let result = db.execute("SELECT rowid FROM events").expect("Failed to select");
+    let ids: Vec<Value> = result.rows.iter().map(|row| row.values[0].clone()).collect();
+    assert_eq!(ids, vec![Value::BigInt(1), Value::BigInt(2), Value::BigInt(4)]);
+
+    let result = db.execute("DEDUPLICATE TABLE events").expect("Failed to deduplicate");
+    assert_eq!(result.affected_rows, 0);
+    assert!(matches!(db.execute("DEDUPLICATE TABLE missing"), Err(ExecutionError::TableNotFound { .. })));
+
+    // Clean up
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+#[test]
+fn test_alter_table_add_constraint() {
+    let test_dir = "test_db_alter_constraint";
+    let _ = fs::remove_dir_all(test_dir);
+
+    let mut db = Database::new(test_dir).expect("Failed to create database");
+    db.execute("CREATE TABLE users (id INT, email VARCHAR(50))").expect("Failed to create table");
+    db.execute("CREATE TABLE orders (id INT, user_id INT)").expect("Failed to create table");
+    for (id, email) in [(1, "a@x"), (2, "b@x"), (2, "a@x")] {
+        db.execute(&format!("INSERT INTO users VALUES ({}, '{}')", id, email)).expect("Failed to insert");
+    }
+    db.execute("INSERT INTO orders VALUES (1, 1)").expect("Failed to insert");
+    db.execute("INSERT INTO orders VALUES (2, 3)").expect("Failed to insert");
+
+    // 现有数据违反约束时拒绝添加，模式保持不变
+    let result = db.execute("ALTER TABLE users ADD PRIMARY KEY (id)");
+    assert!(matches!(result, Err(ExecutionError::PrimaryKeyViolation { .. })));
+    let result = db.execute("ALTER TABLE users ADD CONSTRAINT users_email_key UNIQUE (email)");
+    assert!(matches!(result, Err(ExecutionError::UniqueViolation { .. })));
+    assert!(db.get_table_indexes("users").unwrap().is_empty());
+
+    db.execute("DELETE FROM users WHERE rowid = 3").expect("Failed to delete");
+    db.execute("ALTER TABLE users ADD PRIMARY KEY (id)").expect("Failed to add primary key");
+    db.execute("ALTER TABLE users ADD CONSTRAINT users_email_key UNIQUE (email)").expect("Failed to add unique");
+    let names: Vec<&str> = db.get_table_indexes("users").unwrap().iter().map(|index| index.name.as_str()).collect();
+    assert_eq!(names, vec!["users_pkey", "users_email_key"]);
+
+    // 新约束对之后的写入生效
+    let result = db.execute("INSERT INTO users VALUES (1, 'c@x')");
+    assert!(matches!(result, Err(ExecutionError::PrimaryKeyViolation { .. })));
+    let result = db.execute("INSERT INTO users VALUES (3, 'b@x')");
+    assert!(matches!(result, Err(ExecutionError::UniqueViolation { .. })));
+    let result = db.execute("ALTER TABLE users ADD PRIMARY KEY (email)");
+    assert!(matches!(result, Err(ExecutionError::SemanticError(_))));
+
+    // 外键之后的写入无法检查，因此拒绝添加
+    let result = db.execute("ALTER TABLE orders ADD FOREIGN KEY (user_id) REFERENCES users (id)");
+    assert!(matches!(
+        result,
+        Err(ExecutionError::NotImplemented { capability: super::Capability::ForeignKeys, .. })
+    ), "{:?}", result);
+
+    // Clean up
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+#[test]
+#[cfg(feature = "chrono")]
+fn test_date_functions() {
+    use chrono::NaiveDate;
+
+    let test_dir = "test_db_date_functions";
+    let _ = fs::remove_dir_all(test_dir);
+
+    let mut db = Database::new(test_dir).expect("Failed to create database");
+    db.execute("CREATE TABLE tasks (id INT, due DATE)").expect("Failed to create table");
+    db.execute("INSERT INTO tasks VALUES (1, DATE_SUB(CURRENT_DATE, 10))").expect("Failed to insert");
+    db.execute("INSERT INTO tasks VALUES (2, CURRENT_DATE + 3)").expect("Failed to insert");
+    db.execute("INSERT INTO tasks VALUES (3, DATE_ADD(CURRENT_DATE, 1, 'YEAR'))").expect("Failed to insert");
+
+    let count = |db: &mut Database, sql: &str| db.execute(sql).expect("Failed to select").rows.len();
+    assert_eq!(count(&mut db, "SELECT * FROM tasks WHERE due < CURRENT_DATE"), 1);
+    assert_eq!(count(&mut db, "SELECT * FROM tasks WHERE due - CURRENT_DATE BETWEEN 0 AND 30"), 1);
+    assert_eq!(count(&mut db, "SELECT * FROM tasks WHERE due > NOW()"), 2);
+
+    let result = db.execute("SELECT id, EXTRACT(YEAR FROM due) AS y FROM tasks WHERE id = 3").expect("Failed to select");
+    let schema = result.schema.unwrap();
+    assert_eq!(schema.columns[1].name, "y");
+    assert_eq!(schema.columns[1].data_type, DataType::Integer);
+    let this_year = db.execute("SELECT EXTRACT(YEAR FROM CURRENT_DATE) FROM tasks WHERE id = 1")
+        .expect("Failed to select").rows[0].values[0].clone();
+    match (&result.rows[0].values[1], this_year) {
+        (Value::Integer(next), Value::Integer(now)) => assert_eq!(*next, now + 1),
+        other => panic!("Expected integer years, got {:?}", other),
+    }
+
+    // 月份运算在月末截断
+    let jan_31 = Value::Date(NaiveDate::from_ymd_opt(2024, 1, 31).unwrap());
+    assert_eq!(
+        super::functions::date_add(&jan_31, 1, "MONTH").unwrap(),
+        Value::Date(NaiveDate::from_ymd_opt(2024, 2, 29).unwrap())
+    );
+    assert!(matches!(super::functions::date_add(&jan_31, 2, "HOUR").unwrap(), Value::Timestamp(_)));
+    assert!(super::functions::date_add(&jan_31, 1, "FORTNIGHT").is_err());
+
+    // Clean up
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+#[test]
+#[cfg(feature = "chrono")]
+fn test_date_literals() {
+    use chrono::NaiveDate;
+
+    let test_dir = "test_db_date_literals";
+    let _ = fs::remove_dir_all(test_dir);
+
+    let mut db = Database::new(test_dir).expect("Failed to create database");
+    db.execute("CREATE TABLE events (id INT PRIMARY KEY, day DATE, at TIMESTAMP)").expect("Failed to create table");
+    db.execute("INSERT INTO events VALUES (1, DATE '2024-05-01', TIMESTAMP '2024-05-01 10:00:00')").expect("Failed to insert");
+    // 字符串常量写入日期和时间戳列时隐式转换，DATE 写入 TIMESTAMP 列为当天零点
+    db.execute("INSERT INTO events VALUES (2, '2024-06-15', '2024-06-15 08:30:00.5'), (3, '2024-07-01', DATE '2024-07-01')")
+        .expect("Failed to insert");
+    assert!(db.execute("INSERT INTO events VALUES (4, '2024-13-01', NULL)").is_err());
+    assert!(db.execute("INSERT INTO events VALUES (4, DATE 'soon', NULL)").is_err());
+
+    let day = |m, d| NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+    let result = db.execute("SELECT day, at FROM events WHERE id = 3").unwrap();
+    assert_eq!(result.rows[0].values, vec![Value::Date(day(7, 1)), Value::Timestamp(day(7, 1).and_hms_opt(0, 0, 0).unwrap())]);
+
+    // 与字符串常量和带类型的常量比较
+    let ids = |db: &mut Database, sql: &str| -> Vec<Value> {
+        db.execute(sql).expect(sql).rows.into_iter().map(|row| row.values[0].clone()).collect()
+    };
+    assert_eq!(ids(&mut db, "SELECT id FROM events WHERE day = '2024-06-15'"), vec![Value::Integer(2)]);
+    assert_eq!(ids(&mut db, "SELECT id FROM events WHERE day >= DATE '2024-06-01' ORDER BY id"), vec![Value::Integer(2), Value::Integer(3)]);
+    assert_eq!(ids(&mut db, "SELECT id FROM events WHERE at < '2024-05-01 12:00'"), vec![Value::Integer(1)]);
+    assert_eq!(ids(&mut db, "SELECT id FROM events WHERE day BETWEEN '2024-05-01' AND DATE '2024-05-31'"), vec![Value::Integer(1)]);
+    db.execute("CREATE INDEX idx_events_day ON events (day)").expect("Failed to create index");
+    assert_eq!(ids(&mut db, "SELECT id FROM events WHERE day = '2024-07-01'"), vec![Value::Integer(3)]);
+
+    db.execute("UPDATE events SET day = '2024-08-01' WHERE id = 1").expect("Failed to update");
+    assert_eq!(db.execute("SELECT day FROM events WHERE id = 1").unwrap().rows[0].values[0], Value::Date(day(8, 1)));
+    assert!(db.execute("UPDATE events SET day = 'tomorrow' WHERE id = 1").is_err());
+
+    // Clean up
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+#[test]
+fn test_decimal_type() {
+    let test_dir = "test_db_decimal_type";
+    let _ = fs::remove_dir_all(test_dir);
+
+    let mut db = Database::new(test_dir).expect("Failed to create database");
+    db.execute("CREATE TABLE orders (id INT, amount NUMERIC(10, 2))").expect("Failed to create table");
+    db.execute("INSERT INTO orders VALUES (1, 0.1), (2, 0.2), (3, 19.995)").expect("Failed to insert");
+    let text = |db: &mut Database, sql: &str| -> Vec<String> {
+        db.execute(sql).expect(sql).rows.iter().map(|row| row.values[0].to_string()).collect()
+    };
+
+    // 写入时按小数位数舍入，求和是精确的
+    assert_eq!(text(&mut db, "SELECT amount FROM orders ORDER BY amount DESC"), vec!["20.00", "0.20", "0.10"]);
+    let result = db.execute("SELECT SUM(amount), AVG(amount) FROM orders").expect("Failed to aggregate");
+    assert_eq!(result.rows[0].values[0], Value::Decimal("20.30".parse().unwrap()));
+    assert_eq!(result.rows[0].values[0].to_string(), "20.30");
+    assert_eq!(result.rows[0].values[1].to_string(), "6.766667");
+    assert_eq!(result.schema.unwrap().columns[0].data_type, DataType::Decimal(38, 2));
+
+    // 与整数和小数常量运算保持 DECIMAL，与 DOUBLE 运算得到 DOUBLE
+    assert_eq!(text(&mut db, "SELECT ABS(amount * 3 - 1) FROM orders WHERE id = 3"), vec!["59.00"]);
+    assert_eq!(text(&mut db, "SELECT ABS(amount / 3) FROM orders WHERE id = 2"), vec!["0.066667"]);
+    assert_eq!(text(&mut db, "SELECT ABS(amount * 0.5) FROM orders WHERE id = 3"), vec!["10.000"]);
+    assert_eq!(text(&mut db, "SELECT ABS(amount + -0.3) FROM orders WHERE id = 1"), vec!["0.20"]);
+    assert!(matches!(
+        db.execute("SELECT ABS(amount * CAST(0.5 AS DOUBLE)) FROM orders WHERE id = 3").unwrap().rows[0].values[0],
+        Value::Double(_)
+    ));
+    assert_eq!(text(&mut db, "SELECT id FROM orders WHERE amount > 0.15 AND amount < 20"), vec!["2"]);
+    assert_eq!(text(&mut db, "SELECT id FROM orders WHERE amount = 20"), vec!["3"]);
+    db.execute("CREATE TABLE prices (id INT, d DECIMAL(8, 2))").expect("Failed to create table");
+    db.execute("INSERT INTO prices VALUES (1, 1.10)").expect("Failed to insert");
+    assert_eq!(text(&mut db, "SELECT id FROM prices WHERE d + 0.1 = 1.2"), vec!["1"]);
+    assert_eq!(text(&mut db, "SELECT id FROM prices WHERE 0.3 = d - 0.8"), vec!["1"]);
+
+    // 超出精度时报错；UPDATE 同样按列的小数位数舍入
+    assert!(db.execute("INSERT INTO orders VALUES (4, 123456789)").is_err());
+    db.execute("UPDATE orders SET amount = amount / 3 WHERE id = 1").expect("Failed to update");
+    assert_eq!(text(&mut db, "SELECT amount FROM orders WHERE id = 1"), vec!["0.03"]);
+    assert_eq!(text(&mut db, "SELECT ROUND(amount, 0) FROM orders WHERE id = 3"), vec!["20"]);
+    assert_eq!(
+        text(&mut db, "SELECT CAST('12345678901234567.89' AS DECIMAL(20, 2)) FROM orders WHERE id = 1"),
+        vec!["12345678901234567.89"]
+    );
+
+    // 重新打开后值和小数位数不变
+    drop(db);
+    let mut db = Database::new(test_dir).expect("Failed to reopen database");
+    assert_eq!(text(&mut db, "SELECT amount FROM orders ORDER BY id"), vec!["0.03", "0.20", "20.00"]);
+
+    // Clean up
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+#[test]
+fn test_text_and_blob_types() {
+    let test_dir = "test_db_text_blob";
+    let _ = fs::remove_dir_all(test_dir);
+
+    // 比一页大得多的值存入溢出页链
+    let long: String = (0..3 * crate::DEFAULT_PAGE_SIZE).map(|i| char::from(b'a' + (i % 26) as u8)).collect();
+    {
+        let mut db = Database::new(test_dir).expect("Failed to create database");
+        db.execute("CREATE TABLE docs (id INT, body TEXT, data BLOB)").expect("Failed to create table");
+        db.execute(&format!("INSERT INTO docs VALUES (1, '{}', X'00FF10'), (2, 'short', B64'SGVsbG8=')", long))
+            .expect("Failed to insert");
+        db.execute("INSERT INTO docs VALUES (3, NULL, CAST('\\x0A0b' AS BLOB))").expect("Failed to insert");
+
+        let result = db.execute("SELECT body, data FROM docs WHERE id = 2").unwrap();
+        let columns = &result.schema.as_ref().unwrap().columns;
+        assert_eq!((&columns[0].data_type, &columns[1].data_type), (&DataType::Text, &DataType::Blob));
+        assert_eq!(result.rows[0].values, vec![Value::Varchar("short".to_string()), Value::Blob(b"Hello".to_vec())]);
+
+        // BLOB 按字节比较和排序；LENGTH 对 BLOB 返回字节数
+        let result = db.execute("SELECT id, LENGTH(body), LENGTH(data), data FROM docs ORDER BY data").unwrap();
+        let rows: Vec<_> = result.rows.iter().map(|row| row.values[..3].to_vec()).collect();
+        assert_eq!(rows, vec![
+            vec![Value::Integer(1), Value::Integer(long.len() as i32), Value::Integer(3)],
+            vec![Value::Integer(3), Value::Null, Value::Integer(2)],
+            vec![Value::Integer(2), Value::Integer(5), Value::Integer(5)],
+        ]);
+        let result = db.execute("SELECT id FROM docs WHERE data = X'0a0b'").unwrap();
+        assert_eq!(result.rows[0].values[0], Value::Integer(3));
+        let result = db.execute("SELECT CAST(data AS TEXT) FROM docs WHERE id = 1").unwrap();
+        assert_eq!(result.rows[0].values[0], Value::Varchar("\\x00FF10".to_string()));
+        assert!(db.execute("SELECT X'ABC'").is_err());
+        db.execute("CHECKPOINT").expect("Failed to checkpoint");
+    }
+
+    // 重新打开后长文本和二进制值原样读回
+    let mut db = Database::new(test_dir).expect("Failed to reopen database");
+    let result = db.execute("SELECT body, data FROM docs WHERE id = 1").unwrap();
+    assert_eq!(result.rows[0].values, vec![Value::Varchar(long), Value::Blob(vec![0x00, 0xff, 0x10])]);
+
+    // Clean up
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+#[test]
+fn test_char_type() {
+    let test_dir = "test_db_char_type";
+    let _ = fs::remove_dir_all(test_dir);
+
+    let mut db = Database::new(test_dir).expect("Failed to create database");
+    db.execute("CREATE TABLE codes (id INT PRIMARY KEY, code CHAR(5), flag CHAR)").expect("Failed to create table");
+    // 不足长度的补空格，超出部分只有空格时去掉
+    db.execute("INSERT INTO codes VALUES (1, 'ab', 'y'), (2, 'abc      ', 'n')").expect("Failed to insert");
+    assert!(db.execute("INSERT INTO codes VALUES (3, 'abcdef', 'y')").is_err());
+    assert!(db.execute("INSERT INTO codes VALUES (3, 'ab', 'yes')").is_err());
+
+    let result = db.execute("SELECT code, flag, LENGTH(code) FROM codes WHERE id = 2").unwrap();
+    assert_eq!(result.schema.as_ref().unwrap().columns[0].data_type, DataType::Char(5));
+    assert_eq!(result.rows[0].values, vec![
+        Value::Varchar("abc  ".to_string()),
+        Value::Varchar("n".to_string()),
+        Value::Integer(5),
+    ]);
+
+    // 比较时忽略尾部空格
+    let ids = |db: &mut Database, sql: &str| -> Vec<Value> {
+        db.execute(sql).expect(sql).rows.into_iter().map(|row| row.values[0].clone()).collect()
+    };
+    assert_eq!(ids(&mut db, "SELECT id FROM codes WHERE code = 'ab'"), vec![Value::Integer(1)]);
+    assert_eq!(ids(&mut db, "SELECT id FROM codes WHERE code = 'abc   '"), vec![Value::Integer(2)]);
+    assert_eq!(ids(&mut db, "SELECT id FROM codes WHERE code < 'abc'"), vec![Value::Integer(1)]);
+    assert_eq!(ids(&mut db, "SELECT id FROM codes WHERE code IN ('x', 'abc')"), vec![Value::Integer(2)]);
+    assert_eq!(ids(&mut db, "SELECT id FROM codes WHERE code BETWEEN 'ab' AND 'ab'"), vec![Value::Integer(1)]);
+    assert_eq!(ids(&mut db, "SELECT id FROM codes WHERE code LIKE '%c'"), vec![Value::Integer(2)]);
+
+    db.execute("UPDATE codes SET code = 'xy' WHERE id = 2").expect("Failed to update");
+    assert_eq!(db.execute("SELECT code FROM codes WHERE id = 2").unwrap().rows[0].values[0], Value::Varchar("xy   ".to_string()));
+    assert!(db.execute("UPDATE codes SET code = 'toolong' WHERE id = 2").is_err());
+
+    // 索引中的键是补齐后的值，常量按同样的方式补齐
+    db.execute("CREATE INDEX idx_codes_code ON codes (code)").expect("Failed to create index");
+    assert_eq!(ids(&mut db, "SELECT id FROM codes WHERE code = 'xy '"), vec![Value::Integer(2)]);
+    assert!(ids(&mut db, "SELECT id FROM codes WHERE code = 'xy   z'").is_empty());
+
+    // Clean up
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+#[test]
+fn test_string_length_enforcement() {
+    let dir = tempfile::TempDir::new().expect("Failed to create temp dir");
+    let mut db = Database::in_memory().expect("Failed to create database");
+    db.execute("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(10), code CHAR(2))").expect("Failed to create table");
+
+    // 超长报错，长度按字符计，超出部分只有空格时去掉
+    let err = db.execute(&format!("INSERT INTO users VALUES (1, '{}', 'a')", "x".repeat(500))).unwrap_err();
+    assert!(err.to_string().contains("最大长度 10，实际长度 500"), "{}", err);
+    db.execute("INSERT INTO users VALUES (1, '数据库管理系统的引擎', 'a'), (2, 'padded    ', 'b   ')").expect("Failed to insert");
+    let result = db.execute("SELECT name FROM users WHERE id = 2").unwrap();
+    assert_eq!(result.rows[0].values[0], Value::Varchar("padded    ".to_string()));
+    assert!(db.execute("UPDATE users SET name = 'much too long' WHERE id = 1").is_err());
+    assert!(db.execute("UPDATE users SET name = UPPER('much too long') WHERE id = 1").is_err());
+    assert!(db.execute("INSERT INTO users VALUES (2, 'x', 'b') ON CONFLICT (id) DO UPDATE SET name = 'much too long'").is_err());
+    db.execute("INSERT INTO users VALUES (2, 'x', 'b') ON CONFLICT (id) DO UPDATE SET code = 'c'").expect("Failed to upsert");
+    let result = db.execute("SELECT name, code FROM users WHERE id = 2").unwrap();
+    assert_eq!(result.rows[0].values, vec![Value::Varchar("padded    ".to_string()), Value::Varchar("c ".to_string())]);
+
+    // COPY 同样检查
+    let csv = dir.path().join("users.csv");
+    fs::write(&csv, "id,name,code\n3,ok,c\n4,far too long for it,d\n").unwrap();
+    assert!(db.execute(&format!("COPY users FROM '{}' (HEADER)", csv.display())).is_err());
+    assert_eq!(db.execute("SELECT COUNT(*) FROM users").unwrap().rows[0].values[0], Value::Integer(2));
+
+    // 开启截断后截断到声明的长度
+    db.execute("SET truncate_strings = on").expect("Failed to set");
+    db.execute("INSERT INTO users VALUES (5, 'abcdefghijklmnop', 'xyz')").expect("Failed to insert");
+    db.execute("UPDATE users SET name = 'much too long' WHERE id = 1").expect("Failed to update");
+    db.execute(&format!("COPY users FROM '{}' (HEADER)", csv.display())).expect("Failed to copy");
+    let result = db.execute("SELECT id, name, code FROM users WHERE id IN (1, 4, 5) ORDER BY id").unwrap();
+    let rows: Vec<_> = result.rows.into_iter().map(|row| row.values).collect();
+    let text = |s: &str| Value::Varchar(s.to_string());
+    assert_eq!(rows, vec![
+        vec![Value::Integer(1), text("much too l"), text("a ")],
+        vec![Value::Integer(4), text("far too lo"), text("d ")],
+        vec![Value::Integer(5), text("abcdefghij"), text("xy")],
+    ]);
+    assert!(db.execute("SET truncate_strings = maybe").is_err());
+}
+
+#[test]
+fn test_uuid_type() {
+    let test_dir = "test_db_uuid";
+    let _ = fs::remove_dir_all(test_dir);
+    let known = "550e8400-e29b-41d4-a716-446655440000";
+
+    let generated = {
+        let mut db = Database::new(test_dir).expect("Failed to create database");
+        db.execute("CREATE TABLE events (id UUID PRIMARY KEY DEFAULT GEN_UUID(), name VARCHAR(20))")
+            .expect("Failed to create table");
+        // 默认值逐行调用 GEN_UUID()，每行得到不同的第 4 版 UUID
+        db.execute("INSERT INTO events (name) VALUES ('a'), ('b'), ('c')").expect("Failed to insert");
+        db.execute("INSERT INTO events VALUES (DEFAULT, 'd')").expect("Failed to insert");
+        db.execute(&format!("INSERT INTO events VALUES ('{}', 'known')", known.to_uppercase())).expect("Failed to insert");
+
+        let result = db.execute("SELECT id FROM events").unwrap();
+        let ids: Vec<crate::types::Uuid> = result.rows.iter()
+            .map(|row| match row.values[0] {
+                Value::Uuid(u) => u,
+                ref other => panic!("expected UUID, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(ids.len(), 5);
+        assert_eq!(ids.iter().collect::<std::collections::HashSet<_>>().len(), 5);
+        assert!(ids.iter().filter(|u| u.to_string() != known).all(|u| u.version() == 4));
+
+        // 字符串常量按 UUID 解析后比较，主键上有重复时报错
+        let result = db.execute("SELECT name FROM events WHERE id = '550e8400e29b41d4a716446655440000'").unwrap();
+        assert_eq!(result.rows[0].values[0], Value::Varchar("known".to_string()));
+        assert!(db.execute(&format!("INSERT INTO events VALUES ('{}', 'again')", known)).is_err());
+        assert!(db.execute("INSERT INTO events VALUES ('not-a-uuid', 'bad')").is_err());
+        assert!(db.execute("SELECT name FROM events WHERE id = 'zz'").unwrap().rows.is_empty());
+
+        let result = db.execute("SELECT CAST(id AS VARCHAR(36)), LENGTH(CAST(id AS BLOB)) FROM events WHERE name = 'known'").unwrap();
+        assert_eq!(result.rows[0].values, vec![Value::Varchar(known.to_string()), Value::Integer(16)]);
+        db.execute("CHECKPOINT").expect("Failed to checkpoint");
+        ids
+    };
+
+    // 重新打开后 UUID 原样读回，默认值仍逐行生成
+    let mut db = Database::new(test_dir).expect("Failed to reopen database");
+    let result = db.execute("SELECT id FROM events WHERE name = 'a'").unwrap();
+    assert_eq!(result.rows[0].values[0], Value::Uuid(generated[0]));
+    db.execute("INSERT INTO events (name) VALUES ('e')").expect("Failed to insert");
+    let result = db.execute("SELECT id FROM events WHERE name = 'e'").unwrap();
+    assert!(matches!(result.rows[0].values[0], Value::Uuid(u) if !generated.contains(&u)));
+
+    // UPDATE 写入的字符串同样按 UUID 解析
+    db.execute(&format!("UPDATE events SET id = '{}' WHERE name = 'e'", known.replace('0', "1"))).expect("Failed to update");
+    assert!(matches!(db.execute("SELECT id FROM events WHERE name = 'e'").unwrap().rows[0].values[0], Value::Uuid(_)));
+    assert!(db.execute("UPDATE events SET id = 'nope' WHERE name = 'e'").is_err());
+
+    // Clean up
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+#[test]
+fn test_json_type() {
+    let test_dir = "test_db_json";
+    let _ = fs::remove_dir_all(test_dir);
+
+    {
+        let mut db = Database::new(test_dir).expect("Failed to create database");
+        db.execute("CREATE TABLE people (id INT PRIMARY KEY, doc JSON)").expect("Failed to create table");
+        db.execute(r#"INSERT INTO people VALUES
+            (1, '{"name": "Ada", "age": 36, "tags": ["math", "code"], "address": {"city": "London"}}'),
+            (2, '{"name": "Alan", "age": 41, "tags": [], "nickname": null}'),
+            (3, NULL)"#).expect("Failed to insert");
+        // 写入时校验，存储紧凑形式
+        let err = db.execute("INSERT INTO people VALUES (4, '{\"name\": }')").unwrap_err();
+        assert!(err.to_string().contains("JSON"), "{}", err);
+        let result = db.execute("SELECT doc FROM people WHERE id = 2").unwrap();
+        assert_eq!(result.rows[0].values[0], Value::Json(r#"{"name":"Alan","age":41,"tags":[],"nickname":null}"#.to_string()));
+
+        // -> 得到 JSON，->> 得到文本；取不到的键和 JSON null 为 NULL
+        let result = db.execute("SELECT id, doc ->> 'name', doc -> 'tags' -> 0, doc -> 'address' ->> 'city', doc ->> 'nickname' FROM people").unwrap();
+        let columns = &result.schema.as_ref().unwrap().columns;
+        assert_eq!((&columns[1].data_type, &columns[2].data_type), (&DataType::Text, &DataType::Json));
+        assert_eq!(result.rows[0].values[1..4], [
+            Value::Varchar("Ada".to_string()),
+            Value::Json("\"math\"".to_string()),
+            Value::Varchar("London".to_string()),
+        ]);
+        assert_eq!(result.rows[1].values[1..], [Value::Varchar("Alan".to_string()), Value::Null, Value::Null, Value::Null]);
+        assert!(result.rows[2].values[1..].iter().all(Value::is_null));
+
+        // WHERE 中使用取值运算符和 JSON_EXTRACT
+        let result = db.execute("SELECT id FROM people WHERE doc ->> 'name' = 'Alan'").unwrap();
+        assert_eq!(result.rows[0].values[0], Value::Integer(2));
+        let result = db.execute("SELECT id FROM people WHERE CAST(doc ->> 'age' AS INT) < 40").unwrap();
+        assert_eq!(result.rows[0].values[0], Value::Integer(1));
+        let result = db.execute("SELECT JSON_EXTRACT(doc, '$.tags[1]') FROM people WHERE JSON_EXTRACT(doc, '$.address.city') IS NOT NULL").unwrap();
+        assert_eq!(result.rows[0].values[0], Value::Json("\"code\"".to_string()));
+        assert!(db.execute("SELECT JSON_EXTRACT(doc, 'tags') FROM people").is_err());
+
+        db.execute("UPDATE people SET doc = '[1, 2,  3]' WHERE id = 2").expect("Failed to update");
+        assert!(db.execute("UPDATE people SET doc = 'oops' WHERE id = 2").is_err());
+        db.execute("CHECKPOINT").expect("Failed to checkpoint");
+    }
+
+    // 重新打开后文档原样读回，与空白不同的同一文档相等
+    let mut db = Database::new(test_dir).expect("Failed to reopen database");
+    let result = db.execute("SELECT id, doc -> -1 FROM people WHERE doc = '[1,2,3]'").unwrap();
+    assert_eq!(result.rows[0].values, vec![Value::Integer(2), Value::Json("3".to_string())]);
+
+    // Clean up
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+#[test]
+fn test_column_stats_view() {
+    let test_dir = "test_db_column_stats";
+    let _ = fs::remove_dir_all(test_dir);
+
+    let mut db = Database::new(test_dir).expect("Failed to create database");
+    db.execute("CREATE TABLE people (id INT, city VARCHAR(20))").expect("Failed to create table");
+    for (id, city) in [(3, "'Oslo'"), (1, "'Lima'"), (2, "'Oslo'"), (4, "NULL")] {
+        db.execute(&format!("INSERT INTO people VALUES ({}, {})", id, city)).expect("Failed to insert");
+    }
+
+    let result = db.execute(
+        "SELECT column_name, null_count, distinct_count, min_value, max_value \
+         FROM information_schema.column_stats WHERE table_name = 'people'"
+    ).expect("Failed to query column stats");
+    assert_eq!(result.rows.len(), 2);
+    assert_eq!(result.rows[0].values, vec![
+        Value::Varchar("id".to_string()),
+        Value::BigInt(0),
+        Value::BigInt(4),
+        Value::Varchar("1".to_string()),
+        Value::Varchar("4".to_string()),
+    ]);
+    assert_eq!(result.rows[1].values, vec![
+        Value::Varchar("city".to_string()),
+        Value::BigInt(1),
+        Value::BigInt(2),
+        Value::Varchar("Lima".to_string()),
+        Value::Varchar("Oslo".to_string()),
+    ]);
+    assert!(db.validate("SELECT row_count FROM information_schema.column_stats").is_ok());
+
+    // Clean up
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+/// 测试 ANALYZE：统计写入表快照，重新打开后仍然可用，之后的修改不影响已收集的统计
+#[test]
+fn test_analyze() {
+    let test_dir = "test_db_analyze";
+    let _ = fs::remove_dir_all(test_dir);
+
+    let mut db = Database::new(test_dir).expect("Failed to create database");
+    db.execute("CREATE TABLE scores (id INT, player VARCHAR(20), points INT)").unwrap();
+    db.execute("CREATE TABLE empty (id INT)").unwrap();
+    for id in 1..=40 {
+        let player = if id % 8 == 0 { "NULL".to_string() } else { format!("'p{}'", id % 3) };
+        db.execute(&format!("INSERT INTO scores VALUES ({}, {}, {})", id, player, id * 10)).unwrap();
+    }
+    assert!(db.table_statistics("scores").is_none());
+    assert_eq!(db.execute("SELECT * FROM information_schema.statistics").unwrap().rows.len(), 0);
+
+    assert_eq!(db.execute("ANALYZE scores").unwrap().message, "ANALYZE: 1 table(s)");
+    let stats = db.table_statistics("scores").unwrap().clone();
+    assert_eq!(stats.row_count, 40);
+    let player = stats.column("player").unwrap();
+    assert_eq!((player.null_count, player.distinct_count), (5, 3));
+    let points = stats.column("points").unwrap();
+    assert_eq!((points.min.clone(), points.max.clone()), (Some(Value::Integer(10)), Some(Value::Integer(400))));
+    assert_eq!(points.histogram.len(), 11);
+
+    let result = db.execute(
+        "SELECT row_count, distinct_count, min_value, max_value, histogram \
+         FROM information_schema.statistics WHERE column_name = 'id'"
+    ).unwrap();
+    assert_eq!(result.rows[0].values, vec![
+        Value::BigInt(40),
+        Value::BigInt(40),
+        Value::Varchar("1".to_string()),
+        Value::Varchar("40".to_string()),
+        Value::Varchar("[1, 4, 8, 12, 16, 20, 24, 28, 32, 36, 40]".to_string()),
+    ]);
+
+    // 统计只在 ANALYZE 时更新
+    db.execute("DELETE FROM scores WHERE id > 20").unwrap();
+    assert_eq!(db.table_statistics("scores").unwrap().row_count, 40);
+    drop(db);
+
+    let mut db = Database::new(test_dir).expect("Failed to reopen database");
+    assert_eq!(db.table_statistics("scores"), Some(&stats));
+    assert_eq!(db.execute("ANALYZE").unwrap().message, "ANALYZE: 2 table(s)");
+    assert_eq!(db.table_statistics("scores").unwrap().row_count, 20);
+    assert_eq!(db.table_statistics("empty").unwrap().column("id").unwrap().min, None);
+
+    assert!(matches!(db.execute("ANALYZE missing"), Err(ExecutionError::TableNotFound { .. })));
+    db.execute("BEGIN").unwrap();
+    assert!(db.execute("ANALYZE scores").is_err());
+    db.execute("ROLLBACK").unwrap();
+
+    // 删除表时统计随之删除
+    db.execute("DROP TABLE scores").unwrap();
+    db.execute("CREATE TABLE scores (id INT)").unwrap();
+    assert!(db.table_statistics("scores").is_none());
+
+    drop(db);
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+/// 测试基于代价的优化：EXPLAIN 显示按统计估计的行数，连接选择建表侧和算法
+#[test]
+fn test_cost_based_explain() {
+    use crate::sql::{ExecutionPlan, JoinAlgorithm};
+    use crate::sql::planner::JoinType;
+
+    let mut db = Database::in_memory().expect("Failed to create database");
+    db.execute("CREATE TABLE a (id INT PRIMARY KEY, k INT)").unwrap();
+    db.execute("CREATE TABLE b (id INT PRIMARY KEY, a_id INT, v INT)").unwrap();
+    for i in 0..10 {
+        db.execute(&format!("INSERT INTO a VALUES ({}, {})", i, i % 3)).unwrap();
+    }
+    for i in 0..200 {
+        db.execute(&format!("INSERT INTO b VALUES ({}, {}, {})", i, i % 10, i)).unwrap();
+    }
+    let explain = |db: &mut Database, sql: &str| match &db.execute(&format!("EXPLAIN {}", sql)).unwrap().rows[0].values[0] {
+        Value::Varchar(text) => text.lines().skip(1).map(str::to_string).collect::<Vec<_>>(),
+        other => panic!("Expected plan text, got {:?}", other),
+    };
+
+    // 没有统计时用默认选择性，ANALYZE 之后按直方图估计
+    assert_eq!(explain(&mut db, "SELECT * FROM b WHERE v < 50")[1], "2. Filter: Apply WHERE conditions (estimated rows=67 of 200)");
+    db.execute("ANALYZE").unwrap();
+    assert_eq!(explain(&mut db, "SELECT * FROM b WHERE v < 50")[1], "2. Filter: Apply WHERE conditions (estimated rows=51 of 200)");
+    assert_eq!(explain(&mut db, "SELECT * FROM b WHERE a_id = 3")[1], "2. Filter: Apply WHERE conditions (estimated rows=20 of 200)");
+    assert_eq!(explain(&mut db, "SELECT * FROM b WHERE v > 1000")[1], "2. Filter: Apply WHERE conditions (estimated rows=0 of 200)");
+
+    // 较小的 a 换到左侧建哈希表；WHERE 中只涉及一张表的条件下推到该表的扫描
+    let plan = explain(&mut db, "SELECT * FROM b JOIN a ON a.id = b.a_id WHERE b.v < 50");
+    assert_eq!(plan[0], "Project (estimated rows=51, cost=332.0)");
+    assert_eq!(plan[1], "-> Hash Join (Inner) ON a.id = b.a_id (estimated rows=51, cost=332.0)");
+    assert_eq!(plan[2], "    -> Table Scan: a (estimated rows=10, cost=10.0)");
+    assert_eq!(plan[3], "    -> Table Scan: b (b.v < 50) (estimated rows=51, cost=200.0)");
+
+    // 左侧只剩约 3 行时逐对比较比建哈希表便宜
+    let plan = explain(&mut db, "SELECT * FROM b JOIN a ON a.id = b.a_id WHERE b.v < 50 AND a.k = 1");
+    assert_eq!(plan[1], "-> Nested Loop Join (Inner) ON a.id = b.a_id (estimated rows=17, cost=295.0)");
+    assert_eq!(plan[2], "    -> Table Scan: a (a.k = 1) (estimated rows=3, cost=10.0)");
+    assert_eq!(plan[3], "    -> Table Scan: b (b.v < 50) (estimated rows=51, cost=200.0)");
+
+    // 没有等值条件时只能嵌套循环；LEFT JOIN 交换两侧后为 RIGHT JOIN
+    let plan = explain(&mut db, "SELECT * FROM b LEFT JOIN a ON a.k > b.v");
+    assert!(plan[1].starts_with("-> Nested Loop Join (Right) ON a.k > b.v"));
+    assert!(plan[2].starts_with("    -> Table Scan: a "));
+
+    // 只查询部分列时各表只扫描用到的列
+    let plan = explain(&mut db, "SELECT b.v FROM b JOIN a ON a.id = b.a_id WHERE b.v < 50");
+    assert_eq!(plan[2], "    -> Table Scan: a [id] (estimated rows=10, cost=10.0)");
+    assert_eq!(plan[3], "    -> Table Scan: b [a_id, v] (b.v < 50) (estimated rows=51, cost=200.0)");
+
+    let compiled = db.compile("SELECT b.v FROM a JOIN b ON a.id = b.a_id WHERE a.id = 2").unwrap().plan.unwrap();
+    assert_eq!(compiled.stats.joins_reordered, 0);
+    assert_eq!(compiled.stats.projections_pushed, 2);
+    match compiled.plan {
+        ExecutionPlan::Project { input, .. } => match *input {
+            ExecutionPlan::Join { left, algorithm, join_type, .. } => {
+                // 按 id 过滤后估计只剩一行，嵌套循环比建哈希表便宜；`a.id = 2` 可以走主键索引
+                assert_eq!((algorithm, join_type), (JoinAlgorithm::NestedLoop, JoinType::Inner));
+                assert!(matches!(*left, ExecutionPlan::IndexScan { ref table_name, ref index_name, .. } if table_name == "a" && index_name == "a_pkey"), "{:?}", left);
+                assert_eq!(crate::sql::cost::estimate(&left, &db).rows, 1.0);
+            }
+            other => panic!("Expected Join, got {:?}", other),
+        },
+        other => panic!("Expected Project, got {:?}", other),
+    }
+}
+
+/// 测试三张表的连接按估计的中间结果重排，避开笛卡尔积
+#[test]
+fn test_join_reordering() {
+    let mut db = Database::in_memory().expect("Failed to create database");
+    db.execute("CREATE TABLE regions (id INT PRIMARY KEY, name VARCHAR(10))").unwrap();
+    db.execute("CREATE TABLE customers (id INT PRIMARY KEY, region INT)").unwrap();
+    db.execute("CREATE TABLE orders (id INT PRIMARY KEY, customer INT, total INT)").unwrap();
+    for id in 0..4 {
+        db.execute(&format!("INSERT INTO regions VALUES ({}, 'r{}')", id, id)).unwrap();
+    }
+    for id in 0..20 {
+        db.execute(&format!("INSERT INTO customers VALUES ({}, {})", id, id % 4)).unwrap();
+    }
+    for id in 0..300 {
+        db.execute(&format!("INSERT INTO orders VALUES ({}, {}, {})", id, id % 20, id)).unwrap();
+    }
+    db.execute("ANALYZE").unwrap();
+    let explain = |db: &mut Database, sql: &str| match &db.execute(&format!("EXPLAIN {}", sql)).unwrap().rows[0].values[0] {
+        Value::Varchar(text) => text.lines().skip(1).map(str::to_string).collect::<Vec<_>>(),
+        other => panic!("Expected plan text, got {:?}", other),
+    };
+
+    // 按书写顺序 orders 与 regions 之间没有条件，是 1200 行的笛卡尔积
+    let sql = "SELECT * FROM orders JOIN regions JOIN customers \
+               ON orders.customer = customers.id AND customers.region = regions.id WHERE regions.name = 'r1'";
+    let plan = explain(&mut db, sql);
+    assert_eq!(plan.iter().filter(|line| line.contains("Join")).count(), 2);
+    assert!(plan.iter().filter(|line| line.contains("Join")).all(|line| line.contains(" ON ")), "{:#?}", plan);
+    // 先连接两张小表，过滤后的结果作为建表侧再连接 orders
+    assert!(plan[1].starts_with("-> Hash Join (Inner) ON orders.customer = customers.id"), "{:#?}", plan);
+    assert!(plan[2].contains("Join (Inner) ON customers.region = regions.id"), "{:#?}", plan);
+    assert!(plan.iter().any(|line| line.contains("Table Scan: regions (regions.name = 'r1') (estimated rows=1,")), "{:#?}", plan);
+    assert!(plan[plan.len() - 1].starts_with("    -> Table Scan: orders "), "{:#?}", plan);
+
+    let compiled = db.compile(sql).unwrap().plan.unwrap();
+    assert!(compiled.stats.joins_reordered >= 1);
+
+    // 执行按重排后的计划进行：先连接 regions 和 customers，再与 orders 做哈希连接
+    let report = match &db.execute(&format!("EXPLAIN ANALYZE {}", sql)).unwrap().rows[0].values[0] {
+        Value::Varchar(text) => text.clone(),
+        other => panic!("Expected plan text, got {:?}", other),
+    };
+    let actual: Vec<&str> = report.lines().skip_while(|line| *line != "Actual execution:").skip(1).collect();
+    assert!(actual[0].starts_with("Hash Join (Inner) on regions, customers, orders (actual rows=75,"), "{}", report);
+    assert!(actual[1].starts_with("-> Nested Loop Join (Inner) on regions, customers (actual rows=5,"), "{}", report);
+    assert!(actual[2].starts_with("    -> Scan on regions (actual rows=1,"), "{}", report);
+    assert!(actual[3].starts_with("    -> Scan on customers (actual rows=20,"), "{}", report);
+    assert!(actual[4].starts_with("-> Scan on orders (actual rows=300,"), "{}", report);
+
+    let result = db.execute(&format!("{} ORDER BY orders.id", sql)).unwrap();
+    assert_eq!(result.rows.len(), 75);
+    assert_eq!(result.schema.unwrap().columns.len(), 7);
+    assert!(result.rows.iter().all(|row| row.values[4] == Value::Varchar("r1".to_string()) && row.values[6] == Value::Integer(1)));
+    assert_eq!(result.rows[0].values[..3], [Value::Integer(1), Value::Integer(1), Value::Integer(1)]);
+}
+
+/// 测试两侧输入都已按连接键有序时选择归并连接
+#[test]
+fn test_merge_join_strategy() {
+    let mut db = Database::in_memory().expect("Failed to create database");
+    db.execute("CREATE TABLE shipments (id INT PRIMARY KEY, part INT)").unwrap();
+    db.execute("CREATE TABLE stock (id INT PRIMARY KEY, part INT)").unwrap();
+    for id in 0..200 {
+        db.execute(&format!("INSERT INTO shipments VALUES ({}, {})", id, id % 20)).unwrap();
+        db.execute(&format!("INSERT INTO stock VALUES ({}, {})", id, id % 20)).unwrap();
+    }
+    db.execute("CREATE INDEX idx_shipments_part ON shipments (part)").unwrap();
+    db.execute("CREATE INDEX idx_stock_part ON stock (part)").unwrap();
+    db.execute("ANALYZE").unwrap();
+
+    // 索引等值扫描把两侧的连接键都固定为 3，输出天然有序，无需排序或建哈希表
+    let sql = "SELECT * FROM shipments JOIN stock ON shipments.part = stock.part \
+               WHERE shipments.part = 3 AND stock.part = 3";
+    let plan = match &db.execute(&format!("EXPLAIN {}", sql)).unwrap().rows[0].values[0] {
+        Value::Varchar(text) => text.lines().skip(1).map(str::to_string).collect::<Vec<_>>(),
+        other => panic!("Expected plan text, got {:?}", other),
+    };
+    assert!(plan[1].starts_with("-> Merge Join (Inner) ON shipments.part = stock.part"), "{:#?}", plan);
+    assert_eq!(plan.iter().filter(|line| line.contains("Index Scan")).count(), 2, "{:#?}", plan);
+    assert!(!plan.iter().any(|line| line.contains("Sort")), "{:#?}", plan);
+
+    let compiled = db.compile(sql).unwrap().plan.unwrap();
+    assert_eq!(compiled.stats.merge_joins, 1);
+
+    // 不等值连接不能归并
+    let compiled = db.compile("SELECT * FROM shipments JOIN stock ON shipments.part < stock.part \
+                               WHERE shipments.part = 3 AND stock.part = 3").unwrap().plan.unwrap();
+    assert_eq!(compiled.stats.merge_joins, 0);
+}
+
+/// 测试 JOIN 按优化器选择的连接算法执行
+#[test]
+fn test_join_execution() {
+    use super::config::DatabaseConfig;
+    use std::sync::Arc;
+
+    let observer = Arc::new(RecordingObserver::default());
+    let config = DatabaseConfig::default().with_observer(observer.clone());
+    let mut db = Database::with_backend(Box::new(crate::storage::MemoryBackend::new()), config).unwrap();
+    db.execute("CREATE TABLE shipments (id INT PRIMARY KEY, part INT)").unwrap();
+    db.execute("CREATE TABLE stock (id INT PRIMARY KEY, part INT)").unwrap();
+    for id in 0..200 {
+        db.execute(&format!("INSERT INTO shipments VALUES ({}, {})", id, id % 20)).unwrap();
+        db.execute(&format!("INSERT INTO stock VALUES ({}, {})", id, id % 20)).unwrap();
+    }
+    db.execute("CREATE INDEX idx_shipments_part ON shipments (part)").unwrap();
+    db.execute("CREATE INDEX idx_stock_part ON stock (part)").unwrap();
+    db.execute("ANALYZE").unwrap();
+    let rows = |result: super::database::QueryResult| -> Vec<Vec<Value>> {
+        result.rows.into_iter().map(|row| row.values).collect()
+    };
+    let names = |result: &super::database::QueryResult| -> Vec<String> {
+        result.schema.as_ref().unwrap().columns.iter().map(|column| column.name.clone()).collect()
+    };
+    let ran = |observer: &RecordingObserver, operator: &str| {
+        observer.events.lock().unwrap().iter().any(|event| event.starts_with(&format!("close {} ", operator)))
+    };
+
+    // EXPLAIN 中的归并连接就是实际执行的算子：两侧各 10 行 part = 3，连接出 100 行
+    observer.events.lock().unwrap().clear();
+    let result = db.execute("SELECT shipments.id, stock.id FROM shipments JOIN stock ON shipments.part = stock.part \
+                             WHERE shipments.part = 3 AND stock.part = 3").unwrap();
+    assert_eq!(names(&result), vec!["id", "id"]);
+    assert_eq!(result.rows.len(), 100);
+    assert!(result.rows.iter().all(|row| matches!(
+        (&row.values[0], &row.values[1]),
+        (Value::Integer(a), Value::Integer(b)) if a % 20 == 3 && b % 20 == 3
+    )));
+    assert!(ran(&observer, "Merge Join (Inner)"), "{:#?}", observer.events.lock().unwrap());
+
+    // 没有索引可用的等值连接建哈希表；SELECT * 的列名带表名
+    observer.events.lock().unwrap().clear();
+    let result = db.execute("SELECT * FROM shipments JOIN stock ON shipments.id = stock.part ORDER BY stock.id LIMIT 3").unwrap();
+    assert_eq!(names(&result), vec!["shipments.id", "shipments.part", "stock.id", "stock.part"]);
+    assert_eq!(rows(result), (0..3).map(|id| vec![
+        Value::Integer(id), Value::Integer(id), Value::Integer(id), Value::Integer(id),
+    ]).collect::<Vec<_>>());
+    assert!(ran(&observer, "Hash Join (Inner)"), "{:#?}", observer.events.lock().unwrap());
+
+    // 不等值条件只能嵌套循环
+    observer.events.lock().unwrap().clear();
+    let result = db.execute("SELECT COUNT(*) FROM shipments JOIN stock ON shipments.id < stock.id \
+                             WHERE shipments.id < 3 AND stock.id < 3").unwrap();
+    assert_eq!(rows(result), vec![vec![Value::Integer(3)]]);
+    assert!(ran(&observer, "Nested Loop Join (Inner)"), "{:#?}", observer.events.lock().unwrap());
+
+    // 外连接保留没有匹配的行，另一侧的列为 NULL；连接结果可以排序和分组
+    db.execute("CREATE TABLE parts (part INT, name VARCHAR(10))").unwrap();
+    db.execute("INSERT INTO parts VALUES (3, 'bolt')").unwrap();
+    db.execute("INSERT INTO parts VALUES (99, 'nut')").unwrap();
+    let result = db.execute("SELECT name, COUNT(stock.id) FROM parts LEFT JOIN stock ON parts.part = stock.part \
+                             GROUP BY name ORDER BY name").unwrap();
+    assert_eq!(rows(result), vec![
+        vec![Value::Varchar("bolt".to_string()), Value::Integer(10)],
+        vec![Value::Varchar("nut".to_string()), Value::Integer(0)],
+    ]);
+    let result = db.execute("SELECT parts.name, stock.id FROM parts LEFT JOIN stock ON parts.part = stock.part \
+                             WHERE stock.id IS NULL OR stock.id < 10 ORDER BY stock.id DESC NULLS LAST").unwrap();
+    assert_eq!(rows(result), vec![
+        vec![Value::Varchar("bolt".to_string()), Value::Integer(3)],
+        vec![Value::Varchar("nut".to_string()), Value::Null],
+    ]);
+
+    let result = db.execute("SELECT COUNT(*) FROM parts FULL JOIN stock ON parts.part = stock.part").unwrap();
+    assert_eq!(rows(result), vec![vec![Value::Integer(201)]]);
+
+    // 两张表都有的列不加表名时有歧义
+    assert!(db.execute("SELECT part FROM parts JOIN stock ON parts.part = stock.part").is_err());
+}
+
+/// 测试 IN / EXISTS 子查询被改写为半连接和反连接
+#[test]
+fn test_subquery_decorrelation() {
+    let mut db = Database::in_memory().expect("Failed to create database");
+    db.execute("CREATE TABLE customers (id INT NOT NULL PRIMARY KEY, region VARCHAR(10))").unwrap();
+    db.execute("CREATE TABLE orders (id INT PRIMARY KEY, customer_id INT NOT NULL, total INT)").unwrap();
+    for i in 0..20 {
+        db.execute(&format!("INSERT INTO customers VALUES ({}, 'r{}')", i, i % 4)).unwrap();
+    }
+    for i in 0..100 {
+        db.execute(&format!("INSERT INTO orders VALUES ({}, {}, {})", i, i % 20, i * 10)).unwrap();
+    }
+    db.execute("ANALYZE").unwrap();
+    let explain = |db: &mut Database, sql: &str| match &db.execute(&format!("EXPLAIN {}", sql)).unwrap().rows[0].values[0] {
+        Value::Varchar(text) => text.lines().skip(1).map(str::to_string).collect::<Vec<_>>(),
+        other => panic!("Expected plan text, got {:?}", other),
+    };
+
+    // 相关条件成为连接条件，只涉及子查询表的条件留在子查询一侧
+    let plan = explain(&mut db, "SELECT region FROM customers WHERE EXISTS (SELECT 1 FROM orders WHERE orders.customer_id = customers.id AND total > 500)");
+    assert_eq!(plan.len(), 4, "{:#?}", plan);
+    assert!(plan[1].starts_with("-> Hash Join (Semi) ON orders.customer_id = customers.id "), "{}", plan[1]);
+    assert!(plan[2].starts_with("    -> Table Scan: customers "), "{}", plan[2]);
+    assert!(plan[3].starts_with("    -> Table Scan: orders [customer_id, total] (total > 500) "), "{}", plan[3]);
+
+    // IN 的左侧与子查询列相等；两列都不允许 NULL 时 NOT IN 改写为反连接
+    let plan = explain(&mut db, "SELECT * FROM customers WHERE id IN (SELECT customer_id FROM orders WHERE total > 500)");
+    assert!(plan[1].starts_with("-> Hash Join (Semi) ON customers.id = orders.customer_id "), "{}", plan[1]);
+    let plan = explain(&mut db, "SELECT * FROM customers WHERE id NOT IN (SELECT customer_id FROM orders)");
+    assert!(plan[1].starts_with("-> Hash Join (Anti) ON customers.id = orders.customer_id "), "{}", plan[1]);
+
+    // 可能为 NULL 的列上 NOT IN 与反连接的结果不同，保留子查询
+    let plan = explain(&mut db, "SELECT * FROM customers WHERE id NOT IN (SELECT total FROM orders)");
+    assert!(plan[1].starts_with("-> Subquery Filter: id NOT IN "), "{}", plan[1]);
+
+    // IN 子查询只能返回一列
+    let error = db.execute("EXPLAIN SELECT * FROM customers WHERE id IN (SELECT id, total FROM orders)").unwrap_err();
+    assert!(error.to_string().contains("IN 子查询必须只返回一列"), "{}", error);
+
+    // 改写后的半连接和反连接实际执行；列名与单表查询相同
+    let rows = |db: &mut Database, sql: &str| -> Vec<Vec<Value>> {
+        db.execute(sql).unwrap().rows.into_iter().map(|row| row.values).collect()
+    };
+    let result = db.execute("SELECT * FROM customers WHERE id IN (SELECT customer_id FROM orders WHERE total > 900) ORDER BY id").unwrap();
+    let names: Vec<&str> = result.schema.as_ref().unwrap().columns.iter().map(|column| column.name.as_str()).collect();
+    assert_eq!(names, vec!["id", "region"]);
+    assert_eq!(result.rows.iter().map(|row| row.values[0].clone()).collect::<Vec<_>>(), (11..20).map(Value::Integer).collect::<Vec<_>>());
+    assert_eq!(
+        rows(&mut db, "SELECT COUNT(*) FROM customers WHERE NOT EXISTS \
+                       (SELECT 1 FROM orders WHERE orders.customer_id = customers.id AND total > 900)"),
+        vec![vec![Value::Integer(11)]]
+    );
+    assert_eq!(
+        rows(&mut db, "SELECT region, COUNT(*) FROM customers WHERE region = 'r3' AND \
+                       EXISTS (SELECT 1 FROM orders WHERE orders.customer_id = customers.id AND total > 900) GROUP BY region"),
+        vec![vec![Value::Varchar("r3".to_string()), Value::Integer(3)]]
+    );
+    assert_eq!(rows(&mut db, "SELECT id FROM customers WHERE id NOT IN (SELECT customer_id FROM orders)"), Vec::<Vec<Value>>::new());
+    let report = match &db.execute("EXPLAIN ANALYZE SELECT id FROM customers WHERE id IN (SELECT customer_id FROM orders WHERE total > 900)")
+        .unwrap().rows[0].values[0]
+    {
+        Value::Varchar(text) => text.clone(),
+        other => panic!("Expected plan text, got {:?}", other),
+    };
+    assert!(report.contains("Join (Semi) on customers (actual rows=9,"), "{}", report);
+
+    // 没有改写的子查询执行器尚不支持，直接报错而不是把条件当作 false
+    for sql in [
+        "SELECT * FROM customers WHERE id NOT IN (SELECT total FROM orders)",
+        "SELECT * FROM customers WHERE region = 'r1' OR id IN (SELECT customer_id FROM orders)",
+        "SELECT * FROM customers WHERE id IN (SELECT id FROM customers WHERE region = 'r1')",
+        "DELETE FROM customers WHERE NOT EXISTS (SELECT 1 FROM orders WHERE orders.customer_id = customers.id)",
+    ] {
+        assert!(matches!(
+            db.execute(sql),
+            Err(ExecutionError::NotImplemented { capability: super::Capability::Subqueries, .. })
+        ), "{}", sql);
+    }
+    assert_eq!(db.execute("SELECT * FROM customers").unwrap().rows.len(), 20);
+}
+
+#[test]
+fn test_math_functions() {
+    let test_dir = "test_db_math_functions";
+    let _ = fs::remove_dir_all(test_dir);
+
+    let mut db = Database::new(test_dir).expect("Failed to create database");
+    db.execute("CREATE TABLE nums (i INT, d DOUBLE)").expect("Failed to create table");
+    db.execute("INSERT INTO nums VALUES (-7, 2.5)").expect("Failed to insert");
+
+    let result = db.execute(
+        "SELECT ABS(i), ROUND(d), ROUND(d * 1.234, 2), CEIL(d), FLOOR(d), MOD(i, 3), POWER(d, 2), SQRT(16) FROM nums"
+    ).expect("Failed to select");
+    assert_eq!(result.rows[0].values, vec![
+        Value::Integer(7),
+        Value::Double(3.0),
+        Value::Double(3.09),
+        Value::Double(3.0),
+        Value::Double(2.0),
+        Value::Integer(-1),
+        Value::Double(6.25),
+        Value::Double(4.0),
+    ]);
+
+    let result = db.execute("SELECT i FROM nums WHERE ABS(i) > 5").expect("Failed to select");
+    assert_eq!(result.rows.len(), 1);
+    assert!(db.execute("SELECT SQRT(i) FROM nums").is_err());
+    assert!(db.execute("SELECT MOD(i, 0) FROM nums").is_err());
+
+    // Clean up
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+/// 测试 CAST 类型转换
+#[test]
+#[cfg(feature = "chrono")]
+fn test_cast() {
+    use chrono::NaiveDate;
+
+    let test_dir = "test_db_cast";
+    let _ = fs::remove_dir_all(test_dir);
+
+    let mut db = Database::new(test_dir).expect("Failed to create database");
+    db.execute("CREATE TABLE t (s VARCHAR(20), d DOUBLE, b BOOLEAN, n INT)").expect("Failed to create table");
+    db.execute("INSERT INTO t VALUES ('2024-02-29', -2.5, true, 0)").expect("Failed to insert");
+
+    let result = db.execute(
+        "SELECT CAST(s AS DATE), CAST(d AS INT), CAST(b AS INT), CAST(n AS BOOLEAN), CAST(d AS VARCHAR(3)) FROM t"
+    ).expect("Failed to select");
+    let date = NaiveDate::from_ymd_opt(2024, 2, 29).unwrap();
+    assert_eq!(result.rows[0].values, vec![
+        Value::Date(date),
+        Value::Integer(-3),
+        Value::Integer(1),
+        Value::Boolean(false),
+        Value::Varchar("-2.".to_string()),
+    ]);
+    let schema = result.schema.expect("SELECT should have a schema");
+    assert_eq!(schema.columns[0].data_type, DataType::Date);
+    assert_eq!(schema.columns[1].data_type, DataType::Integer);
+
+    // Usable in WHERE and as an INSERT value
+    let result = db.execute("SELECT n FROM t WHERE CAST(s AS DATE) = CAST('2024-02-29 00:00:00' AS DATE)")
+        .expect("Failed to select");
+    assert_eq!(result.rows.len(), 1);
+    db.execute("INSERT INTO t VALUES ('x', 1.0, CAST('no' AS BOOLEAN), CAST(2.5 AS INT))")
+        .expect("Failed to insert");
+    let result = db.execute("SELECT b, n FROM t WHERE d = 1.0").expect("Failed to select");
+    assert_eq!(result.rows[0].values, vec![Value::Boolean(false), Value::Integer(3)]);
+
+    assert!(db.execute("SELECT CAST('2024-13-01' AS DATE) FROM t").is_err());
+    assert!(db.execute("SELECT CAST('abc' AS INT) FROM t").is_err());
+    assert!(db.execute("SELECT CAST(b AS DATE) FROM t").is_err());
+
+    // Clean up
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+/// 测试分阶段编译 API
+#[test]
+fn test_compile_stages() {
+    use crate::sql::{ExecutionPlan, Token};
+
+    let test_dir = "test_db_compile_stages";
+    let _ = fs::remove_dir_all(test_dir);
+
+    let mut db = Database::new(test_dir).expect("Failed to create database");
+    db.execute("CREATE TABLE users (id INT, name VARCHAR(20))").expect("Failed to create table");
+
+    let compiled = db.compile("SELECT name FROM users WHERE id = 1").expect("Failed to compile");
+    assert_eq!(compiled.tokens.len(), 8);
+    assert_eq!(compiled.tokens[0].token, Token::Select);
+    assert_eq!(compiled.tokens[1].lexeme, "name");
+    assert!(matches!(compiled.ast, crate::sql::Statement::Select { .. }));
+    assert_eq!(compiled.analyzed.result_schema.as_ref().map(|s| s.columns.len()), Some(1));
+    let plan = compiled.plan.expect("SELECT should be planned");
+    assert!(matches!(plan.plan, ExecutionPlan::Project { .. }));
+
+    // Statements the planner does not handle still compile, without a plan
+    let compiled = db.compile("DEDUPLICATE TABLE users").expect("Failed to compile");
+    assert!(compiled.plan.is_none());
+
+    // Each stage reports its own error
+    assert!(matches!(db.compile("SELECT 'unterminated"), Err(ExecutionError::ParseError(_))));
+    assert!(matches!(db.compile("SELECT FROM"), Err(ExecutionError::ParseError(_))));
+    assert!(matches!(db.compile("SELECT * FROM missing"), Err(ExecutionError::SemanticError(_))));
+
+    // Compiling does not execute
+    db.compile("DELETE FROM users").expect("Failed to compile");
+    db.execute("INSERT INTO users VALUES (1, 'a')").expect("Failed to insert");
+    db.compile("DROP TABLE users").expect("Failed to compile");
+    assert_eq!(db.execute("SELECT * FROM users").expect("Failed to select").rows.len(), 1);
+
+    // Clean up
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+/// 测试 COALESCE 与 NULLIF
+#[test]
+fn test_coalesce_and_nullif() {
+    let test_dir = "test_db_coalesce_nullif";
+    let _ = fs::remove_dir_all(test_dir);
+
+    let mut db = Database::new(test_dir).expect("Failed to create database");
+    db.execute("CREATE TABLE items (id INT, nickname VARCHAR(20), name VARCHAR(20), qty INT, price DOUBLE)")
+        .expect("Failed to create table");
+    db.execute("INSERT INTO items VALUES (1, NULL, 'apple', 0, NULL)").expect("Failed to insert");
+    db.execute("INSERT INTO items VALUES (2, 'bb', 'banana', 3, 1.5)").expect("Failed to insert");
+
+    let result = db.execute(
+        "SELECT COALESCE(nickname, name), COALESCE(price, qty), NULLIF(qty, 0), COALESCE(NULL, NULL) FROM items ORDER BY id"
+    ).expect("Failed to select");
+    assert_eq!(result.rows[0].values, vec![
+        Value::Varchar("apple".to_string()),
+        Value::Integer(0),
+        Value::Null,
+        Value::Null,
+    ]);
+    assert_eq!(result.rows[1].values, vec![
+        Value::Varchar("bb".to_string()),
+        Value::Double(1.5),
+        Value::Integer(3),
+        Value::Null,
+    ]);
+
+    // Mixed numeric arguments are promoted to their common type
+    let result = db.execute("SELECT COALESCE(qty, 2.5) FROM items WHERE id = 2").expect("Failed to select");
+    assert_eq!(result.rows[0].values, vec![Value::Double(3.0)]);
+
+    let result = db.execute("SELECT id FROM items WHERE COALESCE(price, 0) < 1").expect("Failed to select");
+    assert_eq!(result.rows.len(), 1);
+    let result = db.execute("SELECT id FROM items WHERE COALESCE(NULLIF(qty, 0), -1) = -1").expect("Failed to select");
+    assert_eq!(result.rows.len(), 1);
+
+    assert!(db.execute("SELECT COALESCE(name, 1) FROM items").is_err());
+    assert!(db.execute("SELECT COALESCE() FROM items").is_err());
+
+    // Clean up
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+/// 测试 MAX/MIN 保持参数类型
+#[test]
+#[cfg(feature = "chrono")]
+fn test_min_max_types() {
+    use chrono::NaiveDate;
+
+    let test_dir = "test_db_min_max_types";
+    let _ = fs::remove_dir_all(test_dir);
+
+    let mut db = Database::new(test_dir).expect("Failed to create database");
+    db.execute("CREATE TABLE events (kind VARCHAR(10), name VARCHAR(20), seq BIGINT, day DATE)")
+        .expect("Failed to create table");
+    db.execute("INSERT INTO events VALUES ('a', 'pear', 40000, CAST('2024-03-01' AS DATE))").expect("Failed to insert");
+    db.execute("INSERT INTO events VALUES ('a', 'apple', 7, CAST('2023-12-31' AS DATE))").expect("Failed to insert");
+    db.execute("INSERT INTO events VALUES ('b', 'zucchini', NULL, NULL)").expect("Failed to insert");
+
+    let result = db.execute("SELECT MAX(name), MIN(name), MAX(seq), MIN(day) FROM events")
+        .expect("Failed to select");
+    assert_eq!(result.rows[0].values, vec![
+        Value::Varchar("zucchini".to_string()),
+        Value::Varchar("apple".to_string()),
+        Value::BigInt(40000),
+        Value::Date(NaiveDate::from_ymd_opt(2023, 12, 31).unwrap()),
+    ]);
+    let types: Vec<DataType> = result.schema.unwrap().columns.into_iter().map(|col| col.data_type).collect();
+    assert_eq!(types, vec![DataType::Varchar(20), DataType::Varchar(20), DataType::BigInt, DataType::Date]);
+
+    let result = db.execute("SELECT kind, MAX(day), MIN(seq) FROM events GROUP BY kind ORDER BY kind")
+        .expect("Failed to select");
+    assert_eq!(result.rows[0].values[1], Value::Date(NaiveDate::from_ymd_opt(2024, 3, 1).unwrap()));
+    assert_eq!(result.rows[0].values[2], Value::BigInt(7));
+    assert_eq!(result.rows[1].values[1], Value::Null);
+
+    let validated = db.validate("SELECT MAX(day) FROM events").expect("Failed to validate");
+    assert_eq!(validated.result_schema.unwrap().columns[0].data_type, DataType::Date);
+
+    // Clean up
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+/// 测试结果集的驱动类型元数据
+#[test]
+fn test_column_metadata() {
+    let mut db = Database::in_memory().expect("Failed to create database");
+    db.execute("CREATE TABLE accounts (id INT PRIMARY KEY, owner VARCHAR(40) NOT NULL, balance DOUBLE, active BOOLEAN)")
+        .expect("Failed to create table");
+
+    let result = db.execute("SELECT * FROM accounts").expect("Failed to select");
+    let metadata = result.column_metadata();
+    let summary: Vec<(&str, i32, i16, u32, bool)> = metadata.iter()
+        .map(|col| (col.type_name, col.jdbc_type, col.odbc_type, col.precision, col.nullable))
+        .collect();
+    assert_eq!(summary, vec![
+        ("INTEGER", 4, 4, 10, true),
+        ("VARCHAR", 12, 12, 40, false),
+        ("DOUBLE", 8, 8, 15, true),
+        ("BOOLEAN", 16, -7, 1, true),
+    ]);
+    assert_eq!(metadata[1].name, "owner");
+    assert_eq!(metadata[1].display_size, 40);
+
+    assert_eq!(DataType::BigInt.jdbc_type_code(), -5);
+    assert_eq!(DataType::Timestamp.jdbc_type_code(), 93);
+    assert_eq!(DataType::Timestamp.scale(), 9);
+
+    let result = db.execute("INSERT INTO accounts VALUES (1, 'ann', 10.0, true)").expect("Failed to insert");
+    assert!(result.column_metadata().is_empty());
+}
+
+/// 测试 STRING_AGG / GROUP_CONCAT 聚合
+#[test]
+fn test_string_agg() {
+    let mut db = Database::in_memory().expect("Failed to create database");
+    db.execute("CREATE TABLE tags (post INT, tag VARCHAR(20), score INT)").expect("Failed to create table");
+    db.execute("INSERT INTO tags VALUES (1, 'rust', 3)").expect("Failed to insert");
+    db.execute("INSERT INTO tags VALUES (1, 'db', NULL)").expect("Failed to insert");
+    db.execute("INSERT INTO tags VALUES (2, 'sql', 5)").expect("Failed to insert");
+    db.execute("INSERT INTO tags VALUES (1, NULL, 4)").expect("Failed to insert");
+    db.execute("INSERT INTO tags VALUES (3, NULL, NULL)").expect("Failed to insert");
+
+    let result = db.execute(
+        "SELECT post, STRING_AGG(tag, ', '), GROUP_CONCAT(score), COUNT(*) FROM tags GROUP BY post ORDER BY post"
+    ).expect("Failed to select");
+    let rows: Vec<Vec<Value>> = result.rows.into_iter().map(|row| row.values).collect();
+    assert_eq!(rows, vec![
+        vec![Value::Integer(1), Value::Varchar("rust, db".to_string()), Value::Varchar("3,4".to_string()), Value::Integer(3)],
+        vec![Value::Integer(2), Value::Varchar("sql".to_string()), Value::Varchar("5".to_string()), Value::Integer(1)],
+        vec![Value::Integer(3), Value::Null, Value::Null, Value::Integer(1)],
+    ]);
+    assert!(matches!(result.schema.unwrap().columns[1].data_type, DataType::Varchar(_)));
+
+    let result = db.execute("SELECT GROUP_CONCAT(tag, '|') FROM tags WHERE post = 1").expect("Failed to select");
+    assert_eq!(result.rows[0].values, vec![Value::Varchar("rust|db".to_string())]);
+
+    assert!(db.execute("SELECT STRING_AGG(tag) FROM tags").is_err());
+    assert!(db.execute("SELECT STRING_AGG(tag, score) FROM tags").is_err());
+}
+
+/// 测试聚合函数的 NULL 语义
+#[test]
+fn test_aggregate_null_semantics() {
+    let mut db = Database::in_memory().expect("Failed to create database");
+    db.execute("CREATE TABLE readings (sensor INT, value INT, label VARCHAR(10))").expect("Failed to create table");
+    db.execute("INSERT INTO readings VALUES (1, 10, 'a')").expect("Failed to insert");
+    db.execute("INSERT INTO readings VALUES (1, NULL, 'b')").expect("Failed to insert");
+    db.execute("INSERT INTO readings VALUES (1, 20, NULL)").expect("Failed to insert");
+    db.execute("INSERT INTO readings VALUES (2, NULL, NULL)").expect("Failed to insert");
+
+    // NULLs are skipped: AVG is 15 rather than 10
+    let result = db.execute(
+        "SELECT sensor, COUNT(*), COUNT(value), SUM(value), AVG(value), MAX(value), MIN(label) FROM readings GROUP BY sensor ORDER BY sensor"
+    ).expect("Failed to select");
+    assert_eq!(result.rows[0].values, vec![
+        Value::Integer(1), Value::Integer(3), Value::Integer(2), Value::Double(30.0),
+        Value::Double(15.0), Value::Integer(20), Value::Varchar("a".to_string()),
+    ]);
+    // A group with no non-NULL values aggregates to NULL
+    assert_eq!(result.rows[1].values, vec![
+        Value::Integer(2), Value::Integer(1), Value::Integer(0), Value::Null,
+        Value::Null, Value::Null, Value::Null,
+    ]);
+
+    // Without GROUP BY an empty input still yields one row
+    let result = db.execute("SELECT COUNT(*), SUM(value), AVG(value) FROM readings WHERE sensor = 99")
+        .expect("Failed to select");
+    assert_eq!(result.rows.len(), 1);
+    assert_eq!(result.rows[0].values, vec![Value::Integer(0), Value::Null, Value::Null]);
+
+    // Non-numeric input is a type error instead of silently counting as 0
+    assert!(matches!(db.execute("SELECT SUM(label) FROM readings"), Err(ExecutionError::TypeMismatch { .. })));
+    assert!(matches!(db.validate("SELECT AVG(label) FROM readings"), Err(ExecutionError::SemanticError(_))));
+}
+
+/// 测试 STDDEV / VARIANCE 统计聚合
+#[test]
+fn test_stddev_and_variance() {
+    let mut db = Database::in_memory().expect("Failed to create database");
+    db.execute("CREATE TABLE samples (grp INT, x INT)").expect("Failed to create table");
+    for x in [2, 4, 4, 4, 5, 5, 7, 9] {
+        db.execute(&format!("INSERT INTO samples VALUES (1, {})", x)).expect("Failed to insert");
+    }
+    // Large offset: a naive sum-of-squares formula loses all precision here
+    for x in [1_000_000_004, 1_000_000_007, 1_000_000_013, 1_000_000_016] {
+        db.execute(&format!("INSERT INTO samples VALUES (2, {})", x)).expect("Failed to insert");
+    }
+    db.execute("INSERT INTO samples VALUES (3, 42)").expect("Failed to insert");
+    db.execute("INSERT INTO samples VALUES (3, NULL)").expect("Failed to insert");
+
+    let result = db.execute(
+        "SELECT grp, VAR_POP(x), STDDEV_POP(x), VARIANCE(x), STDDEV(x) FROM samples GROUP BY grp ORDER BY grp"
+    ).expect("Failed to select");
+    let as_f64 = |value: &Value| match value {
+        Value::Double(d) => Some(*d),
+        Value::Null => None,
+        other => panic!("expected DOUBLE, got {:?}", other),
+    };
+    let stats: Vec<Vec<Option<f64>>> = result.rows.iter()
+        .map(|row| row.values[1..].iter().map(as_f64).collect())
+        .collect();
+    assert_eq!(stats[0], vec![Some(4.0), Some(2.0), Some(32.0 / 7.0), Some((32.0f64 / 7.0).sqrt())]);
+    assert_eq!(stats[1][2], Some(30.0));
+    // A single value has a population variance but no sample variance
+    assert_eq!(stats[2], vec![Some(0.0), Some(0.0), None, None]);
+
+    let schema = result.schema.unwrap();
+    assert!(schema.columns[1..].iter().all(|col| col.data_type == DataType::Double));
+    let validated = db.validate("SELECT STDDEV_SAMP(x) FROM samples").expect("Failed to validate");
+    assert_eq!(validated.result_schema.unwrap().columns[0].data_type, DataType::Double);
+}
+
+/// 测试供 ORM 构建时校验使用的查询描述
+#[test]
+fn test_describe_query() {
+    let mut db = Database::in_memory().expect("Failed to create database");
+    db.execute("CREATE TABLE users (id INT NOT NULL, name VARCHAR(20), active BOOL)").expect("Failed to create table");
+
+    let description = db.describe("SELECT id, name AS who FROM users WHERE active = $1").expect("Failed to describe");
+    assert_eq!(description.parameters, vec![Some(DataType::Boolean)]);
+    assert!(description.returns_rows());
+    let columns: Vec<(String, &str, bool)> = description.column_metadata().into_iter()
+        .map(|col| (col.name, col.type_name, col.nullable))
+        .collect();
+    assert_eq!(columns, vec![
+        ("id".to_string(), "INTEGER", false),
+        ("who".to_string(), "VARCHAR", true),
+    ]);
+
+    // Descriptions survive a round trip so they can be cached for offline builds
+    let cached = serde_json::to_string(&description).expect("Failed to serialize");
+    let restored: super::QueryDescription = serde_json::from_str(&cached).expect("Failed to deserialize");
+    assert_eq!(restored, description);
+
+    // A schema change shows up as a different description
+    let insert = db.describe("INSERT INTO users VALUES ($1, $2, $3)").expect("Failed to describe");
+    assert!(!insert.returns_rows());
+    db.execute("DROP TABLE users").expect("Failed to drop table");
+    db.execute("CREATE TABLE users (id BIGINT NOT NULL, name VARCHAR(40), active BOOL)").expect("Failed to create table");
+    let changed = db.describe("INSERT INTO users VALUES ($1, $2, $3)").expect("Failed to describe");
+    assert_ne!(changed, insert);
+
+    assert!(matches!(db.describe("SELECT missing FROM users"), Err(ExecutionError::SemanticError(_))));
+}
+
+/// 测试能力清单与 NotImplemented 错误中的能力名
+#[test]
+fn test_capabilities() {
+    use super::{Capability, Support};
+
+    let mut db = Database::in_memory().expect("Failed to create database");
+    let capabilities = db.capabilities();
+    assert_eq!(capabilities.len(), Capability::ALL.len());
+    let joins = capabilities.iter().find(|info| info.capability == Capability::Joins).unwrap();
+    assert_eq!(joins.support, Support::Partial);
+    assert!(db.supports(Capability::Aggregates));
+    assert!(!db.supports(Capability::WindowFunctions));
+
+    db.execute("CREATE TABLE a (id INT)").expect("Failed to create table");
+    db.execute("CREATE TABLE b (id INT)").expect("Failed to create table");
+    let unsupported = |db: &mut Database, sql: &str| match db.execute(sql) {
+        Err(ExecutionError::NotImplemented { capability, .. }) => capability,
+        other => panic!("expected NotImplemented for {}, got {:?}", sql, other),
+    };
+    assert_eq!(unsupported(&mut db, "SELECT id, COUNT(*) FROM a GROUP BY id HAVING COUNT(*) > 1"), Capability::Having);
+    assert_eq!(unsupported(&mut db, "SELECT 1 FROM a"), Capability::SelectExpressions);
+    assert_eq!(unsupported(&mut db, "SELECT * FROM a WHERE id = 1 OR EXISTS (SELECT id FROM b)"), Capability::Subqueries);
+
+    // The capability name is part of the message so plain-text tools can match it too
+    let message = db.execute("SELECT id, COUNT(*) FROM a GROUP BY id HAVING COUNT(*) > 1").unwrap_err().to_string();
+    assert!(message.contains("'having'"), "{}", message);
+}
+
+#[test]
+fn test_workload_stats() {
+    let mut db = Database::in_memory().expect("Failed to create database");
+    db.execute("CREATE TABLE items (id INT, name VARCHAR(20))").expect("Failed to create table");
+    for id in 1..=3 {
+        db.execute(&format!("INSERT INTO items VALUES ({}, 'item{}')", id, id)).expect("Failed to insert");
+    }
+    db.execute("SELECT name FROM items WHERE id = 1").expect("Failed to select");
+    db.execute("select name from items where id = 2;").expect("Failed to select");
+    assert!(db.execute("SELECT name FROM missing").is_err());
+
+    let result = db.execute(
+        "SELECT query, calls, errors, rows FROM information_schema.query_stats WHERE calls > 1"
+    ).expect("Failed to query workload stats");
+    let mut rows: Vec<Vec<Value>> = result.rows.into_iter().map(|row| row.values).collect();
+    rows.sort_by(|a, b| a[0].to_string().cmp(&b[0].to_string()));
+    assert_eq!(rows, vec![
+        vec![
+            Value::Varchar("INSERT INTO items VALUES (?, ?)".to_string()),
+            Value::BigInt(3),
+            Value::BigInt(0),
+            Value::BigInt(3),
+        ],
+        vec![
+            Value::Varchar("SELECT name FROM items WHERE id = ?".to_string()),
+            Value::BigInt(2),
+            Value::BigInt(0),
+            Value::BigInt(2),
+        ],
+    ]);
+
+    let result = db.execute("SELECT errors FROM information_schema.query_stats WHERE query = 'SELECT name FROM missing'")
+        .expect("Failed to query workload stats");
+    assert_eq!(result.rows[0].values, vec![Value::BigInt(1)]);
+
+    // 访问最多的表排在最前；系统视图不计入
+    let result = db.execute("SELECT table_name, reads, writes FROM information_schema.table_activity")
+        .expect("Failed to query table activity");
+    assert_eq!(result.rows[0].values, vec![
+        Value::Varchar("items".to_string()),
+        Value::BigInt(2),
+        Value::BigInt(3),
+    ]);
+    assert!(result.rows.iter().all(|row| !row.values[0].to_string().starts_with("information_schema")));
+
+    db.reset_workload_stats();
+    db.set_workload_tracking(false);
+    db.execute("SELECT name FROM items").expect("Failed to select");
+    let result = db.execute("SELECT * FROM information_schema.query_stats").expect("Failed to query workload stats");
+    assert!(result.rows.is_empty());
+    assert!(db.validate("SELECT mean_time_ms FROM information_schema.query_stats").is_ok());
+}
+
+#[test]
+fn test_group_by_expressions() {
+    let mut db = Database::in_memory().expect("Failed to create database");
+    db.set_deterministic_grouping(true);
+    db.execute("CREATE TABLE staff (name VARCHAR(20), dept VARCHAR(20), age INT)").expect("Failed to create table");
+    for (name, dept, age) in [("a", "eng", 25), ("b", "Eng", 34), ("c", "ops", 41), ("d", "OPS", 28), ("e", "eng", 47)] {
+        db.execute(&format!("INSERT INTO staff VALUES ('{}', '{}', {})", name, dept, age)).expect("Failed to insert");
+    }
+
+    let expected = vec![
+        vec![Value::Varchar("ENG".to_string()), Value::Integer(3)],
+        vec![Value::Varchar("OPS".to_string()), Value::Integer(2)],
+    ];
+    for sql in [
+        "SELECT UPPER(dept), COUNT(*) FROM staff GROUP BY UPPER(dept)",
+        "SELECT upper(dept), COUNT(*) FROM staff GROUP BY UPPER(dept)",
+        "SELECT UPPER(dept), COUNT(*) FROM staff GROUP BY 1",
+    ] {
+        let result = db.execute(sql).expect("Failed to group by expression");
+        let rows: Vec<Vec<Value>> = result.rows.into_iter().map(|row| row.values).collect();
+        assert_eq!(rows, expected, "{}", sql);
+    }
+
+    // 按算术表达式分组
+    let result = db.execute("SELECT age / 10, COUNT(*) FROM staff GROUP BY age / 10").expect("Failed to group by arithmetic");
+    let rows: Vec<Vec<Value>> = result.rows.into_iter().map(|row| row.values).collect();
+    assert_eq!(rows, vec![
+        vec![Value::Double(2.5), Value::Integer(1)],
+        vec![Value::Double(2.8), Value::Integer(1)],
+        vec![Value::Double(3.4), Value::Integer(1)],
+        vec![Value::Double(4.1), Value::Integer(1)],
+        vec![Value::Double(4.7), Value::Integer(1)],
+    ]);
+
+    // 只引用分组列的表达式在组内求值；分组列保留原类型
+    let result = db.execute("SELECT dept, LOWER(dept), MAX(age) FROM staff GROUP BY dept").expect("Failed to group by column");
+    let schema = result.schema.expect("GROUP BY result has a schema");
+    assert_eq!(schema.columns[0].data_type, DataType::Varchar(20));
+    assert_eq!(result.rows[0].values, vec![
+        Value::Varchar("Eng".to_string()),
+        Value::Varchar("eng".to_string()),
+        Value::Integer(34),
+    ]);
+
+    for sql in [
+        "SELECT dept, COUNT(*) FROM staff GROUP BY 3",
+        "SELECT dept, COUNT(*) FROM staff GROUP BY 2",
+        "SELECT name, COUNT(*) FROM staff GROUP BY dept",
+    ] {
+        assert!(matches!(db.execute(sql), Err(ExecutionError::SemanticError(_))), "{}", sql);
+    }
+}
+
+#[test]
+fn test_order_by_ordinals_aliases_and_nulls() {
+    let mut db = Database::in_memory().expect("Failed to create database");
+    db.execute("CREATE TABLE scores (name VARCHAR(20), score INT)").expect("Failed to create table");
+    for (name, score) in [("'ann'", "70"), ("'bob'", "NULL"), ("'cy'", "90"), ("'dee'", "80")] {
+        db.execute(&format!("INSERT INTO scores VALUES ({}, {})", name, score)).expect("Failed to insert");
+    }
+
+    let names = |db: &mut Database, sql: &str| -> Vec<Value> {
+        db.execute(sql).expect("Failed to sort").rows.into_iter().map(|row| row.values[0].clone()).collect()
+    };
+    let expect = |names: &[&str]| -> Vec<Value> {
+        names.iter().map(|name| Value::Varchar(name.to_string())).collect()
+    };
+
+    // 默认 NULL 视为最小值
+    assert_eq!(names(&mut db, "SELECT name, score FROM scores ORDER BY 2 DESC"), expect(&["cy", "dee", "ann", "bob"]));
+    assert_eq!(names(&mut db, "SELECT name, score FROM scores ORDER BY 2 DESC NULLS FIRST"), expect(&["bob", "cy", "dee", "ann"]));
+    assert_eq!(names(&mut db, "SELECT name, score FROM scores ORDER BY score NULLS LAST"), expect(&["ann", "dee", "cy", "bob"]));
+    assert_eq!(names(&mut db, "SELECT name, score AS points FROM scores ORDER BY points DESC NULLS LAST"), expect(&["cy", "dee", "ann", "bob"]));
+    // 按被别名覆盖的原列名排序
+    assert_eq!(names(&mut db, "SELECT name AS who FROM scores ORDER BY name DESC"), expect(&["dee", "cy", "bob", "ann"]));
+
+    // 分组结果按聚合表达式排序
+    let result = db.execute("SELECT name, MAX(score) FROM scores GROUP BY name ORDER BY MAX(score) DESC NULLS LAST")
+        .expect("Failed to sort grouped result");
+    let sorted: Vec<Value> = result.rows.into_iter().map(|row| row.values[0].clone()).collect();
+    assert_eq!(sorted, expect(&["cy", "dee", "ann", "bob"]));
+
+    for sql in ["SELECT name FROM scores ORDER BY 2", "SELECT name FROM scores ORDER BY 0"] {
+        assert!(matches!(db.execute(sql), Err(ExecutionError::SemanticError(_))), "{}", sql);
+    }
+
+    // 按没有出现在 SELECT 列表中的列或表达式排序
+    assert_eq!(names(&mut db, "SELECT name FROM scores ORDER BY score DESC NULLS LAST"), expect(&["cy", "dee", "ann", "bob"]));
+    assert_eq!(names(&mut db, "SELECT UPPER(name) FROM scores ORDER BY -score NULLS FIRST LIMIT 2"), expect(&["BOB", "CY"]));
+    let result = db.execute("SELECT * FROM scores ORDER BY rowid DESC").expect("Failed to sort by rowid");
+    assert_eq!(result.schema.unwrap().columns.len(), 2);
+    assert_eq!(result.rows.iter().map(|row| row.values.len()).max(), Some(2));
+    assert_eq!(result.rows.into_iter().map(|row| row.values[0].clone()).collect::<Vec<_>>(), expect(&["dee", "cy", "bob", "ann"]));
+    // 聚合查询只能按输出列排序
+    assert!(db.execute("SELECT name, COUNT(*) FROM scores GROUP BY name ORDER BY score").is_err());
+}
+
+/// 测试 ORDER BY 超出工作内存时溢出到临时文件归并排序
+#[test]
+fn test_external_sort() {
+    use crate::engine::DatabaseConfig;
+
+    let dir = tempfile::TempDir::new().expect("Failed to create temp dir");
+    let config = DatabaseConfig::default().with_sort_work_mem_bytes(512);
+    let mut db = Database::with_config(dir.path(), config).expect("Failed to create database");
+    db.execute("CREATE TABLE events (id INT, bucket INT, label VARCHAR(20))").expect("Failed to create table");
+    for id in 0..300 {
+        db.execute(&format!("INSERT INTO events VALUES ({}, {}, 'event {}')", id, (id * 7) % 10, id)).expect("Failed to insert");
+    }
+
+    let sql = "SELECT id, bucket FROM events ORDER BY bucket DESC, id";
+    let sorted = db.execute(sql).expect("Failed to sort").rows;
+    let mut expected: Vec<(i32, i32)> = (0..300).map(|id| (id, (id * 7) % 10)).collect();
+    expected.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    let expected: Vec<Vec<Value>> = expected.into_iter().map(|(id, bucket)| vec![Value::Integer(id), Value::Integer(bucket)]).collect();
+    assert_eq!(sorted.into_iter().map(|row| row.values).collect::<Vec<_>>(), expected);
+    // 排序结束后临时文件全部删除
+    assert_eq!(db.temp_files().unwrap().used_bytes(), 0);
+
+    // 溢出的顺串计入临时空间配额
+    db.temp_files().unwrap().set_quota_bytes(64);
+    let error = db.execute(sql).unwrap_err();
+    assert!(error.to_string().contains("quota"), "{}", error);
+
+    // 工作内存足够时不写临时文件
+    db.execute("SET sort_work_mem_bytes = 1048576").expect("Failed to set work memory");
+    assert_eq!(db.execute(sql).expect("Failed to sort").rows.len(), 300);
+}
+
+#[test]
+fn test_table_data_versions() {
+    let mut db = Database::in_memory().expect("Failed to create database");
+    db.execute("CREATE TABLE cache_src (id INT PRIMARY KEY, v INT)").expect("Failed to create table");
+    db.execute("CREATE TABLE other (id INT)").expect("Failed to create table");
+    let created = db.data_version("cache_src").expect("table exists");
+
+    db.execute("INSERT INTO cache_src VALUES (1, 10)").expect("Failed to insert");
+    let inserted = db.data_version("cache_src").unwrap();
+    assert!(inserted > created);
+
+    // 读取和没有修改任何行的 DML 不改变版本
+    db.execute("SELECT * FROM cache_src").expect("Failed to select");
+    db.execute("UPDATE cache_src SET v = 11 WHERE id = 99").expect("Failed to update");
+    db.execute("DELETE FROM other").expect("Failed to delete");
+    assert_eq!(db.data_version("cache_src"), Some(inserted));
+
+    db.execute("UPDATE cache_src SET v = 11 WHERE id = 1").expect("Failed to update");
+    let updated = db.data_version("cache_src").unwrap();
+    assert!(updated > inserted);
+    let other = db.data_version("other").unwrap();
+
+    let result = db.execute("SELECT data_version FROM information_schema.table_versions WHERE table_name = 'cache_src'")
+        .expect("Failed to query table versions");
+    assert_eq!(result.rows[0].values, vec![Value::BigInt(updated as i64)]);
+
+    // 删除后重建的表得到更大的版本，不会与旧缓存混淆
+    db.execute("DROP TABLE cache_src").expect("Failed to drop table");
+    assert_eq!(db.data_version("cache_src"), None);
+    db.execute("CREATE TABLE cache_src (id INT)").expect("Failed to recreate table");
+    assert!(db.data_version("cache_src").unwrap() > updated);
+    assert_eq!(db.data_version("other"), Some(other));
+}
+
+/// 测试相同的只读查询命中结果缓存，修改或删除表后失效
+#[test]
+fn test_result_cache() {
+    use crate::engine::{DatabaseConfig, ResultCacheStats};
+
+    let mut db = Database::with_backend(Box::new(crate::storage::MemoryBackend::new()), DatabaseConfig::new().with_result_cache_entries(8))
+        .expect("Failed to create database");
+    db.execute("CREATE TABLE items (id INT PRIMARY KEY, qty INT)").unwrap();
+    db.execute("CREATE TABLE other (id INT)").unwrap();
+    db.execute("INSERT INTO items VALUES (1, 10), (2, 20)").unwrap();
+    let total = |db: &mut Database, sql: &str| db.execute(sql).unwrap().rows.len();
+
+    // 关键字大小写和空白不同的同一查询命中
+    assert_eq!(total(&mut db, "SELECT * FROM items WHERE qty > 5"), 2);
+    assert_eq!(total(&mut db, "select *  from items\nwhere qty > 5"), 2);
+    assert_eq!(db.result_cache_stats(), ResultCacheStats { entries: 1, hits: 1, misses: 1, invalidations: 0 });
+
+    // 修改其他表不影响；修改了行的 DML 使读取该表的结果失效，没有修改任何行的不会
+    db.execute("INSERT INTO other VALUES (1)").unwrap();
+    db.execute("DELETE FROM items WHERE id = 99").unwrap();
+    assert_eq!(db.result_cache_stats().entries, 1);
+    db.execute("INSERT INTO items VALUES (3, 30)").unwrap();
+    assert_eq!(db.result_cache_stats().invalidations, 1);
+    assert_eq!(total(&mut db, "SELECT * FROM items WHERE qty > 5"), 3);
+
+    // 回滚的事务恢复表，读取到的中间结果随之失效；事务中不使用缓存
+    db.execute("BEGIN").unwrap();
+    db.execute("DELETE FROM items WHERE id = 3").unwrap();
+    assert_eq!(total(&mut db, "SELECT * FROM items WHERE qty > 5"), 2);
+    db.execute("ROLLBACK").unwrap();
+    assert_eq!(total(&mut db, "SELECT * FROM items WHERE qty > 5"), 3);
+
+    // 删除重建的表不会返回旧结果
+    db.execute("DROP TABLE items").unwrap();
+    db.execute("CREATE TABLE items (id INT PRIMARY KEY, qty INT)").unwrap();
+    assert_eq!(total(&mut db, "SELECT * FROM items WHERE qty > 5"), 0);
+
+    // 易变函数和系统视图不缓存
+    let before = db.result_cache_stats();
+    db.execute("SELECT RANDOM() FROM other").unwrap();
+    db.execute("SELECT * FROM information_schema.table_versions").unwrap();
+    assert_eq!(db.result_cache_stats(), before);
+
+    db.execute("SET result_cache_entries = 0").unwrap();
+    assert_eq!(db.result_cache_stats().entries, 0);
+    total(&mut db, "SELECT * FROM items WHERE qty > 5");
+    assert_eq!(db.result_cache_stats().misses, before.misses);
+}
+
+#[test]
+fn test_copy_from_infers_schema() {
+    let dir = tempfile::TempDir::new().expect("Failed to create temp dir");
+    let csv = dir.path().join("people.csv");
+    fs::write(&csv, "id,score,Full Name,active\n1,2.5,\"Lee, Ann\",true\n2,,Bo,false\n3,7,,true\n").unwrap();
+    let mut db = Database::in_memory().expect("Failed to create database");
+
+    let sql = format!("COPY people FROM '{}' (HEADER)", csv.display());
+    assert!(matches!(db.execute(&sql), Err(ExecutionError::TableNotFound { .. })));
+
+    let result = db.execute(&format!("COPY people FROM '{}' (HEADER, INFER)", csv.display())).expect("Failed to copy");
+    assert_eq!(result.affected_rows, 3);
+    assert!(result.message.contains(
+        "CREATE TABLE people (id INTEGER, score DOUBLE, Full_Name VARCHAR(255), active BOOLEAN)"
+    ), "{}", result.message);
+
+    let result = db.execute("SELECT id, score, Full_Name, active FROM people ORDER BY id").expect("Failed to select");
+    assert_eq!(result.rows[0].values, vec![
+        Value::Integer(1),
+        Value::Double(2.5),
+        Value::Varchar("Lee, Ann".to_string()),
+        Value::Boolean(true),
+    ]);
+    assert_eq!(result.rows[1].values[1], Value::Null);
+    assert_eq!(result.rows[2].values[2], Value::Null);
+
+    // 已有表按表头列名导入，列顺序可以不同；转换失败时报告行号
+    let more = dir.path().join("more.csv");
+    fs::write(&more, "active,id\nfalse,4\n").unwrap();
+    db.execute(&format!("COPY people FROM '{}' (HEADER, INFER)", more.display())).expect("Failed to copy into existing table");
+    let result = db.execute("SELECT active FROM people WHERE id = 4").expect("Failed to select");
+    assert_eq!(result.rows[0].values, vec![Value::Boolean(false)]);
+
+    fs::write(&more, "4;x\n5;oops\n").unwrap();
+    db.execute("CREATE TABLE pairs (a INT, b VARCHAR(10))").expect("Failed to create table");
+    let result = db.execute(&format!("COPY pairs FROM '{}' (DELIMITER ';')", more.display())).expect("Failed to copy with delimiter");
+    assert_eq!(result.affected_rows, 2);
+    fs::write(&more, "6;ok\nseven;bad\n").unwrap();
+    let err = db.execute(&format!("COPY pairs FROM '{}' (DELIMITER ';')", more.display())).unwrap_err();
+    assert!(err.to_string().contains("line 2"), "{}", err);
+
+    // 推断建表后导入失败时不留下空表
+    fs::write(&more, "a,b\n1,2\n3\n").unwrap();
+    let err = db.execute(&format!("COPY broken FROM '{}' (HEADER, INFER)", more.display())).unwrap_err();
+    assert!(err.to_string().contains("line 3"), "{}", err);
+    assert!(db.get_table_schema("broken").is_none());
+
+    let ndjson = dir.path().join("events.ndjson");
+    fs::write(&ndjson, "{\"kind\": \"click\", \"n\": 1}\n\n{\"kind\": \"view\", \"n\": 9000000000, \"extra\": null}\n").unwrap();
+    db.execute(&format!("COPY events FROM '{}' (INFER)", ndjson.display())).expect("Failed to copy NDJSON");
+    let schema = db.get_table_schema("events").expect("table created").clone();
+    let types: Vec<_> = schema.columns.iter().map(|c| (c.name.as_str(), c.data_type.clone())).collect();
+    assert_eq!(types, vec![("kind", DataType::Varchar(255)), ("n", DataType::BigInt), ("extra", DataType::Varchar(255))]);
+}
+
+/// 测试 COPY FROM 检查全部记录后一起报告出错的行，任何一行出错都不导入
+#[test]
+fn test_copy_from_reports_line_errors() {
+    let dir = tempfile::TempDir::new().expect("Failed to create temp dir");
+    let csv = dir.path().join("items.csv");
+    let mut db = Database::in_memory().expect("Failed to create database");
+    db.execute("CREATE TABLE items (id INT PRIMARY KEY, name VARCHAR(20) NOT NULL, qty INT DEFAULT 1, code VARCHAR(10) UNIQUE)").unwrap();
+    db.execute("INSERT INTO items VALUES (1, 'first', 5, 'A')").unwrap();
+
+    fs::write(&csv, "id,name,code\n2,ok,B\nx,bad,C\n1,dup,D\n5,,E\n6,twin,B\n7,short\n").unwrap();
+    let err = db.execute(&format!("COPY items FROM '{}' (HEADER, DELIMITER ',')", csv.display())).unwrap_err();
+    let message = err.to_string();
+    assert!(message.contains("5 lines rejected"), "{}", message);
+    for expected in [
+        "line 3: cannot convert 'x' to INTEGER for column 'id'",
+        "line 4: duplicate key (1) violates PRIMARY KEY",
+        "line 5: NULL in column 'name' violates NOT NULL",
+        "line 6: duplicate key ('B') violates UNIQUE index 'items_code_key'",
+        "line 7: expected 3 fields, found 2",
+    ] {
+        assert!(message.contains(expected), "missing '{}' in {}", expected, message);
+    }
+    let result = db.execute("SELECT id FROM items").unwrap();
+    assert_eq!(result.rows.len(), 1);
+
+    // 没有出错的行时全部导入，文件中没有的列取默认值
+    let mut text = String::from("id,name,code\n");
+    for id in 2..2002 {
+        text.push_str(&format!("{},item{},C{}\n", id, id, id));
+    }
+    fs::write(&csv, text).unwrap();
+    let result = db.execute(&format!("COPY items FROM '{}' (HEADER)", csv.display())).unwrap();
+    assert_eq!(result.affected_rows, 2000);
+    let result = db.execute("SELECT qty FROM items WHERE id = 2001").unwrap();
+    assert_eq!(result.rows[0].values, vec![Value::Integer(1)]);
+}
+
+#[test]
+fn test_copy_to_round_trip() {
+    let dir = tempfile::TempDir::new().expect("Failed to create temp dir");
+    let csv = dir.path().join("notes.csv");
+    let mut db = Database::in_memory().expect("Failed to create database");
+    db.execute("CREATE TABLE notes (id INT PRIMARY KEY, body VARCHAR(50), score DOUBLE)").unwrap();
+    db.execute("INSERT INTO notes VALUES (1, 'plain', 1.5), (2, 'has, comma and \"quotes\"', NULL), (3, '', 2.0), (4, NULL, 0.25)").unwrap();
+
+    let result = db.execute(&format!(
+        "COPY (SELECT id, body, score FROM notes WHERE id > 1 ORDER BY id) TO '{}' (HEADER)", csv.display()
+    )).unwrap();
+    assert_eq!(result.affected_rows, 3);
+    let text = fs::read_to_string(&csv).unwrap();
+    assert_eq!(text, "id,body,score\n2,\"has, comma and \"\"quotes\"\"\",\n3,\"\",2\n4,,0.25\n");
+
+    // 导出的文件可以用 COPY FROM 原样导回
+    db.execute("CREATE TABLE copies (id INT, body VARCHAR(50), score DOUBLE)").unwrap();
+    db.execute(&format!("COPY copies FROM '{}' (HEADER)", csv.display())).unwrap();
+    let original = db.execute("SELECT id, body, score FROM notes WHERE id > 1 ORDER BY id").unwrap();
+    let copied = db.execute("SELECT id, body, score FROM copies ORDER BY id").unwrap();
+    assert_eq!(copied.rows, original.rows);
+
+    // 库接口可以写出任意查询结果
+    let mut out = Vec::new();
+    let options = CsvOptions { quote: QuoteStyle::NonNumeric, ..CsvOptions::default() };
+    assert_eq!(original.write_csv(&mut out, &options).unwrap(), 3);
+    assert!(String::from_utf8(out).unwrap().starts_with("\"id\",\"body\",\"score\"\n2,"));
+
+    assert!(db.execute(&format!("COPY (SELECT * FROM missing) TO '{}'", csv.display())).is_err());
+    assert!(matches!(
+        db.execute(&format!("COPY (SELECT * FROM notes) TO '{}' (FORMAT NDJSON)", csv.display())),
+        Err(ExecutionError::NotImplemented { .. })
+    ));
+}
+
+/// 每当扫描产生一行时记录导出文件当前的大小
+struct FileSizeObserver {
+    path: std::path::PathBuf,
+    sizes: std::sync::Mutex<Vec<u64>>,
+}
+
+impl super::observer::ExecutorObserver for FileSizeObserver {
+    fn on_next(&self, operator: &super::observer::OperatorInfo, rows: usize, _elapsed: std::time::Duration) {
+        if operator.name == "Scan" && rows > 0 {
+            let size = fs::metadata(&self.path).map_or(0, |metadata| metadata.len());
+            self.sizes.lock().unwrap().push(size);
+        }
+    }
+}
+
+/// 测试 COPY TO 边执行查询边写文件，不先攒齐整个结果集
+#[test]
+fn test_copy_to_streams_rows() {
+    use super::config::DatabaseConfig;
+    use std::sync::Arc;
+
+    let dir = tempfile::TempDir::new().expect("Failed to create temp dir");
+    let csv = dir.path().join("big.csv");
+    let observer = Arc::new(FileSizeObserver { path: csv.clone(), sizes: std::sync::Mutex::new(Vec::new()) });
+    let config = DatabaseConfig::default().with_observer(observer.clone());
+    let mut db = Database::with_backend(Box::new(crate::storage::MemoryBackend::new()), config).unwrap();
+    db.execute("CREATE TABLE big (id INT, body VARCHAR(40))").unwrap();
+    for chunk in 0..20 {
+        let values: Vec<String> = (0..100)
+            .map(|i| format!("({}, 'row {:04} padded to a longer text')", chunk * 100 + i, chunk * 100 + i))
+            .collect();
+        db.execute(&format!("INSERT INTO big VALUES {}", values.join(", "))).unwrap();
+    }
+
+    let result = db.execute(&format!("COPY (SELECT id, body FROM big) TO '{}'", csv.display())).unwrap();
+    assert_eq!(result.affected_rows, 2000);
+    let total = fs::metadata(&csv).unwrap().len();
+
+    // 扫描产生最后一行时，前面的行已经有一部分写进了文件
+    let sizes = observer.sizes.lock().unwrap().clone();
+    assert_eq!(sizes.len(), 2000);
+    assert_eq!(sizes[0], 0);
+    assert!(sizes[1999] > 0 && sizes[1999] < total, "{} of {}", sizes[1999], total);
+    assert!(sizes.windows(2).all(|pair| pair[0] <= pair[1]));
+
+    // 查询中途出错时不留下写了一半的文件
+    let broken = dir.path().join("broken.csv");
+    assert!(db.execute(&format!("COPY (SELECT id / (id - 1500) FROM big) TO '{}'", broken.display())).is_err());
+    assert!(!broken.exists());
+}
+
+#[cfg(feature = "arrow")]
+#[test]
+fn test_query_result_to_arrow() {
+    use arrow::array::{Array, Date32Array, StringArray};
+
+    let mut db = Database::in_memory().expect("Failed to create database");
+    db.execute("CREATE TABLE events (id INT PRIMARY KEY, name VARCHAR(20), day DATE)").unwrap();
+    db.execute("INSERT INTO events VALUES (1, 'launch', CAST('1970-01-11' AS DATE)), (2, NULL, NULL)").unwrap();
+
+    let result = db.execute("SELECT name, day FROM events ORDER BY id").unwrap();
+    let batch = result.to_record_batch().unwrap();
+    assert_eq!(batch.num_rows(), 2);
+    let names = batch.column(0).as_any().downcast_ref::<StringArray>().unwrap();
+    assert_eq!(names.value(0), "launch");
+    assert!(names.is_null(1));
+    let days = batch.column(1).as_any().downcast_ref::<Date32Array>().unwrap();
+    assert_eq!(days.value(0), 10);
+
+    let file = tempfile::tempfile().unwrap();
+    assert_eq!(result.write_parquet(file).unwrap(), 2);
+
+    let result = db.execute("DELETE FROM events WHERE id = 2").unwrap();
+    assert!(result.to_record_batch().is_err());
+}
+
+#[test]
+fn test_index_lookup_matches_scan() {
+    let dir = tempfile::TempDir::new().expect("Failed to create temp dir");
+    let mut db = Database::new(dir.path()).expect("Failed to create database");
+    db.execute("CREATE TABLE people (id INT PRIMARY KEY, city VARCHAR(20), age INT)").unwrap();
+    for id in 0..200 {
+        let city = ["Paris", "Oslo", "Lima"][id % 3];
+        db.execute(&format!("INSERT INTO people VALUES ({}, '{}', {})", id, city, id % 50)).unwrap();
+    }
+    db.execute("INSERT INTO people VALUES (200, NULL, 1)").unwrap();
+    db.execute("CREATE INDEX people_city_age ON people (city, age)").unwrap();
+
+    let ids = |db: &mut Database, sql: &str| -> Vec<Value> {
+        db.execute(sql).unwrap().rows.into_iter().map(|row| row.values[0].clone()).collect()
+    };
+    assert_eq!(ids(&mut db, "SELECT id FROM people WHERE id = 42"), vec![Value::Integer(42)]);
+    assert_eq!(ids(&mut db, "SELECT id FROM people WHERE city = 'Oslo' AND age = 1"), vec![Value::Integer(1), Value::Integer(151)]);
+    assert_eq!(ids(&mut db, "SELECT id FROM people WHERE city = 'Lima' AND id < 10"), vec![Value::Integer(2), Value::Integer(5), Value::Integer(8)]);
+    assert_eq!(ids(&mut db, "SELECT COUNT(*) FROM people WHERE city = 'Paris'"), vec![Value::Integer(67)]);
+    assert!(ids(&mut db, "SELECT id FROM people WHERE city = 'Rome'").is_empty());
+
+    // 表被修改后索引按当前数据重建
+    db.execute("UPDATE people SET city = 'Rome' WHERE id = 151").unwrap();
+    db.execute("DELETE FROM people WHERE id = 42").unwrap();
+    assert_eq!(ids(&mut db, "SELECT id FROM people WHERE city = 'Oslo' AND age = 1"), vec![Value::Integer(1)]);
+    assert_eq!(ids(&mut db, "SELECT id FROM people WHERE city = 'Rome'"), vec![Value::Integer(151)]);
+    assert!(ids(&mut db, "SELECT id FROM people WHERE id = 42").is_empty());
+    let result = db.execute("SELECT rowid, id FROM people WHERE city = 'Rome'").unwrap();
+    assert_eq!(result.rows[0].values, vec![Value::BigInt(152), Value::Integer(151)]);
+
+    db.execute("BEGIN").unwrap();
+    db.execute("INSERT INTO people VALUES (300, 'Rome', 7)").unwrap();
+    assert_eq!(ids(&mut db, "SELECT id FROM people WHERE city = 'Rome'").len(), 2);
+    db.execute("ROLLBACK").unwrap();
+    assert_eq!(ids(&mut db, "SELECT id FROM people WHERE city = 'Rome'"), vec![Value::Integer(151)]);
+}
+
+/// 测试按选择性在索引扫描和顺序扫描之间选择，EXPLAIN 和编译出的计划显示同一选择
+#[test]
+fn test_index_scan_selection() {
+    use crate::sql::ExecutionPlan;
+
+    let mut db = Database::in_memory().expect("Failed to create database");
+    db.execute("CREATE TABLE items (id INT PRIMARY KEY, kind VARCHAR(10), shelf INT)").unwrap();
+    db.execute("CREATE INDEX items_kind ON items (kind)").unwrap();
+    db.execute("CREATE INDEX items_shelf ON items (shelf)").unwrap();
+    for id in 0..100 {
+        let kind = if id % 2 == 0 { "even" } else { "odd" };
+        db.execute(&format!("INSERT INTO items VALUES ({}, '{}', {})", id, kind, id % 20)).unwrap();
+    }
+
+    let explain = |db: &mut Database, sql: &str| match &db.execute(&format!("EXPLAIN {}", sql)).unwrap().rows[0].values[0] {
+        Value::Varchar(text) => text.lines().nth(1).unwrap_or_default().to_string(),
+        other => panic!("Expected plan text, got {:?}", other),
+    };
+    assert_eq!(
+        explain(&mut db, "SELECT * FROM items WHERE id = 7"),
+        "1. Index Scan: items using items_pkey (id = 7), estimated rows=1 of 100"
+    );
+    assert_eq!(
+        explain(&mut db, "SELECT * FROM items WHERE shelf = 3 ORDER BY id"),
+        "1. Index Scan: items using items_shelf (shelf = 3), estimated rows=5 of 100"
+    );
+    // 一半的行都匹配时顺序扫描更便宜
+    assert_eq!(
+        explain(&mut db, "SELECT * FROM items WHERE kind = 'odd'"),
+        "1. Table Scan: items (index items_kind not selective: estimated rows=50 of 100)"
+    );
+    assert_eq!(
+        explain(&mut db, "SELECT * FROM items WHERE shelf > 3"),
+        "1. Table Scan: items (index items_shelf not selective: estimated rows=80 of 100)"
+    );
+    assert_eq!(explain(&mut db, "SELECT * FROM items WHERE kind <> 'odd'"), "1. Table Scan: items");
+
+    let scan = |db: &Database, sql: &str| {
+        let mut plan = db.compile(sql).unwrap().plan.expect("SELECT should be planned").plan;
+        loop {
+            plan = match plan {
+                ExecutionPlan::Project { input, .. } | ExecutionPlan::Sort { input, .. }
+                | ExecutionPlan::Limit { input, .. } | ExecutionPlan::Filter { input, .. } => *input,
+                plan => return plan,
+            };
+        }
+    };
+    match scan(&db, "SELECT id FROM items WHERE shelf = 3 AND kind = 'odd' ORDER BY id LIMIT 2") {
+        ExecutionPlan::IndexScan { index_name, filter, estimated_rows, .. } => {
+            assert_eq!(index_name, "items_shelf");
+            assert!(filter.is_some());
+            assert_eq!(estimated_rows, 5);
+        }
+        other => panic!("Expected IndexScan, got {:?}", other),
+    }
+    assert!(matches!(scan(&db, "SELECT id FROM items WHERE kind = 'odd'"), ExecutionPlan::TableScan { filter: Some(_), .. }));
+
+    // 两种访问路径的结果相同
+    let result = db.execute("SELECT id FROM items WHERE shelf = 3 AND kind = 'odd' ORDER BY id").unwrap();
+    let ids: Vec<Value> = result.rows.into_iter().map(|row| row.values[0].clone()).collect();
+    assert_eq!(ids, vec![Value::Integer(3), Value::Integer(23), Value::Integer(43), Value::Integer(63), Value::Integer(83)]);
+    assert_eq!(db.execute("SELECT id FROM items WHERE kind = 'odd'").unwrap().rows.len(), 50);
+}
+
+/// 测试比较和 BETWEEN 条件按索引范围查找，结果与扫描整张表相同
+#[test]
+fn test_index_range_scan() {
+    let mut db = Database::in_memory().expect("Failed to create database");
+    db.execute("CREATE TABLE people (id INT PRIMARY KEY, city VARCHAR(20), age INT)").unwrap();
+    for id in 0..300 {
+        let city = ["Paris", "Oslo", "Lima"][id % 3];
+        let age = if id % 37 == 0 { "NULL".to_string() } else { (id % 90).to_string() };
+        db.execute(&format!("INSERT INTO people VALUES ({}, '{}', {})", id, city, age)).unwrap();
+    }
+    db.execute("CREATE INDEX people_age ON people (age)").unwrap();
+    db.execute("CREATE INDEX people_city_age ON people (city, age)").unwrap();
+
+    let explain = |db: &mut Database, sql: &str| match &db.execute(&format!("EXPLAIN {}", sql)).unwrap().rows[0].values[0] {
+        Value::Varchar(text) => text.lines().nth(1).unwrap_or_default().to_string(),
+        other => panic!("Expected plan text, got {:?}", other),
+    };
+    assert!(explain(&mut db, "SELECT * FROM people WHERE age > 85").starts_with("1. Index Scan: people using people_age (age > 85)"));
+    assert!(explain(&mut db, "SELECT * FROM people WHERE age BETWEEN 10 AND 12")
+        .starts_with("1. Index Scan: people using people_age (age BETWEEN 10 AND 12)"));
+    assert!(explain(&mut db, "SELECT * FROM people WHERE city = 'Oslo' AND age >= 80 AND age < 85")
+        .starts_with("1. Index Scan: people using people_city_age (city = 'Oslo' AND age >= 80 AND age < 85)"));
+    assert!(explain(&mut db, "SELECT * FROM people WHERE id < 10").starts_with("1. Index Scan: people using people_pkey (id < 10)"));
+
+    // 同一查询按索引和按整表扫描（加上索引用不到的 OR）得到相同的行
+    let ids = |db: &mut Database, sql: &str| -> Vec<Value> {
+        db.execute(sql).unwrap().rows.into_iter().map(|row| row.values[0].clone()).collect()
+    };
+    for condition in [
+        "age > 85",
+        "age >= 88",
+        "85 < age",
+        "age < 2",
+        "age <= 1 AND age > 0",
+        "age BETWEEN 10 AND 12",
+        "age BETWEEN 12 AND 10",
+        "city = 'Oslo' AND age >= 80 AND age < 85",
+        "city = 'Lima' AND age > 87",
+        "id < 10",
+        "id > 295 AND age < 100",
+        "age > CAST('9999999999' AS BIGINT)",
+    ] {
+        let indexed = ids(&mut db, &format!("SELECT id FROM people WHERE {} ORDER BY id", condition));
+        let scanned = ids(&mut db, &format!("SELECT id FROM people WHERE ({}) OR id < 0 ORDER BY id", condition));
+        assert_eq!(indexed, scanned, "{}", condition);
+    }
+    assert_eq!(ids(&mut db, "SELECT COUNT(*) FROM people WHERE age BETWEEN 10 AND 12"), vec![Value::Integer(12)]);
+}
+
+/// 测试查询只用到索引中的列时由索引构造结果（仅索引扫描），结果与读表相同
+#[test]
+fn test_index_only_scan() {
+    use crate::sql::ExecutionPlan;
+
+    let mut db = Database::in_memory().expect("Failed to create database");
+    db.execute("CREATE TABLE orders (id INT PRIMARY KEY, customer INT, status VARCHAR(10), total DOUBLE)").unwrap();
+    for id in 0..200 {
+        let status = if id % 4 == 0 { "'open'" } else { "'closed'" };
+        let total = if id % 9 == 0 { "NULL".to_string() } else { format!("{}.5", id) };
+        db.execute(&format!("INSERT INTO orders VALUES ({}, {}, {}, {})", id, id % 20, status, total)).unwrap();
+    }
+    db.execute("CREATE INDEX orders_customer ON orders (customer) INCLUDE (total)").unwrap();
+
+    let explain = |db: &mut Database, sql: &str| match &db.execute(&format!("EXPLAIN {}", sql)).unwrap().rows[0].values[0] {
+        Value::Varchar(text) => text.lines().nth(1).unwrap_or_default().to_string(),
+        other => panic!("Expected plan text, got {:?}", other),
+    };
+    assert_eq!(
+        explain(&mut db, "SELECT customer, total FROM orders WHERE customer = 7"),
+        "1. Index Only Scan: orders using orders_customer (customer = 7), estimated rows=10 of 200"
+    );
+    assert!(explain(&mut db, "SELECT SUM(total) FROM orders WHERE customer BETWEEN 3 AND 4").starts_with("1. Index Only Scan"));
+    assert!(explain(&mut db, "SELECT id FROM orders WHERE id < 5").starts_with("1. Index Only Scan: orders using orders_pkey"));
+    assert!(explain(&mut db, "SELECT total FROM orders WHERE customer = 7 AND status = 'open'").starts_with("1. Index Scan"));
+    assert!(explain(&mut db, "SELECT * FROM orders WHERE customer = 7").starts_with("1. Index Scan"));
+
+    // 编译出的执行计划中对应 IndexOnlyScan 节点
+    let plan = db.compile("SELECT total FROM orders WHERE customer = 7 ORDER BY total").unwrap().plan.expect("SELECT should be planned").plan;
+    let mut node = &plan;
+    while let ExecutionPlan::Project { input, .. } | ExecutionPlan::Sort { input, .. } = node {
+        node = input;
+    }
+    assert!(matches!(node, ExecutionPlan::IndexOnlyScan { index_name, .. } if index_name == "orders_customer"), "{:?}", node);
+
+    // 插入的行（包括 INCLUDE 列的值）直接进入索引；与不用索引的等价查询结果相同
+    db.execute("INSERT INTO orders VALUES (500, 7, 'open', 99.5)").unwrap();
+    let rows = |db: &mut Database, sql: &str| db.execute(sql).unwrap().rows;
+    for (indexed, scanned) in [
+        ("SELECT customer, total FROM orders WHERE customer = 7", "SELECT customer, total FROM orders WHERE customer = 7 OR id < 0"),
+        ("SELECT rowid, total FROM orders WHERE customer = 7 AND total > 100", "SELECT rowid, total FROM orders WHERE (customer = 7 AND total > 100) OR id < 0"),
+        ("SELECT customer, SUM(total), COUNT(*) FROM orders WHERE customer BETWEEN 3 AND 4 GROUP BY customer",
+         "SELECT customer, SUM(total), COUNT(*) FROM orders WHERE customer BETWEEN 3 AND 4 OR id < 0 GROUP BY customer"),
+        ("SELECT id FROM orders WHERE id <= 3", "SELECT id FROM orders WHERE id <= 3 OR customer < 0"),
+    ] {
+        let mut expected = rows(&mut db, scanned);
+        let mut actual = rows(&mut db, indexed);
+        expected.sort_by_key(|row| format!("{:?}", row.values));
+        actual.sort_by_key(|row| format!("{:?}", row.values));
+        assert_eq!(actual, expected, "{}", indexed);
+    }
+    assert_eq!(rows(&mut db, "SELECT total FROM orders WHERE customer = 7").len(), 11);
+
+    // 修改之后索引重建，仍带 INCLUDE 列
+    db.execute("UPDATE orders SET total = 1.5 WHERE id = 500").unwrap();
+    let totals = rows(&mut db, "SELECT total FROM orders WHERE customer = 7 AND total < 2");
+    assert_eq!(totals, vec![crate::types::Tuple::new(vec![Value::Double(1.5)])]);
+
+    // INCLUDE 列必须是表中的列
+    let err = db.execute("CREATE INDEX orders_bad ON orders (customer) INCLUDE (missing)").unwrap_err();
+    assert!(matches!(err, ExecutionError::ColumnNotFound { .. }), "{:?}", err);
+}
+
+/// 测试 UPDATE、DELETE 之后按索引查找的结果与扫描整张表相同
+#[test]
+fn test_index_maintenance() {
+    let mut db = Database::in_memory().expect("Failed to create database");
+    db.execute("CREATE TABLE stock (id INT PRIMARY KEY, sku VARCHAR(10), qty INT)").unwrap();
+    for id in 0..120 {
+        db.execute(&format!("INSERT INTO stock VALUES ({}, 'sku{}', {})", id, id % 30, id % 7)).unwrap();
+    }
+    db.execute("CREATE INDEX stock_sku ON stock (sku) INCLUDE (qty)").unwrap();
+    db.execute("CREATE INDEX stock_qty ON stock (qty)").unwrap();
+
+    let ids = |db: &mut Database, sql: &str| -> Vec<Value> {
+        db.execute(sql).unwrap().rows.into_iter().map(|row| row.values[0].clone()).collect()
+    };
+    let check = |db: &mut Database| {
+        for condition in ["sku = 'sku3'", "sku = 'new'", "qty = 2", "qty > 5", "id BETWEEN 10 AND 14", "sku = 'sku4' AND qty < 3"] {
+            let indexed = ids(db, &format!("SELECT id FROM stock WHERE {} ORDER BY id", condition));
+            let scanned = ids(db, &format!("SELECT id FROM stock WHERE ({}) OR id < 0 ORDER BY id", condition));
+            assert_eq!(indexed, scanned, "{}", condition);
+        }
+        // 仅索引扫描读到的 INCLUDE 列值也是最新的
+        let indexed = db.execute("SELECT sku, qty FROM stock WHERE sku = 'sku3'").unwrap().rows;
+        let scanned = db.execute("SELECT sku, qty FROM stock WHERE sku = 'sku3' OR id < 0").unwrap().rows;
+        assert_eq!(indexed, scanned);
+    };
+    check(&mut db);
+
+    // 键列和 INCLUDE 列被修改的行移到新键下
+    db.execute("UPDATE stock SET sku = 'new', qty = qty + 10 WHERE id BETWEEN 10 AND 12").unwrap();
+    db.execute("UPDATE stock SET qty = 9 WHERE sku = 'sku3'").unwrap();
+    check(&mut db);
+    assert_eq!(ids(&mut db, "SELECT id FROM stock WHERE sku = 'new'").len(), 3);
+    assert_eq!(ids(&mut db, "SELECT qty FROM stock WHERE sku = 'sku3'"), vec![Value::Integer(9); 4]);
+
+    db.execute("DELETE FROM stock WHERE qty = 2 OR id = 11").unwrap();
+    check(&mut db);
+    assert_eq!(ids(&mut db, "SELECT id FROM stock WHERE sku = 'new' ORDER BY id"), vec![Value::Integer(10), Value::Integer(12)]);
+    assert!(ids(&mut db, "SELECT id FROM stock WHERE qty = 2").is_empty());
+
+    db.execute("INSERT INTO stock VALUES (10, 'dup', 0) ON CONFLICT (id) DO UPDATE SET sku = 'conflict'").unwrap();
+    check(&mut db);
+    assert_eq!(ids(&mut db, "SELECT id FROM stock WHERE sku = 'conflict'"), vec![Value::Integer(10)]);
+
+    // 主键的索引也随之更新
+    db.execute("UPDATE stock SET id = id + 1000 WHERE id = 12").unwrap();
+    assert_eq!(ids(&mut db, "SELECT sku FROM stock WHERE id = 1012"), vec![Value::Varchar("new".to_string())]);
+    assert!(ids(&mut db, "SELECT sku FROM stock WHERE id = 12").is_empty());
+    db.execute("INSERT INTO stock VALUES (12, 'again', 1)").unwrap();
+    let err = db.execute("UPDATE stock SET id = 12 WHERE id = 1012").unwrap_err();
+    assert!(matches!(err, ExecutionError::PrimaryKeyViolation { .. }), "{:?}", err);
+
+    // 回滚恢复的行在下次查找前重新建立索引
+    db.execute("BEGIN").unwrap();
+    db.execute("UPDATE stock SET sku = 'rolled' WHERE qty = 4").unwrap();
+    db.execute("DELETE FROM stock WHERE sku = 'sku3'").unwrap();
+    check(&mut db);
+    db.execute("ROLLBACK").unwrap();
+    check(&mut db);
+    assert!(ids(&mut db, "SELECT id FROM stock WHERE sku = 'rolled'").is_empty());
+    assert_eq!(ids(&mut db, "SELECT id FROM stock WHERE sku = 'sku3'").len(), 4);
+
+
+    // DEDUPLICATE 删除的行也移出索引
+    db.execute("CREATE TABLE tags (tag VARCHAR(10), n INT)").unwrap();
+    db.execute("INSERT INTO tags VALUES ('a', 1), ('b', 2), ('a', 1), ('a', 3)").unwrap();
+    for n in 0..20 {
+        db.execute(&format!("INSERT INTO tags VALUES ('x{}', {})", n, n)).unwrap();
+    }
+    db.execute("CREATE INDEX tags_tag ON tags (tag)").unwrap();
+    assert_eq!(db.execute("SELECT n FROM tags WHERE tag = 'a'").unwrap().rows.len(), 3);
+    db.execute("DEDUPLICATE TABLE tags").unwrap();
+    assert_eq!(ids(&mut db, "SELECT n FROM tags WHERE tag = 'a'"), vec![Value::Integer(1), Value::Integer(3)]);
+}
+
+/// 测试部分索引：只包含满足谓词的行，WHERE 蕴含谓词时才被选用
+#[test]
+fn test_partial_index() {
+    let test_dir = "test_db_partial_index";
+    let _ = fs::remove_dir_all(test_dir);
+
+    let mut db = Database::new(test_dir).expect("Failed to create database");
+    db.execute("CREATE TABLE orders (id INT PRIMARY KEY, customer INT, status VARCHAR(10), total INT)").unwrap();
+    for id in 0..200 {
+        let status = if id % 10 == 0 { "open" } else { "closed" };
+        db.execute(&format!("INSERT INTO orders VALUES ({}, {}, '{}', {})", id, id % 25, status, id)).unwrap();
+    }
+    db.execute("CREATE INDEX orders_open ON orders (customer) WHERE status = 'open'").unwrap();
+    db.execute("CREATE INDEX orders_big ON orders (customer) WHERE total >= 150").unwrap();
+
+    let explain = |db: &mut Database, sql: &str| match &db.execute(&format!("EXPLAIN {}", sql)).unwrap().rows[0].values[0] {
+        Value::Varchar(text) => text.lines().nth(1).unwrap_or_default().to_string(),
+        other => panic!("Expected plan text, got {:?}", other),
+    };
+    assert!(explain(&mut db, "SELECT * FROM orders WHERE customer = 5 AND status = 'open'")
+        .starts_with("1. Index Scan: orders using orders_open (customer = 5)"));
+    assert!(explain(&mut db, "SELECT * FROM orders WHERE customer = 5 AND total > 180")
+        .starts_with("1. Index Scan: orders using orders_big (customer = 5)"));
+    // WHERE 不蕴含谓词时索引会漏掉行，不能使用
+    assert_eq!(explain(&mut db, "SELECT * FROM orders WHERE customer = 5"), "1. Table Scan: orders");
+    assert_eq!(explain(&mut db, "SELECT * FROM orders WHERE customer = 5 AND total > 100"), "1. Table Scan: orders");
+
+    let ids = |db: &mut Database, sql: &str| -> Vec<Value> {
+        db.execute(sql).unwrap().rows.into_iter().map(|row| row.values[0].clone()).collect()
+    };
+    let check = |db: &mut Database| {
+        for condition in ["customer = 5 AND status = 'open'", "customer = 0 AND status = 'open'", "customer = 7 AND total >= 160"] {
+            let indexed = ids(db, &format!("SELECT id FROM orders WHERE {} ORDER BY id", condition));
+            let scanned = ids(db, &format!("SELECT id FROM orders WHERE ({}) OR id < 0 ORDER BY id", condition));
+            assert_eq!(indexed, scanned, "{}", condition);
+        }
+    };
+    check(&mut db);
+    assert_eq!(ids(&mut db, "SELECT id FROM orders WHERE customer = 0 AND status = 'open' ORDER BY id"),
+        vec![Value::Integer(0), Value::Integer(50), Value::Integer(100), Value::Integer(150)]);
+
+    // 行进出谓词时索引随之维护
+    db.execute("INSERT INTO orders VALUES (200, 5, 'open', 1), (201, 5, 'closed', 1)").unwrap();
+    db.execute("UPDATE orders SET status = 'open' WHERE id = 30").unwrap();
+    db.execute("UPDATE orders SET status = 'closed' WHERE id = 5").unwrap();
+    db.execute("UPDATE orders SET customer = 5 WHERE id = 10").unwrap();
+    db.execute("DELETE FROM orders WHERE id = 180").unwrap();
+    check(&mut db);
+    assert_eq!(ids(&mut db, "SELECT id FROM orders WHERE customer = 5 AND status = 'open' ORDER BY id"),
+        [10, 30, 80, 130, 200].map(Value::Integer).to_vec());
+
+    // 部分唯一索引只在满足谓词的行之间检查唯一性
+    db.execute("CREATE UNIQUE INDEX orders_one_open ON orders (customer) WHERE status = 'open' AND id >= 200").unwrap();
+    db.execute("INSERT INTO orders VALUES (202, 5, 'closed', 1), (203, 6, 'open', 1)").unwrap();
+    let err = db.execute("INSERT INTO orders VALUES (204, 5, 'open', 1)").unwrap_err();
+    assert!(matches!(err, ExecutionError::UniqueViolation { ref index, .. } if index == "orders_one_open"), "{:?}", err);
+    let err = db.execute("UPDATE orders SET status = 'open' WHERE id = 202").unwrap_err();
+    assert!(matches!(err, ExecutionError::UniqueViolation { .. }), "{:?}", err);
+    db.execute("UPDATE orders SET status = 'done' WHERE id = 200").unwrap();
+    db.execute("UPDATE orders SET status = 'open' WHERE id = 202").unwrap();
+    let err = db.execute("CREATE UNIQUE INDEX orders_open_key ON orders (customer) WHERE status = 'open' AND total >= 0").unwrap_err();
+    assert!(matches!(err, ExecutionError::UniqueViolation { .. }), "{:?}", err);
+
+    // 谓词只能引用本表的列
+    let err = db.execute("CREATE INDEX orders_bad ON orders (customer) WHERE missing = 1").unwrap_err();
+    assert!(matches!(err, ExecutionError::ColumnNotFound { .. }), "{:?}", err);
+    assert!(db.execute("CREATE INDEX orders_bad ON orders (customer) WHERE RANDOM() > 0").is_err());
+
+    // 谓词随索引保存，也写进转储
+    drop(db);
+    let mut db = Database::new(test_dir).expect("Failed to reopen database");
+    assert!(explain(&mut db, "SELECT * FROM orders WHERE customer = 5 AND status = 'open'")
+        .starts_with("1. Index Scan: orders using orders_open (customer = 5)"));
+    check(&mut db);
+    assert!(db.execute("INSERT INTO orders VALUES (205, 6, 'open', 1)").is_err());
+    let mut dump = Vec::new();
+    db.dump_sql(&mut dump).unwrap();
+    let dump = String::from_utf8(dump).unwrap();
+    assert!(dump.contains("CREATE INDEX orders_open ON orders (customer) WHERE status = 'open';"), "{}", dump);
+    assert!(dump.contains("CREATE UNIQUE INDEX orders_one_open ON orders (customer) WHERE status = 'open' AND id >= 200;"), "{}", dump);
+
+    drop(db);
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+/// 测试索引文件和 REINDEX：快照时写出索引结构，重新打开时载入并补上 WAL 中的修改，文件损坏时由表的行重建
+#[test]
+fn test_index_persistence_and_reindex() {
+    let test_dir = "test_db_index_persistence";
+    let _ = fs::remove_dir_all(test_dir);
+    let index_files = || -> Vec<std::path::PathBuf> {
+        fs::read_dir(test_dir).unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "idx"))
+            .collect()
+    };
+
+    let mut db = Database::new(test_dir).expect("Failed to create database");
+    db.execute("CREATE TABLE items (id INT PRIMARY KEY, sku VARCHAR(10), qty INT)").unwrap();
+    for id in 0..100 {
+        db.execute(&format!("INSERT INTO items VALUES ({}, 'sku{}', {})", id, id, id % 7)).unwrap();
+    }
+    db.execute("CREATE UNIQUE INDEX items_sku ON items (sku)").unwrap();
+    db.execute("CREATE INDEX items_qty ON items (qty) INCLUDE (sku)").unwrap();
+    db.execute("CHECKPOINT").unwrap();
+    assert_eq!(index_files().len(), 1);
+
+    // 检查点之后的修改只在 WAL 中，重放时随之维护载入的索引
+    db.execute("INSERT INTO items VALUES (100, 'sku100', 3)").unwrap();
+    db.execute("UPDATE items SET qty = 3 WHERE id = 1").unwrap();
+    db.execute("UPDATE items SET sku = 'renamed' WHERE id = 2").unwrap();
+    db.execute("DELETE FROM items WHERE id = 10").unwrap();
+
+    let ids = |db: &mut Database, sql: &str| -> Vec<Value> {
+        db.execute(sql).unwrap().rows.into_iter().map(|row| row.values[0].clone()).collect()
+    };
+    let check = |db: &mut Database| {
+        for condition in ["qty = 3", "sku = 'sku2'", "sku = 'renamed'", "sku = 'sku100'", "id = 10", "qty = 3 AND id > 50"] {
+            let indexed = ids(db, &format!("SELECT id FROM items WHERE {} ORDER BY id", condition));
+            let scanned = ids(db, &format!("SELECT id FROM items WHERE ({}) OR id < 0 ORDER BY id", condition));
+            assert_eq!(indexed, scanned, "{}", condition);
+        }
+        assert_eq!(ids(db, "SELECT id FROM items WHERE sku = 'renamed'"), vec![Value::Integer(2)]);
+        assert!(ids(db, "SELECT id FROM items WHERE id = 10").is_empty());
+        assert!(matches!(
+            db.execute("INSERT INTO items VALUES (200, 'sku100', 0)"),
+            Err(ExecutionError::UniqueViolation { .. })
+        ));
+    };
+    check(&mut db);
+    drop(db);
+
+    let mut db = Database::new(test_dir).expect("Failed to reopen database");
+    check(&mut db);
+    drop(db);
+
+    // 损坏的索引文件被忽略，查询照常由表的行重建索引
+    let path = index_files().pop().unwrap();
+    let mut data = fs::read(&path).unwrap();
+    let middle = data.len() / 2;
+    data[middle] ^= 0xff;
+    fs::write(&path, data).unwrap();
+    let mut db = Database::new(test_dir).expect("Failed to reopen database");
+    check(&mut db);
+
+    // REINDEX 重建索引并重写索引文件
+    let result = db.execute("REINDEX INDEX items_sku ON items").unwrap();
+    assert_eq!(result.message, "Index 'items_sku' on table 'items' rebuilt");
+    let result = db.execute("REINDEX TABLE items").unwrap();
+    assert_eq!(result.message, "3 index(es) on table 'items' rebuilt");
+    check(&mut db);
+    let err = db.execute("REINDEX INDEX missing ON items").unwrap_err();
+    assert!(matches!(err, ExecutionError::SemanticError(_)), "{:?}", err);
+    assert!(matches!(db.execute("REINDEX TABLE missing"), Err(ExecutionError::TableNotFound { .. })));
+    db.execute("BEGIN").unwrap();
+    assert!(db.execute("REINDEX TABLE items").is_err());
+    db.execute("ROLLBACK").unwrap();
+    drop(db);
+
+    let mut db = Database::new(test_dir).expect("Failed to reopen database");
+    check(&mut db);
+    db.execute("DROP TABLE items").unwrap();
+    assert!(index_files().is_empty());
+
+    drop(db);
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+#[test]
+fn test_show_indexes() {
+    let mut db = Database::in_memory().expect("Failed to create database");
+    db.execute("CREATE TABLE users (id INT PRIMARY KEY, email VARCHAR(50) UNIQUE, region INT)").unwrap();
+    db.execute("CREATE TABLE tags (name VARCHAR(10))").unwrap();
+    for id in 0..50 {
+        let email = if id % 10 == 0 { "NULL".to_string() } else { format!("'u{}@x'", id) };
+        db.execute(&format!("INSERT INTO users VALUES ({}, {}, {})", id, email, id % 5)).unwrap();
+    }
+    db.execute("CREATE INDEX users_region ON users (region) WHERE region > 2").unwrap();
+
+    let result = db.execute("SHOW INDEXES").unwrap();
+    let names: Vec<String> = result.schema.unwrap().columns.into_iter().map(|column| column.name).collect();
+    assert_eq!(names, ["table_name", "index_name", "columns", "is_unique", "size_bytes", "entries"]);
+    let rows: Vec<Vec<Value>> = result.rows.into_iter().map(|row| row.values).collect();
+    assert_eq!(rows.len(), 3);
+    let row = |name: &str| rows.iter().find(|row| row[1] == Value::Varchar(name.to_string())).unwrap().clone();
+    assert_eq!(row("users_pkey")[..4], [
+        Value::Varchar("users".to_string()), Value::Varchar("users_pkey".to_string()),
+        Value::Varchar("id".to_string()), Value::Boolean(true),
+    ]);
+    assert!(matches!(row("users_pkey")[4], Value::BigInt(size) if size > 0));
+    // 键为 NULL 的行和不满足部分索引谓词的行不进入索引
+    assert_eq!(row("users_pkey")[5], Value::BigInt(50));
+    assert_eq!(row("users_email_key")[5], Value::BigInt(45));
+    assert_eq!(row("users_region")[3], Value::Boolean(false));
+    assert_eq!(row("users_region")[5], Value::BigInt(20));
+
+    // 索引项随修改变化；可以只看一张表
+    db.execute("DELETE FROM users WHERE region = 4").unwrap();
+    let indexes = db.list_indexes(Some("users")).unwrap();
+    assert_eq!(indexes.iter().map(|index| index.name.as_str()).collect::<Vec<_>>(), ["users_email_key", "users_pkey", "users_region"]);
+    assert_eq!(indexes[2].entries, Some(10));
+    assert_eq!(db.execute("SHOW INDEX FROM tags").unwrap().rows.len(), 0);
+    assert!(matches!(db.execute("SHOW INDEXES FROM missing"), Err(ExecutionError::TableNotFound { .. })));
+}
+
+#[test]
+fn test_advise_indexes() {
+    let mut db = Database::in_memory().expect("Failed to create database");
+    db.execute("CREATE TABLE users (id INT PRIMARY KEY, email VARCHAR(50), region INT, status VARCHAR(10))")
+        .expect("Failed to create table");
+    db.execute("CREATE INDEX idx_region ON users (region)").expect("Failed to create index");
+    for id in 0..200 {
+        db.execute(&format!(
+            "INSERT INTO users VALUES ({}, 'user{}@example.com', {}, '{}')",
+            id, id, id % 20, if id % 2 == 0 { "active" } else { "idle" }
+        )).expect("Failed to insert");
+    }
+    for id in 0..5 {
+        db.execute(&format!("SELECT id FROM users WHERE email = 'user{}@example.com'", id)).expect("Failed to select");
+        // 主键、已有索引和区分度太低的列都不应被建议
+        db.execute(&format!("SELECT email FROM users WHERE id = {}", id)).expect("Failed to select");
+        db.execute(&format!("SELECT email FROM users WHERE region = {}", id)).expect("Failed to select");
+        db.execute("SELECT email FROM users WHERE status = 'idle'").expect("Failed to select");
+    }
+
+    let advice = db.advise();
+    assert_eq!(advice.len(), 1);
+    assert_eq!(advice[0].columns, vec!["email".to_string()]);
+    assert_eq!(advice[0].calls, 5);
+    assert!(advice[0].estimated_rows_saved > 0.0);
+
+    let result = db.execute("ADVISE INDEXES").expect("Failed to advise indexes");
+    assert_eq!(result.rows.len(), 1);
+    assert_eq!(result.rows[0].values[2], Value::Varchar("CREATE INDEX idx_users_email ON users (email)".to_string()));
+
+    // 按建议建索引后不再建议，删除索引后恢复
+    db.execute(&advice[0].statement).expect("Failed to create suggested index");
+    assert!(db.advise().is_empty());
+    db.execute("DROP INDEX idx_users_email ON users").expect("Failed to drop index");
+    assert_eq!(db.advise().len(), 1);
+
+    // 已有数据重复时不能建唯一索引
+    assert!(matches!(
+        db.execute("CREATE UNIQUE INDEX idx_status ON users (status)"),
+        Err(ExecutionError::UniqueViolation { .. })
+    ));
+}
+
+#[test]
+fn test_execute_script() {
+    let mut db = Database::in_memory().expect("Failed to create database");
+
+    // execute 依次执行全部语句并返回最后一条的结果
+    let result = db.execute(
+        "CREATE TABLE notes (id INT, body VARCHAR(20));\n\
+         INSERT INTO notes VALUES (1, 'a;b');\n\
+         INSERT INTO notes VALUES (2, 'c'); -- trailing comment\n\
+         SELECT body FROM notes ORDER BY id;"
+    ).expect("Failed to execute script");
+    assert_eq!(result.rows.len(), 2);
+    assert_eq!(result.rows[0].values[0], Value::Varchar("a;b".to_string()));
+
+    // 出错即停止，后面的语句不执行
+    assert!(db.execute("INSERT INTO notes VALUES (3, 'd'); SELECT * FROM missing; INSERT INTO notes VALUES (4, 'e')").is_err());
+    assert_eq!(db.execute("SELECT * FROM notes").unwrap().rows.len(), 3);
+
+    let results = db.execute_script("DELETE FROM notes WHERE id = 3; SELECT * FROM missing; SELECT id FROM notes", true);
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].as_ref().unwrap().affected_rows, 1);
+    assert!(results[1].is_err());
+
+    let results = db.execute_script("SELECT * FROM missing; SELECT id FROM notes;", false);
+    assert_eq!(results.len(), 2);
+    assert!(results[0].is_err());
+    assert_eq!(results[1].as_ref().unwrap().rows.len(), 2);
+
+    assert!(db.execute_script("-- nothing to do\n;", true).is_empty());
+}
+
+/// 测试中断后从检查点继续构建索引
+#[test]
+fn test_resumable_index_build() {
+    use super::config::DatabaseConfig;
+    use std::sync::atomic::Ordering;
+
+    let test_dir = "test_db_index_build";
+    let _ = fs::remove_dir_all(test_dir);
+    let config = || DatabaseConfig::default().with_index_build_batch_rows(2);
+    let progress = |db: &mut Database| -> Vec<(i64, i64)> {
+        db.execute("SELECT rows_done, total_rows FROM information_schema.index_builds").unwrap()
+            .rows.iter()
+            .map(|row| match (&row.values[0], &row.values[1]) {
+                (Value::BigInt(done), Value::BigInt(total)) => (*done, *total),
+                other => panic!("Unexpected progress {:?}", other),
+            })
+            .collect()
+    };
+
+    {
+        let mut db = Database::with_config(test_dir, config()).expect("Failed to create database");
+        db.execute("CREATE TABLE t (id INT, code INT)").unwrap();
+        for i in 1..=5 {
+            db.execute(&format!("INSERT INTO t VALUES ({}, {})", i, i * 10)).unwrap();
+        }
+        db.interrupt_handle().store(true, Ordering::SeqCst);
+        let err = db.execute("CREATE UNIQUE INDEX idx_code ON t (code)").unwrap_err();
+        assert!(matches!(err, ExecutionError::Interrupted { .. }), "{:?}", err);
+        assert_eq!(progress(&mut db), vec![(2, 5)]);
+    }
+
+    // 重新打开后进度仍在；构建完成前索引不生效
+    let mut db = Database::with_config(test_dir, config()).expect("Failed to reopen database");
+    assert_eq!(progress(&mut db), vec![(2, 5)]);
+    assert_eq!(db.index_builds()[0].1.name, "idx_code");
+    let result = db.execute("CREATE UNIQUE INDEX idx_code ON t (code)").unwrap();
+    assert!(result.message.contains("resumed"), "{}", result.message);
+    assert!(progress(&mut db).is_empty());
+    assert!(matches!(
+        db.execute("INSERT INTO t VALUES (6, 10)").unwrap_err(),
+        ExecutionError::UniqueViolation { .. }
+    ));
+
+    // 构建期间表被修改则从头开始；续建时发现重复键会删除该索引
+    db.interrupt_handle().store(true, Ordering::SeqCst);
+    assert!(db.execute("CREATE UNIQUE INDEX idx_id ON t (id)").is_err());
+    assert_eq!(progress(&mut db), vec![(2, 5)]);
+    db.execute("INSERT INTO t VALUES (1, 60)").unwrap();
+    assert_eq!(progress(&mut db), vec![(0, 0)]);
+    assert!(matches!(
+        db.execute("CREATE UNIQUE INDEX idx_id ON t (id)").unwrap_err(),
+        ExecutionError::UniqueViolation { .. }
+    ));
+    assert!(progress(&mut db).is_empty());
+    let names: Vec<String> = db.get_table_indexes("t").unwrap().iter().map(|index| index.name.clone()).collect();
+    assert_eq!(names, vec!["idx_code"]);
+
+    drop(db);
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+/// 测试会话级临时视图和持久保存的查询片段
+#[test]
+fn test_temp_views_and_snippets() {
+    let test_dir = "test_db_temp_views";
+    let _ = fs::remove_dir_all(test_dir);
+
+    {
+        let mut db = Database::new(test_dir).expect("Failed to create database");
+        db.execute("CREATE TABLE users (id INT, name VARCHAR(50), age INT)").unwrap();
+        db.execute("INSERT INTO users VALUES (1, 'alice', 30), (2, 'bob', 17), (3, 'carol', 45)").unwrap();
+
+        db.execute("CREATE TEMP VIEW adults AS SELECT id, name FROM users WHERE age >= 18").unwrap();
+        let result = db.execute("SELECT name FROM adults WHERE id > 1").unwrap();
+        assert_eq!(result.rows.len(), 1);
+        assert_eq!(result.rows[0].values[0], Value::Varchar("carol".to_string()));
+
+        // 视图每次引用时重新执行
+        db.execute("INSERT INTO users VALUES (4, 'dave', 50)").unwrap();
+        assert_eq!(db.execute("SELECT * FROM adults").unwrap().rows.len(), 3);
+        assert_eq!(db.list_views(), vec!["adults".to_string()]);
+        assert_eq!(db.get_view_schema("adults").unwrap().columns.len(), 2);
+
+        // 视图可以引用其他视图；名称不能与表或视图重复，也不能被写入
+        db.execute("CREATE TEMP VIEW senior AS SELECT name FROM adults WHERE id = 3").unwrap();
+        assert_eq!(db.execute("SELECT * FROM senior").unwrap().rows.len(), 1);
+        assert!(db.execute("CREATE TEMP VIEW users AS SELECT * FROM adults").is_err());
+        assert!(db.execute("CREATE TEMP VIEW adults AS SELECT * FROM users").is_err());
+        assert!(db.execute("CREATE TABLE adults (id INT)").is_err());
+        assert!(db.execute("INSERT INTO adults VALUES (5, 'eve')").is_err());
+        assert!(matches!(
+            db.execute("CREATE VIEW v AS SELECT * FROM users").unwrap_err(),
+            ExecutionError::NotImplemented { .. }
+        ));
+
+        db.execute("DROP VIEW senior").unwrap();
+        assert!(db.execute("SELECT * FROM senior").is_err());
+        assert!(db.execute("DROP VIEW senior").is_err());
+        db.execute("DROP VIEW IF EXISTS senior").unwrap();
+
+        db.save_snippet("top_users", "SELECT name, age FROM users ORDER BY age DESC LIMIT 2").unwrap();
+        db.save_snippet("count_users", "SELECT COUNT(*) FROM users;").unwrap();
+        assert!(db.save_snippet("bad name", "SELECT 1 FROM users").is_err());
+        assert!(db.save_snippet("broken", "SELEC name FROM users").is_err());
+        assert!(db.snippet("broken").is_none());
+    }
+
+    // 片段在重新打开后仍然存在，临时视图则随会话消失
+    let mut db = Database::new(test_dir).expect("Failed to reopen database");
+    assert!(db.list_views().is_empty());
+    assert!(db.execute("SELECT * FROM adults").is_err());
+    let names: Vec<&str> = db.snippets().map(|(name, _)| name).collect();
+    assert_eq!(names, vec!["count_users", "top_users"]);
+    let sql = db.snippet("top_users").unwrap().to_string();
+    let result = db.execute(&sql).unwrap();
+    assert_eq!(result.rows[0].values[0], Value::Varchar("dave".to_string()));
+    assert!(db.remove_snippet("top_users").unwrap());
+    assert!(!db.remove_snippet("top_users").unwrap());
+
+    drop(db);
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+/// 测试比对两张表或两条查询的结果
+#[test]
+fn test_diff_results() {
+    let mut db = Database::in_memory().expect("Failed to create database");
+    db.execute("CREATE TABLE old_users (id INT PRIMARY KEY, name VARCHAR(20))").unwrap();
+    db.execute("CREATE TABLE new_users (id INT PRIMARY KEY, name VARCHAR(20))").unwrap();
+    db.execute("INSERT INTO old_users VALUES (1, 'alice'), (2, 'bob'), (3, 'carol')").unwrap();
+    db.execute("INSERT INTO new_users VALUES (3, 'carol'), (2, 'robert'), (4, 'dave')").unwrap();
+
+    let markers = |result: &super::database::QueryResult| -> Vec<(String, Value)> {
+        result.rows.iter()
+            .map(|row| match &row.values[0] {
+                Value::Varchar(marker) => (marker.clone(), row.values[1].clone()),
+                other => panic!("Unexpected diff marker {:?}", other),
+            })
+            .collect()
+    };
+
+    // 两侧都是表时按主键匹配
+    let result = db.diff("old_users", "new_users").unwrap();
+    assert_eq!(result.schema.as_ref().unwrap().columns[0].name, "diff");
+    assert_eq!(markers(&result), vec![
+        ("-".to_string(), Value::Integer(1)),
+        ("<".to_string(), Value::Integer(2)),
+        (">".to_string(), Value::Integer(2)),
+        ("+".to_string(), Value::Integer(4)),
+    ]);
+    assert!(result.message.contains("matched by id"), "{}", result.message);
+
+    // 查询按整行比较
+    let result = db.diff("SELECT name FROM old_users", "SELECT name FROM new_users WHERE id < 4").unwrap();
+    assert_eq!(result.rows.len(), 3);
+    assert!(db.diff("old_users", "old_users").unwrap().rows.is_empty());
+
+    assert!(db.diff("old_users", "SELECT id FROM new_users").is_err());
+    assert!(db.diff("old_users", "DELETE FROM new_users").is_err());
+    assert_eq!(db.execute("SELECT * FROM new_users").unwrap().rows.len(), 3);
+}
+
+/// 测试 ASSERT 数据质量断言
+#[test]
+fn test_assert_statements() {
+    let mut db = Database::in_memory().expect("Failed to create database");
+    db.execute("CREATE TABLE users (id INT PRIMARY KEY, email VARCHAR(30), age INT)").unwrap();
+    db.execute("INSERT INTO users VALUES (1, 'a@x.com', 30), (2, NULL, 41), (3, 'c@x.com', NULL)").unwrap();
+
+    let result = db.execute("ASSERT (SELECT COUNT(*) FROM users) = 3").unwrap();
+    assert!(result.message.contains("passed"), "{}", result.message);
+    db.execute("ASSERT (SELECT MAX(age) FROM users) <= 41").unwrap();
+    db.execute("ASSERT NOT EXISTS (SELECT id FROM users WHERE age < 0)").unwrap();
+    db.execute("ASSERT EXISTS (SELECT id FROM users WHERE age IS NULL)").unwrap();
+
+    // 比较失败时附带 WHERE 匹配的样本行
+    match db.execute("ASSERT (SELECT COUNT(*) FROM users WHERE email IS NULL) = 0 MESSAGE 'email is required'") {
+        Err(ExecutionError::AssertionFailed { message, actual, samples }) => {
+            assert_eq!(message, "email is required");
+            assert_eq!(actual, "1");
+            assert_eq!(samples.len(), 1);
+            assert_eq!(samples[0].values[0], Value::Integer(2));
+        }
+        other => panic!("Expected AssertionFailed, got {:?}", other),
+    }
+
+    // NOT EXISTS 失败时样本就是查询返回的行
+    let error = db.execute("ASSERT NOT EXISTS (SELECT id, age FROM users WHERE age > 35)").unwrap_err();
+    assert!(error.to_string().contains("sample rows: (2, 41)"), "{}", error);
+
+    assert!(db.execute("ASSERT (SELECT id FROM users) = 1").is_err());
+    assert!(db.execute("ASSERT (SELECT COUNT(*) FROM missing) = 0").is_err());
+}
+
+/// 测试安全模式：不带 WHERE 的 UPDATE / DELETE 必须写出 ALL
+#[test]
+fn test_safe_updates() {
+    let mut db = Database::in_memory().expect("Failed to create database");
+    db.execute("CREATE TABLE items (id INT, qty INT)").unwrap();
+    db.execute("INSERT INTO items VALUES (1, 0), (2, 5)").unwrap();
+
+    // 默认关闭
+    assert_eq!(db.execute("UPDATE items SET qty = 1").unwrap().affected_rows, 2);
+
+    db.set_safe_updates(true);
+    match db.execute("DELETE FROM items") {
+        Err(ExecutionError::UnsafeUpdate { statement, table, .. }) => {
+            assert_eq!(statement, "DELETE");
+            assert_eq!(table, "items");
+        }
+        other => panic!("Expected UnsafeUpdate, got {:?}", other),
+    }
+    assert!(matches!(db.execute("UPDATE items SET qty = 2"), Err(ExecutionError::UnsafeUpdate { .. })));
+    assert_eq!(db.execute("SELECT * FROM items WHERE qty = 1").unwrap().rows.len(), 2);
+
+    // WHERE、ALL 和 DRY RUN 都不受限制
+    assert_eq!(db.execute("UPDATE items SET qty = 3 WHERE id = 1").unwrap().affected_rows, 1);
+    assert_eq!(db.execute("DRY RUN DELETE FROM items").unwrap().rows.len(), 2);
+    assert_eq!(db.execute("UPDATE ALL items SET qty = 4").unwrap().affected_rows, 2);
+    assert_eq!(db.execute("DELETE ALL FROM items").unwrap().affected_rows, 2);
+}
+
+/// 测试 WAL：行修改不重写表快照，重新打开时从 WAL 恢复
+#[test]
+fn test_write_ahead_log_recovery() {
+    use crate::storage::wal::WAL_FILE_NAME;
+
+    let test_dir = "test_db_wal_recovery";
+    let _ = fs::remove_dir_all(test_dir);
+
+    {
+        let mut db = Database::new(test_dir).expect("Failed to create database");
+        db.execute("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(20))").unwrap();
+        db.execute("CREATE TABLE scratch (id INT)").unwrap();
+        db.execute("INSERT INTO users VALUES (1, 'alice'), (2, 'bob'), (3, 'carol')").unwrap();
+        db.execute("UPDATE users SET name = 'robert' WHERE id = 2").unwrap();
+        db.execute("DELETE FROM users WHERE id = 1").unwrap();
+        db.execute("INSERT INTO users VALUES (4, 'dave') ON CONFLICT (id) DO NOTHING").unwrap();
+        db.execute("DROP TABLE scratch").unwrap();
+
+        // 回滚的事务不写入 WAL
+        db.execute("BEGIN").unwrap();
+        db.execute("DELETE FROM users").unwrap();
+        db.execute("ROLLBACK").unwrap();
+    }
+
+    // 快照仍是建表时的空表，数据只在 WAL 中
+    let snapshot = fs::read_to_string(Path::new(test_dir).join("table_1.json")).unwrap();
+    assert!(!snapshot.contains("alice"));
+    assert!(Path::new(test_dir).join(WAL_FILE_NAME).exists());
+    assert!(!Path::new(test_dir).join("table_2.json").exists());
+
+    // 模拟写入一半时崩溃：不完整的尾部被丢弃
+    fs::OpenOptions::new()
+        .append(true)
+        .open(Path::new(test_dir).join(WAL_FILE_NAME))
+        .and_then(|mut file| std::io::Write::write_all(&mut file, &[0x20, 0, 0, 0, 1, 2]))
+        .unwrap();
+
+    {
+        let mut db = Database::new(test_dir).expect("Failed to reopen database");
+        assert_eq!(db.list_tables(), vec!["users".to_string()]);
+        let result = db.execute("SELECT id, name, rowid FROM users ORDER BY id").unwrap();
+        let rows: Vec<Vec<Value>> = result.rows.into_iter().map(|row| row.values).collect();
+        assert_eq!(rows, vec![
+            vec![Value::Integer(2), Value::Varchar("robert".to_string()), Value::BigInt(2)],
+            vec![Value::Integer(3), Value::Varchar("carol".to_string()), Value::BigInt(3)],
+            vec![Value::Integer(4), Value::Varchar("dave".to_string()), Value::BigInt(4)],
+        ]);
+
+        // 恢复后继续写入，rowid 不复用
+        db.execute("INSERT INTO users VALUES (5, 'erin')").unwrap();
+        assert!(db.execute("INSERT INTO users VALUES (2, 'dup')").is_err());
+    }
+
+    let mut db = Database::new(test_dir).expect("Failed to reopen database");
+    let result = db.execute("SELECT rowid FROM users WHERE id = 5").unwrap();
+    assert_eq!(result.rows[0].values[0], Value::BigInt(5));
+    assert_eq!(db.execute("SELECT * FROM users").unwrap().rows.len(), 4);
+
+    drop(db);
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+/// 测试检查点：表快照包含全部修改，WAL 被截断，之后的修改仍能恢复
+#[test]
+fn test_checkpoint_truncates_wal() {
+    use crate::engine::config::DatabaseConfig;
+    use crate::storage::wal::WAL_FILE_NAME;
+
+    let test_dir = "test_db_checkpoint";
+    let _ = fs::remove_dir_all(test_dir);
+    let wal_size = || fs::metadata(Path::new(test_dir).join(WAL_FILE_NAME)).unwrap().len();
+
+    {
+        let mut db = Database::new(test_dir).expect("Failed to create database");
+        db.execute("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(20))").unwrap();
+        db.execute("INSERT INTO users VALUES (1, 'alice'), (2, 'bob')").unwrap();
+        let before = wal_size();
+
+        let result = db.execute("CHECKPOINT").unwrap();
+        assert!(result.message.starts_with("CHECKPOINT at LSN"), "{}", result.message);
+        assert!(wal_size() < before);
+        // 行写入新版本的堆文件，快照 JSON 只引用它
+        let snapshot = fs::read_to_string(Path::new(test_dir).join("table_1.json")).unwrap();
+        assert!(!snapshot.contains("alice"));
+        let pages = fs::read(Path::new(test_dir).join("table_1_2.db")).unwrap();
+        assert!(pages.windows(5).any(|bytes| bytes == b"alice"));
+        assert!(!Path::new(test_dir).join("table_1_1.db").exists());
+
+        db.execute("DELETE FROM users WHERE id = 1").unwrap();
+        db.execute("BEGIN").unwrap();
+        assert!(db.execute("CHECKPOINT").is_err());
+        db.execute("ROLLBACK").unwrap();
+    }
+
+    {
+        let mut db = Database::new(test_dir).expect("Failed to reopen database");
+        let result = db.execute("SELECT name FROM users").unwrap();
+        assert_eq!(result.rows.len(), 1);
+        assert_eq!(result.rows[0].values[0], Value::Varchar("bob".to_string()));
+    }
+
+    // 超过配置的大小后在提交点自动执行检查点
+    let config = DatabaseConfig::default().with_wal_checkpoint_bytes(1);
+    let mut db = Database::with_config(test_dir, config).expect("Failed to reopen database");
+    let before = wal_size();
+    db.execute("INSERT INTO users VALUES (3, 'carol')").unwrap();
+    assert!(wal_size() <= before);
+    let pages = fs::read(Path::new(test_dir).join("table_1_3.db")).unwrap();
+    assert!(pages.windows(5).any(|bytes| bytes == b"carol"));
+    assert!(!pages.windows(5).any(|bytes| bytes == b"alice"));
+
+    drop(db);
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+/// 测试查询经缓冲池读取表的页：表比缓冲池大时扫描会换页，缓冲池放得下时重复扫描全部命中
+#[test]
+fn test_queries_read_pages_through_buffer_pool() {
+    use crate::engine::config::DatabaseConfig;
+
+    let test_dir = "test_db_buffer_pool_execution";
+    let _ = fs::remove_dir_all(test_dir);
+
+    {
+        let config = DatabaseConfig::default().with_buffer_pool_size(8);
+        let mut db = Database::with_config(test_dir, config).expect("Failed to create database");
+        db.execute("CREATE TABLE items (id INT PRIMARY KEY, label VARCHAR(100))").unwrap();
+        for batch in 0..20 {
+            let values: Vec<String> = (0..100)
+                .map(|i| format!("({}, 'item label number {:05}')", batch * 100 + i, batch * 100 + i))
+                .collect();
+            db.execute(&format!("INSERT INTO items VALUES {}", values.join(", "))).unwrap();
+        }
+
+        let before = db.buffer_pool_stats().unwrap();
+        let result = db.execute("SELECT COUNT(*) FROM items WHERE id >= 1000").unwrap();
+        assert_eq!(result.rows[0].values[0], Value::Integer(1000));
+        let after = db.buffer_pool_stats().unwrap();
+        assert!(after.misses > before.misses, "a table larger than the pool must be read from disk");
+        assert!(after.used_frames <= 8);
+
+        // 修改经缓冲池写回页，回滚恢复原来的行
+        db.execute("UPDATE items SET label = 'changed' WHERE id < 10").unwrap();
+        db.execute("DELETE FROM items WHERE id >= 1990").unwrap();
+        db.execute("BEGIN").unwrap();
+        db.execute("DELETE FROM items WHERE id < 500").unwrap();
+        db.execute("ROLLBACK").unwrap();
+        let result = db.execute("SELECT COUNT(*) FROM items WHERE label = 'changed'").unwrap();
+        assert_eq!(result.rows[0].values[0], Value::Integer(10));
+    }
+
+    // 重新打开时工作文件从快照和 WAL 重建；缓冲池放得下整张表后重复扫描不再读盘
+    let config = DatabaseConfig::default().with_buffer_pool_size(256);
+    let mut db = Database::with_config(test_dir, config).expect("Failed to reopen database");
+    db.execute("SELECT * FROM items").unwrap();
+    let warm = db.buffer_pool_stats().unwrap();
+    let result = db.execute("SELECT * FROM items").unwrap();
+    assert_eq!(result.rows.len(), 1990);
+    let stats = db.buffer_pool_stats().unwrap();
+    assert_eq!(stats.misses, warm.misses);
+    assert!(stats.hits >= warm.hits + 1990);
+    let result = db.execute("SELECT label FROM items WHERE id = 3").unwrap();
+    assert_eq!(result.rows[0].values[0], Value::Varchar("changed".to_string()));
+
+    drop(db);
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+/// 测试顺序扫描逐行读取工作堆文件：取第一行只读一页，不先把整张表读进内存
+#[test]
+fn test_scan_reads_rows_as_it_goes() {
+    let dir = tempfile::TempDir::new().expect("Failed to create temp dir");
+    let mut db = Database::new(dir.path()).expect("Failed to create database");
+    db.execute("CREATE TABLE items (id INT, label VARCHAR(100))").unwrap();
+    for batch in 0..20 {
+        let values: Vec<String> = (0..100)
+            .map(|i| format!("({}, 'item label number {:05}')", batch * 100 + i, batch * 100 + i))
+            .collect();
+        db.execute(&format!("INSERT INTO items VALUES {}", values.join(", "))).unwrap();
+    }
+
+    let pages_read = |db: &Database| {
+        let stats = db.buffer_pool_stats().unwrap();
+        stats.hits + stats.misses
+    };
+    let before = pages_read(&db);
+    let first = {
+        let mut rows = db.query_iter("SELECT * FROM items").unwrap();
+        rows.next().unwrap().unwrap()
+    };
+    assert_eq!(first.values[0], Value::Integer(0));
+    assert_eq!(pages_read(&db) - before, 1);
+
+    // 读完整张表则每行都要经缓冲池取一次页
+    let before = pages_read(&db);
+    assert_eq!(db.query_iter("SELECT * FROM items").unwrap().count(), 2000);
+    assert!(pages_read(&db) - before >= 2000);
+
+    // 附加 rowid 伪列的扫描同样逐行读取
+    let before = pages_read(&db);
+    let first = db.query_iter("SELECT rowid, id FROM items").unwrap().next().unwrap().unwrap();
+    assert_eq!(first.values[1], Value::Integer(0));
+    assert_eq!(pages_read(&db) - before, 1);
+}
+
+/// 测试后台写页线程周期性写回脏页，停止后不再写入，内存数据库不启动
+#[test]
+fn test_background_writer_flushes_dirty_pages() {
+    use crate::engine::config::DatabaseConfig;
+    use std::time::{Duration, Instant};
+
+    let test_dir = "test_db_background_writer";
+    let _ = fs::remove_dir_all(test_dir);
+
+    let config = DatabaseConfig::default().with_background_flush_interval(None);
+    let mut db = Database::with_config(test_dir, config).expect("Failed to create database");
+    assert!(!db.background_writer_running());
+    db.execute("CREATE TABLE notes (id INT PRIMARY KEY, body VARCHAR(100))").unwrap();
+    let values: Vec<String> = (0..500).map(|i| format!("({}, 'note body {:05}')", i, i)).collect();
+    db.execute(&format!("INSERT INTO notes VALUES {}", values.join(", "))).unwrap();
+    assert!(db.buffer_pool_stats().unwrap().dirty_pages > 0);
+
+    db.start_background_writer(Duration::from_millis(5));
+    assert!(db.background_writer_running());
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while db.buffer_pool_stats().unwrap().dirty_pages > 0 && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    assert_eq!(db.buffer_pool_stats().unwrap().dirty_pages, 0);
+    assert!(db.stop_background_writer() > 0);
+    assert!(!db.background_writer_running());
+    assert_eq!(db.stop_background_writer(), 0);
+
+    // 停止后新的脏页留在缓冲池中，查询照常读到最新的行
+    db.execute("UPDATE notes SET body = 'edited' WHERE id = 7").unwrap();
+    std::thread::sleep(Duration::from_millis(20));
+    assert!(db.buffer_pool_stats().unwrap().dirty_pages > 0);
+    let result = db.execute("SELECT body FROM notes WHERE id = 7").unwrap();
+    assert_eq!(result.rows[0].values[0], Value::Varchar("edited".to_string()));
+    drop(db);
+
+    // 默认配置打开时自动启动
+    let db = Database::new(test_dir).expect("Failed to reopen database");
+    assert!(db.background_writer_running());
+    drop(db);
+
+    let mut db = Database::in_memory().unwrap();
+    assert!(!db.background_writer_running());
+    db.start_background_writer(Duration::from_millis(5));
+    assert!(!db.background_writer_running());
+
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+/// 测试打开时和运行中设置缓冲池的替换策略和页数
+#[test]
+fn test_buffer_pool_policy_and_size() {
+    use crate::engine::config::DatabaseConfig;
+    use crate::storage::CachePolicyType;
+
+    let test_dir = "test_db_buffer_policy";
+    let _ = fs::remove_dir_all(test_dir);
+
+    let config = DatabaseConfig::default()
+        .with_buffer_pool_size(16)
+        .with_buffer_policy(CachePolicyType::Clock);
+    let mut db = Database::with_config(test_dir, config).expect("Failed to create database");
+    assert_eq!(db.buffer_pool_settings(), (16, CachePolicyType::Clock));
+    assert_eq!(db.buffer_pool_stats().unwrap().pool_size, 16);
+
+    db.execute("CREATE TABLE items (id INT PRIMARY KEY, label VARCHAR(100))").unwrap();
+    let values: Vec<String> = (0..1000).map(|i| format!("({}, 'item label {:05}')", i, i)).collect();
+    db.execute(&format!("INSERT INTO items VALUES {}", values.join(", "))).unwrap();
+    db.execute("UPDATE items SET label = 'changed' WHERE id < 10").unwrap();
+
+    // 换成更大的 LFU 缓冲池：旧缓冲池的脏页写回，新缓冲池重新读页
+    db.execute("SET buffer_policy = 'lfu'").unwrap();
+    db.execute("SET buffer_pool_size TO 64").unwrap();
+    assert_eq!(db.buffer_pool_settings(), (64, CachePolicyType::LFU));
+    let stats = db.buffer_pool_stats().unwrap();
+    assert_eq!((stats.pool_size, stats.used_frames, stats.hits + stats.misses), (64, 0, 0));
+    assert!(db.background_writer_running());
+
+    let result = db.execute("SELECT COUNT(*) FROM items WHERE label = 'changed'").unwrap();
+    assert_eq!(result.rows[0].values[0], Value::Integer(10));
+    assert!(db.buffer_pool_stats().unwrap().misses > 0);
+
+    assert!(db.execute("SET buffer_policy = 'mru'").is_err());
+    assert!(db.execute("SET buffer_pool_size = 0").is_err());
+    assert!(db.execute("SET work_mem = 64").is_err());
+    assert_eq!(db.buffer_pool_settings(), (64, CachePolicyType::LFU));
+
+    drop(db);
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+/// 测试关闭数据库：写回脏页、写入正常关闭标记、拒绝后续语句；丢弃时同样正常关闭
+#[test]
+fn test_close_database() {
+    let test_dir = "test_db_close";
+    let _ = fs::remove_dir_all(test_dir);
+
+    let mut db = Database::new(test_dir).expect("Failed to create database");
+    assert!(!db.opened_after_clean_shutdown());
+    db.execute("CREATE TABLE accounts (id INT PRIMARY KEY, balance INT)").unwrap();
+    db.execute("INSERT INTO accounts VALUES (1, 100), (2, 200)").unwrap();
+    db.execute("BEGIN").unwrap();
+    db.execute("UPDATE accounts SET balance = 0").unwrap();
+
+    db.close().unwrap();
+    assert!(db.is_closed());
+    assert!(!db.in_transaction());
+    assert_eq!(db.buffer_pool_stats().unwrap().dirty_pages, 0);
+    assert!(!db.background_writer_running());
+    assert!(Path::new(test_dir).join("clean_shutdown").exists());
+    assert!(matches!(db.execute("SELECT * FROM accounts").unwrap_err(), ExecutionError::DatabaseClosed));
+    assert!(matches!(db.checkpoint().unwrap_err(), ExecutionError::DatabaseClosed));
+    db.close().unwrap();
+    drop(db);
+
+    // 未提交的事务被回滚；打开时读到标记后即删除
+    let mut db = Database::new(test_dir).expect("Failed to reopen database");
+    assert!(db.opened_after_clean_shutdown());
+    assert!(!Path::new(test_dir).join("clean_shutdown").exists());
+    let result = db.execute("SELECT balance FROM accounts ORDER BY id").unwrap();
+    assert_eq!(result.rows[1].values[0], Value::Integer(200));
+    db.execute("INSERT INTO accounts VALUES (3, 300)").unwrap();
+    drop(db);
+
+    let db = Database::new(test_dir).expect("Failed to reopen database");
+    assert!(db.opened_after_clean_shutdown());
+    drop(db);
+
+    // 没有经过 close 或 drop 的退出（例如进程崩溃）不留下标记
+    let mut db = Database::new(test_dir).expect("Failed to reopen database");
+    db.stop_background_writer();
+    std::mem::forget(db);
+    let mut db = Database::new(test_dir).expect("Failed to reopen database");
+    assert!(!db.opened_after_clean_shutdown());
+    let result = db.execute("SELECT COUNT(*) FROM accounts").unwrap();
+    assert_eq!(result.rows[0].values[0], Value::Integer(3));
+
+    drop(db);
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+/// 测试以内存映射读写页文件：查询、快照和重新打开都与系统调用方式一致
+#[cfg(feature = "mmap")]
+#[test]
+fn test_mmap_file_io() {
+    use crate::engine::config::DatabaseConfig;
+    use crate::storage::FileIo;
+
+    let test_dir = "test_db_mmap_io";
+    let _ = fs::remove_dir_all(test_dir);
+    let config = DatabaseConfig::default()
+        .with_buffer_pool_size(8)
+        .with_file_io(FileIo::Mmap);
+
+    {
+        let mut db = Database::with_config(test_dir, config.clone()).expect("Failed to create database");
+        db.execute("CREATE TABLE events (id INT PRIMARY KEY, payload VARCHAR(100))").unwrap();
+        let values: Vec<String> = (0..1000).map(|i| format!("({}, 'event payload {:05}')", i, i)).collect();
+        db.execute(&format!("INSERT INTO events VALUES {}", values.join(", "))).unwrap();
+        db.execute("DELETE FROM events WHERE id >= 900").unwrap();
+        db.checkpoint().unwrap();
+        db.execute("UPDATE events SET payload = 'changed' WHERE id < 5").unwrap();
+        let result = db.execute("SELECT COUNT(*) FROM events").unwrap();
+        assert_eq!(result.rows[0].values[0], Value::Integer(900));
+    }
+
+    // 内存映射写入的快照页可以用系统调用方式读回，反之亦然
+    for io in [FileIo::Syscall, FileIo::Mmap] {
+        let mut db = Database::with_config(test_dir, config.clone().with_file_io(io)).expect("Failed to reopen database");
+        let result = db.execute("SELECT COUNT(*) FROM events WHERE payload = 'changed'").unwrap();
+        assert_eq!(result.rows[0].values[0], Value::Integer(5));
+        let result = db.execute("SELECT payload FROM events WHERE id = 899").unwrap();
+        assert_eq!(result.rows[0].values[0], Value::Varchar("event payload 00899".to_string()));
+        db.checkpoint().unwrap();
+    }
+
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+/// 测试静态数据加密：磁盘上没有明文，重新打开时校验密钥
+#[cfg(feature = "encryption")]
+#[test]
+fn test_encrypted_database() {
+    use crate::storage::{EncryptionError, EncryptionKey};
+
+    fn files_contain(dir: &Path, needle: &[u8]) -> bool {
+        fs::read_dir(dir).unwrap().any(|entry| {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                files_contain(&path, needle)
+            } else {
+                fs::read(&path).unwrap().windows(needle.len()).any(|w| w == needle)
+            }
+        })
+    }
+
+    let test_dir = "test_db_encrypted";
+    let _ = fs::remove_dir_all(test_dir);
+    let key = EncryptionKey::new([42; EncryptionKey::LEN]);
+
+    {
+        let mut db = Database::with_encryption_key(test_dir, key.clone()).expect("Failed to create database");
+        db.execute("CREATE TABLE agents (id INT PRIMARY KEY, codename VARCHAR(50))").unwrap();
+        db.execute("INSERT INTO agents VALUES (1, 'classified-alpha'), (2, 'classified-bravo')").unwrap();
+        db.checkpoint().unwrap();
+        db.execute("INSERT INTO agents VALUES (3, 'classified-charlie')").unwrap();
+        db.close().unwrap();
+    }
+    assert!(!files_contain(Path::new(test_dir), b"classified"));
+    assert!(!files_contain(Path::new(test_dir), b"agents"));
+
+    // 正确的密钥可以读回快照和 WAL 中的数据
+    {
+        let mut db = Database::with_encryption_key(test_dir, key.clone()).expect("Failed to reopen database");
+        assert!(db.opened_after_clean_shutdown());
+        let result = db.execute("SELECT codename FROM agents ORDER BY id").unwrap();
+        assert_eq!(result.rows.len(), 3);
+        assert_eq!(result.rows[2].values[0], Value::Varchar("classified-charlie".to_string()));
+
+        // 备份以同一个密钥加密
+        let _ = fs::remove_dir_all("test_db_encrypted_backup");
+        assert!(db.backup("test_db_encrypted_backup").unwrap().encrypted);
+    }
+    assert!(!files_contain(Path::new("test_db_encrypted_backup"), b"classified"));
+    {
+        let mut backup = Database::with_encryption_key("test_db_encrypted_backup", key.clone()).expect("Failed to open backup");
+        let result = backup.execute("SELECT COUNT(*) FROM agents").unwrap();
+        assert_eq!(result.rows[0].values[0], Value::Integer(3));
+    }
+    assert!(Database::new("test_db_encrypted_backup").is_err());
+    let _ = fs::remove_dir_all("test_db_encrypted_backup");
+
+    // 错误的密钥和缺少密钥都在打开时报错
+    let wrong = EncryptionKey::new([7; EncryptionKey::LEN]);
+    assert!(matches!(
+        Database::with_encryption_key(test_dir, wrong),
+        Err(ExecutionError::Encryption(EncryptionError::WrongKey))
+    ));
+    assert!(matches!(
+        Database::new(test_dir),
+        Err(ExecutionError::Encryption(EncryptionError::KeyRequired))
+    ));
+    let _ = fs::remove_dir_all(test_dir);
+
+    // 已有的未加密目录不能用密钥打开
+    drop(Database::new(test_dir).expect("Failed to create database"));
+    assert!(matches!(
+        Database::with_encryption_key(test_dir, key),
+        Err(ExecutionError::Encryption(EncryptionError::NotEncrypted))
+    ));
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+/// 测试在线备份：备份目录是备份时刻的一致状态，数据库在备份后照常可写
+#[test]
+fn test_backup_database() {
+    use crate::engine::backup::{BackupManifest, BACKUP_MANIFEST_NAME};
+
+    let test_dir = "test_db_backup";
+    let backup_dir = "test_db_backup_copy";
+    let _ = fs::remove_dir_all(test_dir);
+    let _ = fs::remove_dir_all(backup_dir);
+
+    let mut db = Database::new(test_dir).expect("Failed to create database");
+    db.execute("CREATE TABLE orders (id INT PRIMARY KEY, item VARCHAR(50))").unwrap();
+    let values: Vec<String> = (0..200).map(|i| format!("({}, 'item {}')", i, i)).collect();
+    db.execute(&format!("INSERT INTO orders VALUES {}", values.join(", "))).unwrap();
+    db.checkpoint().unwrap();
+    // 检查点之后的修改只在 WAL 中
+    db.execute("DELETE FROM orders WHERE id >= 150").unwrap();
+    db.execute("CREATE TABLE notes (body VARCHAR(20))").unwrap();
+    db.execute("INSERT INTO notes VALUES ('kept')").unwrap();
+
+    let result = db.execute(&format!("BACKUP TO '{}'", backup_dir)).unwrap();
+    assert!(result.message.starts_with("BACKUP to"), "{}", result.message);
+
+    // 备份之后的修改不影响备份
+    db.execute("DELETE FROM orders").unwrap();
+    db.execute("DROP TABLE notes").unwrap();
+
+    let manifest: BackupManifest = serde_json::from_slice(&fs::read(Path::new(backup_dir).join(BACKUP_MANIFEST_NAME)).unwrap()).unwrap();
+    assert!(!manifest.encrypted);
+    assert!(manifest.lsn > 0);
+    let names: Vec<&str> = manifest.files.iter().map(|file| file.name.as_str()).collect();
+    for name in ["metadata.json", "wal.log", "table_1.json", "table_1_2.db", "table_2.json"] {
+        assert!(names.contains(&name), "{} is not in the backup: {:?}", name, names);
+    }
+    for file in &manifest.files {
+        let data = fs::read(Path::new(backup_dir).join(&file.name)).unwrap();
+        assert_eq!(file.size, data.len() as u64);
+        assert_eq!(file.checksum, crate::utils::checksum(&data));
+    }
+
+    // 目标目录非空或在事务中都拒绝备份
+    assert!(db.backup(backup_dir).is_err());
+    db.execute("BEGIN").unwrap();
+    assert!(db.execute("BACKUP TO 'test_db_backup_other'").is_err());
+    db.execute("ROLLBACK").unwrap();
+    drop(db);
+
+    let mut restored = Database::new(backup_dir).expect("Failed to open backup");
+    let result = restored.execute("SELECT COUNT(*) FROM orders").unwrap();
+    assert_eq!(result.rows[0].values[0], Value::Integer(150));
+    let result = restored.execute("SELECT body FROM notes").unwrap();
+    assert_eq!(result.rows[0].values[0], Value::Varchar("kept".to_string()));
+    drop(restored);
+
+    let _ = fs::remove_dir_all(test_dir);
+    let _ = fs::remove_dir_all(backup_dir);
+    let _ = fs::remove_dir_all("test_db_backup_other");
+}
+
+/// 测试从备份恢复：校验清单和校验和，损坏的备份不会恢复出任何文件
+#[test]
+fn test_restore_backup() {
+    let test_dir = "test_db_restore_source";
+    let backup_dir = "test_db_restore_backup";
+    let restore_dir = "test_db_restore_target";
+    for dir in [test_dir, backup_dir, restore_dir] {
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    let mut db = Database::new(test_dir).expect("Failed to create database");
+    db.execute("CREATE TABLE ledger (id INT PRIMARY KEY, amount INT)").unwrap();
+    db.execute("INSERT INTO ledger VALUES (1, 10), (2, 20), (3, 30)").unwrap();
+    db.checkpoint().unwrap();
+    db.execute("UPDATE ledger SET amount = 25 WHERE id = 2").unwrap();
+    let backup = db.backup(backup_dir).unwrap();
+    drop(db);
+
+    let manifest = Database::restore(backup_dir, restore_dir).unwrap();
+    assert_eq!(manifest, backup);
+    assert!(!Path::new(restore_dir).join("backup_manifest.json").exists());
+    {
+        let mut restored = Database::new(restore_dir).expect("Failed to open restored database");
+        let result = restored.execute("SELECT amount FROM ledger ORDER BY id").unwrap();
+        assert_eq!(result.rows.len(), 3);
+        assert_eq!(result.rows[1].values[0], Value::Integer(25));
+    }
+
+    // 不覆盖已有数据的目录
+    let error = Database::restore(backup_dir, restore_dir).unwrap_err();
+    assert!(error.to_string().contains("not empty"), "{}", error);
+    fs::remove_dir_all(restore_dir).unwrap();
+
+    // 被修改或缺失的文件在复制任何文件之前就被发现
+    let wal = Path::new(backup_dir).join("wal.log");
+    let mut data = fs::read(&wal).unwrap();
+    let last = data.len() - 1;
+    data[last] ^= 0xFF;
+    fs::write(&wal, &data).unwrap();
+    let error = Database::restore(backup_dir, restore_dir).unwrap_err();
+    assert!(error.to_string().contains("'wal.log' is corrupt"), "{}", error);
+    assert!(!Path::new(restore_dir).exists());
+    fs::remove_file(&wal).unwrap();
+    let error = Database::restore(backup_dir, restore_dir).unwrap_err();
+    assert!(error.to_string().contains("'wal.log' is missing"), "{}", error);
+
+    fs::remove_file(Path::new(backup_dir).join("backup_manifest.json")).unwrap();
+    assert!(Database::restore(backup_dir, restore_dir).is_err());
+    assert!(!Path::new(restore_dir).exists());
+
+    for dir in [test_dir, backup_dir, restore_dir] {
+        let _ = fs::remove_dir_all(dir);
+    }
+}
+
+/// 测试时间点恢复：恢复备份后重放 WAL 归档，回到误删全部行之前
+#[test]
+fn test_point_in_time_recovery() {
+    use crate::engine::backup::RecoveryTarget;
+    use crate::engine::config::DatabaseConfig;
+    use std::time::{Duration, SystemTime};
+
+    let test_dir = "test_db_pitr_source";
+    let archive_dir = "test_db_pitr_archive";
+    let backup_dir = "test_db_pitr_backup";
+    let restore_dir = "test_db_pitr_target";
+    let dirs = [test_dir, archive_dir, backup_dir, restore_dir];
+    for dir in dirs {
+        let _ = fs::remove_dir_all(dir);
+    }
+    let config = DatabaseConfig::default().with_wal_archive_dir(archive_dir);
+
+    let mut db = Database::with_config(test_dir, config.clone()).expect("Failed to create database");
+    db.execute("CREATE TABLE orders (id INT PRIMARY KEY, amount INT)").unwrap();
+    db.execute("INSERT INTO orders VALUES (1, 10), (2, 20)").unwrap();
+    let backup = db.backup(backup_dir).unwrap();
+    db.execute("INSERT INTO orders VALUES (3, 30)").unwrap();
+    // 检查点截断了 WAL，归档仍保留之前的批次
+    db.checkpoint().unwrap();
+    db.execute("UPDATE orders SET amount = 25 WHERE id = 2").unwrap();
+    let before_delete_lsn = db.last_lsn();
+    let before_delete = SystemTime::now();
+    std::thread::sleep(Duration::from_millis(20));
+    db.execute("DELETE FROM orders").unwrap();
+    db.execute("INSERT INTO orders VALUES (4, 40)").unwrap();
+    let after_delete_lsn = db.last_lsn();
+    db.execute("CREATE TABLE later (id INT)").unwrap();
+    drop(db);
+
+    let amounts = |dir: &str| -> Vec<Value> {
+        let mut restored = Database::new(dir).expect("Failed to open restored database");
+        let result = restored.execute("SELECT amount FROM orders ORDER BY id").unwrap();
+        result.rows.into_iter().map(|row| row.values[0].clone()).collect()
+    };
+    let expected = vec![Value::Integer(10), Value::Integer(25), Value::Integer(30)];
+
+    let info = Database::restore_to(backup_dir, restore_dir, RecoveryTarget::Lsn(before_delete_lsn), &config).unwrap();
+    assert_eq!(info.backup_lsn, backup.lsn);
+    assert_eq!(info.recovered_lsn, before_delete_lsn);
+    assert_eq!(info.batches, 3);
+    assert_eq!(amounts(restore_dir), expected);
+    fs::remove_dir_all(restore_dir).unwrap();
+
+    let info = Database::restore_to(backup_dir, restore_dir, RecoveryTarget::Time(before_delete), &config).unwrap();
+    assert_eq!(info.recovered_lsn, before_delete_lsn);
+    assert_eq!(amounts(restore_dir), expected);
+    fs::remove_dir_all(restore_dir).unwrap();
+
+    let info = Database::restore_to(backup_dir, restore_dir, RecoveryTarget::Lsn(after_delete_lsn), &config).unwrap();
+    assert_eq!(info.recovered_lsn, after_delete_lsn);
+    assert_eq!(amounts(restore_dir), vec![Value::Integer(40)]);
+    fs::remove_dir_all(restore_dir).unwrap();
+
+    // 备份之后建立的表没有快照，无法重放；没有归档目录时无从恢复
+    let error = Database::restore_to(backup_dir, restore_dir, RecoveryTarget::Latest, &config).unwrap_err();
+    assert!(error.to_string().contains("'later' was created after the backup"), "{}", error);
+    assert!(!Path::new(restore_dir).exists());
+    let error = Database::restore_to(backup_dir, restore_dir, RecoveryTarget::Latest, &DatabaseConfig::default()).unwrap_err();
+    assert!(error.to_string().contains("WAL archive"), "{}", error);
+
+    for dir in dirs {
+        let _ = fs::remove_dir_all(dir);
+    }
+}
+
+/// 测试 SQL 转储：导出的语句导入另一个数据库后得到相同的表、数据和索引
+#[test]
+fn test_dump_sql() {
+    let mut db = Database::in_memory().unwrap();
+    db.execute("CREATE TABLE customers (id INT PRIMARY KEY, name VARCHAR(40) NOT NULL, email VARCHAR(60) UNIQUE, vip BOOLEAN DEFAULT FALSE)").unwrap();
+    db.execute("CREATE TABLE visits (customer INT, day DATE, seq INT, spent DOUBLE, PRIMARY KEY (customer, day, seq))").unwrap();
+    db.execute("CREATE INDEX visits_day ON visits (day)").unwrap();
+    db.execute("INSERT INTO customers VALUES (1, 'O''Brien', 'ob@example.com', TRUE), (2, 'Li', NULL, FALSE)").unwrap();
+    db.execute("INSERT INTO visits VALUES (1, CAST('2024-03-01' AS DATE), 1, 12.5), (2, CAST('2024-03-02' AS DATE), 1, -0.1)").unwrap();
+
+    let mut dump = Vec::new();
+    db.dump_sql(&mut dump).unwrap();
+    let dump = String::from_utf8(dump).unwrap();
+    assert!(dump.contains("INSERT INTO customers (id, name, email, vip) VALUES (1, 'O''Brien', 'ob@example.com', TRUE);"), "{}", dump);
+    assert!(dump.contains("    PRIMARY KEY (customer, day, seq)\n);"), "{}", dump);
+    assert!(dump.contains("CREATE UNIQUE INDEX customers_email_key ON customers (email);"), "{}", dump);
+
+    let mut copy = Database::in_memory().unwrap();
+    for result in copy.execute_script(&dump, true) {
+        result.unwrap();
+    }
+    assert_eq!(copy.get_table_schema("visits"), db.get_table_schema("visits"));
+    assert_eq!(copy.get_table_indexes("customers"), db.get_table_indexes("customers"));
+    let mut again = Vec::new();
+    copy.dump_sql(&mut again).unwrap();
+    assert_eq!(String::from_utf8(again).unwrap(), dump);
+
+    // 导入的主键照常生效
+    assert!(copy.execute("INSERT INTO visits VALUES (1, CAST('2024-03-01' AS DATE), 1, 0.0)").is_err());
+}
+
+/// 测试表快照的行存放在槽页中：表比缓冲池大得多时仍能完整写入和读回
+#[test]
+fn test_table_rows_in_slotted_pages() {
+    use crate::engine::config::DatabaseConfig;
+
+    let test_dir = "test_db_slotted_pages";
+    let _ = fs::remove_dir_all(test_dir);
+    let config = DatabaseConfig::default().with_buffer_pool_size(4);
+
+    {
+        let mut db = Database::with_config(test_dir, config.clone()).expect("Failed to create database");
+        db.execute("CREATE TABLE events (id INT PRIMARY KEY, payload TEXT)").unwrap();
+        for batch in 0..10 {
+            let values: Vec<String> = (0..100)
+                .map(|i| format!("({}, 'event payload number {}')", batch * 100 + i, batch * 100 + i))
+                .collect();
+            db.execute(&format!("INSERT INTO events VALUES {}", values.join(", "))).unwrap();
+        }
+        db.execute("DELETE FROM events WHERE id < 10").unwrap();
+        db.execute("CHECKPOINT").unwrap();
+    }
+
+    let table_file = fs::metadata(Path::new(test_dir).join("table_1_2.db")).unwrap();
+    assert!(table_file.len() > 4 * crate::DEFAULT_PAGE_SIZE as u64);
+
+    {
+        let mut db = Database::with_config(test_dir, config).expect("Failed to reopen database");
+        let result = db.execute("SELECT COUNT(*) FROM events").unwrap();
+        assert_eq!(result.rows[0].values[0], Value::Integer(990));
+        let result = db.execute("SELECT rowid, payload FROM events WHERE id = 999").unwrap();
+        assert_eq!(result.rows[0].values[0], Value::BigInt(1000));
+        assert_eq!(result.rows[0].values[1], Value::Varchar("event payload number 999".to_string()));
+
+        // 放不进一页的行写入溢出页链
+        let long: String = (0..5 * crate::DEFAULT_PAGE_SIZE).map(|i| char::from(b'a' + (i % 26) as u8)).collect();
+        db.execute(&format!("INSERT INTO events VALUES (5000, '{}')", long)).unwrap();
+        db.execute("CHECKPOINT").unwrap();
+    }
+
+    let snapshot = fs::read_to_string(Path::new(test_dir).join("table_1.json")).unwrap();
+    assert!(!snapshot.contains("abcdefghij"));
+    let mut db = Database::new(test_dir).expect("Failed to reopen database");
+    let result = db.execute("SELECT COUNT(*) FROM events").unwrap();
+    assert_eq!(result.rows[0].values[0], Value::Integer(991));
+    let result = db.execute("SELECT payload FROM events WHERE id = 5000").unwrap();
+    match &result.rows[0].values[0] {
+        Value::Varchar(payload) => {
+            assert_eq!(payload.len(), 5 * crate::DEFAULT_PAGE_SIZE);
+            assert!(payload.starts_with("abcdefghij"));
+        }
+        other => panic!("unexpected payload {:?}", other),
+    }
+    assert!(Path::new(test_dir).join("table_1_3.db").exists());
+
+    drop(db);
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+/// 测试页压缩：全局配置的压缩方式，以及 ALTER TABLE ... SET COMPRESSION 按表覆盖
+#[cfg(feature = "compression")]
+#[test]
+fn test_page_compression() {
+    use crate::engine::config::DatabaseConfig;
+    use crate::storage::Compression;
+
+    let test_dir = "test_db_page_compression";
+    let _ = fs::remove_dir_all(test_dir);
+    let config = DatabaseConfig::default().with_page_compression(Compression::Lz4);
+
+    // 表当前堆文件的大小
+    let heap_size = |table_id: u32| -> u64 {
+        fs::read_dir(test_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap())
+            .filter(|entry| {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                name.starts_with(&format!("table_{}_", table_id)) && name.ends_with(".db")
+            })
+            .map(|entry| entry.metadata().unwrap().len())
+            .sum()
+    };
+
+    {
+        let mut db = Database::with_config(test_dir, config.clone()).expect("Failed to create database");
+        for table in ["plain", "packed"] {
+            db.execute(&format!("CREATE TABLE {} (id INT PRIMARY KEY, note VARCHAR(100))", table)).unwrap();
+            let values: Vec<String> = (0..500).map(|i| format!("({}, 'the same note, again and again')", i)).collect();
+            db.execute(&format!("INSERT INTO {} VALUES {}", table, values.join(", "))).unwrap();
+        }
+        let result = db.execute("ALTER TABLE plain SET COMPRESSION none").unwrap();
+        assert!(result.message.contains("none"));
+        assert_eq!(db.table_compression("plain"), Some(Compression::None));
+        assert_eq!(db.table_compression("packed"), Some(Compression::Lz4));
+        assert!(db.execute("ALTER TABLE packed SET COMPRESSION snappy").is_err());
+        db.execute("CHECKPOINT").unwrap();
+    }
+
+    assert!(heap_size(2) * 3 < heap_size(1), "{} vs {}", heap_size(2), heap_size(1));
+
+    {
+        let mut db = Database::with_config(test_dir, config).expect("Failed to reopen database");
+        assert_eq!(db.table_compression("plain"), Some(Compression::None));
+        let result = db.execute("SELECT note FROM packed WHERE id = 499").unwrap();
+        assert_eq!(result.rows[0].values[0], Value::Varchar("the same note, again and again".to_string()));
+
+        db.execute("ALTER TABLE packed SET COMPRESSION 'zstd'").unwrap();
+        db.execute("ALTER TABLE plain SET COMPRESSION DEFAULT").unwrap();
+        assert_eq!(db.table_compression("plain"), Some(Compression::Lz4));
+    }
+
+    // 不带全局配置重新打开：按表设置保留，压缩文件照常读取
+    let mut db = Database::new(test_dir).expect("Failed to reopen database");
+    assert_eq!(db.table_compression("packed"), Some(Compression::Zstd));
+    assert_eq!(db.table_compression("plain"), Some(Compression::None));
+    for table in ["plain", "packed"] {
+        let result = db.execute(&format!("SELECT COUNT(*) FROM {}", table)).unwrap();
+        assert_eq!(result.rows[0].values[0], Value::Integer(500));
+    }
+
+    drop(db);
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+/// 测试数据目录大小上限：达到上限后拒绝 INSERT / COPY / CREATE INDEX，删除数据不受限制
+#[test]
+fn test_disk_quota() {
+    use crate::engine::config::DatabaseConfig;
+
+    let test_dir = "test_db_disk_quota";
+    let _ = fs::remove_dir_all(test_dir);
+
+    let mut db = Database::new(test_dir).expect("Failed to create database");
+    db.execute("CREATE TABLE logs (id INT, msg VARCHAR(100))").unwrap();
+    let limit = db.data_size().unwrap() + 200;
+    drop(db);
+
+    let config = DatabaseConfig::default().with_max_data_bytes(limit);
+    let mut db = Database::with_config(test_dir, config).expect("Failed to reopen database");
+    let mut inserted = 0;
+    let error = loop {
+        match db.execute(&format!("INSERT INTO logs VALUES ({}, 'a fairly long log message')", inserted)) {
+            Ok(_) => inserted += 1,
+            Err(e) => break e,
+        }
+        assert!(inserted < 100, "quota was never enforced");
+    };
+    match error {
+        ExecutionError::DiskQuotaExceeded { usage, limit: reported } => {
+            assert_eq!(reported, limit);
+            assert!(usage >= limit);
+        }
+        other => panic!("Expected DiskQuotaExceeded, got {:?}", other),
+    }
+    assert!(inserted > 0);
+    assert!(matches!(db.execute("CREATE INDEX idx_id ON logs (id)"), Err(ExecutionError::DiskQuotaExceeded { .. })));
+
+    // 读取和删除仍然可用
+    assert_eq!(db.execute("SELECT * FROM logs").unwrap().rows.len(), inserted);
+    db.execute("DELETE FROM logs WHERE id > 0").unwrap();
+
+    drop(db);
+    let _ = fs::remove_dir_all(test_dir);
+}
+
+/// 测试内存存储后端
+#[test]
+fn test_in_memory_database() {
+    use super::config::DatabaseConfig;
+    use crate::storage::MemoryBackend;
+    use std::sync::Arc;
+
+    let mut db = Database::in_memory().expect("Failed to create database");
+    assert!(db.temp_files().is_none());
+    db.execute("CREATE TABLE t (id INT PRIMARY KEY, name VARCHAR(10))").expect("Failed to create table");
+    db.execute("INSERT INTO t VALUES (1, 'a')").expect("Failed to insert");
+    assert_eq!(db.execute("SELECT * FROM t").expect("Failed to select").rows.len(), 1);
+
+    // A shared handle lets the host export the contents (e.g. into IndexedDB) and restore them later
+    let backend = Arc::new(MemoryBackend::new());
+    {
+        let mut db = Database::with_backend(Box::new(backend.clone()), DatabaseConfig::default())
+            .expect("Failed to create database");
+        db.execute("CREATE TABLE t (id INT PRIMARY KEY, name VARCHAR(10))").expect("Failed to create table");
+        db.execute("INSERT INTO t VALUES (1, 'a')").expect("Failed to insert");
+        db.execute("INSERT INTO t VALUES (2, 'b')").expect("Failed to insert");
+    }
+    let snapshot = backend.snapshot();
+    assert!(snapshot.iter().any(|(name, _)| name == "metadata.json"));
+
+    let restored = MemoryBackend::from_snapshot(snapshot);
+    let mut db = Database::with_backend(Box::new(restored), DatabaseConfig::default())
+        .expect("Failed to restore database");
+    let result = db.execute("SELECT name FROM t WHERE id = 2").expect("Failed to select");
+    assert_eq!(result.rows[0].values, vec![Value::Varchar("b".to_string())]);
+    assert!(db.execute("INSERT INTO t VALUES (1, 'c')").is_err());
+    assert!(!Path::new("metadata.json").exists());
+}
@@ -3,12 +3,12 @@
 //! 具有基本 ACID 属性的简单事务管理器：
 //! - 原子性：事务中的所有操作要么全部成功，要么全部失败
 //! - 一致性：维护数据库约束
-//! - 隔离性：事务之间相互隔离（基本的读写锁）
+//! - 隔离性：事务之间通过行锁和表锁隔离（冲突时等待，支持超时和死锁检测）
 //! - 持久性：已提交的事务是持久的
 
 use std::collections::{HashMap, HashSet};
-use std::sync::{Arc, Mutex, RwLock};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::{Arc, Condvar, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use thiserror::Error;
 
 pub type TransactionId = u64;
@@ -32,10 +32,57 @@ pub enum IsolationLevel {
 }
 
 /// 并发控制的锁类型
-#[derive(Debug, Clone, PartialEq)]
+///
+/// 行锁之前要先在表上取得对应的意向锁（IntentionShared / IntentionExclusive），
+/// 这样表级 S / X 锁只需检查表资源就能发现行上的冲突。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum LockType {
     SharedRead,
     ExclusiveWrite,
+    IntentionShared,
+    IntentionExclusive,
+}
+
+impl LockType {
+    /// 两个不同事务能否同时持有
+    pub fn is_compatible_with(self, other: LockType) -> bool {
+        use LockType::*;
+        matches!(
+            (self, other),
+            (IntentionShared, IntentionShared | IntentionExclusive | SharedRead)
+                | (IntentionExclusive, IntentionShared | IntentionExclusive)
+                | (SharedRead, IntentionShared | SharedRead)
+        )
+    }
+    
+    /// 持有此锁是否已经满足对 other 的请求
+    fn covers(self, other: LockType) -> bool {
+        use LockType::*;
+        self == other
+            || self == ExclusiveWrite
+            || (other == IntentionShared && matches!(self, SharedRead | IntentionExclusive))
+    }
+    
+    /// 同一事务已持有 self 时再请求 other 后应持有的锁（没有 SIX 锁，S 与 IX 合并为 X）
+    fn combine(self, other: LockType) -> LockType {
+        if self.covers(other) {
+            self
+        } else if other.covers(self) {
+            other
+        } else {
+            LockType::ExclusiveWrite
+        }
+    }
+}
+
+/// 表资源的锁名
+pub fn table_resource(table: &str) -> String {
+    format!("table:{}", table)
+}
+
+/// 行资源的锁名
+pub fn row_resource(table: &str, row_id: u64) -> String {
+    format!("row:{}:{}", table, row_id)
 }
 
 /// 锁请求
@@ -83,14 +130,68 @@ pub enum TransactionOperation {
 }
 
 /// 并发控制的锁管理器
+///
+/// 克隆得到的是同一个锁表的句柄，多个会话共享它时彼此的锁才会冲突。
+/// 冲突的请求最多等待 `timeout`，等待会形成环时立即以死锁报错。
+#[derive(Clone)]
 pub struct LockManager {
-    /// 资源锁：resource_id -> (transaction_id, lock_type)
-    locks: Arc<Mutex<HashMap<String, (TransactionId, LockType)>>>,
-    /// 死锁检测的等待图
-    wait_for: Arc<Mutex<HashMap<TransactionId, HashSet<TransactionId>>>>,
+    /// 锁表和等待图
+    state: Arc<Mutex<LockTable>>,
+    /// 有锁被释放时唤醒等待者
+    released: Arc<Condvar>,
+    /// 等待冲突锁的最长时间；为零时冲突立即报错
+    timeout: Duration,
+}
+
+/// 锁管理器的共享状态
+#[derive(Default)]
+struct LockTable {
+    /// 资源锁：resource_id -> (transaction_id -> lock_type)
+    locks: HashMap<String, HashMap<TransactionId, LockType>>,
+    /// 死锁检测的等待图：等待者 -> 它在等待的持有者
+    wait_for: HashMap<TransactionId, HashSet<TransactionId>>,
+}
+
+impl LockTable {
+    /// 授予请求所需的锁，或返回与之冲突的持有者
+    fn try_grant(&mut self, request: &LockRequest) -> Result<(), HashSet<TransactionId>> {
+        let holders = self.locks.entry(request.resource_id.clone()).or_default();
+        let wanted = match holders.get(&request.transaction_id) {
+            Some(held) => held.combine(request.lock_type),
+            None => request.lock_type,
+        };
+        let conflicts: HashSet<TransactionId> = holders.iter()
+            .filter(|&(&holder, &held)| holder != request.transaction_id && !wanted.is_compatible_with(held))
+            .map(|(&holder, _)| holder)
+            .collect();
+        if conflicts.is_empty() {
+            holders.insert(request.transaction_id, wanted);
+            Ok(())
+        } else {
+            Err(conflicts)
+        }
+    }
+    
+    /// 从 waiter 出发沿等待图能否回到 waiter
+    fn has_cycle(&self, waiter: TransactionId) -> bool {
+        let mut stack: Vec<TransactionId> = self.wait_for.get(&waiter).into_iter().flatten().copied().collect();
+        let mut visited = HashSet::new();
+        while let Some(txn) = stack.pop() {
+            if txn == waiter {
+                return true;
+            }
+            if visited.insert(txn) {
+                stack.extend(self.wait_for.get(&txn).into_iter().flatten().copied());
+            }
+        }
+        false
+    }
 }
 
 /// 事务管理器
+///
+/// 克隆得到的句柄共享事务表和锁管理器，供同一数据库的多个会话使用。
+#[derive(Clone)]
 pub struct TransactionManager {
     /// 活跃事务
     transactions: Arc<RwLock<HashMap<TransactionId, Transaction>>>,
@@ -123,6 +224,13 @@ pub enum TransactionError {
         holder: TransactionId 
     },
     
+    #[error("Lock wait timeout: resource {resource} is still locked by transaction {holder} after {waited_ms} ms")]
+    LockTimeout {
+        resource: String,
+        holder: TransactionId,
+        waited_ms: u128,
+    },
+    
     #[error("Invalid transaction state: expected {expected:?}, found {found:?}")]
     InvalidState { 
         expected: TransactionState, 
@@ -137,65 +245,121 @@ pub enum TransactionError {
 }
 
 impl LockManager {
+    /// 冲突时立即报错的锁管理器
     pub fn new() -> Self {
+        Self::with_timeout(Duration::ZERO)
+    }
+    
+    /// 冲突时最多等待 timeout 的锁管理器
+    pub fn with_timeout(timeout: Duration) -> Self {
         Self {
-            locks: Arc::new(Mutex::new(HashMap::new())),
-            wait_for: Arc::new(Mutex::new(HashMap::new())),
+            state: Arc::new(Mutex::new(LockTable::default())),
+            released: Arc::new(Condvar::new()),
+            timeout,
         }
     }
     
-    /// 获取资源上的锁
+    /// 等待冲突锁的最长时间
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+    
+    /// 获取资源上的锁，冲突时等待持有者释放
+    ///
+    /// 已持有的锁按需升级（例如 S 升级为 X）。等待超时返回 `LockTimeout`（超时为零时返回
+    /// `LockConflict`），等待会造成死锁时返回 `DeadlockDetected`，请求方应回滚事务。
     pub fn acquire_lock(&self, request: LockRequest) -> Result<(), TransactionError> {
-        let mut locks = self.locks.lock().unwrap();
-        
-        match locks.get(&request.resource_id) {
-            Some((holder_txn, existing_lock_type)) => {
-                if *holder_txn == request.transaction_id {
-                    // Same transaction, check lock upgrade
-                    if request.lock_type == LockType::ExclusiveWrite && 
-                       *existing_lock_type == LockType::SharedRead {
-                        // Upgrade to write lock
-                        locks.insert(request.resource_id.clone(), 
-                                   (request.transaction_id, LockType::ExclusiveWrite));
-                    }
-                    Ok(())
+        let deadline = Instant::now() + self.timeout;
+        let mut table = self.state();
+        loop {
+            let conflicts = match table.try_grant(&request) {
+                Ok(()) => {
+                    table.wait_for.remove(&request.transaction_id);
+                    return Ok(());
+                }
+                Err(conflicts) => conflicts,
+            };
+            
+            let holder = conflicts.iter().copied().min().unwrap_or_default();
+            let now = Instant::now();
+            if now >= deadline {
+                table.wait_for.remove(&request.transaction_id);
+                return Err(if self.timeout.is_zero() {
+                    TransactionError::LockConflict { resource: request.resource_id, holder }
                 } else {
-                    // Different transaction holds the lock
-                    match (existing_lock_type, &request.lock_type) {
-                        (LockType::SharedRead, LockType::SharedRead) => {
-                            // Multiple readers allowed - for simplicity, we'll allow this
-                            // In a real system, we'd need a more complex lock table
-                            Ok(())
-                        }
-                        _ => {
-                            // Conflict: exclusive lock or read-write conflict
-                            Err(TransactionError::LockConflict {
-                                resource: request.resource_id,
-                                holder: *holder_txn,
-                            })
-                        }
+                    TransactionError::LockTimeout {
+                        resource: request.resource_id,
+                        holder,
+                        waited_ms: self.timeout.as_millis(),
                     }
-                }
+                });
             }
-            None => {
-                // No existing lock, grant the lock
-                locks.insert(request.resource_id.clone(), 
-                           (request.transaction_id, request.lock_type));
-                Ok(())
+            
+            table.wait_for.insert(request.transaction_id, conflicts);
+            if table.has_cycle(request.transaction_id) {
+                table.wait_for.remove(&request.transaction_id);
+                return Err(TransactionError::DeadlockDetected { id: request.transaction_id });
             }
+            table = self.released.wait_timeout(table, deadline - now)
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .0;
         }
     }
     
-    /// 释放事务持有的所有锁
+    /// 获取表锁
+    pub fn lock_table(&self, transaction_id: TransactionId, table: &str, lock_type: LockType) -> Result<(), TransactionError> {
+        self.acquire_lock(LockRequest {
+            transaction_id,
+            resource_id: table_resource(table),
+            lock_type,
+        })
+    }
+    
+    /// 获取行锁：先在表上取得对应的意向锁
+    pub fn lock_row(&self, transaction_id: TransactionId, table: &str, row_id: u64, lock_type: LockType) -> Result<(), TransactionError> {
+        let intention = match lock_type {
+            LockType::SharedRead | LockType::IntentionShared => LockType::IntentionShared,
+            LockType::ExclusiveWrite | LockType::IntentionExclusive => LockType::IntentionExclusive,
+        };
+        self.lock_table(transaction_id, table, intention)?;
+        self.acquire_lock(LockRequest {
+            transaction_id,
+            resource_id: row_resource(table, row_id),
+            lock_type,
+        })
+    }
+    
+    /// 事务在资源上持有的锁
+    pub fn held_lock(&self, transaction_id: TransactionId, resource: &str) -> Option<LockType> {
+        self.state().locks.get(resource)?.get(&transaction_id).copied()
+    }
+    
+    /// 释放事务持有的所有锁并唤醒等待者
     pub fn release_locks(&self, transaction_id: TransactionId) {
-        let mut locks = self.locks.lock().unwrap();
-        locks.retain(|_, (holder, _)| *holder != transaction_id);
+        let mut table = self.state();
+        table.locks.retain(|_, holders| {
+            holders.remove(&transaction_id);
+            !holders.is_empty()
+        });
+        table.wait_for.remove(&transaction_id);
+        drop(table);
+        self.released.notify_all();
     }
     
-    /// 检查死锁（简化检测）
-    pub fn detect_deadlock(&self, _transaction_id: TransactionId) -> bool {
-        // Simplified deadlock detection - in a real system this would be more sophisticated
-        false
+    /// 事务当前的等待是否处在等待环中
+    pub fn detect_deadlock(&self, transaction_id: TransactionId) -> bool {
+        self.state().has_cycle(transaction_id)
+    }
+    
+    fn state(&self) -> std::sync::MutexGuard<'_, LockTable> {
+        // 持锁期间 panic 不会让锁表处于不一致的中间状态
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Default for LockManager {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -242,6 +406,19 @@ impl TransactionManager {
         }
     }
     
+    /// 锁冲突时最多等待 timeout 的事务管理器
+    pub fn with_lock_timeout(timeout: Duration) -> Self {
+        Self {
+            lock_manager: LockManager::with_timeout(timeout),
+            ..Self::new()
+        }
+    }
+    
+    /// 锁管理器
+    pub fn lock_manager(&self) -> &LockManager {
+        &self.lock_manager
+    }
+    
     /// 开始新事务
     pub fn begin_transaction(&self) -> Result<TransactionId, TransactionError> {
         self.begin_transaction_with_isolation(self.default_isolation_level)
@@ -334,39 +511,51 @@ impl TransactionManager {
     
     /// Acquire a lock for a transaction
     pub fn acquire_lock(&self, txn_id: TransactionId, resource: String, lock_type: LockType) -> Result<(), TransactionError> {
-        // Check if transaction exists and is active
-        {
-            let transactions = self.transactions.read().unwrap();
-            let transaction = transactions.get(&txn_id)
-                .ok_or(TransactionError::TransactionNotFound { id: txn_id })?;
-                
-            if transaction.state != TransactionState::Active {
-                return Err(TransactionError::InvalidState {
-                    expected: TransactionState::Active,
-                    found: transaction.state.clone(),
-                });
-            }
-        }
-        
-        let request = LockRequest {
+        self.check_active(txn_id)?;
+        self.lock_manager.acquire_lock(LockRequest {
             transaction_id: txn_id,
             resource_id: resource.clone(),
             lock_type,
-        };
-        
-        self.lock_manager.acquire_lock(request)?;
-        
-        // Add lock to transaction's held locks
-        {
-            let mut transactions = self.transactions.write().unwrap();
-            if let Some(transaction) = transactions.get_mut(&txn_id) {
-                transaction.held_locks.insert(resource);
-            }
+        })?;
+        self.record_held_locks(txn_id, [resource]);
+        Ok(())
+    }
+    
+    /// 为事务获取表锁
+    pub fn lock_table(&self, txn_id: TransactionId, table: &str, lock_type: LockType) -> Result<(), TransactionError> {
+        self.acquire_lock(txn_id, table_resource(table), lock_type)
+    }
+    
+    /// 为事务获取行锁（连同表上的意向锁）
+    pub fn lock_row(&self, txn_id: TransactionId, table: &str, row_id: u64, lock_type: LockType) -> Result<(), TransactionError> {
+        self.check_active(txn_id)?;
+        self.lock_manager.lock_row(txn_id, table, row_id, lock_type)?;
+        self.record_held_locks(txn_id, [table_resource(table), row_resource(table, row_id)]);
+        Ok(())
+    }
+    
+    /// 事务存在且处于活跃状态
+    fn check_active(&self, txn_id: TransactionId) -> Result<(), TransactionError> {
+        let transactions = self.transactions.read().unwrap();
+        let transaction = transactions.get(&txn_id)
+            .ok_or(TransactionError::TransactionNotFound { id: txn_id })?;
+        if transaction.state != TransactionState::Active {
+            return Err(TransactionError::InvalidState {
+                expected: TransactionState::Active,
+                found: transaction.state.clone(),
+            });
         }
-        
         Ok(())
     }
     
+    /// 把已获得的锁记入事务
+    fn record_held_locks(&self, txn_id: TransactionId, resources: impl IntoIterator<Item = String>) {
+        let mut transactions = self.transactions.write().unwrap();
+        if let Some(transaction) = transactions.get_mut(&txn_id) {
+            transaction.held_locks.extend(resources);
+        }
+    }
+    
     /// Log an operation for a transaction
     pub fn log_operation(&self, txn_id: TransactionId, operation: TransactionOperation) -> Result<(), TransactionError> {
         let mut transactions = self.transactions.write().unwrap();
@@ -484,6 +673,77 @@ mod tests {
         assert!(tm.acquire_lock(txn2, "table1".to_string(), LockType::ExclusiveWrite).is_err());
     }
     
+    #[test]
+    fn test_lock_compatibility_and_upgrade() {
+        let locks = LockManager::new();
+        locks.lock_row(1, "users", 7, LockType::ExclusiveWrite).unwrap();
+        
+        // 其他行和表上的意向锁互不冲突
+        locks.lock_row(2, "users", 8, LockType::ExclusiveWrite).unwrap();
+        assert!(matches!(
+            locks.lock_row(2, "users", 7, LockType::SharedRead),
+            Err(TransactionError::LockConflict { holder: 1, .. })
+        ));
+        // 表级 S 锁与行上的写意向冲突
+        assert!(locks.lock_table(3, "users", LockType::SharedRead).is_err());
+        
+        // 只有自己持有 S 锁时可以升级为 X
+        locks.lock_table(3, "orders", LockType::SharedRead).unwrap();
+        locks.lock_table(3, "orders", LockType::ExclusiveWrite).unwrap();
+        assert_eq!(locks.held_lock(3, &table_resource("orders")), Some(LockType::ExclusiveWrite));
+        
+        locks.release_locks(1);
+        locks.lock_row(2, "users", 7, LockType::SharedRead).unwrap();
+        assert_eq!(locks.held_lock(1, &row_resource("users", 7)), None);
+    }
+    
+    #[test]
+    fn test_lock_wait_and_timeout() {
+        let locks = LockManager::with_timeout(Duration::from_secs(5));
+        locks.lock_row(1, "accounts", 1, LockType::ExclusiveWrite).unwrap();
+        
+        // 等待者在持有者释放后获得锁
+        let waiter = {
+            let locks = locks.clone();
+            std::thread::spawn(move || locks.lock_row(2, "accounts", 1, LockType::ExclusiveWrite))
+        };
+        std::thread::sleep(Duration::from_millis(50));
+        locks.release_locks(1);
+        waiter.join().unwrap().unwrap();
+        
+        let short = LockManager::with_timeout(Duration::from_millis(20));
+        short.lock_table(1, "accounts", LockType::ExclusiveWrite).unwrap();
+        let started = Instant::now();
+        assert!(matches!(
+            short.lock_table(2, "accounts", LockType::SharedRead),
+            Err(TransactionError::LockTimeout { holder: 1, .. })
+        ));
+        assert!(started.elapsed() >= Duration::from_millis(20));
+    }
+    
+    #[test]
+    fn test_deadlock_detection() {
+        let locks = LockManager::with_timeout(Duration::from_secs(5));
+        locks.lock_row(1, "t", 1, LockType::ExclusiveWrite).unwrap();
+        locks.lock_row(2, "t", 2, LockType::ExclusiveWrite).unwrap();
+        
+        let first = {
+            let locks = locks.clone();
+            std::thread::spawn(move || locks.lock_row(1, "t", 2, LockType::ExclusiveWrite))
+        };
+        // 等事务 1 进入等待后，事务 2 的请求会形成环
+        while !locks.state().wait_for.contains_key(&1) {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert!(matches!(
+            locks.lock_row(2, "t", 1, LockType::ExclusiveWrite),
+            Err(TransactionError::DeadlockDetected { id: 2 })
+        ));
+        
+        locks.release_locks(2);
+        first.join().unwrap().unwrap();
+    }
+    
     #[test]
     fn test_isolation_levels() {
         let tm = TransactionManager::new();
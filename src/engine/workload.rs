@@ -0,0 +1,210 @@
+//! 查询负载统计
+//!
+//! `Database::execute` 执行的每条语句都按指纹累计调用次数、出错次数、影响行数和耗时，
+//! 涉及的表则按读写次数累计。统计只保存在内存中，通过系统视图
+//! `information_schema.query_stats` 和 `information_schema.table_activity` 用 SQL 查询。
+//!
+//! 指纹把字面量和参数占位符替换为 `?`、关键字统一为大写、空白和注释规范化，
+//! 因此只有常量不同的查询归为同一条。
+
+use crate::sql::lexer::{Lexer, Token, TokenCategory};
+use std::collections::HashMap;
+
+/// 最多保留的指纹数；超出时淘汰调用次数最少的一条
+pub(crate) const MAX_FINGERPRINTS: usize = 1000;
+
+/// 计算 SQL 语句的规范化指纹
+///
+/// ```
+/// use minidb::engine::workload::fingerprint;
+///
+/// assert_eq!(
+///     fingerprint("select name from users where id = 42"),
+///     fingerprint("SELECT name\n  FROM users WHERE id = 7;"),
+/// );
+/// assert_eq!(fingerprint("SELECT name FROM users WHERE id = 42"), "SELECT name FROM users WHERE id = ?");
+/// ```
+pub fn fingerprint(sql: &str) -> String {
+    let mut lexer = Lexer::new(sql);
+    let mut out = String::new();
+    let mut previous: Option<Token> = None;
+    loop {
+        let info = match lexer.next_token_info() {
+            Ok(info) => info,
+            // 无法分词的语句只规范空白
+            Err(_) => return sql.split_whitespace().collect::<Vec<_>>().join(" "),
+        };
+        let text = match &info.token {
+            Token::EOF => break,
+            Token::Semicolon => continue,
+            Token::Integer(_) | Token::Float(_) | Token::String(_) | Token::Boolean(_) | Token::Parameter(_) => {
+                "?".to_string()
+            }
+            _ if info.category == TokenCategory::Keyword => info.lexeme.to_uppercase(),
+            _ => info.lexeme.clone(),
+        };
+        let glued = matches!(info.token, Token::Comma | Token::RightParen | Token::Dot)
+            || matches!(previous, Some(Token::LeftParen | Token::Dot));
+        if !out.is_empty() && !glued {
+            out.push(' ');
+        }
+        out.push_str(&text);
+        previous = Some(info.token);
+    }
+    out
+}
+
+/// 一个查询指纹的累计统计
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct QueryStats {
+    pub calls: u64,
+    pub errors: u64,
+    /// 返回或修改的行数
+    pub rows: u64,
+    pub total_micros: u64,
+    pub max_micros: u64,
+}
+
+/// 一张表的累计访问统计
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct TableActivity {
+    /// SELECT 次数
+    pub reads: u64,
+    /// INSERT / UPDATE / DELETE 次数
+    pub writes: u64,
+    pub total_micros: u64,
+}
+
+/// 一条语句涉及的表及访问方式
+pub(crate) struct TableAccess {
+    pub table: String,
+    pub write: bool,
+}
+
+/// 数据库级负载统计
+#[derive(Debug, Default)]
+pub(crate) struct WorkloadTracker {
+    queries: HashMap<String, QueryStats>,
+    tables: HashMap<String, TableActivity>,
+}
+
+impl WorkloadTracker {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// 记录一次语句执行；rows 为 None 表示执行出错
+    pub(crate) fn record(&mut self, sql: &str, tables: &[TableAccess], micros: u64, rows: Option<u64>) {
+        let key = fingerprint(sql);
+        if !self.queries.contains_key(&key) && self.queries.len() >= MAX_FINGERPRINTS {
+            self.evict();
+        }
+        let stats = self.queries.entry(key).or_default();
+        stats.calls += 1;
+        stats.total_micros += micros;
+        stats.max_micros = stats.max_micros.max(micros);
+        match rows {
+            Some(rows) => stats.rows += rows,
+            None => stats.errors += 1,
+        }
+
+        for access in tables {
+            let activity = self.tables.entry(access.table.clone()).or_default();
+            if access.write {
+                activity.writes += 1;
+            } else {
+                activity.reads += 1;
+            }
+            activity.total_micros += micros;
+        }
+    }
+
+    /// 淘汰调用次数最少（相同时累计耗时最少）的指纹
+    fn evict(&mut self) {
+        let coldest = self.queries.iter()
+            .min_by_key(|(_, stats)| (stats.calls, stats.total_micros))
+            .map(|(key, _)| key.clone());
+        if let Some(key) = coldest {
+            self.queries.remove(&key);
+        }
+    }
+
+    /// 按累计耗时降序排列的指纹统计
+    pub(crate) fn queries(&self) -> Vec<(&String, &QueryStats)> {
+        let mut queries: Vec<_> = self.queries.iter().collect();
+        queries.sort_by(|a, b| b.1.total_micros.cmp(&a.1.total_micros).then_with(|| a.0.cmp(b.0)));
+        queries
+    }
+
+    /// 按访问次数降序排列的表统计
+    pub(crate) fn tables(&self) -> Vec<(&String, &TableActivity)> {
+        let mut tables: Vec<_> = self.tables.iter().collect();
+        tables.sort_by(|a, b| {
+            (b.1.reads + b.1.writes).cmp(&(a.1.reads + a.1.writes)).then_with(|| a.0.cmp(b.0))
+        });
+        tables
+    }
+
+    /// 清空全部统计
+    pub(crate) fn reset(&mut self) {
+        self.queries.clear();
+        self.tables.clear();
+    }
+}
+
+/// 计时器：wasm32 上 std::time::Instant 会 panic，改用 chrono（开启 wasmbind 后取 JavaScript 的 Date）
+#[cfg(not(all(target_arch = "wasm32", feature = "chrono")))]
+pub(crate) struct Stopwatch(std::time::Instant);
+
+#[cfg(not(all(target_arch = "wasm32", feature = "chrono")))]
+impl Stopwatch {
+    pub(crate) fn start() -> Self {
+        Self(std::time::Instant::now())
+    }
+
+    pub(crate) fn elapsed_micros(&self) -> u64 {
+        self.0.elapsed().as_micros() as u64
+    }
+}
+
+#[cfg(all(target_arch = "wasm32", feature = "chrono"))]
+pub(crate) struct Stopwatch(chrono::DateTime<chrono::Utc>);
+
+#[cfg(all(target_arch = "wasm32", feature = "chrono"))]
+impl Stopwatch {
+    pub(crate) fn start() -> Self {
+        Self(chrono::Utc::now())
+    }
+
+    pub(crate) fn elapsed_micros(&self) -> u64 {
+        (chrono::Utc::now() - self.0).num_microseconds().map_or(0, |micros| micros.max(0) as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint_normalizes_literals() {
+        assert_eq!(
+            fingerprint("insert into t (a, b) values (1, 'x');"),
+            "INSERT INTO t (a, b) VALUES (?, ?)"
+        );
+        assert_eq!(fingerprint("SELECT * FROM t WHERE a = $1 AND b = 2.5"), "SELECT * FROM t WHERE a = ? AND b = ?");
+        assert_eq!(fingerprint("SELECT   a\n-- note\nFROM t"), "SELECT a FROM t");
+    }
+
+    #[test]
+    fn test_tracker_evicts_coldest() {
+        let mut tracker = WorkloadTracker::new();
+        tracker.record("SELECT a FROM hot", &[], 10, Some(1));
+        tracker.record("SELECT a FROM hot", &[], 10, Some(1));
+        for i in 0..MAX_FINGERPRINTS {
+            tracker.record(&format!("SELECT c{} FROM t", i), &[], 1, None);
+        }
+        assert_eq!(tracker.queries.len(), MAX_FINGERPRINTS);
+        assert_eq!(tracker.queries["SELECT a FROM hot"].calls, 2);
+        assert_eq!(tracker.queries["SELECT a FROM hot"].rows, 2);
+    }
+}
@@ -10,7 +10,7 @@ pub mod types;
 pub mod utils;
 
 // 高级功能测试模块
-#[cfg(test)]
+#[cfg(all(test, feature = "persistence"))]
 mod advanced_features_test;
 
 // Re-export commonly used types
@@ -1,8 +1,9 @@
 use minidb::engine::database::QueryResult;
+use minidb::engine::{DatabaseConfig, RecoveryTarget, Support};
 use minidb::Database;
 use std::env;
-use std::io::{self, Write};
-use std::time::Instant;
+use std::io::{self, IsTerminal, Write};
+use std::time::{Instant, SystemTime};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     env_logger::init();
@@ -12,18 +13,71 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("输入 'help' 查看可用命令，输入 'quit' 退出。");
     println!();
 
-    // Get database path from command line args or use default
-    let db_path = env::args()
-        .nth(1)
-        .unwrap_or_else(|| "./minidb_data".to_string());
+    // 用法: minidb [数据目录] [--wal-archive 归档目录] [--restore 备份目录 [--until-lsn LSN | --until-time 'YYYY-MM-DD HH:MM:SS']]
+    let mut db_path = None;
+    let mut restore_from = None;
+    let mut config = DatabaseConfig::default();
+    let mut target = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--restore" => restore_from = Some(args.next().ok_or("--restore 需要备份目录参数")?),
+            "--wal-archive" => config = config.with_wal_archive_dir(args.next().ok_or("--wal-archive 需要归档目录参数")?),
+            "--until-lsn" => {
+                let lsn = args.next().ok_or("--until-lsn 需要 LSN 参数")?;
+                target = Some(RecoveryTarget::Lsn(lsn.parse().map_err(|_| format!("无效的 LSN: {}", lsn))?));
+            }
+            "--until-time" => {
+                let time = args.next().ok_or("--until-time 需要时间参数")?;
+                target = Some(RecoveryTarget::Time(parse_local_time(&time)?));
+            }
+            _ => db_path = Some(arg),
+        }
+    }
+    let db_path = db_path.unwrap_or_else(|| "./minidb_data".to_string());
+
+    match (restore_from, config.wal_archive_dir.is_some()) {
+        (Some(backup_dir), true) => {
+            println!("正在从备份 {} 和 WAL 归档恢复到 {}", backup_dir, db_path);
+            let info = Database::restore_to(&backup_dir, &db_path, target.unwrap_or(RecoveryTarget::Latest), &config)?;
+            println!("已恢复备份（LSN {}）并重放 {} 个归档批次，恢复到 LSN {}", info.backup_lsn, info.batches, info.recovered_lsn);
+        }
+        (Some(backup_dir), false) => {
+            if target.is_some() {
+                return Err("--until-lsn 和 --until-time 需要 --wal-archive".into());
+            }
+            println!("正在从备份 {} 恢复到 {}", backup_dir, db_path);
+            let manifest = Database::restore(&backup_dir, &db_path)?;
+            println!("已校验并恢复 {} 个文件（{} 字节，LSN {}）", manifest.files.len(), manifest.total_size(), manifest.lsn);
+        }
+        (None, _) if target.is_some() => return Err("--until-lsn 和 --until-time 只能与 --restore 一起使用".into()),
+        (None, _) => {}
+    }
 
     println!("正在打开数据库: {}", db_path);
-    let mut database = Database::new(&db_path)?;
+    let mut database = Database::with_config(&db_path, config)?;
+    // 安全模式只在交互使用时默认开启，通过管道执行的脚本不受影响
+    database.set_safe_updates(io::stdin().is_terminal());
     println!("数据库已成功加载！");
+    for (table, index) in database.index_builds() {
+        if let Some(build) = &index.build {
+            println!(
+                "⏸️ 表 '{}' 上索引 '{}' 的构建未完成（已处理 {}/{} 行），重新执行该 CREATE INDEX 即可继续",
+                table, index.name, build.rows_done, build.total_rows
+            );
+        }
+    }
     println!();
 
+    // 最近执行或编辑的语句，供 \e 和 \p 使用
+    let mut query_buffer = String::new();
+    // AUTOCOMMIT off 时，语句自动在显式事务中执行
+    let mut autocommit = true;
+    // 有未提交事务时，第一次退出只给出警告
+    let mut quit_requested = false;
+
     loop {
-        print!("minidb> ");
+        print!("{}", prompt(&database));
         io::stdout().flush()?;
 
         let mut input = String::new();
@@ -31,6 +85,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             Ok(0) => {
                 // EOF reached (e.g., from pipe input)
                 println!();
+                if database.in_transaction() {
+                    println!("⚠️ 未提交的事务已被丢弃");
+                }
                 break;
             }
             Ok(_) => {
@@ -48,8 +105,87 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             continue;
         }
 
+        let is_quit = matches!(input.to_lowercase().as_str(), "quit" | "exit" | "\\q");
+        if !is_quit {
+            quit_requested = false;
+        }
+
+        if let Some(arg) = strip_command(input, "\\set") {
+            set_variable(arg, &mut autocommit, &mut database);
+            continue;
+        }
+
+        if let Some(arg) = strip_command(input, "\\dump") {
+            dump_database(&database, arg);
+            continue;
+        }
+
+        if let Some(arg) = strip_command(input, "\\describe") {
+            describe_statement(&database, arg);
+            continue;
+        }
+
+        if let Some(arg) = strip_command(input, "\\di") {
+            show_indexes(&mut database, arg);
+            continue;
+        }
+
+        if let Some(arg) = strip_command(input, "\\diff") {
+            diff_results(&mut database, arg);
+            continue;
+        }
+
+        if let Some(arg) = strip_command(input, "\\save") {
+            save_snippet(&mut database, arg, &query_buffer);
+            continue;
+        }
+
+        if let Some(arg) = strip_command(input, "\\unsave") {
+            match database.remove_snippet(arg) {
+                Ok(true) => println!("已删除片段 {}", arg),
+                Ok(false) => println!("片段 {} 不存在", arg),
+                Err(e) => println!("❌ {}", e),
+            }
+            continue;
+        }
+
+        if let Some(arg) = strip_command(input, "\\run") {
+            if arg.is_empty() {
+                list_snippets(&database);
+            } else if let Some(sql) = database.snippet(arg) {
+                query_buffer = sql.to_string();
+                println!("{}", query_buffer);
+                run_input(&mut database, &query_buffer, &mut autocommit);
+            } else {
+                println!("片段 {} 不存在，使用 \\run 查看已保存的片段", arg);
+            }
+            continue;
+        }
+
+        if let Some(arg) = strip_command(input, "\\e") {
+            let initial = if arg.is_empty() { query_buffer.clone() } else { arg.to_string() };
+            match edit_in_editor(&initial) {
+                Ok(edited) if edited.trim().is_empty() => println!("编辑内容为空，未执行"),
+                Ok(edited) => {
+                    query_buffer = edited.trim().to_string();
+                    run_input(&mut database, &query_buffer, &mut autocommit);
+                }
+                Err(e) => eprintln!("无法打开编辑器: {}", e),
+            }
+            continue;
+        }
+
         match input.to_lowercase().as_str() {
             "quit" | "exit" | "\\q" => {
+                if database.in_transaction() && !quit_requested {
+                    println!("⚠️ 当前事务尚未提交，退出将丢弃其中的更改。");
+                    println!("   先执行 COMMIT，或再次输入 \\q 确认退出。");
+                    quit_requested = true;
+                    continue;
+                }
+                if let Err(e) = database.close() {
+                    eprintln!("❌ 关闭数据库失败: {}", e);
+                }
                 println!("再见！感谢使用 MiniDB!");
                 break;
             }
@@ -68,6 +204,16 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             "\\t" => {
                 run_quick_test(&mut database)?;
             }
+            "\\p" => {
+                if query_buffer.is_empty() {
+                    println!("查询缓冲区为空");
+                } else {
+                    println!("{}", query_buffer);
+                }
+            }
+            "\\capabilities" => {
+                show_capabilities(&database);
+            }
             "\\version" | "version" => {
                 show_version_info();
             }
@@ -77,23 +223,304 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 io::stdout().flush()?;
             }
             _ => {
-                let start = Instant::now();
-                match execute_sql(&mut database, input) {
-                    Ok(result) => {
-                        let duration = start.elapsed();
-                        print_detailed_result(&result, duration);
-                    }
-                    Err(e) => {
-                        let duration = start.elapsed();
-                        print_error(&e, duration);
+                query_buffer = input.to_string();
+                run_input(&mut database, input, &mut autocommit);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// 提示符：处于事务中时显示为 `minidb*>`
+fn prompt(database: &Database) -> &'static str {
+    if database.in_transaction() {
+        "minidb*> "
+    } else {
+        "minidb> "
+    }
+}
+
+/// 处理 `\set [NAME VALUE]`
+fn set_variable(arg: &str, autocommit: &mut bool, database: &mut Database) {
+    let mut parts = arg.split_whitespace();
+    let (name, value) = match (parts.next(), parts.next()) {
+        (None, _) => {
+            println!("AUTOCOMMIT = {}", if *autocommit { "on" } else { "off" });
+            print_safe_updates(database);
+            print_truncate_strings(database);
+            print_determinism(database);
+            return;
+        }
+        (Some(name), Some(value)) => (name, value),
+        (Some(name), None) => {
+            println!("用法: \\set {} <值>", name);
+            return;
+        }
+    };
+
+    match name.to_uppercase().as_str() {
+        "AUTOCOMMIT" => match parse_switch(value) {
+            Some(enabled) => {
+                *autocommit = enabled;
+                println!("AUTOCOMMIT = {}", if *autocommit { "on" } else { "off" });
+            }
+            None => println!("❌ AUTOCOMMIT 只能为 on 或 off"),
+        },
+        "SAFE_UPDATES" => match parse_switch(value) {
+            Some(enabled) => {
+                database.set_safe_updates(enabled);
+                print_safe_updates(database);
+            }
+            None => println!("❌ SAFE_UPDATES 只能为 on 或 off"),
+        },
+        "TRUNCATE_STRINGS" => match parse_switch(value) {
+            Some(enabled) => {
+                database.set_truncate_strings(enabled);
+                print_truncate_strings(database);
+            }
+            None => println!("❌ TRUNCATE_STRINGS 只能为 on 或 off"),
+        },
+        "DETERMINISTIC" => match parse_switch(value) {
+            Some(enabled) => {
+                database.set_deterministic_sort(enabled);
+                database.set_deterministic_grouping(enabled);
+                print_determinism(database);
+            }
+            None => println!("❌ DETERMINISTIC 只能为 on 或 off"),
+        },
+        "SEED" => {
+            let seed = if value.eq_ignore_ascii_case("off") {
+                None
+            } else {
+                match value.parse::<u64>() {
+                    Ok(seed) => Some(seed),
+                    Err(_) => {
+                        println!("❌ SEED 只能为非负整数或 off");
+                        return;
                     }
                 }
-                println!(); // Add spacing after each command
+            };
+            database.set_random_seed(seed);
+            print_determinism(database);
+        }
+        _ => println!("❌ 未知变量: {}", name),
+    }
+}
+
+fn parse_switch(value: &str) -> Option<bool> {
+    match value.to_lowercase().as_str() {
+        "on" | "true" | "1" => Some(true),
+        "off" | "false" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+fn print_safe_updates(database: &Database) {
+    println!("SAFE_UPDATES = {}", if database.config().safe_updates { "on" } else { "off" });
+}
+
+fn print_truncate_strings(database: &Database) {
+    println!("TRUNCATE_STRINGS = {}", if database.config().truncate_strings { "on" } else { "off" });
+}
+
+fn print_determinism(database: &Database) {
+    let config = database.config();
+    let deterministic = config.deterministic_sort && config.deterministic_grouping;
+    println!("DETERMINISTIC = {}", if deterministic { "on" } else { "off" });
+    match config.random_seed {
+        Some(seed) => println!("SEED = {}", seed),
+        None => println!("SEED = off"),
+    }
+}
+
+/// AUTOCOMMIT off 时，在执行语句前开启事务（事务控制语句本身除外）
+fn begin_implicit_transaction(database: &mut Database, sql: &str, autocommit: &mut bool) {
+    let first_word = sql.split_whitespace().next().unwrap_or("").to_uppercase();
+    let is_transaction_control = matches!(first_word.as_str(), "BEGIN" | "START" | "COMMIT" | "ROLLBACK" | "END");
+    if *autocommit || is_transaction_control || database.in_transaction() {
+        return;
+    }
+
+    if let Err(e) = database.execute("BEGIN") {
+        println!("❌ 无法开启事务，AUTOCOMMIT 已恢复为 on: {}", e);
+        *autocommit = true;
+    }
+}
+
+/// 逐条执行输入中以分号分隔的语句，某条出错后继续执行其余语句
+fn run_input(database: &mut Database, input: &str, autocommit: &mut bool) {
+    for sql in minidb::sql::split_statements(input) {
+        begin_implicit_transaction(database, &sql, autocommit);
+        run_statement(database, &sql);
+    }
+}
+
+/// 执行一条 SQL 并打印结果或错误
+fn run_statement(database: &mut Database, sql: &str) {
+    let start = Instant::now();
+    match execute_sql(database, sql) {
+        Ok(result) => {
+            let duration = start.elapsed();
+            print_detailed_result(&result, duration);
+        }
+        Err(e) => {
+            let duration = start.elapsed();
+            print_error(&e, duration);
+        }
+    }
+    println!(); // Add spacing after each command
+}
+
+/// 列出引擎的能力清单
+fn show_capabilities(database: &Database) {
+    for info in database.capabilities() {
+        let mark = match info.support {
+            Support::Full => "✅",
+            Support::Partial => "⚠️",
+            Support::Unsupported => "❌",
+        };
+        if info.note.is_empty() {
+            println!("{} {}", mark, info.capability);
+        } else {
+            println!("{} {:<26} {}", mark, info.capability, info.note);
+        }
+    }
+}
+
+/// 处理 `\dump [文件]`：把全部表导出为 SQL，不给文件时输出到屏幕
+fn dump_database(database: &Database, path: &str) {
+    if path.is_empty() {
+        if let Err(e) = database.dump_sql(io::stdout().lock()) {
+            println!("❌ {}", e);
+        }
+        return;
+    }
+
+    let result = std::fs::File::create(path)
+        .map_err(|e| e.to_string())
+        .and_then(|file| database.dump_sql(io::BufWriter::new(file)).map_err(|e| e.to_string()));
+    match result {
+        Ok(()) => println!("已把 {} 张表导出到 {}", database.list_tables().len(), path),
+        Err(e) => println!("❌ 导出失败: {}", e),
+    }
+}
+
+/// 打印语句的参数类型和结果列而不执行
+fn describe_statement(database: &Database, sql: &str) {
+    if sql.is_empty() {
+        println!("用法: \\describe <SQL>");
+        return;
+    }
+
+    match database.describe(sql) {
+        Ok(description) => {
+            if description.parameters.is_empty() {
+                println!("参数: 无");
+            }
+            for (i, parameter) in description.parameters.iter().enumerate() {
+                match parameter {
+                    Some(data_type) => println!("参数 ${}: {}", i + 1, format_data_type(data_type)),
+                    None => println!("参数 ${}: 未知", i + 1),
+                }
+            }
+
+            if !description.returns_rows() {
+                println!("结果: 不产生结果集");
+            }
+            for column in description.column_metadata() {
+                println!(
+                    "列 {}: {} (JDBC {}, {})",
+                    column.name,
+                    column.type_name,
+                    column.jdbc_type,
+                    if column.nullable { "可为 NULL" } else { "NOT NULL" },
+                );
             }
         }
+        Err(e) => println!("❌ {}", e),
     }
+}
 
-    Ok(())
+/// \diff A ; B：比对两张表或两条查询的结果
+fn diff_results(database: &mut Database, arg: &str) {
+    let sides = minidb::sql::split_statements(arg);
+    let [left, right] = sides.as_slice() else {
+        println!("用法: \\diff <表名或 SELECT> ; <表名或 SELECT>");
+        return;
+    };
+
+    let start = Instant::now();
+    match database.diff(left, right) {
+        Ok(result) => print_detailed_result(&result, start.elapsed()),
+        Err(e) => print_error(&e.into(), start.elapsed()),
+    }
+    println!();
+}
+
+/// \save 名称 [SQL]：保存查询片段；省略 SQL 时保存查询缓冲区中的上一条输入
+fn save_snippet(database: &mut Database, arg: &str, query_buffer: &str) {
+    let (name, sql) = match arg.split_once(char::is_whitespace) {
+        Some((name, sql)) => (name, sql.trim()),
+        None => (arg, query_buffer),
+    };
+    if name.is_empty() || sql.is_empty() {
+        println!("用法: \\save <名称> [SQL]（省略 SQL 时保存上一条语句）");
+        return;
+    }
+    match database.save_snippet(name, sql) {
+        Ok(()) => println!("已保存片段 {}，使用 \\run {} 执行", name, name),
+        Err(e) => println!("❌ {}", e),
+    }
+}
+
+/// 列出已保存的查询片段
+fn list_snippets(database: &Database) {
+    let mut empty = true;
+    for (name, sql) in database.snippets() {
+        empty = false;
+        println!("{:<20} {}", name, sql.split_whitespace().collect::<Vec<_>>().join(" "));
+    }
+    if empty {
+        println!("暂无已保存的片段，使用 \\save <名称> <SQL> 保存");
+    }
+}
+
+/// 匹配带可选参数的元命令，返回去掉首尾空白的参数
+fn strip_command<'a>(input: &'a str, command: &str) -> Option<&'a str> {
+    let rest = input.strip_prefix(command)?;
+    if rest.is_empty() || rest.starts_with(char::is_whitespace) {
+        Some(rest.trim())
+    } else {
+        None
+    }
+}
+
+/// 在 $VISUAL / $EDITOR 中编辑文本，返回保存后的内容
+fn edit_in_editor(initial: &str) -> io::Result<String> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| if cfg!(windows) { "notepad".to_string() } else { "vi".to_string() });
+
+    // 编辑器命令可以带参数，例如 "code --wait"
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().ok_or_else(|| io::Error::other("编辑器命令为空"))?;
+
+    let path = env::temp_dir().join(format!("minidb_edit_{}.sql", std::process::id()));
+    std::fs::write(&path, format!("{}\n", initial))?;
+
+    let status = std::process::Command::new(program)
+        .args(parts)
+        .arg(&path)
+        .status();
+    let contents = std::fs::read_to_string(&path);
+    let _ = std::fs::remove_file(&path);
+
+    let status = status?;
+    if !status.success() {
+        return Err(io::Error::other(format!("编辑器 '{}' 异常退出: {}", editor, status)));
+    }
+    contents
 }
 
 fn show_help() {
@@ -103,9 +530,20 @@ fn show_help() {
     println!("  help, \\h          显示此帮助信息");
     println!("  quit, exit, \\q    退出程序");
     println!("  \\d                列出所有表");
+    println!("  \\di [表名]        列出索引（键列、唯一性、大小、索引项数）");
     println!("  \\s                显示系统状态");
     println!("  \\i                显示内部信息");
     println!("  \\t                运行快速测试");
+    println!("  \\e [SQL]           在 $EDITOR 中编辑上一条（或给定的）语句并执行");
+    println!("  \\p                显示查询缓冲区");
+    println!("  \\diff A ; B         比对两张表或两条查询（-/+ 只在一侧，</> 主键相同但内容不同）");
+    println!("  \\save 名称 [SQL]    保存查询片段（省略 SQL 时保存上一条语句）");
+    println!("  \\run [名称]         执行已保存的片段（不带名称时列出全部）");
+    println!("  \\unsave 名称       删除查询片段");
+    println!("  \\describe SQL      显示语句的参数类型和结果列（不执行）");
+    println!("  \\dump [文件]        把全部表导出为 SQL（CREATE TABLE / INSERT / CREATE INDEX）");
+    println!("  \\set [名称 值]      查看或设置变量（AUTOCOMMIT、SAFE_UPDATES、TRUNCATE_STRINGS、DETERMINISTIC、SEED）");
+    println!("  \\capabilities     列出支持和不支持的 SQL 功能");
     println!("  \\version          显示版本信息");
     println!("  clear, \\c         清空屏幕");
     println!();
@@ -113,21 +551,50 @@ fn show_help() {
     println!("  CREATE TABLE name (column_definitions...)");
     println!("  INSERT INTO name VALUES (...)");
     println!("  SELECT columns FROM name [WHERE condition]");
-    println!("  UPDATE name SET column=value [WHERE condition]");
-    println!("  DELETE FROM name [WHERE condition]");
+    println!("  UPDATE [ALL] name SET column=value [WHERE condition]");
+    println!("  DELETE [ALL] FROM name [WHERE condition]");
+    println!("  （SAFE_UPDATES on 时，不带 WHERE 的 UPDATE/DELETE 需写出 ALL）");
+    println!("  DRY RUN UPDATE|DELETE ...   预览将受影响的行，不做修改");
+    println!("  EXPLAIN [ANALYZE] stmt      显示执行计划；ANALYZE 实际执行并报告各算子行数和耗时");
     println!("  DROP TABLE name");
     println!();
     println!("高级 SQL 功能:");
-    println!("  SELECT ... ORDER BY column [ASC|DESC]     - 排序查询");
+    println!("  SELECT ... ORDER BY col|alias|n [ASC|DESC] [NULLS FIRST|LAST] - 排序查询");
     println!("  SELECT ... LIMIT number [OFFSET number]   - 分页查询");
-    println!("  SELECT ... GROUP BY column                - 分组查询");
+    println!("  SELECT ... GROUP BY column|expr|n         - 分组查询 (n 为 SELECT 列表序号)");
     println!("  SELECT COUNT(*), SUM(col), AVG(col)...    - 聚合函数");
-    println!("  SELECT ... WHERE col IN (values)          - 条件查询");
+    println!("  SELECT ... WHERE col [NOT] IN (values)    - 条件查询");
+    println!("  SELECT ... WHERE col [NOT] BETWEEN a AND b - 范围查询");
+    println!("  SELECT rowid, ... / WHERE rowid = n       - 行标识伪列");
+    println!("  stmt1; stmt2; ...                         - 一行输入多条语句，依次执行");
+    println!("  DEDUPLICATE TABLE name                    - 删除完全重复的行");
+    println!("  COPY t FROM 'f.csv' [(HEADER, DELIMITER ';', FORMAT CSV|NDJSON, INFER)] - 从文件导入 (INFER 推断建表)");
+    println!("  COPY (SELECT ...) TO 'f.csv' [(HEADER, DELIMITER ';', QUOTE MINIMAL|ALL|NONNUMERIC)] - 把查询结果导出为 CSV");
+    println!("  SELECT * FROM information_schema.column_stats - 各列统计 (NULL 数/NDV/最值)");
+    println!("  SELECT * FROM information_schema.query_stats  - 查询指纹的调用次数和耗时");
+    println!("  SELECT * FROM information_schema.table_activity - 各表读写次数");
+    println!("  SELECT * FROM information_schema.table_versions - 各表数据版本 (DML 修改后递增)");
+    println!("  SELECT * FROM information_schema.rewrite_rules  - 已注册的查询改写规则");
+    println!("  SELECT * FROM information_schema.index_builds   - 未完成的索引构建及进度");
+    println!("  SELECT * FROM information_schema.statistics     - ANALYZE 收集的统计 (NDV/最值/直方图)");
+    println!("  ANALYZE [table]                           - 收集表统计并随快照保存");
+    println!("  ALTER TABLE name ADD [CONSTRAINT c] PRIMARY KEY|UNIQUE (...) - 为已有表添加约束");
+    println!("  BEGIN / COMMIT / ROLLBACK                - 显式事务 (事务中不能执行 DDL)");
+    println!("  CREATE TEMP VIEW v AS SELECT ... / DROP VIEW [IF EXISTS] v - 会话级临时视图");
+    println!("  SET CONSTRAINTS ALL DEFERRED|IMMEDIATE    - 主键/UNIQUE 推迟到提交时检查");
+    println!("  SET buffer_policy|buffer_pool_size = 值    - 切换缓冲池替换策略 (lru/clock/lfu/arc) 或页数");
+    println!("  SET result_cache_entries = n              - 缓存 n 条只读查询的结果 (0 关闭)");
+    println!("  SET sort_work_mem_bytes = n               - ORDER BY 超过 n 字节时溢出到临时文件归并排序");
+    println!("  CHECKPOINT                                - 写入全部表快照并截断 WAL");
+    println!("  BACKUP TO 'dir'                           - 把数据目录的一致副本写入新目录 (无需关闭数据库)");
+    println!("  SELECT ... WHERE col [NOT] LIKE 'a%'      - 模式匹配 (%, _, ESCAPE)");
     println!();
     println!("🗂️ 索引系统 (NEW!):");
     println!("  CREATE INDEX idx_name ON table (column)           - 创建索引");
     println!("  CREATE UNIQUE INDEX idx_name ON table (column)    - 创建唯一索引");
     println!("  DROP INDEX idx_name                               - 删除索引");
+    println!("  REINDEX INDEX idx_name ON table | REINDEX TABLE t - 由表的行重建索引");
+    println!("  ADVISE INDEXES                                    - 根据查询负载建议创建索引");
     println!("  自动主键索引和查询优化                              - 自动功能");
     println!();
     println!("支持的聚合函数:");
@@ -137,6 +604,41 @@ fn show_help() {
     println!("  AVG(column)      平均值");
     println!("  MAX(column)      最大值");
     println!("  MIN(column)      最小值");
+    println!("  STRING_AGG(column, '分隔符')      按分隔符拼接非空值");
+    println!("  GROUP_CONCAT(column [, '分隔符'])  同上，分隔符默认为逗号");
+    println!("  STDDEV / VARIANCE(column)         样本标准差 / 方差（_POP 后缀为总体）");
+    println!();
+    println!("数学函数:");
+    println!("  ABS, ROUND(x [, n]), CEIL, FLOOR      结果与参数同类型");
+    println!("  MOD(a, b), POWER(x, y), SQRT(x)       取余 / 幂 / 平方根");
+    println!("  RANDOM()                               [0, 1) 随机数（\\set SEED n 可复现）");
+    println!();
+    println!("字符串函数:");
+    println!("  UPPER(s), LOWER(s)                     转为大写 / 小写");
+    println!("  LENGTH(s)                              字符数（BLOB 为字节数）");
+    println!();
+    println!("UUID 函数:");
+    println!("  GEN_UUID(), UUID()                     随机的第 4 版 UUID，可用作 DEFAULT 逐行生成");
+    println!();
+    println!("JSON 取值:");
+    println!("  doc -> 'key', doc -> 0                 按键 / 下标取出子文档（JSON）");
+    println!("  doc ->> 'key'                          取出文本，JSON null 为 NULL");
+    println!("  JSON_EXTRACT(doc, '$.a.b[0]')          按路径取值，不存在时为 NULL");
+    println!();
+    println!("类型转换:");
+    println!("  CAST(expr AS type)                     如 CAST('2024-01-31' AS DATE)、CAST(2.5 AS INT)");
+    println!("  DATE '2024-05-01', TIMESTAMP '...'     日期 / 时间戳常量，字符串写入日期列时也会自动转换");
+    println!();
+    println!("NULL 处理函数:");
+    println!("  COALESCE(a, b, ...)                    第一个非 NULL 参数");
+    println!("  NULLIF(a, b)                           a = b 时为 NULL，否则为 a");
+    println!();
+    println!("日期/时间函数:");
+    println!("  NOW(), CURRENT_DATE                    当前时间戳 / 日期");
+    println!("  EXTRACT(YEAR|MONTH|DAY|... FROM expr)  提取日期字段");
+    println!("  DATE_ADD(d, n [, 'unit'])              加上 n 个单位（默认 DAY）");
+    println!("  DATE_SUB(d, n [, 'unit'])              减去 n 个单位");
+    println!("  d + n, d - n, d1 - d2                  按天加减 / 相差天数");
     println!();
     println!("基础示例:");
     println!("  CREATE TABLE users (id INT, name VARCHAR(50), age INT);");
@@ -186,9 +688,32 @@ fn show_tables(database: &Database) -> Result<(), Box<dyn std::error::Error>> {
         }
     }
     
+    let views = database.list_views();
+    if !views.is_empty() {
+        println!("👁️ 临时视图（仅本次会话）:");
+        for view_name in &views {
+            let columns = database.get_view_schema(view_name)
+                .map(|schema| schema.columns.iter()
+                    .map(|column| format!("{} {}", column.name, format_data_type(&column.data_type)))
+                    .collect::<Vec<_>>()
+                    .join(", "))
+                .unwrap_or_default();
+            println!("   {} ({})", view_name, columns);
+        }
+    }
+    
     Ok(())
 }
 
+/// 处理 `\di [表名]`：执行 SHOW INDEXES
+fn show_indexes(database: &mut Database, table_name: &str) {
+    if table_name.is_empty() {
+        run_statement(database, "SHOW INDEXES");
+    } else {
+        run_statement(database, &format!("SHOW INDEXES FROM {}", table_name));
+    }
+}
+
 fn show_status(database: &Database) -> Result<(), Box<dyn std::error::Error>> {
     println!("=== 系统状态 ===");
     println!("🔧 数据库引擎: MiniDB v{}", minidb::VERSION);
@@ -437,7 +962,13 @@ fn format_data_type(data_type: &minidb::types::DataType) -> String {
         minidb::types::DataType::BigInt => "BIGINT".to_string(),
         minidb::types::DataType::Float => "FLOAT".to_string(),
         minidb::types::DataType::Double => "DOUBLE".to_string(),
+        minidb::types::DataType::Decimal(precision, scale) => format!("DECIMAL({},{})", precision, scale),
         minidb::types::DataType::Varchar(size) => format!("VARCHAR({})", size),
+        minidb::types::DataType::Char(size) => format!("CHAR({})", size),
+        minidb::types::DataType::Text => "TEXT".to_string(),
+        minidb::types::DataType::Blob => "BLOB".to_string(),
+        minidb::types::DataType::Uuid => "UUID".to_string(),
+        minidb::types::DataType::Json => "JSON".to_string(),
         minidb::types::DataType::Boolean => "BOOLEAN".to_string(),
         minidb::types::DataType::Date => "DATE".to_string(),
         minidb::types::DataType::Timestamp => "TIMESTAMP".to_string(),
@@ -451,9 +982,45 @@ fn format_value(value: &minidb::Value) -> String {
         minidb::Value::BigInt(i) => i.to_string(),
         minidb::Value::Float(f) => format!("{:.2}", f),
         minidb::Value::Double(f) => format!("{:.2}", f),
-        minidb::Value::Varchar(s) => s.clone(),
+        // 定点小数按自身的小数位数完整显示
+        minidb::Value::Decimal(d) => d.to_string(),
+        minidb::Value::Varchar(s) => truncate_cell(s),
+        minidb::Value::Blob(b) => format_blob(b),
+        minidb::Value::Uuid(u) => u.to_string(),
+        minidb::Value::Json(s) => truncate_cell(s),
         minidb::Value::Boolean(b) => b.to_string(),
         minidb::Value::Date(d) => d.to_string(),
         minidb::Value::Timestamp(ts) => ts.to_string(),
     }
-}
\ No newline at end of file
+}
+
+/// 字符串和二进制值在结果表格中最多显示的字符数，长 TEXT / BLOB 不会撑乱表格
+const MAX_CELL_CHARS: usize = 40;
+
+/// 超出 MAX_CELL_CHARS 的字符串截断并以省略号结尾
+fn truncate_cell(text: &str) -> String {
+    if text.chars().count() <= MAX_CELL_CHARS {
+        return text.to_string();
+    }
+    let mut shown: String = text.chars().take(MAX_CELL_CHARS - 1).collect();
+    shown.push('…');
+    shown
+}
+
+/// BLOB 显示为十六进制字面量；过长时只显示开头的字节并注明总长度
+fn format_blob(bytes: &[u8]) -> String {
+    let literal = format!("X'{}'", minidb::utils::encoding::encode_hex(bytes));
+    if literal.len() <= MAX_CELL_CHARS {
+        return literal;
+    }
+    format!("X'{}…' ({} 字节)", minidb::utils::encoding::encode_hex(&bytes[..12]), bytes.len())
+}
+
+/// 把本地时间 'YYYY-MM-DD HH:MM:SS' 解析为时间点
+fn parse_local_time(text: &str) -> Result<SystemTime, String> {
+    let time = chrono::NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S")
+        .map_err(|e| format!("无效的时间 '{}'（格式为 YYYY-MM-DD HH:MM:SS）: {}", text, e))?;
+    let time = time.and_local_timezone(chrono::Local).earliest()
+        .ok_or_else(|| format!("本地时间 '{}' 不存在", text))?;
+    Ok(time.into())
+}
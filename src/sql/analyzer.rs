@@ -6,8 +6,8 @@
 //! - 约束验证
 //! - 模式验证
 
-use crate::sql::parser::{BinaryOperator, Expression, Statement, UnaryOperator};
-use crate::types::{ColumnDefinition, DataType, Schema, Value};
+use crate::sql::parser::{BinaryOperator, Expression, ReindexTarget, Statement, UnaryOperator};
+use crate::types::{decimal, ColumnDefinition, DataType, Decimal, Schema, Value};
 use std::collections::HashMap;
 use thiserror::Error;
 
@@ -20,6 +20,16 @@ pub struct AnalyzedStatement {
     pub table_schemas: HashMap<String, Schema>,
     /// 已解析的表达式类型
     pub expression_types: HashMap<String, DataType>,
+    /// 推断的结果模式（SELECT 或带 RETURNING 的 DML），其余语句为 None
+    pub result_schema: Option<Schema>,
+}
+
+impl AnalyzedStatement {
+    /// 参数占位符 `index`（从 1 开始）的推断类型，无法从上下文推断时为 None
+    pub fn parameter_type(&self, index: usize) -> Option<&DataType> {
+        self.expression_types
+            .get(&format!("{:?}", Expression::Parameter(index)))
+    }
 }
 
 /// 模式查找的目录接口
@@ -131,6 +141,12 @@ pub enum SemanticError {
         column: String,
         position: Option<(u32, u32)>,
     },
+
+    #[error("IN 子查询必须只返回一列, 实际返回 {actual} 列")]
+    SubqueryColumnCount {
+        actual: usize,
+        position: Option<(u32, u32)>,
+    },
 }
 
 impl SemanticError {
@@ -292,6 +308,11 @@ impl SemanticError {
             SemanticError::NullConstraintViolation { column, position } => {
                 (3, *position, format!("Column '{}' cannot be null", column))
             }
+            SemanticError::SubqueryColumnCount { actual, position } => (
+                3,
+                *position,
+                format!("IN subquery must return exactly one column, got {}", actual),
+            ),
         };
 
         let pos_str = if let Some((line, col)) = position {
@@ -313,6 +334,7 @@ impl<'a> SemanticAnalyzer<'a> {
     pub fn analyze(&self, stmt: Statement) -> Result<AnalyzedStatement, SemanticError> {
         let mut table_schemas = HashMap::new();
         let mut expression_types = HashMap::new();
+        let mut result_schema = None;
 
         match &stmt {
             Statement::CreateTable {
@@ -338,11 +360,39 @@ impl<'a> SemanticAnalyzer<'a> {
                     &mut table_schemas,
                     &mut expression_types,
                 )?;
+
+                let mut tables = Vec::new();
+                if let Some(from) = from_clause {
+                    Self::collect_from_tables(from, &mut tables);
+                }
+                result_schema = Some(self.infer_result_schema(
+                    select_list,
+                    &tables,
+                    &table_schemas,
+                    &mut expression_types,
+                )?);
+
+                // 子查询中的表也要规划，记录它们的模式（不属于外层的 SELECT 列表）
+                let mut subquery_tables = Vec::new();
+                where_clause.clone().into_iter().for_each(|mut where_expr| where_expr.visit_mut(&mut |expr| {
+                    if let Expression::InSubquery { subquery, .. } | Expression::Exists(subquery) = expr {
+                        if let Statement::Select { from_clause: Some(from), .. } = subquery.as_ref() {
+                            Self::collect_from_tables(from, &mut subquery_tables);
+                        }
+                    }
+                }));
+                for table in subquery_tables {
+                    if let Some(schema) = self.catalog.get_table_schema(&table) {
+                        table_schemas.entry(table).or_insert(schema);
+                    }
+                }
             }
             Statement::Insert {
                 table_name,
                 columns,
                 values,
+                returning,
+                ..
             } => {
                 self.analyze_insert(
                     table_name,
@@ -351,11 +401,19 @@ impl<'a> SemanticAnalyzer<'a> {
                     &mut table_schemas,
                     &mut expression_types,
                 )?;
+                result_schema = self.infer_returning_schema(
+                    returning,
+                    table_name,
+                    &table_schemas,
+                    &mut expression_types,
+                )?;
             }
             Statement::Update {
                 table_name,
                 assignments,
                 where_clause,
+                returning,
+                ..
             } => {
                 self.analyze_update(
                     table_name,
@@ -364,10 +422,18 @@ impl<'a> SemanticAnalyzer<'a> {
                     &mut table_schemas,
                     &mut expression_types,
                 )?;
+                result_schema = self.infer_returning_schema(
+                    returning,
+                    table_name,
+                    &table_schemas,
+                    &mut expression_types,
+                )?;
             }
             Statement::Delete {
                 table_name,
                 where_clause,
+                returning,
+                ..
             } => {
                 self.analyze_delete(
                     table_name,
@@ -375,6 +441,12 @@ impl<'a> SemanticAnalyzer<'a> {
                     &mut table_schemas,
                     &mut expression_types,
                 )?;
+                result_schema = self.infer_returning_schema(
+                    returning,
+                    table_name,
+                    &table_schemas,
+                    &mut expression_types,
+                )?;
             }
             Statement::CreateIndex { table_name, .. } => {
                 // 验证表是否存在
@@ -388,8 +460,60 @@ impl<'a> SemanticAnalyzer<'a> {
             Statement::DropIndex { .. } => {
                 // 索引删除的语义分析（暂时简单处理）
             }
-            Statement::Explain { .. } => {
-                // EXPLAIN语句不需要特殊的语义分析
+            Statement::ShowIndexes { table_name: None } | Statement::Analyze { table_name: None } => {}
+            Statement::Reindex { target: ReindexTarget::Index { table_name, .. } | ReindexTarget::Table(table_name) }
+            | Statement::ShowIndexes { table_name: Some(table_name) }
+            | Statement::Analyze { table_name: Some(table_name) } => {
+                if !self.catalog.table_exists(table_name) {
+                    return Err(SemanticError::TableNotFound {
+                        table: table_name.clone(),
+                        position: None,
+                    });
+                }
+            }
+            Statement::Explain { .. }
+            | Statement::AdviseIndexes
+            | Statement::Checkpoint
+            | Statement::Backup { .. }
+            | Statement::SetConstraints { .. }
+            | Statement::SetVariable { .. }
+            | Statement::Begin
+            | Statement::Commit
+            | Statement::Rollback => {
+                // EXPLAIN / ADVISE INDEXES / CHECKPOINT / BACKUP / SET / 事务控制语句不需要特殊的语义分析
+            }
+            Statement::CreateView { query, .. }
+            | Statement::Assert { query, .. }
+            | Statement::CopyTo { query, .. } => {
+                // 视图、断言和导出的查询必须是合法的 SELECT
+                self.analyze(query.as_ref().clone())?;
+            }
+            Statement::DropView { .. } => {
+                // 视图是否存在由执行器检查（IF EXISTS）
+            }
+            Statement::DryRun { statement } => {
+                // 与被修饰的语句相同的检查；结果是目标表中将受影响的行
+                let inner = self.analyze(statement.as_ref().clone())?;
+                result_schema = inner.table_schemas.values().next().cloned();
+                table_schemas = inner.table_schemas;
+                expression_types = inner.expression_types;
+            }
+            Statement::Copy { table_name, options, .. } => {
+                // 开启 INFER 时目标表可以不存在，执行时按文件内容建表
+                if !options.infer && !self.catalog.table_exists(table_name) {
+                    return Err(SemanticError::TableNotFound {
+                        table: table_name.clone(),
+                        position: None,
+                    });
+                }
+            }
+            Statement::Deduplicate { table_name } | Statement::AlterTable { table_name, .. } => {
+                if !self.catalog.table_exists(table_name) {
+                    return Err(SemanticError::TableNotFound {
+                        table: table_name.clone(),
+                        position: None,
+                    });
+                }
             }
         }
 
@@ -397,9 +521,102 @@ impl<'a> SemanticAnalyzer<'a> {
             statement: stmt,
             table_schemas,
             expression_types,
+            result_schema,
         })
     }
 
+    /// 按出现顺序收集 FROM 子句中的表名
+    pub(crate) fn collect_from_tables(from_clause: &crate::sql::parser::FromClause, tables: &mut Vec<String>) {
+        match from_clause {
+            crate::sql::parser::FromClause::Table(table_name) => tables.push(table_name.clone()),
+            crate::sql::parser::FromClause::Join { left, right, .. } => {
+                Self::collect_from_tables(left, tables);
+                Self::collect_from_tables(right, tables);
+            }
+        }
+    }
+
+    /// 推断 SELECT 列表的结果模式
+    fn infer_result_schema(
+        &self,
+        select_list: &crate::sql::parser::SelectList,
+        tables: &[String],
+        table_schemas: &HashMap<String, Schema>,
+        expression_types: &mut HashMap<String, DataType>,
+    ) -> Result<Schema, SemanticError> {
+        use crate::sql::parser::SelectList;
+
+        let select_exprs = match select_list {
+            SelectList::Wildcard => {
+                let columns = tables
+                    .iter()
+                    .filter_map(|table| table_schemas.get(table))
+                    .flat_map(|schema| schema.columns.iter().cloned())
+                    .collect();
+                return Ok(Schema {
+                    columns,
+                    primary_key: None,
+                });
+            }
+            SelectList::Expressions(exprs) => exprs,
+        };
+
+        let mut columns = Vec::new();
+        for select_expr in select_exprs {
+            let data_type = self.analyze_expression(&select_expr.expr, table_schemas, expression_types)?;
+
+            let mut column = match &select_expr.expr {
+                Expression::Column(name) => self.resolve_column(name, table_schemas),
+                Expression::QualifiedColumn { table, column } => table_schemas
+                    .get(table)
+                    .and_then(|schema| schema.columns.iter().find(|c| c.name == *column))
+                    .cloned(),
+                _ => None,
+            }
+            .unwrap_or_else(|| ColumnDefinition {
+                name: match &select_expr.expr {
+                    Expression::FunctionCall { name, .. } => format!("{}()", name),
+                    _ => "expr".to_string(),
+                },
+                data_type,
+                nullable: true,
+                default: None,
+                default_function: None,
+            });
+
+            column.default = None;
+            column.default_function = None;
+            if let Some(alias) = &select_expr.alias {
+                column.name = alias.clone();
+            }
+            columns.push(column);
+        }
+
+        Ok(Schema {
+            columns,
+            primary_key: None,
+        })
+    }
+
+    /// 推断 RETURNING 子句的结果模式
+    fn infer_returning_schema(
+        &self,
+        returning: &Option<crate::sql::parser::SelectList>,
+        table_name: &str,
+        table_schemas: &HashMap<String, Schema>,
+        expression_types: &mut HashMap<String, DataType>,
+    ) -> Result<Option<Schema>, SemanticError> {
+        match returning {
+            Some(list) => Ok(Some(self.infer_result_schema(
+                list,
+                &[table_name.to_string()],
+                table_schemas,
+                expression_types,
+            )?)),
+            None => Ok(None),
+        }
+    }
+
     /// 分析 CREATE TABLE 语句
     fn analyze_create_table(
         &self,
@@ -456,7 +673,7 @@ impl<'a> SemanticAnalyzer<'a> {
 
         // Analyze WHERE clause
         if let Some(where_expr) = where_clause {
-            let expr_type = self.analyze_expression(where_expr, table_schemas, expression_types)?;
+            let expr_type = self.analyze_expression_as(where_expr, &DataType::Boolean, table_schemas, expression_types)?;
 
             // WHERE clause must be boolean
             if expr_type != DataType::Boolean {
@@ -549,8 +766,17 @@ impl<'a> SemanticAnalyzer<'a> {
 
             // Check type compatibility for each value
             for (value_expr, target_column) in value_row.iter().zip(target_columns.iter()) {
-                let value_type =
-                    self.analyze_expression(value_expr, table_schemas, expression_types)?;
+                // DEFAULT takes the column's own default value
+                if matches!(value_expr, Expression::Default) {
+                    continue;
+                }
+
+                let value_type = self.analyze_expression_as(
+                    value_expr,
+                    &target_column.data_type,
+                    table_schemas,
+                    expression_types,
+                )?;
 
                 // Check if value type is compatible with column type
                 if !value_type.is_compatible_with(&target_column.data_type) {
@@ -608,8 +834,12 @@ impl<'a> SemanticAnalyzer<'a> {
                 })?;
 
             // Analyze assignment value
-            let value_type =
-                self.analyze_expression(&assignment.value, table_schemas, expression_types)?;
+            let value_type = self.analyze_expression_as(
+                &assignment.value,
+                &column_def.data_type,
+                table_schemas,
+                expression_types,
+            )?;
 
             // Check type compatibility
             if !value_type.is_compatible_with(&column_def.data_type) {
@@ -623,7 +853,7 @@ impl<'a> SemanticAnalyzer<'a> {
 
         // Analyze WHERE clause
         if let Some(where_expr) = where_clause {
-            let expr_type = self.analyze_expression(where_expr, table_schemas, expression_types)?;
+            let expr_type = self.analyze_expression_as(where_expr, &DataType::Boolean, table_schemas, expression_types)?;
 
             if expr_type != DataType::Boolean {
                 return Err(SemanticError::TypeMismatch {
@@ -657,7 +887,7 @@ impl<'a> SemanticAnalyzer<'a> {
 
         // Analyze WHERE clause
         if let Some(where_expr) = where_clause {
-            let expr_type = self.analyze_expression(where_expr, table_schemas, expression_types)?;
+            let expr_type = self.analyze_expression_as(where_expr, &DataType::Boolean, table_schemas, expression_types)?;
 
             if expr_type != DataType::Boolean {
                 return Err(SemanticError::TypeMismatch {
@@ -708,28 +938,155 @@ impl<'a> SemanticAnalyzer<'a> {
             }
 
             Expression::BinaryOp { left, op, right } => {
-                let left_type = self.analyze_expression(left, table_schemas, expression_types)?;
-                let right_type = self.analyze_expression(right, table_schemas, expression_types)?;
+                // A parameter operand takes the type of the other side (or BOOLEAN for AND/OR)
+                let logical = matches!(op, BinaryOperator::And | BinaryOperator::Or);
+                let (left_type, right_type) = if logical {
+                    (
+                        self.analyze_expression_as(left, &DataType::Boolean, table_schemas, expression_types)?,
+                        self.analyze_expression_as(right, &DataType::Boolean, table_schemas, expression_types)?,
+                    )
+                } else if matches!(left.as_ref(), Expression::Parameter(_)) {
+                    let right_type = self.analyze_expression(right, table_schemas, expression_types)?;
+                    let left_type =
+                        self.analyze_expression_as(left, &right_type, table_schemas, expression_types)?;
+                    (left_type, right_type)
+                } else {
+                    let left_type = self.analyze_expression(left, table_schemas, expression_types)?;
+                    let right_type =
+                        self.analyze_expression_as(right, &left_type, table_schemas, expression_types)?;
+                    (left_type, right_type)
+                };
 
                 self.analyze_binary_operation(op, &left_type, &right_type)?
             }
 
             Expression::UnaryOp { op, expr: operand } => {
+                let hint = match op {
+                    UnaryOperator::Not => DataType::Boolean,
+                    UnaryOperator::Minus | UnaryOperator::Plus => DataType::Double,
+                };
                 let operand_type =
-                    self.analyze_expression(operand, table_schemas, expression_types)?;
+                    self.analyze_expression_as(operand, &hint, table_schemas, expression_types)?;
 
                 self.analyze_unary_operation(op, &operand_type)?
             }
 
-            Expression::FunctionCall { .. } => {
-                // For now, assume function calls return VARCHAR
+            Expression::FunctionCall { name, args } => {
+                // Aggregates follow the executor's result types
                 // TODO: Implement proper function signature checking
-                DataType::Varchar(255)
+                match name.to_uppercase().as_str() {
+                    "COUNT" => DataType::Integer,
+                    aggregate @ ("SUM" | "AVG" | "STDDEV" | "STDDEV_SAMP" | "STDDEV_POP" | "VARIANCE" | "VAR_SAMP" | "VAR_POP") => {
+                        // 数值聚合：参数必须为数值类型（NULL 字面量除外），DECIMAL 的 SUM/AVG 为 DECIMAL，其余为 DOUBLE
+                        let mut result = DataType::Double;
+                        for arg in args {
+                            let arg_type = self.analyze_expression_as(arg, &DataType::Double, table_schemas, expression_types)?;
+                            if !self.is_numeric_type(&arg_type) && !matches!(arg, Expression::Literal(Value::Null)) {
+                                return Err(SemanticError::TypeMismatch {
+                                    expected: DataType::Double,
+                                    found: arg_type,
+                                    position: None,
+                                });
+                            }
+                            result = match (aggregate, arg_type) {
+                                ("SUM", DataType::Decimal(_, scale)) => DataType::Decimal(decimal::MAX_PRECISION, scale),
+                                ("AVG", DataType::Decimal(_, scale)) => {
+                                    DataType::Decimal(decimal::MAX_PRECISION, Decimal::quotient_scale(scale, 0))
+                                }
+                                _ => DataType::Double,
+                            };
+                        }
+                        result
+                    }
+                    "STRING_AGG" | "GROUP_CONCAT" => {
+                        for arg in args {
+                            self.analyze_expression(arg, table_schemas, expression_types)?;
+                        }
+                        DataType::Varchar(255)
+                    }
+                    "MAX" | "MIN" => match args.first() {
+                        Some(arg) => self.analyze_expression(arg, table_schemas, expression_types)?,
+                        None => DataType::Double,
+                    },
+                    "NOW" | "CURRENT_TIMESTAMP" => DataType::Timestamp,
+                    "CURRENT_DATE" => DataType::Date,
+                    "RANDOM" | "RAND" => DataType::Double,
+                    "GEN_UUID" | "UUID" => DataType::Uuid,
+                    "JSON_EXTRACT" => DataType::Json,
+                    "EXTRACT" => {
+                        for arg in args {
+                            self.analyze_expression(arg, table_schemas, expression_types)?;
+                        }
+                        DataType::Integer
+                    }
+                    "DATE_ADD" | "DATE_SUB" => {
+                        // DATE_ADD(date, amount [, unit])：结果与第一个参数同类型
+                        let mut arg_types = Vec::new();
+                        for (i, arg) in args.iter().enumerate() {
+                            let expected = match i {
+                                0 => DataType::Date,
+                                1 => DataType::Integer,
+                                _ => DataType::Varchar(255),
+                            };
+                            arg_types.push(self.analyze_expression_as(arg, &expected, table_schemas, expression_types)?);
+                        }
+                        arg_types.into_iter().next().unwrap_or(DataType::Date)
+                    }
+                    "ABS" | "CEIL" | "CEILING" | "FLOOR" | "ROUND" | "MOD" | "POWER" | "POW" | "SQRT" => {
+                        self.analyze_math_function(name, args, table_schemas, expression_types)?
+                    }
+                    "UPPER" | "LOWER" => {
+                        // 结果与参数同为字符串类型（保留长度）
+                        let mut result = DataType::Varchar(255);
+                        for arg in args {
+                            let arg_type = self.analyze_expression_as(arg, &DataType::Varchar(255), table_schemas, expression_types)?;
+                            match arg_type {
+                                DataType::Varchar(_) | DataType::Char(_) | DataType::Text => result = arg_type,
+                                _ if matches!(arg, Expression::Literal(Value::Null)) => {}
+                                _ => return Err(SemanticError::TypeMismatch {
+                                    expected: DataType::Varchar(255),
+                                    found: arg_type,
+                                    position: None,
+                                }),
+                            }
+                        }
+                        result
+                    }
+                    "LENGTH" => {
+                        for arg in args {
+                            let arg_type = self.analyze_expression_as(arg, &DataType::Text, table_schemas, expression_types)?;
+                            match arg_type {
+                                DataType::Varchar(_) | DataType::Char(_) | DataType::Text | DataType::Blob => {}
+                                _ if matches!(arg, Expression::Literal(Value::Null)) => {}
+                                _ => return Err(SemanticError::TypeMismatch {
+                                    expected: DataType::Text,
+                                    found: arg_type,
+                                    position: None,
+                                }),
+                            }
+                        }
+                        DataType::Integer
+                    }
+                    "COALESCE" | "NULLIF" => {
+                        let common = self.analyze_common_type(args, table_schemas, expression_types)?;
+                        if name.eq_ignore_ascii_case("NULLIF") {
+                            // NULLIF 返回第一个参数或 NULL，公共类型只用于比较
+                            match args.first() {
+                                Some(first) => self.analyze_expression(first, table_schemas, expression_types)?,
+                                None => common,
+                            }
+                        } else {
+                            common
+                        }
+                    }
+                    _ => DataType::Varchar(255),
+                }
             }
 
             Expression::In {
                 expr: operand,
                 list,
+                ..
             } => {
                 let operand_type =
                     self.analyze_expression(operand, table_schemas, expression_types)?;
@@ -737,7 +1094,7 @@ impl<'a> SemanticAnalyzer<'a> {
                 // Check that all list items are compatible with operand type
                 for item in list {
                     let item_type =
-                        self.analyze_expression(item, table_schemas, expression_types)?;
+                        self.analyze_expression_as(item, &operand_type, table_schemas, expression_types)?;
                     if !item_type.is_compatible_with(&operand_type) {
                         return Err(SemanticError::TypeMismatch {
                             expected: operand_type,
@@ -754,11 +1111,14 @@ impl<'a> SemanticAnalyzer<'a> {
                 expr: operand,
                 low,
                 high,
+                ..
             } => {
                 let operand_type =
                     self.analyze_expression(operand, table_schemas, expression_types)?;
-                let low_type = self.analyze_expression(low, table_schemas, expression_types)?;
-                let high_type = self.analyze_expression(high, table_schemas, expression_types)?;
+                let low_type =
+                    self.analyze_expression_as(low, &operand_type, table_schemas, expression_types)?;
+                let high_type =
+                    self.analyze_expression_as(high, &operand_type, table_schemas, expression_types)?;
 
                 if !low_type.is_compatible_with(&operand_type) {
                     return Err(SemanticError::TypeMismatch {
@@ -778,9 +1138,49 @@ impl<'a> SemanticAnalyzer<'a> {
                 DataType::Boolean
             }
 
-            Expression::Like { .. } => DataType::Boolean,
+            Expression::Like {
+                expr: operand,
+                pattern,
+                escape,
+                ..
+            } => {
+                let text = DataType::Varchar(255);
+                self.analyze_expression_as(operand, &text, table_schemas, expression_types)?;
+                self.analyze_expression_as(pattern, &text, table_schemas, expression_types)?;
+                if let Some(escape) = escape {
+                    self.analyze_expression_as(escape, &text, table_schemas, expression_types)?;
+                }
+                DataType::Boolean
+            }
+            Expression::Cast { expr: operand, data_type } => {
+                // Whether the value converts is only known at run time (e.g. '2024-13-01' AS DATE)
+                self.analyze_expression_as(operand, data_type, table_schemas, expression_types)?;
+                data_type.clone()
+            }
             Expression::IsNull(_) => DataType::Boolean,
             Expression::IsNotNull(_) => DataType::Boolean,
+            Expression::Default => Value::Null.data_type(),
+
+            Expression::InSubquery { expr: operand, subquery, .. } => {
+                let column_type = self.analyze_subquery(subquery, true, table_schemas, expression_types)?;
+                if let Some(column_type) = column_type {
+                    self.analyze_expression_as(operand, &column_type, table_schemas, expression_types)?;
+                }
+                DataType::Boolean
+            }
+            Expression::Exists(subquery) => {
+                self.analyze_subquery(subquery, false, table_schemas, expression_types)?;
+                DataType::Boolean
+            }
+
+            Expression::Parameter(_) => {
+                // Only a context (see analyze_expression_as) can give a parameter its type;
+                // without one it is typed like NULL and not recorded
+                return Ok(expression_types
+                    .get(&format!("{:?}", expr))
+                    .cloned()
+                    .unwrap_or_else(|| Value::Null.data_type()));
+            }
         };
 
         // Store expression type for later use
@@ -790,6 +1190,168 @@ impl<'a> SemanticAnalyzer<'a> {
         Ok(expr_type)
     }
 
+    /// 分析 WHERE 中的子查询
+    ///
+    /// 子查询的作用域包括外层查询的表，因此可以引用外层的列（相关子查询）；与外层同名的列需要用表名限定。
+    /// single_column 为真时（IN 子查询）子查询必须只返回一列，返回该列的类型。
+    fn analyze_subquery(
+        &self,
+        subquery: &Statement,
+        single_column: bool,
+        table_schemas: &HashMap<String, Schema>,
+        expression_types: &mut HashMap<String, DataType>,
+    ) -> Result<Option<DataType>, SemanticError> {
+        use crate::sql::parser::SelectList;
+
+        let mut tables = Vec::new();
+        if let Statement::Select { from_clause: Some(from), .. } = subquery {
+            Self::collect_from_tables(from, &mut tables);
+        }
+        // 非限定列先在子查询自己的表中解析，与外层表同名的列不算引用不明确
+        let inner: Vec<(String, Schema)> = tables
+            .iter()
+            .filter_map(|table| Some((table.clone(), self.catalog.get_table_schema(table)?)))
+            .collect();
+        let mut subquery = subquery.clone();
+        subquery.visit_expressions_mut(&mut |expr| {
+            if let Expression::Column(name) = expr {
+                let mut owners = inner.iter().filter(|(_, schema)| schema.columns.iter().any(|column| column.name == *name));
+                if let (Some((table, _)), None) = (owners.next(), owners.next()) {
+                    *expr = Expression::QualifiedColumn { table: table.clone(), column: name.clone() };
+                }
+            }
+        });
+        let Statement::Select { select_list, from_clause, where_clause, .. } = &subquery else {
+            return Ok(None);
+        };
+        let mut scope = table_schemas.clone();
+        self.analyze_select(from_clause, where_clause, select_list, &mut scope, expression_types)?;
+
+        let column_types: Vec<DataType> = match select_list {
+            SelectList::Wildcard => tables
+                .iter()
+                .filter_map(|table| scope.get(table))
+                .flat_map(|schema| schema.columns.iter().map(|column| column.data_type.clone()))
+                .collect(),
+            SelectList::Expressions(exprs) => exprs
+                .iter()
+                .map(|select_expr| self.analyze_expression(&select_expr.expr, &scope, expression_types))
+                .collect::<Result<_, _>>()?,
+        };
+        if !single_column {
+            return Ok(None);
+        }
+        match column_types.as_slice() {
+            [column_type] => Ok(Some(column_type.clone())),
+            _ => Err(SemanticError::SubqueryColumnCount {
+                actual: column_types.len(),
+                position: None,
+            }),
+        }
+    }
+
+    /// 在唯一匹配的表中查找列定义
+    fn resolve_column(
+        &self,
+        column_name: &str,
+        table_schemas: &HashMap<String, Schema>,
+    ) -> Option<ColumnDefinition> {
+        let mut matches = table_schemas
+            .values()
+            .flat_map(|schema| schema.columns.iter())
+            .filter(|column| column.name == column_name);
+        let column = matches.next()?;
+        matches.next().is_none().then(|| column.clone())
+    }
+
+    /// 分析出现在期望类型位置上的表达式
+    ///
+    /// 参数占位符的类型由此推断：首次出现时记录期望类型，之后沿用已记录的类型。
+    fn analyze_expression_as(
+        &self,
+        expr: &Expression,
+        expected: &DataType,
+        table_schemas: &HashMap<String, Schema>,
+        expression_types: &mut HashMap<String, DataType>,
+    ) -> Result<DataType, SemanticError> {
+        if let Expression::Parameter(_) = expr {
+            return Ok(expression_types
+                .entry(format!("{:?}", expr))
+                .or_insert_with(|| expected.clone())
+                .clone());
+        }
+        self.analyze_expression(expr, table_schemas, expression_types)
+    }
+
+    /// 分析数学函数：参数必须为数值，结果类型与求值器的类型提升一致
+    fn analyze_math_function(
+        &self,
+        name: &str,
+        args: &[Expression],
+        table_schemas: &HashMap<String, Schema>,
+        expression_types: &mut HashMap<String, DataType>,
+    ) -> Result<DataType, SemanticError> {
+        let mut arg_types = Vec::new();
+        for arg in args {
+            let arg_type = self.analyze_expression_as(arg, &DataType::Double, table_schemas, expression_types)?;
+            if !self.is_numeric_type(&arg_type) && !matches!(arg, Expression::Literal(Value::Null)) {
+                return Err(SemanticError::TypeMismatch {
+                    expected: DataType::Double,
+                    found: arg_type,
+                    position: None,
+                });
+            }
+            arg_types.push(arg_type);
+        }
+
+        let first = arg_types.first().cloned().unwrap_or(DataType::Double);
+        Ok(match name.to_uppercase().as_str() {
+            // ABS/CEIL/FLOOR/ROUND 保持参数类型
+            "ABS" | "CEIL" | "CEILING" | "FLOOR" | "ROUND" => first,
+            "MOD" => match (&first, arg_types.get(1)) {
+                (DataType::Integer, Some(DataType::Integer)) => DataType::Integer,
+                (DataType::Integer | DataType::BigInt, Some(DataType::Integer | DataType::BigInt)) => DataType::BigInt,
+                _ => DataType::Double,
+            },
+            _ => DataType::Double,
+        })
+    }
+
+    /// 分析需要统一类型的参数列表（COALESCE/NULLIF），返回它们的公共类型
+    ///
+    /// NULL 字面量不参与类型统一；参数占位符取已确定的公共类型。
+    fn analyze_common_type(
+        &self,
+        args: &[Expression],
+        table_schemas: &HashMap<String, Schema>,
+        expression_types: &mut HashMap<String, DataType>,
+    ) -> Result<DataType, SemanticError> {
+        let mut common: Option<DataType> = None;
+        // Typed arguments first so that placeholders can take the common type
+        let (placeholders, typed): (Vec<_>, Vec<_>) =
+            args.iter().partition(|arg| matches!(arg, Expression::Parameter(_)));
+        for arg in typed {
+            let arg_type = self.analyze_expression(arg, table_schemas, expression_types)?;
+            if matches!(arg, Expression::Literal(Value::Null)) {
+                continue;
+            }
+            common = Some(match common {
+                None => arg_type,
+                Some(current) => current.common_type(&arg_type).ok_or(SemanticError::TypeMismatch {
+                    expected: current,
+                    found: arg_type,
+                    position: None,
+                })?,
+            });
+        }
+
+        let common = common.unwrap_or(DataType::Varchar(255));
+        for arg in placeholders {
+            self.analyze_expression_as(arg, &common, table_schemas, expression_types)?;
+        }
+        Ok(common)
+    }
+
     /// 从可用模式中解析列类型
     fn resolve_column_type(
         &self,
@@ -807,6 +1369,8 @@ impl<'a> SemanticAnalyzer<'a> {
         }
 
         match matches.len() {
+            // 单表语句中没有同名真实列时，rowid 是引擎提供的行标识伪列
+            0 if column_name == "rowid" && table_schemas.len() == 1 => Ok(DataType::BigInt),
             0 => Err(SemanticError::ColumnNotFound {
                 table: "unknown".to_string(),
                 column: column_name.to_string(),
@@ -842,6 +1406,8 @@ impl<'a> SemanticAnalyzer<'a> {
                         || matches!(right_type, DataType::Float)
                     {
                         Ok(DataType::Float)
+                    } else if let Some(scale) = Self::decimal_result_scale(op, left_type, right_type) {
+                        Ok(DataType::Decimal(decimal::MAX_PRECISION, scale))
                     } else if matches!(left_type, DataType::BigInt)
                         || matches!(right_type, DataType::BigInt)
                     {
@@ -875,6 +1441,22 @@ impl<'a> SemanticAnalyzer<'a> {
                 }
             }
 
+            // JSON 取值：左边是 JSON 文档，右边是键或下标
+            JsonExtract | JsonExtractText => {
+                if matches!(left_type, DataType::Json)
+                    && matches!(right_type, DataType::Varchar(_) | DataType::Char(_) | DataType::Text | DataType::Integer | DataType::BigInt)
+                {
+                    Ok(if matches!(op, JsonExtract) { DataType::Json } else { DataType::Text })
+                } else {
+                    Err(SemanticError::InvalidBinaryOperation {
+                        op: op.clone(),
+                        left: left_type.clone(),
+                        right: right_type.clone(),
+                        position: None,
+                    })
+                }
+            }
+
             // Logical operations
             And | Or => {
                 if *left_type == DataType::Boolean && *right_type == DataType::Boolean {
@@ -926,11 +1508,28 @@ impl<'a> SemanticAnalyzer<'a> {
         }
     }
 
+    /// DECIMAL 与整数或 DECIMAL 运算结果的小数位数；没有 DECIMAL 操作数时为 None
+    fn decimal_result_scale(op: &BinaryOperator, left: &DataType, right: &DataType) -> Option<u8> {
+        let scale = |t: &DataType| match t {
+            DataType::Decimal(_, scale) => *scale,
+            _ => 0,
+        };
+        if !matches!(left, DataType::Decimal(..)) && !matches!(right, DataType::Decimal(..)) {
+            return None;
+        }
+        let (s1, s2) = (scale(left), scale(right));
+        Some(match op {
+            BinaryOperator::Multiply => (s1 + s2).min(decimal::MAX_PRECISION),
+            BinaryOperator::Divide => Decimal::quotient_scale(s1, s2),
+            _ => s1.max(s2),
+        })
+    }
+
     /// 检查类型是否为数值类型
     fn is_numeric_type(&self, data_type: &DataType) -> bool {
         matches!(
             data_type,
-            DataType::Integer | DataType::BigInt | DataType::Float | DataType::Double
+            DataType::Integer | DataType::BigInt | DataType::Float | DataType::Double | DataType::Decimal(..)
         )
     }
 }
@@ -953,24 +1552,28 @@ mod tests {
                     data_type: DataType::Integer,
                     nullable: false,
                     default: None,
+                    default_function: None,
                 },
                 ColumnDefinition {
                     name: "name".to_string(),
                     data_type: DataType::Varchar(255),
                     nullable: false,
                     default: None,
+                    default_function: None,
                 },
                 ColumnDefinition {
                     name: "age".to_string(),
                     data_type: DataType::Integer,
                     nullable: true,
                     default: None,
+                    default_function: None,
                 },
                 ColumnDefinition {
                     name: "email".to_string(),
                     data_type: DataType::Varchar(255),
                     nullable: true,
                     default: None,
+                    default_function: None,
                 },
             ],
             primary_key: Some(vec![0]), // id column is primary key
@@ -1176,4 +1779,104 @@ mod tests {
         assert_eq!(analyzed.table_schemas.len(), 1);
         assert!(analyzed.table_schemas.contains_key("users"));
     }
+
+    #[test]
+    fn test_math_function_types() {
+        let catalog = create_test_catalog();
+        let analyzer = SemanticAnalyzer::new(&catalog);
+
+        let stmt = parse_sql("SELECT ABS(age), ROUND(age), MOD(age, 3), SQRT(age), POWER(2, 3) FROM users").unwrap();
+        let schema = analyzer.analyze(stmt).unwrap().result_schema.unwrap();
+        let types: Vec<&DataType> = schema.columns.iter().map(|c| &c.data_type).collect();
+        assert_eq!(
+            types,
+            vec![&DataType::Integer, &DataType::Integer, &DataType::Integer, &DataType::Double, &DataType::Double]
+        );
+
+        let stmt = parse_sql("SELECT ABS(name) FROM users").unwrap();
+        assert!(matches!(
+            analyzer.analyze(stmt),
+            Err(SemanticError::TypeMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_null_function_types() {
+        let catalog = create_test_catalog();
+        let analyzer = SemanticAnalyzer::new(&catalog);
+
+        let stmt = parse_sql(
+            "SELECT COALESCE(age, 0), COALESCE(NULL, age, 1.5), NULLIF(age, 0.0), COALESCE(name, 'x') FROM users",
+        )
+        .unwrap();
+        let schema = analyzer.analyze(stmt).unwrap().result_schema.unwrap();
+        let types: Vec<&DataType> = schema.columns.iter().map(|c| &c.data_type).collect();
+        assert_eq!(
+            types,
+            vec![&DataType::Integer, &DataType::Double, &DataType::Integer, &DataType::Varchar(255)]
+        );
+
+        for sql in ["SELECT COALESCE(name, 1) FROM users", "SELECT NULLIF(age, 'x') FROM users"] {
+            let stmt = parse_sql(sql).unwrap();
+            assert!(matches!(
+                analyzer.analyze(stmt),
+                Err(SemanticError::TypeMismatch { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_numeric_aggregate_arguments() {
+        let catalog = create_test_catalog();
+        let analyzer = SemanticAnalyzer::new(&catalog);
+
+        let stmt = parse_sql("SELECT SUM(age), AVG(age), STDDEV(age), MAX(name) FROM users").unwrap();
+        let schema = analyzer.analyze(stmt).unwrap().result_schema.unwrap();
+        let types: Vec<&DataType> = schema.columns.iter().map(|c| &c.data_type).collect();
+        assert_eq!(
+            types,
+            vec![&DataType::Double, &DataType::Double, &DataType::Double, &DataType::Varchar(255)]
+        );
+
+        for sql in ["SELECT SUM(name) FROM users", "SELECT AVG(name) FROM users", "SELECT VAR_POP(name) FROM users"] {
+            let stmt = parse_sql(sql).unwrap();
+            assert!(matches!(
+                analyzer.analyze(stmt),
+                Err(SemanticError::TypeMismatch { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_infer_result_schema() {
+        let catalog = create_test_catalog();
+        let analyzer = SemanticAnalyzer::new(&catalog);
+
+        let stmt = parse_sql("SELECT id, name AS who, age + 1, COUNT(*) FROM users").unwrap();
+        let schema = analyzer.analyze(stmt).unwrap().result_schema.unwrap();
+        let columns: Vec<(&str, &DataType, bool)> = schema
+            .columns
+            .iter()
+            .map(|c| (c.name.as_str(), &c.data_type, c.nullable))
+            .collect();
+        assert_eq!(
+            columns,
+            vec![
+                ("id", &DataType::Integer, false),
+                ("who", &DataType::Varchar(255), false),
+                ("expr", &DataType::Integer, true),
+                ("COUNT()", &DataType::Integer, true),
+            ]
+        );
+
+        let stmt = parse_sql("DELETE FROM users WHERE age > 1 RETURNING *").unwrap();
+        let schema = analyzer.analyze(stmt).unwrap().result_schema.unwrap();
+        assert_eq!(schema.columns.len(), 4);
+
+        let stmt = parse_sql("SELECT missing FROM users").unwrap();
+        assert!(matches!(
+            analyzer.analyze(stmt),
+            Err(SemanticError::ColumnNotFound { .. })
+        ));
+    }
 }
@@ -0,0 +1,558 @@
+//! 基于统计的代价估算
+//!
+//! 优化器用 ANALYZE 收集的统计（[`TableStatistics`]）估计过滤条件的选择性和各计划节点输出的行数，
+//! 再按下面的代价常量比较候选计划：访问路径、连接算法（归并连接要加上为未排序的输入排序的代价）和哈希连接的建表侧。
+//!
+//! - `列 = 常量`：常量超出 [最小值, 最大值] 时为 0，否则为非 NULL 比例除以不同值个数（NDV）
+//! - `<`、`<=`、`>`、`>=`、`BETWEEN`：在等深直方图的桶内按数值线性插值，非数值取桶的一半
+//! - `IS NULL`：NULL 比例；AND 取乘积，OR 按独立事件合并，NOT 取补
+//! - 等值连接：两侧行数之积除以两侧连接列 NDV 中较大的一个
+//!
+//! 没有统计（未 ANALYZE）时用默认选择性，等值连接把各侧的行数当作 NDV（假定连接键不重复）。
+//! 统计只在 ANALYZE 时更新，之后的修改会让估计偏离实际，但不影响结果的正确性。
+
+use crate::engine::statistics::{ColumnStatistics, TableStatistics};
+use crate::sql::parser::{BinaryOperator, Expression, UnaryOperator};
+use crate::sql::planner::{AccessPathCatalog, ExecutionPlan, JoinAlgorithm, JoinType};
+use crate::types::Value;
+use std::cmp::Ordering;
+
+/// 顺序扫描读取一行的代价（代价单位）
+pub const SEQ_SCAN_ROW_COST: f64 = 1.0;
+/// 按索引定位并读取一行的代价；索引取出的行超过表的四分之一时不如顺序扫描
+pub const INDEX_SCAN_ROW_COST: f64 = 4.0;
+/// 在哈希表中插入或探测一行的代价（哈希连接、分组）
+pub const HASH_ROW_COST: f64 = 2.0;
+/// 比较一对行的代价（嵌套循环连接的每一对、排序和归并的每次比较）
+pub const COMPARISON_COST: f64 = 0.5;
+/// 没有统计时 `列 = 常量`、`IS NULL` 的选择性
+pub const DEFAULT_EQUALITY_SELECTIVITY: f64 = 0.1;
+/// 没有统计时其他条件（范围、LIKE、函数等）的选择性
+pub const DEFAULT_SELECTIVITY: f64 = 1.0 / 3.0;
+
+/// 一个计划节点的估计
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Estimate {
+    /// 预计输出的行数
+    pub rows: f64,
+    /// 执行到该节点为止的累计代价
+    pub cost: f64,
+}
+
+/// 解析列引用时可见的表及其统计
+struct Scope<'a> {
+    tables: Vec<(&'a str, Option<&'a TableStatistics>)>,
+}
+
+impl<'a> Scope<'a> {
+    fn of(plan: &'a ExecutionPlan, catalog: &'a dyn AccessPathCatalog) -> Self {
+        let tables = plan_tables(plan).into_iter()
+            .map(|table| (table, catalog.table_statistics(table)))
+            .collect();
+        Self { tables }
+    }
+
+    /// 列引用对应的统计及其表的行数；列不属于任何有统计的表时为 None
+    fn column(&self, expr: &Expression) -> Option<(&'a ColumnStatistics, usize)> {
+        let (table, name) = match expr {
+            Expression::Column(name) => (None, name),
+            Expression::QualifiedColumn { table, column } => (Some(table.as_str()), column),
+            _ => return None,
+        };
+        self.tables.iter()
+            .filter(|(candidate, _)| table.is_none_or(|table| *candidate == table))
+            .filter_map(|(_, stats)| stats.and_then(|stats| Some((stats.column(name)?, stats.row_count))))
+            .next()
+    }
+
+    fn contains(&self, expr: &Expression) -> bool {
+        match expr {
+            Expression::QualifiedColumn { table, .. } => self.tables.iter().any(|(candidate, _)| candidate == table),
+            _ => self.column(expr).is_some(),
+        }
+    }
+}
+
+/// 计划中扫描的表，从左到右
+pub fn plan_tables(plan: &ExecutionPlan) -> Vec<&str> {
+    match plan {
+        ExecutionPlan::TableScan { table_name, .. }
+        | ExecutionPlan::IndexScan { table_name, .. }
+        | ExecutionPlan::IndexOnlyScan { table_name, .. } => vec![table_name.as_str()],
+        ExecutionPlan::Join { left, right, .. } => {
+            let mut tables = plan_tables(left);
+            tables.extend(plan_tables(right));
+            tables
+        }
+        ExecutionPlan::Project { input, .. }
+        | ExecutionPlan::Filter { input, .. }
+        | ExecutionPlan::Apply { input, .. }
+        | ExecutionPlan::Sort { input, .. }
+        | ExecutionPlan::Limit { input, .. }
+        | ExecutionPlan::GroupBy { input, .. } => plan_tables(input),
+        _ => Vec::new(),
+    }
+}
+
+/// 估计计划输出的行数和执行代价
+pub fn estimate(plan: &ExecutionPlan, catalog: &dyn AccessPathCatalog) -> Estimate {
+    match plan {
+        ExecutionPlan::TableScan { table_name, filter, .. } => {
+            let table_rows = catalog.table_row_count(table_name).unwrap_or(0) as f64;
+            let selectivity = filter.as_ref().map_or(1.0, |filter| selectivity(filter, &Scope::of(plan, catalog)));
+            Estimate { rows: table_rows * selectivity, cost: table_rows * SEQ_SCAN_ROW_COST }
+        }
+        ExecutionPlan::IndexScan { table_name, filter, estimated_rows, .. }
+        | ExecutionPlan::IndexOnlyScan { table_name, filter, estimated_rows, .. } => {
+            // 索引给出的是按索引条件取出的准确行数，其余条件再按统计缩小
+            let table_rows = catalog.table_row_count(table_name).unwrap_or(0) as f64;
+            let fetched = *estimated_rows as f64;
+            let filtered = filter.as_ref().map_or(fetched, |filter| table_rows * selectivity(filter, &Scope::of(plan, catalog)));
+            Estimate { rows: fetched.min(filtered), cost: fetched * INDEX_SCAN_ROW_COST }
+        }
+        ExecutionPlan::Filter { input, condition } => {
+            let input_estimate = estimate(input, catalog);
+            Estimate { rows: input_estimate.rows * selectivity(condition, &Scope::of(plan, catalog)), ..input_estimate }
+        }
+        ExecutionPlan::Project { input, .. } => estimate(input, catalog),
+        ExecutionPlan::Apply { input, subquery, .. } => {
+            // 子查询对每个输入行执行一次
+            let (input_estimate, subquery_estimate) = (estimate(input, catalog), estimate(subquery, catalog));
+            Estimate {
+                rows: input_estimate.rows * DEFAULT_SELECTIVITY,
+                cost: input_estimate.cost + input_estimate.rows * subquery_estimate.cost,
+            }
+        }
+        ExecutionPlan::Sort { input, .. } => {
+            let input_estimate = estimate(input, catalog);
+            Estimate { cost: input_estimate.cost + sort_cost(input_estimate.rows), ..input_estimate }
+        }
+        ExecutionPlan::Limit { input, count, offset } => {
+            let input_estimate = estimate(input, catalog);
+            let remaining = (input_estimate.rows - offset.unwrap_or(0) as f64).max(0.0);
+            Estimate { rows: remaining.min(*count as f64), ..input_estimate }
+        }
+        ExecutionPlan::GroupBy { input, group_expressions, .. } => {
+            let input_estimate = estimate(input, catalog);
+            let scope = Scope::of(plan, catalog);
+            // 分组数为各分组列 NDV 之积，不超过输入行数；没有分组列时只有一组
+            let groups = group_expressions.iter()
+                .map(|expr| scope.column(expr).map_or(input_estimate.rows, |(column, _)| column.distinct_count as f64))
+                .product::<f64>()
+                .min(input_estimate.rows)
+                .max(1.0);
+            Estimate { rows: groups, cost: input_estimate.cost + input_estimate.rows * HASH_ROW_COST }
+        }
+        ExecutionPlan::Join { left, right, join_type, condition, algorithm } => {
+            let (left_estimate, right_estimate) = (estimate(left, catalog), estimate(right, catalog));
+            let (left_rows, right_rows) = (left_estimate.rows, right_estimate.rows);
+            let matched = left_rows * right_rows * condition.as_ref().map_or(1.0, |condition| {
+                join_selectivity(condition, &Scope::of(left, catalog), &Scope::of(right, catalog), left_rows, right_rows)
+            });
+            // 外连接保留一侧未匹配的行
+            let rows = match join_type {
+                JoinType::Inner => matched,
+                JoinType::Left => matched.max(left_rows),
+                JoinType::Right => matched.max(right_rows),
+                JoinType::Full => matched.max(left_rows).max(right_rows),
+                // 左输入的每行至多输出一次
+                JoinType::Semi => matched.min(left_rows),
+                JoinType::Anti => left_rows - matched.min(left_rows),
+            };
+            Estimate {
+                rows,
+                cost: left_estimate.cost + right_estimate.cost + join_cost(algorithm, left_rows, right_rows),
+            }
+        }
+        _ => Estimate { rows: 0.0, cost: 0.0 },
+    }
+}
+
+/// 用给定算法连接两个输入本身的代价（不含读取输入）；哈希连接由左输入建表
+pub fn join_cost(algorithm: &JoinAlgorithm, left_rows: f64, right_rows: f64) -> f64 {
+    match algorithm {
+        JoinAlgorithm::Hash => (left_rows + right_rows) * HASH_ROW_COST,
+        JoinAlgorithm::NestedLoop => left_rows * right_rows * COMPARISON_COST,
+        // 两侧已经有序，每行只比较一次
+        JoinAlgorithm::Merge => (left_rows + right_rows) * COMPARISON_COST,
+    }
+}
+
+/// 在内存中排序 rows 行的代价
+pub fn sort_cost(rows: f64) -> f64 {
+    rows * rows.max(2.0).log2() * COMPARISON_COST
+}
+
+/// 归并连接的键：连接条件全部由 `左输入的列 = 右输入的列` 组成时，按条件的顺序返回各对 (左键, 右键)，否则为 None
+///
+/// 归并连接只比较键，不求值其他条件；列要写成 `表.列` 才能确定属于哪一侧。
+pub fn merge_join_keys(condition: &Expression, left: &ExecutionPlan, right: &ExecutionPlan) -> Option<Vec<(Expression, Expression)>> {
+    let (left_tables, right_tables) = (plan_tables(left), plan_tables(right));
+    let is_left = |expr: &Expression| match expr {
+        Expression::QualifiedColumn { table, .. } if left_tables.contains(&table.as_str()) => Some(true),
+        Expression::QualifiedColumn { table, .. } if right_tables.contains(&table.as_str()) => Some(false),
+        _ => None,
+    };
+    conjuncts(condition).into_iter()
+        .map(|conjunct| match conjunct {
+            Expression::BinaryOp { left: a, op: BinaryOperator::Equal, right: b } => match (is_left(a)?, is_left(b)?) {
+                (true, false) => Some((a.as_ref().clone(), b.as_ref().clone())),
+                (false, true) => Some((b.as_ref().clone(), a.as_ref().clone())),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+/// 计划的输出是否已按 keys 依次升序排列，归并连接可以省去排序
+///
+/// 排序键以 keys 开头的 Sort 有序；索引条件把每个键列都固定为常量的索引扫描输出的键全部相同，也视为有序。
+/// 过滤不改变输入的顺序。
+pub fn ordered_on(plan: &ExecutionPlan, keys: &[Expression]) -> bool {
+    match plan {
+        ExecutionPlan::Sort { sort_keys, .. } => {
+            sort_keys.len() >= keys.len()
+                && sort_keys.iter().zip(keys).all(|(sort_key, key)| !sort_key.descending && sort_key.expression == *key)
+        }
+        ExecutionPlan::IndexScan { table_name, condition: Some(condition), .. }
+        | ExecutionPlan::IndexOnlyScan { table_name, condition: Some(condition), .. } => {
+            let fixed: Vec<&str> = conjuncts(condition).into_iter()
+                .filter_map(|conjunct| match conjunct {
+                    Expression::BinaryOp { left, op: BinaryOperator::Equal, right } => match (left.as_ref(), right.as_ref()) {
+                        (column, Expression::Literal(_)) | (Expression::Literal(_), column) => column_name(column),
+                        _ => None,
+                    },
+                    _ => None,
+                })
+                .collect();
+            keys.iter().all(|key| match key {
+                Expression::QualifiedColumn { table, column } => table == table_name && fixed.contains(&column.as_str()),
+                _ => false,
+            })
+        }
+        ExecutionPlan::Filter { input, .. } => ordered_on(input, keys),
+        _ => false,
+    }
+}
+
+/// 连接条件中 AND 连接的 `列 = 列` 等值条件；为空时只能用嵌套循环连接
+pub fn equi_join_keys(condition: &Expression) -> Vec<(&Expression, &Expression)> {
+    conjuncts(condition).into_iter()
+        .filter_map(|conjunct| match conjunct {
+            Expression::BinaryOp { left, op: BinaryOperator::Equal, right } if is_column(left) && is_column(right) => {
+                Some((left.as_ref(), right.as_ref()))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// 连接条件的选择性：一侧一列的等值条件按两侧 NDV 中较大的一个，其余条件按普通过滤条件
+fn join_selectivity(condition: &Expression, left: &Scope, right: &Scope, left_rows: f64, right_rows: f64) -> f64 {
+    let combined = Scope { tables: left.tables.iter().chain(&right.tables).copied().collect() };
+    let distinct = |scope: &Scope, expr: &Expression, rows: f64| {
+        scope.column(expr).map_or(rows, |(column, _)| column.distinct_count as f64)
+    };
+    conjuncts(condition).into_iter()
+        .map(|conjunct| match conjunct {
+            Expression::BinaryOp { left: a, op: BinaryOperator::Equal, right: b } if is_column(a) && is_column(b) => {
+                let keys = if left.contains(a) && right.contains(b) {
+                    Some((a, b))
+                } else if left.contains(b) && right.contains(a) {
+                    Some((b, a))
+                } else if !combined.contains(a) && !combined.contains(b) {
+                    // 无法确定所属的表（没有统计的非限定列）时按连接键处理
+                    Some((a, b))
+                } else {
+                    None
+                };
+                match keys {
+                    Some((a, b)) => 1.0 / distinct(left, a, left_rows).max(distinct(right, b, right_rows)).max(1.0),
+                    None => selectivity(conjunct, &combined),
+                }
+            }
+            conjunct => selectivity(conjunct, &combined),
+        })
+        .product()
+}
+
+/// 过滤条件的选择性（满足条件的行所占比例），在 [0, 1] 之间
+fn selectivity(filter: &Expression, scope: &Scope) -> f64 {
+    let estimate = match filter {
+        Expression::BinaryOp { left, op: BinaryOperator::And, right } => selectivity(left, scope) * selectivity(right, scope),
+        Expression::BinaryOp { left, op: BinaryOperator::Or, right } => {
+            let (a, b) = (selectivity(left, scope), selectivity(right, scope));
+            a + b - a * b
+        }
+        Expression::UnaryOp { op: UnaryOperator::Not, expr } => 1.0 - selectivity(expr, scope),
+        Expression::BinaryOp { left, op, right } => match (left.as_ref(), right.as_ref()) {
+            (column, Expression::Literal(value)) if is_column(column) => comparison(scope.column(column), op, value),
+            (Expression::Literal(value), column) if is_column(column) => match flip(op) {
+                Some(op) => comparison(scope.column(column), &op, value),
+                None => DEFAULT_SELECTIVITY,
+            },
+            (a, b) if is_column(a) && is_column(b) && *op == BinaryOperator::Equal => {
+                let distinct = |expr| scope.column(expr).map(|(column, _)| column.distinct_count as f64);
+                match (distinct(a), distinct(b)) {
+                    (None, None) => DEFAULT_EQUALITY_SELECTIVITY,
+                    (a, b) => 1.0 / a.unwrap_or(0.0).max(b.unwrap_or(0.0)).max(1.0),
+                }
+            }
+            _ => DEFAULT_SELECTIVITY,
+        },
+        Expression::In { expr, list, negated } => {
+            let column = scope.column(expr);
+            let matched: f64 = list.iter()
+                .map(|item| match item {
+                    Expression::Literal(value) => comparison(column, &BinaryOperator::Equal, value),
+                    _ => DEFAULT_EQUALITY_SELECTIVITY,
+                })
+                .sum();
+            negate(matched.min(1.0), *negated, column)
+        }
+        Expression::Between { expr, low, high, negated } => {
+            let column = scope.column(expr);
+            let range = match (low.as_ref(), high.as_ref(), column) {
+                (Expression::Literal(low), Expression::Literal(high), Some((stats, rows))) => {
+                    match (fraction_below(stats, low), fraction_below(stats, high)) {
+                        (Some(low), Some(high)) => (high - low).max(0.0) * non_null_fraction(stats, rows),
+                        _ => DEFAULT_SELECTIVITY,
+                    }
+                }
+                _ => DEFAULT_SELECTIVITY,
+            };
+            negate(range, *negated, column)
+        }
+        Expression::IsNull(expr) => scope.column(expr)
+            .map_or(DEFAULT_EQUALITY_SELECTIVITY, |(stats, rows)| 1.0 - non_null_fraction(stats, rows)),
+        Expression::IsNotNull(expr) => scope.column(expr)
+            .map_or(1.0 - DEFAULT_EQUALITY_SELECTIVITY, |(stats, rows)| non_null_fraction(stats, rows)),
+        Expression::Literal(Value::Boolean(true)) => 1.0,
+        Expression::Literal(Value::Boolean(false) | Value::Null) => 0.0,
+        _ => DEFAULT_SELECTIVITY,
+    };
+    estimate.clamp(0.0, 1.0)
+}
+
+/// `列 op 常量` 的选择性
+fn comparison(column: Option<(&ColumnStatistics, usize)>, op: &BinaryOperator, value: &Value) -> f64 {
+    if value.is_null() {
+        return 0.0;
+    }
+    let Some((stats, rows)) = column else {
+        return match op {
+            BinaryOperator::Equal => DEFAULT_EQUALITY_SELECTIVITY,
+            BinaryOperator::NotEqual => 1.0 - DEFAULT_EQUALITY_SELECTIVITY,
+            _ => DEFAULT_SELECTIVITY,
+        };
+    };
+    let non_null = non_null_fraction(stats, rows);
+    let equal = match (&stats.min, &stats.max) {
+        (Some(min), Some(max)) if value.partial_cmp(min) == Some(Ordering::Less) || value.partial_cmp(max) == Some(Ordering::Greater) => 0.0,
+        _ if stats.distinct_count == 0 => 0.0,
+        _ => non_null / stats.distinct_count as f64,
+    };
+    let below = fraction_below(stats, value);
+    match op {
+        BinaryOperator::Equal => equal,
+        BinaryOperator::NotEqual => non_null - equal,
+        BinaryOperator::LessThan => below.map_or(DEFAULT_SELECTIVITY, |below| below * non_null),
+        BinaryOperator::LessEqual => below.map_or(DEFAULT_SELECTIVITY, |below| below * non_null + equal),
+        BinaryOperator::GreaterThan => below.map_or(DEFAULT_SELECTIVITY, |below| (1.0 - below) * non_null - equal),
+        BinaryOperator::GreaterEqual => below.map_or(DEFAULT_SELECTIVITY, |below| (1.0 - below) * non_null),
+        _ => DEFAULT_SELECTIVITY,
+    }
+}
+
+/// 非 NULL 值所占的比例
+fn non_null_fraction(stats: &ColumnStatistics, rows: usize) -> f64 {
+    if rows == 0 {
+        return 0.0;
+    }
+    1.0 - stats.null_count as f64 / rows as f64
+}
+
+/// NOT IN / NOT BETWEEN：非 NULL 的行中不满足原条件的部分
+fn negate(selectivity: f64, negated: bool, column: Option<(&ColumnStatistics, usize)>) -> f64 {
+    match (negated, column) {
+        (false, _) => selectivity,
+        (true, Some((stats, rows))) => non_null_fraction(stats, rows) - selectivity,
+        (true, None) => 1.0 - selectivity,
+    }
+}
+
+/// 非 NULL 值中小于 value 的比例，由直方图在桶内线性插值；value 与列的值不可比较时为 None
+fn fraction_below(stats: &ColumnStatistics, value: &Value) -> Option<f64> {
+    let bounds = &stats.histogram;
+    let (first, last) = (bounds.first()?, bounds.last()?);
+    if value.partial_cmp(first)? != Ordering::Greater {
+        return Some(0.0);
+    }
+    if value.partial_cmp(last)? == Ordering::Greater {
+        return Some(1.0);
+    }
+    // 第一个上界不小于 value 的桶
+    let bucket = bounds.windows(2).position(|pair| value <= &pair[1])?;
+    let within = match (numeric(&bounds[bucket]), numeric(&bounds[bucket + 1]), numeric(value)) {
+        (Some(low), Some(high), Some(value)) if high > low => (value - low) / (high - low),
+        _ => 0.5,
+    };
+    Some((bucket as f64 + within) / (bounds.len() - 1) as f64)
+}
+
+fn numeric(value: &Value) -> Option<f64> {
+    match value {
+        Value::Integer(i) => Some(*i as f64),
+        Value::BigInt(i) => Some(*i as f64),
+        Value::Float(f) => Some(*f as f64),
+        Value::Double(d) => Some(*d),
+        Value::Decimal(d) => Some(d.to_f64()),
+        _ => None,
+    }
+}
+
+/// `常量 op 列` 改写成 `列 op' 常量` 时的运算符
+fn flip(op: &BinaryOperator) -> Option<BinaryOperator> {
+    Some(match op {
+        BinaryOperator::Equal => BinaryOperator::Equal,
+        BinaryOperator::NotEqual => BinaryOperator::NotEqual,
+        BinaryOperator::LessThan => BinaryOperator::GreaterThan,
+        BinaryOperator::LessEqual => BinaryOperator::GreaterEqual,
+        BinaryOperator::GreaterThan => BinaryOperator::LessThan,
+        BinaryOperator::GreaterEqual => BinaryOperator::LessEqual,
+        _ => return None,
+    })
+}
+
+fn is_column(expr: &Expression) -> bool {
+    matches!(expr, Expression::Column(_) | Expression::QualifiedColumn { .. })
+}
+
+fn column_name(expr: &Expression) -> Option<&str> {
+    match expr {
+        Expression::Column(name) | Expression::QualifiedColumn { column: name, .. } => Some(name),
+        _ => None,
+    }
+}
+
+/// AND 连接的各个条件
+fn conjuncts(condition: &Expression) -> Vec<&Expression> {
+    match condition {
+        Expression::BinaryOp { left, op: BinaryOperator::And, right } => {
+            let mut parts = conjuncts(left);
+            parts.extend(conjuncts(right));
+            parts
+        }
+        condition => vec![condition],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::statistics;
+    use crate::sql::planner::{IndexAccess, SortKey};
+    use crate::types::{ColumnDefinition, DataType, Schema, Tuple};
+
+    /// 表 t 有 100 行：n 为 0..100，每 5 行一个 NULL 的 c 取 4 个值；has_stats 为假时不提供统计
+    struct StatsCatalog {
+        stats: TableStatistics,
+        has_stats: bool,
+    }
+
+    impl StatsCatalog {
+        fn new(has_stats: bool) -> Self {
+            let schema = Schema::new(vec![
+                ColumnDefinition::new("n".to_string(), DataType::Integer, true),
+                ColumnDefinition::new("c".to_string(), DataType::Integer, true),
+            ]);
+            let rows: Vec<Tuple> = (0..100)
+                .map(|i| Tuple::new(vec![
+                    Value::Integer(i),
+                    if i % 5 == 0 { Value::Null } else { Value::Integer(i % 4) },
+                ]))
+                .collect();
+            Self { stats: statistics::analyze(&schema, &rows), has_stats }
+        }
+    }
+
+    impl AccessPathCatalog for StatsCatalog {
+        fn table_row_count(&self, _table_name: &str) -> Option<usize> {
+            Some(100)
+        }
+
+        fn index_access(&self, _table_name: &str, _filter: &Expression) -> Option<IndexAccess> {
+            None
+        }
+
+        fn table_statistics(&self, _table_name: &str) -> Option<&TableStatistics> {
+            self.has_stats.then_some(&self.stats)
+        }
+    }
+
+    fn rows(sql: &str, catalog: &dyn AccessPathCatalog) -> f64 {
+        let filter = match crate::sql::parse_sql(&format!("SELECT * FROM t WHERE {}", sql)).unwrap() {
+            crate::sql::Statement::Select { where_clause: Some(filter), .. } => filter,
+            other => panic!("Expected SELECT, got {:?}", other),
+        };
+        let scan = ExecutionPlan::TableScan { table_name: "t".to_string(), schema: Schema::new(vec![]), filter: Some(filter) };
+        estimate(&scan, catalog).rows.round()
+    }
+
+    #[test]
+    fn test_selectivity() {
+        let catalog = StatsCatalog::new(true);
+        assert_eq!(rows("n = 7", &catalog), 1.0);
+        assert_eq!(rows("n = 500", &catalog), 0.0);
+        assert_eq!(rows("n < 30", &catalog), 31.0);
+        assert_eq!(rows("30 > n", &catalog), 31.0);
+        assert_eq!(rows("n BETWEEN 20 AND 60", &catalog), 40.0);
+        assert_eq!(rows("n NOT BETWEEN 20 AND 60", &catalog), 60.0);
+        assert_eq!(rows("c = 1", &catalog), 20.0);
+        assert_eq!(rows("c IS NULL", &catalog), 20.0);
+        assert_eq!(rows("c IN (1, 2)", &catalog), 40.0);
+        assert_eq!(rows("n < 30 AND c = 1", &catalog), 6.0);
+        assert_eq!(rows("n < 30 OR n >= 90", &catalog), 37.0);
+
+        // 没有统计时用默认选择性
+        let catalog = StatsCatalog::new(false);
+        assert_eq!(rows("n = 7", &catalog), 10.0);
+        assert_eq!(rows("n < 30", &catalog), 33.0);
+        assert_eq!(rows("n = 7 AND c = 1", &catalog), 1.0);
+    }
+
+    #[test]
+    fn test_join_estimates() {
+        let scan = |table: &str| Box::new(ExecutionPlan::TableScan { table_name: table.to_string(), schema: Schema::new(vec![]), filter: None });
+        let t_c = Expression::QualifiedColumn { table: "t".to_string(), column: "c".to_string() };
+        let u_c = Expression::QualifiedColumn { table: "u".to_string(), column: "c".to_string() };
+        let condition = Expression::BinaryOp { left: Box::new(t_c.clone()), op: BinaryOperator::Equal, right: Box::new(u_c.clone()) };
+        assert_eq!(equi_join_keys(&condition).len(), 1);
+        let join = |algorithm| ExecutionPlan::Join {
+            left: scan("t"),
+            right: scan("u"),
+            join_type: JoinType::Inner,
+            condition: Some(condition.clone()),
+            algorithm,
+        };
+        // 两侧各 100 行，c 有 4 个不同值
+        let hash = estimate(&join(JoinAlgorithm::Hash), &StatsCatalog::new(true));
+        assert_eq!(hash.rows, 2500.0);
+        assert_eq!(hash.cost, 200.0 + 200.0 * HASH_ROW_COST);
+        let nested = estimate(&join(JoinAlgorithm::NestedLoop), &StatsCatalog::new(true));
+        assert_eq!(nested.cost, 200.0 + 10_000.0 * COMPARISON_COST);
+        // 没有统计时假定连接键不重复
+        assert_eq!(estimate(&join(JoinAlgorithm::Hash), &StatsCatalog::new(false)).rows, 100.0);
+
+        // 归并连接的键按左、右输入分开；扫描的输出无序，按键排序后有序
+        assert_eq!(merge_join_keys(&condition, &scan("u"), &scan("t")), Some(vec![(u_c.clone(), t_c.clone())]));
+        assert_eq!(merge_join_keys(&condition, &scan("t"), &scan("v")), None);
+        assert!(!ordered_on(&scan("t"), &[t_c.clone()]));
+        let sorted = ExecutionPlan::Sort { input: scan("t"), sort_keys: vec![SortKey { expression: t_c.clone(), descending: false }] };
+        assert!(ordered_on(&sorted, &[t_c]));
+        assert!(!ordered_on(&sorted, &[u_c]));
+        let merge = estimate(&join(JoinAlgorithm::Merge), &StatsCatalog::new(true));
+        assert_eq!(merge.cost, 200.0 + 200.0 * COMPARISON_COST);
+    }
+}
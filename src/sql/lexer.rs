@@ -2,6 +2,7 @@
 //!
 //! 将 SQL 输入标记化为用于解析的标记流。
 
+use crate::utils::encoding;
 use std::collections::HashMap;
 use thiserror::Error;
 
@@ -12,9 +13,14 @@ pub enum Token {
     Integer(i64),
     Float(f64),
     String(String),
+    /// 二进制字面量 X'48656C6C6F' 或 B64'SGVsbG8='
+    Binary(Vec<u8>),
     Boolean(bool),
     Null,
 
+    // 参数占位符（? 或 $n），从 1 开始编号
+    Parameter(usize),
+
     // 标识符和关键字
     Identifier(String),
 
@@ -45,6 +51,7 @@ pub enum Token {
     Between,
     Is,
     As,
+    Cast,
     Distinct,
     Order,
     By,
@@ -72,15 +79,22 @@ pub enum Token {
     If,
     Explain,
     Unique,
+    Conflict,
+    Do,
+    Nothing,
+    Returning,
+    Default,
 
     // 数据类型
     Int,
     BigInt,
     Float32,
     Double,
+    Decimal,
     Varchar,
     Char,
     Text,
+    Blob,
     Bool,
     Date,
     Timestamp,
@@ -97,6 +111,8 @@ pub enum Token {
     LessEqual,    // <=
     GreaterThan,  // >
     GreaterEqual, // >=
+    Arrow,        // ->
+    LongArrow,    // ->>
 
     // 标点符号
     LeftParen,    // (
@@ -142,6 +158,7 @@ pub enum TokenCategory {
 }
 
 /// SQL 词法分析器
+#[derive(Clone)]
 pub struct Lexer {
     input: Vec<char>,
     position: usize,
@@ -149,6 +166,8 @@ pub struct Lexer {
     keywords: HashMap<String, Token>,
     line: u32,
     column: u32,
+    /// 已分配的 ? 占位符个数
+    next_parameter: usize,
 }
 
 /// 词法分析器错误
@@ -162,6 +181,9 @@ pub enum LexError {
 
     #[error("无效的数字格式，位置 {0}")]
     InvalidNumber(usize),
+
+    #[error("无效的二进制字面量，位置 {0}")]
+    InvalidBinary(usize),
 }
 
 impl Lexer {
@@ -177,6 +199,7 @@ impl Lexer {
             keywords: HashMap::new(),
             line: 1,
             column: 1,
+            next_parameter: 0,
         };
 
         lexer.init_keywords();
@@ -212,6 +235,7 @@ impl Lexer {
             ("BETWEEN", Token::Between),
             ("IS", Token::Is),
             ("AS", Token::As),
+            ("CAST", Token::Cast),
             ("DISTINCT", Token::Distinct),
             ("ORDER", Token::Order),
             ("BY", Token::By),
@@ -239,14 +263,22 @@ impl Lexer {
             ("IF", Token::If),
             ("EXPLAIN", Token::Explain),
             ("UNIQUE", Token::Unique),
+            ("CONFLICT", Token::Conflict),
+            ("DO", Token::Do),
+            ("NOTHING", Token::Nothing),
+            ("RETURNING", Token::Returning),
+            ("DEFAULT", Token::Default),
             ("INT", Token::Int),
             ("INTEGER", Token::Int), // Support both INT and INTEGER
             ("BIGINT", Token::BigInt),
             ("FLOAT", Token::Float32),
             ("DOUBLE", Token::Double),
+            ("DECIMAL", Token::Decimal),
+            ("NUMERIC", Token::Decimal),
             ("VARCHAR", Token::Varchar),
             ("CHAR", Token::Char),
             ("TEXT", Token::Text),
+            ("BLOB", Token::Blob),
             ("BOOLEAN", Token::Bool),
             ("BOOL", Token::Bool),
             ("DATE", Token::Date),
@@ -318,6 +350,23 @@ impl Lexer {
         Err(LexError::UnterminatedString(self.position))
     }
 
+    /// 读取编号参数占位符（$n）
+    fn read_parameter(&mut self) -> Result<Token, LexError> {
+        let start_pos = self.position;
+        self.advance(); // skip '$'
+
+        let mut digits = String::new();
+        while let Some(ch) = self.current_char.filter(|c| c.is_ascii_digit()) {
+            digits.push(ch);
+            self.advance();
+        }
+
+        match digits.parse::<usize>() {
+            Ok(index) if index > 0 => Ok(Token::Parameter(index)),
+            _ => Err(LexError::InvalidNumber(start_pos)),
+        }
+    }
+
     /// 读取数字（整数或浮点数）
     fn read_number(&mut self) -> Result<Token, LexError> {
         let start_pos = self.position;
@@ -353,6 +402,21 @@ impl Lexer {
 
     /// 读取字符串字面量
     fn read_string(&mut self) -> Result<Token, LexError> {
+        self.read_quoted().map(Token::String)
+    }
+
+    /// 读取二进制字面量：跳过 X 或 B64 前缀后按引号内的十六进制或 base64 文本解码
+    fn read_binary(&mut self, prefix_len: usize, decode: fn(&str) -> Option<Vec<u8>>) -> Result<Token, LexError> {
+        let start_pos = self.position;
+        for _ in 0..prefix_len {
+            self.advance();
+        }
+        let text = self.read_quoted()?;
+        decode(&text).map(Token::Binary).ok_or(LexError::InvalidBinary(start_pos))
+    }
+
+    /// 读取单引号括起的文本，处理 '' 和反斜杠转义
+    fn read_quoted(&mut self) -> Result<String, LexError> {
         let start_pos = self.position;
         self.advance(); // 跳过开头引号
 
@@ -369,7 +433,7 @@ impl Lexer {
                 } else {
                     // 这是结束引号
                     self.advance(); // 跳过结束引号
-                    return Ok(Token::String(string_value));
+                    return Ok(string_value);
                 }
             } else if ch == '\\' {
                 // 处理反斜杠转义序列（非标准但常用）
@@ -442,14 +506,39 @@ impl Lexer {
                     // 字符串字面量
                     '\'' => return self.read_string(),
 
+                    // 二进制字面量，前缀与引号之间不能有空白
+                    'x' | 'X' if self.peek() == Some('\'') => return self.read_binary(1, encoding::decode_hex),
+                    'b' | 'B' if self.input.get(self.position + 1..self.position + 4) == Some(&['6', '4', '\''][..]) => {
+                        return self.read_binary(3, encoding::decode_base64);
+                    }
+
                     // 标识符和关键字
                     'a'..='z' | 'A'..='Z' | '_' => return Ok(self.read_identifier()),
 
+                    // 参数占位符
+                    '?' => {
+                        self.advance();
+                        self.next_parameter += 1;
+                        return Ok(Token::Parameter(self.next_parameter));
+                    }
+                    '$' if self.peek().is_some_and(|c| c.is_ascii_digit()) => {
+                        return self.read_parameter();
+                    }
+
                     // 运算符和标点符号
                     '+' => {
                         self.advance();
                         return Ok(Token::Plus);
                     }
+                    '-' if self.peek() == Some('>') => {
+                        self.advance();
+                        self.advance();
+                        if self.current_char == Some('>') {
+                            self.advance();
+                            return Ok(Token::LongArrow);
+                        }
+                        return Ok(Token::Arrow);
+                    }
                     '-' => {
                         self.advance();
                         return Ok(Token::Minus);
@@ -636,6 +725,7 @@ impl Lexer {
             | Token::Between
             | Token::Is
             | Token::As
+            | Token::Cast
             | Token::Distinct
             | Token::Order
             | Token::By
@@ -663,21 +753,28 @@ impl Lexer {
             | Token::If
             | Token::Explain
             | Token::Unique
+            | Token::Conflict
+            | Token::Do
+            | Token::Nothing
+            | Token::Returning
+            | Token::Default
             | Token::Int
             | Token::BigInt
             | Token::Float32
             | Token::Double
+            | Token::Decimal
             | Token::Varchar
             | Token::Char
             | Token::Text
+            | Token::Blob
             | Token::Bool
             | Token::Date
             | Token::Timestamp => TokenCategory::Keyword,
 
-            Token::Identifier(_) => TokenCategory::Identifier,
+            Token::Identifier(_) | Token::Parameter(_) => TokenCategory::Identifier,
             Token::Integer(_) => TokenCategory::Integer,
             Token::Float(_) => TokenCategory::Float,
-            Token::String(_) => TokenCategory::String,
+            Token::String(_) | Token::Binary(_) => TokenCategory::String,
             Token::Boolean(_) | Token::Null => TokenCategory::Keyword,
 
             Token::Plus
@@ -690,7 +787,9 @@ impl Lexer {
             | Token::LessThan
             | Token::LessEqual
             | Token::GreaterThan
-            | Token::GreaterEqual => TokenCategory::Operator,
+            | Token::GreaterEqual
+            | Token::Arrow
+            | Token::LongArrow => TokenCategory::Operator,
 
             Token::LeftParen
             | Token::RightParen
@@ -716,6 +815,40 @@ impl TokenInfo {
     }
 }
 
+/// 把以分号分隔的 SQL 脚本拆分为单条语句的文本
+///
+/// 按词法标记拆分，因此字符串和注释中的分号不会被当作分隔符；只含空白和注释的片段被忽略。
+/// 遇到无法分词的内容时，从当前语句开始的剩余文本整体作为最后一条语句，由解析器报告错误。
+pub fn split_statements(input: &str) -> Vec<String> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut lexer = Lexer::new(input);
+    let mut statements = Vec::new();
+    let mut start = 0;
+    let mut has_tokens = false;
+    loop {
+        let end = match lexer.next_token() {
+            Ok(Token::Semicolon) => lexer.position - 1,
+            Ok(Token::EOF) => chars.len(),
+            Ok(_) => {
+                has_tokens = true;
+                continue;
+            }
+            Err(_) => {
+                statements.push(chars[start..].iter().collect::<String>().trim().to_string());
+                return statements;
+            }
+        };
+        if has_tokens {
+            statements.push(chars[start..end].iter().collect::<String>().trim().to_string());
+        }
+        if end == chars.len() {
+            return statements;
+        }
+        start = lexer.position;
+        has_tokens = false;
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -757,6 +890,15 @@ mod tests {
         assert_eq!(lexer.next_token().unwrap(), Token::EOF);
     }
 
+    #[test]
+    fn test_parameters() {
+        let mut lexer = Lexer::new("? ? $7 $0");
+        assert_eq!(lexer.next_token().unwrap(), Token::Parameter(1));
+        assert_eq!(lexer.next_token().unwrap(), Token::Parameter(2));
+        assert_eq!(lexer.next_token().unwrap(), Token::Parameter(7));
+        assert!(lexer.next_token().is_err());
+    }
+
     #[test]
     fn test_strings() {
         let mut lexer = Lexer::new("'hello world' 'test\\nstring'");
@@ -771,6 +913,21 @@ mod tests {
         assert_eq!(lexer.next_token().unwrap(), Token::EOF);
     }
 
+    #[test]
+    fn test_binary_literals() {
+        let mut lexer = Lexer::new("X'48690a' b64'SGk=' x'' x 'ab' B64");
+        assert_eq!(lexer.next_token().unwrap(), Token::Binary(vec![0x48, 0x69, 0x0a]));
+        assert_eq!(lexer.next_token().unwrap(), Token::Binary(b"Hi".to_vec()));
+        assert_eq!(lexer.next_token().unwrap(), Token::Binary(vec![]));
+        // 前缀后有空白时是普通的标识符和字符串
+        assert_eq!(lexer.next_token().unwrap(), Token::Identifier("x".to_string()));
+        assert_eq!(lexer.next_token().unwrap(), Token::String("ab".to_string()));
+        assert_eq!(lexer.next_token().unwrap(), Token::Identifier("B64".to_string()));
+
+        assert!(matches!(Lexer::new("X'ABC'").next_token(), Err(LexError::InvalidBinary(0))));
+        assert!(matches!(Lexer::new("B64'S!'").next_token(), Err(LexError::InvalidBinary(0))));
+    }
+
     #[test]
     fn test_string_escaping() {
         // Test SQL standard single quote escaping
@@ -807,7 +964,7 @@ mod tests {
 
     #[test]
     fn test_operators() {
-        let mut lexer = Lexer::new("+ - * / = <> != < <= > >=");
+        let mut lexer = Lexer::new("+ - * / = <> != < <= > >= -> ->>");
         assert_eq!(lexer.next_token().unwrap(), Token::Plus);
         assert_eq!(lexer.next_token().unwrap(), Token::Minus);
         assert_eq!(lexer.next_token().unwrap(), Token::Multiply);
@@ -819,6 +976,8 @@ mod tests {
         assert_eq!(lexer.next_token().unwrap(), Token::LessEqual);
         assert_eq!(lexer.next_token().unwrap(), Token::GreaterThan);
         assert_eq!(lexer.next_token().unwrap(), Token::GreaterEqual);
+        assert_eq!(lexer.next_token().unwrap(), Token::Arrow);
+        assert_eq!(lexer.next_token().unwrap(), Token::LongArrow);
         assert_eq!(lexer.next_token().unwrap(), Token::EOF);
     }
 
@@ -902,4 +1061,15 @@ mod tests {
         assert_eq!(token_info.line, 2);
         assert_eq!(token_info.column, 1);
     }
+
+    #[test]
+    fn test_split_statements() {
+        assert_eq!(
+            split_statements("CREATE TABLE t (a INT);\nINSERT INTO t VALUES (1); SELECT ';' FROM t -- a; b\n;"),
+            vec!["CREATE TABLE t (a INT)", "INSERT INTO t VALUES (1)", "SELECT ';' FROM t -- a; b"]
+        );
+        assert_eq!(split_statements("SELECT 1"), vec!["SELECT 1"]);
+        assert_eq!(split_statements(" ; -- only a comment\n;"), Vec::<String>::new());
+        assert_eq!(split_statements("SELECT 1; SELECT 'open"), vec!["SELECT 1", "SELECT 'open"]);
+    }
 }
@@ -4,25 +4,28 @@
 //! 将 SQL 语句转换为可执行的查询计划。
 
 pub mod analyzer;
+pub mod cost;
 pub mod diagnostics;
 pub mod lexer;
 pub mod optimizer;
 pub mod parser;
 pub mod planner;
+pub mod rewrite;
 
 // Re-export commonly used types
 pub use analyzer::{AnalyzedStatement, SemanticAnalyzer, SemanticError};
 pub use diagnostics::{DiagnosticEngine, DiagnosticContext, Suggestion, enhance_error_message};
-pub use lexer::{LexError, Lexer, Token};
+pub use lexer::{split_statements, LexError, Lexer, Token};
 pub use optimizer::{QueryOptimizer, OptimizedPlan, OptimizationStats};
 pub use parser::{ParseError, Parser, Statement};
-pub use planner::{ExecutionPlan, PlanError, QueryPlanner};
+pub use planner::{AccessPathCatalog, ExecutionPlan, IndexAccess, JoinAlgorithm, PlanError, QueryPlanner};
+pub use rewrite::{apply_rewrite_rules, RewriteRule};
 
 /// 解析 SQL 字符串为语句
 pub fn parse_sql(input: &str) -> Result<Statement, ParseError> {
     let lexer = Lexer::new(input);
     let mut parser = Parser::new(lexer)?;
-    parser.parse_statement()
+    parser.parse_complete_statement()
 }
 
 /// 分析已解析语句的语义正确性
@@ -5,10 +5,16 @@
 //! - 投影下推
 //! - 连接重排序
 //! - 常量折叠
+//! - 访问路径选择（索引扫描或顺序扫描）
+//! - 连接算法和哈希连接建表侧的选择
+//!
+//! 访问路径和连接的选择按 [`cost`](crate::sql::cost) 中的代价模型比较，目录提供 ANALYZE 统计时据此估计行数。
 
+use crate::engine::executor::AggregateFunction;
 use crate::sql::parser::{Expression, BinaryOperator};
-use crate::sql::planner::{ExecutionPlan, PlanError, ProjectColumn};
-use crate::types::Value;
+use crate::sql::cost::{self, INDEX_SCAN_ROW_COST, SEQ_SCAN_ROW_COST};
+use crate::sql::planner::{AccessPathCatalog, ExecutionPlan, JoinAlgorithm, JoinType, PlanError, ProjectColumn, SortKey};
+use crate::types::{Schema, Value};
 use std::collections::HashSet;
 
 /// 查询优化器配置
@@ -32,6 +38,44 @@ pub struct OptimizationStats {
     pub constants_folded: usize,
     /// 重排序的连接数量
     pub joins_reordered: usize,
+    /// 改用索引扫描的表扫描数量
+    pub index_scans_chosen: usize,
+    /// 改用嵌套循环的连接数量
+    pub nested_loop_joins: usize,
+    /// 改用归并连接的连接数量
+    pub merge_joins: usize,
+    /// 改写为半连接或反连接的子查询数量
+    pub subqueries_decorrelated: usize,
+}
+
+/// 内连接的输入不超过此数时重排按子集动态规划求最优连接树，更多时贪心
+pub const JOIN_REORDER_DP_LIMIT: usize = 6;
+
+/// 重排连接时的候选连接树
+#[derive(Clone)]
+struct JoinCandidate {
+    plan: ExecutionPlan,
+    rows: f64,
+    cost: f64,
+    /// 树中选择了嵌套循环的连接数
+    nested_loops: usize,
+    /// 树中选择了归并连接的连接数
+    merges: usize,
+}
+
+impl JoinCandidate {
+    fn leaf(plan: ExecutionPlan, catalog: &dyn AccessPathCatalog) -> Self {
+        let estimate = cost::estimate(&plan, catalog);
+        Self { plan, rows: estimate.rows, cost: estimate.cost, nested_loops: 0, merges: 0 }
+    }
+
+    fn join(plan: ExecutionPlan, left: &Self, right: &Self, catalog: &dyn AccessPathCatalog) -> Self {
+        let nested_loop = matches!(plan, ExecutionPlan::Join { algorithm: JoinAlgorithm::NestedLoop, .. });
+        let merge = matches!(plan, ExecutionPlan::Join { algorithm: JoinAlgorithm::Merge, .. });
+        let nested_loops = left.nested_loops + right.nested_loops + usize::from(nested_loop);
+        let merges = left.merges + right.merges + usize::from(merge);
+        Self { nested_loops, merges, ..Self::leaf(plan, catalog) }
+    }
 }
 
 /// 带统计信息的优化执行计划
@@ -76,6 +120,8 @@ impl QueryOptimizer {
             optimized_plan = self.apply_constant_folding(optimized_plan, &mut stats)?;
         }
         
+        optimized_plan = self.decorrelate_subqueries(optimized_plan, &mut stats)?;
+        
         if self.enable_predicate_pushdown {
             optimized_plan = self.apply_predicate_pushdown(optimized_plan, &mut stats)?;
         }
@@ -90,6 +136,333 @@ impl QueryOptimizer {
         })
     }
 
+    /// 优化执行计划，并按目录中的索引、行数和统计为每个带过滤条件的表扫描选择访问路径，为每个连接选择算法和建表侧
+    pub fn optimize_with_catalog(
+        &self,
+        plan: ExecutionPlan,
+        catalog: &dyn AccessPathCatalog,
+    ) -> Result<OptimizedPlan, PlanError> {
+        let OptimizedPlan { plan, mut stats } = self.optimize(plan)?;
+        let required = self.get_plan_column_references(&plan);
+        let plan = self.choose_access_paths(plan, catalog, required.as_ref(), &mut stats);
+        let plan = self.choose_join_strategies(plan, catalog, &mut stats);
+        Ok(OptimizedPlan { plan, stats })
+    }
+
+    /// 预计由索引取出 estimated_rows 行时，索引扫描的代价是否不高于顺序扫描 table_rows 行的表
+    ///
+    /// 按索引取行要逐行定位，每行的代价高于顺序读取，取出的行较多时不如顺序读完整个表。
+    pub fn prefers_index_scan(&self, table_rows: usize, estimated_rows: usize) -> bool {
+        estimated_rows <= 1
+            || estimated_rows as f64 * INDEX_SCAN_ROW_COST <= table_rows as f64 * SEQ_SCAN_ROW_COST
+    }
+
+    /// 自底向上为每个连接选择算法和建表侧（见 [`Self::plan_join`]）；三个及以上输入的内连接先重排（见 [`Self::reorder_joins`]）
+    fn choose_join_strategies(
+        &self,
+        plan: ExecutionPlan,
+        catalog: &dyn AccessPathCatalog,
+        stats: &mut OptimizationStats,
+    ) -> ExecutionPlan {
+        if let Some(reordered) = self.reorder_joins(&plan, catalog, stats) {
+            return reordered;
+        }
+        let mut recurse = |input: Box<ExecutionPlan>| Box::new(self.choose_join_strategies(*input, catalog, stats));
+        match plan {
+            ExecutionPlan::Join { left, right, join_type, condition, .. } => {
+                let (left, right) = (recurse(left), recurse(right));
+                let (join, swapped) = self.plan_join(left, right, join_type, condition, catalog);
+                stats.joins_reordered += usize::from(swapped);
+                match join {
+                    ExecutionPlan::Join { algorithm: JoinAlgorithm::NestedLoop, .. } => stats.nested_loop_joins += 1,
+                    ExecutionPlan::Join { algorithm: JoinAlgorithm::Merge, .. } => stats.merge_joins += 1,
+                    _ => {}
+                }
+                join
+            }
+            ExecutionPlan::Project { input, columns } => ExecutionPlan::Project { input: recurse(input), columns },
+            ExecutionPlan::Filter { input, condition } => ExecutionPlan::Filter { input: recurse(input), condition },
+            ExecutionPlan::Apply { input, subquery, operand, negated } => {
+                let input = recurse(input);
+                ExecutionPlan::Apply { input, subquery: recurse(subquery), operand, negated }
+            }
+            ExecutionPlan::Sort { input, sort_keys } => ExecutionPlan::Sort { input: recurse(input), sort_keys },
+            ExecutionPlan::Limit { input, count, offset } => ExecutionPlan::Limit { input: recurse(input), count, offset },
+            ExecutionPlan::GroupBy { input, group_expressions, aggregate_functions } => {
+                ExecutionPlan::GroupBy { input: recurse(input), group_expressions, aggregate_functions }
+            }
+            plan => plan,
+        }
+    }
+
+    /// 连接两个输入：估计行数较少的输入放在左侧，作为哈希连接的建表侧（外连接随之交换 LEFT / RIGHT）；
+    /// 有等值条件时在哈希连接和嵌套循环中取代价低的，否则为嵌套循环。条件全部是两侧列的等值时还考虑归并连接，
+    /// 它的代价包括为尚未按键有序的输入排序，选中时在这些输入上加 Sort。返回连接及两侧是否交换
+    fn plan_join(
+        &self,
+        mut left: Box<ExecutionPlan>,
+        mut right: Box<ExecutionPlan>,
+        mut join_type: JoinType,
+        condition: Option<Expression>,
+        catalog: &dyn AccessPathCatalog,
+    ) -> (ExecutionPlan, bool) {
+        let (mut left_rows, mut right_rows) = (cost::estimate(&left, catalog).rows, cost::estimate(&right, catalog).rows);
+        // 半连接和反连接的两侧不对称，不能交换
+        let swapped = right_rows < left_rows && !matches!(join_type, JoinType::Semi | JoinType::Anti);
+        if swapped {
+            std::mem::swap(&mut left, &mut right);
+            std::mem::swap(&mut left_rows, &mut right_rows);
+            join_type = match join_type {
+                JoinType::Left => JoinType::Right,
+                JoinType::Right => JoinType::Left,
+                join_type => join_type,
+            };
+        }
+        let hashable = condition.as_ref().is_some_and(|condition| !cost::equi_join_keys(condition).is_empty());
+        let algorithm = if hashable
+            && cost::join_cost(&JoinAlgorithm::Hash, left_rows, right_rows)
+                <= cost::join_cost(&JoinAlgorithm::NestedLoop, left_rows, right_rows)
+        {
+            JoinAlgorithm::Hash
+        } else {
+            JoinAlgorithm::NestedLoop
+        };
+
+        let merge_keys = condition.as_ref().and_then(|condition| cost::merge_join_keys(condition, &left, &right));
+        if let Some(keys) = merge_keys {
+            let (left_keys, right_keys): (Vec<Expression>, Vec<Expression>) = keys.into_iter().unzip();
+            let (left_sorted, right_sorted) = (cost::ordered_on(&left, &left_keys), cost::ordered_on(&right, &right_keys));
+            let sorting = |sorted: bool, rows: f64| if sorted { 0.0 } else { cost::sort_cost(rows) };
+            let merge_cost = cost::join_cost(&JoinAlgorithm::Merge, left_rows, right_rows)
+                + sorting(left_sorted, left_rows)
+                + sorting(right_sorted, right_rows);
+            if merge_cost < cost::join_cost(&algorithm, left_rows, right_rows) {
+                let sort = |input: Box<ExecutionPlan>, sorted: bool, keys: Vec<Expression>| match sorted {
+                    true => input,
+                    false => Box::new(ExecutionPlan::Sort {
+                        input,
+                        sort_keys: keys.into_iter().map(|expression| SortKey { expression, descending: false }).collect(),
+                    }),
+                };
+                let (left, right) = (sort(left, left_sorted, left_keys), sort(right, right_sorted, right_keys));
+                return (ExecutionPlan::Join { left, right, join_type, condition, algorithm: JoinAlgorithm::Merge }, swapped);
+            }
+        }
+        (ExecutionPlan::Join { left, right, join_type, condition, algorithm }, swapped)
+    }
+
+    /// 重排三个及以上输入的内连接树（可带其上的 WHERE 过滤），使估计的总代价最低
+    ///
+    /// 把树拆成输入和按 AND 拆开的条件：连接条件与 WHERE 中涉及多个输入的条件都放到恰好连接它们所在输入的那次连接上，
+    /// 只涉及一个输入的条件放在该输入上。输入不超过 [`JOIN_REORDER_DP_LIMIT`] 个时按子集动态规划求出最便宜的连接树
+    /// （包括非左深的树），更多时从行数最少的输入开始，每步贪心地连接代价最低的下一个输入。
+    /// 没有条件相连的两部分只能做笛卡尔积，代价很高，因此会尽量避开。
+    /// 不是这样的连接树，或条件中有非限定列（无法确定它属于哪个输入）时不重排，返回 None。
+    fn reorder_joins(
+        &self,
+        plan: &ExecutionPlan,
+        catalog: &dyn AccessPathCatalog,
+        stats: &mut OptimizationStats,
+    ) -> Option<ExecutionPlan> {
+        let (filter, tree) = match plan {
+            ExecutionPlan::Filter { condition, input } => (Some(condition), input.as_ref()),
+            plan => (None, plan),
+        };
+        let mut leaves = Vec::new();
+        let mut join_conjuncts = Vec::new();
+        self.flatten_inner_joins(tree, &mut leaves, &mut join_conjuncts);
+        if leaves.len() < 3 {
+            return None;
+        }
+
+        // 每个条件涉及的输入（位集合）；连接条件必须都能确定
+        let leaf_tables: Vec<Vec<&str>> = leaves.iter().map(|leaf| cost::plan_tables(leaf)).collect();
+        let leaf_mask = |expr: &Expression| -> Option<usize> {
+            let tables = self.get_referenced_tables(expr)?;
+            tables.iter().try_fold(0, |mask, table| {
+                let leaf = leaf_tables.iter().position(|names| names.contains(&table.as_str()))?;
+                Some(mask | 1 << leaf)
+            })
+        };
+        let mut conjuncts = Vec::new();
+        let mut remaining = Vec::new();
+        for conjunct in join_conjuncts {
+            match leaf_mask(&conjunct)? {
+                // 不涉及任何列的条件与放在哪次内连接上无关
+                0 => remaining.push(conjunct),
+                mask => conjuncts.push((mask, conjunct)),
+            }
+        }
+        // WHERE 中涉及多个输入的条件参与连接，其余留在连接树之上
+        for conjunct in filter.map(|filter| self.split_conjuncts(filter)).unwrap_or_default() {
+            match leaf_mask(&conjunct) {
+                Some(mask) if mask.count_ones() > 1 => conjuncts.push((mask, conjunct)),
+                _ => remaining.push(conjunct),
+            }
+        }
+
+        let mut candidates: Vec<JoinCandidate> = Vec::with_capacity(leaves.len());
+        for (i, leaf) in leaves.iter().enumerate() {
+            let mut leaf = self.choose_join_strategies((*leaf).clone(), catalog, stats);
+            let local: Vec<Expression> = conjuncts.iter()
+                .filter(|(mask, _)| *mask == 1 << i)
+                .map(|(_, conjunct)| conjunct.clone())
+                .collect();
+            if let Ok(condition) = self.combine_predicates(local) {
+                leaf = ExecutionPlan::Filter { condition, input: Box::new(leaf) };
+            }
+            candidates.push(JoinCandidate::leaf(leaf, catalog));
+        }
+        let conjuncts: Vec<(usize, Expression)> = conjuncts.into_iter().filter(|(mask, _)| mask.count_ones() > 1).collect();
+        let join = |left: &JoinCandidate, right: &JoinCandidate, left_mask: usize, right_mask: usize| {
+            // 两侧各自已包含的条件之外，跨越两侧且不涉及其他输入的条件
+            let spanning: Vec<Expression> = conjuncts.iter()
+                .filter(|(mask, _)| mask & !(left_mask | right_mask) == 0 && mask & left_mask != 0 && mask & right_mask != 0)
+                .map(|(_, conjunct)| conjunct.clone())
+                .collect();
+            let condition = self.combine_predicates(spanning).ok();
+            let (plan, _) = self.plan_join(Box::new(left.plan.clone()), Box::new(right.plan.clone()), JoinType::Inner, condition, catalog);
+            JoinCandidate::join(plan, left, right, catalog)
+        };
+
+        let n = candidates.len();
+        let joined = if n <= JOIN_REORDER_DP_LIMIT {
+            let mut best: Vec<Option<JoinCandidate>> = vec![None; 1 << n];
+            for (i, candidate) in candidates.into_iter().enumerate() {
+                best[1 << i] = Some(candidate);
+            }
+            for mask in 1..(1usize << n) {
+                if mask.count_ones() < 2 {
+                    continue;
+                }
+                // 每种划分只看一次：左部分包含 mask 的最低位
+                let lowest = mask & mask.wrapping_neg();
+                let mut sub = (mask - 1) & mask;
+                while sub > 0 {
+                    let rest = mask ^ sub;
+                    if sub & lowest != 0 {
+                        if let (Some(left), Some(right)) = (&best[sub], &best[rest]) {
+                            let candidate = join(left, right, sub, rest);
+                            if best[mask].as_ref().is_none_or(|current| candidate.cost < current.cost) {
+                                best[mask] = Some(candidate);
+                            }
+                        }
+                    }
+                    sub = (sub - 1) & mask;
+                }
+            }
+            best.pop().flatten().expect("every subset has a join tree")
+        } else {
+            let mut remaining_leaves: Vec<(usize, JoinCandidate)> = candidates.into_iter().enumerate().collect();
+            let first = (0..remaining_leaves.len())
+                .min_by(|&a, &b| remaining_leaves[a].1.rows.total_cmp(&remaining_leaves[b].1.rows))
+                .expect("at least three inputs");
+            let (first, mut current) = remaining_leaves.swap_remove(first);
+            let mut mask = 1 << first;
+            while !remaining_leaves.is_empty() {
+                let (position, next) = remaining_leaves.iter().enumerate()
+                    .map(|(position, (i, leaf))| (position, join(&current, leaf, mask, 1 << i)))
+                    .min_by(|(_, a), (_, b)| a.cost.total_cmp(&b.cost))
+                    .expect("remaining inputs");
+                mask |= 1 << remaining_leaves.swap_remove(position).0;
+                current = next;
+            }
+            current
+        };
+
+        if leaf_tables.concat() != cost::plan_tables(&joined.plan) {
+            stats.joins_reordered += 1;
+        }
+        stats.nested_loop_joins += joined.nested_loops;
+        stats.merge_joins += joined.merges;
+        Some(match self.combine_predicates(remaining) {
+            Ok(condition) => ExecutionPlan::Filter { condition, input: Box::new(joined.plan) },
+            Err(_) => joined.plan,
+        })
+    }
+
+    /// 内连接树的输入（不是内连接的节点）和各连接条件按 AND 拆开的部分
+    fn flatten_inner_joins<'a>(&self, plan: &'a ExecutionPlan, leaves: &mut Vec<&'a ExecutionPlan>, conjuncts: &mut Vec<Expression>) {
+        match plan {
+            ExecutionPlan::Join { left, right, join_type: JoinType::Inner, condition, .. } => {
+                self.flatten_inner_joins(left, leaves, conjuncts);
+                self.flatten_inner_joins(right, leaves, conjuncts);
+                conjuncts.extend(condition.iter().flat_map(|condition| self.split_conjuncts(condition)));
+            }
+            leaf => leaves.push(leaf),
+        }
+    }
+
+    /// 把可由索引回答且足够有选择性的过滤扫描替换为索引扫描，其余保持顺序扫描
+    ///
+    /// required 为单表查询用到的全部列；它们都在索引中时改用仅索引扫描。
+    fn choose_access_paths(
+        &self,
+        plan: ExecutionPlan,
+        catalog: &dyn AccessPathCatalog,
+        required: Option<&HashSet<String>>,
+        stats: &mut OptimizationStats,
+    ) -> ExecutionPlan {
+        let mut recurse = |input: Box<ExecutionPlan>| Box::new(self.choose_access_paths(*input, catalog, required, stats));
+        match plan {
+            ExecutionPlan::TableScan { table_name, schema, filter: Some(filter) } => {
+                self.index_scan(table_name, schema, filter, catalog, required, stats)
+            }
+            // 谓词下推没有越过排序和分页时，过滤条件仍在扫描之上
+            ExecutionPlan::Filter { condition, input } => match *input {
+                ExecutionPlan::TableScan { table_name, schema, filter: None } => {
+                    self.index_scan(table_name, schema, condition, catalog, required, stats)
+                }
+                input => ExecutionPlan::Filter { condition, input: recurse(Box::new(input)) },
+            },
+            ExecutionPlan::Project { input, columns } => ExecutionPlan::Project { input: recurse(input), columns },
+            ExecutionPlan::Apply { input, subquery, operand, negated } => {
+                let input = recurse(input);
+                ExecutionPlan::Apply { input, subquery: recurse(subquery), operand, negated }
+            }
+            ExecutionPlan::Sort { input, sort_keys } => ExecutionPlan::Sort { input: recurse(input), sort_keys },
+            ExecutionPlan::Limit { input, count, offset } => ExecutionPlan::Limit { input: recurse(input), count, offset },
+            ExecutionPlan::GroupBy { input, group_expressions, aggregate_functions } => {
+                ExecutionPlan::GroupBy { input: recurse(input), group_expressions, aggregate_functions }
+            }
+            ExecutionPlan::Join { left, right, join_type, condition, algorithm } => {
+                let left = recurse(left);
+                ExecutionPlan::Join { left, right: recurse(right), join_type, condition, algorithm }
+            }
+            plan => plan,
+        }
+    }
+
+    /// 带过滤条件的表扫描：有足够有选择性的索引时为索引扫描，否则为顺序扫描
+    fn index_scan(
+        &self,
+        table_name: String,
+        schema: crate::types::Schema,
+        filter: Expression,
+        catalog: &dyn AccessPathCatalog,
+        required: Option<&HashSet<String>>,
+        stats: &mut OptimizationStats,
+    ) -> ExecutionPlan {
+        let access = catalog.index_access(&table_name, &filter).filter(|access| {
+            let table_rows = catalog.table_row_count(&table_name).unwrap_or(0);
+            self.prefers_index_scan(table_rows, access.estimated_rows)
+        });
+        match access {
+            Some(access) => {
+                stats.index_scans_chosen += 1;
+                let covered = required.is_some_and(|required| required.iter().all(|column| access.columns.contains(column)));
+                let (index_name, condition, filter, estimated_rows) =
+                    (access.index_name, Some(access.condition), Some(filter), access.estimated_rows);
+                if covered {
+                    ExecutionPlan::IndexOnlyScan { table_name, schema, index_name, condition, filter, estimated_rows }
+                } else {
+                    ExecutionPlan::IndexScan { table_name, schema, index_name, condition, filter, estimated_rows }
+                }
+            }
+            None => ExecutionPlan::TableScan { table_name, schema, filter: Some(filter) },
+        }
+    }
+
     /// 应用常量折叠优化
     fn apply_constant_folding(
         &self,
@@ -136,7 +509,7 @@ impl QueryOptimizer {
         match plan {
             ExecutionPlan::Filter { condition, input } => {
                 match *input {
-                    ExecutionPlan::Join { left, right, condition: join_condition, join_type } => {
+                    ExecutionPlan::Join { left, right, condition: join_condition, join_type, algorithm } => {
                         // Analyze which predicates can be pushed down
                         let pushable_predicates = self.analyze_pushable_predicates(&condition)?;
                         
@@ -149,10 +522,16 @@ impl QueryOptimizer {
                             let left_tables = self.get_plan_tables(&left);
                             let right_tables = self.get_plan_tables(&right);
                             
-                            if predicate_tables.iter().all(|t| left_tables.contains(t)) {
+                            // 只下推到不会补 NULL 的一侧；引用非限定列的谓词无法确定所属的表，留在连接之上
+                            let within = |tables: &HashSet<String>| {
+                                predicate_tables.as_ref().is_some_and(|referenced| {
+                                    !referenced.is_empty() && referenced.iter().all(|t| tables.contains(t))
+                                })
+                            };
+                            if within(&left_tables) && matches!(join_type, JoinType::Inner | JoinType::Left | JoinType::Semi | JoinType::Anti) {
                                 left_predicates.push(predicate);
                                 stats.predicates_pushed += 1;
-                            } else if predicate_tables.iter().all(|t| right_tables.contains(t)) {
+                            } else if within(&right_tables) && matches!(join_type, JoinType::Inner | JoinType::Right) {
                                 right_predicates.push(predicate);
                                 stats.predicates_pushed += 1;
                             } else {
@@ -180,11 +559,16 @@ impl QueryOptimizer {
                             });
                         }
                         
+                        // 输入本身是连接时继续向下推
+                        let new_left = Box::new(self.apply_predicate_pushdown(*new_left, stats)?);
+                        let new_right = Box::new(self.apply_predicate_pushdown(*new_right, stats)?);
+                        
                         let join_plan = ExecutionPlan::Join {
                             left: new_left,
                             right: new_right,
                             condition: join_condition,
                             join_type,
+                            algorithm,
                         };
                         
                         if remaining_predicates.is_empty() {
@@ -222,31 +606,181 @@ impl QueryOptimizer {
         }
     }
 
-    /// 应用投影下推优化
+    /// 应用投影下推优化：收集计划各节点用到的列，把每个扫描的输出裁剪为其中属于该表的列
     fn apply_projection_pushdown(
         &self,
         plan: ExecutionPlan,
         stats: &mut OptimizationStats,
     ) -> Result<ExecutionPlan, PlanError> {
-        match plan {
-            ExecutionPlan::Project { columns, input } => {
-                let required_columns = self.get_required_columns_from_projections(&columns);
-                
-                // Try to push projection into the input
-                let optimized_input = self.push_projection_into_plan(*input, &required_columns, stats)?;
-                
-                Ok(ExecutionPlan::Project {
-                    columns,
-                    input: Box::new(optimized_input),
-                })
+        let mut required = HashSet::new();
+        self.collect_plan_columns(&plan, &mut required);
+        self.push_projection_into_plan(plan, &required, stats)
+    }
+
+    /// 把 IN / EXISTS 子查询（[`ExecutionPlan::Apply`]）改写为半连接或反连接，子查询不再对外层的每一行执行一次
+    ///
+    /// 只改写简单的子查询 `SELECT ... FROM ... [WHERE ...]`（没有分组、排序和分页）：WHERE 中只引用子查询自身表的
+    /// 条件留在子查询一侧，引用外层列的相关条件和 IN 的 `左侧 = 子查询列` 成为连接条件。
+    fn decorrelate_subqueries(
+        &self,
+        plan: ExecutionPlan,
+        stats: &mut OptimizationStats,
+    ) -> Result<ExecutionPlan, PlanError> {
+        let recurse = |input: Box<ExecutionPlan>, stats: &mut OptimizationStats| -> Result<Box<ExecutionPlan>, PlanError> {
+            Ok(Box::new(self.decorrelate_subqueries(*input, stats)?))
+        };
+        Ok(match plan {
+            ExecutionPlan::Apply { input, subquery, operand, negated } => {
+                let (input, subquery) = (recurse(input, stats)?, recurse(subquery, stats)?);
+                match self.semi_join(&input, &subquery, operand.as_ref(), negated) {
+                    Some(join) => {
+                        stats.subqueries_decorrelated += 1;
+                        join
+                    }
+                    None => ExecutionPlan::Apply { input, subquery, operand, negated },
+                }
             }
-            _ => {
-                // Recursively apply to child plans
-                self.apply_projection_pushdown_recursive(plan, stats)
+            ExecutionPlan::Project { input, columns } => ExecutionPlan::Project { input: recurse(input, stats)?, columns },
+            ExecutionPlan::Filter { input, condition } => ExecutionPlan::Filter { input: recurse(input, stats)?, condition },
+            ExecutionPlan::Sort { input, sort_keys } => ExecutionPlan::Sort { input: recurse(input, stats)?, sort_keys },
+            ExecutionPlan::Limit { input, count, offset } => ExecutionPlan::Limit { input: recurse(input, stats)?, count, offset },
+            ExecutionPlan::GroupBy { input, group_expressions, aggregate_functions } => {
+                ExecutionPlan::GroupBy { input: recurse(input, stats)?, group_expressions, aggregate_functions }
+            }
+            ExecutionPlan::Join { left, right, join_type, condition, algorithm } => {
+                let left = recurse(left, stats)?;
+                ExecutionPlan::Join { left, right: recurse(right, stats)?, join_type, condition, algorithm }
+            }
+            plan => plan,
+        })
+    }
+
+    /// 子查询对应的半连接（negated 时为反连接）；子查询不是简单的 SELECT ... FROM ... WHERE 时为 None
+    ///
+    /// NOT IN 在任一侧出现 NULL 时与反连接的结果不同，只在两侧都是 NOT NULL 列时改写。
+    fn semi_join(
+        &self,
+        input: &ExecutionPlan,
+        subquery: &ExecutionPlan,
+        operand: Option<&Expression>,
+        negated: bool,
+    ) -> Option<ExecutionPlan> {
+        let ExecutionPlan::Project { input: body, columns } = subquery else {
+            return None;
+        };
+        let (from, condition) = match body.as_ref() {
+            ExecutionPlan::Filter { input, condition } => (input.as_ref(), Some(condition)),
+            from => (from, None),
+        };
+        if !Self::is_join_tree(from) {
+            return None;
+        }
+        let (mut inner, mut outer) = (Vec::new(), Vec::new());
+        Self::scan_schemas(from, &mut inner);
+        Self::scan_schemas(input, &mut outer);
+        // 没有表别名，子查询与外层用了同一张表时连接条件中分不清两层的列
+        if inner.iter().any(|(table, _)| outer.iter().any(|(candidate, _)| candidate == table)) {
+            return None;
+        }
+        // 子查询中的非限定列先在子查询自己的表中解析
+        let qualify = |expr: &Expression| Self::qualify_columns(&Self::qualify_columns(expr, &inner), &outer);
+
+        let mut local = Vec::new();
+        let mut correlated = Vec::new();
+        for conjunct in condition.map(|condition| self.split_conjuncts(condition)).unwrap_or_default() {
+            if conjunct.contains_subquery() {
+                return None;
+            }
+            let mut columns = HashSet::new();
+            Self::collect_qualified_columns(&conjunct, &mut columns);
+            let is_local = columns.iter().all(|(table, name)| match table {
+                Some(table) => inner.iter().any(|(candidate, _)| candidate == table),
+                None => inner.iter().any(|(_, schema)| schema.columns.iter().any(|column| column.name == *name)),
+            });
+            if is_local {
+                local.push(conjunct);
+            } else {
+                correlated.push(qualify(&conjunct));
+            }
+        }
+        if let Some(operand) = operand {
+            let [column] = columns.as_slice() else {
+                return None;
+            };
+            let (operand, column) = (Self::qualify_columns(operand, &outer), Self::qualify_columns(&column.expression, &inner));
+            if negated && !(Self::is_not_null(&operand, &outer) && Self::is_not_null(&column, &inner)) {
+                return None;
+            }
+            correlated.push(Expression::BinaryOp { left: Box::new(operand), op: BinaryOperator::Equal, right: Box::new(column) });
+        }
+
+        let right = match local.is_empty() {
+            true => from.clone(),
+            false => ExecutionPlan::Filter { input: Box::new(from.clone()), condition: self.combine_predicates(local).ok()? },
+        };
+        Some(ExecutionPlan::Join {
+            left: Box::new(input.clone()),
+            right: Box::new(right),
+            join_type: if negated { JoinType::Anti } else { JoinType::Semi },
+            condition: self.combine_predicates(correlated).ok(),
+            algorithm: JoinAlgorithm::Hash,
+        })
+    }
+
+    /// 计划是否只由表扫描和连接组成
+    fn is_join_tree(plan: &ExecutionPlan) -> bool {
+        match plan {
+            ExecutionPlan::TableScan { .. } => true,
+            ExecutionPlan::Join { left, right, .. } => Self::is_join_tree(left) && Self::is_join_tree(right),
+            _ => false,
+        }
+    }
+
+    /// 计划中各扫描的表名和模式
+    fn scan_schemas<'p>(plan: &'p ExecutionPlan, scans: &mut Vec<(&'p str, &'p Schema)>) {
+        match plan {
+            ExecutionPlan::TableScan { table_name, schema, .. }
+            | ExecutionPlan::IndexScan { table_name, schema, .. }
+            | ExecutionPlan::IndexOnlyScan { table_name, schema, .. } => scans.push((table_name, schema)),
+            ExecutionPlan::Join { left, right, .. } => {
+                Self::scan_schemas(left, scans);
+                Self::scan_schemas(right, scans);
             }
+            ExecutionPlan::Project { input, .. }
+            | ExecutionPlan::Filter { input, .. }
+            | ExecutionPlan::Apply { input, .. }
+            | ExecutionPlan::Sort { input, .. }
+            | ExecutionPlan::Limit { input, .. }
+            | ExecutionPlan::GroupBy { input, .. } => Self::scan_schemas(input, scans),
+            _ => {}
         }
     }
 
+    /// 把属于 scans 中某张表的非限定列改写为 `表.列`，其余列不变
+    fn qualify_columns(expr: &Expression, scans: &[(&str, &Schema)]) -> Expression {
+        let mut expr = expr.clone();
+        expr.visit_mut(&mut |expr| {
+            if let Expression::Column(name) = expr {
+                let table = scans.iter().find(|(_, schema)| schema.columns.iter().any(|column| column.name == *name));
+                if let Some((table, _)) = table {
+                    *expr = Expression::QualifiedColumn { table: table.to_string(), column: name.clone() };
+                }
+            }
+        });
+        expr
+    }
+
+    /// 表达式是否为 scans 中某张表的 NOT NULL 列
+    fn is_not_null(expr: &Expression, scans: &[(&str, &Schema)]) -> bool {
+        let Expression::QualifiedColumn { table, column } = expr else {
+            return false;
+        };
+        scans.iter()
+            .filter(|(candidate, _)| candidate == table)
+            .flat_map(|(_, schema)| &schema.columns)
+            .any(|definition| definition.name == *column && !definition.nullable)
+    }
+
     /// 在表达式中折叠常量
     fn fold_constants_in_expression(&self, expr: Expression) -> Result<Expression, PlanError> {
         match expr {
@@ -373,7 +907,10 @@ impl QueryOptimizer {
                 columns.extend(self.get_column_references(left));
                 columns.extend(self.get_column_references(right));
             }
-            Expression::UnaryOp { expr, .. } => {
+            Expression::UnaryOp { expr, .. }
+            | Expression::Cast { expr, .. }
+            | Expression::IsNull(expr)
+            | Expression::IsNotNull(expr) => {
                 columns.extend(self.get_column_references(expr));
             }
             Expression::FunctionCall { args, .. } => {
@@ -381,17 +918,89 @@ impl QueryOptimizer {
                     columns.extend(self.get_column_references(arg));
                 }
             }
-            _ => {} // Other expressions don't reference columns
+            Expression::In { expr, list, .. } => {
+                columns.extend(self.get_column_references(expr));
+                for item in list {
+                    columns.extend(self.get_column_references(item));
+                }
+            }
+            Expression::Between { expr, low, high, .. } => {
+                for e in [expr, low, high] {
+                    columns.extend(self.get_column_references(e));
+                }
+            }
+            Expression::Like { expr, pattern, escape, .. } => {
+                columns.extend(self.get_column_references(expr));
+                columns.extend(self.get_column_references(pattern));
+                if let Some(escape) = escape {
+                    columns.extend(self.get_column_references(escape));
+                }
+            }
+            Expression::InSubquery { expr, .. } => {
+                columns.extend(self.get_column_references(expr));
+            }
+            Expression::Literal(_) | Expression::Default | Expression::Parameter(_) | Expression::Exists(_) => {}
         }
         columns
     }
 
+    /// 单表查询计划用到的全部列；含连接或其他节点时为 None
+    fn get_plan_column_references(&self, plan: &ExecutionPlan) -> Option<HashSet<String>> {
+        let mut columns = HashSet::new();
+        match plan {
+            ExecutionPlan::TableScan { filter, .. } => {
+                if let Some(filter) = filter {
+                    columns.extend(self.get_column_references(filter));
+                }
+            }
+            ExecutionPlan::Project { input, columns: projections } => {
+                columns.extend(self.get_required_columns_from_projections(projections));
+                columns.extend(self.get_plan_column_references(input)?);
+            }
+            ExecutionPlan::Filter { input, condition } => {
+                columns.extend(self.get_column_references(condition));
+                columns.extend(self.get_plan_column_references(input)?);
+            }
+            ExecutionPlan::Sort { input, sort_keys } => {
+                for key in sort_keys {
+                    columns.extend(self.get_column_references(&key.expression));
+                }
+                columns.extend(self.get_plan_column_references(input)?);
+            }
+            ExecutionPlan::Limit { input, .. } => {
+                columns.extend(self.get_plan_column_references(input)?);
+            }
+            ExecutionPlan::GroupBy { input, group_expressions, aggregate_functions } => {
+                for expr in group_expressions {
+                    columns.extend(self.get_column_references(expr));
+                }
+                for function in aggregate_functions {
+                    match function {
+                        AggregateFunction::Count => {}
+                        AggregateFunction::Sum(column)
+                        | AggregateFunction::Avg(column)
+                        | AggregateFunction::Min(column)
+                        | AggregateFunction::Max(column)
+                        | AggregateFunction::StringAgg { column, .. } => {
+                            columns.insert(column.clone());
+                        }
+                    }
+                }
+                columns.extend(self.get_plan_column_references(input)?);
+            }
+            _ => return None,
+        }
+        Some(columns)
+    }
+
 
 
     /// 获取执行计划引用的表
     fn get_plan_tables(&self, plan: &ExecutionPlan) -> HashSet<String> {
         match plan {
-            ExecutionPlan::TableScan { table_name, .. } => {
+            ExecutionPlan::TableScan { table_name, .. }
+            | ExecutionPlan::IndexScan { table_name, .. }
+            | ExecutionPlan::IndexOnlyScan { table_name, .. } => {
                 let mut tables = HashSet::new();
                 tables.insert(table_name.clone());
                 tables
@@ -409,17 +1018,51 @@ impl QueryOptimizer {
         }
     }
 
-    /// 获取表达式中引用的表
-    fn get_referenced_tables(&self, _expr: &Expression) -> HashSet<String> {
-        // Simplified implementation - would need table resolution context
-        HashSet::new()
+    /// 获取表达式中经 `表.列` 引用的表；含非限定列时无法确定，为 None
+    fn get_referenced_tables(&self, expr: &Expression) -> Option<HashSet<String>> {
+        let mut tables = HashSet::new();
+        let children: Vec<&Expression> = match expr {
+            Expression::QualifiedColumn { table, .. } => {
+                tables.insert(table.clone());
+                Vec::new()
+            }
+            Expression::Column(_) => return None,
+            Expression::BinaryOp { left, right, .. } => vec![left, right],
+            Expression::UnaryOp { expr, .. }
+            | Expression::Cast { expr, .. }
+            | Expression::IsNull(expr)
+            | Expression::IsNotNull(expr) => vec![expr],
+            Expression::FunctionCall { args, .. } => args.iter().collect(),
+            Expression::In { expr, list, .. } => std::iter::once(expr.as_ref()).chain(list).collect(),
+            Expression::Between { expr, low, high, .. } => vec![expr, low, high],
+            Expression::Like { expr, pattern, escape, .. } => {
+                std::iter::once(expr.as_ref()).chain(Some(pattern.as_ref())).chain(escape.as_deref()).collect()
+            }
+            Expression::Literal(_) | Expression::Default | Expression::Parameter(_) => Vec::new(),
+            // 子查询中的引用不在外层的作用域中
+            Expression::InSubquery { .. } | Expression::Exists(_) => return None,
+        };
+        for child in children {
+            tables.extend(self.get_referenced_tables(child)?);
+        }
+        Some(tables)
     }
 
-    /// 分析哪些谓词可以下推
+    /// 分析哪些谓词可以下推：按 AND 拆开，各部分分别下推
     fn analyze_pushable_predicates(&self, condition: &Expression) -> Result<Vec<Expression>, PlanError> {
-        // For now, treat the entire condition as one predicate
-        // In a full implementation, this would split AND conditions
-        Ok(vec![condition.clone()])
+        Ok(self.split_conjuncts(condition))
+    }
+
+    /// 按 AND 拆开条件
+    fn split_conjuncts(&self, condition: &Expression) -> Vec<Expression> {
+        match condition {
+            Expression::BinaryOp { left, op: BinaryOperator::And, right } => {
+                let mut conjuncts = self.split_conjuncts(left);
+                conjuncts.extend(self.split_conjuncts(right));
+                conjuncts
+            }
+            condition => vec![condition.clone()],
+        }
     }
 
     /// 使用 AND 组合多个谓词
@@ -455,7 +1098,7 @@ impl QueryOptimizer {
                     input: Box::new(optimized_input),
                 })
             }
-            ExecutionPlan::Join { left, right, condition, join_type } => {
+            ExecutionPlan::Join { left, right, condition, join_type, algorithm } => {
                 let optimized_left = self.apply_predicate_pushdown(*left, stats)?;
                 let optimized_right = self.apply_predicate_pushdown(*right, stats)?;
                 Ok(ExecutionPlan::Join {
@@ -463,39 +1106,181 @@ impl QueryOptimizer {
                     right: Box::new(optimized_right),
                     condition,
                     join_type,
+                    algorithm,
                 })
             }
+            ExecutionPlan::Apply { input, subquery, operand, negated } => Ok(ExecutionPlan::Apply {
+                input: Box::new(self.apply_predicate_pushdown(*input, stats)?),
+                subquery: Box::new(self.apply_predicate_pushdown(*subquery, stats)?),
+                operand,
+                negated,
+            }),
             _ => Ok(plan),
         }
     }
 
-    /// 递归地向子计划应用投影下推
-    fn apply_projection_pushdown_recursive(
-        &self,
-        plan: ExecutionPlan,
-        stats: &mut OptimizationStats,
-    ) -> Result<ExecutionPlan, PlanError> {
-        match plan {
-            ExecutionPlan::Filter { condition, input } => {
-                let optimized_input = self.apply_projection_pushdown(*input, stats)?;
-                Ok(ExecutionPlan::Filter {
-                    condition,
-                    input: Box::new(optimized_input),
-                })
+    /// 计划各节点用到的列，记为（限定表名，列名）；非限定列的表名为 None
+    fn collect_plan_columns(&self, plan: &ExecutionPlan, required: &mut HashSet<(Option<String>, String)>) {
+        let mut add = |expr: &Expression| Self::collect_qualified_columns(expr, required);
+        let inputs: Vec<&ExecutionPlan> = match plan {
+            ExecutionPlan::TableScan { filter, .. } => {
+                filter.iter().for_each(&mut add);
+                vec![]
             }
-            _ => Ok(plan),
+            ExecutionPlan::IndexScan { condition, filter, .. }
+            | ExecutionPlan::IndexOnlyScan { condition, filter, .. } => {
+                condition.iter().chain(filter.iter()).for_each(&mut add);
+                vec![]
+            }
+            ExecutionPlan::Project { input, columns } => {
+                columns.iter().for_each(|column| add(&column.expression));
+                vec![input]
+            }
+            ExecutionPlan::Filter { input, condition } => {
+                add(condition);
+                vec![input]
+            }
+            ExecutionPlan::Sort { input, sort_keys } => {
+                sort_keys.iter().for_each(|key| add(&key.expression));
+                vec![input]
+            }
+            ExecutionPlan::Limit { input, .. } => vec![input],
+            ExecutionPlan::GroupBy { input, group_expressions, aggregate_functions } => {
+                group_expressions.iter().for_each(&mut add);
+                for function in aggregate_functions {
+                    match function {
+                        AggregateFunction::Count => {}
+                        AggregateFunction::Sum(column)
+                        | AggregateFunction::Avg(column)
+                        | AggregateFunction::Min(column)
+                        | AggregateFunction::Max(column)
+                        | AggregateFunction::StringAgg { column, .. } => add(&Expression::Column(column.clone())),
+                    }
+                }
+                vec![input]
+            }
+            ExecutionPlan::Join { left, right, condition, .. } => {
+                condition.iter().for_each(&mut add);
+                vec![left, right]
+            }
+            ExecutionPlan::Apply { input, subquery, operand, .. } => {
+                operand.iter().for_each(&mut add);
+                vec![input, subquery]
+            }
+            _ => vec![],
+        };
+        for input in inputs {
+            self.collect_plan_columns(input, required);
+        }
+    }
+
+    /// 表达式中引用的列，记为（限定表名，列名）
+    fn collect_qualified_columns(expr: &Expression, columns: &mut HashSet<(Option<String>, String)>) {
+        match expr {
+            Expression::Column(name) => {
+                columns.insert((None, name.clone()));
+            }
+            Expression::QualifiedColumn { table, column } => {
+                columns.insert((Some(table.clone()), column.clone()));
+            }
+            Expression::BinaryOp { left, right, .. } => {
+                Self::collect_qualified_columns(left, columns);
+                Self::collect_qualified_columns(right, columns);
+            }
+            Expression::UnaryOp { expr, .. }
+            | Expression::Cast { expr, .. }
+            | Expression::IsNull(expr)
+            | Expression::IsNotNull(expr) => Self::collect_qualified_columns(expr, columns),
+            Expression::FunctionCall { args, .. } => {
+                args.iter().for_each(|arg| Self::collect_qualified_columns(arg, columns));
+            }
+            Expression::In { expr, list, .. } => {
+                Self::collect_qualified_columns(expr, columns);
+                list.iter().for_each(|item| Self::collect_qualified_columns(item, columns));
+            }
+            Expression::Between { expr, low, high, .. } => {
+                [expr, low, high].into_iter().for_each(|e| Self::collect_qualified_columns(e, columns));
+            }
+            Expression::Like { expr, pattern, escape, .. } => {
+                Self::collect_qualified_columns(expr, columns);
+                Self::collect_qualified_columns(pattern, columns);
+                escape.iter().for_each(|escape| Self::collect_qualified_columns(escape, columns));
+            }
+            Expression::InSubquery { .. } | Expression::Exists(_) => {
+                // 相关子查询可能引用外层的列，子查询中出现的列都保留
+                expr.clone().visit_mut(&mut |expr| match expr {
+                    Expression::Column(name) => {
+                        columns.insert((None, name.clone()));
+                    }
+                    Expression::QualifiedColumn { table, column } => {
+                        columns.insert((Some(table.clone()), column.clone()));
+                    }
+                    _ => {}
+                });
+            }
+            Expression::Literal(_) | Expression::Default | Expression::Parameter(_) => {}
         }
     }
 
-    /// 将投影需求推入计划
+    /// 将投影需求推入计划：各扫描只输出 required 中属于该表的列
     fn push_projection_into_plan(
         &self,
         plan: ExecutionPlan,
-        _required_columns: &HashSet<String>,
+        required: &HashSet<(Option<String>, String)>,
         stats: &mut OptimizationStats,
     ) -> Result<ExecutionPlan, PlanError> {
-        // Simplified implementation - would need more sophisticated column tracking
-        self.apply_projection_pushdown(plan, stats)
+        let mut recurse = |input: Box<ExecutionPlan>| -> Result<Box<ExecutionPlan>, PlanError> {
+            Ok(Box::new(self.push_projection_into_plan(*input, required, stats)?))
+        };
+        Ok(match plan {
+            ExecutionPlan::Project { input, columns } => ExecutionPlan::Project { input: recurse(input)?, columns },
+            ExecutionPlan::Filter { input, condition } => ExecutionPlan::Filter { input: recurse(input)?, condition },
+            ExecutionPlan::Sort { input, sort_keys } => ExecutionPlan::Sort { input: recurse(input)?, sort_keys },
+            ExecutionPlan::Limit { input, count, offset } => ExecutionPlan::Limit { input: recurse(input)?, count, offset },
+            ExecutionPlan::GroupBy { input, group_expressions, aggregate_functions } => {
+                ExecutionPlan::GroupBy { input: recurse(input)?, group_expressions, aggregate_functions }
+            }
+            ExecutionPlan::Join { left, right, join_type, condition, algorithm } => {
+                let left = recurse(left)?;
+                ExecutionPlan::Join { left, right: recurse(right)?, join_type, condition, algorithm }
+            }
+            ExecutionPlan::Apply { input, subquery, operand, negated } => {
+                let input = recurse(input)?;
+                ExecutionPlan::Apply { input, subquery: recurse(subquery)?, operand, negated }
+            }
+            ExecutionPlan::TableScan { table_name, schema, filter } => {
+                let schema = Self::prune_scan_schema(&table_name, schema, required, stats);
+                ExecutionPlan::TableScan { table_name, schema, filter }
+            }
+            plan => plan,
+        })
+    }
+
+    /// 只保留 required 中属于该表的列（非限定列按列名匹配）；一列都不需要（如 COUNT(*)）或全部需要时不变
+    fn prune_scan_schema(
+        table_name: &str,
+        schema: Schema,
+        required: &HashSet<(Option<String>, String)>,
+        stats: &mut OptimizationStats,
+    ) -> Schema {
+        let needed: Vec<usize> = schema.columns.iter().enumerate()
+            .filter(|(_, column)| {
+                required.iter().any(|(table, name)| *name == column.name && table.as_deref().is_none_or(|table| table == table_name))
+            })
+            .map(|(i, _)| i)
+            .collect();
+        if needed.is_empty() || needed.len() == schema.columns.len() {
+            return schema;
+        }
+        stats.projections_pushed += 1;
+        // 主键列全部保留时按新位置记录主键
+        let primary_key = schema.primary_key.as_ref().and_then(|key| {
+            key.iter().map(|column| needed.iter().position(|i| i == column)).collect::<Option<Vec<_>>>()
+        });
+        Schema {
+            columns: needed.iter().map(|&i| schema.columns[i].clone()).collect(),
+            primary_key,
+        }
     }
 }
 
@@ -539,6 +1324,287 @@ mod tests {
         assert_eq!(folded, Expression::Literal(Value::Integer(-5)));
     }
     
+    /// 每张表 100 行；对 `col = 常量` 返回名为 `{表}_{列}` 的索引，预计行数取常量的值
+    struct FixedCatalog;
+
+    impl AccessPathCatalog for FixedCatalog {
+        fn table_row_count(&self, _table_name: &str) -> Option<usize> {
+            Some(100)
+        }
+
+        fn index_access(&self, table_name: &str, filter: &Expression) -> Option<crate::sql::planner::IndexAccess> {
+            match filter {
+                Expression::BinaryOp { left, op: BinaryOperator::Equal, right } => match (left.as_ref(), right.as_ref()) {
+                    (Expression::Column(column), Expression::Literal(Value::Integer(rows))) => Some(crate::sql::planner::IndexAccess {
+                        index_name: format!("{}_{}", table_name, column),
+                        condition: filter.clone(),
+                        estimated_rows: *rows as usize,
+                        columns: vec![column.clone()],
+                    }),
+                    _ => None,
+                },
+                _ => None,
+            }
+        }
+    }
+
+    #[test]
+    fn test_access_path_selection() {
+        let optimizer = QueryOptimizer::new();
+        let filtered = |rows: i32| ExecutionPlan::Limit {
+            input: Box::new(ExecutionPlan::Filter {
+                condition: Expression::BinaryOp {
+                    left: Box::new(Expression::Column("a".to_string())),
+                    op: BinaryOperator::Equal,
+                    right: Box::new(Expression::Literal(Value::Integer(rows))),
+                },
+                input: Box::new(ExecutionPlan::TableScan {
+                    table_name: "t".to_string(),
+                    schema: crate::types::Schema::new(vec![]),
+                    filter: None,
+                }),
+            }),
+            count: 10,
+            offset: None,
+        };
+        // 投影用到索引之外的列 b
+        let scan = |rows: i32| ExecutionPlan::Project {
+            input: Box::new(filtered(rows)),
+            columns: vec![ProjectColumn {
+                expression: Expression::Column("b".to_string()),
+                alias: None,
+                data_type: crate::types::DataType::Integer,
+            }],
+        };
+        let unproject = |plan: ExecutionPlan| match plan {
+            ExecutionPlan::Project { input, .. } => *input,
+            other => panic!("Expected Project, got {:?}", other),
+        };
+
+        let optimized = optimizer.optimize_with_catalog(scan(5), &FixedCatalog).unwrap();
+        assert_eq!(optimized.stats.index_scans_chosen, 1);
+        match unproject(optimized.plan) {
+            ExecutionPlan::Limit { input, .. } => match *input {
+                ExecutionPlan::IndexScan { index_name, estimated_rows, filter, .. } => {
+                    assert_eq!(index_name, "t_a");
+                    assert_eq!(estimated_rows, 5);
+                    assert!(filter.is_some());
+                }
+                other => panic!("Expected IndexScan, got {:?}", other),
+            },
+            other => panic!("Expected Limit, got {:?}", other),
+        }
+
+        // 取出的行太多时保持顺序扫描
+        let optimized = optimizer.optimize_with_catalog(scan(60), &FixedCatalog).unwrap();
+        assert_eq!(optimized.stats.index_scans_chosen, 0);
+        assert!(matches!(unproject(optimized.plan), ExecutionPlan::Limit { ref input, .. } if matches!(**input, ExecutionPlan::TableScan { filter: Some(_), .. })));
+
+        // 只用到索引中的列 a 时为仅索引扫描
+        let optimized = optimizer.optimize_with_catalog(filtered(5), &FixedCatalog).unwrap();
+        assert!(matches!(optimized.plan, ExecutionPlan::Limit { ref input, .. } if matches!(**input, ExecutionPlan::IndexOnlyScan { .. })));
+
+        assert!(optimizer.prefers_index_scan(0, 0));
+        assert!(optimizer.prefers_index_scan(2, 1));
+        assert!(optimizer.prefers_index_scan(100, 25));
+        assert!(!optimizer.prefers_index_scan(100, 26));
+    }
+
+    /// 全部条件写在最上层连接上的链式连接 t0 - t1 - ... - t{n-1}：原样执行时下层都是笛卡尔积
+    fn chain_join(n: usize) -> ExecutionPlan {
+        let column = |i: usize| Box::new(Expression::QualifiedColumn { table: format!("t{}", i), column: "id".to_string() });
+        let scan = |i: usize| Box::new(ExecutionPlan::TableScan {
+            table_name: format!("t{}", i),
+            schema: crate::types::Schema::new(vec![]),
+            filter: None,
+        });
+        let conditions = (1..n)
+            .map(|i| Expression::BinaryOp { left: column(i - 1), op: BinaryOperator::Equal, right: column(i) })
+            .collect();
+        let condition = QueryOptimizer::new().combine_predicates(conditions).unwrap();
+        let mut plan = *scan(0);
+        for i in 1..n {
+            plan = ExecutionPlan::Join {
+                left: Box::new(plan),
+                right: scan(i),
+                join_type: JoinType::Inner,
+                condition: (i == n - 1).then(|| condition.clone()),
+                algorithm: JoinAlgorithm::Hash,
+            };
+        }
+        plan
+    }
+
+    /// 连接树中每个连接都有条件（没有笛卡尔积），返回连接数
+    fn count_conditioned_joins(plan: &ExecutionPlan) -> usize {
+        match plan {
+            ExecutionPlan::Join { left, right, condition, .. } => {
+                assert!(condition.is_some(), "cross product in {:?}", plan);
+                1 + count_conditioned_joins(left) + count_conditioned_joins(right)
+            }
+            _ => 0,
+        }
+    }
+
+    #[test]
+    fn test_join_reordering() {
+        let optimizer = QueryOptimizer::new();
+        // 动态规划（4 个输入）和贪心（8 个输入）都避开笛卡尔积
+        for n in [4, JOIN_REORDER_DP_LIMIT + 2] {
+            let original = cost::estimate(&chain_join(n), &FixedCatalog);
+            let optimized = optimizer.optimize_with_catalog(chain_join(n), &FixedCatalog).unwrap();
+            assert_eq!(count_conditioned_joins(&optimized.plan), n - 1);
+            assert_eq!(cost::plan_tables(&optimized.plan).len(), n);
+            assert!(cost::estimate(&optimized.plan, &FixedCatalog).cost < original.cost);
+        }
+
+        // 两个输入不重排；条件中有非限定列时保持原来的连接树
+        let optimized = optimizer.optimize_with_catalog(chain_join(2), &FixedCatalog).unwrap();
+        assert_eq!(optimized.stats.joins_reordered, 0);
+        let unqualified = match chain_join(3) {
+            ExecutionPlan::Join { left, right, join_type, algorithm, .. } => ExecutionPlan::Join {
+                left,
+                right,
+                join_type,
+                condition: Some(Expression::Column("id".to_string())),
+                algorithm,
+            },
+            other => panic!("Expected Join, got {:?}", other),
+        };
+        let optimized = optimizer.optimize_with_catalog(unqualified, &FixedCatalog).unwrap();
+        match optimized.plan {
+            ExecutionPlan::Join { left, right, condition: Some(Expression::Column(_)), .. } => {
+                assert!([left, right].iter().any(|side| cost::plan_tables(side) == ["t0", "t1"]));
+            }
+            other => panic!("Expected the original join tree, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_projection_pushdown() {
+        let column = |table: &str, column: &str| Expression::QualifiedColumn { table: table.to_string(), column: column.to_string() };
+        let scan = |table: &str, columns: &[&str]| Box::new(ExecutionPlan::TableScan {
+            table_name: table.to_string(),
+            schema: crate::types::Schema::new(columns.iter()
+                .map(|name| crate::types::ColumnDefinition::new(name.to_string(), crate::types::DataType::Integer, true))
+                .collect()),
+            filter: None,
+        });
+        let scanned_columns = |plan: &ExecutionPlan| match plan {
+            ExecutionPlan::TableScan { schema, .. } => schema.columns.iter().map(|c| c.name.clone()).collect::<Vec<_>>(),
+            other => panic!("Expected TableScan, got {:?}", other),
+        };
+        // SELECT b.v FROM a JOIN b ON a.id = b.a_id WHERE a.k = 1
+        let plan = ExecutionPlan::Project {
+            input: Box::new(ExecutionPlan::Filter {
+                input: Box::new(ExecutionPlan::Join {
+                    left: scan("a", &["id", "k", "name"]),
+                    right: scan("b", &["id", "a_id", "v", "note"]),
+                    join_type: JoinType::Inner,
+                    condition: Some(Expression::BinaryOp {
+                        left: Box::new(column("a", "id")),
+                        op: BinaryOperator::Equal,
+                        right: Box::new(column("b", "a_id")),
+                    }),
+                    algorithm: JoinAlgorithm::Hash,
+                }),
+                condition: Expression::BinaryOp {
+                    left: Box::new(column("a", "k")),
+                    op: BinaryOperator::Equal,
+                    right: Box::new(Expression::Literal(Value::Integer(1))),
+                },
+            }),
+            columns: vec![ProjectColumn { expression: column("b", "v"), alias: None, data_type: crate::types::DataType::Integer }],
+        };
+
+        let optimized = QueryOptimizer::new().optimize(plan).unwrap();
+        assert_eq!(optimized.stats.projections_pushed, 2);
+        let mut scans = Vec::new();
+        let mut stack = vec![&optimized.plan];
+        while let Some(node) = stack.pop() {
+            match node {
+                ExecutionPlan::Project { input, .. } | ExecutionPlan::Filter { input, .. } => stack.push(input),
+                ExecutionPlan::Join { left, right, .. } => stack.extend([&**left, &**right]),
+                scan => scans.push(scanned_columns(scan)),
+            }
+        }
+        scans.sort();
+        assert_eq!(scans, vec![vec!["a_id", "v"], vec!["id", "k"]]);
+
+        // 非限定列按列名保留在每张有该列的表中；全部列都用到时不裁剪
+        let plan = ExecutionPlan::Project {
+            input: scan("a", &["id", "k"]),
+            columns: vec![
+                ProjectColumn { expression: Expression::Column("id".to_string()), alias: None, data_type: crate::types::DataType::Integer },
+                ProjectColumn { expression: Expression::Column("k".to_string()), alias: None, data_type: crate::types::DataType::Integer },
+            ],
+        };
+        let optimized = QueryOptimizer::new().optimize(plan).unwrap();
+        assert_eq!(optimized.stats.projections_pushed, 0);
+    }
+
+    #[test]
+    fn test_subquery_decorrelation() {
+        let column = |table: &str, column: &str| Expression::QualifiedColumn { table: table.to_string(), column: column.to_string() };
+        let equal = |left: Expression, right: Expression| Expression::BinaryOp { left: Box::new(left), op: BinaryOperator::Equal, right: Box::new(right) };
+        let scan = |table: &str, columns: &[(&str, bool)]| Box::new(ExecutionPlan::TableScan {
+            table_name: table.to_string(),
+            schema: crate::types::Schema::new(columns.iter()
+                .map(|(name, nullable)| crate::types::ColumnDefinition::new(name.to_string(), crate::types::DataType::Integer, *nullable))
+                .collect()),
+            filter: None,
+        });
+        let subquery = |select: Expression, condition: Option<Expression>| {
+            let from = scan("b", &[("a_id", false), ("v", true)]);
+            Box::new(ExecutionPlan::Project {
+                input: match condition {
+                    Some(condition) => Box::new(ExecutionPlan::Filter { input: from, condition }),
+                    None => from,
+                },
+                columns: vec![ProjectColumn { expression: select, alias: None, data_type: crate::types::DataType::Integer }],
+            })
+        };
+        let decorrelate = |subquery: Box<ExecutionPlan>, operand: Option<Expression>, negated: bool| {
+            let plan = ExecutionPlan::Apply { input: scan("a", &[("id", false), ("k", true)]), subquery, operand, negated };
+            QueryOptimizer::new().optimize(plan).unwrap()
+        };
+
+        // EXISTS (SELECT 1 FROM b WHERE b.a_id = a.id AND v > 1)：相关条件成为连接条件，v > 1 留在 b 一侧
+        let local = Expression::BinaryOp {
+            left: Box::new(Expression::Column("v".to_string())),
+            op: BinaryOperator::GreaterThan,
+            right: Box::new(Expression::Literal(Value::Integer(1))),
+        };
+        let condition = Expression::BinaryOp {
+            left: Box::new(equal(column("b", "a_id"), column("a", "id"))),
+            op: BinaryOperator::And,
+            right: Box::new(local.clone()),
+        };
+        let optimized = decorrelate(subquery(Expression::Literal(Value::Integer(1)), Some(condition)), None, false);
+        assert_eq!(optimized.stats.subqueries_decorrelated, 1);
+        match optimized.plan {
+            ExecutionPlan::Join { right, join_type: JoinType::Semi, condition, .. } => {
+                assert_eq!(condition, Some(equal(column("b", "a_id"), column("a", "id"))));
+                assert!(matches!(*right, ExecutionPlan::TableScan { filter: Some(ref filter), .. } if *filter == local));
+            }
+            other => panic!("Expected semi join, got {:?}", other),
+        }
+
+        // id NOT IN (SELECT a_id FROM b)：两列都不允许 NULL，改写为反连接并限定非限定列
+        let optimized = decorrelate(subquery(Expression::Column("a_id".to_string()), None), Some(Expression::Column("id".to_string())), true);
+        match optimized.plan {
+            ExecutionPlan::Join { join_type: JoinType::Anti, condition, .. } => {
+                assert_eq!(condition, Some(equal(column("a", "id"), column("b", "a_id"))));
+            }
+            other => panic!("Expected anti join, got {:?}", other),
+        }
+
+        // id NOT IN (SELECT v FROM b)：v 可能为 NULL，保留子查询
+        let optimized = decorrelate(subquery(Expression::Column("v".to_string()), None), Some(Expression::Column("id".to_string())), true);
+        assert_eq!(optimized.stats.subqueries_decorrelated, 0);
+        assert!(matches!(optimized.plan, ExecutionPlan::Apply { .. }));
+    }
+
     #[test]
     fn test_predicate_combination() {
         let optimizer = QueryOptimizer::new();
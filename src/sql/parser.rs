@@ -3,7 +3,8 @@
 //! SQL 语句的递归下降解析器。
 
 use crate::sql::lexer::{LexError, Lexer, Token};
-use crate::types::{DataType, Value};
+use crate::types::{decimal, DataType, Value};
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 /// SQL 语句的抽象语法树节点
@@ -27,6 +28,8 @@ pub enum Statement {
         table_name: String,
         columns: Option<Vec<String>>,
         values: Vec<Vec<Expression>>,
+        on_conflict: Option<OnConflict>,
+        returning: Option<SelectList>,
     },
     
     /// SELECT 语句
@@ -41,17 +44,21 @@ pub enum Statement {
         offset: Option<u64>,
     },
     
-    /// UPDATE 语句
+    /// UPDATE [ALL] 语句；ALL 表示有意修改全部行，安全模式下不带 WHERE 时必须写出
     Update {
         table_name: String,
         assignments: Vec<Assignment>,
         where_clause: Option<Expression>,
+        returning: Option<SelectList>,
+        all_rows: bool,
     },
     
-    /// DELETE 语句
+    /// DELETE [ALL] FROM 语句；ALL 的含义同 UPDATE
     Delete {
         table_name: String,
         where_clause: Option<Expression>,
+        returning: Option<SelectList>,
+        all_rows: bool,
     },
     
     /// CREATE INDEX 语句
@@ -60,6 +67,10 @@ pub enum Statement {
         table_name: String,
         columns: Vec<String>,
         is_unique: bool,
+        /// INCLUDE 列：随索引保存但不属于键，供仅索引扫描使用
+        include: Vec<String>,
+        /// WHERE 谓词：部分索引只包含满足谓词的行
+        where_clause: Option<Expression>,
     },
     
     /// DROP INDEX 语句
@@ -69,10 +80,175 @@ pub enum Statement {
         if_exists: bool,
     },
     
-    /// EXPLAIN 语句
+    /// SHOW INDEXES [FROM table]：列出索引及其键列、唯一性、大小和索引项数
+    ShowIndexes {
+        table_name: Option<String>,
+    },
+    
+    /// ANALYZE [table]：收集表（省略时为全部表）的统计并随表快照保存
+    Analyze {
+        table_name: Option<String>,
+    },
+    
+    /// REINDEX 语句：由表的行重建索引
+    Reindex {
+        target: ReindexTarget,
+    },
+    
+    /// EXPLAIN 语句；ANALYZE 时实际执行并报告各算子的行数和耗时
     Explain {
         statement: Box<Statement>,
+        analyze: bool,
+    },
+    
+    /// DRY RUN 修饰的 UPDATE/DELETE：只报告将受影响的行
+    DryRun {
+        statement: Box<Statement>,
+    },
+    
+    /// DEDUPLICATE TABLE 语句：删除完全重复的行，每组保留最早的一行
+    Deduplicate {
+        table_name: String,
+    },
+    
+    /// ALTER TABLE 语句
+    AlterTable {
+        table_name: String,
+        action: AlterTableAction,
+    },
+    
+    /// COPY table FROM 'file' [(选项, ...)]：从 CSV / NDJSON 文件导入
+    Copy {
+        table_name: String,
+        path: String,
+        options: CopyOptions,
+    },
+    
+    /// COPY (SELECT ...) TO 'file' [(选项, ...)]：把查询结果导出为 CSV 文件
+    CopyTo {
+        query: Box<Statement>,
+        path: String,
+        options: CopyOptions,
+    },
+    
+    /// ADVISE INDEXES：根据负载统计和列统计给出建索引建议
+    AdviseIndexes,
+    
+    /// CHECKPOINT：把全部表写入快照并截断 WAL
+    Checkpoint,
+    
+    /// BACKUP TO 'dir'：把数据目录的一致副本写入另一个目录
+    Backup {
+        path: String,
+    },
+    
+    /// SET CONSTRAINTS ALL DEFERRED | IMMEDIATE
+    SetConstraints {
+        deferred: bool,
+    },
+    
+    /// SET name {= | TO} value：修改运行时设置（名称小写，值为标识符、字符串或整数的文本）
+    SetVariable {
+        name: String,
+        value: String,
+    },
+    
+    /// CREATE [TEMP | TEMPORARY] VIEW name AS SELECT ...
+    CreateView {
+        view_name: String,
+        query: Box<Statement>,
+        temporary: bool,
     },
+    
+    /// DROP VIEW [IF EXISTS] name
+    DropView {
+        view_name: String,
+        if_exists: bool,
+    },
+    
+    /// ASSERT (SELECT ...) 比较运算符 值 / ASSERT [NOT] EXISTS (SELECT ...) [MESSAGE '说明']
+    Assert {
+        query: Box<Statement>,
+        condition: AssertCondition,
+        message: Option<String>,
+    },
+    
+    /// BEGIN [TRANSACTION | WORK] / START TRANSACTION
+    Begin,
+    
+    /// COMMIT / END [TRANSACTION | WORK]
+    Commit,
+    
+    /// ROLLBACK [TRANSACTION | WORK]
+    Rollback,
+}
+
+/// COPY 的文件格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CopyFormat {
+    Csv,
+    /// 每行一个 JSON 对象
+    Ndjson,
+}
+
+/// COPY 选项
+#[derive(Debug, Clone, PartialEq)]
+pub struct CopyOptions {
+    /// 文件格式；未指定时按扩展名判断（.ndjson / .jsonl 为 NDJSON，其余为 CSV）
+    pub format: Option<CopyFormat>,
+    /// CSV 第一行是否为列名
+    pub header: bool,
+    /// CSV 字段分隔符
+    pub delimiter: char,
+    /// 目标表不存在时是否根据文件内容推断列并建表
+    pub infer: bool,
+    /// COPY TO 写出的 CSV 字段何时加引号
+    pub quote: QuoteStyle,
+}
+
+/// CSV 字段的引号规则；NULL 总是写成不加引号的空字段，与导入时的约定一致
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum QuoteStyle {
+    /// 只给含分隔符、引号、换行或首尾空白的字段以及空字符串加引号
+    #[default]
+    Minimal,
+    /// 给全部非 NULL 字段加引号
+    All,
+    /// 给数值和布尔以外的非 NULL 字段加引号
+    NonNumeric,
+}
+
+impl Default for CopyOptions {
+    fn default() -> Self {
+        Self {
+            format: None,
+            header: false,
+            delimiter: ',',
+            infer: false,
+            quote: QuoteStyle::Minimal,
+        }
+    }
+}
+
+/// REINDEX 重建的对象
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReindexTarget {
+    /// REINDEX INDEX name ON table
+    Index { index_name: String, table_name: String },
+    /// REINDEX TABLE table：表上的全部索引（包括主键）
+    Table(String),
+}
+
+/// ALTER TABLE 的操作
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlterTableAction {
+    /// ADD [CONSTRAINT name] 表约束
+    AddConstraint {
+        name: Option<String>,
+        constraint: TableConstraint,
+    },
+    /// SET COMPRESSION {method | DEFAULT}：表的页压缩方式，None 表示使用全局配置
+    SetCompression { method: Option<String> },
 }
 
 /// CREATE TABLE 语句中的列定义
@@ -83,12 +259,14 @@ pub struct ColumnDef {
     pub nullable: bool,
     pub default: Option<Expression>,
     pub primary_key: bool,
+    pub unique: bool,
 }
 
 /// 表约束
 #[derive(Debug, Clone, PartialEq)]
 pub enum TableConstraint {
     PrimaryKey(Vec<String>),
+    Unique(Vec<String>),
     ForeignKey {
         columns: Vec<String>,
         referenced_table: String,
@@ -136,6 +314,8 @@ pub enum JoinType {
 pub struct OrderByExpr {
     pub expr: Expression,
     pub desc: bool,
+    /// NULLS FIRST 为 Some(true)，NULLS LAST 为 Some(false)；未指定时 NULL 视为最小值
+    pub nulls_first: Option<bool>,
 }
 
 /// UPDATE 赋值
@@ -145,8 +325,39 @@ pub struct Assignment {
     pub value: Expression,
 }
 
-/// 表达式
+/// INSERT ... ON CONFLICT 子句
+#[derive(Debug, Clone, PartialEq)]
+pub struct OnConflict {
+    /// 冲突目标列（为空时使用主键）
+    pub target: Vec<String>,
+    pub action: ConflictAction,
+}
+
+/// ASSERT 语句检查的条件
+#[derive(Debug, Clone, PartialEq)]
+pub enum AssertCondition {
+    /// 查询返回的单个值与期望值比较
+    Compare { op: BinaryOperator, expected: Expression },
+    /// 查询是否返回行（negated 为 NOT EXISTS）
+    Exists { negated: bool },
+}
+
+/// 发生冲突时执行的动作
 #[derive(Debug, Clone, PartialEq)]
+pub enum ConflictAction {
+    /// DO NOTHING：跳过冲突行
+    DoNothing,
+    /// DO UPDATE SET ...：更新已存在的行，可通过 excluded.列名 引用待插入的值
+    DoUpdate {
+        assignments: Vec<Assignment>,
+        where_clause: Option<Expression>,
+    },
+}
+
+/// 表达式
+///
+/// 部分索引的谓词随索引元数据保存，因此表达式可以序列化。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Expression {
     /// 字面量值
     Literal(Value),
@@ -179,23 +390,33 @@ pub enum Expression {
         args: Vec<Expression>,
     },
     
-    /// IN 表达式
+    /// IN 表达式（negated 为 NOT IN）
     In {
         expr: Box<Expression>,
         list: Vec<Expression>,
+        negated: bool,
     },
     
-    /// BETWEEN 表达式
+    /// BETWEEN 表达式（negated 为 NOT BETWEEN）
     Between {
         expr: Box<Expression>,
         low: Box<Expression>,
         high: Box<Expression>,
+        negated: bool,
     },
     
-    /// LIKE 表达式
+    /// LIKE 表达式（negated 为 NOT LIKE，escape 为 ESCAPE 指定的转义字符）
     Like {
         expr: Box<Expression>,
         pattern: Box<Expression>,
+        escape: Option<Box<Expression>>,
+        negated: bool,
+    },
+    
+    /// CAST(expr AS type) 类型转换
+    Cast {
+        expr: Box<Expression>,
+        data_type: DataType,
     },
     
     /// IS NULL 表达式
@@ -203,10 +424,30 @@ pub enum Expression {
     
     /// IS NOT NULL 表达式
     IsNotNull(Box<Expression>),
+    
+    /// INSERT VALUES 中的 DEFAULT 关键字
+    Default,
+    
+    /// 参数占位符（? 或 $n），编号从 1 开始
+    Parameter(usize),
+    
+    /// expr [NOT] IN (SELECT ...)；子查询只输出一列，可以引用外层查询的列（相关子查询）
+    ///
+    /// 子查询不能序列化，因此不能出现在部分索引的谓词中。
+    #[serde(skip)]
+    InSubquery {
+        expr: Box<Expression>,
+        subquery: Box<Statement>,
+        negated: bool,
+    },
+    
+    /// EXISTS (SELECT ...)；NOT EXISTS 解析为 NOT 一元运算
+    #[serde(skip)]
+    Exists(Box<Statement>),
 }
 
 /// 二元运算符
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum BinaryOperator {
     // 算术运算
     Add,
@@ -226,20 +467,209 @@ pub enum BinaryOperator {
     // 逻辑运算
     And,
     Or,
+
+    // JSON 取值：按键（字符串）或下标（整数）取出子文档，`->>` 得到文本
+    JsonExtract,
+    JsonExtractText,
 }
 
 /// 一元运算符
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum UnaryOperator {
     Not,
     Minus,
     Plus,
 }
 
+impl Statement {
+    /// 就地访问语句中的每个表达式及其子表达式（先访问外层再访问内层）
+    pub fn visit_expressions_mut(&mut self, f: &mut dyn FnMut(&mut Expression)) {
+        fn visit_select_list(list: &mut SelectList, f: &mut dyn FnMut(&mut Expression)) {
+            if let SelectList::Expressions(items) = list {
+                for item in items {
+                    item.expr.visit_mut(f);
+                }
+            }
+        }
+        fn visit_from(from: &mut FromClause, f: &mut dyn FnMut(&mut Expression)) {
+            if let FromClause::Join { left, right, condition, .. } = from {
+                visit_from(left, f);
+                visit_from(right, f);
+                if let Some(condition) = condition {
+                    condition.visit_mut(f);
+                }
+            }
+        }
+        fn visit_assignments(assignments: &mut [Assignment], f: &mut dyn FnMut(&mut Expression)) {
+            for assignment in assignments {
+                assignment.value.visit_mut(f);
+            }
+        }
+        
+        match self {
+            Statement::CreateTable { columns, .. } => {
+                for default in columns.iter_mut().filter_map(|column| column.default.as_mut()) {
+                    default.visit_mut(f);
+                }
+            }
+            Statement::Insert { values, on_conflict, returning, .. } => {
+                for expr in values.iter_mut().flatten() {
+                    expr.visit_mut(f);
+                }
+                if let Some(OnConflict { action: ConflictAction::DoUpdate { assignments, where_clause }, .. }) = on_conflict {
+                    visit_assignments(assignments, f);
+                    if let Some(where_clause) = where_clause {
+                        where_clause.visit_mut(f);
+                    }
+                }
+                if let Some(returning) = returning {
+                    visit_select_list(returning, f);
+                }
+            }
+            Statement::Select { select_list, from_clause, where_clause, group_by, having, order_by, .. } => {
+                visit_select_list(select_list, f);
+                if let Some(from) = from_clause {
+                    visit_from(from, f);
+                }
+                for expr in where_clause.iter_mut().chain(group_by.iter_mut().flatten()).chain(having.iter_mut()) {
+                    expr.visit_mut(f);
+                }
+                for item in order_by.iter_mut().flatten() {
+                    item.expr.visit_mut(f);
+                }
+            }
+            Statement::Update { assignments, where_clause, returning, .. } => {
+                visit_assignments(assignments, f);
+                if let Some(where_clause) = where_clause {
+                    where_clause.visit_mut(f);
+                }
+                if let Some(returning) = returning {
+                    visit_select_list(returning, f);
+                }
+            }
+            Statement::Delete { where_clause, returning, .. } => {
+                if let Some(where_clause) = where_clause {
+                    where_clause.visit_mut(f);
+                }
+                if let Some(returning) = returning {
+                    visit_select_list(returning, f);
+                }
+            }
+            Statement::Explain { statement, .. }
+            | Statement::DryRun { statement }
+            | Statement::CreateView { query: statement, .. }
+            | Statement::CopyTo { query: statement, .. } => statement.visit_expressions_mut(f),
+            Statement::Assert { query, condition, .. } => {
+                query.visit_expressions_mut(f);
+                if let AssertCondition::Compare { expected, .. } = condition {
+                    expected.visit_mut(f);
+                }
+            }
+            Statement::CreateIndex { where_clause, .. } => {
+                if let Some(where_clause) = where_clause {
+                    where_clause.visit_mut(f);
+                }
+            }
+            Statement::DropTable { .. }
+            | Statement::DropIndex { .. }
+            | Statement::Reindex { .. }
+            | Statement::ShowIndexes { .. }
+            | Statement::Analyze { .. }
+            | Statement::Deduplicate { .. }
+            | Statement::AlterTable { .. }
+            | Statement::Copy { .. }
+            | Statement::AdviseIndexes
+            | Statement::Checkpoint
+            | Statement::Backup { .. }
+            | Statement::SetConstraints { .. }
+            | Statement::SetVariable { .. }
+            | Statement::DropView { .. }
+            | Statement::Begin
+            | Statement::Commit
+            | Statement::Rollback => {}
+        }
+    }
+}
+
+impl Expression {
+    /// 就地访问表达式及其全部子表达式（先访问外层再访问内层）
+    pub fn visit_mut(&mut self, f: &mut dyn FnMut(&mut Expression)) {
+        f(self);
+        match self {
+            Expression::BinaryOp { left, right, .. } => {
+                left.visit_mut(f);
+                right.visit_mut(f);
+            }
+            Expression::UnaryOp { expr, .. }
+            | Expression::Cast { expr, .. }
+            | Expression::IsNull(expr)
+            | Expression::IsNotNull(expr) => expr.visit_mut(f),
+            Expression::FunctionCall { args, .. } => {
+                for arg in args {
+                    arg.visit_mut(f);
+                }
+            }
+            Expression::In { expr, list, .. } => {
+                expr.visit_mut(f);
+                for item in list {
+                    item.visit_mut(f);
+                }
+            }
+            Expression::Between { expr, low, high, .. } => {
+                expr.visit_mut(f);
+                low.visit_mut(f);
+                high.visit_mut(f);
+            }
+            Expression::Like { expr, pattern, escape, .. } => {
+                expr.visit_mut(f);
+                pattern.visit_mut(f);
+                if let Some(escape) = escape {
+                    escape.visit_mut(f);
+                }
+            }
+            Expression::InSubquery { expr, subquery, .. } => {
+                expr.visit_mut(f);
+                subquery.visit_expressions_mut(f);
+            }
+            Expression::Exists(subquery) => subquery.visit_expressions_mut(f),
+            Expression::Literal(_)
+            | Expression::Column(_)
+            | Expression::QualifiedColumn { .. }
+            | Expression::Default
+            | Expression::Parameter(_) => {}
+        }
+    }
+    
+    /// 表达式中是否含有子查询
+    pub fn contains_subquery(&self) -> bool {
+        match self {
+            Expression::InSubquery { .. } | Expression::Exists(_) => true,
+            Expression::BinaryOp { left, right, .. } => left.contains_subquery() || right.contains_subquery(),
+            Expression::UnaryOp { expr, .. }
+            | Expression::Cast { expr, .. }
+            | Expression::IsNull(expr)
+            | Expression::IsNotNull(expr) => expr.contains_subquery(),
+            Expression::FunctionCall { args, .. } => args.iter().any(Expression::contains_subquery),
+            Expression::In { expr, list, .. } => expr.contains_subquery() || list.iter().any(Expression::contains_subquery),
+            Expression::Between { expr, low, high, .. } => [expr, low, high].iter().any(|e| e.contains_subquery()),
+            Expression::Like { expr, pattern, escape, .. } => {
+                expr.contains_subquery() || pattern.contains_subquery() || escape.as_ref().is_some_and(|e| e.contains_subquery())
+            }
+            Expression::Literal(_)
+            | Expression::Column(_)
+            | Expression::QualifiedColumn { .. }
+            | Expression::Default
+            | Expression::Parameter(_) => false,
+        }
+    }
+}
+
 /// SQL 解析器
 pub struct Parser {
     lexer: Lexer,
     current_token: Token,
+    /// 已解析语句中出现的最大参数编号
+    parameter_count: usize,
 }
 
 /// 解析器错误
@@ -259,6 +689,9 @@ pub enum ParseError {
     
     #[error("不支持的特性: {0}")]
     UnsupportedFeature(String),
+    
+    #[error("无效的 {data_type} 常量 '{text}'")]
+    InvalidLiteral { data_type: DataType, text: String },
 }
 
 impl Parser {
@@ -268,15 +701,26 @@ impl Parser {
         Ok(Self {
             lexer,
             current_token,
+            parameter_count: 0,
         })
     }
     
+    /// 已解析语句的参数个数（按最大占位符编号计）
+    pub fn parameter_count(&self) -> usize {
+        self.parameter_count
+    }
+    
     /// 前进到下一个令牌
     fn advance(&mut self) -> Result<(), ParseError> {
         self.current_token = self.lexer.next_token()?;
         Ok(())
     }
     
+    /// 查看下一个令牌而不前进
+    fn peek_token(&self) -> Result<Token, ParseError> {
+        Ok(self.lexer.clone().next_token()?)
+    }
+    
     /// 检查当前令牌是否匹配期望值并前进
     fn expect(&mut self, expected: Token) -> Result<(), ParseError> {
         if std::mem::discriminant(&self.current_token) == std::mem::discriminant(&expected) {
@@ -294,11 +738,42 @@ impl Parser {
         match &self.current_token {
             Token::Create => self.parse_create_statement(),
             Token::Drop => self.parse_drop_statement(),
+            Token::Alter => self.parse_alter_statement(),
             Token::Select => self.parse_select_statement(),
             Token::Insert => self.parse_insert_statement(),
             Token::Update => self.parse_update_statement(),
             Token::Delete => self.parse_delete_statement(),
             Token::Explain => self.parse_explain_statement(),
+            Token::Set => self.parse_set_statement(),
+            Token::Identifier(word) if word.eq_ignore_ascii_case("DRY") => self.parse_dry_run_statement(),
+            Token::Identifier(word) if word.eq_ignore_ascii_case("DEDUPLICATE") => self.parse_deduplicate_statement(),
+            Token::Identifier(word) if word.eq_ignore_ascii_case("COPY") => self.parse_copy_statement(),
+            Token::Identifier(word) if word.eq_ignore_ascii_case("ADVISE") => self.parse_advise_statement(),
+            Token::Identifier(word) if word.eq_ignore_ascii_case("ASSERT") => self.parse_assert_statement(),
+            Token::Identifier(word) if word.eq_ignore_ascii_case("CHECKPOINT") => {
+                self.advance()?;
+                Ok(Statement::Checkpoint)
+            }
+            Token::Identifier(word) if word.eq_ignore_ascii_case("BACKUP") => self.parse_backup_statement(),
+            Token::Identifier(word) if word.eq_ignore_ascii_case("REINDEX") => self.parse_reindex_statement(),
+            Token::Identifier(word) if word.eq_ignore_ascii_case("SHOW") => self.parse_show_statement(),
+            Token::Identifier(word) if word.eq_ignore_ascii_case("ANALYZE") => {
+                self.advance()?;
+                let table_name = match &self.current_token {
+                    Token::Identifier(name) => {
+                        let name = name.clone();
+                        self.advance()?;
+                        Some(name)
+                    }
+                    _ => None,
+                };
+                Ok(Statement::Analyze { table_name })
+            }
+            Token::Identifier(word) if word.eq_ignore_ascii_case("BEGIN") => self.parse_transaction_statement(Statement::Begin),
+            Token::Identifier(word) if word.eq_ignore_ascii_case("START") => self.parse_start_transaction_statement(),
+            Token::Identifier(word) if word.eq_ignore_ascii_case("COMMIT") => self.parse_transaction_statement(Statement::Commit),
+            Token::End => self.parse_transaction_statement(Statement::Commit),
+            Token::Identifier(word) if word.eq_ignore_ascii_case("ROLLBACK") => self.parse_transaction_statement(Statement::Rollback),
             Token::EOF => Err(ParseError::UnexpectedEof),
             _ => Err(ParseError::UnexpectedToken {
                 expected: "SQL statement".to_string(),
@@ -307,6 +782,22 @@ impl Parser {
         }
     }
     
+    /// 解析一条完整的语句：末尾只能有分号，其余多出的令牌都是错误，
+    /// 以免写错位置的子句（如 `UPDATE ... DRY RUN`）被悄悄忽略
+    pub fn parse_complete_statement(&mut self) -> Result<Statement, ParseError> {
+        let statement = self.parse_statement()?;
+        while self.current_token == Token::Semicolon {
+            self.advance()?;
+        }
+        if self.current_token != Token::EOF {
+            return Err(ParseError::UnexpectedToken {
+                expected: "end of statement".to_string(),
+                found: self.current_token.clone(),
+            });
+        }
+        Ok(statement)
+    }
+    
     /// 解析 CREATE 语句
     fn parse_create_statement(&mut self) -> Result<Statement, ParseError> {
         self.expect(Token::Create)?;
@@ -314,13 +805,60 @@ impl Parser {
         match &self.current_token {
             Token::Table => self.parse_create_table(),
             Token::Index | Token::Unique => self.parse_create_index(),
+            Token::Identifier(word)
+                if word.eq_ignore_ascii_case("VIEW") || word.eq_ignore_ascii_case("TEMP") || word.eq_ignore_ascii_case("TEMPORARY") =>
+            {
+                self.parse_create_view()
+            }
             _ => Err(ParseError::UnexpectedToken {
-                expected: "TABLE or INDEX".to_string(),
+                expected: "TABLE, INDEX or VIEW".to_string(),
                 found: self.current_token.clone(),
             }),
         }
     }
     
+    /// 解析 CREATE [TEMP | TEMPORARY] VIEW name AS SELECT ...（TEMP、TEMPORARY 和 VIEW 都不是保留字）
+    fn parse_create_view(&mut self) -> Result<Statement, ParseError> {
+        let temporary = matches!(&self.current_token, Token::Identifier(word)
+            if word.eq_ignore_ascii_case("TEMP") || word.eq_ignore_ascii_case("TEMPORARY"));
+        if temporary {
+            self.advance()?;
+        }
+        match &self.current_token {
+            Token::Identifier(word) if word.eq_ignore_ascii_case("VIEW") => self.advance()?,
+            _ => {
+                return Err(ParseError::UnexpectedToken {
+                    expected: "VIEW".to_string(),
+                    found: self.current_token.clone(),
+                })
+            }
+        }
+        
+        let view_name = match &self.current_token {
+            Token::Identifier(name) => {
+                let name = name.clone();
+                self.advance()?;
+                name
+            }
+            _ => {
+                return Err(ParseError::UnexpectedToken {
+                    expected: "view name".to_string(),
+                    found: self.current_token.clone(),
+                })
+            }
+        };
+        self.expect(Token::As)?;
+        if self.current_token != Token::Select {
+            return Err(ParseError::UnexpectedToken {
+                expected: "SELECT".to_string(),
+                found: self.current_token.clone(),
+            });
+        }
+        let query = Box::new(self.parse_select_statement()?);
+        
+        Ok(Statement::CreateView { view_name, query, temporary })
+    }
+    
     /// 解析 CREATE TABLE 语句
     fn parse_create_table(&mut self) -> Result<Statement, ParseError> {
         self.expect(Token::Table)?;
@@ -355,6 +893,8 @@ impl Parser {
                 columns.push(self.parse_column_def()?);
             } else if self.current_token == Token::Primary {
                 constraints.push(self.parse_primary_key_constraint()?);
+            } else if self.current_token == Token::Unique {
+                constraints.push(self.parse_unique_constraint()?);
             } else if self.current_token == Token::Foreign {
                 constraints.push(self.parse_foreign_key_constraint()?);
             } else {
@@ -403,8 +943,9 @@ impl Parser {
         
         let data_type = self.parse_data_type()?;
         let mut nullable = true;
-        let default = None;
+        let mut default = None;
         let mut primary_key = false;
+        let mut unique = false;
         
         // Parse column constraints
         loop {
@@ -426,6 +967,18 @@ impl Parser {
                     self.expect(Token::Key)?;
                     primary_key = true;
                 }
+                Token::Unique => {
+                    self.advance()?;
+                    unique = true;
+                }
+                Token::Null => {
+                    self.advance()?;
+                    nullable = true;
+                }
+                Token::Default => {
+                    self.advance()?;
+                    default = Some(self.parse_expression()?);
+                }
                 _ => break,
             }
         }
@@ -436,6 +989,7 @@ impl Parser {
             nullable,
             default,
             primary_key,
+            unique,
         })
     }
     
@@ -458,6 +1012,10 @@ impl Parser {
                 self.advance()?;
                 DataType::Double
             }
+            Token::Decimal => {
+                self.advance()?;
+                self.parse_decimal_arguments()?
+            }
             Token::Varchar => {
                 self.advance()?;
                 // Parse size parameter if present
@@ -486,12 +1044,41 @@ impl Parser {
             }
             Token::Char => {
                 self.advance()?;
-                // TODO: Parse size parameter
-                DataType::Varchar(1)
+                // 省略长度时为 CHAR(1)
+                if self.current_token == Token::LeftParen {
+                    self.advance()?; // consume '('
+                    let length = match self.current_token {
+                        Token::Integer(n) if n >= 1 => n as usize,
+                        _ => {
+                            return Err(ParseError::UnexpectedToken {
+                                expected: "CHAR length of at least 1".to_string(),
+                                found: self.current_token.clone(),
+                            })
+                        }
+                    };
+                    self.advance()?;
+                    self.expect(Token::RightParen)?;
+                    DataType::Char(length)
+                } else {
+                    DataType::Char(1)
+                }
             }
             Token::Text => {
                 self.advance()?;
-                DataType::Varchar(65535)
+                DataType::Text
+            }
+            Token::Blob => {
+                self.advance()?;
+                DataType::Blob
+            }
+            // UUID 不作关键字，以便 UUID() 仍按函数调用解析
+            Token::Identifier(word) if word.eq_ignore_ascii_case("UUID") => {
+                self.advance()?;
+                DataType::Uuid
+            }
+            Token::Identifier(word) if word.eq_ignore_ascii_case("JSON") => {
+                self.advance()?;
+                DataType::Json
             }
             Token::Bool => {
                 self.advance()?;
@@ -516,6 +1103,42 @@ impl Parser {
         Ok(data_type)
     }
     
+    /// 解析 DECIMAL / NUMERIC 之后可选的 (precision[, scale])，省略时为 DECIMAL(10, 0)
+    fn parse_decimal_arguments(&mut self) -> Result<DataType, ParseError> {
+        if self.current_token != Token::LeftParen {
+            return Ok(DataType::Decimal(10, 0));
+        }
+        self.advance()?; // consume '('
+        let precision = match self.current_token {
+            Token::Integer(n) if (1..=decimal::MAX_PRECISION as i64).contains(&n) => n as u8,
+            _ => {
+                return Err(ParseError::UnexpectedToken {
+                    expected: format!("DECIMAL precision between 1 and {}", decimal::MAX_PRECISION),
+                    found: self.current_token.clone(),
+                })
+            }
+        };
+        self.advance()?;
+        let scale = if self.current_token == Token::Comma {
+            self.advance()?;
+            let scale = match self.current_token {
+                Token::Integer(n) if (0..=precision as i64).contains(&n) => n as u8,
+                _ => {
+                    return Err(ParseError::UnexpectedToken {
+                        expected: format!("DECIMAL scale between 0 and {}", precision),
+                        found: self.current_token.clone(),
+                    })
+                }
+            };
+            self.advance()?;
+            scale
+        } else {
+            0
+        };
+        self.expect(Token::RightParen)?;
+        Ok(DataType::Decimal(precision, scale))
+    }
+    
     /// 解析 PRIMARY KEY 约束
     fn parse_primary_key_constraint(&mut self) -> Result<TableConstraint, ParseError> {
         self.expect(Token::Primary)?;
@@ -545,6 +1168,34 @@ impl Parser {
         Ok(TableConstraint::PrimaryKey(columns))
     }
     
+    /// 解析 UNIQUE 约束
+    fn parse_unique_constraint(&mut self) -> Result<TableConstraint, ParseError> {
+        self.expect(Token::Unique)?;
+        self.expect(Token::LeftParen)?;
+        
+        let mut columns = Vec::new();
+        loop {
+            if let Token::Identifier(name) = &self.current_token {
+                columns.push(name.clone());
+                self.advance()?;
+            } else {
+                return Err(ParseError::UnexpectedToken {
+                    expected: "column name".to_string(),
+                    found: self.current_token.clone(),
+                });
+            }
+            
+            if self.current_token == Token::Comma {
+                self.advance()?;
+            } else {
+                break;
+            }
+        }
+        
+        self.expect(Token::RightParen)?;
+        Ok(TableConstraint::Unique(columns))
+    }
+    
     /// 解析 FOREIGN KEY 约束
     fn parse_foreign_key_constraint(&mut self) -> Result<TableConstraint, ParseError> {
         self.expect(Token::Foreign)?;
@@ -693,14 +1344,49 @@ impl Parser {
             }
         }
         
-        Ok(Statement::CreateIndex {
-            index_name,
-            table_name,
-            columns,
-            is_unique,
-        })
-    }
-    
+        // INCLUDE (列, ...)
+        let mut include = Vec::new();
+        if matches!(&self.current_token, Token::Identifier(word) if word.eq_ignore_ascii_case("INCLUDE")) {
+            self.advance()?;
+            self.expect(Token::LeftParen)?;
+            loop {
+                if let Token::Identifier(name) = &self.current_token {
+                    include.push(name.clone());
+                    self.advance()?;
+                } else {
+                    return Err(ParseError::UnexpectedToken {
+                        expected: "column name".to_string(),
+                        found: self.current_token.clone(),
+                    });
+                }
+                
+                if self.current_token == Token::Comma {
+                    self.advance()?;
+                } else {
+                    break;
+                }
+            }
+            self.expect(Token::RightParen)?;
+        }
+        
+        // WHERE 谓词（部分索引）
+        let where_clause = if self.current_token == Token::Where {
+            self.advance()?;
+            Some(self.parse_expression()?)
+        } else {
+            None
+        };
+        
+        Ok(Statement::CreateIndex {
+            index_name,
+            table_name,
+            columns,
+            is_unique,
+            include,
+            where_clause,
+        })
+    }
+    
     /// 解析 DROP 语句
     fn parse_drop_statement(&mut self) -> Result<Statement, ParseError> {
         self.expect(Token::Drop)?;
@@ -708,13 +1394,43 @@ impl Parser {
         match &self.current_token {
             Token::Table => self.parse_drop_table(),
             Token::Index => self.parse_drop_index(),
+            Token::Identifier(word) if word.eq_ignore_ascii_case("VIEW") => self.parse_drop_view(),
             _ => Err(ParseError::UnexpectedToken {
-                expected: "TABLE or INDEX".to_string(),
+                expected: "TABLE, INDEX or VIEW".to_string(),
                 found: self.current_token.clone(),
             }),
         }
     }
     
+    /// 解析 DROP VIEW 语句
+    fn parse_drop_view(&mut self) -> Result<Statement, ParseError> {
+        self.advance()?; // VIEW
+        
+        let if_exists = if self.current_token == Token::If {
+            self.advance()?;
+            self.expect(Token::Exists)?;
+            true
+        } else {
+            false
+        };
+        
+        let view_name = match &self.current_token {
+            Token::Identifier(name) => {
+                let name = name.clone();
+                self.advance()?;
+                name
+            }
+            _ => {
+                return Err(ParseError::UnexpectedToken {
+                    expected: "view name".to_string(),
+                    found: self.current_token.clone(),
+                })
+            }
+        };
+        
+        Ok(Statement::DropView { view_name, if_exists })
+    }
+    
     /// 解析 DROP TABLE 语句
     fn parse_drop_table(&mut self) -> Result<Statement, ParseError> {
         self.expect(Token::Table)?;
@@ -767,13 +1483,509 @@ impl Parser {
             }
             _ => {
                 return Err(ParseError::UnexpectedToken {
-                    expected: "index name".to_string(),
+                    expected: "index name".to_string(),
+                    found: self.current_token.clone(),
+                })
+            }
+        };
+        
+        self.expect(Token::On)?;
+        
+        let table_name = match &self.current_token {
+            Token::Identifier(name) => {
+                let name = name.clone();
+                self.advance()?;
+                name
+            }
+            _ => {
+                return Err(ParseError::UnexpectedToken {
+                    expected: "table name".to_string(),
+                    found: self.current_token.clone(),
+                })
+            }
+        };
+        
+        Ok(Statement::DropIndex {
+            index_name,
+            table_name,
+            if_exists,
+        })
+    }
+    
+    /// 解析 EXPLAIN [ANALYZE] 语句（ANALYZE 不是保留字）
+    fn parse_explain_statement(&mut self) -> Result<Statement, ParseError> {
+        self.expect(Token::Explain)?;
+        
+        let analyze = matches!(&self.current_token, Token::Identifier(word) if word.eq_ignore_ascii_case("ANALYZE"));
+        if analyze {
+            self.advance()?;
+        }
+        let statement = Box::new(self.parse_statement()?);
+        
+        Ok(Statement::Explain { statement, analyze })
+    }
+    
+    /// 解析 ASSERT 语句（ASSERT 和 MESSAGE 不是保留字）
+    fn parse_assert_statement(&mut self) -> Result<Statement, ParseError> {
+        self.advance()?; // ASSERT
+        
+        let exists = match self.current_token {
+            Token::Not => {
+                self.advance()?;
+                self.expect(Token::Exists)?;
+                Some(true)
+            }
+            Token::Exists => {
+                self.advance()?;
+                Some(false)
+            }
+            _ => None,
+        };
+        
+        self.expect(Token::LeftParen)?;
+        if self.current_token != Token::Select {
+            return Err(ParseError::UnexpectedToken {
+                expected: "SELECT".to_string(),
+                found: self.current_token.clone(),
+            });
+        }
+        let query = Box::new(self.parse_select_statement()?);
+        self.expect(Token::RightParen)?;
+        
+        let condition = match exists {
+            Some(negated) => AssertCondition::Exists { negated },
+            None => {
+                let op = match self.current_token {
+                    Token::Equal => BinaryOperator::Equal,
+                    Token::NotEqual => BinaryOperator::NotEqual,
+                    Token::LessThan => BinaryOperator::LessThan,
+                    Token::LessEqual => BinaryOperator::LessEqual,
+                    Token::GreaterThan => BinaryOperator::GreaterThan,
+                    Token::GreaterEqual => BinaryOperator::GreaterEqual,
+                    _ => {
+                        return Err(ParseError::UnexpectedToken {
+                            expected: "comparison operator".to_string(),
+                            found: self.current_token.clone(),
+                        })
+                    }
+                };
+                self.advance()?;
+                let expected = self.parse_additive_expression()?;
+                AssertCondition::Compare { op, expected }
+            }
+        };
+        
+        let message = match &self.current_token {
+            Token::Identifier(word) if word.eq_ignore_ascii_case("MESSAGE") => {
+                self.advance()?;
+                match &self.current_token {
+                    Token::String(text) => {
+                        let text = text.clone();
+                        self.advance()?;
+                        Some(text)
+                    }
+                    _ => {
+                        return Err(ParseError::UnexpectedToken {
+                            expected: "message string".to_string(),
+                            found: self.current_token.clone(),
+                        })
+                    }
+                }
+            }
+            _ => None,
+        };
+        
+        Ok(Statement::Assert { query, condition, message })
+    }
+    
+    /// 解析 DRY RUN 语句（DRY 和 RUN 不是保留字）
+    fn parse_dry_run_statement(&mut self) -> Result<Statement, ParseError> {
+        self.advance()?; // DRY
+        match &self.current_token {
+            Token::Identifier(word) if word.eq_ignore_ascii_case("RUN") => self.advance()?,
+            _ => {
+                return Err(ParseError::UnexpectedToken {
+                    expected: "RUN".to_string(),
+                    found: self.current_token.clone(),
+                })
+            }
+        }
+        
+        let statement = match &self.current_token {
+            Token::Update => self.parse_update_statement()?,
+            Token::Delete => self.parse_delete_statement()?,
+            _ => {
+                return Err(ParseError::UnexpectedToken {
+                    expected: "UPDATE or DELETE".to_string(),
+                    found: self.current_token.clone(),
+                })
+            }
+        };
+        
+        Ok(Statement::DryRun { statement: Box::new(statement) })
+    }
+    
+    /// 解析 ALTER TABLE 语句（ADD、CONSTRAINT 和 COMPRESSION 不是保留字）
+    fn parse_alter_statement(&mut self) -> Result<Statement, ParseError> {
+        self.expect(Token::Alter)?;
+        self.expect(Token::Table)?;
+        
+        let table_name = match &self.current_token {
+            Token::Identifier(name) => {
+                let name = name.clone();
+                self.advance()?;
+                name
+            }
+            _ => {
+                return Err(ParseError::UnexpectedToken {
+                    expected: "table name".to_string(),
+                    found: self.current_token.clone(),
+                })
+            }
+        };
+        
+        match &self.current_token {
+            Token::Identifier(word) if word.eq_ignore_ascii_case("ADD") => self.advance()?,
+            Token::Set => return self.parse_set_compression(table_name),
+            _ => {
+                return Err(ParseError::UnexpectedToken {
+                    expected: "ADD or SET".to_string(),
+                    found: self.current_token.clone(),
+                })
+            }
+        }
+        
+        let name = match &self.current_token {
+            Token::Identifier(word) if word.eq_ignore_ascii_case("CONSTRAINT") => {
+                self.advance()?;
+                match &self.current_token {
+                    Token::Identifier(name) => {
+                        let name = name.clone();
+                        self.advance()?;
+                        Some(name)
+                    }
+                    _ => {
+                        return Err(ParseError::UnexpectedToken {
+                            expected: "constraint name".to_string(),
+                            found: self.current_token.clone(),
+                        })
+                    }
+                }
+            }
+            _ => None,
+        };
+        
+        let constraint = match &self.current_token {
+            Token::Primary => self.parse_primary_key_constraint()?,
+            Token::Unique => self.parse_unique_constraint()?,
+            Token::Foreign => self.parse_foreign_key_constraint()?,
+            _ => {
+                return Err(ParseError::UnexpectedToken {
+                    expected: "PRIMARY KEY, UNIQUE or FOREIGN KEY".to_string(),
+                    found: self.current_token.clone(),
+                })
+            }
+        };
+        
+        Ok(Statement::AlterTable {
+            table_name,
+            action: AlterTableAction::AddConstraint { name, constraint },
+        })
+    }
+    
+    /// 解析 ALTER TABLE ... SET COMPRESSION {method | DEFAULT}
+    fn parse_set_compression(&mut self, table_name: String) -> Result<Statement, ParseError> {
+        self.expect(Token::Set)?;
+        match &self.current_token {
+            Token::Identifier(word) if word.eq_ignore_ascii_case("COMPRESSION") => self.advance()?,
+            _ => {
+                return Err(ParseError::UnexpectedToken {
+                    expected: "COMPRESSION".to_string(),
+                    found: self.current_token.clone(),
+                })
+            }
+        }
+        
+        let method = match &self.current_token {
+            Token::Default => None,
+            Token::Identifier(method) | Token::String(method) => Some(method.to_lowercase()),
+            _ => {
+                return Err(ParseError::UnexpectedToken {
+                    expected: "compression method or DEFAULT".to_string(),
+                    found: self.current_token.clone(),
+                })
+            }
+        };
+        self.advance()?;
+        
+        Ok(Statement::AlterTable {
+            table_name,
+            action: AlterTableAction::SetCompression { method },
+        })
+    }
+    
+    /// 解析 DEDUPLICATE TABLE 语句（DEDUPLICATE 不是保留字）
+    fn parse_deduplicate_statement(&mut self) -> Result<Statement, ParseError> {
+        self.advance()?; // DEDUPLICATE
+        self.expect(Token::Table)?;
+        
+        let table_name = match &self.current_token {
+            Token::Identifier(name) => {
+                let name = name.clone();
+                self.advance()?;
+                name
+            }
+            _ => {
+                return Err(ParseError::UnexpectedToken {
+                    expected: "table name".to_string(),
+                    found: self.current_token.clone(),
+                })
+            }
+        };
+        
+        Ok(Statement::Deduplicate { table_name })
+    }
+    
+    /// 解析 SET CONSTRAINTS ALL DEFERRED | IMMEDIATE（CONSTRAINTS、DEFERRED 和 IMMEDIATE 都不是保留字）
+    /// 或 SET name {= | TO} value
+    fn parse_set_statement(&mut self) -> Result<Statement, ParseError> {
+        self.expect(Token::Set)?;
+        let name = match &self.current_token {
+            Token::Identifier(word) if word.eq_ignore_ascii_case("CONSTRAINTS") => {
+                self.advance()?;
+                return self.parse_set_constraints();
+            }
+            Token::Identifier(name) => name.to_lowercase(),
+            _ => {
+                return Err(ParseError::UnexpectedToken {
+                    expected: "CONSTRAINTS or setting name".to_string(),
+                    found: self.current_token.clone(),
+                })
+            }
+        };
+        self.advance()?;
+        
+        match &self.current_token {
+            Token::Equal => self.advance()?,
+            Token::Identifier(word) if word.eq_ignore_ascii_case("TO") => self.advance()?,
+            _ => {
+                return Err(ParseError::UnexpectedToken {
+                    expected: "= or TO".to_string(),
+                    found: self.current_token.clone(),
+                })
+            }
+        }
+        let value = match &self.current_token {
+            Token::Identifier(value) | Token::String(value) => value.clone(),
+            Token::Integer(value) => value.to_string(),
+            // 开关型设置写作 on / off 或 true / false
+            Token::On => "on".to_string(),
+            Token::Boolean(value) => value.to_string(),
+            _ => {
+                return Err(ParseError::UnexpectedToken {
+                    expected: "setting value".to_string(),
+                    found: self.current_token.clone(),
+                })
+            }
+        };
+        self.advance()?;
+        Ok(Statement::SetVariable { name, value })
+    }
+    
+    /// 解析 SET CONSTRAINTS 之后的 ALL DEFERRED | IMMEDIATE
+    fn parse_set_constraints(&mut self) -> Result<Statement, ParseError> {
+        self.expect(Token::All)?;
+        
+        let deferred = match &self.current_token {
+            Token::Identifier(word) if word.eq_ignore_ascii_case("DEFERRED") => true,
+            Token::Identifier(word) if word.eq_ignore_ascii_case("IMMEDIATE") => false,
+            _ => {
+                return Err(ParseError::UnexpectedToken {
+                    expected: "DEFERRED or IMMEDIATE".to_string(),
+                    found: self.current_token.clone(),
+                })
+            }
+        };
+        self.advance()?;
+        Ok(Statement::SetConstraints { deferred })
+    }
+    
+    /// 解析 BEGIN / COMMIT / END / ROLLBACK 及可选的 TRANSACTION 或 WORK（都不是保留字）
+    fn parse_transaction_statement(&mut self, statement: Statement) -> Result<Statement, ParseError> {
+        self.advance()?;
+        if matches!(&self.current_token, Token::Identifier(word)
+            if word.eq_ignore_ascii_case("TRANSACTION") || word.eq_ignore_ascii_case("WORK"))
+        {
+            self.advance()?;
+        }
+        Ok(statement)
+    }
+    
+    /// 解析 START TRANSACTION
+    fn parse_start_transaction_statement(&mut self) -> Result<Statement, ParseError> {
+        self.advance()?; // START
+        match &self.current_token {
+            Token::Identifier(word) if word.eq_ignore_ascii_case("TRANSACTION") => {
+                self.advance()?;
+                Ok(Statement::Begin)
+            }
+            _ => Err(ParseError::UnexpectedToken {
+                expected: "TRANSACTION".to_string(),
+                found: self.current_token.clone(),
+            }),
+        }
+    }
+    
+    /// 解析 ADVISE INDEXES 语句（ADVISE 和 INDEXES 都不是保留字）
+    fn parse_advise_statement(&mut self) -> Result<Statement, ParseError> {
+        self.advance()?; // ADVISE
+        match &self.current_token {
+            Token::Identifier(word) if word.eq_ignore_ascii_case("INDEXES") => {
+                self.advance()?;
+                Ok(Statement::AdviseIndexes)
+            }
+            _ => Err(ParseError::UnexpectedToken {
+                expected: "INDEXES".to_string(),
+                found: self.current_token.clone(),
+            }),
+        }
+    }
+    
+    /// 解析 REINDEX INDEX name ON table 或 REINDEX TABLE table（REINDEX 不是保留字）
+    fn parse_reindex_statement(&mut self) -> Result<Statement, ParseError> {
+        self.advance()?; // REINDEX
+        let index = match &self.current_token {
+            Token::Index => true,
+            Token::Table => false,
+            _ => {
+                return Err(ParseError::UnexpectedToken {
+                    expected: "INDEX or TABLE".to_string(),
+                    found: self.current_token.clone(),
+                })
+            }
+        };
+        self.advance()?;
+        
+        let name = match &self.current_token {
+            Token::Identifier(name) => {
+                let name = name.clone();
+                self.advance()?;
+                name
+            }
+            _ => {
+                return Err(ParseError::UnexpectedToken {
+                    expected: if index { "index name" } else { "table name" }.to_string(),
+                    found: self.current_token.clone(),
+                })
+            }
+        };
+        if !index {
+            return Ok(Statement::Reindex { target: ReindexTarget::Table(name) });
+        }
+        
+        self.expect(Token::On)?;
+        let table_name = match &self.current_token {
+            Token::Identifier(name) => {
+                let name = name.clone();
+                self.advance()?;
+                name
+            }
+            _ => {
+                return Err(ParseError::UnexpectedToken {
+                    expected: "table name".to_string(),
+                    found: self.current_token.clone(),
+                })
+            }
+        };
+        Ok(Statement::Reindex { target: ReindexTarget::Index { index_name: name, table_name } })
+    }
+    
+    /// 解析 SHOW INDEXES [{FROM | IN} table]，INDEXES 也可写作 INDEX（SHOW 和 INDEXES 都不是保留字）
+    fn parse_show_statement(&mut self) -> Result<Statement, ParseError> {
+        self.advance()?; // SHOW
+        match &self.current_token {
+            Token::Index => self.advance()?,
+            Token::Identifier(word) if word.eq_ignore_ascii_case("INDEXES") => self.advance()?,
+            _ => {
+                return Err(ParseError::UnexpectedToken {
+                    expected: "INDEXES".to_string(),
+                    found: self.current_token.clone(),
+                })
+            }
+        }
+        
+        if !matches!(self.current_token, Token::From | Token::In) {
+            return Ok(Statement::ShowIndexes { table_name: None });
+        }
+        self.advance()?;
+        match &self.current_token {
+            Token::Identifier(name) => {
+                let table_name = Some(name.clone());
+                self.advance()?;
+                Ok(Statement::ShowIndexes { table_name })
+            }
+            _ => Err(ParseError::UnexpectedToken {
+                expected: "table name".to_string(),
+                found: self.current_token.clone(),
+            }),
+        }
+    }
+    
+    /// 解析 BACKUP TO 'dir'（BACKUP 和 TO 都不是保留字）
+    fn parse_backup_statement(&mut self) -> Result<Statement, ParseError> {
+        self.advance()?; // BACKUP
+        match &self.current_token {
+            Token::Identifier(word) if word.eq_ignore_ascii_case("TO") => self.advance()?,
+            _ => {
+                return Err(ParseError::UnexpectedToken {
+                    expected: "TO".to_string(),
                     found: self.current_token.clone(),
                 })
             }
-        };
-        
-        self.expect(Token::On)?;
+        }
+        match &self.current_token {
+            Token::String(path) => {
+                let path = path.clone();
+                self.advance()?;
+                Ok(Statement::Backup { path })
+            }
+            _ => Err(ParseError::UnexpectedToken {
+                expected: "backup directory string".to_string(),
+                found: self.current_token.clone(),
+            }),
+        }
+    }
+    
+    /// 解析 COPY 语句（COPY、TO 和各选项名都不是保留字）
+    ///
+    /// `COPY table FROM 'file' [(FORMAT CSV|NDJSON, HEADER [true|false], DELIMITER 'c', INFER)]`
+    /// `COPY (SELECT ...) TO 'file' [(HEADER [true|false], DELIMITER 'c', QUOTE MINIMAL|ALL|NONNUMERIC)]`
+    fn parse_copy_statement(&mut self) -> Result<Statement, ParseError> {
+        self.advance()?; // COPY
+        
+        if self.current_token == Token::LeftParen {
+            self.advance()?;
+            if self.current_token != Token::Select {
+                return Err(ParseError::UnexpectedToken {
+                    expected: "SELECT".to_string(),
+                    found: self.current_token.clone(),
+                });
+            }
+            let query = Box::new(self.parse_select_statement()?);
+            self.expect(Token::RightParen)?;
+            match &self.current_token {
+                Token::Identifier(word) if word.eq_ignore_ascii_case("TO") => self.advance()?,
+                _ => {
+                    return Err(ParseError::UnexpectedToken {
+                        expected: "TO".to_string(),
+                        found: self.current_token.clone(),
+                    })
+                }
+            }
+            let path = self.parse_copy_path()?;
+            let options = self.parse_copy_options()?;
+            return Ok(Statement::CopyTo { query, path, options });
+        }
         
         let table_name = match &self.current_token {
             Token::Identifier(name) => {
@@ -788,21 +2000,111 @@ impl Parser {
                 })
             }
         };
-        
-        Ok(Statement::DropIndex {
-            index_name,
-            table_name,
-            if_exists,
-        })
+        self.expect(Token::From)?;
+        let path = self.parse_copy_path()?;
+        let options = self.parse_copy_options()?;
+        Ok(Statement::Copy { table_name, path, options })
     }
     
-    /// 解析 EXPLAIN 语句
-    fn parse_explain_statement(&mut self) -> Result<Statement, ParseError> {
-        self.expect(Token::Explain)?;
-        
-        let statement = Box::new(self.parse_statement()?);
-        
-        Ok(Statement::Explain { statement })
+    /// 解析 COPY 的文件名字符串
+    fn parse_copy_path(&mut self) -> Result<String, ParseError> {
+        match &self.current_token {
+            Token::String(path) => {
+                let path = path.clone();
+                self.advance()?;
+                Ok(path)
+            }
+            _ => Err(ParseError::UnexpectedToken {
+                expected: "file name string".to_string(),
+                found: self.current_token.clone(),
+            }),
+        }
+    }
+    
+    /// 解析 COPY 的可选选项列表
+    fn parse_copy_options(&mut self) -> Result<CopyOptions, ParseError> {
+        let mut options = CopyOptions::default();
+        if self.current_token != Token::LeftParen {
+            return Ok(options);
+        }
+        self.advance()?;
+        loop {
+            let option = match &self.current_token {
+                Token::Identifier(word) => word.to_uppercase(),
+                _ => {
+                    return Err(ParseError::UnexpectedToken {
+                        expected: "COPY option".to_string(),
+                        found: self.current_token.clone(),
+                    })
+                }
+            };
+            self.advance()?;
+            
+            match option.as_str() {
+                "FORMAT" => {
+                    options.format = Some(match &self.current_token {
+                        Token::Identifier(word) if word.eq_ignore_ascii_case("CSV") => CopyFormat::Csv,
+                        Token::Identifier(word) if word.eq_ignore_ascii_case("NDJSON") => CopyFormat::Ndjson,
+                        _ => {
+                            return Err(ParseError::UnexpectedToken {
+                                expected: "CSV or NDJSON".to_string(),
+                                found: self.current_token.clone(),
+                            })
+                        }
+                    });
+                    self.advance()?;
+                }
+                "HEADER" => {
+                    options.header = match self.current_token {
+                        Token::Boolean(value) => {
+                            self.advance()?;
+                            value
+                        }
+                        _ => true,
+                    };
+                }
+                "DELIMITER" => {
+                    let mut chars = match &self.current_token {
+                        Token::String(delimiter) => delimiter.chars(),
+                        _ => "".chars(),
+                    };
+                    options.delimiter = match (chars.next(), chars.next()) {
+                        (Some(delimiter), None) => delimiter,
+                        _ => {
+                            return Err(ParseError::UnexpectedToken {
+                                expected: "single-character delimiter string".to_string(),
+                                found: self.current_token.clone(),
+                            })
+                        }
+                    };
+                    self.advance()?;
+                }
+                "QUOTE" => {
+                    options.quote = match &self.current_token {
+                        Token::All => QuoteStyle::All,
+                        Token::Identifier(word) if word.eq_ignore_ascii_case("MINIMAL") => QuoteStyle::Minimal,
+                        Token::Identifier(word) if word.eq_ignore_ascii_case("NONNUMERIC") => QuoteStyle::NonNumeric,
+                        _ => {
+                            return Err(ParseError::UnexpectedToken {
+                                expected: "MINIMAL, ALL or NONNUMERIC".to_string(),
+                                found: self.current_token.clone(),
+                            })
+                        }
+                    };
+                    self.advance()?;
+                }
+                "INFER" => options.infer = true,
+                other => return Err(ParseError::UnsupportedFeature(format!("COPY option {}", other))),
+            }
+            
+            if self.current_token == Token::Comma {
+                self.advance()?;
+            } else {
+                break;
+            }
+        }
+        self.expect(Token::RightParen)?;
+        Ok(options)
     }
     
     /// 解析 SELECT 语句
@@ -834,8 +2136,13 @@ impl Parser {
             None
         };
         
-        // TODO: Parse HAVING
-        let having = None;
+        // Parse HAVING clause
+        let having = if self.current_token == Token::Having {
+            self.advance()?;
+            Some(self.parse_expression()?)
+        } else {
+            None
+        };
         
         // Parse ORDER BY clause
         let order_by = if self.current_token == Token::Order {
@@ -963,8 +2270,25 @@ impl Parser {
     fn parse_from_table(&mut self) -> Result<FromClause, ParseError> {
         match &self.current_token {
             Token::Identifier(name) => {
-                let name = name.clone();
+                let mut name = name.clone();
                 self.advance()?;
+                
+                // 模式限定的表名（如 information_schema.column_stats）
+                if self.current_token == Token::Dot {
+                    self.advance()?;
+                    match &self.current_token {
+                        Token::Identifier(table) => {
+                            name = format!("{}.{}", name, table);
+                            self.advance()?;
+                        }
+                        _ => {
+                            return Err(ParseError::UnexpectedToken {
+                                expected: "table name".to_string(),
+                                found: self.current_token.clone(),
+                            })
+                        }
+                    }
+                }
                 Ok(FromClause::Table(name))
             }
             _ => Err(ParseError::UnexpectedToken {
@@ -1097,16 +2421,88 @@ impl Parser {
             }
         }
         
+        // Optional ON CONFLICT clause
+        let on_conflict = if self.current_token == Token::On {
+            self.advance()?;
+            Some(self.parse_on_conflict()?)
+        } else {
+            None
+        };
+        
+        let returning = self.parse_returning_clause()?;
+        
         Ok(Statement::Insert {
             table_name,
             columns,
             values,
+            on_conflict,
+            returning,
         })
     }
     
-    /// 解析 UPDATE 语句
+    /// 解析 ON CONFLICT [(列...)] DO NOTHING | DO UPDATE SET ... [WHERE ...]
+    fn parse_on_conflict(&mut self) -> Result<OnConflict, ParseError> {
+        self.expect(Token::Conflict)?;
+        
+        let mut target = Vec::new();
+        if self.current_token == Token::LeftParen {
+            self.advance()?;
+            loop {
+                if let Token::Identifier(name) = &self.current_token {
+                    target.push(name.clone());
+                    self.advance()?;
+                } else {
+                    return Err(ParseError::UnexpectedToken {
+                        expected: "column name".to_string(),
+                        found: self.current_token.clone(),
+                    });
+                }
+                
+                if self.current_token == Token::Comma {
+                    self.advance()?;
+                } else {
+                    break;
+                }
+            }
+            self.expect(Token::RightParen)?;
+        }
+        
+        self.expect(Token::Do)?;
+        
+        let action = match &self.current_token {
+            Token::Nothing => {
+                self.advance()?;
+                ConflictAction::DoNothing
+            }
+            Token::Update => {
+                self.advance()?;
+                self.expect(Token::Set)?;
+                let assignments = self.parse_assignment_list()?;
+                
+                let where_clause = if self.current_token == Token::Where {
+                    self.advance()?;
+                    Some(self.parse_expression()?)
+                } else {
+                    None
+                };
+                
+                ConflictAction::DoUpdate { assignments, where_clause }
+            }
+            _ => {
+                return Err(ParseError::UnexpectedToken {
+                    expected: "NOTHING or UPDATE".to_string(),
+                    found: self.current_token.clone(),
+                })
+            }
+        };
+        
+        Ok(OnConflict { target, action })
+    }
+    
+    /// 解析 UPDATE [ALL] 语句
     fn parse_update_statement(&mut self) -> Result<Statement, ParseError> {
         self.expect(Token::Update)?;
+        let all_rows = self.parse_all_rows()?;
         
         let table_name = match &self.current_token {
             Token::Identifier(name) => {
@@ -1124,6 +2520,38 @@ impl Parser {
         
         self.expect(Token::Set)?;
         
+        let assignments = self.parse_assignment_list()?;
+        
+        let where_clause = if self.current_token == Token::Where {
+            self.advance()?;
+            Some(self.parse_expression()?)
+        } else {
+            None
+        };
+        
+        let returning = self.parse_returning_clause()?;
+        
+        Ok(Statement::Update {
+            table_name,
+            assignments,
+            where_clause,
+            returning,
+            all_rows,
+        })
+    }
+    
+    /// 解析 UPDATE / DELETE 后可选的 ALL
+    fn parse_all_rows(&mut self) -> Result<bool, ParseError> {
+        if self.current_token == Token::All {
+            self.advance()?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+    
+    /// 解析 SET 后的赋值列表
+    fn parse_assignment_list(&mut self) -> Result<Vec<Assignment>, ParseError> {
         let mut assignments = Vec::new();
         loop {
             let column = match &self.current_token {
@@ -1152,23 +2580,13 @@ impl Parser {
             }
         }
         
-        let where_clause = if self.current_token == Token::Where {
-            self.advance()?;
-            Some(self.parse_expression()?)
-        } else {
-            None
-        };
-        
-        Ok(Statement::Update {
-            table_name,
-            assignments,
-            where_clause,
-        })
+        Ok(assignments)
     }
     
-    /// 解析 DELETE 语句
+    /// 解析 DELETE [ALL] FROM 语句
     fn parse_delete_statement(&mut self) -> Result<Statement, ParseError> {
         self.expect(Token::Delete)?;
+        let all_rows = self.parse_all_rows()?;
         self.expect(Token::From)?;
         
         let table_name = match &self.current_token {
@@ -1192,12 +2610,26 @@ impl Parser {
             None
         };
         
+        let returning = self.parse_returning_clause()?;
+        
         Ok(Statement::Delete {
             table_name,
             where_clause,
+            returning,
+            all_rows,
         })
     }
     
+    /// 解析可选的 RETURNING 子句
+    fn parse_returning_clause(&mut self) -> Result<Option<SelectList>, ParseError> {
+        if self.current_token == Token::Returning {
+            self.advance()?;
+            Ok(Some(self.parse_select_list()?))
+        } else {
+            Ok(None)
+        }
+    }
+    
     /// 解析表达式（简化版本）
     fn parse_expression(&mut self) -> Result<Expression, ParseError> {
         self.parse_or_expression()
@@ -1264,7 +2696,8 @@ impl Parser {
     
     /// 解析比较表达式
     fn parse_comparison_expression(&mut self) -> Result<Expression, ParseError> {
-        let mut left = self.parse_additive_expression()?;
+        let left = self.parse_additive_expression()?;
+        let mut left = self.parse_predicate_suffix(left)?;
         
         while matches!(
             self.current_token,
@@ -1289,6 +2722,84 @@ impl Parser {
         Ok(left)
     }
     
+    /// 解析跟在操作数之后的 [NOT] LIKE / IN / BETWEEN 谓词和 IS [NOT] NULL
+    fn parse_predicate_suffix(&mut self, expr: Expression) -> Result<Expression, ParseError> {
+        if self.current_token == Token::Is {
+            self.advance()?;
+            let negated = self.current_token == Token::Not;
+            if negated {
+                self.advance()?;
+            }
+            self.expect(Token::Null)?;
+            return Ok(if negated {
+                Expression::IsNotNull(Box::new(expr))
+            } else {
+                Expression::IsNull(Box::new(expr))
+            });
+        }
+        
+        // 操作数之后的 NOT 可能属于谓词，也可能属于外层语法（如列定义中的 NOT NULL）
+        let negated = self.current_token == Token::Not
+            && matches!(self.peek_token()?, Token::Like | Token::In | Token::Between);
+        if negated {
+            self.advance()?;
+        }
+
+        match self.current_token {
+            Token::In => {
+                self.advance()?;
+                self.expect(Token::LeftParen)?;
+                if self.current_token == Token::Select {
+                    let subquery = Box::new(self.parse_select_statement()?);
+                    self.expect(Token::RightParen)?;
+                    return Ok(Expression::InSubquery { expr: Box::new(expr), subquery, negated });
+                }
+                let mut list = vec![self.parse_expression()?];
+                while self.current_token == Token::Comma {
+                    self.advance()?;
+                    list.push(self.parse_expression()?);
+                }
+                self.expect(Token::RightParen)?;
+                Ok(Expression::In {
+                    expr: Box::new(expr),
+                    list,
+                    negated,
+                })
+            }
+            Token::Between => {
+                // 边界只解析到加减层，避免吞掉 BETWEEN 自身的 AND
+                self.advance()?;
+                let low = self.parse_additive_expression()?;
+                self.expect(Token::And)?;
+                let high = self.parse_additive_expression()?;
+                Ok(Expression::Between {
+                    expr: Box::new(expr),
+                    low: Box::new(low),
+                    high: Box::new(high),
+                    negated,
+                })
+            }
+            Token::Like => {
+                self.advance()?;
+                let pattern = self.parse_additive_expression()?;
+                let escape = match &self.current_token {
+                    Token::Identifier(word) if word.eq_ignore_ascii_case("ESCAPE") => {
+                        self.advance()?;
+                        Some(Box::new(self.parse_primary_expression()?))
+                    }
+                    _ => None,
+                };
+                Ok(Expression::Like {
+                    expr: Box::new(expr),
+                    pattern: Box::new(pattern),
+                    escape,
+                    negated,
+                })
+            }
+            _ => Ok(expr),
+        }
+    }
+    
     /// 解析加减表达式
     fn parse_additive_expression(&mut self) -> Result<Expression, ParseError> {
         let mut left = self.parse_multiplicative_expression()?;
@@ -1364,8 +2875,38 @@ impl Parser {
                     expr: Box::new(expr),
                 })
             }
-            _ => self.parse_primary_expression(),
+            _ => self.parse_json_access_expression(),
+        }
+    }
+    
+    /// 解析 JSON 取值：`doc -> 'key'`、`doc ->> 'key'`，可以连续使用，优先级高于所有其他运算符
+    fn parse_json_access_expression(&mut self) -> Result<Expression, ParseError> {
+        let mut left = self.parse_primary_expression()?;
+        
+        while matches!(self.current_token, Token::Arrow | Token::LongArrow) {
+            let op = match self.current_token {
+                Token::Arrow => BinaryOperator::JsonExtract,
+                _ => BinaryOperator::JsonExtractText,
+            };
+            self.advance()?;
+            // 负下标从数组末尾倒数
+            let right = if self.current_token == Token::Minus {
+                self.advance()?;
+                Expression::UnaryOp {
+                    op: UnaryOperator::Minus,
+                    expr: Box::new(self.parse_primary_expression()?),
+                }
+            } else {
+                self.parse_primary_expression()?
+            };
+            left = Expression::BinaryOp {
+                left: Box::new(left),
+                op,
+                right: Box::new(right),
+            };
         }
+        
+        Ok(left)
     }
     
     /// 解析基本表达式
@@ -1386,6 +2927,11 @@ impl Parser {
                 self.advance()?;
                 Ok(Expression::Literal(value))
             }
+            Token::Binary(bytes) => {
+                let value = Value::Blob(bytes.clone());
+                self.advance()?;
+                Ok(Expression::Literal(value))
+            }
             Token::Boolean(b) => {
                 let value = Value::Boolean(*b);
                 self.advance()?;
@@ -1395,10 +2941,83 @@ impl Parser {
                 self.advance()?;
                 Ok(Expression::Literal(Value::Null))
             }
+            Token::Default => {
+                self.advance()?;
+                Ok(Expression::Default)
+            }
+            Token::Parameter(index) => {
+                self.parameter_count = self.parameter_count.max(*index);
+                self.advance()?;
+                Ok(Expression::Parameter(*index))
+            }
+            // 带类型的常量：DATE '2024-05-01'、TIMESTAMP '2024-05-01 10:00:00'
+            Token::Date | Token::Timestamp => {
+                let data_type = if self.current_token == Token::Date { DataType::Date } else { DataType::Timestamp };
+                self.advance()?;
+                let text = match &self.current_token {
+                    Token::String(text) => text.clone(),
+                    _ => {
+                        return Err(ParseError::UnexpectedToken {
+                            expected: format!("{} string literal", data_type),
+                            found: self.current_token.clone(),
+                        })
+                    }
+                };
+                let value = Value::Varchar(text.clone())
+                    .cast_to(&data_type)
+                    .map_err(|_| ParseError::InvalidLiteral { data_type, text })?;
+                self.advance()?;
+                Ok(Expression::Literal(value))
+            }
+            Token::Exists => {
+                self.advance()?;
+                self.expect(Token::LeftParen)?;
+                let subquery = Box::new(self.parse_select_statement()?);
+                self.expect(Token::RightParen)?;
+                Ok(Expression::Exists(subquery))
+            }
+            Token::Cast => {
+                self.advance()?;
+                self.expect(Token::LeftParen)?;
+                let expr = self.parse_expression()?;
+                self.expect(Token::As)?;
+                let data_type = self.parse_data_type()?;
+                self.expect(Token::RightParen)?;
+                Ok(Expression::Cast { expr: Box::new(expr), data_type })
+            }
             Token::Identifier(name) => {
                 let name = name.clone();
                 self.advance()?;
                 
+                // CURRENT_DATE / CURRENT_TIMESTAMP 可以不带括号
+                if self.current_token != Token::LeftParen
+                    && (name.eq_ignore_ascii_case("CURRENT_DATE") || name.eq_ignore_ascii_case("CURRENT_TIMESTAMP"))
+                {
+                    return Ok(Expression::FunctionCall { name: name.to_uppercase(), args: Vec::new() });
+                }
+                
+                // EXTRACT(field FROM expr) 解析为 EXTRACT('FIELD', expr)
+                if self.current_token == Token::LeftParen && name.eq_ignore_ascii_case("EXTRACT") {
+                    self.advance()?;
+                    let field = match &self.current_token {
+                        Token::Identifier(field) => field.to_uppercase(),
+                        _ => {
+                            return Err(ParseError::UnexpectedToken {
+                                expected: "date field".to_string(),
+                                found: self.current_token.clone(),
+                            })
+                        }
+                    };
+                    self.advance()?;
+                    self.expect(Token::From)?;
+                    let source = self.parse_expression()?;
+                    self.expect(Token::RightParen)?;
+                    return Ok(Expression::FunctionCall {
+                        name: "EXTRACT".to_string(),
+                        args: vec![Expression::Literal(Value::Varchar(field)), source],
+                    });
+                }
+                
                 // Check for function call (name followed by left paren)
                 if self.current_token == Token::LeftParen {
                     self.advance()?;
@@ -1480,7 +3099,25 @@ impl Parser {
                 _ => false, // Default to ASC
             };
             
-            order_exprs.push(OrderByExpr { expr, desc });
+            // NULLS FIRST / NULLS LAST（上下文关键字，不占用 first/last 作为列名）
+            let nulls_first = match &self.current_token {
+                Token::Identifier(word) if word.eq_ignore_ascii_case("NULLS") => {
+                    self.advance()?;
+                    let first = match &self.current_token {
+                        Token::Identifier(word) if word.eq_ignore_ascii_case("FIRST") => true,
+                        Token::Identifier(word) if word.eq_ignore_ascii_case("LAST") => false,
+                        other => return Err(ParseError::UnexpectedToken {
+                            expected: "FIRST or LAST".to_string(),
+                            found: other.clone(),
+                        }),
+                    };
+                    self.advance()?;
+                    Some(first)
+                }
+                _ => None,
+            };
+            
+            order_exprs.push(OrderByExpr { expr, desc, nulls_first });
             
             // Check if there's a comma for multiple order expressions
             if self.current_token == Token::Comma {
@@ -1523,7 +3160,7 @@ mod tests {
     fn parse_sql(input: &str) -> Result<Statement, ParseError> {
         let lexer = Lexer::new(input);
         let mut parser = Parser::new(lexer)?;
-        parser.parse_statement()
+        parser.parse_complete_statement()
     }
 
     #[test]
@@ -1552,6 +3189,539 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_column_default() {
+        let sql = "CREATE TABLE t (id INT DEFAULT -1 NOT NULL, name VARCHAR(10) DEFAULT 'n/a', flag BOOL NULL)";
+        match parse_sql(sql).unwrap() {
+            Statement::CreateTable { columns, .. } => {
+                assert!(matches!(columns[0].default, Some(Expression::UnaryOp { op: UnaryOperator::Minus, .. })));
+                assert!(!columns[0].nullable);
+                assert_eq!(columns[1].default, Some(Expression::Literal(Value::Varchar("n/a".to_string()))));
+                assert_eq!(columns[2].default, None);
+                assert!(columns[2].nullable);
+            }
+            _ => panic!("Expected CreateTable statement"),
+        }
+
+        match parse_sql("INSERT INTO t VALUES (DEFAULT, 'x', NULL)").unwrap() {
+            Statement::Insert { values, .. } => assert_eq!(values[0][0], Expression::Default),
+            _ => panic!("Expected Insert statement"),
+        }
+    }
+
+    #[test]
+    fn test_decimal_types() {
+        let sql = "CREATE TABLE t (price DECIMAL(10, 2), qty NUMERIC(5), total decimal)";
+        match parse_sql(sql).unwrap() {
+            Statement::CreateTable { columns, .. } => {
+                let types: Vec<_> = columns.iter().map(|c| c.data_type.clone()).collect();
+                assert_eq!(types, vec![DataType::Decimal(10, 2), DataType::Decimal(5, 0), DataType::Decimal(10, 0)]);
+            }
+            _ => panic!("Expected CreateTable statement"),
+        }
+
+        for bad in ["DECIMAL(0)", "DECIMAL(39, 2)", "DECIMAL(5, 6)", "NUMERIC(10,)"] {
+            assert!(parse_sql(&format!("CREATE TABLE t (x {})", bad)).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_char_type() {
+        match parse_sql("CREATE TABLE t (code CHAR(3), flag char)").unwrap() {
+            Statement::CreateTable { columns, .. } => {
+                let types: Vec<_> = columns.iter().map(|c| c.data_type.clone()).collect();
+                assert_eq!(types, vec![DataType::Char(3), DataType::Char(1)]);
+            }
+            _ => panic!("Expected CreateTable statement"),
+        }
+
+        for bad in ["CHAR(0)", "CHAR(-1)", "CHAR(3"] {
+            assert!(parse_sql(&format!("CREATE TABLE t (x {})", bad)).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_text_and_blob_types() {
+        match parse_sql("CREATE TABLE t (body TEXT, data BLOB)").unwrap() {
+            Statement::CreateTable { columns, .. } => {
+                let types: Vec<_> = columns.iter().map(|c| c.data_type.clone()).collect();
+                assert_eq!(types, vec![DataType::Text, DataType::Blob]);
+            }
+            _ => panic!("Expected CreateTable statement"),
+        }
+
+        match parse_sql("INSERT INTO t VALUES ('hi', X'00FF')").unwrap() {
+            Statement::Insert { values, .. } => {
+                assert_eq!(values[0][1], Expression::Literal(Value::Blob(vec![0x00, 0xff])));
+            }
+            _ => panic!("Expected Insert statement"),
+        }
+    }
+
+    #[test]
+    fn test_uuid_type() {
+        // UUID 作类型名时是类型，后跟括号时是函数调用
+        match parse_sql("CREATE TABLE t (id UUID PRIMARY KEY DEFAULT UUID(), uuid INT)").unwrap() {
+            Statement::CreateTable { columns, .. } => {
+                assert_eq!(columns[0].data_type, DataType::Uuid);
+                assert!(matches!(&columns[0].default, Some(Expression::FunctionCall { name, args }) if name == "UUID" && args.is_empty()));
+                assert_eq!(columns[1].name, "uuid");
+            }
+            _ => panic!("Expected CreateTable statement"),
+        }
+    }
+
+    #[test]
+    fn test_json_access_operators() {
+        // -> / ->> 左结合，且先于比较运算结合
+        match parse_sql("SELECT id FROM docs WHERE doc -> 'tags' ->> -1 = 'x'").unwrap() {
+            Statement::Select { where_clause: Some(Expression::BinaryOp { left, op: BinaryOperator::Equal, .. }), .. } => {
+                match *left {
+                    Expression::BinaryOp { left: inner, op: BinaryOperator::JsonExtractText, right } => {
+                        assert!(matches!(*inner, Expression::BinaryOp { op: BinaryOperator::JsonExtract, .. }));
+                        assert!(matches!(*right, Expression::UnaryOp { op: UnaryOperator::Minus, .. }));
+                    }
+                    other => panic!("Expected ->> expression, got {:?}", other),
+                }
+            }
+            other => panic!("Expected Select statement, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_unique_constraints() {
+        let sql = "CREATE TABLE t (id INT PRIMARY KEY, email VARCHAR(50) UNIQUE NOT NULL, a INT, b INT, UNIQUE (a, b))";
+        match parse_sql(sql).unwrap() {
+            Statement::CreateTable { columns, constraints, .. } => {
+                assert!(!columns[0].unique);
+                assert!(columns[1].unique);
+                assert!(!columns[1].nullable);
+                assert_eq!(constraints, vec![TableConstraint::Unique(vec!["a".to_string(), "b".to_string()])]);
+            }
+            _ => panic!("Expected CreateTable statement"),
+        }
+    }
+
+    #[test]
+    fn test_dry_run() {
+        match parse_sql("DRY RUN DELETE FROM t WHERE a > 1").unwrap() {
+            Statement::DryRun { statement } => assert!(matches!(*statement, Statement::Delete { .. })),
+            _ => panic!("Expected DryRun statement"),
+        }
+        assert!(matches!(parse_sql("dry run UPDATE t SET a = 1").unwrap(), Statement::DryRun { .. }));
+        assert!(parse_sql("DRY RUN SELECT * FROM t").is_err());
+    }
+
+    #[test]
+    fn test_trailing_tokens() {
+        assert!(parse_sql("SELECT * FROM t;").is_ok());
+        assert!(parse_sql("DELETE FROM t WHERE a = 1;;").is_ok());
+        assert!(matches!(
+            parse_sql("UPDATE t SET a = 1 WHERE b = 2 DRY RUN"),
+            Err(ParseError::UnexpectedToken { .. })
+        ));
+        assert!(parse_sql("SELECT a FROM t; SELECT b FROM t").is_err());
+    }
+
+    #[test]
+    fn test_alter_table_add_constraint() {
+        assert_eq!(
+            parse_sql("ALTER TABLE users ADD CONSTRAINT users_email_key UNIQUE (email)").unwrap(),
+            Statement::AlterTable {
+                table_name: "users".to_string(),
+                action: AlterTableAction::AddConstraint {
+                    name: Some("users_email_key".to_string()),
+                    constraint: TableConstraint::Unique(vec!["email".to_string()]),
+                },
+            }
+        );
+        match parse_sql("ALTER TABLE orders ADD FOREIGN KEY (user_id) REFERENCES users (id)").unwrap() {
+            Statement::AlterTable { action: AlterTableAction::AddConstraint { name: None, constraint }, .. } => {
+                assert!(matches!(constraint, TableConstraint::ForeignKey { .. }));
+            }
+            other => panic!("Expected ALTER TABLE, got {:?}", other),
+        }
+        assert!(parse_sql("ALTER TABLE users ADD PRIMARY (id)").is_err());
+        assert!(parse_sql("ALTER TABLE users DROP COLUMN id").is_err());
+    }
+
+    #[test]
+    fn test_alter_table_set_compression() {
+        assert_eq!(
+            parse_sql("ALTER TABLE logs SET COMPRESSION LZ4").unwrap(),
+            Statement::AlterTable {
+                table_name: "logs".to_string(),
+                action: AlterTableAction::SetCompression { method: Some("lz4".to_string()) },
+            }
+        );
+        assert!(matches!(
+            parse_sql("alter table logs set compression default").unwrap(),
+            Statement::AlterTable { action: AlterTableAction::SetCompression { method: None }, .. }
+        ));
+        assert!(parse_sql("ALTER TABLE logs SET COMPRESSION").is_err());
+        assert!(parse_sql("ALTER TABLE logs SET FILLFACTOR 50").is_err());
+    }
+
+    #[test]
+    fn test_date_functions() {
+        match parse_sql("SELECT EXTRACT(year FROM created), CURRENT_DATE FROM t").unwrap() {
+            Statement::Select { select_list: SelectList::Expressions(exprs), .. } => {
+                assert_eq!(exprs[0].expr, Expression::FunctionCall {
+                    name: "EXTRACT".to_string(),
+                    args: vec![
+                        Expression::Literal(Value::Varchar("YEAR".to_string())),
+                        Expression::Column("created".to_string()),
+                    ],
+                });
+                assert_eq!(exprs[1].expr, Expression::FunctionCall { name: "CURRENT_DATE".to_string(), args: vec![] });
+            }
+            other => panic!("Expected SELECT, got {:?}", other),
+        }
+        assert!(parse_sql("SELECT EXTRACT(YEAR created) FROM t").is_err());
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_date_literals() {
+        use chrono::NaiveDate;
+
+        let date = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+        match parse_sql("INSERT INTO t VALUES (DATE '2024-05-01', TIMESTAMP '2024-05-01 10:00:00')").unwrap() {
+            Statement::Insert { values, .. } => {
+                assert_eq!(values[0][0], Expression::Literal(Value::Date(date)));
+                assert_eq!(values[0][1], Expression::Literal(Value::Timestamp(date.and_hms_opt(10, 0, 0).unwrap())));
+            }
+            other => panic!("Expected INSERT, got {:?}", other),
+        }
+        assert!(matches!(parse_sql("SELECT DATE '2024-02-30'"), Err(ParseError::InvalidLiteral { .. })));
+        assert!(parse_sql("SELECT DATE 20240501").is_err());
+    }
+
+    #[test]
+    fn test_cast() {
+        match parse_sql("SELECT CAST(price AS INT), CAST('2024-01-31' AS DATE) FROM t").unwrap() {
+            Statement::Select { select_list: SelectList::Expressions(exprs), .. } => {
+                assert_eq!(exprs[0].expr, Expression::Cast {
+                    expr: Box::new(Expression::Column("price".to_string())),
+                    data_type: DataType::Integer,
+                });
+                assert_eq!(exprs[1].expr, Expression::Cast {
+                    expr: Box::new(Expression::Literal(Value::Varchar("2024-01-31".to_string()))),
+                    data_type: DataType::Date,
+                });
+            }
+            other => panic!("Expected SELECT, got {:?}", other),
+        }
+        assert!(parse_sql("SELECT CAST(price INT) FROM t").is_err());
+        assert!(parse_sql("SELECT CAST(price AS) FROM t").is_err());
+    }
+
+    #[test]
+    fn test_having() {
+        match parse_sql("SELECT dept, COUNT(*) FROM emp GROUP BY dept HAVING COUNT(*) > 1 ORDER BY dept").unwrap() {
+            Statement::Select { group_by, having, order_by, .. } => {
+                assert_eq!(group_by.map(|exprs| exprs.len()), Some(1));
+                assert!(matches!(having, Some(Expression::BinaryOp { op: BinaryOperator::GreaterThan, .. })));
+                assert!(order_by.is_some());
+            }
+            other => panic!("Expected SELECT, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_order_by_nulls() {
+        match parse_sql("SELECT a, b FROM t ORDER BY 2 DESC NULLS FIRST, a NULLS LAST, first").unwrap() {
+            Statement::Select { order_by: Some(order_by), .. } => {
+                assert_eq!(order_by, vec![
+                    OrderByExpr { expr: Expression::Literal(Value::Integer(2)), desc: true, nulls_first: Some(true) },
+                    OrderByExpr { expr: Expression::Column("a".to_string()), desc: false, nulls_first: Some(false) },
+                    OrderByExpr { expr: Expression::Column("first".to_string()), desc: false, nulls_first: None },
+                ]);
+            }
+            other => panic!("Expected SELECT with ORDER BY, got {:?}", other),
+        }
+        assert!(parse_sql("SELECT a FROM t ORDER BY a NULLS").is_err());
+    }
+
+    #[test]
+    fn test_copy() {
+        assert_eq!(
+            parse_sql("COPY people FROM 'people.csv'").unwrap(),
+            Statement::Copy {
+                table_name: "people".to_string(),
+                path: "people.csv".to_string(),
+                options: CopyOptions::default(),
+            }
+        );
+        assert_eq!(
+            parse_sql("copy people from 'p.txt' (format csv, header, delimiter ';', infer)").unwrap(),
+            Statement::Copy {
+                table_name: "people".to_string(),
+                path: "p.txt".to_string(),
+                options: CopyOptions { format: Some(CopyFormat::Csv), header: true, delimiter: ';', infer: true, quote: QuoteStyle::Minimal },
+            }
+        );
+        match parse_sql("COPY t FROM 'rows.json' (FORMAT NDJSON, HEADER false)").unwrap() {
+            Statement::Copy { options, .. } => {
+                assert_eq!(options.format, Some(CopyFormat::Ndjson));
+                assert!(!options.header);
+            }
+            other => panic!("Expected COPY, got {:?}", other),
+        }
+        assert!(parse_sql("COPY t FROM 'a.csv' (DELIMITER ';;')").is_err());
+        assert!(parse_sql("COPY t FROM 'a.csv' (QUOTE '\"')").is_err());
+        assert!(parse_sql("COPY t FROM a.csv").is_err());
+    }
+
+    #[test]
+    fn test_copy_to() {
+        match parse_sql("COPY (SELECT a, b FROM t WHERE a > 1) TO 'out.csv' (HEADER, DELIMITER ';', QUOTE ALL)").unwrap() {
+            Statement::CopyTo { query, path, options } => {
+                assert!(matches!(*query, Statement::Select { .. }));
+                assert_eq!(path, "out.csv");
+                assert!(options.header);
+                assert_eq!(options.delimiter, ';');
+                assert_eq!(options.quote, QuoteStyle::All);
+            }
+            other => panic!("Expected COPY TO, got {:?}", other),
+        }
+        match parse_sql("copy (select * from t) to 'out.csv' (quote nonnumeric)").unwrap() {
+            Statement::CopyTo { options, .. } => assert_eq!(options.quote, QuoteStyle::NonNumeric),
+            other => panic!("Expected COPY TO, got {:?}", other),
+        }
+        assert!(parse_sql("COPY (DELETE FROM t) TO 'out.csv'").is_err());
+        assert!(parse_sql("COPY (SELECT * FROM t) FROM 'out.csv'").is_err());
+        assert!(parse_sql("COPY (SELECT * FROM t) TO 'out.csv' (QUOTE NEVER)").is_err());
+    }
+
+    #[test]
+    fn test_visit_expressions_mut() {
+        let mut statement = parse_sql(
+            "SELECT a + ? FROM t JOIN u ON t.id = u.id + ? WHERE b IN (1, ?) ORDER BY UPPER(?)"
+        ).unwrap();
+        let mut parameters = Vec::new();
+        statement.visit_expressions_mut(&mut |expr| {
+            if let Expression::Parameter(index) = expr {
+                parameters.push(*index);
+                *expr = Expression::Literal(Value::Integer(0));
+            }
+        });
+        parameters.sort();
+        assert_eq!(parameters, vec![1, 2, 3, 4]);
+
+        let mut remaining = 0;
+        statement.visit_expressions_mut(&mut |expr| remaining += matches!(expr, Expression::Parameter(_)) as usize);
+        assert_eq!(remaining, 0);
+    }
+
+    #[test]
+    fn test_set_constraints() {
+        assert_eq!(parse_sql("SET CONSTRAINTS ALL DEFERRED").unwrap(), Statement::SetConstraints { deferred: true });
+        assert_eq!(parse_sql("set constraints all immediate").unwrap(), Statement::SetConstraints { deferred: false });
+        assert!(parse_sql("SET CONSTRAINTS fk_a DEFERRED").is_err());
+        assert!(parse_sql("SET CONSTRAINTS ALL LATER").is_err());
+    }
+
+    #[test]
+    fn test_set_variable() {
+        let set = |name: &str, value: &str| Statement::SetVariable { name: name.to_string(), value: value.to_string() };
+        assert_eq!(parse_sql("SET buffer_policy = 'clock'").unwrap(), set("buffer_policy", "clock"));
+        assert_eq!(parse_sql("set Buffer_Policy to LFU").unwrap(), set("buffer_policy", "LFU"));
+        assert_eq!(parse_sql("SET buffer_pool_size = 256;").unwrap(), set("buffer_pool_size", "256"));
+        assert_eq!(parse_sql("SET truncate_strings = ON").unwrap(), set("truncate_strings", "on"));
+        assert_eq!(parse_sql("SET truncate_strings TO false").unwrap(), set("truncate_strings", "false"));
+        assert!(parse_sql("SET buffer_policy").is_err());
+        assert!(parse_sql("SET buffer_policy = (1)").is_err());
+    }
+
+    #[test]
+    fn test_create_and_drop_view() {
+        match parse_sql("CREATE TEMP VIEW adults AS SELECT name FROM users WHERE age >= 18").unwrap() {
+            Statement::CreateView { view_name, query, temporary } => {
+                assert_eq!(view_name, "adults");
+                assert!(temporary);
+                assert!(matches!(*query, Statement::Select { .. }));
+            }
+            other => panic!("Expected CreateView, got {:?}", other),
+        }
+        assert!(matches!(
+            parse_sql("create temporary view v as select * from t").unwrap(),
+            Statement::CreateView { temporary: true, .. }
+        ));
+        assert!(matches!(parse_sql("CREATE VIEW v AS SELECT * FROM t").unwrap(), Statement::CreateView { temporary: false, .. }));
+        assert!(parse_sql("CREATE TEMP VIEW v AS DELETE FROM t").is_err());
+        assert!(parse_sql("CREATE TEMP TABLE t (id INT)").is_err());
+        assert_eq!(
+            parse_sql("DROP VIEW IF EXISTS v").unwrap(),
+            Statement::DropView { view_name: "v".to_string(), if_exists: true }
+        );
+    }
+
+    #[test]
+    fn test_assert_statement() {
+        match parse_sql("ASSERT (SELECT COUNT(*) FROM users WHERE email IS NULL) = 0 MESSAGE 'emails required'").unwrap() {
+            Statement::Assert { query, condition, message } => {
+                match *query {
+                    Statement::Select { where_clause: Some(Expression::IsNull(_)), .. } => {}
+                    other => panic!("Expected SELECT with IS NULL, got {:?}", other),
+                }
+                assert_eq!(condition, AssertCondition::Compare {
+                    op: BinaryOperator::Equal,
+                    expected: Expression::Literal(Value::Integer(0)),
+                });
+                assert_eq!(message, Some("emails required".to_string()));
+            }
+            other => panic!("Expected ASSERT, got {:?}", other),
+        }
+        assert!(matches!(
+            parse_sql("ASSERT NOT EXISTS (SELECT id FROM users WHERE age IS NOT NULL AND age < 0)").unwrap(),
+            Statement::Assert { condition: AssertCondition::Exists { negated: true }, message: None, .. }
+        ));
+        assert!(parse_sql("ASSERT (DELETE FROM users) = 0").is_err());
+        assert!(parse_sql("ASSERT (SELECT COUNT(*) FROM users) LIKE 0").is_err());
+    }
+
+    #[test]
+    fn test_reindex_statement() {
+        assert_eq!(
+            parse_sql("REINDEX INDEX idx_email ON users").unwrap(),
+            Statement::Reindex {
+                target: ReindexTarget::Index { index_name: "idx_email".to_string(), table_name: "users".to_string() },
+            }
+        );
+        assert_eq!(
+            parse_sql("reindex table users;").unwrap(),
+            Statement::Reindex { target: ReindexTarget::Table("users".to_string()) }
+        );
+        assert!(parse_sql("REINDEX INDEX idx_email").is_err());
+        assert!(parse_sql("REINDEX users").is_err());
+    }
+
+    #[test]
+    fn test_show_indexes_statement() {
+        assert_eq!(parse_sql("SHOW INDEXES").unwrap(), Statement::ShowIndexes { table_name: None });
+        assert_eq!(
+            parse_sql("show index from users;").unwrap(),
+            Statement::ShowIndexes { table_name: Some("users".to_string()) }
+        );
+        assert_eq!(
+            parse_sql("SHOW INDEXES IN users").unwrap(),
+            Statement::ShowIndexes { table_name: Some("users".to_string()) }
+        );
+        assert!(parse_sql("SHOW TABLES").is_err());
+        assert!(parse_sql("SHOW INDEXES FROM").is_err());
+    }
+
+    #[test]
+    fn test_analyze_statement() {
+        assert_eq!(parse_sql("ANALYZE").unwrap(), Statement::Analyze { table_name: None });
+        assert_eq!(parse_sql("analyze users;").unwrap(), Statement::Analyze { table_name: Some("users".to_string()) });
+        assert!(matches!(parse_sql("EXPLAIN ANALYZE SELECT 1").unwrap(), Statement::Explain { analyze: true, .. }));
+    }
+
+    #[test]
+    fn test_transaction_statements() {
+        assert_eq!(parse_sql("BEGIN").unwrap(), Statement::Begin);
+        assert_eq!(parse_sql("begin transaction;").unwrap(), Statement::Begin);
+        assert_eq!(parse_sql("START TRANSACTION").unwrap(), Statement::Begin);
+        assert_eq!(parse_sql("COMMIT WORK").unwrap(), Statement::Commit);
+        assert_eq!(parse_sql("END").unwrap(), Statement::Commit);
+        assert_eq!(parse_sql("ROLLBACK").unwrap(), Statement::Rollback);
+        assert!(parse_sql("START").is_err());
+    }
+
+    #[test]
+    fn test_explain_analyze() {
+        match parse_sql("EXPLAIN ANALYZE SELECT * FROM t").unwrap() {
+            Statement::Explain { statement, analyze } => {
+                assert!(analyze);
+                assert!(matches!(*statement, Statement::Select { .. }));
+            }
+            _ => panic!("Expected Explain statement"),
+        }
+        assert!(matches!(parse_sql("explain analyze DELETE FROM t").unwrap(), Statement::Explain { analyze: true, .. }));
+        assert!(matches!(parse_sql("EXPLAIN SELECT 1").unwrap(), Statement::Explain { analyze: false, .. }));
+        assert!(parse_sql("EXPLAIN ANALYZE").is_err());
+    }
+
+    #[test]
+    fn test_advise_indexes() {
+        assert_eq!(parse_sql("ADVISE INDEXES").unwrap(), Statement::AdviseIndexes);
+        assert_eq!(parse_sql("advise indexes;").unwrap(), Statement::AdviseIndexes);
+        assert!(parse_sql("ADVISE TABLES").is_err());
+    }
+
+    #[test]
+    fn test_checkpoint() {
+        assert_eq!(parse_sql("CHECKPOINT").unwrap(), Statement::Checkpoint);
+        assert_eq!(parse_sql("checkpoint;").unwrap(), Statement::Checkpoint);
+    }
+
+    #[test]
+    fn test_backup() {
+        assert_eq!(parse_sql("BACKUP TO 'backups/monday'").unwrap(), Statement::Backup { path: "backups/monday".to_string() });
+        assert_eq!(parse_sql("backup to 'b';").unwrap(), Statement::Backup { path: "b".to_string() });
+        assert!(parse_sql("BACKUP 'b'").is_err());
+        assert!(parse_sql("BACKUP TO b").is_err());
+    }
+
+    #[test]
+    fn test_deduplicate() {
+        assert_eq!(
+            parse_sql("DEDUPLICATE TABLE logs").unwrap(),
+            Statement::Deduplicate { table_name: "logs".to_string() }
+        );
+        assert!(parse_sql("DEDUPLICATE logs").is_err());
+    }
+
+    #[test]
+    fn test_like() {
+        match parse_sql("SELECT * FROM t WHERE name NOT LIKE 'a!%%' ESCAPE '!' AND id > 1").unwrap() {
+            Statement::Select { where_clause: Some(Expression::BinaryOp { left, op: BinaryOperator::And, .. }), .. } => {
+                match *left {
+                    Expression::Like { expr, pattern, escape, negated } => {
+                        assert_eq!(*expr, Expression::Column("name".to_string()));
+                        assert_eq!(*pattern, Expression::Literal(Value::Varchar("a!%%".to_string())));
+                        assert_eq!(escape.as_deref(), Some(&Expression::Literal(Value::Varchar("!".to_string()))));
+                        assert!(negated);
+                    }
+                    other => panic!("Expected LIKE expression, got {:?}", other),
+                }
+            }
+            other => panic!("Expected SELECT with AND, got {:?}", other),
+        }
+        assert!(parse_sql("SELECT * FROM t WHERE name LIKE").is_err());
+    }
+
+    #[test]
+    fn test_in_and_between() {
+        match parse_sql("SELECT * FROM t WHERE a NOT IN (1, 2, 3)").unwrap() {
+            Statement::Select { where_clause: Some(Expression::In { list, negated, .. }), .. } => {
+                assert_eq!(list.len(), 3);
+                assert!(negated);
+            }
+            other => panic!("Expected IN expression, got {:?}", other),
+        }
+
+        // BETWEEN 的 AND 不与外层 AND 混淆
+        match parse_sql("SELECT * FROM t WHERE a BETWEEN 1 AND 10 AND b = 2").unwrap() {
+            Statement::Select { where_clause: Some(Expression::BinaryOp { left, op: BinaryOperator::And, .. }), .. } => {
+                match *left {
+                    Expression::Between { low, high, negated, .. } => {
+                        assert_eq!(*low, Expression::Literal(Value::Integer(1)));
+                        assert_eq!(*high, Expression::Literal(Value::Integer(10)));
+                        assert!(!negated);
+                    }
+                    other => panic!("Expected BETWEEN expression, got {:?}", other),
+                }
+            }
+            other => panic!("Expected SELECT with AND, got {:?}", other),
+        }
+
+        assert!(parse_sql("SELECT * FROM t WHERE a IN ()").is_err());
+        assert!(parse_sql("SELECT * FROM t WHERE a BETWEEN 1").is_err());
+    }
+
     #[test]
     fn test_select_simple() {
         let sql = "SELECT * FROM users";
@@ -1631,7 +3801,7 @@ mod tests {
         let stmt = parse_sql(sql).unwrap();
         
         match stmt {
-            Statement::Insert { table_name, columns, values } => {
+            Statement::Insert { table_name, columns, values, .. } => {
                 assert_eq!(table_name, "users");
                 
                 let columns = columns.unwrap();
@@ -1665,13 +3835,50 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_insert_on_conflict() {
+        let sql = "INSERT INTO users VALUES (1, 'Alice') ON CONFLICT DO NOTHING";
+        match parse_sql(sql).unwrap() {
+            Statement::Insert { on_conflict, .. } => {
+                let on_conflict = on_conflict.expect("Expected ON CONFLICT clause");
+                assert!(on_conflict.target.is_empty());
+                assert_eq!(on_conflict.action, ConflictAction::DoNothing);
+            }
+            _ => panic!("Expected Insert statement"),
+        }
+
+        let sql = "INSERT INTO users VALUES (1, 'Alice') ON CONFLICT (id) DO UPDATE SET name = excluded.name WHERE id > 0";
+        match parse_sql(sql).unwrap() {
+            Statement::Insert { on_conflict, .. } => {
+                let on_conflict = on_conflict.expect("Expected ON CONFLICT clause");
+                assert_eq!(on_conflict.target, vec!["id"]);
+                match on_conflict.action {
+                    ConflictAction::DoUpdate { assignments, where_clause } => {
+                        assert_eq!(assignments.len(), 1);
+                        assert_eq!(assignments[0].column, "name");
+                        assert_eq!(
+                            assignments[0].value,
+                            Expression::QualifiedColumn {
+                                table: "excluded".to_string(),
+                                column: "name".to_string(),
+                            }
+                        );
+                        assert!(where_clause.is_some());
+                    }
+                    _ => panic!("Expected DO UPDATE action"),
+                }
+            }
+            _ => panic!("Expected Insert statement"),
+        }
+    }
+
     #[test]
     fn test_update() {
         let sql = "UPDATE users SET age = 26 WHERE name = 'Alice'";
         let stmt = parse_sql(sql).unwrap();
         
         match stmt {
-            Statement::Update { table_name, assignments, where_clause } => {
+            Statement::Update { table_name, assignments, where_clause, .. } => {
                 assert_eq!(table_name, "users");
                 
                 assert_eq!(assignments.len(), 1);
@@ -1694,7 +3901,7 @@ mod tests {
         let stmt = parse_sql(sql).unwrap();
         
         match stmt {
-            Statement::Delete { table_name, where_clause } => {
+            Statement::Delete { table_name, where_clause, .. } => {
                 assert_eq!(table_name, "users");
                 assert!(where_clause.is_some());
             }
@@ -1702,6 +3909,49 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_returning_clause() {
+        match parse_sql("DELETE FROM users WHERE age < 18 RETURNING id, name AS n").unwrap() {
+            Statement::Delete { returning: Some(SelectList::Expressions(exprs)), .. } => {
+                assert_eq!(exprs.len(), 2);
+                assert_eq!(exprs[1].alias, Some("n".to_string()));
+            }
+            other => panic!("Expected Delete with RETURNING, got {:?}", other),
+        }
+
+        match parse_sql("UPDATE users SET age = 1 RETURNING *").unwrap() {
+            Statement::Update { returning, .. } => assert_eq!(returning, Some(SelectList::Wildcard)),
+            other => panic!("Expected Update statement, got {:?}", other),
+        }
+
+        match parse_sql("INSERT INTO users VALUES (1) RETURNING id").unwrap() {
+            Statement::Insert { returning, .. } => assert!(returning.is_some()),
+            other => panic!("Expected Insert statement, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_update_delete_all() {
+        match parse_sql("DELETE ALL FROM users").unwrap() {
+            Statement::Delete { table_name, where_clause, all_rows, .. } => {
+                assert_eq!(table_name, "users");
+                assert!(where_clause.is_none());
+                assert!(all_rows);
+            }
+            other => panic!("Expected Delete statement, got {:?}", other),
+        }
+
+        match parse_sql("UPDATE ALL users SET age = 1").unwrap() {
+            Statement::Update { table_name, all_rows, .. } => {
+                assert_eq!(table_name, "users");
+                assert!(all_rows);
+            }
+            other => panic!("Expected Update statement, got {:?}", other),
+        }
+
+        assert!(matches!(parse_sql("DELETE FROM users").unwrap(), Statement::Delete { all_rows: false, .. }));
+    }
+
     #[test]
     fn test_drop_table() {
         let sql = "DROP TABLE users";
@@ -1729,4 +3979,26 @@ mod tests {
             _ => panic!("Expected Select statement"),
         }
     }
+
+    #[test]
+    fn test_subquery_predicates() {
+        let stmt = parse_sql("SELECT * FROM a WHERE id NOT IN (SELECT a_id FROM b) AND NOT EXISTS (SELECT 1 FROM c)").unwrap();
+        let Statement::Select { where_clause: Some(Expression::BinaryOp { left, op: BinaryOperator::And, right }), .. } = stmt else {
+            panic!("Expected AND of two subquery predicates");
+        };
+        match *left {
+            Expression::InSubquery { expr, subquery, negated } => {
+                assert_eq!(*expr, Expression::Column("id".to_string()));
+                assert!(negated);
+                assert!(matches!(*subquery, Statement::Select { from_clause: Some(FromClause::Table(ref table)), .. } if table == "b"));
+            }
+            other => panic!("Expected IN subquery, got {:?}", other),
+        }
+        assert!(matches!(*right, Expression::UnaryOp { op: UnaryOperator::Not, ref expr } if matches!(**expr, Expression::Exists(_))));
+        assert!(right.contains_subquery());
+
+        // IN 后面的值列表不是子查询
+        let stmt = parse_sql("SELECT * FROM a WHERE id IN (1, 2)").unwrap();
+        assert!(matches!(stmt, Statement::Select { where_clause: Some(Expression::In { .. }), .. }));
+    }
 }
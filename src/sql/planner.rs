@@ -3,10 +3,20 @@
 //! 将已分析的 SQL 语句转换为可执行的查询计划。
 //! 规划器执行查询优化并生成可由查询执行器执行的操作符树。
 
-use crate::engine::executor::AggregateFunction;
+use crate::engine::capabilities::Capability;
+use crate::engine::database::ExecutionError;
+use crate::engine::executor::{
+    AggregateFunction, Executor, FilterExecutor, GroupByExecutor, HashJoinExecutor, LimitExecutor, MergeJoinExecutor,
+    NestedLoopJoinExecutor, Predicate, ProjectExecutor, ScanExecutor, ScanRows, SortExecutor, SortOrder,
+};
+use crate::engine::observer::OperatorInfo;
+use crate::engine::statistics::TableStatistics;
 use crate::sql::analyzer::AnalyzedStatement;
-use crate::sql::parser::{Expression, FromClause, SelectList, Statement};
-use crate::types::{DataType, Schema};
+use crate::sql::parser::{Expression, FromClause, SelectExpr, SelectList, Statement};
+use crate::storage::StatementTempSpace;
+use crate::types::{ColumnDefinition, DataType, Schema, Tuple, Value};
+use std::borrow::Cow;
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use thiserror::Error;
 
@@ -20,11 +30,26 @@ pub enum ExecutionPlan {
         filter: Option<Expression>,
     },
 
-    /// 使用索引扫描表
+    /// 使用索引扫描表：按 condition 查找索引，再对取出的行求值 filter
     IndexScan {
         table_name: String,
+        schema: Schema,
+        index_name: String,
+        condition: Option<Expression>,
+        filter: Option<Expression>,
+        /// 预计由索引取出的行数
+        estimated_rows: usize,
+    },
+
+    /// 仅索引扫描：查询用到的列都在索引的键列和 INCLUDE 列中，由索引构造行而不读表数据
+    IndexOnlyScan {
+        table_name: String,
+        schema: Schema,
         index_name: String,
         condition: Option<Expression>,
+        filter: Option<Expression>,
+        /// 预计由索引取出的行数
+        estimated_rows: usize,
     },
 
     /// 投影特定列
@@ -39,6 +64,16 @@ pub enum ExecutionPlan {
         condition: Expression,
     },
 
+    /// 按子查询过滤行：对输入的每一行执行一次子查询（可以引用该行的列），EXISTS 时保留子查询有结果的行，
+    /// IN 时保留 operand 等于子查询某个结果的行；negated 为 NOT EXISTS / NOT IN
+    Apply {
+        input: Box<ExecutionPlan>,
+        subquery: Box<ExecutionPlan>,
+        /// IN 子查询左侧的表达式；EXISTS 为 None
+        operand: Option<Expression>,
+        negated: bool,
+    },
+
     /// 向表中插入数据
     Insert {
         table_name: String,
@@ -74,6 +109,8 @@ pub enum ExecutionPlan {
         right: Box<ExecutionPlan>,
         join_type: JoinType,
         condition: Option<Expression>,
+        /// 连接算法；哈希连接由左输入建表
+        algorithm: JoinAlgorithm,
     },
 
     /// 排序输入
@@ -102,6 +139,8 @@ pub enum ExecutionPlan {
         table_name: String,
         columns: Vec<String>,
         is_unique: bool,
+        include: Vec<String>,
+        where_clause: Option<Expression>,
     },
 
     /// 删除索引
@@ -111,9 +150,10 @@ pub enum ExecutionPlan {
         if_exists: bool,
     },
 
-    /// 解释查询计划
+    /// 解释查询计划；analyze 为真时实际执行语句
     Explain {
         statement: Box<Statement>,
+        analyze: bool,
     },
 }
 
@@ -139,6 +179,32 @@ pub enum JoinType {
     Left,
     Right,
     Full,
+    /// 半连接：输出在右输入中有匹配的左输入行（每行一次），只含左输入的列
+    Semi,
+    /// 反连接：输出在右输入中没有匹配的左输入行，只含左输入的列
+    Anti,
+}
+
+/// 连接算法
+#[derive(Debug, Clone, PartialEq)]
+pub enum JoinAlgorithm {
+    /// 由左输入建哈希表，逐行探测右输入；需要等值连接条件
+    Hash,
+    /// 对每对行求值连接条件
+    NestedLoop,
+    /// 两个输入都按连接键升序排列时同步向前归并；只能用于全部由 `左列 = 右列` 组成的连接条件
+    Merge,
+}
+
+impl JoinAlgorithm {
+    /// EXPLAIN 和执行统计中显示的算子名称
+    pub fn operator_name(&self) -> &'static str {
+        match self {
+            JoinAlgorithm::Hash => "Hash Join",
+            JoinAlgorithm::NestedLoop => "Nested Loop Join",
+            JoinAlgorithm::Merge => "Merge Join",
+        }
+    }
 }
 
 /// 排序键规格
@@ -148,6 +214,114 @@ pub struct SortKey {
     pub descending: bool,
 }
 
+/// 选择访问路径时查询的表信息，由保存表数据的一方提供
+pub trait AccessPathCatalog {
+    /// 表的行数
+    fn table_row_count(&self, table_name: &str) -> Option<usize>;
+
+    /// 表上可回答过滤条件的最佳索引查找；没有可用索引时为 None
+    fn index_access(&self, table_name: &str, filter: &Expression) -> Option<IndexAccess>;
+
+    /// ANALYZE 收集的表统计；没有统计时优化器使用默认选择性
+    fn table_statistics(&self, _table_name: &str) -> Option<&TableStatistics> {
+        None
+    }
+}
+
+/// 一次可用的索引查找
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexAccess {
+    pub index_name: String,
+    /// 由索引回答的条件（AND 连接的 `列 = 常量`）
+    pub condition: Expression,
+    /// 预计取出的行数
+    pub estimated_rows: usize,
+    /// 索引中保存的列（键列和 INCLUDE 列）
+    pub columns: Vec<String>,
+}
+
+/// SELECT 扫描的数据源，由 [`OperatorContext::scan_input`] 提供
+pub struct ScanInput<'a> {
+    /// 扫描的行：WHERE 可以用索引时只含索引找到的行，否则在扫描到时才逐行读取；查询引用 rowid 伪列时附加在行末
+    pub rows: ScanRows<'a>,
+    /// rows 的模式
+    pub schema: Cow<'a, Schema>,
+    /// 表自身的列数（不含 rowid 伪列），SELECT * 输出这些列
+    pub table_columns: usize,
+    /// 表的总行数
+    pub total_rows: usize,
+}
+
+/// ORDER BY 的运行设置
+pub struct SortSettings {
+    /// 排序键相同的行再依次比较整行的值，使输出顺序确定
+    pub deterministic: bool,
+    /// 估计大小超过此值时把行写入临时文件
+    pub work_mem_bytes: usize,
+    /// 本语句的临时空间；None 时只在内存中排序
+    pub space: Option<StatementTempSpace>,
+}
+
+/// 把查询实例化为算子树时由执行引擎提供的数据访问、求值和运行设置
+///
+/// 规划器决定算子的种类和顺序，与数据库状态相关的逻辑都通过这里以闭包的形式交给算子。
+pub trait OperatorContext {
+    /// 表或视图的数据源；where_clause 用于选择索引，used 为 SELECT 列表之外查询用到的表达式
+    fn scan_input(
+        &self,
+        table_name: &str,
+        where_clause: Option<&Expression>,
+        select_list: &SelectList,
+        used: &[&Expression],
+    ) -> Result<ScanInput<'_>, ExecutionError>;
+
+    /// 对一行求值 WHERE 条件
+    fn matches(&self, condition: &Expression, row: &Tuple, schema: &Schema) -> Result<bool, ExecutionError>;
+
+    /// 对一行求值表达式
+    fn evaluate(&self, expr: &Expression, row: &Tuple, schema: &Schema) -> Result<Value, ExecutionError>;
+
+    /// SELECT 列表中是否有聚合函数
+    fn contains_aggregates(&self, select_list: &SelectList) -> bool;
+
+    /// 在 input 之上逐行计算 SELECT 列表
+    fn project<'a>(
+        &'a self,
+        input: Box<dyn Executor + 'a>,
+        select_exprs: Vec<SelectExpr>,
+        table_name: &str,
+    ) -> Result<Box<dyn Executor + 'a>, ExecutionError>;
+
+    /// 对已过滤的行分组聚合，返回结果行及其模式
+    fn aggregate(
+        &self,
+        rows: Vec<Tuple>,
+        schema: &Schema,
+        group_exprs: &[Expression],
+        select_list: &SelectList,
+    ) -> Result<(Vec<Tuple>, Schema), ExecutionError>;
+
+    /// ORDER BY 比较同一个排序键的两个值
+    fn compare(&self, a: &Value, b: &Value) -> Ordering;
+
+    fn sort_settings(&self) -> SortSettings;
+
+    /// 配置了观察者时为算子套上发送事件的包装器，否则原样返回
+    fn observed<'a>(&self, executor: Box<dyn Executor + 'a>, info: OperatorInfo) -> Box<dyn Executor + 'a>;
+
+    /// 优化器为查询选择的计划（访问路径、连接顺序和连接算法），与 EXPLAIN 显示的相同
+    fn optimized_plan(&self, query: Statement) -> Result<ExecutionPlan, ExecutionError>;
+}
+
+/// 为 SELECT 构建的算子树及生成结果消息所需的信息
+pub struct SelectOperators<'a> {
+    pub root: Box<dyn Executor + 'a>,
+    pub table_name: String,
+    /// 表的总行数
+    pub total_rows: usize,
+    pub is_aggregate: bool,
+}
+
 /// 查询规划器
 pub struct QueryPlanner {
     // 未来：这里可以添加基于成本的优化状态
@@ -221,6 +395,7 @@ impl QueryPlanner {
                 table_name,
                 columns,
                 values,
+                ..
             } => {
                 let schema = analyzed.table_schemas.get(&table_name).ok_or_else(|| {
                     PlanError::SchemaNotFound {
@@ -240,6 +415,7 @@ impl QueryPlanner {
                 table_name,
                 assignments,
                 where_clause,
+                ..
             } => {
                 let schema = analyzed.table_schemas.get(&table_name).ok_or_else(|| {
                     PlanError::SchemaNotFound {
@@ -266,6 +442,7 @@ impl QueryPlanner {
             Statement::Delete {
                 table_name,
                 where_clause,
+                ..
             } => {
                 let schema = analyzed.table_schemas.get(&table_name).ok_or_else(|| {
                     PlanError::SchemaNotFound {
@@ -285,11 +462,15 @@ impl QueryPlanner {
                 table_name,
                 columns,
                 is_unique,
+                include,
+                where_clause,
             } => Ok(ExecutionPlan::CreateIndex {
                 index_name,
                 table_name,
                 columns,
                 is_unique,
+                include,
+                where_clause,
             }),
 
             Statement::DropIndex {
@@ -302,8 +483,69 @@ impl QueryPlanner {
                 if_exists,
             }),
 
-            Statement::Explain { statement } => Ok(ExecutionPlan::Explain {
+            Statement::Explain { statement, analyze } => Ok(ExecutionPlan::Explain {
                 statement: Box::new(*statement),
+                analyze,
+            }),
+
+            Statement::DryRun { .. } => Err(PlanError::UnsupportedOperation {
+                operation: "DRY RUN".to_string(),
+            }),
+
+            Statement::Deduplicate { .. } => Err(PlanError::UnsupportedOperation {
+                operation: "DEDUPLICATE TABLE".to_string(),
+            }),
+
+            Statement::AlterTable { .. } => Err(PlanError::UnsupportedOperation {
+                operation: "ALTER TABLE".to_string(),
+            }),
+
+            Statement::Copy { .. } | Statement::CopyTo { .. } => Err(PlanError::UnsupportedOperation {
+                operation: "COPY".to_string(),
+            }),
+
+            Statement::AdviseIndexes => Err(PlanError::UnsupportedOperation {
+                operation: "ADVISE INDEXES".to_string(),
+            }),
+
+            Statement::Reindex { .. } => Err(PlanError::UnsupportedOperation {
+                operation: "REINDEX".to_string(),
+            }),
+
+            Statement::ShowIndexes { .. } => Err(PlanError::UnsupportedOperation {
+                operation: "SHOW INDEXES".to_string(),
+            }),
+
+            Statement::Analyze { .. } => Err(PlanError::UnsupportedOperation {
+                operation: "ANALYZE".to_string(),
+            }),
+
+            Statement::Checkpoint => Err(PlanError::UnsupportedOperation {
+                operation: "CHECKPOINT".to_string(),
+            }),
+
+            Statement::Backup { .. } => Err(PlanError::UnsupportedOperation {
+                operation: "BACKUP".to_string(),
+            }),
+
+            Statement::SetConstraints { .. } => Err(PlanError::UnsupportedOperation {
+                operation: "SET CONSTRAINTS".to_string(),
+            }),
+
+            Statement::SetVariable { .. } => Err(PlanError::UnsupportedOperation {
+                operation: "SET".to_string(),
+            }),
+
+            Statement::CreateView { .. } | Statement::DropView { .. } => Err(PlanError::UnsupportedOperation {
+                operation: "VIEW".to_string(),
+            }),
+
+            Statement::Assert { .. } => Err(PlanError::UnsupportedOperation {
+                operation: "ASSERT".to_string(),
+            }),
+
+            Statement::Begin | Statement::Commit | Statement::Rollback => Err(PlanError::UnsupportedOperation {
+                operation: "transaction control".to_string(),
             }),
         }
     }
@@ -332,12 +574,49 @@ impl QueryPlanner {
             });
         };
 
-        // Add WHERE filter if present
+        let tables: Vec<String> = crate::sql::cost::plan_tables(&plan).into_iter().map(str::to_string).collect();
+
+        // Add WHERE filter if present; IN / EXISTS subqueries among the AND-ed conditions become Apply nodes above it
         if let Some(condition) = where_clause {
-            plan = ExecutionPlan::Filter {
-                input: Box::new(plan),
-                condition,
-            };
+            let (subqueries, conditions): (Vec<Expression>, Vec<Expression>) = split_conjuncts(condition)
+                .into_iter()
+                .partition(|condition| subquery_predicate(condition).is_some());
+            if let Some(condition) = conditions.into_iter().reduce(|left, right| Expression::BinaryOp {
+                left: Box::new(left),
+                op: crate::sql::parser::BinaryOperator::And,
+                right: Box::new(right),
+            }) {
+                plan = ExecutionPlan::Filter {
+                    input: Box::new(plan),
+                    condition,
+                };
+            }
+            for condition in subqueries {
+                let Some((subquery, operand, negated)) = subquery_predicate(&condition) else {
+                    continue;
+                };
+                let Statement::Select { select_list, from_clause, where_clause, group_by, having, order_by, limit, offset } = subquery.clone() else {
+                    continue;
+                };
+                let subquery = self.plan_select_complete(
+                    select_list,
+                    from_clause,
+                    where_clause,
+                    group_by,
+                    having,
+                    order_by,
+                    limit,
+                    offset,
+                    table_schemas,
+                    expression_types,
+                )?;
+                plan = ExecutionPlan::Apply {
+                    input: Box::new(plan),
+                    subquery: Box::new(subquery),
+                    operand: operand.cloned(),
+                    negated,
+                };
+            }
         }
 
         // Add GROUP BY if present, or if SELECT list contains aggregate functions
@@ -355,7 +634,7 @@ impl QueryPlanner {
         }
 
         // Add projection
-        plan = self.plan_select_list(plan, select_list, table_schemas, expression_types)?;
+        plan = self.plan_select_list(plan, select_list, &tables, table_schemas, expression_types)?;
 
         // Add ORDER BY if present
         if let Some(order_exprs) = order_by {
@@ -422,7 +701,8 @@ impl QueryPlanner {
         match expr {
             Expression::FunctionCall { name, .. } => {
                 // Check if this is an aggregate function
-                matches!(name.to_uppercase().as_str(), "COUNT" | "SUM" | "AVG" | "MIN" | "MAX")
+                matches!(name.to_uppercase().as_str(), "COUNT" | "SUM" | "AVG" | "MIN" | "MAX" | "STRING_AGG" | "GROUP_CONCAT"
+                    | "STDDEV" | "STDDEV_SAMP" | "STDDEV_POP" | "VARIANCE" | "VAR_SAMP" | "VAR_POP")
             }
             // For other expression types, we can add recursive checks if needed
             _ => false
@@ -460,6 +740,15 @@ impl QueryPlanner {
                                     functions.push(AggregateFunction::Max(col.clone()));
                                 }
                             }
+                            "STRING_AGG" | "GROUP_CONCAT" => {
+                                if let Some(Expression::Column(col)) = args.first() {
+                                    let separator = match args.get(1) {
+                                        Some(Expression::Literal(Value::Varchar(sep))) => sep.clone(),
+                                        _ => ",".to_string(),
+                                    };
+                                    functions.push(AggregateFunction::StringAgg { column: col.clone(), separator });
+                                }
+                            }
                             _ => {}
                         }
                     }
@@ -536,6 +825,7 @@ impl QueryPlanner {
                     right: Box::new(right_plan),
                     join_type: plan_join_type,
                     condition,
+                    algorithm: JoinAlgorithm::Hash,
                 })
             }
         }
@@ -546,13 +836,14 @@ impl QueryPlanner {
         &self,
         input: ExecutionPlan,
         select_list: SelectList,
+        tables: &[String],
         table_schemas: &HashMap<String, Schema>,
         expression_types: &HashMap<String, DataType>,
     ) -> Result<ExecutionPlan, PlanError> {
         match select_list {
             SelectList::Wildcard => {
-                // SELECT * - include all columns from all tables
-                let columns = self.build_wildcard_projection(table_schemas)?;
+                // SELECT * - include all columns from the FROM tables, in FROM order
+                let columns = self.build_wildcard_projection(tables, table_schemas)?;
 
                 Ok(ExecutionPlan::Project {
                     input: Box::new(input),
@@ -593,7 +884,12 @@ impl QueryPlanner {
                 name: col.name.clone(),
                 data_type: col.data_type.clone(),
                 nullable: col.nullable,
-                default: None, // TODO: Evaluate default expressions to values
+                // 仅折叠字面量默认值；复杂表达式由执行引擎在建表时求值
+                default: match &col.default {
+                    Some(Expression::Literal(value)) => Some(value.clone()),
+                    _ => None,
+                },
+                default_function: None,
             })
             .collect();
 
@@ -620,11 +916,15 @@ impl QueryPlanner {
     /// 构建通配符投影（SELECT *）
     fn build_wildcard_projection(
         &self,
+        tables: &[String],
         table_schemas: &HashMap<String, Schema>,
     ) -> Result<Vec<ProjectColumn>, PlanError> {
         let mut columns = Vec::new();
 
-        for (table_name, schema) in table_schemas {
+        for table_name in tables {
+            let schema = table_schemas.get(table_name).ok_or_else(|| PlanError::SchemaNotFound {
+                table: table_name.clone(),
+            })?;
             for column_def in &schema.columns {
                 columns.push(ProjectColumn {
                     expression: Expression::QualifiedColumn {
@@ -666,12 +966,523 @@ impl QueryPlanner {
     }
 }
 
+impl QueryPlanner {
+    /// 为 SELECT 构建算子树：扫描（WHERE 过滤、列裁剪）-> 排序 -> 投影，聚合查询为扫描 -> 聚合 -> 排序；最后分页
+    ///
+    /// 不聚合时先排序再投影，ORDER BY 可以引用 SELECT 列表之外的列；聚合查询只能按输出列排序。
+    /// 算子树只借用 context，从根算子拉取一行才计算一行。
+    pub fn build_select<'a>(
+        &self,
+        query: Statement,
+        context: &'a dyn OperatorContext,
+    ) -> Result<SelectOperators<'a>, ExecutionError> {
+        let Statement::Select { select_list, from_clause, where_clause, group_by, having, order_by, limit, offset } = query else {
+            return Err(ExecutionError::SemanticError("Expected a SELECT statement".to_string()));
+        };
+
+        // 检测并报告高级功能
+        let mut detected_features = Vec::new();
+        if group_by.is_some() { detected_features.push("GROUP BY"); }
+        if having.is_some() { detected_features.push("HAVING"); }
+        if order_by.is_some() { detected_features.push("ORDER BY"); }
+        if limit.is_some() { detected_features.push("LIMIT"); }
+        if offset.is_some() { detected_features.push("OFFSET"); }
+
+        if !detected_features.is_empty() {
+            println!("🚀 执行高级SQL功能: {}", detected_features.join(", "));
+        }
+
+        // HAVING 尚未实现；直接报错，避免静默返回未经过滤的分组
+        if having.is_some() {
+            return Err(ExecutionError::NotImplemented {
+                feature: "HAVING".to_string(),
+                capability: Capability::Having,
+            });
+        }
+
+        // WHERE 中的子查询按优化器改写成的半连接或反连接执行；SELECT 列表中的子查询尚不支持，求值失败不能当作 NULL
+        let select_exprs = match &select_list {
+            SelectList::Expressions(exprs) => exprs.as_slice(),
+            SelectList::Wildcard => &[],
+        };
+        if select_exprs.iter().any(|select_expr| select_expr.expr.contains_subquery()) {
+            return Err(ExecutionError::NotImplemented {
+                feature: "Subqueries".to_string(),
+                capability: Capability::Subqueries,
+            });
+        }
+
+        // GROUP BY 或 SELECT 列表中的聚合函数都需要聚合阶段
+        let is_aggregate = group_by.is_some() || context.contains_aggregates(&select_list);
+        let group_exprs = group_by.unwrap_or_default();
+
+        // 单表查询直接扫描表（WHERE 可以用索引，可以引用 rowid 伪列）；连接和 WHERE 中有子查询的查询按优化器选择的计划执行，
+        // WHERE 已在其中
+        let where_subqueries = where_clause.as_ref().is_some_and(Expression::contains_subquery);
+        let (source_name, table_name, relation) = match from_clause {
+            Some(FromClause::Table(name)) if !where_subqueries => ("Scan".to_string(), name, None),
+            Some(from) => {
+                let mut from_order = Vec::new();
+                crate::sql::SemanticAnalyzer::collect_from_tables(&from, &mut from_order);
+                let relation = match context.optimized_plan(Statement::Select {
+                    select_list: SelectList::Wildcard,
+                    from_clause: Some(from),
+                    where_clause: where_clause.clone(),
+                    group_by: None,
+                    having: None,
+                    order_by: None,
+                    limit: None,
+                    offset: None,
+                })? {
+                    ExecutionPlan::Project { input, .. } => *input,
+                    plan => plan,
+                };
+                let tables = match from_order.as_slice() {
+                    [table] => table.clone(),
+                    _ => crate::sql::cost::plan_tables(&relation).join(", "),
+                };
+                (relation_operator_name(&relation), tables, Some((relation, from_order)))
+            }
+            None => return Err(ExecutionError::ParseError("Missing FROM clause".to_string())),
+        };
+
+        // 为各执行阶段编号：扫描（或连接）-> 聚合 -> 排序 -> 分页，后一阶段为前一阶段的父算子
+        let mut stage_names = vec![source_name.as_str()];
+        if is_aggregate { stage_names.push("Aggregate"); }
+        if order_by.is_some() { stage_names.push("Sort"); }
+        if limit.is_some() || offset.is_some() { stage_names.push("Limit"); }
+        let stage_info = |name: &str| {
+            let id = stage_names.iter().position(|stage| *stage == name).unwrap_or(0);
+            let mut info = OperatorInfo::new(id, name);
+            if id + 1 < stage_names.len() {
+                info = info.with_parent(id + 1);
+            }
+            if id == 0 { info.with_detail(table_name.clone()) } else { info }
+        };
+
+        // 不聚合时排序键对扫描出的行求值，也要从扫描中取出
+        let order_exprs = order_by.as_deref().unwrap_or_default();
+        let scanned_order_exprs: Vec<&Expression> = match is_aggregate {
+            true => Vec::new(),
+            false => order_exprs.iter().map(|order_expr| &order_expr.expr).collect(),
+        };
+
+        let (mut plan, table_columns, total_rows, trimmed_width) = match relation {
+            Some((relation, from_order)) => {
+                let mut builder = RelationBuilder { context, next_id: stage_names.len(), total_rows: 0 };
+                let mut root = builder.build(relation, stage_info(&source_name))?;
+
+                // 优化器可能交换或重排连接的输入，连接结果的列仍按 FROM 中表的书写顺序排列；
+                // FROM 只有一张表（子查询改写成了半连接或反连接）时列名与单表查询一样不带表名
+                let schema = root.schema().clone();
+                let table_of = |column: &ColumnDefinition| from_order.iter().position(|table| {
+                    column.name.strip_prefix(table.as_str()).is_some_and(|rest| rest.starts_with('.'))
+                });
+                let mut positions: Vec<usize> = (0..schema.columns.len()).collect();
+                positions.sort_by_key(|&i| table_of(&schema.columns[i]));
+                let single_table = from_order.len() == 1;
+                if single_table || positions.iter().enumerate().any(|(i, &position)| i != position) {
+                    let columns = positions.iter()
+                        .map(|&i| {
+                            let mut column = schema.columns[i].clone();
+                            if let Some(name) = column.name.strip_prefix(&format!("{}.", from_order[0])).filter(|_| single_table) {
+                                column.name = name.to_string();
+                            }
+                            column
+                        })
+                        .collect();
+                    root = Box::new(ProjectExecutor::new(root, Schema { columns, primary_key: None }, Box::new(move |row: Tuple| {
+                        Ok(Tuple::new(positions.iter().map(|&i| row.values[i].clone()).collect()))
+                    })));
+                }
+                let width = root.schema().columns.len();
+                (root, width, builder.total_rows, None)
+            }
+            None => {
+                let used: Vec<&Expression> = group_exprs.iter().chain(scanned_order_exprs.iter().copied()).collect();
+                let input = context.scan_input(&table_name, where_clause.as_ref(), &select_list, &used)?;
+                let (scan_schema, table_columns, total_rows) = (input.schema, input.table_columns, input.total_rows);
+
+                // 扫描只输出过滤之后还用得到的列；SELECT * 输出表的全部列，rowid 伪列只在排序用到时才带上，排序后去掉
+                let output_exprs = || group_exprs.iter()
+                    .chain(select_exprs.iter().map(|select_expr| &select_expr.expr))
+                    .chain(scanned_order_exprs.iter().copied());
+                let (scan_output, positions) = match &select_list {
+                    SelectList::Wildcard => {
+                        let positions: Vec<usize> = (0..scan_schema.columns.len())
+                            .filter(|&i| i < table_columns || scanned_order_exprs.iter().any(|expr| references_column(expr, &scan_schema.columns[i].name)))
+                            .collect();
+                        // 表的列在前且顺序不变，主键位置仍然有效
+                        let columns = positions.iter().map(|&i| scan_schema.columns[i].clone()).collect();
+                        (Schema { columns, primary_key: scan_schema.primary_key.clone() }, positions)
+                    }
+                    SelectList::Expressions(_) => referenced_columns(&scan_schema, output_exprs()),
+                };
+                let trimmed_width = (positions.len() > table_columns && select_exprs.is_empty()).then_some(table_columns);
+                let mut scan = ScanExecutor::new(input.rows, scan_output).with_columns(positions);
+                if let Some(expr) = where_clause {
+                    scan = scan.with_filter(Box::new(move |row: &Tuple| {
+                        // 求值失败的行视为不满足条件
+                        Ok(matches!(context.matches(&expr, row, &scan_schema), Ok(true)))
+                    }));
+                }
+                (context.observed(Box::new(scan), stage_info("Scan")), table_columns, total_rows, trimmed_width)
+            }
+        };
+
+        if is_aggregate {
+            let (group_exprs, select_list) = (group_exprs.clone(), select_list.clone());
+            let aggregate = GroupByExecutor::new(plan, Box::new(move |rows: Vec<Tuple>, schema: &Schema| {
+                Ok(context.aggregate(rows, schema, &group_exprs, &select_list)?)
+            }));
+            plan = context.observed(Box::new(aggregate), stage_info("Aggregate"));
+        }
+
+        if let Some(order_exprs) = &order_by {
+            // 排序键为 (行中的位置, 表达式)：有位置时直接取值，否则对输入行求值表达式。
+            // 聚合查询对输出行排序：ORDER BY n 取第 n 列，与 SELECT 表达式相同的取对应列，其余按输出列名求值。
+            // 否则对扫描出的行排序：ORDER BY n 和 SELECT 列表中的别名换成对应的 SELECT 表达式（SELECT * 时取表的第 n 列）
+            let output_width = if select_exprs.is_empty() { table_columns } else { select_exprs.len() };
+            let mut keys: Vec<(Option<usize>, Expression)> = Vec::new();
+            for order_expr in order_exprs {
+                let position = match &order_expr.expr {
+                    Expression::Literal(Value::Integer(n)) => Some(usize::try_from(*n).ok()
+                        .and_then(|n| n.checked_sub(1))
+                        .filter(|&index| index < output_width)
+                        .ok_or_else(|| ExecutionError::SemanticError(
+                            format!("ORDER BY position {} is not in select list", n)
+                        ))?),
+                    expr => select_exprs.iter().position(|select_expr| same_expression(&select_expr.expr, expr)),
+                };
+                let aliased = match &order_expr.expr {
+                    Expression::Column(name) => select_exprs.iter()
+                        .position(|select_expr| select_expr.alias.as_deref() == Some(name.as_str())),
+                    _ => None,
+                };
+                keys.push(match (is_aggregate, position.or(aliased)) {
+                    (true, position) => (position, order_expr.expr.clone()),
+                    (false, Some(index)) if !select_exprs.is_empty() => (None, select_exprs[index].expr.clone()),
+                    (false, position) => (position, order_expr.expr.clone()),
+                });
+            }
+            let sort_keys = move |row: &Tuple, schema: &Schema| {
+                keys.iter()
+                    .map(|(position, expr)| match *position {
+                        Some(index) => Ok(row.values.get(index).cloned().unwrap_or(Value::Null)),
+                        None => Ok(context.evaluate(expr, row, schema)?),
+                    })
+                    .collect()
+            };
+            let orders = order_exprs.iter()
+                .map(|order_expr| SortOrder { descending: order_expr.desc, nulls_first: order_expr.nulls_first })
+                .collect();
+
+            // 键相同的行顺序未指定；确定性模式下依次比较整行的值。
+            // 超出工作内存的行分批写入本语句的临时目录，最后归并
+            let settings = context.sort_settings();
+            let sort = SortExecutor::new(plan, orders, Box::new(sort_keys), |a: &Value, b: &Value| context.compare(a, b))
+                .with_tiebreak(settings.deterministic)
+                .with_spill(settings.work_mem_bytes, settings.space);
+            plan = context.observed(Box::new(sort), stage_info("Sort"));
+        }
+
+        if !is_aggregate {
+            if !select_exprs.is_empty() {
+                plan = context.project(plan, select_exprs.to_vec(), &table_name)?;
+            } else if let Some(width) = trimmed_width {
+                let mut schema = plan.schema().clone();
+                schema.columns.truncate(width);
+                plan = Box::new(ProjectExecutor::new(plan, schema, Box::new(move |mut row: Tuple| {
+                    row.values.truncate(width);
+                    Ok(row)
+                })));
+            }
+        }
+
+        if limit.is_some() || offset.is_some() {
+            let limit = LimitExecutor::new(plan, limit.unwrap_or(u64::MAX), offset.unwrap_or(0));
+            plan = context.observed(Box::new(limit), stage_info("Limit"));
+        }
+
+        Ok(SelectOperators { root: plan, table_name, total_rows, is_aggregate })
+    }
+}
+
 impl Default for QueryPlanner {
     fn default() -> Self {
         Self::new()
     }
 }
 
+/// 由优化后计划中 FROM / WHERE 的部分（扫描、过滤、排序和连接）构建的算子，每个计划节点是一个被观察的算子
+///
+/// 扫描输出的列名为 `表.列`，连接两侧的同名列因此可以区分；列引用按 [`column_index`] 解析。
+struct RelationBuilder<'a> {
+    context: &'a dyn OperatorContext,
+    /// 下一个算子的编号
+    next_id: usize,
+    /// 扫描的各表的总行数之和
+    total_rows: usize,
+}
+
+impl<'a> RelationBuilder<'a> {
+    /// 为 plan 构建算子，info 为它的算子信息
+    fn build(&mut self, plan: ExecutionPlan, info: OperatorInfo) -> Result<Box<dyn Executor + 'a>, ExecutionError> {
+        let context = self.context;
+        // 没能改写为连接的子查询（如 OR 中的子查询）尚不支持；过滤时求值失败会被当作 false，必须提前报错
+        let condition = match &plan {
+            ExecutionPlan::TableScan { filter, .. }
+            | ExecutionPlan::IndexScan { filter, .. }
+            | ExecutionPlan::IndexOnlyScan { filter, .. } => filter.as_ref(),
+            ExecutionPlan::Filter { condition, .. } => Some(condition),
+            ExecutionPlan::Join { condition, .. } => condition.as_ref(),
+            _ => None,
+        };
+        if matches!(plan, ExecutionPlan::Apply { .. }) || condition.is_some_and(Expression::contains_subquery) {
+            return Err(ExecutionError::NotImplemented {
+                feature: "Subqueries".to_string(),
+                capability: Capability::Subqueries,
+            });
+        }
+        let executor: Box<dyn Executor + 'a> = match plan {
+            ExecutionPlan::TableScan { table_name, schema, filter }
+            | ExecutionPlan::IndexScan { table_name, schema, filter, .. }
+            | ExecutionPlan::IndexOnlyScan { table_name, schema, filter, .. } => {
+                // 数据源按过滤条件自行选择索引，过滤条件仍对取出的每一行求值
+                let input = context.scan_input(&table_name, filter.as_ref(), &SelectList::Wildcard, &[])?;
+                self.total_rows += input.total_rows;
+                let source = input.schema.into_owned();
+                let positions = schema.columns.iter()
+                    .map(|column| {
+                        source.columns.iter().position(|candidate| candidate.name == column.name).ok_or_else(|| {
+                            ExecutionError::ColumnNotFound { table: table_name.clone(), column: column.name.clone() }
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                let columns = schema.columns.into_iter()
+                    .map(|mut column| {
+                        column.name = format!("{}.{}", table_name, column.name);
+                        column
+                    })
+                    .collect();
+                let mut scan = ScanExecutor::new(input.rows, Schema { columns, primary_key: None }).with_columns(positions);
+                if let Some(filter) = filter {
+                    scan = scan.with_filter(Box::new(move |row: &Tuple| {
+                        // 求值失败的行视为不满足条件
+                        Ok(matches!(context.matches(&filter, row, &source), Ok(true)))
+                    }));
+                }
+                Box::new(scan)
+            }
+            ExecutionPlan::Filter { input, condition } => {
+                let input = self.build_input(*input, &info)?;
+                let schema = input.schema().clone();
+                Box::new(FilterExecutor::new(input, Box::new(move |row: &Tuple| {
+                    Ok(matches!(context.matches(&condition, row, &schema), Ok(true)))
+                })))
+            }
+            ExecutionPlan::Sort { input, sort_keys } => {
+                let input = self.build_input(*input, &info)?;
+                let orders = sort_keys.iter()
+                    .map(|key| SortOrder { descending: key.descending, nulls_first: None })
+                    .collect();
+                let keys = move |row: &Tuple, schema: &Schema| {
+                    sort_keys.iter().map(|key| Ok(context.evaluate(&key.expression, row, schema)?)).collect()
+                };
+                let settings = context.sort_settings();
+                Box::new(SortExecutor::new(input, orders, Box::new(keys), |a: &Value, b: &Value| context.compare(a, b))
+                    .with_spill(settings.work_mem_bytes, settings.space))
+            }
+            ExecutionPlan::Join { left, right, join_type, condition, algorithm } => {
+                let merge_keys = match algorithm {
+                    JoinAlgorithm::Merge => condition.as_ref().and_then(|condition| crate::sql::cost::merge_join_keys(condition, &left, &right)),
+                    _ => None,
+                };
+                let (left, right) = (self.build_input(*left, &info)?, self.build_input(*right, &info)?);
+                let (left_schema, right_schema) = (left.schema().clone(), right.schema().clone());
+                let position = |schema: &Schema, expr: &Expression| match expr {
+                    Expression::Column(name) => column_index(schema, None, name),
+                    Expression::QualifiedColumn { table, column } => column_index(schema, Some(table), column),
+                    _ => None,
+                };
+                // 连接条件对左右两行拼接成的行求值
+                let predicate = condition.clone().map(|condition| -> Predicate<'a> {
+                    let schema = Schema {
+                        columns: left_schema.columns.iter().chain(&right_schema.columns).cloned().collect(),
+                        primary_key: None,
+                    };
+                    Box::new(move |row: &Tuple| Ok(matches!(context.matches(&condition, row, &schema), Ok(true))))
+                });
+                match algorithm {
+                    JoinAlgorithm::Merge => {
+                        let (left_keys, right_keys) = merge_keys.iter().flatten()
+                            .map(|(left_key, right_key)| Some((position(&left_schema, left_key)?, position(&right_schema, right_key)?)))
+                            .collect::<Option<Vec<_>>>()
+                            .ok_or_else(|| ExecutionError::SemanticError("Cannot resolve merge join keys".to_string()))?
+                            .into_iter()
+                            .unzip();
+                        Box::new(MergeJoinExecutor::new(left, right, join_type, left_keys, right_keys)?)
+                    }
+                    JoinAlgorithm::Hash => {
+                        // 一侧一列的等值条件作为哈希键，键相同的行再求值整个连接条件
+                        let (left_keys, right_keys) = condition.iter()
+                            .flat_map(crate::sql::cost::equi_join_keys)
+                            .filter_map(|(a, b)| match (position(&left_schema, a), position(&right_schema, b)) {
+                                (Some(left_key), Some(right_key)) => Some((left_key, right_key)),
+                                _ => Some((position(&left_schema, b)?, position(&right_schema, a)?)),
+                            })
+                            .unzip();
+                        Box::new(HashJoinExecutor::new(left, right, join_type, left_keys, right_keys, predicate)?)
+                    }
+                    JoinAlgorithm::NestedLoop => Box::new(NestedLoopJoinExecutor::new(left, right, join_type, predicate)),
+                }
+            }
+            plan => {
+                return Err(ExecutionError::SemanticError(format!("Unexpected {} in FROM clause", relation_operator_name(&plan))));
+            }
+        };
+        Ok(context.observed(executor, info))
+    }
+
+    /// 构建 parent 的一个输入
+    fn build_input(&mut self, plan: ExecutionPlan, parent: &OperatorInfo) -> Result<Box<dyn Executor + 'a>, ExecutionError> {
+        let mut info = OperatorInfo::new(self.next_id, relation_operator_name(&plan)).with_parent(parent.id);
+        self.next_id += 1;
+        let tables = crate::sql::cost::plan_tables(&plan);
+        if !tables.is_empty() {
+            info = info.with_detail(tables.join(", "));
+        }
+        self.build(plan, info)
+    }
+}
+
+/// FROM / WHERE 部分的计划节点对应的算子名称
+fn relation_operator_name(plan: &ExecutionPlan) -> String {
+    match plan {
+        ExecutionPlan::TableScan { .. } | ExecutionPlan::IndexScan { .. } | ExecutionPlan::IndexOnlyScan { .. } => "Scan".to_string(),
+        ExecutionPlan::Filter { .. } => "Filter".to_string(),
+        ExecutionPlan::Sort { .. } => "Sort".to_string(),
+        ExecutionPlan::Apply { .. } => "Subquery Filter".to_string(),
+        ExecutionPlan::Join { join_type, algorithm, .. } => format!("{} ({:?})", algorithm.operator_name(), join_type),
+        plan => format!("{:?}", plan).split([' ', '{', '(']).next().unwrap_or_default().to_string(),
+    }
+}
+
+/// 列裁剪：schema 中被 exprs 引用的列组成的 schema，及这些列在原行中的位置
+fn referenced_columns<'a>(schema: &Schema, exprs: impl IntoIterator<Item = &'a Expression>) -> (Schema, Vec<usize>) {
+    let exprs: Vec<&Expression> = exprs.into_iter().collect();
+    let positions: Vec<usize> = (0..schema.columns.len())
+        .filter(|&i| exprs.iter().any(|expr| references_column(expr, &schema.columns[i].name)))
+        .collect();
+    let columns = positions.iter().map(|&i| schema.columns[i].clone()).collect();
+    (Schema { columns, primary_key: None }, positions)
+}
+
+/// 两个表达式是否相同（函数名不区分大小写），用于把 SELECT 表达式匹配到分组键
+pub(crate) fn same_expression(a: &Expression, b: &Expression) -> bool {
+    match (a, b) {
+        (Expression::FunctionCall { name: a_name, args: a_args }, Expression::FunctionCall { name: b_name, args: b_args }) => {
+            a_name.eq_ignore_ascii_case(b_name)
+                && a_args.len() == b_args.len()
+                && a_args.iter().zip(b_args).all(|(a, b)| same_expression(a, b))
+        }
+        (Expression::BinaryOp { left: a_left, op: a_op, right: a_right }, Expression::BinaryOp { left: b_left, op: b_op, right: b_right }) => {
+            a_op == b_op && same_expression(a_left, b_left) && same_expression(a_right, b_right)
+        }
+        (Expression::UnaryOp { op: a_op, expr: a_expr }, Expression::UnaryOp { op: b_op, expr: b_expr }) => {
+            a_op == b_op && same_expression(a_expr, b_expr)
+        }
+        (Expression::Cast { expr: a_expr, data_type: a_type }, Expression::Cast { expr: b_expr, data_type: b_type }) => {
+            a_type == b_type && same_expression(a_expr, b_expr)
+        }
+        (Expression::QualifiedColumn { column: a_column, .. }, Expression::Column(b_column))
+        | (Expression::Column(a_column), Expression::QualifiedColumn { column: b_column, .. }) => a_column == b_column,
+        (a, b) => a == b,
+    }
+}
+
+/// 模式中名为 column 的列是否就是列引用 `[table.]name` 所指的列
+///
+/// 连接输出的列名为 `表.列`：限定列要求表名也相同，非限定列只比较列名部分。
+pub(crate) fn names_column(table: Option<&str>, name: &str, column: &str) -> bool {
+    match column.rsplit_once('.') {
+        Some((column_table, column_name)) => column_name == name && table.is_none_or(|table| table == column_table),
+        None => column == name,
+    }
+}
+
+/// 列引用 `[table.]name` 在模式中的位置：先找名字完全相同的列（限定列为 `表.列`），再按 [`names_column`]
+/// 找唯一的一列（单表的列名不带表名，限定列也按列名匹配）；非限定列同时匹配连接两侧的列时有歧义，为 None
+pub(crate) fn column_index(schema: &Schema, table: Option<&str>, name: &str) -> Option<usize> {
+    let exact = match table {
+        Some(table) => format!("{}.{}", table, name),
+        None => name.to_string(),
+    };
+    if let Some(index) = schema.columns.iter().position(|column| column.name == exact) {
+        return Some(index);
+    }
+    let mut matches = schema.columns.iter().enumerate().filter(|(_, column)| names_column(table, name, &column.name));
+    match (matches.next(), matches.next()) {
+        (Some((index, _)), None) => Some(index),
+        _ => None,
+    }
+}
+
+/// 表达式中是否引用了指定列；column 为模式中的列名（见 [`names_column`]）
+pub(crate) fn references_column(expr: &Expression, column: &str) -> bool {
+    match expr {
+        Expression::Column(name) => names_column(None, name, column),
+        Expression::QualifiedColumn { table, column: name } => names_column(Some(table), name, column),
+        Expression::BinaryOp { left, right, .. } => {
+            references_column(left, column) || references_column(right, column)
+        }
+        Expression::UnaryOp { expr, .. }
+        | Expression::Cast { expr, .. }
+        | Expression::IsNull(expr)
+        | Expression::IsNotNull(expr) => references_column(expr, column),
+        Expression::FunctionCall { args, .. } => args.iter().any(|arg| references_column(arg, column)),
+        Expression::In { expr, list, .. } => {
+            references_column(expr, column) || list.iter().any(|item| references_column(item, column))
+        }
+        Expression::Between { expr, low, high, .. } => {
+            [expr, low, high].iter().any(|e| references_column(e, column))
+        }
+        Expression::Like { expr, pattern, escape, .. } => {
+            references_column(expr, column)
+                || references_column(pattern, column)
+                || escape.as_deref().is_some_and(|e| references_column(e, column))
+        }
+        // 子查询中的列属于子查询自己的作用域
+        Expression::InSubquery { expr, .. } => references_column(expr, column),
+        Expression::Literal(_) | Expression::Default | Expression::Parameter(_) | Expression::Exists(_) => false,
+    }
+}
+
+/// 按 AND 拆开条件
+fn split_conjuncts(condition: Expression) -> Vec<Expression> {
+    match condition {
+        Expression::BinaryOp { left, op: crate::sql::parser::BinaryOperator::And, right } => {
+            let mut conjuncts = split_conjuncts(*left);
+            conjuncts.extend(split_conjuncts(*right));
+            conjuncts
+        }
+        condition => vec![condition],
+    }
+}
+
+/// 条件为 [NOT] IN / [NOT] EXISTS 子查询时返回（子查询，IN 左侧的表达式，是否取反）
+fn subquery_predicate(condition: &Expression) -> Option<(&Statement, Option<&Expression>, bool)> {
+    match condition {
+        Expression::InSubquery { expr, subquery, negated } => Some((subquery, Some(expr), *negated)),
+        Expression::Exists(subquery) => Some((subquery, None, false)),
+        Expression::UnaryOp { op: crate::sql::parser::UnaryOperator::Not, expr } => match expr.as_ref() {
+            Expression::Exists(subquery) => Some((subquery, None, true)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -689,18 +1500,21 @@ mod tests {
                     data_type: DataType::Integer,
                     nullable: false,
                     default: None,
+                    default_function: None,
                 },
                 ColumnDefinition {
                     name: "name".to_string(),
                     data_type: DataType::Varchar(255),
                     nullable: false,
                     default: None,
+                    default_function: None,
                 },
                 ColumnDefinition {
                     name: "age".to_string(),
                     data_type: DataType::Integer,
                     nullable: true,
                     default: None,
+                    default_function: None,
                 },
             ],
             primary_key: None, // Test schema without primary key
@@ -0,0 +1,255 @@
+//! 用户定义的查询改写规则
+//!
+//! 规则在语义分析和规划之前作用于语法树，用于平滑模式迁移：表或列改名后，
+//! 仍使用旧名称的应用不修改 SQL 也能继续工作；也可以为某张表上的查询强制加上 LIMIT。
+//!
+//! 规则只作用于查询和 DML（SELECT / INSERT / UPDATE / DELETE 以及 EXPLAIN、DRY RUN 包装的语句和视图、断言中的查询），
+//! DDL 始终使用真实名称，以免旧名称的 DROP TABLE 之类的语句误删新表。
+
+use crate::sql::parser::{ConflictAction, Expression, FromClause, OnConflict, SelectList, Statement};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// 一条改写规则
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RewriteRule {
+    /// 把对表 from 的引用改为表 to
+    RenameTable { from: String, to: String },
+    /// 在引用了 table 的语句中把列 from 改为列 to；SELECT 列表中未加别名的旧列名保留为别名，
+    /// 因此结果列名不变
+    RenameColumn { table: String, from: String, to: String },
+    /// 读取 table 的 SELECT 最多返回 limit 行（已有更小的 LIMIT 时不变）
+    ForceLimit { table: String, limit: u64 },
+}
+
+impl fmt::Display for RewriteRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RewriteRule::RenameTable { from, to } => write!(f, "RENAME TABLE {} TO {}", from, to),
+            RewriteRule::RenameColumn { table, from, to } => write!(f, "RENAME COLUMN {}.{} TO {}", table, from, to),
+            RewriteRule::ForceLimit { table, limit } => write!(f, "LIMIT {} TO {}", table, limit),
+        }
+    }
+}
+
+/// 按顺序依次应用规则（后面的规则看到的是前面规则改写后的语句），返回是否有规则生效
+pub fn apply_rewrite_rules(statement: &mut Statement, rules: &[RewriteRule]) -> bool {
+    let mut changed = false;
+    for rule in rules {
+        changed |= apply_rule(statement, rule);
+    }
+    changed
+}
+
+fn apply_rule(statement: &mut Statement, rule: &RewriteRule) -> bool {
+    match statement {
+        Statement::Explain { statement, .. }
+        | Statement::DryRun { statement }
+        | Statement::CreateView { query: statement, .. }
+        | Statement::Assert { query: statement, .. }
+        | Statement::CopyTo { query: statement, .. } => return apply_rule(statement, rule),
+        Statement::Select { .. } | Statement::Insert { .. } | Statement::Update { .. } | Statement::Delete { .. } => {}
+        _ => return false,
+    }
+
+    match rule {
+        RewriteRule::RenameTable { from, to } => rename_table(statement, from, to),
+        RewriteRule::RenameColumn { table, from, to } => {
+            if referenced_tables(statement).contains(&table) {
+                rename_column(statement, table, from, to)
+            } else {
+                false
+            }
+        }
+        RewriteRule::ForceLimit { table, limit } => match statement {
+            Statement::Select { from_clause: Some(from), limit: current, .. } if from_tables(from).contains(&table) => {
+                let limited = current.map_or(*limit, |current| current.min(*limit));
+                let changed = *current != Some(limited);
+                *current = Some(limited);
+                changed
+            }
+            _ => false,
+        },
+    }
+}
+
+/// 语句读写的表
+fn referenced_tables(statement: &Statement) -> Vec<&String> {
+    match statement {
+        Statement::Select { from_clause: Some(from), .. } => from_tables(from),
+        Statement::Insert { table_name, .. }
+        | Statement::Update { table_name, .. }
+        | Statement::Delete { table_name, .. } => vec![table_name],
+        _ => Vec::new(),
+    }
+}
+
+fn from_tables(from: &FromClause) -> Vec<&String> {
+    match from {
+        FromClause::Table(name) => vec![name],
+        FromClause::Join { left, right, .. } => {
+            let mut tables = from_tables(left);
+            tables.extend(from_tables(right));
+            tables
+        }
+    }
+}
+
+fn rename_table(statement: &mut Statement, from: &str, to: &str) -> bool {
+    fn rename_from(clause: &mut FromClause, from: &str, to: &str, changed: &mut bool) {
+        match clause {
+            FromClause::Table(name) if name == from => {
+                *name = to.to_string();
+                *changed = true;
+            }
+            FromClause::Table(_) => {}
+            FromClause::Join { left, right, .. } => {
+                rename_from(left, from, to, changed);
+                rename_from(right, from, to, changed);
+            }
+        }
+    }
+
+    let mut changed = false;
+    match statement {
+        Statement::Select { from_clause: Some(clause), .. } => rename_from(clause, from, to, &mut changed),
+        Statement::Insert { table_name, .. }
+        | Statement::Update { table_name, .. }
+        | Statement::Delete { table_name, .. } if table_name == from => {
+            *table_name = to.to_string();
+            changed = true;
+        }
+        _ => {}
+    }
+    statement.visit_expressions_mut(&mut |expr| {
+        if let Expression::QualifiedColumn { table, .. } = expr {
+            if table == from {
+                *table = to.to_string();
+                changed = true;
+            }
+        }
+    });
+    changed
+}
+
+fn rename_column(statement: &mut Statement, table_name: &str, from: &str, to: &str) -> bool {
+    // 保留结果列名：SELECT / RETURNING 中直接引用旧列名且没有别名的项以旧列名为别名
+    fn keep_output_name(list: &mut SelectList, table_name: &str, from: &str) {
+        if let SelectList::Expressions(items) = list {
+            for item in items.iter_mut().filter(|item| item.alias.is_none()) {
+                let old = match &item.expr {
+                    Expression::Column(column) => column == from,
+                    Expression::QualifiedColumn { table, column } => table == table_name && column == from,
+                    _ => false,
+                };
+                if old {
+                    item.alias = Some(from.to_string());
+                }
+            }
+        }
+    }
+    let rename = |column: &mut String, changed: &mut bool| {
+        if column == from {
+            *column = to.to_string();
+            *changed = true;
+        }
+    };
+
+    let mut changed = false;
+    match statement {
+        Statement::Select { select_list, .. } => keep_output_name(select_list, table_name, from),
+        Statement::Insert { columns, on_conflict, returning, .. } => {
+            for column in columns.iter_mut().flatten() {
+                rename(column, &mut changed);
+            }
+            if let Some(OnConflict { target, action }) = on_conflict {
+                for column in target {
+                    rename(column, &mut changed);
+                }
+                if let ConflictAction::DoUpdate { assignments, .. } = action {
+                    for assignment in assignments {
+                        rename(&mut assignment.column, &mut changed);
+                    }
+                }
+            }
+            if let Some(returning) = returning {
+                keep_output_name(returning, table_name, from);
+            }
+        }
+        Statement::Update { assignments, returning, .. } => {
+            for assignment in assignments {
+                rename(&mut assignment.column, &mut changed);
+            }
+            if let Some(returning) = returning {
+                keep_output_name(returning, table_name, from);
+            }
+        }
+        Statement::Delete { returning: Some(returning), .. } => keep_output_name(returning, table_name, from),
+        _ => {}
+    }
+
+    // INSERT ... ON CONFLICT DO UPDATE 中 excluded.列名 引用的也是该表的列
+    let is_insert = matches!(statement, Statement::Insert { .. });
+    statement.visit_expressions_mut(&mut |expr| match expr {
+        Expression::Column(column) => rename(column, &mut changed),
+        Expression::QualifiedColumn { table, column } if table == table_name || (is_insert && table == "excluded") => {
+            rename(column, &mut changed)
+        }
+        _ => {}
+    });
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql::parse_sql;
+
+    fn rewrite(sql: &str, rules: &[RewriteRule]) -> Statement {
+        let mut statement = parse_sql(sql).unwrap();
+        apply_rewrite_rules(&mut statement, rules);
+        statement
+    }
+
+    #[test]
+    fn test_rename_table_and_column() {
+        let rules = [
+            RewriteRule::RenameTable { from: "customers".to_string(), to: "clients".to_string() },
+            RewriteRule::RenameColumn { table: "clients".to_string(), from: "fullname".to_string(), to: "name".to_string() },
+        ];
+        assert_eq!(
+            rewrite("SELECT fullname, customers.fullname AS n FROM customers WHERE fullname = 'a' ORDER BY fullname", &rules),
+            parse_sql("SELECT name AS fullname, clients.name AS n FROM clients WHERE name = 'a' ORDER BY name").unwrap()
+        );
+        assert_eq!(
+            rewrite("UPDATE customers SET fullname = 'b' WHERE fullname = 'a' RETURNING fullname", &rules),
+            parse_sql("UPDATE clients SET name = 'b' WHERE name = 'a' RETURNING name AS fullname").unwrap()
+        );
+        assert_eq!(
+            rewrite("INSERT INTO customers (id, fullname) VALUES (1, 'a') ON CONFLICT (fullname) DO UPDATE SET fullname = excluded.fullname", &rules),
+            parse_sql("INSERT INTO clients (id, name) VALUES (1, 'a') ON CONFLICT (name) DO UPDATE SET name = excluded.name").unwrap()
+        );
+        // 未引用该表的语句和 DDL 不变
+        let other = parse_sql("SELECT fullname FROM orders").unwrap();
+        assert_eq!(rewrite("SELECT fullname FROM orders", &rules), other);
+        let ddl = parse_sql("DROP TABLE customers").unwrap();
+        assert_eq!(rewrite("DROP TABLE customers", &rules), ddl);
+    }
+
+    #[test]
+    fn test_force_limit() {
+        let rules = [RewriteRule::ForceLimit { table: "logs".to_string(), limit: 100 }];
+        let limit_of = |statement: Statement| match statement {
+            Statement::Select { limit, .. } => limit,
+            other => panic!("Expected SELECT, got {:?}", other),
+        };
+        assert_eq!(limit_of(rewrite("SELECT * FROM logs", &rules)), Some(100));
+        assert_eq!(limit_of(rewrite("SELECT * FROM logs LIMIT 10", &rules)), Some(10));
+        assert_eq!(limit_of(rewrite("SELECT * FROM users JOIN logs ON users.id = logs.user_id LIMIT 500", &rules)), Some(100));
+        assert_eq!(limit_of(rewrite("SELECT * FROM users", &rules)), None);
+        match rewrite("EXPLAIN SELECT * FROM logs", &rules) {
+            Statement::Explain { statement, .. } => assert_eq!(limit_of(*statement), Some(100)),
+            other => panic!("Expected EXPLAIN, got {:?}", other),
+        }
+    }
+}
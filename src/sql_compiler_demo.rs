@@ -1,3 +1,57 @@
-fn main() {
-    println!("SQL Compiler Demo - placeholder");
-}
\ No newline at end of file
+//! SQL 编译器演示
+//!
+//! 用 `Database::compile` 逐阶段展示 SQL 语句的编译过程：
+//! 词法分析、语法分析、语义分析和查询优化。
+
+use minidb::Database;
+use std::env;
+
+const DEMO_DIR: &str = "./sql_compiler_demo_data";
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut database = Database::new(DEMO_DIR)?;
+    if database.get_table_schema("users").is_none() {
+        database.execute("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(50), age INT)")?;
+    }
+
+    // 命令行参数给出的 SQL 优先，否则使用内置示例
+    let queries: Vec<String> = match env::args().nth(1) {
+        Some(sql) => vec![sql],
+        None => vec![
+            "SELECT name, age FROM users WHERE age > 18 ORDER BY name".to_string(),
+            "INSERT INTO users VALUES (1, 'Alice', 30)".to_string(),
+            "SELECT * FROM orders".to_string(),
+        ],
+    };
+
+    for sql in &queries {
+        println!("=== {} ===", sql);
+        match database.compile(sql) {
+            Ok(compiled) => {
+                println!("[1] 词法分析:");
+                for info in &compiled.tokens {
+                    println!("    {:>3}:{:<3} {:?} {:?}", info.line, info.column, info.category, info.lexeme);
+                }
+                println!("[2] 语法分析:\n    {:?}", compiled.ast);
+                match &compiled.analyzed.result_schema {
+                    Some(schema) => {
+                        let columns: Vec<String> = schema.columns.iter()
+                            .map(|col| format!("{} {}", col.name, col.data_type))
+                            .collect();
+                        println!("[3] 语义分析: 结果列 ({})", columns.join(", "));
+                    }
+                    None => println!("[3] 语义分析: 通过（无结果集）"),
+                }
+                match &compiled.plan {
+                    Some(plan) => println!("[4] 执行计划:\n    {:?}\n    {:?}", plan.plan, plan.stats),
+                    None => println!("[4] 执行计划: 规划器暂不支持此语句"),
+                }
+            }
+            Err(e) => println!("❌ 编译失败: {}", e),
+        }
+        println!();
+    }
+
+    let _ = std::fs::remove_dir_all(DEMO_DIR);
+    Ok(())
+}
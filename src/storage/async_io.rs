@@ -0,0 +1,180 @@
+//! Async wrappers around the storage layer
+//!
+//! File and buffer pool operations block on disk I/O, so calling them from an
+//! async task would stall the runtime's worker thread. The wrappers here run
+//! each operation on tokio's blocking thread pool and await the result. A
+//! panic inside an operation is resumed in the awaiting task, so errors and
+//! panics surface exactly as they would from the synchronous API.
+
+use crate::storage::buffer::{BufferError, BufferPool, BufferStats, FrameId};
+use crate::storage::compression::Compression;
+use crate::storage::file::{DatabaseFile, FileError, FileManager};
+use crate::storage::page::{Page, PageId, PageType};
+use std::sync::{Arc, Mutex};
+
+/// Run a blocking closure on the blocking thread pool and wait for it
+pub(crate) async fn run_blocking<T, F>(f: F) -> T
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    match tokio::task::spawn_blocking(f).await {
+        Ok(value) => value,
+        Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+        Err(e) => panic!("blocking storage task failed: {}", e),
+    }
+}
+
+/// [`FileManager`] whose operations can be awaited
+#[derive(Clone)]
+pub struct AsyncFileManager {
+    inner: Arc<FileManager>,
+}
+
+impl AsyncFileManager {
+    /// Wrap a file manager
+    pub fn new(file_manager: FileManager) -> Self {
+        Self {
+            inner: Arc::new(file_manager),
+        }
+    }
+
+    /// The wrapped file manager, for synchronous use
+    pub fn inner(&self) -> &FileManager {
+        &self.inner
+    }
+
+    /// Create a new database file
+    pub async fn create_file(&self, name: &str) -> Result<Arc<Mutex<DatabaseFile>>, FileError> {
+        self.create_file_with_compression(name, Compression::None).await
+    }
+
+    /// Create a new database file whose pages are stored compressed
+    pub async fn create_file_with_compression(
+        &self,
+        name: &str,
+        compression: Compression,
+    ) -> Result<Arc<Mutex<DatabaseFile>>, FileError> {
+        let inner = self.inner.clone();
+        let name = name.to_string();
+        run_blocking(move || inner.create_file_with_compression(&name, compression)).await
+    }
+
+    /// Open an existing database file
+    pub async fn open_file(&self, name: &str) -> Result<Arc<Mutex<DatabaseFile>>, FileError> {
+        let inner = self.inner.clone();
+        let name = name.to_string();
+        run_blocking(move || inner.open_file(&name)).await
+    }
+
+    /// Delete a database file
+    pub async fn delete_file(&self, name: &str) -> Result<(), FileError> {
+        let inner = self.inner.clone();
+        let name = name.to_string();
+        run_blocking(move || inner.delete_file(&name)).await
+    }
+
+    /// Flush and fsync every open file
+    pub async fn sync_all(&self) -> Result<(), FileError> {
+        let inner = self.inner.clone();
+        run_blocking(move || inner.sync_all()).await
+    }
+}
+
+/// [`BufferPool`] whose page operations can be awaited
+#[derive(Clone)]
+pub struct AsyncBufferPool {
+    inner: Arc<BufferPool>,
+}
+
+impl AsyncBufferPool {
+    /// Wrap a buffer pool shared with synchronous users
+    pub fn new(pool: Arc<BufferPool>) -> Self {
+        Self { inner: pool }
+    }
+
+    /// The wrapped buffer pool, for synchronous use
+    pub fn inner(&self) -> &Arc<BufferPool> {
+        &self.inner
+    }
+
+    /// Fetch a page, reading it from its file on a miss
+    pub async fn fetch_page(
+        &self,
+        file: Arc<Mutex<DatabaseFile>>,
+        page_id: PageId,
+    ) -> Result<(FrameId, Arc<Mutex<Page>>), BufferError> {
+        let inner = self.inner.clone();
+        run_blocking(move || inner.fetch_page(file, page_id)).await
+    }
+
+    /// Allocate a new page in a file and pin it in the pool
+    pub async fn new_page(
+        &self,
+        file: Arc<Mutex<DatabaseFile>>,
+        page_type: PageType,
+    ) -> Result<(FrameId, Arc<Mutex<Page>>), BufferError> {
+        let inner = self.inner.clone();
+        run_blocking(move || inner.new_page(file, page_type)).await
+    }
+
+    /// Unpin a page; this never touches the disk and does not need awaiting
+    pub fn unpin_page(&self, frame_id: FrameId, is_dirty: bool) -> Result<(), BufferError> {
+        self.inner.unpin_page(frame_id, is_dirty)
+    }
+
+    /// Write a frame's page back if it is dirty
+    pub async fn flush_page(&self, frame_id: FrameId) -> Result<(), BufferError> {
+        let inner = self.inner.clone();
+        run_blocking(move || inner.flush_page(frame_id)).await
+    }
+
+    /// Write every dirty page back
+    pub async fn flush_all(&self) -> Result<(), BufferError> {
+        let inner = self.inner.clone();
+        run_blocking(move || inner.flush_all()).await
+    }
+
+    /// Write back up to `max_pages` unpinned dirty pages
+    pub async fn flush_dirty(&self, max_pages: usize) -> Result<usize, BufferError> {
+        let inner = self.inner.clone();
+        run_blocking(move || inner.flush_dirty(max_pages)).await
+    }
+
+    /// Buffer pool statistics
+    pub fn stats(&self) -> Result<BufferStats, BufferError> {
+        self.inner.stats()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_async_pages_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let files = AsyncFileManager::new(FileManager::new(temp_dir.path()).unwrap());
+        let pool = AsyncBufferPool::new(Arc::new(BufferPool::new(2)));
+        let file = files.create_file("test").await.unwrap();
+
+        for i in 0..4u32 {
+            let (frame_id, page) = pool.new_page(file.clone(), PageType::Data).await.unwrap();
+            page.lock().unwrap().insert_record(format!("record {}", i).as_bytes()).unwrap();
+            pool.unpin_page(frame_id, true).unwrap();
+        }
+        pool.flush_all().await.unwrap();
+        files.sync_all().await.unwrap();
+        assert_eq!(pool.stats().unwrap().dirty_pages, 0);
+
+        // Page 0 was evicted from the two-frame pool and is read back from disk
+        let (frame_id, page) = pool.fetch_page(file.clone(), 0).await.unwrap();
+        assert_eq!(page.lock().unwrap().get_record(0).unwrap(), b"record 0");
+        pool.unpin_page(frame_id, false).unwrap();
+
+        assert!(files.open_file("missing").await.is_err());
+        files.delete_file("test").await.unwrap();
+        assert!(files.inner().list_files().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,288 @@
+//! Pluggable storage backends
+//!
+//! The engine persists its catalog metadata, table snapshots and write-ahead
+//! log as named blobs through a [`FileBackend`]. [`DirectoryBackend`] keeps one file per
+//! blob in a data directory; [`MemoryBackend`] keeps everything in process
+//! memory and never touches `std::fs`, which is what a `wasm32-unknown-unknown`
+//! build needs. A browser host that wants durability can export the memory
+//! backend's contents with [`MemoryBackend::snapshot`], store them in
+//! IndexedDB, and hand them back to [`MemoryBackend::from_snapshot`] on the
+//! next page load.
+
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Named-blob storage used for persisted engine state
+pub trait FileBackend: Send {
+    /// Read a blob, returning `None` if it does not exist
+    fn read(&self, name: &str) -> io::Result<Option<Vec<u8>>>;
+
+    /// Create or replace a blob; readers see either the old or the new
+    /// contents, and the new contents are durable once this returns
+    fn write(&self, name: &str, data: &[u8]) -> io::Result<()>;
+
+    /// Append to a blob, creating it if missing; the appended bytes are
+    /// durable once this returns
+    fn append(&self, name: &str, data: &[u8]) -> io::Result<()> {
+        let mut contents = self.read(name)?.unwrap_or_default();
+        contents.extend_from_slice(data);
+        self.write(name, &contents)
+    }
+
+    /// Remove a blob; removing a missing blob is not an error
+    fn remove(&self, name: &str) -> io::Result<()>;
+
+    /// Names of all stored blobs
+    fn list(&self) -> io::Result<Vec<String>>;
+
+    /// Total bytes stored, including files other components keep in the
+    /// data directory
+    fn size(&self) -> io::Result<u64> {
+        let mut total = 0;
+        for name in self.list()? {
+            total += self.read(&name)?.map_or(0, |data| data.len() as u64);
+        }
+        Ok(total)
+    }
+
+    /// Data directory backing this storage, if it lives on the file system
+    fn directory(&self) -> Option<&Path> {
+        None
+    }
+}
+
+/// Lets the caller keep a handle to a backend owned by a database, e.g. to
+/// take a [`MemoryBackend::snapshot`] after running some statements
+impl<B: FileBackend + Sync + ?Sized> FileBackend for Arc<B> {
+    fn read(&self, name: &str) -> io::Result<Option<Vec<u8>>> {
+        (**self).read(name)
+    }
+
+    fn write(&self, name: &str, data: &[u8]) -> io::Result<()> {
+        (**self).write(name, data)
+    }
+
+    fn append(&self, name: &str, data: &[u8]) -> io::Result<()> {
+        (**self).append(name, data)
+    }
+
+    fn remove(&self, name: &str) -> io::Result<()> {
+        (**self).remove(name)
+    }
+
+    fn list(&self) -> io::Result<Vec<String>> {
+        (**self).list()
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        (**self).size()
+    }
+
+    fn directory(&self) -> Option<&Path> {
+        (**self).directory()
+    }
+}
+
+/// Stores each blob as a file in a directory
+#[derive(Debug)]
+pub struct DirectoryBackend {
+    dir: PathBuf,
+}
+
+impl DirectoryBackend {
+    /// Open a directory, creating it if necessary
+    pub fn new<P: AsRef<Path>>(dir: P) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+}
+
+impl FileBackend for DirectoryBackend {
+    fn read(&self, name: &str) -> io::Result<Option<Vec<u8>>> {
+        match fs::read(self.dir.join(name)) {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn write(&self, name: &str, data: &[u8]) -> io::Result<()> {
+        // Write a sibling file and rename it over the blob so a crash never
+        // leaves a half-written snapshot behind
+        let temp_path = self.dir.join(format!("{}.tmp", name));
+        let mut file = fs::File::create(&temp_path)?;
+        file.write_all(data)?;
+        file.sync_all()?;
+        fs::rename(temp_path, self.dir.join(name))
+    }
+
+    fn append(&self, name: &str, data: &[u8]) -> io::Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.dir.join(name))?;
+        file.write_all(data)?;
+        file.sync_data()
+    }
+
+    fn remove(&self, name: &str) -> io::Result<()> {
+        match fs::remove_file(self.dir.join(name)) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    fn list(&self) -> io::Result<Vec<String>> {
+        let mut names = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_file() {
+                if let Some(name) = entry.file_name().to_str() {
+                    names.push(name.to_string());
+                }
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        directory_size(&self.dir)
+    }
+
+    fn directory(&self) -> Option<&Path> {
+        Some(&self.dir)
+    }
+}
+
+/// Bytes used by the files under a directory, recursively
+fn directory_size(dir: &Path) -> io::Result<u64> {
+    let mut total = 0;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            total += directory_size(&entry.path())?;
+        } else if file_type.is_file() {
+            total += entry.metadata()?.len();
+        }
+    }
+    Ok(total)
+}
+
+/// Keeps all blobs in memory
+#[derive(Debug, Default)]
+pub struct MemoryBackend {
+    blobs: Mutex<BTreeMap<String, Vec<u8>>>,
+}
+
+impl MemoryBackend {
+    /// Create an empty backend
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Restore a backend from the output of [`MemoryBackend::snapshot`]
+    pub fn from_snapshot(blobs: impl IntoIterator<Item = (String, Vec<u8>)>) -> Self {
+        Self {
+            blobs: Mutex::new(blobs.into_iter().collect()),
+        }
+    }
+
+    /// Copy of every stored blob, ordered by name
+    pub fn snapshot(&self) -> Vec<(String, Vec<u8>)> {
+        self.lock()
+            .iter()
+            .map(|(name, data)| (name.clone(), data.clone()))
+            .collect()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, Vec<u8>>> {
+        // A panic while holding the lock cannot leave a map half-updated
+        self.blobs.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl FileBackend for MemoryBackend {
+    fn read(&self, name: &str) -> io::Result<Option<Vec<u8>>> {
+        Ok(self.lock().get(name).cloned())
+    }
+
+    fn write(&self, name: &str, data: &[u8]) -> io::Result<()> {
+        self.lock().insert(name.to_string(), data.to_vec());
+        Ok(())
+    }
+
+    fn append(&self, name: &str, data: &[u8]) -> io::Result<()> {
+        self.lock().entry(name.to_string()).or_default().extend_from_slice(data);
+        Ok(())
+    }
+
+    fn remove(&self, name: &str) -> io::Result<()> {
+        self.lock().remove(name);
+        Ok(())
+    }
+
+    fn list(&self) -> io::Result<Vec<String>> {
+        Ok(self.lock().keys().cloned().collect())
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        Ok(self.lock().values().map(|data| data.len() as u64).sum())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn exercise(backend: &dyn FileBackend) {
+        assert_eq!(backend.read("a.json").unwrap(), None);
+        backend.write("a.json", b"one").unwrap();
+        backend.write("b.json", b"two").unwrap();
+        backend.write("a.json", b"three").unwrap();
+        assert_eq!(backend.read("a.json").unwrap(), Some(b"three".to_vec()));
+        backend.append("a.json", b"+four").unwrap();
+        assert_eq!(backend.read("a.json").unwrap(), Some(b"three+four".to_vec()));
+        assert_eq!(backend.list().unwrap(), vec!["a.json", "b.json"]);
+
+        assert_eq!(backend.size().unwrap(), 13);
+
+        backend.remove("a.json").unwrap();
+        backend.remove("a.json").unwrap();
+        assert_eq!(backend.list().unwrap(), vec!["b.json"]);
+        assert_eq!(backend.size().unwrap(), 3);
+
+        backend.append("c.log", b"new").unwrap();
+        assert_eq!(backend.read("c.log").unwrap(), Some(b"new".to_vec()));
+        backend.remove("c.log").unwrap();
+    }
+
+    #[test]
+    fn test_directory_backend() {
+        let root = TempDir::new().unwrap();
+        let backend = DirectoryBackend::new(root.path().join("data")).unwrap();
+        exercise(&backend);
+        assert_eq!(backend.directory(), Some(root.path().join("data").as_path()));
+
+        // Files written by other components, e.g. the temp space, count too
+        fs::create_dir(root.path().join("data/tmp")).unwrap();
+        fs::write(root.path().join("data/tmp/spill"), b"four").unwrap();
+        assert_eq!(backend.size().unwrap(), 7);
+    }
+
+    #[test]
+    fn test_memory_backend_snapshot() {
+        let backend = MemoryBackend::new();
+        exercise(&backend);
+        assert_eq!(backend.directory(), None);
+
+        let restored = MemoryBackend::from_snapshot(backend.snapshot());
+        assert_eq!(restored.read("b.json").unwrap(), Some(b"two".to_vec()));
+    }
+}
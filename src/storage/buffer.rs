@@ -1,13 +1,17 @@
 //! Buffer pool management
 //!
 //! This module implements a buffer pool that manages pages in memory.
-//! It supports multiple cache replacement policies: LRU, Clock, and LFU.
-//! It handles dirty page write-back to storage.
+//! It supports multiple cache replacement policies: LRU, Clock, LFU and ARC.
+//! It handles dirty page write-back to storage, either on eviction and
+//! explicit flushes or periodically from a [`BackgroundWriter`] thread.
 
 use crate::storage::file::{DatabaseFile, FileError};
 use crate::storage::page::{Page, PageId};
 use std::collections::{HashMap, VecDeque};
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex, TryLockError};
+use std::thread::JoinHandle;
+use std::time::Duration;
 use thiserror::Error;
 
 /// Cache replacement policy trait
@@ -15,8 +19,8 @@ pub trait CachePolicy: Send + Sync {
     /// Called when a frame is accessed
     fn on_access(&mut self, frame_id: FrameId);
     
-    /// Called when a new frame is added to the pool
-    fn on_insert(&mut self, frame_id: FrameId);
+    /// Called when a page is loaded into a frame
+    fn on_insert(&mut self, frame_id: FrameId, page: &PageKey);
     
     /// Find the best frame to evict
     fn find_victim(&mut self, frames: &[Mutex<Frame>]) -> Option<FrameId>;
@@ -52,12 +56,64 @@ pub struct LFUPolicy {
     global_time: u64,
 }
 
+/// ARC (Adaptive Replacement Cache) policy
+///
+/// Resident pages are split between `t1` (seen once recently) and `t2`
+/// (seen at least twice). Pages evicted from either list are remembered in
+/// the ghost lists `b1` / `b2`; loading a page that is still remembered
+/// shifts the target size `p` of `t1` towards the list that would have kept
+/// it. A sequential scan only ever touches `t1`, so it cannot push the
+/// frequently used pages in `t2` out of the pool the way it does with LRU.
+#[derive(Debug)]
+pub struct ARCPolicy {
+    /// Resident pages seen once, least recently used first
+    t1: VecDeque<FrameId>,
+    /// Resident pages seen more than once, least recently used first
+    t2: VecDeque<FrameId>,
+    /// Pages recently evicted from `t1`, oldest first
+    b1: VecDeque<PageKey>,
+    /// Pages recently evicted from `t2`, oldest first
+    b2: VecDeque<PageKey>,
+    /// Page held by each resident frame
+    pages: HashMap<FrameId, PageKey>,
+    /// Target size of `t1`
+    p: usize,
+    pool_size: usize,
+}
+
 /// Cache policy type enumeration
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum CachePolicyType {
+    #[default]
     LRU,
     Clock,
     LFU,
+    ARC,
+}
+
+impl CachePolicyType {
+    /// Every policy
+    pub const ALL: [CachePolicyType; 4] = [
+        CachePolicyType::LRU,
+        CachePolicyType::Clock,
+        CachePolicyType::LFU,
+        CachePolicyType::ARC,
+    ];
+
+    /// Lowercase name used in configuration and `SET buffer_policy`
+    pub fn name(self) -> &'static str {
+        match self {
+            CachePolicyType::LRU => "lru",
+            CachePolicyType::Clock => "clock",
+            CachePolicyType::LFU => "lfu",
+            CachePolicyType::ARC => "arc",
+        }
+    }
+
+    /// Parse a policy name, ignoring case
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|policy| policy.name().eq_ignore_ascii_case(name))
+    }
 }
 
 impl LRUPolicy {
@@ -81,7 +137,7 @@ impl CachePolicy for LRUPolicy {
         self.frame_access_time.insert(frame_id, self.access_counter);
     }
     
-    fn on_insert(&mut self, frame_id: FrameId) {
+    fn on_insert(&mut self, frame_id: FrameId, _page: &PageKey) {
         self.on_access(frame_id);
     }
     
@@ -144,7 +200,7 @@ impl CachePolicy for ClockPolicy {
         }
     }
     
-    fn on_insert(&mut self, frame_id: FrameId) {
+    fn on_insert(&mut self, frame_id: FrameId, _page: &PageKey) {
         self.on_access(frame_id);
     }
     
@@ -215,7 +271,7 @@ impl CachePolicy for LFUPolicy {
         self.access_times.insert(frame_id, self.global_time);
     }
     
-    fn on_insert(&mut self, frame_id: FrameId) {
+    fn on_insert(&mut self, frame_id: FrameId, _page: &PageKey) {
         self.on_access(frame_id);
     }
     
@@ -257,14 +313,123 @@ impl CachePolicy for LFUPolicy {
     }
 }
 
+impl ARCPolicy {
+    pub fn new(pool_size: usize) -> Self {
+        Self {
+            t1: VecDeque::with_capacity(pool_size),
+            t2: VecDeque::with_capacity(pool_size),
+            b1: VecDeque::new(),
+            b2: VecDeque::new(),
+            pages: HashMap::new(),
+            p: 0,
+            pool_size,
+        }
+    }
+
+    /// First evictable frame of a resident list, least recently used first
+    fn lru_evictable(list: &VecDeque<FrameId>, frames: &[Mutex<Frame>]) -> Option<FrameId> {
+        list.iter()
+            .copied()
+            .find(|&frame_id| frames[frame_id].lock().is_ok_and(|frame| frame.is_evictable()))
+    }
+
+    /// Remove a page from a ghost list, returning whether it was there
+    fn take_ghost(list: &mut VecDeque<PageKey>, page: &PageKey) -> bool {
+        match list.iter().position(|ghost| ghost == page) {
+            Some(index) => {
+                list.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Keep the ghost lists within the directory size of 2c pages
+    fn trim_ghosts(&mut self) {
+        while self.t1.len() + self.b1.len() > self.pool_size && self.b1.pop_front().is_some() {}
+        while self.t1.len() + self.t2.len() + self.b1.len() + self.b2.len() > 2 * self.pool_size
+            && self.b2.pop_front().is_some()
+        {}
+    }
+}
+
+impl CachePolicy for ARCPolicy {
+    fn on_access(&mut self, frame_id: FrameId) {
+        // A hit promotes the page to the most recent end of t2
+        if let Some(index) = self.t1.iter().position(|&id| id == frame_id) {
+            self.t1.remove(index);
+        } else if let Some(index) = self.t2.iter().position(|&id| id == frame_id) {
+            self.t2.remove(index);
+        } else {
+            return;
+        }
+        self.t2.push_back(frame_id);
+    }
+
+    fn on_insert(&mut self, frame_id: FrameId, page: &PageKey) {
+        if Self::take_ghost(&mut self.b1, page) {
+            // Evicted from t1 too early: give recency more room
+            let delta = (self.b2.len() / (self.b1.len() + 1)).max(1);
+            self.p = (self.p + delta).min(self.pool_size);
+            self.t2.push_back(frame_id);
+        } else if Self::take_ghost(&mut self.b2, page) {
+            // Evicted from t2 too early: give frequency more room
+            let delta = (self.b1.len() / (self.b2.len() + 1)).max(1);
+            self.p = self.p.saturating_sub(delta);
+            self.t2.push_back(frame_id);
+        } else {
+            self.t1.push_back(frame_id);
+        }
+        self.pages.insert(frame_id, page.clone());
+        self.trim_ghosts();
+    }
+
+    fn find_victim(&mut self, frames: &[Mutex<Frame>]) -> Option<FrameId> {
+        if let Some(frame_id) = (0..frames.len())
+            .find(|&frame_id| frames[frame_id].lock().is_ok_and(|frame| frame.is_free()))
+        {
+            return Some(frame_id);
+        }
+
+        // Take from t1 while it is above its target size, otherwise from t2
+        let (first, second) = if !self.t1.is_empty() && self.t1.len() > self.p {
+            (&self.t1, &self.t2)
+        } else {
+            (&self.t2, &self.t1)
+        };
+        Self::lru_evictable(first, frames).or_else(|| Self::lru_evictable(second, frames))
+    }
+
+    fn on_evict(&mut self, frame_id: FrameId) {
+        let Some(page) = self.pages.remove(&frame_id) else {
+            return;
+        };
+        if let Some(index) = self.t1.iter().position(|&id| id == frame_id) {
+            self.t1.remove(index);
+            self.b1.push_back(page);
+        } else if let Some(index) = self.t2.iter().position(|&id| id == frame_id) {
+            self.t2.remove(index);
+            self.b2.push_back(page);
+        }
+        self.trim_ghosts();
+    }
+
+    fn name(&self) -> &'static str {
+        "ARC"
+    }
+}
+
 /// Frame identifier in buffer pool
 pub type FrameId = usize;
 
+/// Identity of a cached page: the file stem and the page number
+pub type PageKey = (String, PageId);
+
 /// Buffer pool frame containing a page and metadata
 #[derive(Debug)]
 pub struct Frame {
-    /// The page stored in this frame
-    page: Option<Page>,
+    /// The page stored in this frame, shared with every caller that has it pinned
+    page: Option<Arc<Mutex<Page>>>,
     /// File containing this page
     file: Option<Arc<Mutex<DatabaseFile>>>,
     /// Whether this frame is pinned (cannot be evicted)
@@ -278,11 +443,15 @@ pub struct BufferPool {
     /// Array of frames
     frames: Vec<Mutex<Frame>>,
     /// Map from (file_name, page_id) to frame_id
-    page_table: Mutex<HashMap<(String, PageId), FrameId>>,
+    page_table: Mutex<HashMap<PageKey, FrameId>>,
     /// Cache replacement policy
     cache_policy: Mutex<Box<dyn CachePolicy>>,
     /// Pool size
     pool_size: usize,
+    /// Fetches served from a frame
+    hits: AtomicU64,
+    /// Fetches that had to read the page from its file
+    misses: AtomicU64,
 }
 
 /// Buffer pool errors
@@ -346,6 +515,7 @@ impl BufferPool {
             CachePolicyType::LRU => Box::new(LRUPolicy::new(pool_size)),
             CachePolicyType::Clock => Box::new(ClockPolicy::new(pool_size)),
             CachePolicyType::LFU => Box::new(LFUPolicy::new(pool_size)),
+            CachePolicyType::ARC => Box::new(ARCPolicy::new(pool_size)),
         };
 
         Self {
@@ -353,6 +523,8 @@ impl BufferPool {
             page_table: Mutex::new(HashMap::new()),
             cache_policy: Mutex::new(policy),
             pool_size,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
         }
     }
 
@@ -387,18 +559,21 @@ impl BufferPool {
                     .lock()
                     .map_err(|e| BufferError::LockError(e.to_string()))?;
 
-            frame.pin_count += 1;
-            
-            // Update cache policy
-            if let Ok(mut policy) = self.cache_policy.lock() {
-                policy.on_access(frame_id);
-            }                if let Some(ref page) = frame.page {
-                    return Ok((frame_id, Arc::new(Mutex::new(page.clone()))));
+                if let Some(page) = frame.page.clone() {
+                    frame.pin_count += 1;
+                    self.hits.fetch_add(1, Ordering::Relaxed);
+
+                    // Update cache policy
+                    if let Ok(mut policy) = self.cache_policy.lock() {
+                        policy.on_access(frame_id);
+                    }
+                    return Ok((frame_id, page));
                 }
             }
         }
 
         // Page not in buffer, need to load from file
+        self.misses.fetch_add(1, Ordering::Relaxed);
         let frame_id = self.find_victim_frame()?;
 
         // Evict current page if necessary
@@ -409,16 +584,18 @@ impl BufferPool {
             let mut f = file
                 .lock()
                 .map_err(|e| BufferError::LockError(e.to_string()))?;
-            f.read_page(page_id)?
+            Arc::new(Mutex::new(f.read_page(page_id)?))
         };
 
+        let key = (file_name, page_id);
+
         // Install page in frame
         {
             let mut frame = self.frames[frame_id]
                 .lock()
                 .map_err(|e| BufferError::LockError(e.to_string()))?;
 
-            frame.page = Some(page);
+            frame.page = Some(page.clone());
             frame.file = Some(file.clone());
             frame.pin_count = 1;
             frame.is_dirty = false;
@@ -426,7 +603,7 @@ impl BufferPool {
             // Update cache policy
             drop(frame);
             if let Ok(mut policy) = self.cache_policy.lock() {
-                policy.on_insert(frame_id);
+                policy.on_insert(frame_id, &key);
             }
         }
 
@@ -436,16 +613,10 @@ impl BufferPool {
                 .page_table
                 .lock()
                 .map_err(|e| BufferError::LockError(e.to_string()))?;
-            page_table.insert((file_name, page_id), frame_id);
+            page_table.insert(key, frame_id);
         }
 
-        // Return reference to the page in the frame
-        let frame = self.frames[frame_id]
-            .lock()
-            .map_err(|e| BufferError::LockError(e.to_string()))?;
-        let page_ref = frame.page.as_ref().unwrap().clone();
-
-        Ok((frame_id, Arc::new(Mutex::new(page_ref))))
+        Ok((frame_id, page))
     }
 
     /// Create a new page in file and buffer pool
@@ -470,7 +641,9 @@ impl BufferPool {
         self.evict_frame(frame_id)?;
 
         // Create new page
-        let page = Page::new(page_id, page_type);
+        let page = Arc::new(Mutex::new(Page::new(page_id, page_type)));
+
+        let key = (file_name, page_id);
 
         // Install page in frame
         {
@@ -486,7 +659,7 @@ impl BufferPool {
             // Update cache policy
             drop(frame);
             if let Ok(mut policy) = self.cache_policy.lock() {
-                policy.on_insert(frame_id);
+                policy.on_insert(frame_id, &key);
             }
         }
 
@@ -496,10 +669,10 @@ impl BufferPool {
                 .page_table
                 .lock()
                 .map_err(|e| BufferError::LockError(e.to_string()))?;
-            page_table.insert((file_name, page_id), frame_id);
+            page_table.insert(key, frame_id);
         }
 
-        Ok((frame_id, Arc::new(Mutex::new(page))))
+        Ok((frame_id, page))
     }
 
     /// Unpin a page (decrement pin count)
@@ -535,40 +708,91 @@ impl BufferPool {
             .lock()
             .map_err(|e| BufferError::LockError(e.to_string()))?;
 
-        if frame.is_dirty && frame.page.is_some() && frame.file.is_some() {
-            let file = frame.file.as_ref().unwrap().clone();
-            let mut page = frame.page.take().unwrap();
-
-            println!("Before flush: page has {} slots", page.slot_count());
+        Self::write_back(&mut frame).map(|_| ())
+    }
 
-            // Release frame lock before acquiring file lock
-            drop(frame);
+    /// Flush all dirty pages to disk
+    pub fn flush_all(&self) -> Result<(), BufferError> {
+        for frame_id in 0..self.pool_size {
+            self.flush_page(frame_id)?;
+        }
+        Ok(())
+    }
 
-            // Write page to file
-            {
-                let mut f = file
-                    .lock()
-                    .map_err(|e| BufferError::LockError(e.to_string()))?;
-                f.write_page(&mut page)?;
+    /// Write back up to `max_pages` dirty pages that nobody has pinned,
+    /// returning how many were written
+    ///
+    /// Frames that are locked by another thread are skipped rather than
+    /// waited for, so a background flush never stalls foreground work.
+    pub fn flush_dirty(&self, max_pages: usize) -> Result<usize, BufferError> {
+        let mut written = 0;
+        for frame in &self.frames {
+            if written >= max_pages {
+                break;
+            }
+            let mut frame = match frame.try_lock() {
+                Ok(frame) => frame,
+                Err(TryLockError::WouldBlock) => continue,
+                Err(TryLockError::Poisoned(e)) => return Err(BufferError::LockError(e.to_string())),
+            };
+            if frame.is_dirty && frame.is_evictable() && Self::write_back(&mut frame)? {
+                written += 1;
             }
+        }
+        Ok(written)
+    }
+
+    /// Write a frame's page to its file if it is dirty, returning whether it was
+    fn write_back(frame: &mut Frame) -> Result<bool, BufferError> {
+        let (Some(file), Some(page)) = (&frame.file, &frame.page) else {
+            return Ok(false);
+        };
+        if !frame.is_dirty {
+            return Ok(false);
+        }
 
-            println!("After flush: page has {} slots", page.slot_count());
+        // Holding the frame lock keeps the page from being evicted mid-write
+        let mut page = page
+            .lock()
+            .map_err(|e| BufferError::LockError(e.to_string()))?;
+        let mut f = file
+            .lock()
+            .map_err(|e| BufferError::LockError(e.to_string()))?;
+        f.write_page(&mut page)?;
+        drop((page, f));
+        frame.is_dirty = false;
+        Ok(true)
+    }
 
-            // Reacquire frame lock and update
+    /// Drop every cached page of a file without writing it back, e.g.
+    /// before the file is deleted
+    pub fn discard_file(&self, file_name: &str) -> Result<(), BufferError> {
+        let mut page_table = self
+            .page_table
+            .lock()
+            .map_err(|e| BufferError::LockError(e.to_string()))?;
+
+        let frame_ids: Vec<FrameId> = page_table
+            .iter()
+            .filter(|((name, _), _)| name == file_name)
+            .map(|(_, &frame_id)| frame_id)
+            .collect();
+        page_table.retain(|(name, _), _| name != file_name);
+        drop(page_table);
+
+        for frame_id in frame_ids {
             let mut frame = self.frames[frame_id]
                 .lock()
                 .map_err(|e| BufferError::LockError(e.to_string()))?;
-            frame.page = Some(page);
+            frame.page = None;
+            frame.file = None;
+            frame.pin_count = 0;
             frame.is_dirty = false;
-        }
-
-        Ok(())
-    }
+            drop(frame);
 
-    /// Flush all dirty pages to disk
-    pub fn flush_all(&self) -> Result<(), BufferError> {
-        for frame_id in 0..self.pool_size {
-            self.flush_page(frame_id)?;
+            if let Ok(mut policy) = self.cache_policy.lock() {
+                policy.on_evict(frame_id);
+            }
         }
         Ok(())
     }
@@ -602,6 +826,8 @@ impl BufferPool {
             used_frames,
             pinned_pages,
             dirty_pages,
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
         })
     }
 
@@ -638,8 +864,11 @@ impl BufferPool {
             
             if frame.is_dirty && frame.page.is_some() && frame.file.is_some() {
                 let file = frame.file.as_ref().unwrap().clone();
-                let mut page = frame.page.take().unwrap();
-                let page_id = page.page_id();
+                let page = frame.page.take().unwrap();
+                let page_id = page
+                    .lock()
+                    .map_err(|e| BufferError::LockError(e.to_string()))?
+                    .page_id();
 
                 // Remove from page table
                 {
@@ -661,7 +890,13 @@ impl BufferPool {
                 file_and_page = Some((file, page));
             } else if frame.page.is_some() {
                 // Clean page, just remove from page table
-                let page_id = frame.page.as_ref().unwrap().page_id();
+                let page_id = frame
+                    .page
+                    .as_ref()
+                    .unwrap()
+                    .lock()
+                    .map_err(|e| BufferError::LockError(e.to_string()))?
+                    .page_id();
 
                 if let Some(ref file) = frame.file {
                     let mut page_table = self
@@ -690,7 +925,10 @@ impl BufferPool {
         };
 
         // Write dirty page to file if needed (outside of frame lock)
-        if let Some((file, mut page)) = need_file_write {
+        if let Some((file, page)) = need_file_write {
+            let mut page = page
+                .lock()
+                .map_err(|e| BufferError::LockError(e.to_string()))?;
             let mut f = file
                 .lock()
                 .map_err(|e| BufferError::LockError(e.to_string()))?;
@@ -744,10 +982,106 @@ impl BufferPool {
             used_frames,
             pinned_pages,
             dirty_pages,
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
         })
     }
 }
 
+/// Background thread that periodically writes dirty pages of a buffer pool
+/// back to disk
+///
+/// Spreading write-back over time keeps evictions from having to write
+/// pages on the query path and bounds how much an explicit flush has to do.
+/// The thread runs until [`BackgroundWriter::stop`] is called or the writer
+/// is dropped; both wait for a flush in progress to finish.
+#[derive(Debug)]
+pub struct BackgroundWriter {
+    /// Stop flag, with a condition variable that wakes the thread early
+    stop: Arc<(Mutex<bool>, Condvar)>,
+    /// Total pages written by the thread
+    pages_written: Arc<AtomicU64>,
+    /// Time between flushes
+    interval: Duration,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl BackgroundWriter {
+    /// Start a writer that flushes up to `max_pages` dirty pages of `pool`
+    /// every `interval`
+    pub fn start(pool: Arc<BufferPool>, interval: Duration, max_pages: usize) -> Self {
+        let stop = Arc::new((Mutex::new(false), Condvar::new()));
+        let pages_written = Arc::new(AtomicU64::new(0));
+        let handle = {
+            let stop = stop.clone();
+            let pages_written = pages_written.clone();
+            std::thread::Builder::new()
+                .name("minidb-bgwriter".to_string())
+                .spawn(move || {
+                    let (stopped, wake) = &*stop;
+                    let Ok(mut stopped) = stopped.lock() else {
+                        return;
+                    };
+                    loop {
+                        stopped = match wake.wait_timeout_while(stopped, interval, |stopped| !*stopped) {
+                            Ok((guard, _)) => guard,
+                            Err(_) => return,
+                        };
+                        if *stopped {
+                            return;
+                        }
+                        // A failed write leaves the page dirty; eviction or
+                        // the next flush reports the error
+                        if let Ok(written) = pool.flush_dirty(max_pages) {
+                            pages_written.fetch_add(written as u64, Ordering::Relaxed);
+                        }
+                    }
+                })
+                .ok()
+        };
+        Self {
+            stop,
+            pages_written,
+            interval,
+            handle,
+        }
+    }
+
+    /// Time between flushes
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Whether the thread is running
+    pub fn is_running(&self) -> bool {
+        self.handle.is_some()
+    }
+
+    /// Total pages the thread has written back
+    pub fn pages_written(&self) -> u64 {
+        self.pages_written.load(Ordering::Relaxed)
+    }
+
+    /// Stop the thread and wait for it to exit (no-op if already stopped)
+    pub fn stop(&mut self) {
+        let Some(handle) = self.handle.take() else {
+            return;
+        };
+        let (stopped, wake) = &*self.stop;
+        if let Ok(mut stopped) = stopped.lock() {
+            *stopped = true;
+        }
+        wake.notify_all();
+        let _ = handle.join();
+    }
+}
+
+impl Drop for BackgroundWriter {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
 /// Buffer pool statistics
 #[derive(Debug, Clone)]
 pub struct BufferStats {
@@ -755,6 +1089,20 @@ pub struct BufferStats {
     pub used_frames: usize,
     pub pinned_pages: usize,
     pub dirty_pages: usize,
+    /// Page fetches served from the pool since it was created
+    pub hits: u64,
+    /// Page fetches that read the page from disk since the pool was created
+    pub misses: u64,
+}
+
+impl BufferStats {
+    /// Fraction of page fetches served from the pool (0 before any fetch)
+    pub fn hit_ratio(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            total => self.hits as f64 / total as f64,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -805,12 +1153,57 @@ mod tests {
         assert_eq!(stats.pinned_pages, 0);
     }
 
-    // TODO: Fix fetch_page test - buffer pool sharing issue
-    // #[test]
-    // fn test_fetch_page() {
-    //     // This test has issues with page sharing between buffer pool and external references
-    //     // The underlying storage components work correctly as shown by other tests
-    // }    #[test]
+    #[test]
+    fn test_fetch_page() {
+        let temp_dir = TempDir::new().unwrap();
+        let fm = FileManager::new(temp_dir.path()).unwrap();
+        let file = fm.create_file("test").unwrap();
+        let pool = BufferPool::new(2);
+
+        let (frame_id, page_arc) = pool.new_page(file.clone(), PageType::Data).unwrap();
+        let slot_id = page_arc.lock().unwrap().insert_record(b"shared").unwrap();
+        pool.unpin_page(frame_id, true).unwrap();
+
+        // A cached page is the same page the writer modified
+        let (cached_frame, cached) = pool.fetch_page(file.clone(), 0).unwrap();
+        assert_eq!(cached_frame, frame_id);
+        assert_eq!(cached.lock().unwrap().get_record(slot_id).unwrap(), b"shared");
+        pool.unpin_page(cached_frame, false).unwrap();
+
+        // Evicting the page writes it back, and fetching reads it from disk
+        for _ in 0..2 {
+            let (frame_id, _) = pool.new_page(file.clone(), PageType::Data).unwrap();
+            pool.unpin_page(frame_id, false).unwrap();
+        }
+        let (frame_id, reloaded) = pool.fetch_page(file, 0).unwrap();
+        assert_eq!(reloaded.lock().unwrap().get_record(slot_id).unwrap(), b"shared");
+        pool.unpin_page(frame_id, false).unwrap();
+
+        let stats = pool.stats().unwrap();
+        assert_eq!((stats.hits, stats.misses), (1, 1));
+        assert_eq!(stats.hit_ratio(), 0.5);
+    }
+
+    #[test]
+    fn test_discard_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let fm = FileManager::new(temp_dir.path()).unwrap();
+        let kept = fm.create_file("kept").unwrap();
+        let dropped = fm.create_file("dropped").unwrap();
+        let pool = BufferPool::new(4);
+
+        for file in [&kept, &dropped, &dropped] {
+            let (frame_id, _) = pool.new_page(file.clone(), PageType::Data).unwrap();
+            pool.unpin_page(frame_id, true).unwrap();
+        }
+
+        pool.discard_file("dropped").unwrap();
+        let stats = pool.get_stats().unwrap();
+        assert_eq!(stats.used_frames, 1);
+        assert_eq!(stats.dirty_pages, 1);
+    }
+
+    #[test]
     fn test_buffer_eviction() {
         let temp_dir = TempDir::new().unwrap();
         let fm = FileManager::new(temp_dir.path()).unwrap();
@@ -859,6 +1252,65 @@ mod tests {
         assert_eq!(stats.dirty_pages, 0);
     }
 
+    #[test]
+    fn test_flush_dirty_skips_pinned_pages() {
+        let temp_dir = TempDir::new().unwrap();
+        let fm = FileManager::new(temp_dir.path()).unwrap();
+        let file = fm.create_file("test").unwrap();
+        let pool = BufferPool::new(4);
+
+        let mut frames = Vec::new();
+        for _ in 0..4 {
+            let (frame_id, _page) = pool.new_page(file.clone(), PageType::Data).unwrap();
+            frames.push(frame_id);
+        }
+        for &frame_id in &frames[1..] {
+            pool.unpin_page(frame_id, true).unwrap();
+        }
+
+        // At most two pages per call, and never the pinned one
+        assert_eq!(pool.flush_dirty(2).unwrap(), 2);
+        assert_eq!(pool.flush_dirty(10).unwrap(), 1);
+        assert_eq!(pool.flush_dirty(10).unwrap(), 0);
+        assert_eq!(pool.stats().unwrap().dirty_pages, 1);
+
+        pool.unpin_page(frames[0], true).unwrap();
+        assert_eq!(pool.flush_dirty(10).unwrap(), 1);
+        assert_eq!(pool.stats().unwrap().dirty_pages, 0);
+    }
+
+    #[test]
+    fn test_background_writer() {
+        let temp_dir = TempDir::new().unwrap();
+        let fm = FileManager::new(temp_dir.path()).unwrap();
+        let file = fm.create_file("test").unwrap();
+        let pool = Arc::new(BufferPool::new(8));
+
+        for _ in 0..5 {
+            let (frame_id, _page) = pool.new_page(file.clone(), PageType::Data).unwrap();
+            pool.unpin_page(frame_id, true).unwrap();
+        }
+
+        let mut writer = BackgroundWriter::start(pool.clone(), Duration::from_millis(5), 2);
+        assert!(writer.is_running());
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while pool.stats().unwrap().dirty_pages > 0 && std::time::Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(pool.stats().unwrap().dirty_pages, 0);
+
+        writer.stop();
+        assert!(!writer.is_running());
+        assert_eq!(writer.pages_written(), 5);
+        writer.stop();
+
+        // A stopped writer leaves new dirty pages alone
+        let (frame_id, _page) = pool.new_page(file.clone(), PageType::Data).unwrap();
+        pool.unpin_page(frame_id, true).unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(pool.stats().unwrap().dirty_pages, 1);
+    }
+
     #[test]
     fn test_lru_cache_policy() {
         let temp_dir = TempDir::new().unwrap();
@@ -895,6 +1347,8 @@ mod tests {
         let pool = BufferPool::with_policy(3, CachePolicyType::Clock);
 
         assert_eq!(pool.cache_policy_name().unwrap(), "Clock");
+        assert_eq!(CachePolicyType::from_name("CLOCK"), Some(CachePolicyType::Clock));
+        assert_eq!(CachePolicyType::from_name("mru"), None);
 
         // Fill buffer pool
         for _ in 0..3 {
@@ -938,4 +1392,61 @@ mod tests {
         let stats = pool.get_stats().unwrap();
         assert_eq!(stats.used_frames, 3);
     }
+
+    #[test]
+    fn test_arc_cache_policy_resists_scans() {
+        let temp_dir = TempDir::new().unwrap();
+        let fm = FileManager::new(temp_dir.path()).unwrap();
+        let file = fm.create_file("test").unwrap();
+        let setup = BufferPool::new(4);
+        for _ in 0..30 {
+            let (frame_id, _) = setup.new_page(file.clone(), PageType::Data).unwrap();
+            setup.unpin_page(frame_id, true).unwrap();
+        }
+        setup.flush_all().unwrap();
+
+        // Two hot pages read twice, then a scan over many cold pages
+        let hot_pages_after_scan = |policy| {
+            let pool = BufferPool::with_policy(4, policy);
+            for page_id in [0, 1, 0, 1].into_iter().chain(10..30) {
+                let (frame_id, _) = pool.fetch_page(file.clone(), page_id).unwrap();
+                pool.unpin_page(frame_id, false).unwrap();
+            }
+            let misses = pool.stats().unwrap().misses;
+            for page_id in [0, 1] {
+                let (frame_id, _) = pool.fetch_page(file.clone(), page_id).unwrap();
+                pool.unpin_page(frame_id, false).unwrap();
+            }
+            (pool.cache_policy_name().unwrap(), pool.stats().unwrap().misses - misses)
+        };
+
+        assert_eq!(hot_pages_after_scan(CachePolicyType::ARC), ("ARC".to_string(), 0));
+        assert_eq!(hot_pages_after_scan(CachePolicyType::LRU), ("LRU".to_string(), 2));
+    }
+
+    #[test]
+    fn test_arc_cache_policy_adapts_to_ghost_hits() {
+        let mut policy = ARCPolicy::new(2);
+        let frames: Vec<Mutex<Frame>> = (0..2).map(|_| Mutex::new(Frame::new())).collect();
+        let key = |page_id: PageId| ("test".to_string(), page_id);
+
+        policy.on_insert(0, &key(0));
+        policy.on_insert(1, &key(1));
+        for frame in &frames {
+            frame.lock().unwrap().page = Some(Arc::new(Mutex::new(Page::new(0, PageType::Data))));
+        }
+
+        // Page 0 is the least recently used page of t1
+        assert_eq!(policy.find_victim(&frames), Some(0));
+        policy.on_evict(0);
+        assert_eq!(policy.b1, VecDeque::from([key(0)]));
+
+        // Reloading it is a ghost hit: t1 gets more room and the page goes to t2
+        policy.on_insert(0, &key(0));
+        assert_eq!(policy.p, 1);
+        assert!(policy.b1.is_empty());
+        assert_eq!(policy.t2, VecDeque::from([0]));
+        // t1 is now at its target size, so the next victim comes from t2
+        assert_eq!(policy.find_victim(&frames), Some(0));
+    }
 }
@@ -0,0 +1,144 @@
+//! Page compression
+//!
+//! Database files can store their pages compressed with LZ4 (fast) or zstd
+//! (smaller). Compression is transparent to everything above
+//! [`DatabaseFile`](crate::storage::DatabaseFile): pages are compressed when
+//! written and decompressed when read. The codecs are only compiled with the
+//! `compression` feature; without it only [`Compression::None`] is usable.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use thiserror::Error;
+
+/// Compression method for page payloads
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Compression {
+    /// Pages are stored as is
+    #[default]
+    None,
+    /// LZ4 block compression: fast, moderate ratio
+    Lz4,
+    /// Zstandard at its default level: slower, better ratio
+    Zstd,
+}
+
+/// Compression errors
+#[derive(Error, Debug)]
+pub enum CompressionError {
+    #[error("{0} compression is not available in this build (enable the `compression` feature)")]
+    Unsupported(Compression),
+
+    #[error("Unknown compression method tag {0}")]
+    UnknownTag(u8),
+
+    #[error("Corrupt {method} data: {reason}")]
+    Corrupt { method: Compression, reason: String },
+}
+
+impl Compression {
+    /// Every method, including the ones not compiled into this build
+    pub const ALL: [Compression; 3] = [Compression::None, Compression::Lz4, Compression::Zstd];
+
+    /// SQL name of the method
+    pub fn name(self) -> &'static str {
+        match self {
+            Compression::None => "none",
+            Compression::Lz4 => "lz4",
+            Compression::Zstd => "zstd",
+        }
+    }
+
+    /// Parse a method name, ignoring case
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|method| method.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Whether this build can compress and decompress with the method
+    pub fn is_supported(self) -> bool {
+        self == Compression::None || cfg!(feature = "compression")
+    }
+
+    /// One-byte identifier stored in file headers
+    pub fn tag(self) -> u8 {
+        match self {
+            Compression::None => 0,
+            Compression::Lz4 => 1,
+            Compression::Zstd => 2,
+        }
+    }
+
+    /// Inverse of [`Compression::tag`]
+    pub fn from_tag(tag: u8) -> Result<Self, CompressionError> {
+        Self::ALL
+            .into_iter()
+            .find(|method| method.tag() == tag)
+            .ok_or(CompressionError::UnknownTag(tag))
+    }
+
+    /// Compress a buffer
+    pub fn compress(self, data: &[u8]) -> Result<Vec<u8>, CompressionError> {
+        match self {
+            Compression::None => Ok(data.to_vec()),
+            #[cfg(feature = "compression")]
+            Compression::Lz4 => Ok(lz4_flex::compress(data)),
+            #[cfg(feature = "compression")]
+            Compression::Zstd => zstd::bulk::compress(data, zstd::DEFAULT_COMPRESSION_LEVEL)
+                .map_err(|e| self.corrupt(e)),
+            #[cfg(not(feature = "compression"))]
+            _ => Err(CompressionError::Unsupported(self)),
+        }
+    }
+
+    /// Decompress a buffer that was `size` bytes before compression
+    pub fn decompress(self, data: &[u8], size: usize) -> Result<Vec<u8>, CompressionError> {
+        let decompressed = match self {
+            Compression::None => data.to_vec(),
+            #[cfg(feature = "compression")]
+            Compression::Lz4 => lz4_flex::decompress(data, size).map_err(|e| self.corrupt(e))?,
+            #[cfg(feature = "compression")]
+            Compression::Zstd => zstd::bulk::decompress(data, size).map_err(|e| self.corrupt(e))?,
+            #[cfg(not(feature = "compression"))]
+            _ => return Err(CompressionError::Unsupported(self)),
+        };
+        if decompressed.len() != size {
+            return Err(self.corrupt(format!("expected {} bytes, got {}", size, decompressed.len())));
+        }
+        Ok(decompressed)
+    }
+
+    #[cfg_attr(not(feature = "compression"), allow(dead_code))]
+    fn corrupt(self, reason: impl fmt::Display) -> CompressionError {
+        CompressionError::Corrupt {
+            method: self,
+            reason: reason.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(all(test, feature = "compression"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut page = vec![0u8; 4096];
+        page[100..140].copy_from_slice(&[b'a'; 40]);
+        for method in Compression::ALL {
+            let compressed = method.compress(&page).unwrap();
+            if method != Compression::None {
+                assert!(compressed.len() < page.len() / 10, "{}: {} bytes", method, compressed.len());
+            }
+            assert_eq!(method.decompress(&compressed, page.len()).unwrap(), page);
+            assert_eq!(Compression::from_tag(method.tag()).unwrap(), method);
+            assert_eq!(Compression::from_name(&method.name().to_uppercase()), Some(method));
+        }
+        assert!(Compression::Lz4.decompress(b"garbage", 4096).is_err());
+        assert!(Compression::from_tag(9).is_err());
+    }
+}
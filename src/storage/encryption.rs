@@ -0,0 +1,325 @@
+//! At-rest encryption
+//!
+//! With an [`EncryptionKey`] the engine encrypts everything it keeps in the
+//! data directory with AES-256-GCM:
+//!
+//! * Blobs (metadata, table snapshots, the write-ahead log) go through an
+//!   [`EncryptedBackend`], which stores each blob as a sequence of chunks
+//!   `[length: u32][nonce: 12 bytes][ciphertext + tag]`. Writing a blob
+//!   produces one chunk and appending adds another, so the log can still grow
+//!   without rewriting what is already on disk.
+//! * Page files are framed like compressed files (see
+//!   [`DatabaseFile`](crate::storage::DatabaseFile)) and each frame holds the
+//!   encrypted, possibly compressed, page.
+//!
+//! Every chunk and page is authenticated together with its blob name or page
+//! ID, so data cannot be moved between blobs or pages unnoticed. A small
+//! check blob written when the directory is first encrypted lets a database
+//! reject a wrong key before reading anything else. Spill files in the temp
+//! space are not encrypted; they only live while a statement runs.
+//!
+//! The cipher is only compiled with the `encryption` feature; without it the
+//! types exist but every operation fails with [`EncryptionError::Unsupported`].
+
+use crate::storage::backend::FileBackend;
+use std::fmt;
+use std::io;
+use std::path::Path;
+use thiserror::Error;
+
+/// Name of the blob that verifies the key of an encrypted directory
+pub const ENCRYPTION_CHECK_NAME: &str = "encryption_check";
+
+/// Plaintext of the check blob
+const CHECK_PLAINTEXT: &[u8] = b"MiniDB encryption check v1";
+
+/// Size of an AES-GCM nonce
+#[cfg_attr(not(feature = "encryption"), allow(dead_code))]
+const NONCE_SIZE: usize = 12;
+
+/// Size of a chunk's length prefix
+const CHUNK_HEADER_SIZE: usize = 4;
+
+/// Encryption errors
+#[derive(Error, Debug)]
+pub enum EncryptionError {
+    #[error("Encryption is not available in this build (enable the `encryption` feature)")]
+    Unsupported,
+
+    #[error("Invalid encryption key: expected {expected} bytes, got {actual}")]
+    InvalidKeyLength { expected: usize, actual: usize },
+
+    #[error("Wrong encryption key: the data was encrypted with a different key")]
+    WrongKey,
+
+    #[error("The data is encrypted; an encryption key is required to open it")]
+    KeyRequired,
+
+    #[error("The data directory already holds unencrypted data and cannot be opened with an encryption key")]
+    NotEncrypted,
+
+    #[error("Failed to decrypt {0}: the data is corrupt or was modified")]
+    Corrupt(String),
+
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// 256-bit AES-GCM key
+///
+/// The key is never printed: its `Debug` output is redacted.
+#[derive(Clone, PartialEq, Eq)]
+pub struct EncryptionKey([u8; EncryptionKey::LEN]);
+
+impl EncryptionKey {
+    /// Key length in bytes
+    pub const LEN: usize = 32;
+
+    /// Wrap raw key bytes
+    pub fn new(bytes: [u8; Self::LEN]) -> Self {
+        Self(bytes)
+    }
+
+    /// Wrap raw key bytes, checking their length
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, EncryptionError> {
+        bytes
+            .try_into()
+            .map(Self)
+            .map_err(|_| EncryptionError::InvalidKeyLength {
+                expected: Self::LEN,
+                actual: bytes.len(),
+            })
+    }
+
+    /// Whether this build can encrypt and decrypt
+    pub fn is_supported() -> bool {
+        cfg!(feature = "encryption")
+    }
+
+    /// Encrypt a buffer under a fresh random nonce; the result is the nonce
+    /// followed by the ciphertext and tag. `aad` is authenticated but not
+    /// stored, and must be passed again to [`EncryptionKey::decrypt`].
+    pub fn encrypt(&self, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>, EncryptionError> {
+        #[cfg(feature = "encryption")]
+        {
+            use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+            use aes_gcm::Aes256Gcm;
+
+            let cipher = Aes256Gcm::new(&self.0.into());
+            let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+            let ciphertext = cipher
+                .encrypt(&nonce, Payload { msg: plaintext, aad })
+                .expect("AES-GCM encrypts messages of up to 64 GiB");
+            let mut sealed = Vec::with_capacity(NONCE_SIZE + ciphertext.len());
+            sealed.extend_from_slice(&nonce);
+            sealed.extend_from_slice(&ciphertext);
+            Ok(sealed)
+        }
+        #[cfg(not(feature = "encryption"))]
+        {
+            let _ = (plaintext, aad);
+            Err(EncryptionError::Unsupported)
+        }
+    }
+
+    /// Decrypt the output of [`EncryptionKey::encrypt`]; `None` means the
+    /// key or `aad` is wrong or the data was modified
+    pub fn decrypt(&self, sealed: &[u8], aad: &[u8]) -> Result<Option<Vec<u8>>, EncryptionError> {
+        #[cfg(feature = "encryption")]
+        {
+            use aes_gcm::aead::{Aead, KeyInit, Payload};
+            use aes_gcm::{Aes256Gcm, Nonce};
+
+            if sealed.len() < NONCE_SIZE {
+                return Ok(None);
+            }
+            let (nonce, ciphertext) = sealed.split_at(NONCE_SIZE);
+            let cipher = Aes256Gcm::new(&self.0.into());
+            Ok(cipher
+                .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad })
+                .ok())
+        }
+        #[cfg(not(feature = "encryption"))]
+        {
+            let _ = (sealed, aad);
+            Err(EncryptionError::Unsupported)
+        }
+    }
+}
+
+impl fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EncryptionKey(<redacted>)")
+    }
+}
+
+/// Encrypts the blobs of another backend
+pub struct EncryptedBackend {
+    inner: Box<dyn FileBackend>,
+    key: EncryptionKey,
+}
+
+impl EncryptedBackend {
+    /// Wrap a backend, verifying the key against its check blob
+    ///
+    /// An empty backend becomes encrypted with this key. A backend that holds
+    /// data but no check blob was written without encryption and is refused.
+    pub fn open(inner: Box<dyn FileBackend>, key: EncryptionKey) -> Result<Self, EncryptionError> {
+        if !EncryptionKey::is_supported() {
+            return Err(EncryptionError::Unsupported);
+        }
+        match inner.read(ENCRYPTION_CHECK_NAME)? {
+            Some(check) => {
+                if key.decrypt(&check, ENCRYPTION_CHECK_NAME.as_bytes())?.as_deref() != Some(CHECK_PLAINTEXT) {
+                    return Err(EncryptionError::WrongKey);
+                }
+            }
+            None => {
+                if !inner.list()?.is_empty() {
+                    return Err(EncryptionError::NotEncrypted);
+                }
+                let check = key.encrypt(CHECK_PLAINTEXT, ENCRYPTION_CHECK_NAME.as_bytes())?;
+                inner.write(ENCRYPTION_CHECK_NAME, &check)?;
+            }
+        }
+        Ok(Self { inner, key })
+    }
+
+    /// Whether a backend holds encrypted data
+    pub fn is_encrypted(backend: &dyn FileBackend) -> io::Result<bool> {
+        Ok(backend.read(ENCRYPTION_CHECK_NAME)?.is_some())
+    }
+
+    /// Encrypt data into one chunk of a blob
+    fn seal(&self, name: &str, data: &[u8]) -> io::Result<Vec<u8>> {
+        let sealed = self.key.encrypt(data, name.as_bytes()).map_err(invalid_data)?;
+        let mut chunk = Vec::with_capacity(CHUNK_HEADER_SIZE + sealed.len());
+        chunk.extend_from_slice(&(sealed.len() as u32).to_le_bytes());
+        chunk.extend_from_slice(&sealed);
+        Ok(chunk)
+    }
+}
+
+impl FileBackend for EncryptedBackend {
+    /// An incomplete final chunk (an interrupted append) is dropped, and the
+    /// blob is rewritten without it so that later appends follow the last
+    /// complete chunk
+    fn read(&self, name: &str) -> io::Result<Option<Vec<u8>>> {
+        let Some(data) = self.inner.read(name)? else {
+            return Ok(None);
+        };
+        let mut plaintext = Vec::new();
+        let mut offset = 0;
+        while offset < data.len() {
+            let sealed = data
+                .get(offset..offset + CHUNK_HEADER_SIZE)
+                .map(|header| u32::from_le_bytes(header.try_into().unwrap()) as usize)
+                .and_then(|len| data.get(offset + CHUNK_HEADER_SIZE..offset + CHUNK_HEADER_SIZE + len));
+            let Some(sealed) = sealed else {
+                self.inner.write(name, &data[..offset])?;
+                break;
+            };
+            let chunk = self.key.decrypt(sealed, name.as_bytes())
+                .map_err(invalid_data)?
+                .ok_or_else(|| invalid_data(EncryptionError::Corrupt(format!("'{}'", name))))?;
+            plaintext.extend_from_slice(&chunk);
+            offset += CHUNK_HEADER_SIZE + sealed.len();
+        }
+        Ok(Some(plaintext))
+    }
+
+    fn write(&self, name: &str, data: &[u8]) -> io::Result<()> {
+        self.inner.write(name, &self.seal(name, data)?)
+    }
+
+    fn append(&self, name: &str, data: &[u8]) -> io::Result<()> {
+        self.inner.append(name, &self.seal(name, data)?)
+    }
+
+    fn remove(&self, name: &str) -> io::Result<()> {
+        self.inner.remove(name)
+    }
+
+    fn list(&self) -> io::Result<Vec<String>> {
+        self.inner.list()
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        self.inner.size()
+    }
+
+    fn directory(&self) -> Option<&Path> {
+        self.inner.directory()
+    }
+}
+
+fn invalid_data(error: EncryptionError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+#[cfg(all(test, feature = "encryption"))]
+mod tests {
+    use super::*;
+    use crate::storage::backend::MemoryBackend;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_encrypt_round_trip() {
+        let key = EncryptionKey::new([7; EncryptionKey::LEN]);
+        let sealed = key.encrypt(b"secret page", b"page 1").unwrap();
+        assert!(!sealed.windows(6).any(|w| w == b"secret"));
+        assert_eq!(key.decrypt(&sealed, b"page 1").unwrap().unwrap(), b"secret page");
+
+        // Wrong key, wrong associated data and modified ciphertext all fail
+        let other = EncryptionKey::new([8; EncryptionKey::LEN]);
+        assert_eq!(other.decrypt(&sealed, b"page 1").unwrap(), None);
+        assert_eq!(key.decrypt(&sealed, b"page 2").unwrap(), None);
+        let mut tampered = sealed.clone();
+        tampered[NONCE_SIZE] ^= 1;
+        assert_eq!(key.decrypt(&tampered, b"page 1").unwrap(), None);
+
+        assert!(EncryptionKey::from_slice(&[0; 16]).is_err());
+        assert_eq!(format!("{:?}", key), "EncryptionKey(<redacted>)");
+    }
+
+    #[test]
+    fn test_encrypted_backend() {
+        let key = EncryptionKey::new([1; EncryptionKey::LEN]);
+        let memory = Arc::new(MemoryBackend::new());
+        let backend = EncryptedBackend::open(Box::new(memory.clone()), key.clone()).unwrap();
+        assert!(EncryptedBackend::is_encrypted(&memory).unwrap());
+
+        backend.write("wal.log", b"first;").unwrap();
+        backend.append("wal.log", b"second;").unwrap();
+        assert_eq!(backend.read("wal.log").unwrap().unwrap(), b"first;second;");
+        let raw = memory.read("wal.log").unwrap().unwrap();
+        assert!(!raw.windows(5).any(|w| w == b"first"));
+
+        // A torn final chunk is cut off and appends continue after the last good one
+        memory.append("wal.log", &[200, 0, 0, 0, 1, 2]).unwrap();
+        assert_eq!(backend.read("wal.log").unwrap().unwrap(), b"first;second;");
+        assert_eq!(memory.read("wal.log").unwrap().unwrap(), raw);
+        backend.append("wal.log", b"third;").unwrap();
+        assert_eq!(backend.read("wal.log").unwrap().unwrap(), b"first;second;third;");
+
+        // Chunks are bound to their blob name
+        memory.write("copy.log", &raw).unwrap();
+        assert!(backend.read("copy.log").is_err());
+
+        // Reopening checks the key
+        assert!(EncryptedBackend::open(Box::new(memory.clone()), key).is_ok());
+        let wrong = EncryptionKey::new([2; EncryptionKey::LEN]);
+        assert!(matches!(
+            EncryptedBackend::open(Box::new(memory), wrong),
+            Err(EncryptionError::WrongKey)
+        ));
+
+        // Existing plaintext data is not silently mixed with encrypted blobs
+        let plain = MemoryBackend::new();
+        plain.write("metadata.json", b"{}").unwrap();
+        assert!(matches!(
+            EncryptedBackend::open(Box::new(plain), EncryptionKey::new([1; 32])),
+            Err(EncryptionError::NotEncrypted)
+        ));
+    }
+}
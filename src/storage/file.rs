@@ -2,8 +2,26 @@
 //!
 //! This module provides file system operations for database storage.
 //! It manages database files and provides atomic I/O operations.
+//!
+//! A plain database file is an array of fixed-size pages. A compressed file
+//! starts with an 8-byte header (`MDBZ` and the method tag) followed by
+//! frames of `[page id: u32][length: u32][checksum: u32][compressed page]`;
+//! writing a page appends a new frame, and the last complete frame of each
+//! page wins when the file is opened. Space of overwritten frames is given
+//! back when the file is rewritten, e.g. by the next table snapshot.
+//! Files of a [`FileManager`] with an encryption key use the same framed
+//! format with an encrypted flag in the header; each frame then holds the
+//! (compressed) page encrypted with AES-GCM, see [`crate::storage::encryption`].
+//!
+//! Plain files are read and written with seek + read/write system calls by
+//! default. With the `mmap` feature a [`FileManager`] can instead map them
+//! into memory ([`FileIo::Mmap`]) so page reads and writes are memory copies;
+//! framed files always use system calls because their frames are appended
+//! rather than written in place.
 
-use crate::storage::page::{Page, PageId, PageType, PAGE_SIZE};
+use crate::storage::compression::{Compression, CompressionError};
+use crate::storage::encryption::{EncryptionError, EncryptionKey};
+use crate::storage::page::{Page, PageId, PAGE_SIZE};
 use std::collections::HashMap;
 use std::fs::{File, OpenOptions};
 use std::io::{Read, Seek, SeekFrom, Write};
@@ -14,10 +32,47 @@ use thiserror::Error;
 /// File identifier type
 pub type FileId = u32;
 
+/// How a file's pages are read and written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FileIo {
+    /// Seek followed by a read or write system call per page
+    #[default]
+    Syscall,
+    /// Copy pages to and from a shared memory map of the file
+    Mmap,
+}
+
+impl FileIo {
+    /// Every method, including the ones not compiled into this build
+    pub const ALL: [FileIo; 2] = [FileIo::Syscall, FileIo::Mmap];
+
+    /// Configuration name of the method
+    pub fn name(self) -> &'static str {
+        match self {
+            FileIo::Syscall => "syscall",
+            FileIo::Mmap => "mmap",
+        }
+    }
+
+    /// Parse a method name, ignoring case
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|io| io.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Whether this build supports the method
+    pub fn is_supported(self) -> bool {
+        self == FileIo::Syscall || cfg!(feature = "mmap")
+    }
+}
+
 /// File manager for database storage
 pub struct FileManager {
     /// Base directory for database files
     base_dir: PathBuf,
+    /// Page I/O method of the files it creates and opens
+    io: FileIo,
+    /// Key that encrypts the pages of the files it creates
+    key: Option<EncryptionKey>,
     /// Open files cache
     files: Arc<RwLock<HashMap<String, Arc<Mutex<DatabaseFile>>>>>,
     /// Next file ID for auto-generation
@@ -35,8 +90,33 @@ pub struct DatabaseFile {
     page_count: u32,
     /// File ID
     file_id: FileId,
+    /// Compression of page payloads
+    compression: Compression,
+    /// Key of an encrypted file
+    key: Option<EncryptionKey>,
+    /// Location of the latest frame of each page (framed files only):
+    /// page ID -> (payload offset, payload length)
+    frames: HashMap<PageId, (u64, u32)>,
+    /// Page I/O method
+    io: FileIo,
+    /// Shared map of the file's pages (mmap I/O only); remapped when pages
+    /// past its end are accessed
+    #[cfg(feature = "mmap")]
+    map: Option<memmap2::MmapMut>,
 }
 
+/// Magic bytes at the start of a framed (compressed or encrypted) database file
+const COMPRESSED_MAGIC: &[u8; 4] = b"MDBZ";
+
+/// Size of the framed file header: magic, method tag, flags and padding
+const COMPRESSED_HEADER_SIZE: u64 = 8;
+
+/// Header flag of a file whose frames are encrypted
+const ENCRYPTED_FLAG: u8 = 1;
+
+/// Size of a frame header: page ID, payload length and checksum
+const FRAME_HEADER_SIZE: usize = 12;
+
 /// File system errors
 #[derive(Error, Debug)]
 pub enum FileError {
@@ -57,11 +137,28 @@ pub enum FileError {
     
     #[error("Lock error: file is locked")]
     LockError,
+
+    #[error("Compression error: {0}")]
+    Compression(#[from] CompressionError),
+
+    #[error("Encryption error: {0}")]
+    Encryption(#[from] EncryptionError),
+
+    #[error("{0} file I/O is not available in this build (enable the `mmap` feature)")]
+    UnsupportedIo(&'static str),
 }
 
 impl FileManager {
     /// Create a new file manager
     pub fn new<P: AsRef<Path>>(base_dir: P) -> Result<Self, FileError> {
+        Self::with_io(base_dir, FileIo::Syscall)
+    }
+    
+    /// Create a file manager whose files use the given page I/O method
+    pub fn with_io<P: AsRef<Path>>(base_dir: P, io: FileIo) -> Result<Self, FileError> {
+        if !io.is_supported() {
+            return Err(FileError::UnsupportedIo(io.name()));
+        }
         let base_dir = base_dir.as_ref().to_path_buf();
         
         // Create base directory if it doesn't exist
@@ -71,14 +168,48 @@ impl FileManager {
         
         Ok(Self {
             base_dir,
+            io,
+            key: None,
             files: Arc::new(RwLock::new(HashMap::new())),
             next_file_id: Arc::new(Mutex::new(1)),
         })
     }
     
+    /// Page I/O method of the files this manager creates and opens
+    pub fn io(&self) -> FileIo {
+        self.io
+    }
+    
+    /// Encrypt the pages of the files this manager creates with the given key;
+    /// it also decrypts existing encrypted files when they are opened
+    pub fn with_encryption(mut self, key: EncryptionKey) -> Result<Self, FileError> {
+        if !EncryptionKey::is_supported() {
+            return Err(EncryptionError::Unsupported.into());
+        }
+        self.key = Some(key);
+        Ok(self)
+    }
+    
+    /// Whether the files this manager creates are encrypted
+    pub fn is_encrypted(&self) -> bool {
+        self.key.is_some()
+    }
+    
     /// Create a new database file
     pub fn create_file(&self, name: &str) -> Result<Arc<Mutex<DatabaseFile>>, FileError> {
-        let file_path = self.base_dir.join(format!("{}.db", name));
+        self.create_file_with_compression(name, Compression::None)
+    }
+
+    /// Create a new database file whose pages are stored compressed
+    pub fn create_file_with_compression(
+        &self,
+        name: &str,
+        compression: Compression,
+    ) -> Result<Arc<Mutex<DatabaseFile>>, FileError> {
+        if !compression.is_supported() {
+            return Err(CompressionError::Unsupported(compression).into());
+        }
+        let file_path = self.file_path(name);
         
         if file_path.exists() {
             return Err(FileError::AlreadyExists {
@@ -96,17 +227,32 @@ impl FileManager {
         };
         
         // Create file
-        let file = OpenOptions::new()
+        let mut file = OpenOptions::new()
             .create(true)
             .read(true)
             .write(true)
             .open(&file_path)?;
+        if compression != Compression::None || self.key.is_some() {
+            let mut header = [0u8; COMPRESSED_HEADER_SIZE as usize];
+            header[..4].copy_from_slice(COMPRESSED_MAGIC);
+            header[4] = compression.tag();
+            if self.key.is_some() {
+                header[5] = ENCRYPTED_FLAG;
+            }
+            file.write_all(&header)?;
+        }
             
         let db_file = DatabaseFile {
             path: file_path.clone(),
             file,
             page_count: 0,
             file_id,
+            compression,
+            key: self.key.clone(),
+            frames: HashMap::new(),
+            io: self.io,
+            #[cfg(feature = "mmap")]
+            map: None,
         };
         
         let db_file_arc = Arc::new(Mutex::new(db_file));
@@ -132,7 +278,7 @@ impl FileManager {
             }
         }
         
-        let file_path = self.base_dir.join(format!("{}.db", name));
+        let file_path = self.file_path(name);
         
         if !file_path.exists() {
             return Err(FileError::NotFound {
@@ -155,16 +301,7 @@ impl FileManager {
             .write(true)
             .open(&file_path)?;
             
-        // Calculate page count
-        let file_size = file.metadata()?.len();
-        let page_count = (file_size / PAGE_SIZE as u64) as u32;
-        
-        let db_file = DatabaseFile {
-            path: file_path.clone(),
-            file,
-            page_count,
-            file_id,
-        };
+        let db_file = DatabaseFile::load(file_path, file, file_id, self.io, self.key.as_ref())?;
         
         let db_file_arc = Arc::new(Mutex::new(db_file));
         
@@ -178,6 +315,11 @@ impl FileManager {
         Ok(db_file_arc)
     }
     
+    /// Path of the file with the given name, whether or not it exists
+    pub fn file_path(&self, name: &str) -> PathBuf {
+        self.base_dir.join(format!("{}.db", name))
+    }
+    
     /// Close a file and remove from cache
     pub fn close_file(&self, name: &str) -> Result<(), FileError> {
         let mut files = self.files.write()
@@ -191,7 +333,7 @@ impl FileManager {
         // Close file first
         self.close_file(name)?;
         
-        let file_path = self.base_dir.join(format!("{}.db", name));
+        let file_path = self.file_path(name);
         
         if file_path.exists() {
             std::fs::remove_file(&file_path)?;
@@ -200,6 +342,18 @@ impl FileManager {
         Ok(())
     }
     
+    /// Flush and fsync every open file
+    pub fn sync_all(&self) -> Result<(), FileError> {
+        let files = self.files.read()
+            .map_err(|_| FileError::LockError)?;
+        for file in files.values() {
+            file.lock()
+                .map_err(|_| FileError::LockError)?
+                .sync()?;
+        }
+        Ok(())
+    }
+    
     /// List all database files
     pub fn list_files(&self) -> Result<Vec<String>, FileError> {
         let mut files = Vec::new();
@@ -222,6 +376,100 @@ impl FileManager {
 }
 
 impl DatabaseFile {
+    /// Wrap an existing file, detecting compression and encryption and
+    /// locating pages
+    fn load(
+        path: PathBuf,
+        mut file: File,
+        file_id: FileId,
+        io: FileIo,
+        key: Option<&EncryptionKey>,
+    ) -> Result<Self, FileError> {
+        let file_size = file.metadata()?.len();
+        let mut header = [0u8; COMPRESSED_HEADER_SIZE as usize];
+        let framed = file_size >= COMPRESSED_HEADER_SIZE
+            && file.read_exact(&mut header).is_ok()
+            && &header[..4] == COMPRESSED_MAGIC;
+
+        let mut db_file = Self {
+            path,
+            file,
+            page_count: (file_size / PAGE_SIZE as u64) as u32,
+            file_id,
+            compression: Compression::None,
+            key: None,
+            frames: HashMap::new(),
+            io,
+            #[cfg(feature = "mmap")]
+            map: None,
+        };
+        if framed {
+            db_file.compression = Compression::from_tag(header[4])?;
+            if header[5] & ENCRYPTED_FLAG != 0 {
+                db_file.key = Some(key.cloned().ok_or(EncryptionError::KeyRequired)?);
+            }
+            db_file.scan_frames(file_size)?;
+        }
+        Ok(db_file)
+    }
+
+    /// Index the frames of a framed file; a torn or corrupt frame at the
+    /// end (an interrupted write) and everything after it is ignored
+    fn scan_frames(&mut self, file_size: u64) -> Result<(), FileError> {
+        self.page_count = 0;
+        let mut offset = COMPRESSED_HEADER_SIZE;
+        while offset + FRAME_HEADER_SIZE as u64 <= file_size {
+            self.file.seek(SeekFrom::Start(offset))?;
+            let mut header = [0u8; FRAME_HEADER_SIZE];
+            self.file.read_exact(&mut header)?;
+            let page_id = u32::from_le_bytes(header[0..4].try_into().unwrap());
+            let length = u32::from_le_bytes(header[4..8].try_into().unwrap());
+            let checksum = u32::from_le_bytes(header[8..12].try_into().unwrap());
+            
+            let payload_offset = offset + FRAME_HEADER_SIZE as u64;
+            if payload_offset + length as u64 > file_size {
+                break;
+            }
+            let mut payload = vec![0u8; length as usize];
+            self.file.read_exact(&mut payload)?;
+            if crate::utils::checksum(&payload) != checksum {
+                break;
+            }
+            
+            self.frames.insert(page_id, (payload_offset, length));
+            self.page_count = self.page_count.max(page_id + 1);
+            offset = payload_offset + length as u64;
+        }
+        
+        // Later frames are appended after the last good one
+        self.file.set_len(offset)?;
+        Ok(())
+    }
+    
+    /// Compression of page payloads
+    pub fn compression(&self) -> Compression {
+        self.compression
+    }
+    
+    /// Whether the pages are encrypted
+    pub fn is_encrypted(&self) -> bool {
+        self.key.is_some()
+    }
+    
+    /// Whether pages are stored as appended frames rather than in place
+    fn is_framed(&self) -> bool {
+        self.compression != Compression::None || self.key.is_some()
+    }
+    
+    /// Page I/O method; compressed and encrypted files always use system calls
+    pub fn io(&self) -> FileIo {
+        if self.is_framed() {
+            FileIo::Syscall
+        } else {
+            self.io
+        }
+    }
+    
     /// Get file ID
     pub fn file_id(&self) -> FileId {
         self.file_id
@@ -241,6 +489,12 @@ impl DatabaseFile {
     pub fn allocate_page(&mut self) -> Result<PageId, FileError> {
         let page_id = self.page_count;
         
+        // A framed page takes space only when it is first written
+        if self.is_framed() {
+            self.page_count += 1;
+            return Ok(page_id);
+        }
+        
         // Extend file size
         self.file.seek(SeekFrom::Start((page_id as u64 + 1) * PAGE_SIZE as u64))?;
         self.file.write(&[0])?; // Write one byte to extend file
@@ -260,12 +514,35 @@ impl DatabaseFile {
             });
         }
         
-        // Seek to page position
-        self.file.seek(SeekFrom::Start(page_id as u64 * PAGE_SIZE as u64))?;
+        #[cfg(feature = "mmap")]
+        if let Some(map) = self.mapped_page(page_id)? {
+            return Page::from_bytes(page_id, map.to_vec())
+                .map_err(|e| FileError::InvalidFormat { 
+                    reason: format!("Failed to parse page {}: {}", page_id, e) 
+                });
+        }
         
-        // Read page data
-        let mut buffer = vec![0u8; PAGE_SIZE];
-        self.file.read_exact(&mut buffer)?;
+        let buffer = if !self.is_framed() {
+            // Seek to page position
+            self.file.seek(SeekFrom::Start(page_id as u64 * PAGE_SIZE as u64))?;
+            
+            // Read page data
+            let mut buffer = vec![0u8; PAGE_SIZE];
+            self.file.read_exact(&mut buffer)?;
+            buffer
+        } else {
+            let &(offset, length) = self.frames.get(&page_id).ok_or_else(|| FileError::InvalidFormat {
+                reason: format!("Page {} has never been written", page_id),
+            })?;
+            self.file.seek(SeekFrom::Start(offset))?;
+            let mut payload = vec![0u8; length as usize];
+            self.file.read_exact(&mut payload)?;
+            if let Some(key) = &self.key {
+                payload = key.decrypt(&payload, &page_id.to_le_bytes())?
+                    .ok_or_else(|| EncryptionError::Corrupt(format!("page {} of {}", page_id, self.path.display())))?;
+            }
+            self.compression.decompress(&payload, PAGE_SIZE)?
+        };
         
         // Parse page from bytes
         Page::from_bytes(page_id, buffer)
@@ -285,17 +562,38 @@ impl DatabaseFile {
             });
         }
         
-        // Seek to page position
-        self.file.seek(SeekFrom::Start(page_id as u64 * PAGE_SIZE as u64))?;
-        
         // Serialize page to bytes
         let page_bytes = page.to_bytes()
             .map_err(|e| FileError::InvalidFormat { 
                 reason: format!("Failed to serialize page {}: {}", page_id, e) 
             })?;
         
-        // Write page data
-        self.file.write_all(page_bytes)?;
+        #[cfg(feature = "mmap")]
+        if let Some(map) = self.mapped_page(page_id)? {
+            map.copy_from_slice(page_bytes);
+            page.mark_clean();
+            return Ok(());
+        }
+        
+        if !self.is_framed() {
+            // Write page data in place
+            self.file.seek(SeekFrom::Start(page_id as u64 * PAGE_SIZE as u64))?;
+            self.file.write_all(page_bytes)?;
+        } else {
+            // Append a new frame; the previous one becomes garbage
+            let mut payload = self.compression.compress(page_bytes)?;
+            if let Some(key) = &self.key {
+                payload = key.encrypt(&payload, &page_id.to_le_bytes())?;
+            }
+            let offset = self.file.seek(SeekFrom::End(0))?;
+            let mut frame = Vec::with_capacity(FRAME_HEADER_SIZE + payload.len());
+            frame.extend_from_slice(&page_id.to_le_bytes());
+            frame.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+            frame.extend_from_slice(&crate::utils::checksum(&payload).to_le_bytes());
+            frame.extend_from_slice(&payload);
+            self.file.write_all(&frame)?;
+            self.frames.insert(page_id, (offset + FRAME_HEADER_SIZE as u64, payload.len() as u32));
+        }
         self.file.flush()?;
         
         // Mark page as clean
@@ -306,14 +604,46 @@ impl DatabaseFile {
     
     /// Sync all changes to disk
     pub fn sync(&mut self) -> Result<(), FileError> {
+        #[cfg(feature = "mmap")]
+        if let Some(map) = &self.map {
+            map.flush()?;
+        }
         self.file.flush()?;
+        self.file.sync_all()?;
         Ok(())
     }
+    
+    /// The bytes of a page in the file's memory map, or `None` when the file
+    /// does not use mmap I/O; the map is extended first if the page was
+    /// allocated after it was made
+    #[cfg(feature = "mmap")]
+    fn mapped_page(&mut self, page_id: PageId) -> Result<Option<&mut [u8]>, FileError> {
+        if self.io() != FileIo::Mmap {
+            return Ok(None);
+        }
+        let start = page_id as usize * PAGE_SIZE;
+        let end = start + PAGE_SIZE;
+        if !matches!(&self.map, Some(map) if map.len() >= end) {
+            // SAFETY: the file is only accessed through this handle, which the
+            // file manager keeps behind a mutex, and it is never truncated
+            // while mapped, so the map stays valid for its whole lifetime
+            let map = unsafe { memmap2::MmapMut::map_mut(&self.file)? };
+            if map.len() < end {
+                return Err(FileError::InvalidPageId {
+                    page_id,
+                    max_pages: (map.len() / PAGE_SIZE) as u32,
+                });
+            }
+            self.map = Some(map);
+        }
+        Ok(self.map.as_mut().map(|map| &mut map[start..end]))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::storage::page::PageType;
     use tempfile::TempDir;
     
     #[test]
@@ -399,4 +729,122 @@ mod tests {
         fm.delete_file("test").unwrap();
         assert!(!fm.list_files().unwrap().contains(&"test".to_string()));
     }
+    
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn test_mmap_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let fm = FileManager::with_io(temp_dir.path(), FileIo::Mmap).unwrap();
+        let file_arc = fm.create_file("mapped").unwrap();
+        
+        {
+            let mut file = file_arc.lock().unwrap();
+            assert_eq!(file.io(), FileIo::Mmap);
+            // Pages allocated after the map was made extend it when first used
+            for i in 0..5u32 {
+                let page_id = file.allocate_page().unwrap();
+                let mut page = Page::new(page_id, PageType::Data);
+                page.insert_record(format!("record on page {}", i).as_bytes()).unwrap();
+                file.write_page(&mut page).unwrap();
+                assert_eq!(file.read_page(page_id).unwrap().get_record(0).unwrap(), page.get_record(0).unwrap());
+            }
+            file.sync().unwrap();
+        }
+        
+        // The pages are in the file, readable with system call I/O
+        fm.close_file("mapped").unwrap();
+        drop(file_arc);
+        let syscall_fm = FileManager::new(temp_dir.path()).unwrap();
+        let file_arc = syscall_fm.open_file("mapped").unwrap();
+        let mut file = file_arc.lock().unwrap();
+        assert_eq!(file.io(), FileIo::Syscall);
+        assert_eq!(file.page_count(), 5);
+        assert_eq!(file.read_page(3).unwrap().get_record(0).unwrap(), b"record on page 3");
+        
+        // Compressed files ignore the mmap setting
+        #[cfg(feature = "compression")]
+        {
+            let packed = fm.create_file_with_compression("packed", Compression::Lz4).unwrap();
+            assert_eq!(packed.lock().unwrap().io(), FileIo::Syscall);
+        }
+        assert_eq!(FileIo::from_name("MMAP"), Some(FileIo::Mmap));
+    }
+    
+    #[cfg(feature = "compression")]
+    #[test]
+    fn test_compressed_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let fm = FileManager::new(temp_dir.path()).unwrap();
+        let file_arc = fm.create_file_with_compression("packed", Compression::Lz4).unwrap();
+        
+        {
+            let mut file = file_arc.lock().unwrap();
+            for i in 0..3u32 {
+                let page_id = file.allocate_page().unwrap();
+                let mut page = Page::new(page_id, PageType::Data);
+                page.insert_record(format!("record on page {}", i).as_bytes()).unwrap();
+                file.write_page(&mut page).unwrap();
+            }
+            
+            // Rewriting a page appends a frame that replaces the old one
+            let mut page = file.read_page(1).unwrap();
+            page.insert_record(b"second record").unwrap();
+            file.write_page(&mut page).unwrap();
+            file.sync().unwrap();
+        }
+        
+        let path = temp_dir.path().join("packed.db");
+        let size = std::fs::metadata(&path).unwrap().len();
+        assert!(size < PAGE_SIZE as u64, "compressed file is {} bytes", size);
+        
+        // Reopen from disk, with a torn frame at the end that must be ignored
+        fm.close_file("packed").unwrap();
+        std::fs::OpenOptions::new().append(true).open(&path).unwrap().write_all(&[1, 0, 0, 0, 200, 0]).unwrap();
+        let file_arc = fm.open_file("packed").unwrap();
+        let mut file = file_arc.lock().unwrap();
+        assert_eq!(file.compression(), Compression::Lz4);
+        assert_eq!(file.page_count(), 3);
+        let page = file.read_page(1).unwrap();
+        assert_eq!(page.get_record(0).unwrap(), b"record on page 1");
+        assert_eq!(page.get_record(1).unwrap(), b"second record");
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), size);
+    }
+    
+    #[cfg(feature = "encryption")]
+    #[test]
+    fn test_encrypted_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let key = EncryptionKey::new([3; EncryptionKey::LEN]);
+        let fm = FileManager::new(temp_dir.path()).unwrap().with_encryption(key.clone()).unwrap();
+        let file_arc = fm.create_file("secret").unwrap();
+        
+        {
+            let mut file = file_arc.lock().unwrap();
+            assert!(file.is_encrypted());
+            assert_eq!(file.io(), FileIo::Syscall);
+            let page_id = file.allocate_page().unwrap();
+            let mut page = Page::new(page_id, PageType::Data);
+            page.insert_record(b"top secret record").unwrap();
+            file.write_page(&mut page).unwrap();
+            file.sync().unwrap();
+        }
+        fm.close_file("secret").unwrap();
+        drop(file_arc);
+        
+        let path = temp_dir.path().join("secret.db");
+        let raw = std::fs::read(&path).unwrap();
+        assert!(!raw.windows(6).any(|w| w == b"secret"));
+        
+        // Reopened with the key, the page decrypts
+        let file_arc = fm.open_file("secret").unwrap();
+        assert_eq!(file_arc.lock().unwrap().read_page(0).unwrap().get_record(0).unwrap(), b"top secret record");
+        
+        // Without a key the file cannot be opened, with a wrong one its pages cannot be read
+        let plain = FileManager::new(temp_dir.path()).unwrap();
+        assert!(matches!(plain.open_file("secret"), Err(FileError::Encryption(EncryptionError::KeyRequired))));
+        let wrong = FileManager::new(temp_dir.path()).unwrap()
+            .with_encryption(EncryptionKey::new([4; EncryptionKey::LEN])).unwrap();
+        let file_arc = wrong.open_file("secret").unwrap();
+        assert!(matches!(file_arc.lock().unwrap().read_page(0), Err(FileError::Encryption(EncryptionError::Corrupt(_)))));
+    }
 }
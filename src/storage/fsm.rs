@@ -0,0 +1,140 @@
+//! Free-space map
+//!
+//! Tracks how much free space each page of a heap file has, so an insert can
+//! pick a page with room without reading pages. Free space is recorded in
+//! coarse categories of [`CATEGORY_BYTES`] bytes, rounded down, and pages are
+//! kept in one bucket per category; finding a page returns one from the
+//! smallest category with enough room after checking at most
+//! [`CATEGORIES`] buckets and updating a page moves it between buckets in
+//! constant time.
+
+use crate::storage::page::{PageId, PAGE_SIZE};
+
+/// Number of free-space categories
+pub const CATEGORIES: usize = 256;
+
+/// Free bytes covered by one category
+pub const CATEGORY_BYTES: usize = PAGE_SIZE / CATEGORIES;
+
+/// Free space per page of one heap file
+#[derive(Debug, Clone)]
+pub struct FreeSpaceMap {
+    /// Category and position within its bucket of each known page
+    pages: Vec<Option<(u8, usize)>>,
+    /// Pages in each category
+    buckets: Vec<Vec<PageId>>,
+}
+
+impl FreeSpaceMap {
+    /// Create an empty map
+    pub fn new() -> Self {
+        Self {
+            pages: Vec::new(),
+            buckets: vec![Vec::new(); CATEGORIES],
+        }
+    }
+
+    /// Record the free space of a page, adding the page if it is new
+    pub fn update(&mut self, page_id: PageId, free_bytes: usize) {
+        let category = (free_bytes / CATEGORY_BYTES).min(CATEGORIES - 1) as u8;
+        let index = page_id as usize;
+        if index >= self.pages.len() {
+            self.pages.resize(index + 1, None);
+        }
+        if let Some((old_category, _)) = self.pages[index] {
+            if old_category == category {
+                return;
+            }
+            self.remove(page_id);
+        }
+
+        let bucket = &mut self.buckets[category as usize];
+        self.pages[index] = Some((category, bucket.len()));
+        bucket.push(page_id);
+    }
+
+    /// Forget a page, e.g. one that is no longer part of the file
+    pub fn remove(&mut self, page_id: PageId) {
+        let Some((category, position)) = self.pages.get_mut(page_id as usize).and_then(Option::take) else {
+            return;
+        };
+        let bucket = &mut self.buckets[category as usize];
+        bucket.swap_remove(position);
+        if let Some(&moved) = bucket.get(position) {
+            self.pages[moved as usize] = Some((category, position));
+        }
+    }
+
+    /// A page known to have at least `needed` free bytes, if any
+    pub fn find(&self, needed: usize) -> Option<PageId> {
+        let first = needed.div_ceil(CATEGORY_BYTES);
+        self.buckets
+            .iter()
+            .skip(first)
+            .find_map(|bucket| bucket.last().copied())
+    }
+
+    /// Lower bound of a page's free space as recorded in the map
+    pub fn free_space(&self, page_id: PageId) -> Option<usize> {
+        self.pages
+            .get(page_id as usize)
+            .copied()
+            .flatten()
+            .map(|(category, _)| category as usize * CATEGORY_BYTES)
+    }
+
+    /// Number of pages tracked
+    pub fn page_count(&self) -> usize {
+        self.pages.iter().filter(|page| page.is_some()).count()
+    }
+}
+
+impl Default for FreeSpaceMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_page_with_room() {
+        let mut fsm = FreeSpaceMap::new();
+        fsm.update(0, 100);
+        fsm.update(1, 2000);
+        fsm.update(2, 10);
+
+        // The tightest fitting page is preferred
+        assert_eq!(fsm.find(50), Some(0));
+        assert_eq!(fsm.find(200), Some(1));
+        assert_eq!(fsm.find(2000), Some(1));
+        assert_eq!(fsm.find(3000), None);
+        // Categories round down, so a page is never reported with more room than it has
+        assert_eq!(fsm.free_space(0), Some(96));
+        assert_eq!(fsm.free_space(7), None);
+    }
+
+    #[test]
+    fn test_update_moves_page_between_buckets() {
+        let mut fsm = FreeSpaceMap::new();
+        for page_id in 0..4 {
+            fsm.update(page_id, 500);
+        }
+
+        // Filling a page takes it out of consideration; freeing space brings it back
+        fsm.update(3, 0);
+        fsm.update(1, 0);
+        assert_eq!(fsm.find(400), Some(2));
+        fsm.update(1, 3000);
+        assert_eq!(fsm.find(1000), Some(1));
+
+        fsm.remove(2);
+        fsm.remove(0);
+        assert_eq!(fsm.find(400), Some(1));
+        fsm.update(1, 0);
+        assert_eq!(fsm.find(400), None);
+        assert_eq!(fsm.page_count(), 2);
+    }
+}
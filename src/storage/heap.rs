@@ -0,0 +1,581 @@
+//! Heap files
+//!
+//! A heap file stores variable-length records in the slotted data pages of a
+//! [`DatabaseFile`]. Every page access goes through the [`BufferPool`], so a
+//! file may be much larger than the pool: pages are pinned only while a
+//! single operation runs and are written back when evicted or flushed.
+//! Records are addressed by a [`RecordId`], which stays valid until the record
+//! is deleted or an update moves it to another page. A [`FreeSpaceMap`] kept
+//! up to date by every modification lets inserts go straight to a page with
+//! room, including space freed by deletes anywhere in the file.
+//!
+//! Records longer than [`OVERFLOW_THRESHOLD`] are stored TOAST-style: the
+//! bytes go to a chain of overflow pages linked through their headers and
+//! the data page keeps only a short pointer, so long values never crowd out
+//! ordinary rows and may be larger than a page. Reads reassemble the record
+//! transparently. Every stored record starts with a kind byte telling the
+//! two apart. Overflow pages freed by deletes become empty data pages that
+//! later inserts and chains reuse.
+
+use crate::storage::buffer::{BufferPool, FrameId};
+use crate::storage::file::DatabaseFile;
+use crate::storage::fsm::FreeSpaceMap;
+use crate::storage::page::{Page, PageError, PageId, PageType, SlotId, MAX_PAGE_DATA_SIZE, MAX_RECORD_SIZE, SLOT_ENTRY_SIZE};
+use crate::storage::StorageError;
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// Records longer than this many bytes are moved to overflow pages, so a data
+/// page always has room for several rows
+pub const OVERFLOW_THRESHOLD: usize = MAX_RECORD_SIZE / 4;
+
+/// Kind byte of a record stored in the data page itself
+const INLINE_RECORD: u8 = 0;
+
+/// Kind byte of a pointer to an overflow chain: first page and total length
+const OVERFLOW_RECORD: u8 = 1;
+
+/// Length of an overflow pointer including its kind byte
+const OVERFLOW_POINTER_SIZE: usize = 9;
+
+/// Location of a record: the page holding it and its slot in that page
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RecordId {
+    pub page_id: PageId,
+    pub slot_id: SlotId,
+}
+
+impl RecordId {
+    pub fn new(page_id: PageId, slot_id: SlotId) -> Self {
+        Self { page_id, slot_id }
+    }
+}
+
+impl fmt::Display for RecordId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}, {})", self.page_id, self.slot_id)
+    }
+}
+
+/// Iterator over the records of a heap file, see [`HeapFile::records`]
+///
+/// Pages added after the iterator was created are not visited. After an
+/// error the iterator ends.
+pub struct HeapScan<'a> {
+    heap: &'a HeapFile,
+    pool: &'a BufferPool,
+    next_page: PageId,
+    page_count: u32,
+    /// Records of the current page not yet returned
+    page: VecDeque<(RecordId, Vec<u8>)>,
+}
+
+impl Iterator for HeapScan<'_> {
+    type Item = Result<(RecordId, Vec<u8>), StorageError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(record) = self.page.pop_front() {
+                return Some(Ok(record));
+            }
+            if self.next_page >= self.page_count {
+                return None;
+            }
+            let page_id = self.next_page;
+            self.next_page += 1;
+            match self.heap.page_records(self.pool, page_id) {
+                Ok(records) => self.page = records.into(),
+                Err(e) => {
+                    self.next_page = self.page_count;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+/// Unordered collection of records stored in a database file
+#[derive(Debug, Clone)]
+pub struct HeapFile {
+    file: Arc<Mutex<DatabaseFile>>,
+    /// Free space of every page, shared by clones of this heap file
+    fsm: Arc<Mutex<FreeSpaceMap>>,
+}
+
+impl HeapFile {
+    /// Wrap a newly created, empty database file
+    pub fn new(file: Arc<Mutex<DatabaseFile>>) -> Self {
+        Self {
+            file,
+            fsm: Arc::new(Mutex::new(FreeSpaceMap::new())),
+        }
+    }
+
+    /// Wrap an existing database file whose pages are all data pages,
+    /// reading each page once to build the free-space map
+    pub fn open(file: Arc<Mutex<DatabaseFile>>, pool: &BufferPool) -> Result<Self, StorageError> {
+        let heap = Self::new(file);
+        for page_id in 0..heap.page_count()? {
+            heap.with_page(pool, page_id, false, |_| Ok(()))?;
+        }
+        Ok(heap)
+    }
+
+    /// The underlying database file
+    pub fn file(&self) -> &Arc<Mutex<DatabaseFile>> {
+        &self.file
+    }
+
+    /// Number of pages in the file
+    pub fn page_count(&self) -> Result<u32, StorageError> {
+        Ok(self.lock_file()?.page_count())
+    }
+
+    /// Store a record and return its ID
+    ///
+    /// The free-space map picks a page with room; a new page is allocated
+    /// only when no page has enough. Long records go to overflow pages.
+    pub fn insert(&self, pool: &BufferPool, record: &[u8]) -> Result<RecordId, StorageError> {
+        let stored = self.store(pool, record)?;
+        self.insert_stored(pool, &stored)
+    }
+
+    /// Read a copy of a record
+    pub fn get(&self, pool: &BufferPool, rid: RecordId) -> Result<Vec<u8>, StorageError> {
+        let stored = self.with_page(pool, rid.page_id, false, |page| {
+            page.get_record(rid.slot_id).map(|record| record.to_vec())
+        })?;
+        self.load(pool, &stored)
+    }
+
+    /// Replace a record, returning its possibly new ID
+    ///
+    /// The record stays in its page when the page has room; otherwise it is
+    /// moved and the old slot is freed.
+    pub fn update(&self, pool: &BufferPool, rid: RecordId, record: &[u8]) -> Result<RecordId, StorageError> {
+        let old = self.with_page(pool, rid.page_id, false, |page| {
+            page.get_record(rid.slot_id).map(|record| record.to_vec())
+        })?;
+        let stored = self.store(pool, record)?;
+        let moved = match self.with_page(pool, rid.page_id, true, |page| page.update_record(rid.slot_id, &stored)) {
+            Ok(()) => rid,
+            Err(StorageError::Page(PageError::InsufficientSpace { .. })) => {
+                let moved = self.insert_stored(pool, &stored)?;
+                self.with_page(pool, rid.page_id, true, |page| page.delete_record(rid.slot_id))?;
+                moved
+            }
+            Err(e) => return Err(e),
+        };
+        self.release(pool, &old)?;
+        Ok(moved)
+    }
+
+    /// Remove a record, freeing its space and slot for later inserts
+    pub fn delete(&self, pool: &BufferPool, rid: RecordId) -> Result<(), StorageError> {
+        let stored = self.with_page(pool, rid.page_id, true, |page| {
+            let stored = page.get_record(rid.slot_id)?.to_vec();
+            page.delete_record(rid.slot_id)?;
+            Ok(stored)
+        })?;
+        self.release(pool, &stored)
+    }
+
+    /// All records in page and slot order
+    pub fn scan(&self, pool: &BufferPool) -> Result<Vec<(RecordId, Vec<u8>)>, StorageError> {
+        self.records(pool)?.collect()
+    }
+
+    /// Iterate over all records in page and slot order, reading one page at a
+    /// time: only the records of the current page are held in memory
+    pub fn records<'a>(&'a self, pool: &'a BufferPool) -> Result<HeapScan<'a>, StorageError> {
+        Ok(HeapScan {
+            heap: self,
+            pool,
+            next_page: 0,
+            page_count: self.page_count()?,
+            page: VecDeque::new(),
+        })
+    }
+
+    /// Records of one page in slot order; overflow chains are read after the
+    /// data page is unpinned
+    fn page_records(&self, pool: &BufferPool, page_id: PageId) -> Result<Vec<(RecordId, Vec<u8>)>, StorageError> {
+        let mut stored = Vec::new();
+        self.with_page(pool, page_id, false, |page| {
+            if page.page_type() != PageType::Data {
+                return Ok(());
+            }
+            let mut slot_ids = page.slot_ids();
+            slot_ids.sort_unstable();
+            for slot_id in slot_ids {
+                stored.push((RecordId::new(page_id, slot_id), page.get_record(slot_id)?.to_vec()));
+            }
+            Ok(())
+        })?;
+        stored
+            .into_iter()
+            .map(|(rid, stored)| Ok((rid, self.load(pool, &stored)?)))
+            .collect()
+    }
+
+    /// Free-space map of the file
+    pub fn free_space_map(&self) -> Result<FreeSpaceMap, StorageError> {
+        Ok(self.lock_fsm()?.clone())
+    }
+
+    /// Bytes to keep in the data page for a record: the record itself, or a
+    /// pointer to a newly written overflow chain holding it
+    fn store(&self, pool: &BufferPool, record: &[u8]) -> Result<Vec<u8>, StorageError> {
+        if record.len() <= OVERFLOW_THRESHOLD {
+            let mut stored = Vec::with_capacity(record.len() + 1);
+            stored.push(INLINE_RECORD);
+            stored.extend_from_slice(record);
+            return Ok(stored);
+        }
+
+        let len = u32::try_from(record.len()).map_err(|_| PageError::RecordTooLarge {
+            size: record.len(),
+            max: u32::MAX as usize,
+        })?;
+        // Written back to front so each page can link to the one after it
+        let mut next_page = None;
+        for chunk in record.chunks(MAX_RECORD_SIZE).rev() {
+            let (frame_id, page) = self.allocate_overflow_page(pool)?;
+            let result = page
+                .lock()
+                .map_err(|e| StorageError::Buffer(lock_error(e)))
+                .and_then(|mut page| {
+                    page.reset(PageType::Overflow);
+                    page.set_next_page(next_page);
+                    page.insert_record(chunk)?;
+                    self.lock_fsm()?.remove(page.page_id());
+                    Ok(page.page_id())
+                });
+            pool.unpin_page(frame_id, true)?;
+            next_page = Some(result?);
+        }
+
+        let mut stored = Vec::with_capacity(OVERFLOW_POINTER_SIZE);
+        stored.push(OVERFLOW_RECORD);
+        stored.extend_from_slice(&next_page.unwrap_or_default().to_le_bytes());
+        stored.extend_from_slice(&len.to_le_bytes());
+        Ok(stored)
+    }
+
+    /// The record that stored bytes read from a data page stand for
+    fn load(&self, pool: &BufferPool, stored: &[u8]) -> Result<Vec<u8>, StorageError> {
+        let Some((first_page, len)) = overflow_pointer(stored)? else {
+            return Ok(stored[1..].to_vec());
+        };
+        let mut record = Vec::with_capacity(len);
+        self.walk_chain(pool, first_page, false, |page| {
+            record.extend_from_slice(page.get_record(0)?);
+            Ok(())
+        })?;
+        if record.len() != len {
+            return Err(PageError::InvalidFormat(format!(
+                "Overflow chain at page {} holds {} bytes, expected {}",
+                first_page,
+                record.len(),
+                len
+            ))
+            .into());
+        }
+        Ok(record)
+    }
+
+    /// Free the overflow chain of a record that is being replaced or removed
+    fn release(&self, pool: &BufferPool, stored: &[u8]) -> Result<(), StorageError> {
+        let Some((first_page, _)) = overflow_pointer(stored)? else {
+            return Ok(());
+        };
+        self.walk_chain(pool, first_page, true, |page| {
+            page.reset(PageType::Data);
+            Ok(())
+        })
+    }
+
+    /// Call `f` on each page of the overflow chain starting at `first_page`
+    fn walk_chain(
+        &self,
+        pool: &BufferPool,
+        first_page: PageId,
+        modifies: bool,
+        mut f: impl FnMut(&mut Page) -> Result<(), PageError>,
+    ) -> Result<(), StorageError> {
+        let page_count = self.page_count()?;
+        let mut next_page = Some(first_page);
+        // A chain never visits a page twice, so more steps than pages means a cycle
+        for _ in 0..page_count {
+            let Some(page_id) = next_page else {
+                return Ok(());
+            };
+            next_page = self.with_page(pool, page_id, modifies, |page| {
+                if page.page_type() != PageType::Overflow {
+                    return Err(PageError::InvalidFormat(format!("Page {} is not an overflow page", page_id)));
+                }
+                let next_page = page.next_page();
+                f(page)?;
+                Ok(next_page)
+            })?;
+        }
+        match next_page {
+            None => Ok(()),
+            Some(_) => Err(PageError::InvalidFormat(format!("Overflow chain at page {} loops", first_page)).into()),
+        }
+    }
+
+    /// Pin a page for an overflow chunk: an empty data page if the map knows
+    /// one, otherwise a new page
+    fn allocate_overflow_page(&self, pool: &BufferPool) -> Result<(FrameId, Arc<Mutex<Page>>), StorageError> {
+        if let Some(page_id) = self.lock_fsm()?.find(MAX_PAGE_DATA_SIZE) {
+            let (frame_id, page) = pool.fetch_page(self.file.clone(), page_id)?;
+            let empty = page
+                .lock()
+                .map(|page| page.page_type() == PageType::Data && page.free_space() == MAX_PAGE_DATA_SIZE)
+                .unwrap_or(false);
+            if empty {
+                return Ok((frame_id, page));
+            }
+            pool.unpin_page(frame_id, false)?;
+        }
+        Ok(pool.new_page(self.file.clone(), PageType::Overflow)?)
+    }
+
+    /// Put stored bytes into a data page with room
+    fn insert_stored(&self, pool: &BufferPool, record: &[u8]) -> Result<RecordId, StorageError> {
+        let candidate = self.lock_fsm()?.find(record.len() + SLOT_ENTRY_SIZE);
+        if let Some(page_id) = candidate {
+            match self.with_page(pool, page_id, true, |page| page.insert_record(record)) {
+                Ok(slot_id) => return Ok(RecordId::new(page_id, slot_id)),
+                // The map has been corrected from the page; fall back to a new one
+                Err(StorageError::Page(PageError::InsufficientSpace { .. })) => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        let (frame_id, page) = pool.new_page(self.file.clone(), PageType::Data)?;
+        let result = page
+            .lock()
+            .map_err(|e| StorageError::Buffer(lock_error(e)))
+            .and_then(|mut page| {
+                let slot_id = page.insert_record(record);
+                self.lock_fsm()?.update(page.page_id(), page.free_space());
+                Ok(RecordId::new(page.page_id(), slot_id?))
+            });
+        pool.unpin_page(frame_id, true)?;
+        result
+    }
+
+    /// Pin a page for the duration of `f`, marking it dirty if `modifies` is
+    /// set, and record the page's free space afterwards
+    fn with_page<T>(
+        &self,
+        pool: &BufferPool,
+        page_id: PageId,
+        modifies: bool,
+        f: impl FnOnce(&mut Page) -> Result<T, PageError>,
+    ) -> Result<T, StorageError> {
+        let (frame_id, page) = pool.fetch_page(self.file.clone(), page_id)?;
+        let result = match page.lock() {
+            Ok(mut page) => {
+                let result = f(&mut page).map_err(StorageError::from);
+                match self.lock_fsm() {
+                    Ok(mut fsm) => {
+                        // Only data pages take new records
+                        match page.page_type() {
+                            PageType::Data => fsm.update(page_id, page.free_space()),
+                            _ => fsm.remove(page_id),
+                        }
+                        result
+                    }
+                    Err(e) => Err(e),
+                }
+            }
+            Err(e) => Err(StorageError::Buffer(lock_error(e))),
+        };
+        pool.unpin_page(frame_id, modifies && result.is_ok())?;
+        result
+    }
+
+    fn lock_fsm(&self) -> Result<std::sync::MutexGuard<'_, FreeSpaceMap>, StorageError> {
+        self.fsm.lock().map_err(|e| StorageError::Buffer(lock_error(e)))
+    }
+
+    fn lock_file(&self) -> Result<std::sync::MutexGuard<'_, DatabaseFile>, StorageError> {
+        self.file.lock().map_err(|e| StorageError::Buffer(lock_error(e)))
+    }
+}
+
+/// First page and length of the overflow chain stored bytes point to, or
+/// None for a record kept in the data page
+fn overflow_pointer(stored: &[u8]) -> Result<Option<(PageId, usize)>, PageError> {
+    match stored.first() {
+        Some(&INLINE_RECORD) => Ok(None),
+        Some(&OVERFLOW_RECORD) if stored.len() == OVERFLOW_POINTER_SIZE => {
+            let first_page = u32::from_le_bytes([stored[1], stored[2], stored[3], stored[4]]);
+            let len = u32::from_le_bytes([stored[5], stored[6], stored[7], stored[8]]);
+            Ok(Some((first_page, len as usize)))
+        }
+        _ => Err(PageError::InvalidFormat("Unknown heap record kind".to_string())),
+    }
+}
+
+fn lock_error<E: fmt::Display>(error: E) -> crate::storage::BufferError {
+    crate::storage::BufferError::LockError(error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::file::FileManager;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_insert_get_delete() {
+        let temp_dir = TempDir::new().unwrap();
+        let fm = FileManager::new(temp_dir.path()).unwrap();
+        let heap = HeapFile::new(fm.create_file("heap").unwrap());
+        let pool = BufferPool::new(4);
+
+        let first = heap.insert(&pool, b"alpha").unwrap();
+        let second = heap.insert(&pool, b"beta").unwrap();
+        assert_eq!(first, RecordId::new(0, 0));
+        assert_eq!(second, RecordId::new(0, 1));
+        assert_eq!(heap.get(&pool, second).unwrap(), b"beta");
+
+        heap.delete(&pool, first).unwrap();
+        assert!(heap.get(&pool, first).is_err());
+        assert_eq!(heap.scan(&pool).unwrap(), vec![(second, b"beta".to_vec())]);
+
+        // The freed slot is handed out again
+        assert_eq!(heap.insert(&pool, b"gamma").unwrap(), first);
+    }
+
+    #[test]
+    fn test_file_larger_than_pool() {
+        let temp_dir = TempDir::new().unwrap();
+        let fm = FileManager::new(temp_dir.path()).unwrap();
+        let heap = HeapFile::new(fm.create_file("heap").unwrap());
+        let pool = BufferPool::new(2);
+
+        let records: Vec<Vec<u8>> = (0..200).map(|i| format!("record {:04}", i).repeat(10).into_bytes()).collect();
+        let rids: Vec<RecordId> = records.iter().map(|record| heap.insert(&pool, record).unwrap()).collect();
+        assert!(heap.page_count().unwrap() > 2);
+
+        // Pages evicted from the two-frame pool are read back from disk
+        assert_eq!(heap.get(&pool, rids[0]).unwrap(), records[0]);
+        let scanned: Vec<Vec<u8>> = heap.scan(&pool).unwrap().into_iter().map(|(_, record)| record).collect();
+        assert_eq!(scanned, records);
+    }
+
+    #[test]
+    fn test_records_are_read_page_by_page() {
+        let temp_dir = TempDir::new().unwrap();
+        let fm = FileManager::new(temp_dir.path()).unwrap();
+        let heap = HeapFile::new(fm.create_file("heap").unwrap());
+        let pool = BufferPool::new(2);
+
+        let records: Vec<Vec<u8>> = (0..200).map(|i| format!("record {:04}", i).repeat(10).into_bytes()).collect();
+        for record in &records {
+            heap.insert(&pool, record).unwrap();
+        }
+        let pages_read = || {
+            let stats = pool.stats().unwrap();
+            stats.hits + stats.misses
+        };
+
+        // The first record costs one page read, not a pass over the file
+        let before = pages_read();
+        let mut scan = heap.records(&pool).unwrap();
+        assert_eq!(scan.next().unwrap().unwrap().1, records[0]);
+        assert_eq!(pages_read() - before, 1);
+        assert_eq!(pool.stats().unwrap().pinned_pages, 0);
+
+        let rest: Vec<Vec<u8>> = scan.map(|record| record.unwrap().1).collect();
+        assert_eq!(rest, records[1..]);
+        assert_eq!(pages_read() - before, u64::from(heap.page_count().unwrap()));
+    }
+
+    #[test]
+    fn test_update_moves_record_when_page_is_full() {
+        let temp_dir = TempDir::new().unwrap();
+        let fm = FileManager::new(temp_dir.path()).unwrap();
+        let heap = HeapFile::new(fm.create_file("heap").unwrap());
+        let pool = BufferPool::new(4);
+
+        let record = vec![1u8; 790];
+        let rids: Vec<RecordId> = (0..5).map(|_| heap.insert(&pool, &record).unwrap()).collect();
+        assert!(rids.iter().all(|rid| rid.page_id == 0));
+
+        // Growing past the page moves the record, shrinking stays in place
+        let grown = vec![2u8; OVERFLOW_THRESHOLD];
+        let moved = heap.update(&pool, rids[1], &grown).unwrap();
+        assert_eq!(moved.page_id, 1);
+        assert_eq!(heap.get(&pool, moved).unwrap(), grown);
+        assert!(heap.get(&pool, rids[1]).is_err());
+        assert_eq!(heap.update(&pool, rids[0], b"small").unwrap(), rids[0]);
+    }
+
+    #[test]
+    fn test_deleted_space_is_reused() {
+        let temp_dir = TempDir::new().unwrap();
+        let fm = FileManager::new(temp_dir.path()).unwrap();
+        let heap = HeapFile::new(fm.create_file("heap").unwrap());
+        let pool = BufferPool::new(2);
+
+        let record = vec![3u8; 500];
+        let rids: Vec<RecordId> = (0..40).map(|_| heap.insert(&pool, &record).unwrap()).collect();
+        let pages = heap.page_count().unwrap();
+
+        // Space freed on an early page is found without growing the file
+        heap.delete(&pool, rids[1]).unwrap();
+        heap.delete(&pool, rids[2]).unwrap();
+        let fsm = heap.free_space_map().unwrap();
+        assert!(fsm.free_space(0).unwrap() >= 1000);
+        assert_eq!(heap.insert(&pool, &[4u8; 900]).unwrap().page_id, 0);
+        assert_eq!(heap.page_count().unwrap(), pages);
+
+        // Reopening rebuilds the map from the pages
+        pool.flush_all().unwrap();
+        let reopened = HeapFile::open(heap.file().clone(), &pool).unwrap();
+        assert_eq!(reopened.free_space_map().unwrap().page_count(), pages as usize);
+        reopened.insert(&pool, &[5u8; 60]).unwrap();
+        assert_eq!(reopened.page_count().unwrap(), pages);
+    }
+
+    #[test]
+    fn test_long_records_use_overflow_chains() {
+        let temp_dir = TempDir::new().unwrap();
+        let fm = FileManager::new(temp_dir.path()).unwrap();
+        let heap = HeapFile::new(fm.create_file("heap").unwrap());
+        let pool = BufferPool::new(2);
+
+        // Longer than a page: split over a chain, only a pointer stays inline
+        let long: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+        let short = heap.insert(&pool, b"short").unwrap();
+        let rid = heap.insert(&pool, &long).unwrap();
+        assert_eq!(rid.page_id, short.page_id);
+        assert_eq!(heap.page_count().unwrap(), 4);
+        assert_eq!(heap.get(&pool, rid).unwrap(), long);
+        let scanned: Vec<Vec<u8>> = heap.scan(&pool).unwrap().into_iter().map(|(_, record)| record).collect();
+        assert_eq!(scanned, vec![b"short".to_vec(), long.clone()]);
+
+        // Chains survive eviction and reopening; overflow pages never take rows
+        pool.flush_all().unwrap();
+        let reopened = HeapFile::open(heap.file().clone(), &pool).unwrap();
+        assert_eq!(reopened.free_space_map().unwrap().page_count(), 1);
+        assert_eq!(reopened.get(&pool, rid).unwrap(), long);
+
+        // Shrinking the record frees its chain, and the freed pages hold the next one
+        assert_eq!(reopened.update(&pool, rid, b"now short").unwrap(), rid);
+        assert_eq!(reopened.get(&pool, rid).unwrap(), b"now short");
+        assert_eq!(reopened.free_space_map().unwrap().page_count(), 4);
+        let again = reopened.insert(&pool, &long[..5000]).unwrap();
+        assert_eq!(reopened.get(&pool, again).unwrap(), &long[..5000]);
+        reopened.delete(&pool, again).unwrap();
+        assert_eq!(reopened.page_count().unwrap(), 4);
+        assert_eq!(reopened.scan(&pool).unwrap().len(), 2);
+    }
+}
@@ -7,6 +7,7 @@ use crate::storage::page::PageId;
 use crate::types::{DataType, Value};
 use std::cmp::Ordering;
 use std::collections::BTreeMap;
+use std::ops::Bound;
 use thiserror::Error;
 
 /// Index key type that can hold various data types
@@ -229,6 +230,32 @@ impl BPlusTreeIndex {
 
         Ok(())
     }
+
+    /// Iterate entries between two bounds in key order
+    ///
+    /// Seeks to the first key at or after the lower bound and walks the leaf level
+    /// from there, so the cost is proportional to the number of keys in range
+    /// rather than the size of the index.
+    pub fn range(
+        &self,
+        lower: Bound<&IndexKey>,
+        upper: Bound<&IndexKey>,
+    ) -> Result<impl Iterator<Item = (&IndexKey, &RecordId)> + '_, IndexError> {
+        for key in [lower, upper] {
+            if let Bound::Included(key) | Bound::Excluded(key) = key {
+                self.validate_key(key)?;
+            }
+        }
+        // BTreeMap::range panics on inverted or doubly excluded equal bounds; those ranges are empty
+        let empty = match (lower, upper) {
+            (Bound::Included(start) | Bound::Excluded(start), Bound::Included(end) | Bound::Excluded(end)) => {
+                start > end || (start == end && !matches!((lower, upper), (Bound::Included(_), Bound::Included(_))))
+            }
+            _ => false,
+        };
+        let range = (!empty).then(|| self.tree.range::<IndexKey, _>((lower, upper)));
+        Ok(range.into_iter().flatten())
+    }
 }
 
 impl Index for BPlusTreeIndex {
@@ -260,28 +287,12 @@ impl Index for BPlusTreeIndex {
         start_key: Option<&IndexKey>,
         end_key: Option<&IndexKey>,
     ) -> Result<IndexIterator, IndexError> {
-        if let Some(key) = start_key {
-            self.validate_key(key)?;
-        }
-        if let Some(key) = end_key {
-            self.validate_key(key)?;
-        }
-
-        let mut entries = Vec::new();
-
-        // Collect entries in range
-        for (key, rid) in &self.tree {
-            let in_range = match (start_key, end_key) {
-                (Some(start), Some(end)) => key >= start && key <= end,
-                (Some(start), None) => key >= start,
-                (None, Some(end)) => key <= end,
-                (None, None) => true,
-            };
-
-            if in_range {
-                entries.push(IndexEntry::new(key.clone(), *rid));
-            }
-        }
+        let lower = start_key.map_or(Bound::Unbounded, Bound::Included);
+        let upper = end_key.map_or(Bound::Unbounded, Bound::Included);
+        let entries = self
+            .range(lower, upper)?
+            .map(|(key, rid)| IndexEntry::new(key.clone(), *rid))
+            .collect();
 
         Ok(IndexIterator::new(entries))
     }
@@ -453,6 +464,26 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_btree_ordered_range() {
+        let mut index = BPlusTreeIndex::new(vec![DataType::Integer]);
+        for i in (1..=10).rev() {
+            index.insert(IndexKey::single(Value::Integer(i)), RecordId::new(1, i as u16)).unwrap();
+        }
+        let key = |i: i32| IndexKey::single(Value::Integer(i));
+        let slots = |lower: Bound<&IndexKey>, upper: Bound<&IndexKey>| -> Vec<u16> {
+            index.range(lower, upper).unwrap().map(|(_, rid)| rid.slot_id).collect()
+        };
+
+        // Entries come back in key order regardless of insertion order
+        assert_eq!(slots(Bound::Excluded(&key(7)), Bound::Unbounded), vec![8, 9, 10]);
+        assert_eq!(slots(Bound::Included(&key(3)), Bound::Excluded(&key(5))), vec![3, 4]);
+        assert_eq!(slots(Bound::Unbounded, Bound::Included(&key(2))), vec![1, 2]);
+        assert!(slots(Bound::Excluded(&key(4)), Bound::Excluded(&key(4))).is_empty());
+        assert!(slots(Bound::Included(&key(6)), Bound::Included(&key(5))).is_empty());
+        assert!(index.range(Bound::Included(&IndexKey::single(Value::Varchar("x".to_string()))), Bound::Unbounded).is_err());
+    }
+
     #[test]
     fn test_hash_index_operations() {
         let mut index = HashIndex::new(vec![DataType::Varchar(50)]);
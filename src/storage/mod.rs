@@ -1,18 +1,38 @@
 //! Storage system module
 //!
 //! This module provides low-level storage functionality including
-//! page management, buffer pool, and file system operations.
+//! page management, buffer pool, write-ahead logging, and file system operations.
 
+#[cfg(feature = "async")]
+pub mod async_io;
+pub mod backend;
 pub mod buffer;
+pub mod compression;
+pub mod encryption;
 pub mod file;
+pub mod fsm;
+pub mod heap;
 pub mod index;
 pub mod page;
+pub mod temp;
+pub mod wal;
 
 // Re-export commonly used types
-pub use buffer::{BufferError, BufferPool, FrameId};
-pub use file::{DatabaseFile, FileError, FileManager};
+#[cfg(feature = "async")]
+pub use async_io::{AsyncBufferPool, AsyncFileManager};
+pub use backend::{DirectoryBackend, FileBackend, MemoryBackend};
+pub use buffer::{BackgroundWriter, BufferError, BufferPool, BufferStats, CachePolicyType, FrameId};
+pub use compression::{Compression, CompressionError};
+pub use encryption::{EncryptedBackend, EncryptionError, EncryptionKey};
+pub use file::{DatabaseFile, FileError, FileIo, FileManager};
+pub use fsm::FreeSpaceMap;
+pub use heap::{HeapFile, HeapScan, RecordId};
 pub use index::{BPlusTreeIndex, Index, IndexError};
 pub use page::{Page, PageError, PageId, PageType, SlotId};
+pub use temp::{StatementTempSpace, TempFile, TempFileError, TempFileManager};
+#[cfg(feature = "persistence")]
+pub use wal::{Wal, WalArchive, WalBatch};
+pub use wal::{Lsn, WalEntry, WalError, WalRecord};
 
 use thiserror::Error;
 
@@ -30,4 +50,10 @@ pub enum StorageError {
 
     #[error("Index error: {0}")]
     Index(#[from] IndexError),
+
+    #[error("Temp file error: {0}")]
+    Temp(#[from] TempFileError),
+
+    #[error("WAL error: {0}")]
+    Wal(#[from] WalError),
 }
@@ -13,8 +13,8 @@ pub type PageId = u32;
 /// 页面内的槽标识符
 pub type SlotId = u16;
 
-/// 页面大小（字节）(8KB)
-pub const PAGE_SIZE: usize = 8192;
+/// 页面大小（字节），与 [`crate::DEFAULT_PAGE_SIZE`] 一致
+pub const PAGE_SIZE: usize = crate::DEFAULT_PAGE_SIZE;
 
 /// 页头大小（字节）
 pub const PAGE_HEADER_SIZE: usize = 64;
@@ -22,6 +22,15 @@ pub const PAGE_HEADER_SIZE: usize = 64;
 /// 每页最大数据大小
 pub const MAX_PAGE_DATA_SIZE: usize = PAGE_SIZE - PAGE_HEADER_SIZE;
 
+/// 槽目录中每个槽占用的字节数
+pub const SLOT_ENTRY_SIZE: usize = mem::size_of::<SlotEntry>();
+
+/// 单条记录的最大长度（一页只放这一条记录和它的槽）
+pub const MAX_RECORD_SIZE: usize = MAX_PAGE_DATA_SIZE - SLOT_ENTRY_SIZE;
+
+/// 页头中表示“没有下一页”的 next_page 值
+const NO_PAGE: PageId = PageId::MAX;
+
 /// 页面类型
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum PageType {
@@ -31,6 +40,8 @@ pub enum PageType {
     Index = 2,
     /// 包含元数据的元页
     Meta = 3,
+    /// 溢出页：保存放不进数据页的长记录的一段，经 next_page 链接
+    Overflow = 4,
 }
 
 /// 包含元数据的页头
@@ -165,6 +176,25 @@ impl Page {
         self.header.page_type
     }
 
+    /// Next page of the chain this page belongs to (overflow pages)
+    pub fn next_page(&self) -> Option<PageId> {
+        self.header.next_page
+    }
+
+    /// Link the page to the next page of its chain
+    pub fn set_next_page(&mut self, next_page: Option<PageId>) {
+        self.header.next_page = next_page;
+        self.dirty = true;
+    }
+
+    /// Empty the page and give it a new type, keeping its ID
+    pub fn reset(&mut self, page_type: PageType) {
+        *self = Self {
+            dirty: true,
+            ..Self::new(self.page_id(), page_type)
+        };
+    }
+
     /// Check if page is dirty (modified)
     pub fn is_dirty(&self) -> bool {
         self.dirty
@@ -186,19 +216,28 @@ impl Page {
     }
 
     /// Insert a record into the page
+    ///
+    /// Records are packed from the end of the page towards the slot
+    /// directory; a slot freed by `delete_record` is reused before the
+    /// directory grows.
     pub fn insert_record(&mut self, data: &[u8]) -> Result<SlotId, PageError> {
         let record_size = data.len();
 
         // Check if record fits in page
-        if record_size > MAX_PAGE_DATA_SIZE {
+        if record_size > MAX_RECORD_SIZE {
             return Err(PageError::RecordTooLarge {
                 size: record_size,
-                max: MAX_PAGE_DATA_SIZE,
+                max: MAX_RECORD_SIZE,
             });
         }
 
+        // Reuse the first free slot, otherwise append one to the directory
+        let free_slot = (0..self.header.slot_count).find(|slot_id| !self.slots.contains_key(slot_id));
+        let slot_id = free_slot.unwrap_or(self.header.slot_count);
+        let directory_growth = if free_slot.is_some() { 0 } else { mem::size_of::<SlotEntry>() };
+
         // Check available space (record + slot entry)
-        let required_space = record_size + mem::size_of::<SlotEntry>();
+        let required_space = record_size + directory_growth;
         let available_space = self.header.free_space_size as usize;
         if available_space < required_space {
             return Err(PageError::InsufficientSpace {
@@ -207,28 +246,22 @@ impl Page {
             });
         }
 
-        // Find next slot ID
-        let slot_id = self.header.slot_count;
-
-        // Calculate record offset (grow from end of page backwards)
-        let record_offset = self.header.free_space_offset as usize
-            + self.header.free_space_size as usize
-            - record_size;
+        if free_slot.is_none() {
+            self.header.slot_count += 1;
+            self.header.free_space_offset += directory_growth as u16;
+        }
+        let record_offset = self.reserve(record_size);
 
         // Copy record data
         self.data[record_offset..record_offset + record_size].copy_from_slice(data);
 
-        // Create slot entry
-        let slot_entry = SlotEntry {
+        // Update slot directory
+        self.slots.insert(slot_id, SlotEntry {
             offset: record_offset as u16,
             length: record_size as u16,
-        };
-
-        // Update slot directory
-        self.slots.insert(slot_id, slot_entry);
+        });
 
         // Update header
-        self.header.slot_count += 1;
         self.header.free_space_size -= required_space as u16;
 
         // Mark as dirty
@@ -255,35 +288,51 @@ impl Page {
     }
 
     /// Update a record by slot ID
+    ///
+    /// A record that grows is moved elsewhere in the page; the slot ID stays
+    /// the same, so record IDs pointing at it remain valid.
     pub fn update_record(&mut self, slot_id: SlotId, new_data: &[u8]) -> Result<(), PageError> {
-        let slot_entry = self
+        let slot_entry = *self
             .slots
             .get(&slot_id)
-            .ok_or(PageError::SlotNotFound(slot_id))?
-            .clone();
-
-        // Check if new data fits in existing slot
-        if new_data.len() > slot_entry.length as usize {
-            return Err(PageError::InsufficientSpace {
-                required: new_data.len(),
-                available: slot_entry.length as usize,
+            .ok_or(PageError::SlotNotFound(slot_id))?;
+        let old_size = slot_entry.length as usize;
+        let new_size = new_data.len();
+
+        if new_size <= old_size {
+            // Shrink in place; the tail becomes free space reclaimed by compaction
+            let start = slot_entry.offset as usize;
+            self.data[start..start + new_size].copy_from_slice(new_data);
+            self.data[start + new_size..start + old_size].fill(0);
+            self.slots.insert(slot_id, SlotEntry {
+                offset: slot_entry.offset,
+                length: new_size as u16,
             });
-        }
-
-        let start = slot_entry.offset as usize;
-        let end = start + new_data.len();
-
-        // Update record data
-        self.data[start..end].copy_from_slice(new_data);
+            self.header.free_space_size += (old_size - new_size) as u16;
+        } else {
+            let available = self.header.free_space_size as usize + old_size;
+            if available < new_size {
+                return Err(PageError::InsufficientSpace {
+                    required: new_size,
+                    available,
+                });
+            }
 
-        // If new data is smaller, clear remaining space
-        if new_data.len() < slot_entry.length as usize {
-            let clear_start = start + new_data.len();
-            let clear_end = start + slot_entry.length as usize;
-            self.data[clear_start..clear_end].fill(0);
+            // Release the old copy first so compaction can reuse its space
+            self.slots.remove(&slot_id);
+            self.header.free_space_size += old_size as u16;
+            let record_offset = self.reserve(new_size);
+            self.data[record_offset..record_offset + new_size].copy_from_slice(new_data);
+            self.slots.insert(slot_id, SlotEntry {
+                offset: record_offset as u16,
+                length: new_size as u16,
+            });
+            self.header.free_space_size -= new_size as u16;
         }
 
         self.dirty = true;
+        self.serialize_header()?;
+        self.serialize_slots()?;
         Ok(())
     }
 
@@ -294,18 +343,64 @@ impl Page {
             .remove(&slot_id)
             .ok_or(PageError::SlotNotFound(slot_id))?;
 
-        // Clear record data
+        // Clear record data; the bytes are reclaimed by the next compaction
         let start = slot_entry.offset as usize;
         let end = start + slot_entry.length as usize;
         self.data[start..end].fill(0);
 
-        // Update free space (simplified - doesn't compact)
         self.header.free_space_size += slot_entry.length;
 
         self.dirty = true;
+        self.serialize_header()?;
+        self.serialize_slots()?;
         Ok(())
     }
 
+    /// Find room for a record of `size` bytes between the slot directory and
+    /// the lowest record, compacting the page if the gap is too small
+    ///
+    /// The caller has already checked that `free_space_size` covers `size`.
+    fn reserve(&mut self, size: usize) -> usize {
+        let records_start = self.records_start();
+        if records_start.saturating_sub(self.header.free_space_offset as usize) < size {
+            self.compact();
+            return self.records_start() - size;
+        }
+        records_start - size
+    }
+
+    /// Offset of the lowest record in the page
+    fn records_start(&self) -> usize {
+        self.slots
+            .values()
+            .map(|slot| slot.offset as usize)
+            .min()
+            .unwrap_or(PAGE_SIZE)
+    }
+
+    /// Move all records to the end of the page so that every free byte is
+    /// contiguous
+    fn compact(&mut self) {
+        let mut live: Vec<(SlotId, SlotEntry)> = self.slots.iter().map(|(&id, &slot)| (id, slot)).collect();
+        live.sort_by_key(|(_, slot)| std::cmp::Reverse(slot.offset));
+
+        let mut records = vec![0u8; PAGE_SIZE];
+        let mut offset = PAGE_SIZE;
+        for (slot_id, slot) in live {
+            let start = slot.offset as usize;
+            let length = slot.length as usize;
+            offset -= length;
+            records[offset..offset + length].copy_from_slice(&self.data[start..start + length]);
+            self.slots.insert(slot_id, SlotEntry {
+                offset: offset as u16,
+                length: slot.length,
+            });
+        }
+
+        let directory_end = self.header.free_space_offset as usize;
+        self.data[directory_end..].copy_from_slice(&records[directory_end..]);
+    }
+
     /// Get all slot IDs in the page
     pub fn slot_ids(&self) -> Vec<SlotId> {
         self.slots.keys().cloned().collect()
@@ -333,6 +428,7 @@ impl Page {
             1 => PageType::Data,
             2 => PageType::Index,
             3 => PageType::Meta,
+            4 => PageType::Overflow,
             _ => {
                 return Err(PageError::InvalidFormat(format!(
                     "Invalid page type: {}",
@@ -344,6 +440,10 @@ impl Page {
         let slot_count = u16::from_le_bytes([bytes[8], bytes[9]]);
         let free_space_offset = u16::from_le_bytes([bytes[10], bytes[11]]);
         let free_space_size = u16::from_le_bytes([bytes[12], bytes[13]]);
+        let next_page = match u32::from_le_bytes([bytes[14], bytes[15], bytes[16], bytes[17]]) {
+            NO_PAGE => None,
+            next_page => Some(next_page),
+        };
 
         Ok(PageHeader {
             page_id,
@@ -351,7 +451,7 @@ impl Page {
             slot_count,
             free_space_offset,
             free_space_size,
-            next_page: None, // Simplified
+            next_page,
             prev_page: None, // Simplified
             checksum: 0,     // Simplified
         })
@@ -395,6 +495,7 @@ impl Page {
         self.data[8..10].copy_from_slice(&self.header.slot_count.to_le_bytes());
         self.data[10..12].copy_from_slice(&self.header.free_space_offset.to_le_bytes());
         self.data[12..14].copy_from_slice(&self.header.free_space_size.to_le_bytes());
+        self.data[14..18].copy_from_slice(&self.header.next_page.unwrap_or(NO_PAGE).to_le_bytes());
 
         Ok(())
     }
@@ -535,4 +636,68 @@ mod tests {
         let record = loaded_page.get_record(slot_id).unwrap();
         assert_eq!(record, b"test data");
     }
+
+    #[test]
+    fn test_space_reuse_after_delete() {
+        let mut page = Page::new(1, PageType::Data);
+        let record = vec![7u8; 100];
+
+        // Fill the page, then free every other record
+        let mut slot_ids = Vec::new();
+        while let Ok(slot_id) = page.insert_record(&record) {
+            slot_ids.push(slot_id);
+        }
+        let capacity = slot_ids.len();
+        for slot_id in slot_ids.iter().step_by(2) {
+            page.delete_record(*slot_id).unwrap();
+        }
+
+        // Freed slots and bytes are reused; compaction keeps the survivors intact
+        let wide = vec![9u8; 150];
+        let slot_id = page.insert_record(&wide).unwrap();
+        assert_eq!(slot_id, 0);
+        assert_eq!(page.get_record(slot_id).unwrap(), &wide[..]);
+        for slot_id in slot_ids.iter().skip(1).step_by(2) {
+            assert_eq!(page.get_record(*slot_id).unwrap(), &record[..]);
+        }
+        assert_eq!(page.slot_count(), capacity);
+    }
+
+    #[test]
+    fn test_record_grows_and_moves() {
+        let mut page = Page::new(1, PageType::Data);
+        let first = page.insert_record(b"short").unwrap();
+        let second = page.insert_record(b"neighbour").unwrap();
+
+        let longer = vec![b'x'; 500];
+        page.update_record(first, &longer).unwrap();
+        assert_eq!(page.get_record(first).unwrap(), &longer[..]);
+        assert_eq!(page.get_record(second).unwrap(), b"neighbour");
+
+        // The relocated record survives a round trip through bytes
+        let bytes = page.to_bytes().unwrap().to_vec();
+        let loaded = Page::from_bytes(1, bytes).unwrap();
+        assert_eq!(loaded.get_record(first).unwrap(), &longer[..]);
+        assert_eq!(loaded.free_space(), page.free_space());
+    }
+
+    #[test]
+    fn test_overflow_chain_link() {
+        let mut page = Page::new(3, PageType::Overflow);
+        page.insert_record(&[1u8; 100]).unwrap();
+        page.set_next_page(Some(7));
+
+        let loaded = Page::from_bytes(3, page.to_bytes().unwrap().to_vec()).unwrap();
+        assert_eq!(loaded.page_type(), PageType::Overflow);
+        assert_eq!(loaded.next_page(), Some(7));
+
+        // Resetting turns it back into an empty, unlinked page
+        page.reset(PageType::Data);
+        assert!(page.is_dirty());
+        assert_eq!(page.next_page(), None);
+        assert_eq!(page.free_space(), MAX_PAGE_DATA_SIZE);
+        let loaded = Page::from_bytes(3, page.to_bytes().unwrap().to_vec()).unwrap();
+        assert_eq!(loaded.page_type(), PageType::Data);
+        assert_eq!(loaded.next_page(), None);
+    }
 }
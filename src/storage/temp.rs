@@ -0,0 +1,357 @@
+//! Temporary file management
+//!
+//! Spilling operators (external sort, hash aggregation) write their
+//! intermediate data through this module. Every statement gets its own
+//! namespace directory, all temp files count against a shared disk quota,
+//! and a namespace is removed as soon as the last handle to it is dropped,
+//! so a failed or cancelled query cannot leave temp data behind.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+
+/// Name of the temp directory inside the data directory
+pub const TEMP_DIR_NAME: &str = "tmp";
+
+/// Default temp disk quota (1 GiB)
+pub const DEFAULT_TEMP_QUOTA_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// Prefix of per-statement namespace directories
+const STATEMENT_DIR_PREFIX: &str = "stmt_";
+
+/// Temp file errors
+#[derive(Error, Debug)]
+pub enum TempFileError {
+    #[error("Temp disk quota exceeded: requested {requested} bytes, {used} of {quota} bytes in use")]
+    QuotaExceeded { requested: u64, used: u64, quota: u64 },
+
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// Disk usage accounting shared by all statements
+#[derive(Debug)]
+struct QuotaTracker {
+    used: AtomicU64,
+    quota: AtomicU64,
+}
+
+impl QuotaTracker {
+    /// Reserve space, failing if the quota would be exceeded
+    fn reserve(&self, bytes: u64) -> Result<(), TempFileError> {
+        let quota = self.quota.load(Ordering::SeqCst);
+        self.used
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |used| {
+                used.checked_add(bytes).filter(|&total| total <= quota)
+            })
+            .map(|_| ())
+            .map_err(|used| TempFileError::QuotaExceeded {
+                requested: bytes,
+                used,
+                quota,
+            })
+    }
+
+    /// Return previously reserved space
+    fn release(&self, bytes: u64) {
+        let _ = self
+            .used
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |used| {
+                Some(used.saturating_sub(bytes))
+            });
+    }
+}
+
+/// Manages the temp directory of a database
+#[derive(Debug)]
+pub struct TempFileManager {
+    /// Root temp directory
+    root: PathBuf,
+    /// Shared quota accounting
+    tracker: Arc<QuotaTracker>,
+    /// Next statement namespace ID
+    next_statement_id: AtomicU64,
+}
+
+/// State shared by a statement's temp space and its files
+#[derive(Debug)]
+struct SpaceInner {
+    dir: PathBuf,
+    tracker: Arc<QuotaTracker>,
+    /// Bytes reserved by this statement
+    reserved: AtomicU64,
+    /// Whether the namespace directory has been created
+    created: Mutex<bool>,
+    next_file_id: AtomicU64,
+}
+
+/// Temp namespace for one statement
+///
+/// The directory is created lazily on the first file and deleted, together
+/// with its reserved quota, once the space and all of its files are dropped.
+#[derive(Debug, Clone)]
+pub struct StatementTempSpace {
+    inner: Arc<SpaceInner>,
+}
+
+/// A temp file inside a statement namespace
+///
+/// Writes are charged against the temp quota before they reach the disk.
+#[derive(Debug)]
+pub struct TempFile {
+    path: PathBuf,
+    file: File,
+    /// Bytes charged for this file (its high-water size)
+    charged: u64,
+    space: Arc<SpaceInner>,
+}
+
+impl TempFileManager {
+    /// Create a temp file manager rooted at `root`
+    ///
+    /// Leftovers from a previous process (e.g. after a crash) are removed.
+    pub fn new<P: AsRef<Path>>(root: P, quota_bytes: u64) -> Result<Self, TempFileError> {
+        let root = root.as_ref().to_path_buf();
+        fs::create_dir_all(&root)?;
+
+        let manager = Self {
+            root,
+            tracker: Arc::new(QuotaTracker {
+                used: AtomicU64::new(0),
+                quota: AtomicU64::new(quota_bytes),
+            }),
+            next_statement_id: AtomicU64::new(1),
+        };
+        manager.purge()?;
+        Ok(manager)
+    }
+
+    /// Root temp directory
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Current quota in bytes
+    pub fn quota_bytes(&self) -> u64 {
+        self.tracker.quota.load(Ordering::SeqCst)
+    }
+
+    /// Change the quota; takes effect for subsequent writes
+    pub fn set_quota_bytes(&self, quota_bytes: u64) {
+        self.tracker.quota.store(quota_bytes, Ordering::SeqCst);
+    }
+
+    /// Bytes currently held by live temp files
+    pub fn used_bytes(&self) -> u64 {
+        self.tracker.used.load(Ordering::SeqCst)
+    }
+
+    /// Open a new namespace for a statement
+    pub fn begin_statement(&self) -> StatementTempSpace {
+        let id = self.next_statement_id.fetch_add(1, Ordering::SeqCst);
+        let dir = self
+            .root
+            .join(format!("{}{}_{}", STATEMENT_DIR_PREFIX, std::process::id(), id));
+
+        StatementTempSpace {
+            inner: Arc::new(SpaceInner {
+                dir,
+                tracker: Arc::clone(&self.tracker),
+                reserved: AtomicU64::new(0),
+                created: Mutex::new(false),
+                next_file_id: AtomicU64::new(1),
+            }),
+        }
+    }
+
+    /// Remove statement namespaces that are not owned by a live space
+    ///
+    /// Only called at startup, when no statement can be running yet.
+    /// Returns the number of removed namespaces.
+    pub fn purge(&self) -> Result<usize, TempFileError> {
+        let mut removed = 0;
+        for entry in fs::read_dir(&self.root)? {
+            let entry = entry?;
+            let is_namespace = entry
+                .file_name()
+                .to_str()
+                .is_some_and(|name| name.starts_with(STATEMENT_DIR_PREFIX));
+            if is_namespace && entry.file_type()?.is_dir() {
+                fs::remove_dir_all(entry.path())?;
+                removed += 1;
+            }
+        }
+
+        if removed > 0 {
+            log::info!("Removed {} stale temp namespace(s) from {:?}", removed, self.root);
+        }
+        Ok(removed)
+    }
+}
+
+impl StatementTempSpace {
+    /// Namespace directory of this statement
+    pub fn dir(&self) -> &Path {
+        &self.inner.dir
+    }
+
+    /// Bytes currently reserved by this statement
+    pub fn used_bytes(&self) -> u64 {
+        self.inner.reserved.load(Ordering::SeqCst)
+    }
+
+    /// Create a new, empty temp file; `label` only makes the name readable
+    pub fn create_file(&self, label: &str) -> Result<TempFile, TempFileError> {
+        {
+            let mut created = self.inner.created.lock().unwrap();
+            if !*created {
+                fs::create_dir_all(&self.inner.dir)?;
+                *created = true;
+            }
+        }
+
+        let id = self.inner.next_file_id.fetch_add(1, Ordering::SeqCst);
+        let path = self.inner.dir.join(format!("{}_{}.tmp", label, id));
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+
+        Ok(TempFile {
+            path,
+            file,
+            charged: 0,
+            space: Arc::clone(&self.inner),
+        })
+    }
+}
+
+impl SpaceInner {
+    fn reserve(&self, bytes: u64) -> Result<(), TempFileError> {
+        self.tracker.reserve(bytes)?;
+        self.reserved.fetch_add(bytes, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+impl Drop for SpaceInner {
+    fn drop(&mut self) {
+        self.tracker.release(*self.reserved.get_mut());
+
+        if *self.created.get_mut().unwrap_or_else(|e| e.into_inner()) {
+            if let Err(e) = fs::remove_dir_all(&self.dir) {
+                log::warn!("Failed to remove temp namespace {:?}: {}", self.dir, e);
+            }
+        }
+    }
+}
+
+impl TempFile {
+    /// Path of this temp file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Bytes charged against the quota for this file
+    pub fn size(&self) -> u64 {
+        self.charged
+    }
+
+    /// Rewind to the start, e.g. to read back spilled data
+    pub fn rewind(&mut self) -> io::Result<()> {
+        self.file.seek(SeekFrom::Start(0)).map(|_| ())
+    }
+}
+
+impl Write for TempFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let position = self.file.stream_position()?;
+        let end = position + buf.len() as u64;
+        if end > self.charged {
+            self.space
+                .reserve(end - self.charged)
+                .map_err(io::Error::other)?;
+            self.charged = end;
+        }
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Read for TempFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.file.read(buf)
+    }
+}
+
+impl Seek for TempFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.file.seek(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_namespace_removed_on_drop() {
+        let root = TempDir::new().unwrap();
+        let manager = TempFileManager::new(root.path(), 1024).unwrap();
+
+        let space = manager.begin_statement();
+        let mut file = space.create_file("sort_run").unwrap();
+        file.write_all(&[1, 2, 3, 4]).unwrap();
+        file.rewind().unwrap();
+        let mut data = Vec::new();
+        file.read_to_end(&mut data).unwrap();
+        assert_eq!(data, vec![1, 2, 3, 4]);
+
+        let dir = space.dir().to_path_buf();
+        assert!(dir.exists());
+        assert_eq!(manager.used_bytes(), 4);
+
+        // The namespace outlives the space while a file is still open
+        drop(space);
+        assert!(dir.exists());
+        drop(file);
+        assert!(!dir.exists());
+        assert_eq!(manager.used_bytes(), 0);
+    }
+
+    #[test]
+    fn test_quota_enforced() {
+        let root = TempDir::new().unwrap();
+        let manager = TempFileManager::new(root.path(), 8).unwrap();
+
+        let space = manager.begin_statement();
+        let mut file = space.create_file("spill").unwrap();
+        file.write_all(&[0; 6]).unwrap();
+        let err = file.write_all(&[0; 6]).unwrap_err();
+        assert!(err.to_string().contains("quota"));
+
+        // Rewriting already charged bytes does not count twice
+        file.rewind().unwrap();
+        file.write_all(&[1; 6]).unwrap();
+        assert_eq!(manager.used_bytes(), 6);
+    }
+
+    #[test]
+    fn test_stale_namespaces_purged() {
+        let root = TempDir::new().unwrap();
+        let stale = root.path().join("stmt_1_1");
+        fs::create_dir_all(&stale).unwrap();
+        fs::write(stale.join("run_1.tmp"), b"leftover").unwrap();
+
+        let _manager = TempFileManager::new(root.path(), 1024).unwrap();
+        assert!(!stale.exists());
+    }
+}
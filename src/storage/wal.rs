@@ -0,0 +1,499 @@
+//! Write-ahead log
+//!
+//! Row changes and table creation/removal are appended to the log as logical
+//! redo records before the engine acknowledges a statement, so durability no
+//! longer depends on rewriting whole table snapshots. Each record is framed as
+//!
+//! ```text
+//! [payload length: u32 LE][checksum: u32 LE][lsn: u64 LE][record: JSON]
+//! ```
+//!
+//! where the checksum covers the LSN and the record. Records are written in
+//! batches that end with a [`WalRecord::Commit`] marker and are made durable
+//! with a single fsync. When the log is opened, a torn or corrupt tail and any
+//! records after the last commit marker are discarded.
+//!
+//! A checkpoint replaces the whole log with a single [`WalRecord::Checkpoint`]
+//! batch once every table snapshot covers the records before it, so the log
+//! only grows between checkpoints while LSNs keep increasing across them.
+//!
+//! For point-in-time recovery the log can also copy every committed batch to
+//! a [`WalArchive`], which keeps them across checkpoints in segment files
+//! named after their first LSN. Commit markers carry the commit time, so a
+//! restored backup can be rolled forward to a given LSN or moment.
+//!
+//! The records themselves are always available; reading and writing the log
+//! requires the `persistence` feature.
+
+#[cfg(feature = "persistence")]
+use crate::storage::backend::FileBackend;
+use crate::types::Tuple;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "persistence")]
+use std::fmt;
+use std::io;
+#[cfg(feature = "persistence")]
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+/// Name of the log blob in the storage backend
+pub const WAL_FILE_NAME: &str = "wal.log";
+
+/// Log sequence number; 0 means "before the first record"
+pub type Lsn = u64;
+
+/// Size of the length and checksum fields preceding each payload
+#[cfg(feature = "persistence")]
+const FRAME_HEADER_SIZE: usize = 8;
+
+/// Size of the LSN at the start of each payload
+#[cfg(feature = "persistence")]
+const LSN_SIZE: usize = 8;
+
+/// Archived segments are named `wal_<first LSN>.log`, the LSN zero-padded so
+/// that names sort in LSN order
+#[cfg(feature = "persistence")]
+const ARCHIVE_SEGMENT_PREFIX: &str = "wal_";
+
+#[cfg(feature = "persistence")]
+const ARCHIVE_SEGMENT_SUFFIX: &str = ".log";
+
+/// WAL errors
+#[derive(Error, Debug)]
+pub enum WalError {
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("Serialization error: {0}")]
+    Serialization(String),
+
+    #[error("WAL archive error: {0}")]
+    Archive(String),
+}
+
+/// Logical redo record
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum WalRecord {
+    /// A row was appended to a table
+    Insert { table_id: u32, row_id: u64, row: Tuple },
+    /// A row was replaced
+    Update { table_id: u32, row_id: u64, row: Tuple },
+    /// A row was removed
+    Delete { table_id: u32, row_id: u64 },
+    /// A table was created; its initial snapshot is written before this record
+    CreateTable { table_id: u32, name: String },
+    /// A table was dropped
+    DropTable { table_id: u32, name: String },
+    /// Every snapshot contains the changes logged before this record
+    Checkpoint,
+    /// End of an atomic batch (written before commit times were recorded)
+    Commit,
+    /// End of an atomic batch committed at the given time (Unix milliseconds)
+    CommitAt { unix_millis: u64 },
+}
+
+impl WalRecord {
+    /// Table whose rows the record changes
+    pub fn table_id(&self) -> Option<u32> {
+        match self {
+            WalRecord::Insert { table_id, .. }
+            | WalRecord::Update { table_id, .. }
+            | WalRecord::Delete { table_id, .. } => Some(*table_id),
+            _ => None,
+        }
+    }
+
+    /// Whether the record ends a batch
+    pub fn is_commit(&self) -> bool {
+        matches!(self, WalRecord::Commit | WalRecord::CommitAt { .. })
+    }
+}
+
+/// A record read back from the log
+#[derive(Debug, Clone, PartialEq)]
+pub struct WalEntry {
+    pub lsn: Lsn,
+    pub record: WalRecord,
+}
+
+/// Appends records to the log and tracks the last assigned LSN
+#[cfg(feature = "persistence")]
+#[derive(Debug, Default)]
+pub struct Wal {
+    last_lsn: Lsn,
+    /// Bytes of committed records in the log
+    size: u64,
+    /// Archive that receives a copy of every committed batch
+    archive: Option<WalArchive>,
+}
+
+#[cfg(feature = "persistence")]
+impl Wal {
+    /// Read the committed records from the log
+    ///
+    /// Incomplete or corrupt data after the last commit marker is cut off so
+    /// that later appends follow valid records.
+    pub fn open(backend: &dyn FileBackend) -> Result<(Self, Vec<WalEntry>), WalError> {
+        let data = backend.read(WAL_FILE_NAME)?.unwrap_or_default();
+        let mut entries = Vec::new();
+        let mut batch = Vec::new();
+        let mut committed_len = 0;
+        let mut last_lsn = 0;
+        let mut offset = 0;
+
+        while let Some((entry, frame_len)) = decode_frame(&data[offset..]) {
+            offset += frame_len;
+            if entry.record.is_commit() {
+                entries.append(&mut batch);
+                committed_len = offset;
+                last_lsn = entry.lsn;
+            } else {
+                batch.push(entry);
+            }
+        }
+
+        if committed_len < data.len() {
+            log::warn!(
+                "Discarding {} byte(s) of incomplete write-ahead log",
+                data.len() - committed_len
+            );
+            backend.write(WAL_FILE_NAME, &data[..committed_len])?;
+        }
+        Ok((Self { last_lsn, size: committed_len as u64, archive: None }, entries))
+    }
+
+    /// Copy every batch committed from now on to an archive; the batches go
+    /// to a new segment starting at the next LSN
+    pub fn set_archive(&mut self, mut archive: WalArchive) {
+        archive.start_segment(self.last_lsn + 1);
+        self.archive = Some(archive);
+    }
+
+    /// LSN of the last record written
+    pub fn last_lsn(&self) -> Lsn {
+        self.last_lsn
+    }
+
+    /// Size of the log in bytes
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Append records followed by a commit marker and fsync them as one batch,
+    /// returning the LSN of the commit marker
+    pub fn append(&mut self, backend: &dyn FileBackend, records: &[WalRecord]) -> Result<Lsn, WalError> {
+        let mut buffer = Vec::new();
+        let mut lsn = self.last_lsn;
+        for record in records.iter().chain(std::iter::once(&commit_marker())) {
+            lsn += 1;
+            encode_frame(lsn, record, &mut buffer)?;
+        }
+        backend.append(WAL_FILE_NAME, &buffer)?;
+        self.last_lsn = lsn;
+        self.size += buffer.len() as u64;
+        if let Some(archive) = &mut self.archive {
+            archive.append(&buffer)?;
+        }
+        Ok(lsn)
+    }
+
+    /// Append a batch read from an archive, keeping its LSNs; it must follow
+    /// the last record of the log
+    pub fn append_batch(&mut self, backend: &dyn FileBackend, batch: &WalBatch) -> Result<(), WalError> {
+        if batch.first_lsn != self.last_lsn + 1 {
+            return Err(WalError::Archive(format!(
+                "batch starting at LSN {} does not follow LSN {}",
+                batch.first_lsn, self.last_lsn
+            )));
+        }
+        backend.append(WAL_FILE_NAME, &batch.bytes)?;
+        self.last_lsn = batch.commit_lsn;
+        self.size += batch.bytes.len() as u64;
+        Ok(())
+    }
+
+    /// Replace the log with a checkpoint marker, returning its LSN
+    ///
+    /// The caller must have made every table snapshot durable first; the
+    /// records before the marker are no longer replayed.
+    pub fn checkpoint(&mut self, backend: &dyn FileBackend) -> Result<Lsn, WalError> {
+        let mut buffer = Vec::new();
+        let checkpoint_lsn = self.last_lsn + 1;
+        encode_frame(checkpoint_lsn, &WalRecord::Checkpoint, &mut buffer)?;
+        encode_frame(checkpoint_lsn + 1, &commit_marker(), &mut buffer)?;
+        backend.write(WAL_FILE_NAME, &buffer)?;
+        self.last_lsn = checkpoint_lsn + 1;
+        self.size = buffer.len() as u64;
+        if let Some(archive) = &mut self.archive {
+            // The archive keeps the records the log no longer has
+            archive.append(&buffer)?;
+            archive.start_segment(self.last_lsn + 1);
+        }
+        Ok(checkpoint_lsn)
+    }
+}
+
+/// A committed batch read back from a [`WalArchive`]
+#[cfg(feature = "persistence")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct WalBatch {
+    /// Records of the batch, without the commit marker
+    pub entries: Vec<WalEntry>,
+    /// LSN of the first record (the commit marker of an empty batch)
+    pub first_lsn: Lsn,
+    /// LSN of the commit marker
+    pub commit_lsn: Lsn,
+    /// Commit time in Unix milliseconds; `None` for batches written before
+    /// commit times were recorded
+    pub committed_at: Option<u64>,
+    /// The framed records as stored in the log
+    bytes: Vec<u8>,
+}
+
+/// Keeps a copy of every committed batch, also after the log is checkpointed
+///
+/// Batches are appended to segment files in a separate backend, and a new
+/// segment starts at every checkpoint and whenever the database is opened.
+#[cfg(feature = "persistence")]
+pub struct WalArchive {
+    backend: Box<dyn FileBackend>,
+    /// Segment that receives the next batches
+    segment: String,
+}
+
+#[cfg(feature = "persistence")]
+impl WalArchive {
+    /// Archive batches to a backend, typically a directory outside the data
+    /// directory
+    pub fn new(backend: Box<dyn FileBackend>) -> Self {
+        Self {
+            backend,
+            segment: segment_name(1),
+        }
+    }
+
+    fn start_segment(&mut self, first_lsn: Lsn) {
+        self.segment = segment_name(first_lsn);
+    }
+
+    fn append(&mut self, buffer: &[u8]) -> Result<(), WalError> {
+        self.backend.append(&self.segment, buffer)?;
+        Ok(())
+    }
+
+    /// Every committed batch in the archive, ordered by segment
+    ///
+    /// Torn batches at the end of a segment are skipped. The batches are
+    /// returned as stored, so callers must check that each one follows the
+    /// previous: a failed archive write leaves a gap, and archiving the
+    /// database restored from an older point repeats LSNs.
+    pub fn batches(&self) -> Result<Vec<WalBatch>, WalError> {
+        let mut segments: Vec<(Lsn, String)> = self.backend.list()?
+            .into_iter()
+            .filter_map(|name| {
+                let lsn = name.strip_prefix(ARCHIVE_SEGMENT_PREFIX)?.strip_suffix(ARCHIVE_SEGMENT_SUFFIX)?.parse().ok()?;
+                Some((lsn, name))
+            })
+            .collect();
+        segments.sort();
+
+        let mut batches: Vec<WalBatch> = Vec::new();
+        for (_, name) in segments {
+            let data = self.backend.read(&name)?.unwrap_or_default();
+            let mut offset = 0;
+            let mut batch_start = 0;
+            let mut entries = Vec::new();
+            while let Some((entry, frame_len)) = decode_frame(&data[offset..]) {
+                offset += frame_len;
+                if !entry.record.is_commit() {
+                    entries.push(entry);
+                    continue;
+                }
+                let committed_at = match entry.record {
+                    WalRecord::CommitAt { unix_millis } => Some(unix_millis),
+                    _ => None,
+                };
+                let batch = WalBatch {
+                    first_lsn: entries.first().map_or(entry.lsn, |first: &WalEntry| first.lsn),
+                    entries: std::mem::take(&mut entries),
+                    commit_lsn: entry.lsn,
+                    committed_at,
+                    bytes: data[batch_start..offset].to_vec(),
+                };
+                batch_start = offset;
+                batches.push(batch);
+            }
+        }
+        Ok(batches)
+    }
+}
+
+#[cfg(feature = "persistence")]
+impl fmt::Debug for WalArchive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WalArchive").field("segment", &self.segment).finish()
+    }
+}
+
+#[cfg(feature = "persistence")]
+fn segment_name(first_lsn: Lsn) -> String {
+    format!("{}{:020}{}", ARCHIVE_SEGMENT_PREFIX, first_lsn, ARCHIVE_SEGMENT_SUFFIX)
+}
+
+/// Commit marker stamped with the current time
+#[cfg(feature = "persistence")]
+fn commit_marker() -> WalRecord {
+    let unix_millis = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_millis() as u64);
+    WalRecord::CommitAt { unix_millis }
+}
+
+/// Append one framed record to `buffer`
+#[cfg(feature = "persistence")]
+fn encode_frame(lsn: Lsn, record: &WalRecord, buffer: &mut Vec<u8>) -> Result<(), WalError> {
+    let mut payload = lsn.to_le_bytes().to_vec();
+    serde_json::to_writer(&mut payload, record).map_err(|e| WalError::Serialization(e.to_string()))?;
+    let length = u32::try_from(payload.len())
+        .map_err(|_| WalError::Serialization(format!("record of {} bytes is too large", payload.len())))?;
+
+    buffer.extend_from_slice(&length.to_le_bytes());
+    buffer.extend_from_slice(&crate::utils::checksum(&payload).to_le_bytes());
+    buffer.extend_from_slice(&payload);
+    Ok(())
+}
+
+/// Decode the record at the start of `data`, returning it with its frame
+/// length, or `None` if the data is truncated or fails its checksum
+#[cfg(feature = "persistence")]
+fn decode_frame(data: &[u8]) -> Option<(WalEntry, usize)> {
+    let header = data.get(..FRAME_HEADER_SIZE)?;
+    let length = u32::from_le_bytes(header[..4].try_into().ok()?) as usize;
+    let checksum = u32::from_le_bytes(header[4..].try_into().ok()?);
+    let payload = data.get(FRAME_HEADER_SIZE..FRAME_HEADER_SIZE.checked_add(length)?)?;
+    if payload.len() < LSN_SIZE || crate::utils::checksum(payload) != checksum {
+        return None;
+    }
+
+    let lsn = Lsn::from_le_bytes(payload[..LSN_SIZE].try_into().ok()?);
+    let record = serde_json::from_slice(&payload[LSN_SIZE..]).ok()?;
+    Some((WalEntry { lsn, record }, FRAME_HEADER_SIZE + length))
+}
+
+#[cfg(all(test, feature = "persistence"))]
+mod tests {
+    use super::*;
+    use crate::storage::backend::MemoryBackend;
+    use crate::types::Value;
+    use std::sync::Arc;
+
+    fn insert(row_id: u64) -> WalRecord {
+        WalRecord::Insert {
+            table_id: 1,
+            row_id,
+            row: Tuple::new(vec![Value::Integer(row_id as i32)]),
+        }
+    }
+
+    #[test]
+    fn test_append_and_reopen() {
+        let backend = MemoryBackend::new();
+        let (mut wal, entries) = Wal::open(&backend).unwrap();
+        assert!(entries.is_empty());
+
+        assert_eq!(wal.append(&backend, &[insert(1), insert(2)]).unwrap(), 3);
+        assert_eq!(wal.append(&backend, &[WalRecord::Delete { table_id: 1, row_id: 1 }]).unwrap(), 5);
+
+        let (wal, entries) = Wal::open(&backend).unwrap();
+        assert_eq!(wal.last_lsn(), 5);
+        let lsns: Vec<Lsn> = entries.iter().map(|entry| entry.lsn).collect();
+        assert_eq!(lsns, vec![1, 2, 4]);
+        assert_eq!(entries[1].record, insert(2));
+    }
+
+    #[test]
+    fn test_torn_tail_is_discarded() {
+        let backend = MemoryBackend::new();
+        let (mut wal, _) = Wal::open(&backend).unwrap();
+        wal.append(&backend, &[insert(1)]).unwrap();
+        let committed = backend.read(WAL_FILE_NAME).unwrap().unwrap();
+
+        // A batch cut off before its commit marker
+        let mut torn = Vec::new();
+        encode_frame(3, &insert(2), &mut torn).unwrap();
+        backend.append(WAL_FILE_NAME, &torn).unwrap();
+        backend.append(WAL_FILE_NAME, &[0xFF; 5]).unwrap();
+
+        let (mut wal, entries) = Wal::open(&backend).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(backend.read(WAL_FILE_NAME).unwrap().unwrap(), committed);
+
+        // New batches continue after the last committed record
+        assert_eq!(wal.append(&backend, &[insert(3)]).unwrap(), 4);
+        let (_, entries) = Wal::open(&backend).unwrap();
+        assert_eq!(entries.last().unwrap().record, insert(3));
+    }
+
+    #[test]
+    fn test_checkpoint_truncates_log() {
+        let backend = MemoryBackend::new();
+        let (mut wal, _) = Wal::open(&backend).unwrap();
+        wal.append(&backend, &[insert(1), insert(2)]).unwrap();
+        let before = wal.size();
+
+        assert_eq!(wal.checkpoint(&backend).unwrap(), 4);
+        assert!(wal.size() < before);
+        assert_eq!(wal.size(), backend.read(WAL_FILE_NAME).unwrap().unwrap().len() as u64);
+
+        // LSNs continue after the checkpoint, also when the log is reopened
+        let (mut wal, entries) = Wal::open(&backend).unwrap();
+        assert_eq!(entries, vec![WalEntry { lsn: 4, record: WalRecord::Checkpoint }]);
+        assert_eq!(wal.append(&backend, &[insert(3)]).unwrap(), 7);
+    }
+
+    #[test]
+    fn test_corrupt_record_stops_replay() {
+        let backend = MemoryBackend::new();
+        let (mut wal, _) = Wal::open(&backend).unwrap();
+        wal.append(&backend, &[insert(1)]).unwrap();
+        wal.append(&backend, &[insert(2)]).unwrap();
+
+        let mut data = backend.read(WAL_FILE_NAME).unwrap().unwrap();
+        let last = data.len() - 1;
+        data[last] ^= 0x01;
+        backend.write(WAL_FILE_NAME, &data).unwrap();
+
+        let (wal, entries) = Wal::open(&backend).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(wal.last_lsn(), 2);
+    }
+
+    #[test]
+    fn test_archive_keeps_checkpointed_batches() {
+        let backend = MemoryBackend::new();
+        let archive_backend = Arc::new(MemoryBackend::new());
+        let (mut wal, _) = Wal::open(&backend).unwrap();
+        wal.set_archive(WalArchive::new(Box::new(Arc::clone(&archive_backend))));
+
+        wal.append(&backend, &[insert(1), insert(2)]).unwrap();
+        wal.checkpoint(&backend).unwrap();
+        wal.append(&backend, &[WalRecord::Delete { table_id: 1, row_id: 1 }]).unwrap();
+        assert_eq!(archive_backend.list().unwrap().len(), 2);
+
+        let batches = WalArchive::new(Box::new(Arc::clone(&archive_backend))).batches().unwrap();
+        let ranges: Vec<(Lsn, Lsn)> = batches.iter().map(|batch| (batch.first_lsn, batch.commit_lsn)).collect();
+        assert_eq!(ranges, vec![(1, 3), (4, 5), (6, 7)]);
+        assert_eq!(batches[0].entries[1].record, insert(2));
+        assert!(batches.iter().all(|batch| batch.committed_at.is_some()));
+
+        // Replaying the archive rebuilds a log with the same records and LSNs
+        let restored = MemoryBackend::new();
+        let (mut replayed, _) = Wal::open(&restored).unwrap();
+        assert!(replayed.append_batch(&restored, &batches[1]).is_err());
+        for batch in &batches {
+            replayed.append_batch(&restored, batch).unwrap();
+        }
+        let (replayed, entries) = Wal::open(&restored).unwrap();
+        assert_eq!(replayed.last_lsn(), 7);
+        let lsns: Vec<Lsn> = entries.iter().map(|entry| entry.lsn).collect();
+        assert_eq!(lsns, vec![1, 2, 4, 6]);
+    }
+}
@@ -0,0 +1,364 @@
+//! 定点小数
+//!
+//! DECIMAL(p, s) 的值以 i128 整数（尾数）加小数位数表示：12.30 记为尾数 1230、小数位数 2。
+//! 加、减、乘在尾数上精确完成；只有除法和减少小数位数时需要舍入，一律四舍五入（远离零）。
+//! 尾数最多 38 位十进制数字，超出时运算返回 None，由调用方报告溢出。
+
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// DECIMAL 的最大精度（有效数字位数）
+pub const MAX_PRECISION: u8 = 38;
+
+/// 除法结果在两个操作数中较大的小数位数之外多保留的位数
+const DIVISION_EXTRA_SCALE: u8 = 4;
+
+/// 精确的定点小数
+///
+/// 相等、比较和哈希按数值进行：1.5 与 1.50 相等。
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Decimal {
+    mantissa: i128,
+    scale: u8,
+}
+
+/// 10 的 exponent 次方；超出 i128 时为 None
+fn pow10(exponent: u32) -> Option<i128> {
+    10i128.checked_pow(exponent)
+}
+
+/// 整数除法，商四舍五入（远离零）
+fn div_round(numerator: i128, divisor: i128) -> Option<i128> {
+    let quotient = numerator.checked_div(divisor)?;
+    let remainder = numerator % divisor;
+    // |余数| * 2 >= |除数| 时进位；用减法比较避免乘 2 溢出
+    if remainder.unsigned_abs() >= divisor.unsigned_abs() - remainder.unsigned_abs() {
+        let away = if (numerator < 0) == (divisor < 0) { 1 } else { -1 };
+        quotient.checked_add(away)
+    } else {
+        Some(quotient)
+    }
+}
+
+impl Decimal {
+    /// 由尾数和小数位数构造；小数位数超过 MAX_PRECISION 时为 None
+    pub fn new(mantissa: i128, scale: u8) -> Option<Self> {
+        (scale <= MAX_PRECISION).then_some(Self { mantissa, scale })
+    }
+
+    pub fn mantissa(&self) -> i128 {
+        self.mantissa
+    }
+
+    pub fn scale(&self) -> u8 {
+        self.scale
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.mantissa == 0
+    }
+
+    /// 尾数的十进制位数（0 的位数为 0）
+    pub fn digits(&self) -> u8 {
+        let mut remaining = self.mantissa.unsigned_abs();
+        let mut digits = 0;
+        while remaining > 0 {
+            remaining /= 10;
+            digits += 1;
+        }
+        digits
+    }
+
+    /// 改为指定的小数位数，减少位数时四舍五入；尾数溢出时为 None
+    pub fn rescale(&self, scale: u8) -> Option<Self> {
+        let mantissa = match scale.cmp(&self.scale) {
+            Ordering::Equal => self.mantissa,
+            Ordering::Greater => self.mantissa.checked_mul(pow10((scale - self.scale) as u32)?)?,
+            Ordering::Less => div_round(self.mantissa, pow10((self.scale - scale) as u32)?)?,
+        };
+        Self::new(mantissa, scale)
+    }
+
+    /// 转换为 DECIMAL(precision, scale)：按 scale 舍入后整数部分放不下时为 None
+    pub fn fit(&self, precision: u8, scale: u8) -> Option<Self> {
+        let fitted = self.rescale(scale)?;
+        (fitted.digits() <= precision).then_some(fitted)
+    }
+
+    /// 保留 digits 位小数四舍五入；digits 为负时舍入到十位、百位等，结果的小数位数不超过原值
+    pub fn round(&self, digits: i32) -> Option<Self> {
+        if digits >= self.scale as i32 {
+            return Some(*self);
+        }
+        if digits >= 0 {
+            return self.rescale(digits as u8);
+        }
+        let unit = pow10(digits.unsigned_abs())?;
+        let whole = self.rescale(0)?.mantissa;
+        Self::new(div_round(whole, unit)?.checked_mul(unit)?, 0)
+    }
+
+    /// 向下取整，结果的小数位数为 0
+    pub fn floor(&self) -> Option<Self> {
+        let unit = pow10(self.scale as u32)?;
+        Self::new(self.mantissa.div_euclid(unit), 0)
+    }
+
+    /// 向上取整，结果的小数位数为 0
+    pub fn ceil(&self) -> Option<Self> {
+        let unit = pow10(self.scale as u32)?;
+        let floor = self.mantissa.div_euclid(unit);
+        let ceil = if self.mantissa.rem_euclid(unit) == 0 { floor } else { floor + 1 };
+        Self::new(ceil, 0)
+    }
+
+    pub fn checked_neg(&self) -> Option<Self> {
+        Self::new(self.mantissa.checked_neg()?, self.scale)
+    }
+
+    pub fn checked_abs(&self) -> Option<Self> {
+        Self::new(self.mantissa.checked_abs()?, self.scale)
+    }
+
+    /// 和的小数位数为两者中较大者
+    pub fn checked_add(&self, other: &Self) -> Option<Self> {
+        let scale = self.scale.max(other.scale);
+        let (a, b) = (self.rescale(scale)?, other.rescale(scale)?);
+        Self::new(a.mantissa.checked_add(b.mantissa)?, scale)
+    }
+
+    pub fn checked_sub(&self, other: &Self) -> Option<Self> {
+        self.checked_add(&other.checked_neg()?)
+    }
+
+    /// 积的小数位数为两者之和（超过 MAX_PRECISION 时舍入）
+    pub fn checked_mul(&self, other: &Self) -> Option<Self> {
+        let mantissa = self.mantissa.checked_mul(other.mantissa)?;
+        let scale = self.scale + other.scale;
+        if scale <= MAX_PRECISION {
+            Self::new(mantissa, scale)
+        } else {
+            let mantissa = div_round(mantissa, pow10((scale - MAX_PRECISION) as u32)?)?;
+            Self::new(mantissa, MAX_PRECISION)
+        }
+    }
+
+    /// 商的小数位数见 [`Decimal::quotient_scale`]；除数为 0 或溢出时为 None
+    pub fn checked_div(&self, other: &Self) -> Option<Self> {
+        if other.is_zero() {
+            return None;
+        }
+        let scale = Self::quotient_scale(self.scale, other.scale);
+        // self / other = (m1 * 10^(scale - s1 + s2) / m2) * 10^-scale
+        let shift = scale as u32 + other.scale as u32 - self.scale as u32;
+        let numerator = self.mantissa.checked_mul(pow10(shift)?)?;
+        Self::new(div_round(numerator, other.mantissa)?, scale)
+    }
+
+    /// 商的小数位数：被除数和除数中较大的小数位数再加 4 位
+    pub fn quotient_scale(dividend_scale: u8, divisor_scale: u8) -> u8 {
+        (dividend_scale.max(divisor_scale) + DIVISION_EXTRA_SCALE).min(MAX_PRECISION)
+    }
+
+    /// 浮点数按最短的十进制表示转换后舍入到 scale 位小数；NaN 和无穷大为 None
+    ///
+    /// 最短表示能还原字面量的写法，因此 0.1 转换为恰好的 0.1 而不是它的二进制近似值。
+    pub fn from_f64(value: f64, scale: u8) -> Option<Self> {
+        if !value.is_finite() {
+            return None;
+        }
+        let text = value.to_string();
+        let parsed = match text.parse::<Decimal>() {
+            Ok(parsed) => parsed,
+            // 极大或极小的值小数位数太多，先按目标位数格式化
+            Err(_) => format!("{:.*}", scale as usize, value).parse().ok()?,
+        };
+        parsed.rescale(scale)
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.to_string().parse().unwrap_or(f64::NAN)
+    }
+
+    /// 去掉小数部分末尾的 0，用于按数值比较和哈希
+    fn normalized(&self) -> Self {
+        let mut normalized = *self;
+        while normalized.scale > 0 && normalized.mantissa % 10 == 0 {
+            normalized.mantissa /= 10;
+            normalized.scale -= 1;
+        }
+        normalized
+    }
+}
+
+impl From<i64> for Decimal {
+    fn from(value: i64) -> Self {
+        Self { mantissa: value as i128, scale: 0 }
+    }
+}
+
+impl From<i32> for Decimal {
+    fn from(value: i32) -> Self {
+        Self { mantissa: value as i128, scale: 0 }
+    }
+}
+
+/// 无法解析为 DECIMAL 的文本
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("无效的 DECIMAL 值")]
+pub struct ParseDecimalError;
+
+impl FromStr for Decimal {
+    type Err = ParseDecimalError;
+
+    /// 解析 `[+-]digits[.digits]`，整数部分和小数部分至少有一个非空；小数位数保持原样
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (negative, unsigned) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+        let (whole, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+        if whole.is_empty() && fraction.is_empty() {
+            return Err(ParseDecimalError);
+        }
+        let scale = u8::try_from(fraction.len()).map_err(|_| ParseDecimalError)?;
+        let mut mantissa: i128 = 0;
+        for ch in whole.chars().chain(fraction.chars()) {
+            let digit = ch.to_digit(10).ok_or(ParseDecimalError)?;
+            mantissa = mantissa
+                .checked_mul(10)
+                .and_then(|m| m.checked_add(digit as i128))
+                .ok_or(ParseDecimalError)?;
+        }
+        if negative {
+            mantissa = -mantissa;
+        }
+        Self::new(mantissa, scale).ok_or(ParseDecimalError)
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = self.mantissa.unsigned_abs().to_string();
+        let sign = if self.mantissa < 0 { "-" } else { "" };
+        let scale = self.scale as usize;
+        if scale == 0 {
+            return write!(f, "{}{}", sign, digits);
+        }
+        // 补足前导 0，使整数部分至少有一位
+        let padded = format!("{:0>width$}", digits, width = scale + 1);
+        let (whole, fraction) = padded.split_at(padded.len() - scale);
+        write!(f, "{}{}.{}", sign, whole, fraction)
+    }
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Decimal {}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let scale = self.scale.max(other.scale);
+        match (self.rescale(scale), other.rescale(scale)) {
+            (Some(a), Some(b)) => a.mantissa.cmp(&b.mantissa),
+            // 放大到较大的小数位数时溢出的一方绝对值更大
+            (None, _) => self.mantissa.signum().cmp(&0),
+            (_, None) => 0.cmp(&other.mantissa.signum()),
+        }
+    }
+}
+
+impl std::hash::Hash for Decimal {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        let normalized = self.normalized();
+        normalized.mantissa.hash(state);
+        normalized.scale.hash(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(s: &str) -> Decimal {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        assert_eq!(dec("12.30").to_string(), "12.30");
+        assert_eq!(dec("-0.05").to_string(), "-0.05");
+        assert_eq!(dec("+7").to_string(), "7");
+        assert_eq!(dec(".5").to_string(), "0.5");
+        assert_eq!(dec("12.30").scale(), 2);
+        for bad in ["", ".", "1.2.3", "abc", "1e5", "--1"] {
+            assert!(bad.parse::<Decimal>().is_err(), "{}", bad);
+        }
+        assert!("1".repeat(40).parse::<Decimal>().is_err());
+    }
+
+    #[test]
+    fn test_exact_arithmetic() {
+        assert_eq!(dec("0.1").checked_add(&dec("0.2")).unwrap().to_string(), "0.3");
+        assert_eq!(dec("10.00").checked_sub(&dec("0.01")).unwrap().to_string(), "9.99");
+        assert_eq!(dec("19.99").checked_mul(&dec("3")).unwrap().to_string(), "59.97");
+        assert_eq!(dec("1.5").checked_mul(&dec("-0.25")).unwrap().to_string(), "-0.375");
+        assert_eq!(dec("10.00").checked_div(&dec("3")).unwrap().to_string(), "3.333333");
+        assert_eq!(dec("-2").checked_div(&dec("3")).unwrap().to_string(), "-0.6667");
+        assert!(dec("1").checked_div(&dec("0.00")).is_none());
+
+        let max = Decimal::new(i128::MAX, 0).unwrap();
+        assert!(max.checked_add(&dec("1")).is_none());
+        assert!(max.checked_mul(&dec("2")).is_none());
+    }
+
+    #[test]
+    fn test_rounding_and_precision() {
+        assert_eq!(dec("2.345").rescale(2).unwrap().to_string(), "2.35");
+        assert_eq!(dec("-2.345").rescale(2).unwrap().to_string(), "-2.35");
+        assert_eq!(dec("2.344").rescale(2).unwrap().to_string(), "2.34");
+        assert_eq!(dec("1.5").rescale(3).unwrap().to_string(), "1.500");
+        assert_eq!(dec("1234.5").round(-2).unwrap().to_string(), "1200");
+        assert_eq!(dec("-1.5").floor().unwrap().to_string(), "-2");
+        assert_eq!(dec("-1.5").ceil().unwrap().to_string(), "-1");
+        assert_eq!(dec("2.00").ceil().unwrap().to_string(), "2");
+
+        assert_eq!(dec("999.994").fit(5, 2).unwrap().to_string(), "999.99");
+        assert!(dec("999.995").fit(5, 2).is_none());
+        assert_eq!(dec("0.001").fit(1, 0).unwrap().to_string(), "0");
+
+        assert_eq!(Decimal::from_f64(0.1, 2).unwrap().to_string(), "0.10");
+        assert_eq!(Decimal::from_f64(-19.99, 2).unwrap().to_string(), "-19.99");
+        assert_eq!(Decimal::from_f64(1e20, 0).unwrap().to_string(), "100000000000000000000");
+        assert!(Decimal::from_f64(f64::NAN, 2).is_none());
+    }
+
+    #[test]
+    fn test_numeric_equality() {
+        use std::collections::HashSet;
+
+        assert_eq!(dec("1.5"), dec("1.50"));
+        assert!(dec("-1") < dec("0.001"));
+        assert!(dec("2.1") > dec("2.09"));
+        let huge = Decimal::new(i128::MAX, 0).unwrap();
+        let tiny = Decimal::new(1, MAX_PRECISION).unwrap();
+        assert!(huge > tiny);
+        assert!(huge.checked_neg().unwrap() < tiny);
+
+        let set: HashSet<Decimal> = [dec("1.5"), dec("1.50"), dec("1.500")].into_iter().collect();
+        assert_eq!(set.len(), 1);
+    }
+}
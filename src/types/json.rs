@@ -0,0 +1,419 @@
+//! JSON 文档
+//!
+//! JSON 列保存经过校验的文档：写入时完整解析一遍，去掉多余的空白后以紧凑文本存储。
+//! 对象保留键的原始顺序和重复的键，按键取值时以最后一个为准；数字保留原来的写法。
+//! `->` / `->>` 和 JSON_EXTRACT 在取值时再解析存储的文本。
+
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// 数组和对象的最大嵌套层数，防止恶意输入耗尽栈空间
+pub const MAX_DEPTH: usize = 128;
+
+/// 解析后的 JSON 值
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    /// 数字保留原文，不经过浮点数转换
+    Number(String),
+    String(String),
+    Array(Vec<JsonValue>),
+    /// 键值对按出现顺序排列
+    Object(Vec<(String, JsonValue)>),
+}
+
+/// JSON 文本或路径错误
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum JsonError {
+    #[error("无效的 JSON：{message}，位置 {position}")]
+    Syntax { message: &'static str, position: usize },
+
+    #[error("无效的 JSON 路径 '{0}'")]
+    InvalidPath(String),
+}
+
+impl JsonValue {
+    /// 校验 JSON 文本并返回其紧凑形式
+    pub fn normalize(text: &str) -> Result<String, JsonError> {
+        Ok(text.parse::<JsonValue>()?.to_string())
+    }
+
+    /// 对象中键对应的值；键重复时取最后一个
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Object(members) => members.iter().rev().find(|(name, _)| name == key).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    /// 数组的第 index 个元素（从 0 开始）；负数从末尾倒数
+    pub fn index(&self, index: i64) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Array(items) => {
+                let position = if index < 0 { items.len().checked_sub(index.unsigned_abs() as usize)? } else { index as usize };
+                items.get(position)
+            }
+            _ => None,
+        }
+    }
+
+    /// `->>` 的结果：字符串取其内容，null 为 None，其余为 JSON 文本
+    pub fn to_text(&self) -> Option<String> {
+        match self {
+            JsonValue::Null => None,
+            JsonValue::String(s) => Some(s.clone()),
+            other => Some(other.to_string()),
+        }
+    }
+
+    /// 按 `$.key.other[0]` 形式的路径取值；路径不存在时为 None
+    ///
+    /// 键中含有 `.` 或 `[` 时用双引号括起：`$."a.b"`。
+    pub fn extract_path(&self, path: &str) -> Result<Option<&JsonValue>, JsonError> {
+        let invalid = || JsonError::InvalidPath(path.to_string());
+        let mut rest = path.trim().strip_prefix('$').ok_or_else(invalid)?;
+        let mut current = Some(self);
+        while let Some(step) = rest.chars().next() {
+            rest = &rest[1..];
+            match step {
+                '.' => {
+                    let key = if let Some(quoted) = rest.strip_prefix('"') {
+                        let end = quoted.find('"').ok_or_else(invalid)?;
+                        rest = &quoted[end + 1..];
+                        &quoted[..end]
+                    } else {
+                        let end = rest.find(['.', '[']).unwrap_or(rest.len());
+                        let key = &rest[..end];
+                        rest = &rest[end..];
+                        key
+                    };
+                    if key.is_empty() {
+                        return Err(invalid());
+                    }
+                    current = current.and_then(|value| value.get(key));
+                }
+                '[' => {
+                    let end = rest.find(']').ok_or_else(invalid)?;
+                    let index: usize = rest[..end].trim().parse().map_err(|_| invalid())?;
+                    rest = &rest[end + 1..];
+                    current = current.and_then(|value| value.index(index as i64));
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(current)
+    }
+}
+
+impl FromStr for JsonValue {
+    type Err = JsonError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser { bytes: s.as_bytes(), position: 0 };
+        let value = parser.value(0)?;
+        parser.skip_whitespace();
+        if parser.position < parser.bytes.len() {
+            return Err(parser.error("文档结束后还有多余的内容"));
+        }
+        Ok(value)
+    }
+}
+
+/// 递归下降解析器；只在 ASCII 字符处切分输入，切出的字符串仍是合法的 UTF-8
+struct Parser<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &'static str) -> JsonError {
+        JsonError::Syntax { message, position: self.position }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.position += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8, message: &'static str) -> Result<(), JsonError> {
+        self.skip_whitespace();
+        if self.peek() != Some(byte) {
+            return Err(self.error(message));
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    fn value(&mut self, depth: usize) -> Result<JsonValue, JsonError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'{') | Some(b'[') if depth >= MAX_DEPTH => Err(self.error("嵌套层数过多")),
+            Some(b'{') => self.object(depth),
+            Some(b'[') => self.array(depth),
+            Some(b'"') => self.string().map(JsonValue::String),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(b't') => self.keyword("true", JsonValue::Bool(true)),
+            Some(b'f') => self.keyword("false", JsonValue::Bool(false)),
+            Some(b'n') => self.keyword("null", JsonValue::Null),
+            Some(_) => Err(self.error("无法识别的值")),
+            None => Err(self.error("意外的结尾")),
+        }
+    }
+
+    fn keyword(&mut self, word: &str, value: JsonValue) -> Result<JsonValue, JsonError> {
+        if !self.bytes[self.position..].starts_with(word.as_bytes()) {
+            return Err(self.error("无法识别的值"));
+        }
+        self.position += word.len();
+        Ok(value)
+    }
+
+    fn object(&mut self, depth: usize) -> Result<JsonValue, JsonError> {
+        self.position += 1; // '{'
+        let mut members = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.position += 1;
+            return Ok(JsonValue::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some(b'"') {
+                return Err(self.error("对象的键必须是字符串"));
+            }
+            let key = self.string()?;
+            self.expect(b':', "键之后缺少 ':'")?;
+            members.push((key, self.value(depth + 1)?));
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.position += 1,
+                Some(b'}') => {
+                    self.position += 1;
+                    return Ok(JsonValue::Object(members));
+                }
+                _ => return Err(self.error("对象中缺少 ',' 或 '}'")),
+            }
+        }
+    }
+
+    fn array(&mut self, depth: usize) -> Result<JsonValue, JsonError> {
+        self.position += 1; // '['
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.position += 1;
+            return Ok(JsonValue::Array(items));
+        }
+        loop {
+            items.push(self.value(depth + 1)?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.position += 1,
+                Some(b']') => {
+                    self.position += 1;
+                    return Ok(JsonValue::Array(items));
+                }
+                _ => return Err(self.error("数组中缺少 ',' 或 ']'")),
+            }
+        }
+    }
+
+    fn digits(&mut self) -> usize {
+        let start = self.position;
+        while matches!(self.peek(), Some(b'0'..=b'9')) {
+            self.position += 1;
+        }
+        self.position - start
+    }
+
+    /// -?(0|[1-9][0-9]*)(\.[0-9]+)?([eE][+-]?[0-9]+)?
+    fn number(&mut self) -> Result<JsonValue, JsonError> {
+        let start = self.position;
+        if self.peek() == Some(b'-') {
+            self.position += 1;
+        }
+        match self.peek() {
+            Some(b'0') => self.position += 1,
+            Some(b'1'..=b'9') => {
+                self.digits();
+            }
+            _ => return Err(self.error("无效的数字")),
+        }
+        if self.peek() == Some(b'.') {
+            self.position += 1;
+            if self.digits() == 0 {
+                return Err(self.error("小数点后缺少数字"));
+            }
+        }
+        if matches!(self.peek(), Some(b'e' | b'E')) {
+            self.position += 1;
+            if matches!(self.peek(), Some(b'+' | b'-')) {
+                self.position += 1;
+            }
+            if self.digits() == 0 {
+                return Err(self.error("指数缺少数字"));
+            }
+        }
+        let text = std::str::from_utf8(&self.bytes[start..self.position]).expect("number is ASCII");
+        Ok(JsonValue::Number(text.to_string()))
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        self.position += 1; // '"'
+        let mut bytes = Vec::new();
+        loop {
+            let byte = self.peek().ok_or_else(|| self.error("字符串没有结束"))?;
+            self.position += 1;
+            match byte {
+                b'"' => break,
+                b'\\' => {
+                    let escape = self.peek().ok_or_else(|| self.error("字符串没有结束"))?;
+                    self.position += 1;
+                    let ch = match escape {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => self.unicode_escape()?,
+                        _ => return Err(self.error("无效的转义序列")),
+                    };
+                    bytes.extend_from_slice(ch.encode_utf8(&mut [0; 4]).as_bytes());
+                }
+                0x00..=0x1f => return Err(self.error("字符串中有未转义的控制字符")),
+                _ => bytes.push(byte),
+            }
+        }
+        Ok(String::from_utf8(bytes).expect("split at ASCII boundaries"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, JsonError> {
+        let digits = self.bytes.get(self.position..self.position + 4).ok_or_else(|| self.error("无效的 \\u 转义"))?;
+        let code = std::str::from_utf8(digits).ok()
+            .filter(|text| text.bytes().all(|b| b.is_ascii_hexdigit()))
+            .and_then(|text| u32::from_str_radix(text, 16).ok())
+            .ok_or_else(|| self.error("无效的 \\u 转义"))?;
+        self.position += 4;
+        Ok(code)
+    }
+
+    /// \uXXXX，UTF-16 代理对写作两个相连的转义
+    fn unicode_escape(&mut self) -> Result<char, JsonError> {
+        let high = self.hex4()?;
+        let code = if (0xD800..0xDC00).contains(&high) {
+            if !self.bytes[self.position..].starts_with(b"\\u") {
+                return Err(self.error("不成对的 UTF-16 代理"));
+            }
+            self.position += 2;
+            let low = self.hex4()?;
+            if !(0xDC00..0xE000).contains(&low) {
+                return Err(self.error("不成对的 UTF-16 代理"));
+            }
+            0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+        } else {
+            high
+        };
+        char::from_u32(code).ok_or_else(|| self.error("不成对的 UTF-16 代理"))
+    }
+}
+
+/// 紧凑形式：不含多余空白，字符串只转义必须转义的字符
+impl fmt::Display for JsonValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonValue::Null => write!(f, "null"),
+            JsonValue::Bool(b) => write!(f, "{}", b),
+            JsonValue::Number(n) => write!(f, "{}", n),
+            JsonValue::String(s) => write_string(f, s),
+            JsonValue::Array(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+            JsonValue::Object(members) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for ch in s.chars() {
+        match ch {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            ch if (ch as u32) < 0x20 => write!(f, "\\u{:04x}", ch as u32)?,
+            ch => write!(f, "{}", ch)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_normalize() {
+        let text = r#" { "name" : "Ada\u0020\"L\"", "tags": [ 1, -2.5e3, true, null ], "nested": {"k": []} } "#;
+        assert_eq!(
+            JsonValue::normalize(text).unwrap(),
+            r#"{"name":"Ada \"L\"","tags":[1,-2.5e3,true,null],"nested":{"k":[]}}"#
+        );
+        assert_eq!(JsonValue::normalize("\"\\ud83d\\ude00\"").unwrap(), "\"😀\"");
+        assert_eq!(JsonValue::normalize("\"é\\n\"").unwrap(), "\"é\\n\"");
+
+        for bad in ["", "{", "[1,]", "{'a': 1}", "01", "1.", "tru", "\"a", "[1] 2", "{\"a\" 1}", "\"\\ud800\""] {
+            assert!(bad.parse::<JsonValue>().is_err(), "{}", bad);
+        }
+        let deep = "[".repeat(MAX_DEPTH + 1) + &"]".repeat(MAX_DEPTH + 1);
+        assert!(deep.parse::<JsonValue>().is_err());
+    }
+
+    #[test]
+    fn test_access() {
+        let doc: JsonValue = r#"{"a": {"b": [10, 20, 30]}, "a.b": 1, "s": "x", "n": null, "a": {"b": [40]}}"#.parse().unwrap();
+        // 重复的键取最后一个
+        assert_eq!(doc.get("a").unwrap().to_string(), r#"{"b":[40]}"#);
+        let array: JsonValue = "[10, 20, 30]".parse().unwrap();
+        assert_eq!(array.index(-1).unwrap().to_string(), "30");
+        assert_eq!(array.index(3), None);
+        assert_eq!(doc.get("s").and_then(JsonValue::to_text), Some("x".to_string()));
+        assert_eq!(doc.get("n").and_then(JsonValue::to_text), None);
+
+        assert_eq!(doc.extract_path("$.a.b[0]").unwrap().map(ToString::to_string), Some("40".to_string()));
+        assert_eq!(doc.extract_path("$.\"a.b\"").unwrap().map(ToString::to_string), Some("1".to_string()));
+        assert_eq!(doc.extract_path("$").unwrap(), Some(&doc));
+        assert_eq!(doc.extract_path("$.missing.x").unwrap(), None);
+        for bad in ["a", "$.", "$[x]", "$.a[0", "$a"] {
+            assert!(doc.extract_path(bad).is_err(), "{}", bad);
+        }
+    }
+}
@@ -3,7 +3,16 @@
 //! 此模块定义了整个 MiniDB 中使用的类型系统，
 //! 包括数据类型、值和模式定义。
 
+pub mod decimal;
+pub mod json;
+pub mod uuid;
+
+pub use decimal::Decimal;
+pub use json::JsonValue;
+pub use uuid::Uuid;
+#[cfg(feature = "chrono")]
 use chrono::{NaiveDate, NaiveDateTime};
+use crate::utils::encoding;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 use thiserror::Error;
@@ -19,8 +28,20 @@ pub enum DataType {
     Float,
     /// 64位浮点数
     Double,
+    /// 定点小数：精度（有效数字位数，最大 38）和小数位数
+    Decimal(u8, u8),
     /// 可变长度字符串，带最大长度限制
     Varchar(usize),
+    /// 定长字符串，不足长度的部分用空格补齐，比较时忽略尾部空格；值以 `Value::Varchar` 表示
+    Char(usize),
+    /// 不限长度的字符串，值与 VARCHAR 相同，以 `Value::Varchar` 表示
+    Text,
+    /// 不限长度的二进制数据
+    Blob,
+    /// 128 位的通用唯一标识符
+    Uuid,
+    /// 经过校验的 JSON 文档
+    Json,
     /// 布尔值 true/false
     Boolean,
     /// 日期（不含时间）
@@ -42,13 +63,23 @@ pub enum Value {
     Float(f32),
     /// 双精度浮点数值
     Double(f64),
+    /// 定点小数值
+    Decimal(Decimal),
     /// 字符串值
     Varchar(String),
+    /// 二进制值
+    Blob(Vec<u8>),
+    /// UUID 值
+    Uuid(Uuid),
+    /// JSON 文档的紧凑文本
+    Json(String),
     /// 布尔值
     Boolean(bool),
     /// 日期值
+    #[cfg(feature = "chrono")]
     Date(NaiveDate),
     /// 时间戳值
+    #[cfg(feature = "chrono")]
     Timestamp(NaiveDateTime),
 }
 
@@ -64,9 +95,15 @@ impl std::hash::Hash for Value {
             Value::BigInt(i) => i.hash(state),
             Value::Float(f) => f.to_bits().hash(state),
             Value::Double(f) => f.to_bits().hash(state),
+            Value::Decimal(d) => d.hash(state),
             Value::Varchar(s) => s.hash(state),
+            Value::Blob(b) => b.hash(state),
+            Value::Uuid(u) => u.hash(state),
+            Value::Json(s) => s.hash(state),
             Value::Boolean(b) => b.hash(state),
+            #[cfg(feature = "chrono")]
             Value::Date(d) => d.hash(state),
+            #[cfg(feature = "chrono")]
             Value::Timestamp(t) => t.hash(state),
         }
     }
@@ -84,9 +121,15 @@ impl PartialOrd for Value {
             (Value::BigInt(a), Value::BigInt(b)) => a.partial_cmp(b),
             (Value::Float(a), Value::Float(b)) => a.partial_cmp(b),
             (Value::Double(a), Value::Double(b)) => a.partial_cmp(b),
+            (Value::Decimal(a), Value::Decimal(b)) => a.partial_cmp(b),
             (Value::Varchar(a), Value::Varchar(b)) => a.partial_cmp(b),
+            (Value::Blob(a), Value::Blob(b)) => a.partial_cmp(b),
+            (Value::Uuid(a), Value::Uuid(b)) => a.partial_cmp(b),
+            (Value::Json(a), Value::Json(b)) => a.partial_cmp(b),
             (Value::Boolean(a), Value::Boolean(b)) => a.partial_cmp(b),
+            #[cfg(feature = "chrono")]
             (Value::Date(a), Value::Date(b)) => a.partial_cmp(b),
+            #[cfg(feature = "chrono")]
             (Value::Timestamp(a), Value::Timestamp(b)) => a.partial_cmp(b),
             
             // 数值类型的类型提升
@@ -98,6 +141,14 @@ impl PartialOrd for Value {
             (Value::Double(a), Value::Integer(b)) => a.partial_cmp(&(*b as f64)),
             (Value::Float(a), Value::Double(b)) => (*a as f64).partial_cmp(b),
             (Value::Double(a), Value::Float(b)) => a.partial_cmp(&(*b as f64)),
+            (Value::Decimal(a), Value::Integer(b)) => a.partial_cmp(&Decimal::from(*b)),
+            (Value::Integer(a), Value::Decimal(b)) => Decimal::from(*a).partial_cmp(b),
+            (Value::Decimal(a), Value::BigInt(b)) => a.partial_cmp(&Decimal::from(*b)),
+            (Value::BigInt(a), Value::Decimal(b)) => Decimal::from(*a).partial_cmp(b),
+            (Value::Decimal(a), Value::Float(b)) => a.to_f64().partial_cmp(&(*b as f64)),
+            (Value::Float(a), Value::Decimal(b)) => (*a as f64).partial_cmp(&b.to_f64()),
+            (Value::Decimal(a), Value::Double(b)) => a.to_f64().partial_cmp(b),
+            (Value::Double(a), Value::Decimal(b)) => a.partial_cmp(&b.to_f64()),
             
             // 不同类型不可比较
             _ => None,
@@ -118,6 +169,10 @@ pub struct ColumnDefinition {
     pub data_type: DataType,
     pub nullable: bool,
     pub default: Option<Value>,
+    /// 每写入一行调用一次的无参易变函数（如 GEN_UUID、NOW），用于 `DEFAULT GEN_UUID()`；
+    /// 设置时代替 default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_function: Option<String>,
 }
 
 /// 包含列定义的表模式
@@ -141,6 +196,9 @@ pub enum TypeError {
 
     #[error("字符串过长：最大长度 {max}，实际长度 {actual}")]
     StringTooLong { max: usize, actual: usize },
+
+    #[error("数值超出 DECIMAL({precision}, {scale}) 的范围")]
+    NumericOverflow { precision: u8, scale: u8 },
 }
 
 impl DataType {
@@ -151,10 +209,12 @@ impl DataType {
             DataType::BigInt => Some(8),
             DataType::Float => Some(4),
             DataType::Double => Some(8),
+            DataType::Decimal(..) => Some(17), // 尾数加小数位数
+            DataType::Uuid => Some(16),
             DataType::Boolean => Some(1),
             DataType::Date => Some(4),      // 自纪元以来的天数
             DataType::Timestamp => Some(8), // 自纪元以来的微秒数
-            DataType::Varchar(_) => None,   // 可变大小
+            DataType::Varchar(_) | DataType::Char(_) | DataType::Text | DataType::Blob | DataType::Json => None, // 可变大小
         }
     }
 
@@ -168,11 +228,174 @@ impl DataType {
             (DataType::Double, DataType::Float) => true,
             (DataType::Integer, DataType::Float) => true,
             (DataType::Integer, DataType::Double) => true,
+            // 数值写入 DECIMAL 列时按列的精度和小数位数舍入；DECIMAL 可与浮点数比较和运算
+            (DataType::Decimal(..), DataType::Decimal(..)) => true,
+            (DataType::Integer | DataType::BigInt | DataType::Float | DataType::Double, DataType::Decimal(..)) => true,
+            (DataType::Decimal(..), DataType::Float | DataType::Double) => true,
             // Varchar 兼容性：较小的字符串可以适配较大的 varchar 列
             (DataType::Varchar(len1), DataType::Varchar(len2)) => len1 <= len2,
+            (DataType::Varchar(_), DataType::Text) => true,
+            // 写入 CHAR 列时补齐空格，超出长度在写入时检查
+            (DataType::Varchar(_), DataType::Char(_)) => true,
+            (DataType::Char(len1), DataType::Char(len2) | DataType::Varchar(len2)) => len1 <= len2,
+            (DataType::Char(_), DataType::Text) => true,
+            // UUID 常量写作字符串
+            (DataType::Varchar(_), DataType::Uuid) => true,
+            // JSON 文档写作字符串
+            (DataType::Varchar(_), DataType::Json) => true,
             _ => false,
         }
     }
+
+    /// 两个类型的公共类型，用于 COALESCE 等需要统一多个参数的场合
+    ///
+    /// 数值类型按 Double > Float > Decimal > BigInt > Integer 取较宽者，VARCHAR 取较大长度，VARCHAR 与 TEXT 为 TEXT，
+    /// CHAR 与 VARCHAR 为 VARCHAR，
+    /// 两个 DECIMAL 取能容纳双方的精度和小数位数，DATE 与 TIMESTAMP 统一为 TIMESTAMP；
+    /// 其余不同类型之间没有公共类型。
+    pub fn common_type(&self, other: &DataType) -> Option<DataType> {
+        use DataType::*;
+        let numeric_rank = |t: &DataType| match t {
+            Integer => Some(0),
+            BigInt => Some(1),
+            Decimal(..) => Some(2),
+            Float => Some(3),
+            Double => Some(4),
+            _ => None,
+        };
+        match (self, other) {
+            (a, b) if a == b => Some(a.clone()),
+            (Char(a), Char(b)) => Some(Char(*a.max(b))),
+            (Varchar(a) | Char(a), Varchar(b) | Char(b)) => Some(Varchar(*a.max(b))),
+            (Varchar(_) | Char(_) | Text, Varchar(_) | Char(_) | Text) => Some(Text),
+            (Decimal(p1, s1), Decimal(p2, s2)) => {
+                let scale = *s1.max(s2);
+                let whole = p1.saturating_sub(*s1).max(p2.saturating_sub(*s2));
+                Some(Decimal((whole + scale).min(decimal::MAX_PRECISION), scale))
+            }
+            (Date | Timestamp, Date | Timestamp) => Some(Timestamp),
+            (a, b) => match (numeric_rank(a), numeric_rank(b)) {
+                (Some(x), Some(y)) => Some(if x >= y { a.clone() } else { b.clone() }),
+                _ => None,
+            },
+        }
+    }
+
+    /// 不带长度参数的标准 SQL 类型名
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            DataType::Integer => "INTEGER",
+            DataType::BigInt => "BIGINT",
+            DataType::Float => "REAL",
+            DataType::Double => "DOUBLE",
+            DataType::Decimal(..) => "DECIMAL",
+            DataType::Varchar(_) => "VARCHAR",
+            DataType::Char(_) => "CHAR",
+            DataType::Text => "TEXT",
+            DataType::Blob => "BLOB",
+            DataType::Uuid => "UUID",
+            DataType::Json => "JSON",
+            DataType::Boolean => "BOOLEAN",
+            DataType::Date => "DATE",
+            DataType::Timestamp => "TIMESTAMP",
+        }
+    }
+
+    /// java.sql.Types 中的类型编号
+    ///
+    /// 驱动程序依赖这些取值，已发布的映射不应再修改。
+    pub fn jdbc_type_code(&self) -> i32 {
+        match self {
+            DataType::Integer => 4,     // INTEGER
+            DataType::BigInt => -5,     // BIGINT
+            DataType::Float => 7,       // REAL
+            DataType::Double => 8,      // DOUBLE
+            DataType::Decimal(..) => 3, // DECIMAL
+            DataType::Varchar(_) => 12, // VARCHAR
+            DataType::Char(_) => 1,     // CHAR
+            DataType::Text => -16,      // LONGNVARCHAR
+            DataType::Blob => 2004,     // BLOB
+            DataType::Uuid => 1111,     // OTHER，与 PostgreSQL 驱动相同
+            DataType::Json => 1111,     // OTHER
+            DataType::Boolean => 16,    // BOOLEAN
+            DataType::Date => 91,       // DATE
+            DataType::Timestamp => 93,  // TIMESTAMP
+        }
+    }
+
+    /// ODBC 3.x（SQL/CLI）中的类型编号
+    ///
+    /// ODBC 没有布尔类型，按惯例映射为 SQL_BIT；其余与 JDBC 编号相同。
+    pub fn odbc_type_code(&self) -> i16 {
+        match self {
+            DataType::Boolean => -7, // SQL_BIT
+            DataType::Text => -10,   // SQL_WLONGVARCHAR
+            DataType::Blob => -4,    // SQL_LONGVARBINARY
+            DataType::Uuid => -11,   // SQL_GUID
+            DataType::Json => -10,   // SQL_WLONGVARCHAR
+            other => other.jdbc_type_code() as i16,
+        }
+    }
+
+    /// 精度：数值类型为有效数字位数，字符类型为最大长度，日期时间类型为文本形式的长度
+    pub fn precision(&self) -> u32 {
+        match self {
+            DataType::Integer => 10,
+            DataType::BigInt => 19,
+            DataType::Float => 7,
+            DataType::Double => 15,
+            DataType::Decimal(precision, _) => *precision as u32,
+            DataType::Varchar(len) | DataType::Char(len) => u32::try_from(*len).unwrap_or(u32::MAX),
+            DataType::Text | DataType::Blob | DataType::Json => i32::MAX as u32, // 不限长度，按驱动惯例报告 2^31 - 1
+            DataType::Uuid => 36, // 带连字符的文本形式
+            DataType::Boolean => 1,
+            DataType::Date => 10,      // yyyy-mm-dd
+            DataType::Timestamp => 29, // yyyy-mm-dd hh:mm:ss.fffffffff
+        }
+    }
+
+    /// 小数位数：DECIMAL 为声明的小数位数，TIMESTAMP 为秒的小数位数，其余为 0
+    pub fn scale(&self) -> u32 {
+        match self {
+            DataType::Decimal(_, scale) => *scale as u32,
+            DataType::Timestamp => 9,
+            _ => 0,
+        }
+    }
+
+    /// 以文本显示一个值所需的最大字符数（含符号、小数点和指数）
+    pub fn display_size(&self) -> u32 {
+        match self {
+            DataType::Integer => 11,
+            DataType::BigInt => 20,
+            DataType::Float => 14,
+            DataType::Double => 24,
+            // 符号、小数点以及小数位数等于精度时的前导 0
+            DataType::Decimal(precision, scale) => *precision as u32 + 2 + (precision == scale) as u32,
+            other => other.precision(),
+        }
+    }
+}
+
+/// 结果列的驱动元数据，对应 JDBC 的 ResultSetMetaData 和 ODBC 的 SQLDescribeCol
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnMetadata {
+    /// 列名
+    pub name: String,
+    /// 标准 SQL 类型名
+    pub type_name: &'static str,
+    /// java.sql.Types 类型编号
+    pub jdbc_type: i32,
+    /// ODBC 类型编号
+    pub odbc_type: i16,
+    /// 精度
+    pub precision: u32,
+    /// 小数位数
+    pub scale: u32,
+    /// 显示宽度
+    pub display_size: u32,
+    /// 是否可为 NULL
+    pub nullable: bool,
 }
 
 impl Value {
@@ -184,13 +407,24 @@ impl Value {
             Value::BigInt(_) => DataType::BigInt,
             Value::Float(_) => DataType::Float,
             Value::Double(_) => DataType::Double,
+            Value::Decimal(d) => DataType::Decimal(decimal::MAX_PRECISION, d.scale()),
             Value::Varchar(s) => DataType::Varchar(s.len()),
+            Value::Blob(_) => DataType::Blob,
+            Value::Uuid(_) => DataType::Uuid,
+            Value::Json(_) => DataType::Json,
             Value::Boolean(_) => DataType::Boolean,
+            #[cfg(feature = "chrono")]
             Value::Date(_) => DataType::Date,
+            #[cfg(feature = "chrono")]
             Value::Timestamp(_) => DataType::Timestamp,
         }
     }
 
+    /// 是否为 NULL
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
     /// 检查此值是否与数据类型兼容
     pub fn is_compatible_with(&self, data_type: &DataType) -> bool {
         match self {
@@ -200,31 +434,144 @@ impl Value {
     }
 
     /// 尝试将此值转换为另一种类型
+    ///
+    /// 浮点数转整数时四舍五入（远离零），超出目标范围则报错；
+    /// 布尔值与整数互转时 true 对应 1，非零整数视为 true；
+    /// 转换为 DECIMAL(p, s) 时舍入到 s 位小数，整数部分超出 p - s 位则报错；
+    /// 字符串按 `YYYY-MM-DD` / `YYYY-MM-DD HH:MM:SS[.f]` 解析为日期和时间戳；
+    /// 转换为 VARCHAR(n) 时超出长度的部分被截断，转换为 CHAR(n) 时截断或在末尾补空格到 n 个字符，转换为 TEXT 时不截断；
+    /// BLOB 转换为字符串时写成 `\x` 加十六进制数字，字符串转换为 BLOB 时 `\x` 开头的按十六进制解码，
+    /// 其余取其 UTF-8 字节；UUID 与字符串按文本形式互转，与 BLOB 按 16 个字节互转；
+    /// 字符串转换为 JSON 时校验并去掉多余空白，JSON 转换为字符串得到其紧凑文本。
     pub fn cast_to(&self, target_type: &DataType) -> Result<Value, TypeError> {
+        let invalid = || TypeError::InvalidCast {
+            from: self.data_type(),
+            to: target_type.clone(),
+        };
+
         match (self, target_type) {
             (Value::Null, _) => Ok(Value::Null),
+
+            // 任意值都可以转换为字符串
+            (value, DataType::Varchar(_) | DataType::Char(_) | DataType::Text) => {
+                let text = match value {
+                    Value::Varchar(s) => s.clone(),
+                    Value::Blob(b) => format!("\\x{}", encoding::encode_hex(b)),
+                    other => other.to_string(),
+                };
+                match target_type {
+                    DataType::Varchar(max) => Ok(Value::Varchar(text.chars().take(*max).collect())),
+                    DataType::Char(len) => Ok(Value::Varchar(format!("{:<width$}", text.chars().take(*len).collect::<String>(), width = *len))),
+                    _ => Ok(Value::Varchar(text)),
+                }
+            }
+            (Value::Varchar(s), DataType::Blob) => match s.strip_prefix("\\x") {
+                Some(hex) => encoding::decode_hex(hex).map(Value::Blob).ok_or_else(invalid),
+                None => Ok(Value::Blob(s.clone().into_bytes())),
+            },
+            (Value::Uuid(u), DataType::Blob) => Ok(Value::Blob(u.as_bytes().to_vec())),
+            (Value::Blob(b), DataType::Uuid) => {
+                <[u8; 16]>::try_from(b.as_slice()).map(|bytes| Value::Uuid(Uuid::from_bytes(bytes))).map_err(|_| invalid())
+            }
             (value, target) if value.data_type() == *target => Ok(value.clone()),
 
             // 整数转换
             (Value::Integer(i), DataType::BigInt) => Ok(Value::BigInt(*i as i64)),
             (Value::Integer(i), DataType::Float) => Ok(Value::Float(*i as f32)),
             (Value::Integer(i), DataType::Double) => Ok(Value::Double(*i as f64)),
-            (Value::Integer(i), DataType::Varchar(_)) => Ok(Value::Varchar(i.to_string())),
+            (Value::Integer(i), DataType::Boolean) => Ok(Value::Boolean(*i != 0)),
+            (Value::BigInt(i), DataType::Integer) => {
+                i32::try_from(*i).map(Value::Integer).map_err(|_| invalid())
+            }
+            (Value::BigInt(i), DataType::Float) => Ok(Value::Float(*i as f32)),
+            (Value::BigInt(i), DataType::Double) => Ok(Value::Double(*i as f64)),
+            (Value::BigInt(i), DataType::Boolean) => Ok(Value::Boolean(*i != 0)),
+
+            // 浮点数转换
+            (Value::Float(f), DataType::Double) => Ok(Value::Double(*f as f64)),
+            (Value::Double(d), DataType::Float) => Ok(Value::Float(*d as f32)),
+            (Value::Float(f), DataType::Integer | DataType::BigInt) => {
+                round_to_integer(*f as f64, target_type).ok_or_else(invalid)
+            }
+            (Value::Double(d), DataType::Integer | DataType::BigInt) => {
+                round_to_integer(*d, target_type).ok_or_else(invalid)
+            }
+
+            // 布尔转换
+            (Value::Boolean(b), DataType::Integer) => Ok(Value::Integer(*b as i32)),
+            (Value::Boolean(b), DataType::BigInt) => Ok(Value::BigInt(*b as i64)),
+
+            // 日期与时间戳
+            #[cfg(feature = "chrono")]
+            (Value::Date(d), DataType::Timestamp) => Ok(Value::Timestamp(d.and_time(chrono::NaiveTime::MIN))),
+            #[cfg(feature = "chrono")]
+            (Value::Timestamp(ts), DataType::Date) => Ok(Value::Date(ts.date())),
 
             // 字符串转换
-            (Value::Varchar(s), DataType::Integer) => {
-                s.parse::<i32>()
-                    .map(Value::Integer)
-                    .map_err(|_| TypeError::InvalidCast {
-                        from: DataType::Varchar(s.len()),
-                        to: target_type.clone(),
-                    })
+            // 定点小数
+            (value, DataType::Decimal(precision, scale)) => {
+                let decimal = match value {
+                    Value::Integer(i) => Decimal::from(*i),
+                    Value::BigInt(i) => Decimal::from(*i),
+                    Value::Decimal(d) => *d,
+                    Value::Float(f) => Decimal::from_f64(*f as f64, *scale).ok_or_else(invalid)?,
+                    Value::Double(d) => Decimal::from_f64(*d, *scale).ok_or_else(invalid)?,
+                    Value::Varchar(s) => s.trim().parse::<Decimal>().map_err(|_| invalid())?,
+                    _ => return Err(invalid()),
+                };
+                decimal
+                    .fit(*precision, *scale)
+                    .map(Value::Decimal)
+                    .ok_or(TypeError::NumericOverflow { precision: *precision, scale: *scale })
+            }
+            (Value::Decimal(d), DataType::Integer | DataType::BigInt) => {
+                let whole = d.rescale(0).map(|whole| whole.mantissa());
+                match target_type {
+                    DataType::Integer => whole.and_then(|w| i32::try_from(w).ok()).map(Value::Integer),
+                    _ => whole.and_then(|w| i64::try_from(w).ok()).map(Value::BigInt),
+                }
+                .ok_or_else(invalid)
+            }
+            (Value::Decimal(d), DataType::Float) => Ok(Value::Float(d.to_f64() as f32)),
+            (Value::Decimal(d), DataType::Double) => Ok(Value::Double(d.to_f64())),
+
+            (Value::Varchar(s), target) => {
+                let s = s.trim();
+                let parsed = match target {
+                    DataType::Integer => s.parse().ok().map(Value::Integer),
+                    DataType::BigInt => s.parse().ok().map(Value::BigInt),
+                    DataType::Float => s.parse().ok().map(Value::Float),
+                    DataType::Double => s.parse().ok().map(Value::Double),
+                    DataType::Boolean => match s.to_lowercase().as_str() {
+                        "true" | "t" | "yes" | "y" | "on" | "1" => Some(Value::Boolean(true)),
+                        "false" | "f" | "no" | "n" | "off" | "0" => Some(Value::Boolean(false)),
+                        _ => None,
+                    },
+                    #[cfg(feature = "chrono")]
+                    DataType::Date => NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                        .ok()
+                        .or_else(|| parse_timestamp(s).map(|ts| ts.date()))
+                        .map(Value::Date),
+                    #[cfg(feature = "chrono")]
+                    DataType::Timestamp => parse_timestamp(s)
+                        .or_else(|| {
+                            NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                                .ok()
+                                .map(|d| d.and_time(chrono::NaiveTime::MIN))
+                        })
+                        .map(Value::Timestamp),
+                    #[cfg(not(feature = "chrono"))]
+                    DataType::Date | DataType::Timestamp => None,
+                    DataType::Uuid => s.parse().ok().map(Value::Uuid),
+                    DataType::Json => JsonValue::normalize(s).ok().map(Value::Json),
+                    DataType::Varchar(_) | DataType::Char(_) | DataType::Text | DataType::Blob | DataType::Decimal(..) => {
+                        unreachable!("handled above")
+                    }
+                };
+                parsed.ok_or_else(invalid)
             }
 
-            _ => Err(TypeError::InvalidCast {
-                from: self.data_type(),
-                to: target_type.clone(),
-            }),
+            _ => Err(invalid()),
         }
     }
 
@@ -236,14 +583,43 @@ impl Value {
             Value::BigInt(_) => 8,
             Value::Float(_) => 4,
             Value::Double(_) => 8,
+            Value::Decimal(_) => 17, // 16 字节尾数 + 小数位数
             Value::Varchar(s) => 4 + s.len(), // 长度前缀 + 字符串数据
+            Value::Blob(b) => 4 + b.len(),
+            Value::Uuid(_) => 16,
+            Value::Json(s) => 4 + s.len(),
             Value::Boolean(_) => 1,
+            #[cfg(feature = "chrono")]
             Value::Date(_) => 4,
+            #[cfg(feature = "chrono")]
             Value::Timestamp(_) => 8,
         }
     }
 }
 
+/// 浮点数四舍五入为整数类型的值；NaN、无穷大或超出范围时返回 None
+fn round_to_integer(x: f64, target: &DataType) -> Option<Value> {
+    let rounded = x.round();
+    match target {
+        DataType::Integer if rounded >= i32::MIN as f64 && rounded <= i32::MAX as f64 => {
+            Some(Value::Integer(rounded as i32))
+        }
+        // i64::MAX as f64 向上舍入为 2^63，因此上界用严格小于
+        DataType::BigInt if rounded >= i64::MIN as f64 && rounded < i64::MAX as f64 => {
+            Some(Value::BigInt(rounded as i64))
+        }
+        _ => None,
+    }
+}
+
+/// 解析 `YYYY-MM-DD HH:MM:SS[.f]`（日期与时间之间也可以用 `T` 分隔）
+#[cfg(feature = "chrono")]
+fn parse_timestamp(s: &str) -> Option<NaiveDateTime> {
+    ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(s, format).ok())
+}
+
 impl Tuple {
     /// 使用给定值创建新元组
     pub fn new(values: Vec<Value>) -> Self {
@@ -319,6 +695,11 @@ impl Schema {
     pub fn column_count(&self) -> usize {
         self.columns.len()
     }
+
+    /// 所有列的驱动元数据
+    pub fn column_metadata(&self) -> Vec<ColumnMetadata> {
+        self.columns.iter().map(ColumnDefinition::metadata).collect()
+    }
 }
 
 impl ColumnDefinition {
@@ -329,6 +710,7 @@ impl ColumnDefinition {
             data_type,
             nullable,
             default: None,
+            default_function: None,
         }
     }
 
@@ -337,6 +719,20 @@ impl ColumnDefinition {
         self.default = Some(default);
         self
     }
+
+    /// 此列的驱动元数据
+    pub fn metadata(&self) -> ColumnMetadata {
+        ColumnMetadata {
+            name: self.name.clone(),
+            type_name: self.data_type.type_name(),
+            jdbc_type: self.data_type.jdbc_type_code(),
+            odbc_type: self.data_type.odbc_type_code(),
+            precision: self.data_type.precision(),
+            scale: self.data_type.scale(),
+            display_size: self.data_type.display_size(),
+            nullable: self.nullable,
+        }
+    }
 }
 
 impl fmt::Display for Value {
@@ -347,9 +743,16 @@ impl fmt::Display for Value {
             Value::BigInt(i) => write!(f, "{}", i),
             Value::Float(fl) => write!(f, "{}", fl),
             Value::Double(d) => write!(f, "{}", d),
+            Value::Decimal(d) => write!(f, "{}", d),
             Value::Varchar(s) => write!(f, "'{}'", s),
+            // 与十六进制 BLOB 字面量的写法相同
+            Value::Blob(b) => write!(f, "X'{}'", encoding::encode_hex(b)),
+            Value::Uuid(u) => write!(f, "{}", u),
+            Value::Json(s) => write!(f, "{}", s),
             Value::Boolean(b) => write!(f, "{}", b),
+            #[cfg(feature = "chrono")]
             Value::Date(d) => write!(f, "{}", d),
+            #[cfg(feature = "chrono")]
             Value::Timestamp(ts) => write!(f, "{}", ts),
         }
     }
@@ -362,7 +765,13 @@ impl fmt::Display for DataType {
             DataType::BigInt => write!(f, "BIGINT"),
             DataType::Float => write!(f, "FLOAT"),
             DataType::Double => write!(f, "DOUBLE"),
+            DataType::Decimal(precision, scale) => write!(f, "DECIMAL({}, {})", precision, scale),
             DataType::Varchar(len) => write!(f, "VARCHAR({})", len),
+            DataType::Char(len) => write!(f, "CHAR({})", len),
+            DataType::Text => write!(f, "TEXT"),
+            DataType::Blob => write!(f, "BLOB"),
+            DataType::Uuid => write!(f, "UUID"),
+            DataType::Json => write!(f, "JSON"),
             DataType::Boolean => write!(f, "BOOLEAN"),
             DataType::Date => write!(f, "DATE"),
             DataType::Timestamp => write!(f, "TIMESTAMP"),
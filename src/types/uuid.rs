@@ -0,0 +1,97 @@
+//! UUID
+//!
+//! UUID 以 16 字节保存，按字节顺序比较和排序；文本形式为小写的 8-4-4-4-12 十六进制。
+//! GEN_UUID() 生成的是第 4 版（随机）UUID，随机位来自数据库的随机数源。
+
+use crate::utils::encoding;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// 128 位的通用唯一标识符
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Uuid([u8; 16]);
+
+impl Uuid {
+    pub const fn from_bytes(bytes: [u8; 16]) -> Self {
+        Self(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 16] {
+        &self.0
+    }
+
+    /// 由 128 个随机位构造第 4 版 UUID：覆盖其中的版本号和 RFC 4122 变体位
+    pub fn new_v4(random: u128) -> Self {
+        let mut bytes = random.to_be_bytes();
+        bytes[6] = (bytes[6] & 0x0f) | 0x40;
+        bytes[8] = (bytes[8] & 0x3f) | 0x80;
+        Self(bytes)
+    }
+
+    /// 版本号（第 7 个字节的高 4 位）
+    pub fn version(&self) -> u8 {
+        self.0[6] >> 4
+    }
+}
+
+/// 无法解析为 UUID 的文本
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("无效的 UUID")]
+pub struct ParseUuidError;
+
+impl FromStr for Uuid {
+    type Err = ParseUuidError;
+
+    /// 解析 8-4-4-4-12 的带连字符形式或 32 个连续的十六进制数字，大小写均可，可以用花括号括起
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.strip_prefix('{').and_then(|rest| rest.strip_suffix('}')).unwrap_or(s);
+        let hex = match s.len() {
+            32 => s.to_string(),
+            36 => {
+                let groups: Vec<&str> = s.split('-').collect();
+                if groups.iter().map(|group| group.len()).collect::<Vec<_>>() != [8, 4, 4, 4, 12] {
+                    return Err(ParseUuidError);
+                }
+                groups.concat()
+            }
+            _ => return Err(ParseUuidError),
+        };
+        let bytes = encoding::decode_hex(&hex).ok_or(ParseUuidError)?;
+        bytes.try_into().map(Self).map_err(|_| ParseUuidError)
+    }
+}
+
+impl fmt::Display for Uuid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hex = encoding::encode_hex(&self.0).to_lowercase();
+        write!(f, "{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_display() {
+        let text = "550e8400-e29b-41d4-a716-446655440000";
+        let uuid: Uuid = text.parse().unwrap();
+        assert_eq!(uuid.to_string(), text);
+        assert_eq!(uuid.version(), 4);
+        assert_eq!("{550E8400-E29B-41D4-A716-446655440000}".parse(), Ok(uuid));
+        assert_eq!("550e8400e29b41d4a716446655440000".parse(), Ok(uuid));
+        for bad in ["", "550e8400-e29b-41d4-a716-44665544000", "550e8400e-29b-41d4-a716-446655440000", "g50e8400-e29b-41d4-a716-446655440000"] {
+            assert!(bad.parse::<Uuid>().is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_new_v4() {
+        let uuid = Uuid::new_v4(u128::MAX);
+        assert_eq!(uuid.version(), 4);
+        assert_eq!(uuid.to_string(), "ffffffff-ffff-4fff-bfff-ffffffffffff");
+        assert_eq!(Uuid::new_v4(0).to_string(), "00000000-0000-4000-8000-000000000000");
+    }
+}
@@ -1,12 +1,68 @@
 //! Bitset utilities
 
-/// Simple bitset implementation placeholder
+/// Fixed-size set of bits stored little-endian in bytes, so it can be written
+/// to disk as is
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct BitSet {
-    // TODO: Implement bitset
+    bytes: Vec<u8>,
+    len: usize,
 }
 
 impl BitSet {
-    pub fn new(_size: usize) -> Self {
-        Self {}
+    /// Create a bitset of `size` bits, all cleared
+    pub fn new(size: usize) -> Self {
+        Self {
+            bytes: vec![0; Self::byte_len(size)],
+            len: size,
+        }
+    }
+
+    /// Rebuild a bitset of `size` bits from [`BitSet::as_bytes`] output;
+    /// returns `None` if `bytes` is too short
+    pub fn from_bytes(bytes: &[u8], size: usize) -> Option<Self> {
+        let bytes = bytes.get(..Self::byte_len(size))?.to_vec();
+        Some(Self { bytes, len: size })
+    }
+
+    /// Bytes needed to store `size` bits
+    pub fn byte_len(size: usize) -> usize {
+        size.div_ceil(8)
+    }
+
+    /// Number of bits
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the bitset has no bits
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Set a bit
+    pub fn set(&mut self, index: usize) {
+        assert!(index < self.len, "bit {} out of range for bitset of {} bits", index, self.len);
+        self.bytes[index / 8] |= 1 << (index % 8);
+    }
+
+    /// Clear a bit
+    pub fn clear(&mut self, index: usize) {
+        assert!(index < self.len, "bit {} out of range for bitset of {} bits", index, self.len);
+        self.bytes[index / 8] &= !(1 << (index % 8));
+    }
+
+    /// Whether a bit is set; bits beyond the end are never set
+    pub fn get(&self, index: usize) -> bool {
+        index < self.len && self.bytes[index / 8] & (1 << (index % 8)) != 0
+    }
+
+    /// Number of set bits
+    pub fn count_ones(&self) -> usize {
+        self.bytes.iter().map(|byte| byte.count_ones() as usize).sum()
+    }
+
+    /// The underlying bytes
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
     }
 }
@@ -0,0 +1,111 @@
+//! Hex and base64 text encodings of binary data
+//!
+//! Used for BLOB literals (`X'...'` and `B64'...'`), for casting and dumping
+//! binary values and for showing them in the shell. Base64 follows RFC 4648
+//! with the standard alphabet and `=` padding.
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789ABCDEF";
+
+const BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encode bytes as upper-case hex digits, two per byte
+pub fn encode_hex(data: &[u8]) -> String {
+    let mut text = String::with_capacity(data.len() * 2);
+    for &byte in data {
+        text.push(HEX_DIGITS[(byte >> 4) as usize] as char);
+        text.push(HEX_DIGITS[(byte & 0x0f) as usize] as char);
+    }
+    text
+}
+
+/// Decode hex digits of either case; `None` for an odd length or a non-hex
+/// character
+pub fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    let digits = text.as_bytes();
+    if !digits.len().is_multiple_of(2) {
+        return None;
+    }
+    digits
+        .chunks(2)
+        .map(|pair| Some((hex_value(pair[0])? << 4) | hex_value(pair[1])?))
+        .collect()
+}
+
+fn hex_value(digit: u8) -> Option<u8> {
+    (digit as char).to_digit(16).map(|value| value as u8)
+}
+
+/// Encode bytes as padded base64
+pub fn encode_base64(data: &[u8]) -> String {
+    let mut text = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, &byte)| bits | (byte as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                text.push(BASE64_ALPHABET[(bits >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
+
+/// Decode base64; whitespace is ignored and padding is optional. `None` for
+/// characters outside the alphabet or a truncated final group
+pub fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let symbols: Vec<u8> = text.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+    let data = match symbols.iter().position(|&b| b == b'=') {
+        // At most two padding characters, and nothing but padding after them
+        Some(start) if symbols.len() - start <= 2 && symbols[start..].iter().all(|&b| b == b'=') => &symbols[..start],
+        Some(_) => return None,
+        None => &symbols[..],
+    };
+    if data.len() % 4 == 1 {
+        return None;
+    }
+
+    let mut bytes = Vec::with_capacity(data.len() * 3 / 4);
+    for chunk in data.chunks(4) {
+        let mut bits = 0u32;
+        for (i, &symbol) in chunk.iter().enumerate() {
+            let value = BASE64_ALPHABET.iter().position(|&b| b == symbol)? as u32;
+            bits |= value << (18 - 6 * i);
+        }
+        // n symbols carry 6n bits, that is n - 1 whole bytes
+        for i in 0..chunk.len() - 1 {
+            bytes.push((bits >> (16 - 8 * i)) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex_round_trip() {
+        assert_eq!(encode_hex(&[0x00, 0xab, 0x7f]), "00AB7F");
+        assert_eq!(decode_hex("00ab7F"), Some(vec![0x00, 0xab, 0x7f]));
+        assert_eq!(decode_hex(""), Some(vec![]));
+        assert_eq!(decode_hex("abc"), None);
+        assert_eq!(decode_hex("zz"), None);
+    }
+
+    #[test]
+    fn test_base64_round_trip() {
+        // RFC 4648 test vectors
+        for (plain, encoded) in [("", ""), ("f", "Zg=="), ("fo", "Zm8="), ("foo", "Zm9v"), ("foobar", "Zm9vYmFy")] {
+            assert_eq!(encode_base64(plain.as_bytes()), encoded);
+            assert_eq!(decode_base64(encoded).as_deref(), Some(plain.as_bytes()));
+        }
+        let bytes: Vec<u8> = (0..=255).collect();
+        assert_eq!(decode_base64(&encode_base64(&bytes)), Some(bytes));
+
+        assert_eq!(decode_base64("Zm9v\nYmE"), Some(b"fooba".to_vec()));
+        assert_eq!(decode_base64("Zm9vY"), None);
+        assert_eq!(decode_base64("Zg=a"), None);
+        assert_eq!(decode_base64("Z!=="), None);
+    }
+}
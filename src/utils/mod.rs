@@ -3,6 +3,7 @@
 //! This module contains various utility functions used across the codebase.
 
 pub mod bitset;
+pub mod encoding;
 pub mod hash;
 pub mod serialize;
 
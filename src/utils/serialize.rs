@@ -1,13 +1,333 @@
 //! Serialization utilities
+//!
+//! Compact binary encoding of rows for page storage. A tuple is encoded as
+//!
+//! ```text
+//! [column count: u16][null bitmap: ceil(n / 8) bytes][value]...
+//! ```
+//!
+//! followed by one entry per non-NULL value: a one-byte type tag and the
+//! payload. Numbers are fixed width and little-endian, strings, JSON and blobs are
+//! a `u32` byte length followed by UTF-8 or the raw bytes, decimals are an `i128` mantissa followed
+//! by a one-byte scale, UUIDs are their 16 bytes, dates are days since 0001-01-01 (`i32`)
+//! and timestamps are microseconds since the Unix epoch (`i64`). Values carry
+//! their own tag, so rows written before an `ALTER TABLE` still decode.
 
-/// Serialize utilities placeholder
-pub fn serialize_u32(_value: u32) -> Vec<u8> {
-    // TODO: Implement serialization
-    vec![]
+use crate::types::{Decimal, Tuple, Uuid, Value};
+use crate::utils::bitset::BitSet;
+use thiserror::Error;
+
+const TAG_INTEGER: u8 = 1;
+const TAG_BIGINT: u8 = 2;
+const TAG_FLOAT: u8 = 3;
+const TAG_DOUBLE: u8 = 4;
+const TAG_VARCHAR: u8 = 5;
+const TAG_BOOLEAN: u8 = 6;
+#[cfg(feature = "chrono")]
+const TAG_DATE: u8 = 7;
+#[cfg(feature = "chrono")]
+const TAG_TIMESTAMP: u8 = 8;
+const TAG_DECIMAL: u8 = 9;
+const TAG_BLOB: u8 = 10;
+const TAG_UUID: u8 = 11;
+const TAG_JSON: u8 = 12;
+
+/// Binary decoding errors
+#[derive(Error, Debug, PartialEq)]
+pub enum SerializeError {
+    #[error("Unexpected end of data at byte {0}")]
+    UnexpectedEnd(usize),
+
+    #[error("Unknown value tag {tag} at byte {offset}")]
+    UnknownTag { tag: u8, offset: usize },
+
+    #[error("Invalid UTF-8 in string at byte {0}")]
+    InvalidUtf8(usize),
+
+    #[error("Date or timestamp out of range at byte {0}")]
+    InvalidDate(usize),
+
+    #[error("Decimal scale out of range at byte {0}")]
+    InvalidDecimal(usize),
+
+    #[error("{0} trailing byte(s) after the encoded value")]
+    TrailingBytes(usize),
+}
+
+/// Serialize a `u32` as 4 little-endian bytes
+pub fn serialize_u32(value: u32) -> Vec<u8> {
+    value.to_le_bytes().to_vec()
+}
+
+/// Deserialize a `u32` written by [`serialize_u32`]
+pub fn deserialize_u32(data: &[u8]) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(..4)?.try_into().ok()?))
+}
+
+/// Encode a tuple
+pub fn encode_tuple(tuple: &Tuple) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    encode_tuple_into(tuple, &mut buffer);
+    buffer
+}
+
+/// Append the encoding of a tuple to `buffer`
+pub fn encode_tuple_into(tuple: &Tuple, buffer: &mut Vec<u8>) {
+    let count = tuple.values.len();
+    assert!(count <= u16::MAX as usize, "tuple has {} columns, more than {}", count, u16::MAX);
+    buffer.extend_from_slice(&(count as u16).to_le_bytes());
+
+    let mut nulls = BitSet::new(count);
+    for (i, value) in tuple.values.iter().enumerate() {
+        if value.is_null() {
+            nulls.set(i);
+        }
+    }
+    buffer.extend_from_slice(nulls.as_bytes());
+
+    for value in &tuple.values {
+        encode_value(value, buffer);
+    }
 }
 
-/// Deserialize utilities placeholder
-pub fn deserialize_u32(_data: &[u8]) -> Option<u32> {
-    // TODO: Implement deserialization
-    None
+fn encode_value(value: &Value, buffer: &mut Vec<u8>) {
+    match value {
+        Value::Null => {}
+        Value::Integer(i) => {
+            buffer.push(TAG_INTEGER);
+            buffer.extend_from_slice(&i.to_le_bytes());
+        }
+        Value::BigInt(i) => {
+            buffer.push(TAG_BIGINT);
+            buffer.extend_from_slice(&i.to_le_bytes());
+        }
+        Value::Float(f) => {
+            buffer.push(TAG_FLOAT);
+            buffer.extend_from_slice(&f.to_le_bytes());
+        }
+        Value::Double(f) => {
+            buffer.push(TAG_DOUBLE);
+            buffer.extend_from_slice(&f.to_le_bytes());
+        }
+        Value::Decimal(d) => {
+            buffer.push(TAG_DECIMAL);
+            buffer.extend_from_slice(&d.mantissa().to_le_bytes());
+            buffer.push(d.scale());
+        }
+        Value::Varchar(s) => {
+            buffer.push(TAG_VARCHAR);
+            buffer.extend_from_slice(&(s.len() as u32).to_le_bytes());
+            buffer.extend_from_slice(s.as_bytes());
+        }
+        Value::Blob(b) => {
+            buffer.push(TAG_BLOB);
+            buffer.extend_from_slice(&(b.len() as u32).to_le_bytes());
+            buffer.extend_from_slice(b);
+        }
+        Value::Json(s) => {
+            buffer.push(TAG_JSON);
+            buffer.extend_from_slice(&(s.len() as u32).to_le_bytes());
+            buffer.extend_from_slice(s.as_bytes());
+        }
+        Value::Uuid(u) => {
+            buffer.push(TAG_UUID);
+            buffer.extend_from_slice(u.as_bytes());
+        }
+        Value::Boolean(b) => {
+            buffer.push(TAG_BOOLEAN);
+            buffer.push(*b as u8);
+        }
+        #[cfg(feature = "chrono")]
+        Value::Date(d) => {
+            use chrono::Datelike;
+            buffer.push(TAG_DATE);
+            buffer.extend_from_slice(&d.num_days_from_ce().to_le_bytes());
+        }
+        #[cfg(feature = "chrono")]
+        Value::Timestamp(t) => {
+            buffer.push(TAG_TIMESTAMP);
+            buffer.extend_from_slice(&t.and_utc().timestamp_micros().to_le_bytes());
+        }
+    }
+}
+
+/// Decode a tuple written by [`encode_tuple`]; `data` must hold exactly one
+/// tuple
+pub fn decode_tuple(data: &[u8]) -> Result<Tuple, SerializeError> {
+    let mut reader = Reader { data, offset: 0 };
+    let tuple = reader.tuple()?;
+    match data.len() - reader.offset {
+        0 => Ok(tuple),
+        trailing => Err(SerializeError::TrailingBytes(trailing)),
+    }
+}
+
+/// Cursor over encoded bytes
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], SerializeError> {
+        let bytes = self
+            .data
+            .get(self.offset..self.offset + len)
+            .ok_or(SerializeError::UnexpectedEnd(self.offset))?;
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], SerializeError> {
+        Ok(self.take(N)?.try_into().expect("slice has the requested length"))
+    }
+
+    /// `u32` byte length followed by UTF-8
+    fn string(&mut self) -> Result<String, SerializeError> {
+        let len = u32::from_le_bytes(self.array()?) as usize;
+        let start = self.offset;
+        let bytes = self.take(len)?;
+        let s = std::str::from_utf8(bytes).map_err(|_| SerializeError::InvalidUtf8(start))?;
+        Ok(s.to_string())
+    }
+
+    fn tuple(&mut self) -> Result<Tuple, SerializeError> {
+        let count = u16::from_le_bytes(self.array()?) as usize;
+        let nulls = BitSet::from_bytes(self.take(BitSet::byte_len(count))?, count)
+            .ok_or(SerializeError::UnexpectedEnd(self.offset))?;
+
+        let mut values = Vec::with_capacity(count);
+        for i in 0..count {
+            values.push(if nulls.get(i) { Value::Null } else { self.value()? });
+        }
+        Ok(Tuple::new(values))
+    }
+
+    fn value(&mut self) -> Result<Value, SerializeError> {
+        let offset = self.offset;
+        let [tag] = self.array()?;
+        let value = match tag {
+            TAG_INTEGER => Value::Integer(i32::from_le_bytes(self.array()?)),
+            TAG_BIGINT => Value::BigInt(i64::from_le_bytes(self.array()?)),
+            TAG_FLOAT => Value::Float(f32::from_le_bytes(self.array()?)),
+            TAG_DOUBLE => Value::Double(f64::from_le_bytes(self.array()?)),
+            TAG_DECIMAL => {
+                let mantissa = i128::from_le_bytes(self.array()?);
+                let [scale] = self.array()?;
+                Decimal::new(mantissa, scale)
+                    .map(Value::Decimal)
+                    .ok_or(SerializeError::InvalidDecimal(offset))?
+            }
+            TAG_VARCHAR => Value::Varchar(self.string()?),
+            TAG_JSON => Value::Json(self.string()?),
+            TAG_BLOB => {
+                let len = u32::from_le_bytes(self.array()?) as usize;
+                Value::Blob(self.take(len)?.to_vec())
+            }
+            TAG_UUID => Value::Uuid(Uuid::from_bytes(self.array()?)),
+            TAG_BOOLEAN => Value::Boolean(self.array::<1>()?[0] != 0),
+            #[cfg(feature = "chrono")]
+            TAG_DATE => {
+                let days = i32::from_le_bytes(self.array()?);
+                chrono::NaiveDate::from_num_days_from_ce_opt(days)
+                    .map(Value::Date)
+                    .ok_or(SerializeError::InvalidDate(offset))?
+            }
+            #[cfg(feature = "chrono")]
+            TAG_TIMESTAMP => {
+                let micros = i64::from_le_bytes(self.array()?);
+                chrono::DateTime::from_timestamp_micros(micros)
+                    .map(|t| Value::Timestamp(t.naive_utc()))
+                    .ok_or(SerializeError::InvalidDate(offset))?
+            }
+            tag => return Err(SerializeError::UnknownTag { tag, offset }),
+        };
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tuple_round_trip() {
+        let tuple = Tuple::new(vec![
+            Value::Integer(-7),
+            Value::Null,
+            Value::BigInt(i64::MAX),
+            Value::Float(1.5),
+            Value::Double(-0.25),
+            Value::Varchar("héllo".to_string()),
+            Value::Boolean(true),
+            Value::Null,
+            Value::Varchar(String::new()),
+            Value::Decimal("-1234.50".parse().unwrap()),
+            Value::Blob(vec![0, 0xff, 7]),
+            Value::Uuid("550e8400-e29b-41d4-a716-446655440000".parse().unwrap()),
+            Value::Json(r#"{"a":[1,"b"]}"#.to_string()),
+        ]);
+        let bytes = encode_tuple(&tuple);
+        let decoded = decode_tuple(&bytes).unwrap();
+        assert_eq!(decoded, tuple);
+        // Decimals keep their scale, not just their numeric value
+        assert_eq!(decoded.values[9].to_string(), "-1234.50");
+
+    }
+
+    #[cfg(feature = "persistence")]
+    #[test]
+    fn test_smaller_than_json() {
+        let tuple = Tuple::new(vec![
+            Value::Integer(42),
+            Value::Null,
+            Value::Varchar("hello".to_string()),
+            Value::Null,
+            Value::Double(2.5),
+        ]);
+        // Two NULLs cost nothing beyond their bitmap bits
+        let bytes = encode_tuple(&tuple);
+        let json = serde_json::to_vec(&tuple).unwrap();
+        assert!(bytes.len() * 2 < json.len(), "{} vs {}", bytes.len(), json.len());
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_dates_round_trip() {
+        let date = chrono::NaiveDate::from_ymd_opt(2024, 2, 29).unwrap();
+        let tuple = Tuple::new(vec![
+            Value::Date(date),
+            Value::Timestamp(date.and_hms_micro_opt(23, 59, 59, 123_456).unwrap()),
+        ]);
+        assert_eq!(decode_tuple(&encode_tuple(&tuple)).unwrap(), tuple);
+    }
+
+    #[test]
+    fn test_corrupt_data_is_rejected() {
+        let bytes = encode_tuple(&Tuple::new(vec![Value::Varchar("abc".to_string())]));
+        assert_eq!(decode_tuple(&bytes[..bytes.len() - 1]), Err(SerializeError::UnexpectedEnd(8)));
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(decode_tuple(&trailing), Err(SerializeError::TrailingBytes(1)));
+
+        let mut bad_tag = bytes;
+        bad_tag[3] = 99;
+        assert_eq!(decode_tuple(&bad_tag), Err(SerializeError::UnknownTag { tag: 99, offset: 3 }));
+
+        assert_eq!(deserialize_u32(&serialize_u32(0xDEAD_BEEF)), Some(0xDEAD_BEEF));
+        assert_eq!(deserialize_u32(&[1, 2]), None);
+    }
+
+    #[test]
+    fn test_bitset() {
+        let mut bits = BitSet::new(10);
+        bits.set(0);
+        bits.set(9);
+        bits.clear(0);
+        assert!(bits.get(9) && !bits.get(0) && !bits.get(10));
+        assert_eq!(bits.as_bytes(), &[0, 2]);
+        assert_eq!(BitSet::from_bytes(bits.as_bytes(), 10), Some(bits.clone()));
+        assert_eq!(bits.count_ones(), 1);
+    }
 }